@@ -423,6 +423,39 @@ fn maybe_cgexec(cmd: &str) -> Command {
     }
 }
 
+/// The compression to request a basebackup with first. zstd is only requested if the control
+/// plane enabled it, and [`ComputeNode::get_basebackup`] falls back to gzip if the pageserver
+/// turns out not to support it.
+fn basebackup_compression(spec: &ParsedSpec) -> BaseBackupCompression {
+    if spec.spec.features.contains(&ComputeFeature::BasebackupZstd) {
+        BaseBackupCompression::Zstd
+    } else {
+        BaseBackupCompression::Gzip
+    }
+}
+
+/// Whether `err` is how a pageserver that predates zstd basebackups rejects a zstd request: the
+/// libpq command fails to parse `--zstd`, and gRPC doesn't know the compression value.
+fn is_zstd_unsupported(err: &anyhow::Error) -> bool {
+    let msg = format!("{err:#}");
+    msg.contains("--zstd") || msg.contains("field 'compression'")
+}
+
+/// Wraps a basebackup stream in the decoder for the compression requested from the pageserver.
+fn basebackup_decoder<'a, R>(
+    compression: BaseBackupCompression,
+    reader: R,
+) -> Result<Box<dyn std::io::Read + 'a>>
+where
+    R: std::io::Read + 'a,
+{
+    match compression {
+        BaseBackupCompression::None => Ok(Box::new(reader)),
+        BaseBackupCompression::Gzip => Ok(Box::new(flate2::read::GzDecoder::new(reader))),
+        BaseBackupCompression::Zstd => Ok(Box::new(zstd::stream::read::Decoder::new(reader)?)),
+    }
+}
+
 struct PostgresHandle {
     postgres: std::process::Child,
     log_collector: JoinHandle<Result<()>>,
@@ -1219,15 +1252,21 @@ fn create_pgdata(&self) -> Result<()> {
 
     /// Fetches a basebackup from the Pageserver using the compute state's Pageserver connstring and
     /// unarchives it to `pgdata` directory, replacing any existing contents.
-    #[instrument(skip_all, fields(%lsn))]
-    fn try_get_basebackup(&self, compute_state: &ComputeState, lsn: Lsn) -> Result<()> {
+    #[instrument(skip_all, fields(%lsn, ?compression))]
+    fn try_get_basebackup(
+        &self,
+        compute_state: &ComputeState,
+        lsn: Lsn,
+        compression: BaseBackupCompression,
+    ) -> Result<()> {
         let spec = compute_state.pspec.as_ref().expect("spec must be set");
 
         let started = Instant::now();
         let (connected, size) = match spec.pageserver_conninfo.prefer_protocol {
-            PageserverProtocol::Grpc => self.try_get_basebackup_grpc(spec, lsn)?,
-            PageserverProtocol::Libpq => self.try_get_basebackup_libpq(spec, lsn)?,
+            PageserverProtocol::Grpc => self.try_get_basebackup_grpc(spec, lsn, compression)?,
+            PageserverProtocol::Libpq => self.try_get_basebackup_libpq(spec, lsn, compression)?,
         };
+        info!("fetched {compression:?} basebackup of {size} bytes");
 
         self.fix_zenith_signal_neon_signal()?;
 
@@ -1263,7 +1302,12 @@ fn fix_zenith_signal_neon_signal(&self) -> Result<()> {
 
     /// Fetches a basebackup via gRPC. The connstring must use grpc://. Returns the timestamp when
     /// the connection was established, and the (compressed) size of the basebackup.
-    fn try_get_basebackup_grpc(&self, spec: &ParsedSpec, lsn: Lsn) -> Result<(Instant, usize)> {
+    fn try_get_basebackup_grpc(
+        &self,
+        spec: &ParsedSpec,
+        lsn: Lsn,
+        compression: BaseBackupCompression,
+    ) -> Result<(Instant, usize)> {
         let shard0_index = ShardIndex {
             shard_number: ShardNumber(0),
             shard_count: spec.pageserver_conninfo.shard_count,
@@ -1286,7 +1330,7 @@ fn try_get_basebackup_grpc(&self, spec: &ParsedSpec, lsn: Lsn) -> Result<(Instan
             let reader = client
                 .get_base_backup(page_api::GetBaseBackupRequest {
                     lsn: (lsn != Lsn(0)).then_some(lsn),
-                    compression: BaseBackupCompression::Gzip,
+                    compression,
                     replica: spec.spec.mode != ComputeMode::Primary,
                     full: false,
                 })
@@ -1299,7 +1343,7 @@ fn try_get_basebackup_grpc(&self, spec: &ParsedSpec, lsn: Lsn) -> Result<(Instan
         // Set `ignore_zeros` so that unpack() reads the entire stream and doesn't just stop at the
         // end-of-archive marker. If the server errors, the tar::Builder drop handler will write an
         // end-of-archive marker before the error is emitted, and we would not see the error.
-        let mut ar = tar::Archive::new(flate2::read::GzDecoder::new(&mut reader));
+        let mut ar = tar::Archive::new(basebackup_decoder(compression, &mut reader)?);
         ar.set_ignore_zeros(true);
         ar.unpack(&self.params.pgdata)?;
 
@@ -1308,7 +1352,12 @@ fn try_get_basebackup_grpc(&self, spec: &ParsedSpec, lsn: Lsn) -> Result<(Instan
 
     /// Fetches a basebackup via libpq. The connstring must use postgresql://. Returns the timestamp
     /// when the connection was established, and the (compressed) size of the basebackup.
-    fn try_get_basebackup_libpq(&self, spec: &ParsedSpec, lsn: Lsn) -> Result<(Instant, usize)> {
+    fn try_get_basebackup_libpq(
+        &self,
+        spec: &ParsedSpec,
+        lsn: Lsn,
+        compression: BaseBackupCompression,
+    ) -> Result<(Instant, usize)> {
         let shard0_connstr = spec
             .pageserver_conninfo
             .shard_url(ShardNumber(0), PageserverProtocol::Libpq)?;
@@ -1333,31 +1382,18 @@ fn try_get_basebackup_libpq(&self, spec: &ParsedSpec, lsn: Lsn) -> Result<(Insta
         let mut client = config.connect(NoTls)?;
         let connected = Instant::now();
 
-        let basebackup_cmd = match lsn {
-            Lsn(0) => {
-                if spec.spec.mode != ComputeMode::Primary {
-                    format!(
-                        "basebackup {} {} --gzip --replica",
-                        spec.tenant_id, spec.timeline_id
-                    )
-                } else {
-                    format!("basebackup {} {} --gzip", spec.tenant_id, spec.timeline_id)
-                }
-            }
-            _ => {
-                if spec.spec.mode != ComputeMode::Primary {
-                    format!(
-                        "basebackup {} {} {} --gzip --replica",
-                        spec.tenant_id, spec.timeline_id, lsn
-                    )
-                } else {
-                    format!(
-                        "basebackup {} {} {} --gzip",
-                        spec.tenant_id, spec.timeline_id, lsn
-                    )
-                }
-            }
-        };
+        let mut basebackup_cmd = format!("basebackup {} {}", spec.tenant_id, spec.timeline_id);
+        if lsn != Lsn(0) {
+            basebackup_cmd.push_str(&format!(" {lsn}"));
+        }
+        match compression {
+            BaseBackupCompression::None => {}
+            BaseBackupCompression::Gzip => basebackup_cmd.push_str(" --gzip"),
+            BaseBackupCompression::Zstd => basebackup_cmd.push_str(" --zstd"),
+        }
+        if spec.spec.mode != ComputeMode::Primary {
+            basebackup_cmd.push_str(" --replica");
+        }
 
         let copyreader = client.copy_out(basebackup_cmd.as_str())?;
         let mut measured_reader = MeasuredReader::new(copyreader);
@@ -1370,7 +1406,7 @@ fn try_get_basebackup_libpq(&self, spec: &ParsedSpec, lsn: Lsn) -> Result<(Insta
         // sends an Error after finishing the tarball, we will not notice it.
         // The tar::Builder drop handler will write an end-of-archive marker
         // before emitting the error, and we would not see it otherwise.
-        let mut ar = tar::Archive::new(flate2::read::GzDecoder::new(&mut bufreader));
+        let mut ar = tar::Archive::new(basebackup_decoder(compression, &mut bufreader)?);
         ar.set_ignore_zeros(true);
         ar.unpack(&self.params.pgdata)?;
 
@@ -1391,12 +1427,25 @@ pub fn get_basebackup(&self, compute_state: &ComputeState, lsn: Lsn) -> Result<(
         };
         #[cfg(not(feature = "testing"))]
         let max_attempts = DEFAULT_ATTEMPTS;
+        let spec = compute_state.pspec.as_ref().expect("spec must be set");
+        let mut compression = basebackup_compression(spec);
         loop {
-            let result = self.try_get_basebackup(compute_state, lsn);
+            let result = self.try_get_basebackup(compute_state, lsn, compression);
             match result {
                 Ok(_) => {
                     return result;
                 }
+                Err(ref e)
+                    if matches!(compression, BaseBackupCompression::Zstd)
+                        && is_zstd_unsupported(e) =>
+                {
+                    // Not a failed attempt: retry right away.
+                    warn!(
+                        "Pageserver does not support zstd basebackups, falling back to gzip: {e:#}"
+                    );
+                    compression = BaseBackupCompression::Gzip;
+                    continue;
+                }
                 Err(ref e) if attempts < max_attempts => {
                     warn!("Failed to get basebackup: {e:?} (attempt {attempts}/{max_attempts})");
                     std::thread::sleep(std::time::Duration::from_millis(retry_period_ms as u64));
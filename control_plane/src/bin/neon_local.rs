@@ -30,6 +30,7 @@
 };
 use control_plane::pageserver::PageServerNode;
 use control_plane::safekeeper::SafekeeperNode;
+use control_plane::stock_standby::StockStandby;
 use control_plane::storage_controller::{
     NeonStorageControllerStartArgs, NeonStorageControllerStopArgs, StorageController,
 };
@@ -47,7 +48,7 @@
 };
 use pageserver_api::shard::{DEFAULT_STRIPE_SIZE, ShardCount, ShardStripeSize, TenantShardId};
 use postgres_backend::AuthType;
-use safekeeper_api::membership::{SafekeeperGeneration, SafekeeperId};
+use safekeeper_api::membership::{MemberRole, SafekeeperGeneration, SafekeeperId};
 use safekeeper_api::{
     DEFAULT_HTTP_LISTEN_PORT as DEFAULT_SAFEKEEPER_HTTP_PORT,
     DEFAULT_PG_LISTEN_PORT as DEFAULT_SAFEKEEPER_PG_PORT, PgMajorVersion, PgVersionId,
@@ -102,6 +103,8 @@ enum NeonLocalCmd {
     #[command(subcommand)]
     Endpoint(EndpointCmd),
     #[command(subcommand)]
+    StockStandby(StockStandbyCmd),
+    #[command(subcommand)]
     Mappings(MappingsCmd),
 
     Start(StartCmdArgs),
@@ -436,6 +439,63 @@ enum SafekeeperCmd {
     Restart(SafekeeperRestartCmdArgs),
 }
 
+/// Manage unmodified postgres standbys streaming WAL from a safekeeper.
+#[derive(clap::Subcommand)]
+enum StockStandbyCmd {
+    Create(StockStandbyCreateCmdArgs),
+    Start(StockStandbyStartCmdArgs),
+    Stop(StockStandbyStopCmdArgs),
+}
+
+/// Create a stock standby from the initdb archive of the timeline.
+#[derive(clap::Args)]
+struct StockStandbyCreateCmdArgs {
+    /// Standby name.
+    name: String,
+    /// Tenant ID, as a 32-byte hexadecimal string.
+    #[clap(long = "tenant-id")]
+    tenant_id: Option<TenantId>,
+    /// Name of the branch to replicate.
+    #[clap(long)]
+    branch_name: Option<String>,
+    /// Safekeeper to stream WAL from.
+    #[clap(long = "safekeeper-id")]
+    #[arg(default_value_t = DEFAULT_SAFEKEEPER_ID)]
+    safekeeper_id: NodeId,
+    #[clap(long)]
+    pg_port: u16,
+    /// Postgres version.
+    #[arg(default_value = DEFAULT_PG_VERSION_NUM)]
+    #[clap(long)]
+    pg_version: PgMajorVersion,
+}
+
+/// Start a stock standby.
+#[derive(clap::Args)]
+struct StockStandbyStartCmdArgs {
+    /// Standby name.
+    name: String,
+    /// Postgres version.
+    #[arg(default_value = DEFAULT_PG_VERSION_NUM)]
+    #[clap(long)]
+    pg_version: PgMajorVersion,
+}
+
+/// Stop a stock standby.
+#[derive(clap::Args)]
+struct StockStandbyStopCmdArgs {
+    /// Standby name.
+    name: String,
+    /// Postgres version.
+    #[arg(default_value = DEFAULT_PG_VERSION_NUM)]
+    #[clap(long)]
+    pg_version: PgMajorVersion,
+    /// If 'immediate', don't flush repository data on shutdown.
+    #[clap(short = 'm')]
+    #[arg(value_enum, default_value = "fast")]
+    stop_mode: StopMode,
+}
+
 /// Manage object storage.
 #[derive(clap::Subcommand)]
 enum EndpointStorageCmd {
@@ -782,6 +842,7 @@ fn main() -> Result<()> {
                 rt.block_on(handle_endpoint_storage(&subcmd, env))
             }
             NeonLocalCmd::Endpoint(subcmd) => rt.block_on(handle_endpoint(&subcmd, env)),
+            NeonLocalCmd::StockStandby(subcmd) => rt.block_on(handle_stock_standby(&subcmd, env)),
             NeonLocalCmd::Mappings(subcmd) => handle_mappings(&subcmd, env),
         };
 
@@ -1253,6 +1314,7 @@ async fn handle_timeline(cmd: &TimelineCmd, env: &mut local_env::LocalEnv) -> Re
                             host: default_host,
                             id: default_sk.conf.id,
                             pg_port: default_sk.conf.pg_port,
+                            role: MemberRole::Data,
                         }],
                     },
                     new_members: None,
@@ -1828,6 +1890,35 @@ async fn handle_safekeeper(subcmd: &SafekeeperCmd, env: &local_env::LocalEnv) ->
     Ok(())
 }
 
+async fn handle_stock_standby(subcmd: &StockStandbyCmd, env: &local_env::LocalEnv) -> Result<()> {
+    match subcmd {
+        StockStandbyCmd::Create(args) => {
+            let tenant_id = get_tenant_id(args.tenant_id, env)?;
+            let branch_name = args
+                .branch_name
+                .clone()
+                .unwrap_or(DEFAULT_BRANCH_NAME.to_owned());
+            let timeline_id = env
+                .get_branch_timeline_id(&branch_name, tenant_id)
+                .ok_or_else(|| anyhow!("Found no timeline id for branch name '{branch_name}'"))?;
+            StockStandby::new(env, &args.name, args.pg_version)
+                .create(tenant_id, timeline_id, args.safekeeper_id, args.pg_port)
+                .await?;
+        }
+        StockStandbyCmd::Start(args) => {
+            StockStandby::new(env, &args.name, args.pg_version).start()?;
+        }
+        StockStandbyCmd::Stop(args) => {
+            let immediate = match args.stop_mode {
+                StopMode::Fast => false,
+                StopMode::Immediate => true,
+            };
+            StockStandby::new(env, &args.name, args.pg_version).stop(immediate)?;
+        }
+    }
+    Ok(())
+}
+
 async fn handle_endpoint_storage(
     subcmd: &EndpointStorageCmd,
     env: &local_env::LocalEnv,
@@ -14,4 +14,5 @@
 pub mod pageserver;
 pub mod postgresql_conf;
 pub mod safekeeper;
+pub mod stock_standby;
 pub mod storage_controller;
@@ -198,6 +198,8 @@ pub struct NeonStorageControllerConf {
 
     pub max_secondary_lag_bytes: Option<u64>,
 
+    pub min_secondary_readiness_pct: Option<u8>,
+
     #[serde(with = "humantime_serde")]
     pub heartbeat_interval: Duration,
 
@@ -214,6 +216,8 @@ pub struct NeonStorageControllerConf {
 
     pub timeline_safekeeper_count: Option<usize>,
 
+    pub timeline_safekeeper_witness: bool,
+
     pub posthog_config: Option<PostHogConfig>,
 
     pub kick_secondary_downloads: Option<bool>,
@@ -244,6 +248,7 @@ fn default() -> Self {
             initial_split_threshold: None,
             initial_split_shards: None,
             max_secondary_lag_bytes: None,
+            min_secondary_readiness_pct: None,
             heartbeat_interval: Self::DEFAULT_HEARTBEAT_INTERVAL,
             long_reconcile_threshold: None,
             use_https_pageserver_api: false,
@@ -251,6 +256,7 @@ fn default() -> Self {
             use_https_safekeeper_api: false,
             use_local_compute_notifications: true,
             timeline_safekeeper_count: None,
+            timeline_safekeeper_witness: false,
             posthog_config: None,
             kick_secondary_downloads: None,
             shard_split_request_timeout: None,
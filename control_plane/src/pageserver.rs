@@ -452,6 +452,11 @@ pub fn parse_config(mut settings: HashMap<&str, &str>) -> anyhow::Result<models:
                 .map(|x| x.parse::<usize>())
                 .transpose()
                 .context("Failed to parse 'image_creation_threshold' as non zero integer")?,
+            image_creation_policy: settings
+                .remove("image_creation_policy")
+                .map(serde_json::from_str)
+                .transpose()
+                .context("Failed to parse 'image_creation_policy' json")?,
             // HADRON
             image_layer_force_creation_period: settings
                 .remove("image_layer_force_creation_period")
@@ -571,6 +576,36 @@ pub fn parse_config(mut settings: HashMap<&str, &str>) -> anyhow::Result<models:
                 .map(|x| x.parse::<bool>())
                 .transpose()
                 .context("Failed to parse 'basebackup_cache_enabled' as bool")?,
+            timeline_logical_size_limit: settings
+                .remove("timeline_logical_size_limit")
+                .map(|x| x.parse::<u64>())
+                .transpose()
+                .context("Failed to parse 'timeline_logical_size_limit' as integer")?,
+            timeline_physical_size_limit: settings
+                .remove("timeline_physical_size_limit")
+                .map(|x| x.parse::<u64>())
+                .transpose()
+                .context("Failed to parse 'timeline_physical_size_limit' as integer")?,
+            tenant_logical_size_limit: settings
+                .remove("tenant_logical_size_limit")
+                .map(|x| x.parse::<u64>())
+                .transpose()
+                .context("Failed to parse 'tenant_logical_size_limit' as integer")?,
+            tenant_physical_size_limit: settings
+                .remove("tenant_physical_size_limit")
+                .map(|x| x.parse::<u64>())
+                .transpose()
+                .context("Failed to parse 'tenant_physical_size_limit' as integer")?,
+            timeline_delete_grace_period: settings
+                .remove("timeline_delete_grace_period")
+                .map(humantime::parse_duration)
+                .transpose()
+                .context("Failed to parse 'timeline_delete_grace_period' as duration")?,
+            eviction_weight_pct: settings
+                .remove("eviction_weight_pct")
+                .map(|x| x.parse::<u32>())
+                .transpose()
+                .context("Failed to parse 'eviction_weight_pct' as integer")?,
         };
         if !settings.is_empty() {
             bail!("Unrecognized tenant settings: {settings:?}")
@@ -0,0 +1,162 @@
+//! Code to manage unmodified postgres physical standbys streaming WAL
+//! directly from a safekeeper with a read-only tenant token.
+//!
+//! In the local test environment, the data for each standby is stored in
+//!
+//! ```text
+//!   .neon/stock_standbys/<standby name>
+//! ```
+//!
+//! The standby is bootstrapped from the initdb archive of the timeline in the
+//! pageserver's local remote storage, which has the same system id and start
+//! of WAL as the timeline, and replays all WAL since then. So this works only
+//! while safekeepers still have WAL from the start of the timeline, which is
+//! the case in tests.
+//!
+//! Computes of Postgres 16 and later log heap changes with the custom neon
+//! resource manager, which postgres can't replay unless the `neon_rmgr`
+//! library of the postgres distribution (`pgxn/neon_rmgr`) registers it, so the
+//! standby preloads it. The library does nothing for older versions.
+use std::path::PathBuf;
+use std::process::Command;
+
+use anyhow::{Context, Result, bail};
+use camino::Utf8PathBuf;
+use safekeeper_api::PgMajorVersion;
+use utils::auth::{Claims, Scope};
+use utils::id::{NodeId, TenantId, TimelineId};
+
+use crate::local_env::LocalEnv;
+use crate::pageserver::PAGESERVER_REMOTE_STORAGE_DIR;
+use crate::postgresql_conf::PostgresConf;
+
+pub struct StockStandby {
+    pub name: String,
+    pub pg_version: PgMajorVersion,
+    pub env: LocalEnv,
+}
+
+impl StockStandby {
+    pub fn new(env: &LocalEnv, name: &str, pg_version: PgMajorVersion) -> StockStandby {
+        StockStandby {
+            name: name.to_owned(),
+            pg_version,
+            env: env.clone(),
+        }
+    }
+
+    pub fn standby_path(&self) -> PathBuf {
+        self.env
+            .base_data_dir
+            .join("stock_standbys")
+            .join(&self.name)
+    }
+
+    pub fn pgdata(&self) -> PathBuf {
+        self.standby_path().join("pgdata")
+    }
+
+    /// Create data directory of the standby replicating the timeline from the
+    /// given safekeeper.
+    pub async fn create(
+        &self,
+        tenant_id: TenantId,
+        timeline_id: TimelineId,
+        safekeeper_id: NodeId,
+        pg_port: u16,
+    ) -> Result<()> {
+        let pgdata = self.pgdata();
+        if pgdata.exists() {
+            bail!("stock standby {} already exists", self.name);
+        }
+        let Some(sk) = self
+            .env
+            .safekeepers
+            .iter()
+            .find(|sk| sk.id == safekeeper_id)
+        else {
+            bail!("could not find safekeeper {safekeeper_id}");
+        };
+
+        // Unsharded tenant has the same remote path as the tenant shard.
+        let initdb_path = self
+            .env
+            .base_data_dir
+            .join(PAGESERVER_REMOTE_STORAGE_DIR)
+            .join(format!(
+                "tenants/{tenant_id}/timelines/{timeline_id}/initdb.tar.zst"
+            ));
+        let initdb = tokio::fs::File::open(&initdb_path).await.with_context(|| {
+            format!(
+                "open initdb archive {}, only local fs pageserver remote storage is supported",
+                initdb_path.display()
+            )
+        })?;
+        let pgdata_utf8 = Utf8PathBuf::from_path_buf(pgdata.clone())
+            .map_err(|p| anyhow::anyhow!("non-Unicode path {}", p.display()))?;
+        tokio::fs::create_dir_all(&pgdata).await?;
+        utils::zstd::extract_zst_tarball(&pgdata_utf8, tokio::io::BufReader::new(initdb))
+            .await
+            .context("extract initdb archive")?;
+
+        let sk_port = sk.get_compute_port();
+        let sk_host = sk.listen_addr.as_deref().unwrap_or("127.0.0.1");
+        let mut primary_conninfo = format!(
+            "host={sk_host} port={sk_port} options='-c tenant_id={tenant_id} -c timeline_id={timeline_id}'"
+        );
+        if sk.auth_enabled {
+            let token = self
+                .env
+                .generate_auth_token(&Claims::new(Some(tenant_id), Scope::TenantReadOnly))?;
+            primary_conninfo.push_str(&format!(" password={token}"));
+        }
+
+        let mut conf = PostgresConf::new();
+        conf.append("listen_addresses", "127.0.0.1");
+        conf.append("port", &pg_port.to_string());
+        conf.append("hot_standby", "on");
+        // WAL of the timeline contains neon resource manager records, see above.
+        conf.append("shared_preload_libraries", "neon_rmgr");
+        conf.append("primary_conninfo", &primary_conninfo);
+
+        // Settings appended to postgresql.conf override the initdb ones.
+        let mut file = std::fs::OpenOptions::new()
+            .append(true)
+            .open(pgdata.join("postgresql.conf"))?;
+        std::io::Write::write_all(&mut file, format!("\n{conf}").as_bytes())?;
+        std::fs::File::create(pgdata.join("standby.signal"))?;
+
+        Ok(())
+    }
+
+    pub fn start(&self) -> Result<()> {
+        let log_path = self.standby_path().join("postgres.log");
+        self.pg_ctl(&["-l", log_path.to_str().unwrap(), "start"])
+    }
+
+    pub fn stop(&self, immediate: bool) -> Result<()> {
+        self.pg_ctl(&["-m", if immediate { "immediate" } else { "fast" }, "stop"])
+    }
+
+    fn pg_ctl(&self, args: &[&str]) -> Result<()> {
+        let pg_ctl_path = self.env.pg_bin_dir(self.pg_version)?.join("pg_ctl");
+        let pg_lib_dir = self.env.pg_lib_dir(self.pg_version)?;
+        let pg_ctl = Command::new(&pg_ctl_path)
+            .args(["-D", self.pgdata().to_str().unwrap(), "-w"])
+            .args(args)
+            .env_clear()
+            .env("LD_LIBRARY_PATH", &pg_lib_dir)
+            .env("DYLD_LIBRARY_PATH", &pg_lib_dir)
+            .output()
+            .context(format!("{} failed", pg_ctl_path.display()))?;
+        if !pg_ctl.status.success() {
+            bail!(
+                "pg_ctl failed, exit code: {}, stdout: {}, stderr: {}",
+                pg_ctl.status,
+                String::from_utf8_lossy(&pg_ctl.stdout),
+                String::from_utf8_lossy(&pg_ctl.stderr),
+            );
+        }
+        Ok(())
+    }
+}
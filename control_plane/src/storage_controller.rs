@@ -620,6 +620,10 @@ pub async fn start(&self, start_args: NeonStorageControllerStartArgs) -> anyhow:
             args.push(format!("--max-secondary-lag-bytes={lag}"))
         }
 
+        if let Some(readiness) = self.config.min_secondary_readiness_pct.as_ref() {
+            args.push(format!("--min-secondary-readiness-pct={readiness}"))
+        }
+
         if let Some(threshold) = self.config.long_reconcile_threshold {
             args.push(format!(
                 "--long-reconcile-threshold={}",
@@ -650,6 +654,10 @@ pub async fn start(&self, start_args: NeonStorageControllerStartArgs) -> anyhow:
             args.push(format!("--timeline-safekeeper-count={sk_cnt}"));
         }
 
+        if self.config.timeline_safekeeper_witness {
+            args.push("--timeline-safekeeper-witness=true".to_string());
+        }
+
         if let Some(duration) = self.config.shard_split_request_timeout {
             args.push(format!(
                 "--shard-split-request-timeout={}",
@@ -11,7 +11,8 @@
     PlacementPolicy, SafekeeperDescribeResponse, SafekeeperSchedulingPolicyRequest,
     ShardSchedulingPolicy, ShardsPreferredAzsRequest, ShardsPreferredAzsResponse,
     SkSchedulingPolicy, TenantCreateRequest, TenantDescribeResponse, TenantPolicyRequest,
-    TenantShardMigrateRequest, TenantShardMigrateResponse, TimelineSafekeeperMigrateRequest,
+    TenantShardMigrateRequest, TenantShardMigrateResponse, TenantSnapshotPolicy,
+    TenantSnapshotPolicyDescribe, TimelineSafekeeperMigrateRequest,
 };
 use pageserver_api::models::{
     EvictionPolicy, EvictionPolicyLayerAccessThreshold, ShardParameters, TenantConfig,
@@ -112,6 +113,28 @@ enum Command {
         #[arg(long)]
         scheduling: Option<ShardSchedulingPolicyArg>,
     },
+    /// Take scheduled read-only snapshots of a timeline, replacing any previous snapshot policy
+    /// of the tenant.  Snapshots taken under a previous policy remain subject to retention.
+    TenantSnapshotPolicySet {
+        #[arg(long)]
+        tenant_id: TenantId,
+        #[arg(long)]
+        timeline_id: TimelineId,
+        /// Cron expression in UTC, including a seconds field, e.g. `0 0 3 * * *`
+        #[arg(long)]
+        schedule: String,
+        /// How many snapshots to keep: older ones are deleted
+        #[arg(long)]
+        retention_count: usize,
+        /// How many of the most recent snapshots to keep unarchived
+        #[arg(long, default_value_t = 1)]
+        unarchived_count: usize,
+    },
+    /// Stop taking scheduled snapshots of a tenant.  Existing snapshots are left in place.
+    TenantSnapshotPolicyClear {
+        #[arg(long)]
+        tenant_id: TenantId,
+    },
     /// List nodes known to the storage controller
     Nodes {},
     /// List soft deleted nodes known to the storage controller
@@ -302,6 +325,10 @@ enum Command {
         /// Example: --new-sk-set 1,2,3
         #[arg(long, required = true, value_delimiter = ',')]
         new_sk_set: Vec<NodeId>,
+        /// Bootstrap new members from a snapshot in remote storage if none of
+        /// the current members is reachable.
+        #[arg(long)]
+        allow_remote_snapshot: bool,
     },
     /// Abort ongoing safekeeper migration.
     TimelineSafekeeperMigrateAbort {
@@ -672,6 +699,39 @@ async fn main() -> anyhow::Result<()> {
                 )
                 .await?;
         }
+        Command::TenantSnapshotPolicySet {
+            tenant_id,
+            timeline_id,
+            schedule,
+            retention_count,
+            unarchived_count,
+        } => {
+            let req = TenantSnapshotPolicy {
+                timeline_id,
+                schedule,
+                retention_count,
+                unarchived_count,
+            };
+            let describe = storcon_client
+                .dispatch::<_, TenantSnapshotPolicyDescribe>(
+                    Method::PUT,
+                    format!("control/v1/tenant/{tenant_id}/snapshot_policy"),
+                    Some(req),
+                )
+                .await?;
+            if let Some(next) = describe.next_snapshot_at {
+                println!("Next snapshot at {next}");
+            }
+        }
+        Command::TenantSnapshotPolicyClear { tenant_id } => {
+            storcon_client
+                .dispatch::<(), ()>(
+                    Method::DELETE,
+                    format!("control/v1/tenant/{tenant_id}/snapshot_policy"),
+                    None,
+                )
+                .await?;
+        }
         Command::TenantShardSplit {
             tenant_id,
             shard_count,
@@ -796,6 +856,7 @@ async fn main() -> anyhow::Result<()> {
                 stripe_size,
                 policy,
                 config,
+                snapshot_policy,
             } = storcon_client
                 .dispatch::<(), TenantDescribeResponse>(
                     Method::GET,
@@ -873,6 +934,47 @@ async fn main() -> anyhow::Result<()> {
                 ]);
             }
             println!("{table}");
+
+            if let Some(snapshot_policy) = snapshot_policy {
+                let TenantSnapshotPolicy {
+                    timeline_id,
+                    schedule,
+                    retention_count,
+                    unarchived_count,
+                } = snapshot_policy.policy;
+                println!("Snapshots:");
+                let mut table = comfy_table::Table::new();
+                table.add_row(["Timeline", &timeline_id.to_string()]);
+                table.add_row(["Schedule", &schedule]);
+                table.add_row([
+                    "Retention",
+                    &format!("{retention_count} ({unarchived_count} unarchived)"),
+                ]);
+                table.add_row([
+                    "Next snapshot",
+                    &snapshot_policy
+                        .next_snapshot_at
+                        .map(|t| t.to_string())
+                        .unwrap_or_default(),
+                ]);
+                table.add_row([
+                    "Last error",
+                    &snapshot_policy.last_error.unwrap_or_default(),
+                ]);
+                println!("{table}");
+
+                let mut table = comfy_table::Table::new();
+                table.set_header(["Snapshot", "LSN", "Created", "Archived"]);
+                for snapshot in snapshot_policy.snapshots {
+                    table.add_row([
+                        snapshot.timeline_id.to_string(),
+                        snapshot.lsn.to_string(),
+                        snapshot.created_at.to_string(),
+                        snapshot.archived.to_string(),
+                    ]);
+                }
+                println!("{table}");
+            }
         }
         Command::TenantSetPreferredAz {
             tenant_id,
@@ -1392,6 +1494,7 @@ struct MigrationMove {
             tenant_id,
             timeline_id,
             new_sk_set,
+            allow_remote_snapshot,
         } => {
             let path = format!("v1/tenant/{tenant_id}/timeline/{timeline_id}/safekeeper_migrate");
 
@@ -1399,7 +1502,10 @@ struct MigrationMove {
                 .dispatch::<_, ()>(
                     Method::POST,
                     path,
-                    Some(TimelineSafekeeperMigrateRequest { new_sk_set }),
+                    Some(TimelineSafekeeperMigrateRequest {
+                        new_sk_set,
+                        allow_remote_snapshot,
+                    }),
                 )
                 .await?;
         }
@@ -226,6 +226,11 @@ pub enum ComputeFeature {
     /// Enable TLS functionality.
     TlsExperimental,
 
+    /// Request zstd-compressed basebackups from the pageserver, instead of gzip. zstd is much
+    /// cheaper to decompress, which shortens cold starts of small computes. compute_ctl falls
+    /// back to gzip if the pageserver rejects zstd.
+    BasebackupZstd,
+
     /// This is a special feature flag that is used to represent unknown feature flags.
     /// Basically all unknown to enum flags are represented as this one. See unit test
     /// `parse_unknown_features()` for more details.
@@ -24,7 +24,7 @@
 use serde_with::serde_as;
 use utils::logging::LogFormat;
 
-use crate::models::{ImageCompressionAlgorithm, LsnLease};
+use crate::models::{BasebackupCompression, ImageCompressionAlgorithm, LsnLease};
 
 // Certain metadata (e.g. externally-addressable name, AZ) is delivered
 // as a separate structure.  This information is not needed by the pageserver
@@ -275,6 +275,9 @@ pub struct ConfigToml {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub image_layer_generation_large_timeline_threshold: Option<u64>,
     pub force_metric_collection_on_scrape: bool,
+    pub compaction_scheduler: CompactionSchedulerConfig,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub slow_layer_tier: Option<SlowLayerTierConfig>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
@@ -433,6 +436,9 @@ pub struct BasebackupCacheConfig {
     /// Size of the channel used to send prepare requests to the basebackup cache worker.
     /// If exceeded, new prepare requests will be dropped.
     pub prepare_channel_size: usize,
+    /// Compression of cached basebackups. Only requests for this compression are served from
+    /// the cache. Changing it discards the existing cache entries on restart.
+    pub compression: BasebackupCompression,
 }
 
 impl Default for BasebackupCacheConfig {
@@ -443,10 +449,44 @@ fn default() -> Self {
             // max_entry_size_bytes: 16 * 1024 * 1024,   // 16 MiB
             max_size_entries: 10000,
             prepare_channel_size: 100,
+            compression: BasebackupCompression::Gzip,
         }
     }
 }
 
+/// Budget of the per-node compaction scheduler, which runs compaction for all tenant shards on
+/// the node in order of urgency.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct CompactionSchedulerConfig {
+    /// Maximum number of tenant shards compacting at the same time. Defaults to 3/4 of the
+    /// background runtime's worker threads, leaving threads for other work.
+    pub max_concurrent_compactions: Option<NonZeroUsize>,
+    /// Maximum rate of L0 bytes scheduled for compaction across all tenant shards. L0 compaction
+    /// reads and rewrites all L0 layers, so this approximates the compaction write IO. Unlimited
+    /// if unset.
+    pub max_l0_bytes_per_second: Option<NonZeroU64>,
+}
+
+/// A second, slower local directory for resident layers. The pageserver's workdir is the fast
+/// tier: layers are always downloaded and written there. Under disk pressure, the disk usage
+/// eviction task demotes cold layers to the slow tier instead of evicting them to remote storage,
+/// and promotes them back once they are accessed again and the fast tier has room. Requires
+/// `disk_usage_based_eviction` to be enabled.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct SlowLayerTierConfig {
+    /// Directory holding the demoted layers, mirroring the `tenants/` layout of the workdir.
+    pub path: Utf8PathBuf,
+    /// Usage threshold of the slow tier's filesystem above which demoted layers are evicted.
+    pub max_usage_pct: utils::serde_percent::Percent,
+    /// Minimum available bytes on the slow tier's filesystem before demoted layers are evicted.
+    pub min_avail_bytes: u64,
+    /// Demoted layers accessed at least this many times per hour are promoted back to the fast
+    /// tier when it has room. Promotion is disabled if unset.
+    #[serde(default)]
+    pub promote_min_accesses_per_hour: Option<NonZeroU64>,
+}
+
 pub mod statvfs {
     pub mod mock {
         #[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
@@ -485,6 +525,14 @@ pub enum EvictionOrder {
     RelativeAccessed {
         highest_layer_count_loses_first: bool,
     },
+    /// Scores layers by how much it would cost to evict them: access frequency and recency, layer
+    /// size, visibility, the estimated cost of downloading them again and the tenant's
+    /// `eviction_weight_pct`.
+    CostAware {
+        /// Fixed cost of an on-demand download, expressed in bytes that could have been downloaded
+        /// in the same time. Makes small layers relatively more expensive to evict.
+        redownload_overhead_bytes: u64,
+    },
 }
 
 impl Default for EvictionOrder {
@@ -564,6 +612,8 @@ pub struct TenantConfigToml {
     pub gc_period: Duration,
     // Delta layer churn threshold to create L1 image layers.
     pub image_creation_threshold: usize,
+    /// How compaction picks the partitions to create image layers for.
+    pub image_creation_policy: crate::models::ImageCreationPolicy,
     // HADRON
     // When the timeout is reached, PageServer will (1) force compact any remaining L0 deltas and
     // (2) create image layers if there are any L1 deltas.
@@ -651,6 +701,26 @@ pub struct TenantConfigToml {
     // FIXME: Remove skip_serializing_if when the feature is stable.
     #[serde(skip_serializing_if = "std::ops::Not::not")]
     pub basebackup_cache_enabled: bool,
+
+    /// Size limits, in bytes. When any of them is exceeded, the pageserver keeps ingesting WAL
+    /// but asks the compute (via safekeepers) to stop growing the database, i.e. to enter
+    /// read-only mode. Logical limits are evaluated on shard zero, which is the only shard that
+    /// maintains the logical size; physical limits are split evenly across the shards of the
+    /// tenant, and each shard enforces its share. None means unlimited.
+    pub timeline_logical_size_limit: Option<u64>,
+    pub timeline_physical_size_limit: Option<u64>,
+    pub tenant_logical_size_limit: Option<u64>,
+    pub tenant_physical_size_limit: Option<u64>,
+
+    /// How long a deleted timeline is kept in remote storage before it is purged. Until then, the
+    /// deletion can be undone. Zero means that timelines are deleted right away.
+    #[serde(with = "humantime_serde")]
+    pub timeline_delete_grace_period: Duration,
+
+    /// Relative cost of evicting this tenant's layers under disk pressure, in percent. Only used
+    /// by the `CostAware` eviction order: layers of a tenant with weight 200 are kept resident
+    /// as if they were accessed twice as often as those of a tenant with the default of 100.
+    pub eviction_weight_pct: u32,
 }
 
 pub mod defaults {
@@ -838,6 +908,8 @@ fn default() -> Self {
             posthog_config: None,
             image_layer_generation_large_timeline_threshold: Some(2 * 1024 * 1024 * 1024),
             force_metric_collection_on_scrape: true,
+            compaction_scheduler: CompactionSchedulerConfig::default(),
+            slow_layer_tier: None,
         }
     }
 }
@@ -905,6 +977,7 @@ pub mod tenant_conf_defaults {
     pub const DEFAULT_GC_COMPACTION_INITIAL_THRESHOLD_KB: u64 = 5 * 1024 * 1024; // 5GB
     pub const DEFAULT_GC_COMPACTION_RATIO_PERCENT: u64 = 100;
     pub const DEFAULT_RELSIZE_SNAPSHOT_CACHE_CAPACITY: usize = 1000;
+    pub const DEFAULT_EVICTION_WEIGHT_PCT: u32 = 100;
 }
 
 impl Default for TenantConfigToml {
@@ -931,6 +1004,7 @@ fn default() -> Self {
             gc_period: humantime::parse_duration(DEFAULT_GC_PERIOD)
                 .expect("cannot parse default gc period"),
             image_creation_threshold: DEFAULT_IMAGE_CREATION_THRESHOLD,
+            image_creation_policy: crate::models::ImageCreationPolicy::DeltaCount,
             image_layer_force_creation_period: None,
             pitr_interval: humantime::parse_duration(DEFAULT_PITR_INTERVAL)
                 .expect("cannot parse default PITR interval"),
@@ -964,6 +1038,12 @@ fn default() -> Self {
             sampling_ratio: None,
             relsize_snapshot_cache_capacity: DEFAULT_RELSIZE_SNAPSHOT_CACHE_CAPACITY,
             basebackup_cache_enabled: false,
+            timeline_logical_size_limit: None,
+            timeline_physical_size_limit: None,
+            tenant_logical_size_limit: None,
+            tenant_physical_size_limit: None,
+            timeline_delete_grace_period: Duration::ZERO,
+            eviction_weight_pct: DEFAULT_EVICTION_WEIGHT_PCT,
         }
     }
 }
@@ -11,7 +11,10 @@
 use utils::id::{NodeId, TenantId, TimelineId};
 use utils::lsn::Lsn;
 
-use crate::models::{PageserverUtilization, ShardParameters, TenantConfig, TimelineInfo};
+use crate::models::{
+    PageserverUtilization, ShardParameters, TenantConfig, TimelineExportStatus, TimelineInfo,
+    TimelineLayerRewriteStatus,
+};
 use crate::shard::{ShardStripeSize, TenantShardId};
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -82,6 +85,72 @@ pub struct TenantPolicyRequest {
     pub scheduling: Option<ShardSchedulingPolicy>,
 }
 
+/// A schedule for taking automatic snapshots of a timeline, executed by the storage controller.
+///
+/// Snapshots are read-only branches of the timeline. The most recent `unarchived_count`
+/// snapshots stay unarchived; older ones are archived, which lets the pageserver offload them.
+/// Snapshots beyond `retention_count` are deleted.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct TenantSnapshotPolicy {
+    pub timeline_id: TimelineId,
+    /// A cron expression in UTC including a seconds field, e.g. `0 0 3 * * *` for daily at 03:00.
+    pub schedule: String,
+    pub retention_count: usize,
+    #[serde(default = "TenantSnapshotPolicy::default_unarchived_count")]
+    pub unarchived_count: usize,
+}
+
+impl TenantSnapshotPolicy {
+    fn default_unarchived_count() -> usize {
+        1
+    }
+}
+
+/// A snapshot taken by a [`TenantSnapshotPolicy`].
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct TenantSnapshot {
+    pub timeline_id: TimelineId,
+    pub lsn: Lsn,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub archived: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TenantSnapshotPolicyDescribe {
+    pub policy: TenantSnapshotPolicy,
+    /// Live snapshots, oldest first.
+    pub snapshots: Vec<TenantSnapshot>,
+    pub next_snapshot_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// The error from the last run of the policy, if it failed.
+    pub last_error: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TimelineExportShardStatus {
+    pub tenant_shard_id: TenantShardId,
+    pub status: TimelineExportStatus,
+}
+
+/// Status of a timeline export, for every shard of the tenant.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TimelineExportResponse {
+    pub shards: Vec<TimelineExportShardStatus>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct NodeLayerRewriteStatus {
+    pub node_id: NodeId,
+    /// Set if the pageserver could not be reached, in which case `timelines` is empty.
+    pub error: Option<String>,
+    pub timelines: Vec<TimelineLayerRewriteStatus>,
+}
+
+/// Status of the layer rewrites on every pageserver, see [`TimelineLayerRewriteStatus`].
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct LayerRewriteResponse {
+    pub nodes: Vec<NodeLayerRewriteStatus>,
+}
+
 #[derive(Clone, Serialize, Deserialize, PartialEq, Eq, Hash, Debug, PartialOrd, Ord)]
 pub struct AvailabilityZone(pub String);
 
@@ -130,6 +199,8 @@ pub struct TenantDescribeResponse {
     pub stripe_size: ShardStripeSize,
     pub policy: PlacementPolicy,
     pub config: TenantConfig,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snapshot_policy: Option<TenantSnapshotPolicyDescribe>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -602,6 +673,11 @@ pub struct TimelineImportRequest {
 #[derive(serde::Serialize, serde::Deserialize, Clone)]
 pub struct TimelineSafekeeperMigrateRequest {
     pub new_sk_set: Vec<NodeId>,
+    /// Let new members bootstrap the timeline from a snapshot in remote
+    /// storage if none of the current members is reachable. The snapshot
+    /// may miss the latest WAL, so this is for recovering from a lost quorum.
+    #[serde(default)]
+    pub allow_remote_snapshot: bool,
 }
 
 #[cfg(test)]
@@ -423,7 +423,7 @@ pub struct TimelineCreateRequestModeImportPgdata {
     pub idempotency_key: ImportPgdataIdempotencyKey,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
 pub enum ImportPgdataLocation {
     #[cfg(feature = "testing")]
     LocalFs { path: Utf8PathBuf },
@@ -454,6 +454,124 @@ pub fn random() -> Self {
     }
 }
 
+/// Request to export a timeline as a full basebackup into remote storage, the inverse of
+/// [`TimelineCreateRequestMode::ImportPgdata`].
+///
+/// Each shard exports separately. Relation files of a sharded tenant have the blocks of other
+/// shards zeroed, and are merged by OR-ing the shards' files byte by byte.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TimelineExportRequest {
+    /// The LSN to export at, defaults to the last record LSN. All shards of a tenant must export
+    /// the same LSN: the storage controller exports shard zero first and passes its LSN on.
+    #[serde(default)]
+    pub lsn: Option<Lsn>,
+    /// Where to write the export. Uses the same format as the location of an import.
+    pub location: ImportPgdataLocation,
+    /// Target size of a single part before compression, in bytes. Capped at 1GiB.
+    #[serde(default)]
+    pub part_size: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum TimelineExportStatus {
+    InProgress {
+        lsn: Lsn,
+        started_at: chrono::NaiveDateTime,
+        parts_uploaded: usize,
+        bytes_uploaded: u64,
+    },
+    Done {
+        lsn: Lsn,
+        started_at: chrono::NaiveDateTime,
+        finished_at: chrono::NaiveDateTime,
+        parts_uploaded: usize,
+        bytes_uploaded: u64,
+    },
+    Failed {
+        lsn: Lsn,
+        started_at: chrono::NaiveDateTime,
+        finished_at: chrono::NaiveDateTime,
+        error: String,
+    },
+}
+
+impl TimelineExportStatus {
+    pub fn lsn(&self) -> Lsn {
+        match self {
+            Self::InProgress { lsn, .. } | Self::Done { lsn, .. } | Self::Failed { lsn, .. } => {
+                *lsn
+            }
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        !matches!(self, Self::InProgress { .. })
+    }
+}
+
+/// Request to rewrite the old image layers of a timeline in the current storage format, e.g.
+/// with the configured image compression.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct LayerRewriteRequest {
+    /// Upper bound on the rate at which layers are read for rewriting. Unlimited if unset.
+    #[serde(default)]
+    pub max_bytes_per_second: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum LayerRewriteStatus {
+    InProgress {
+        started_at: chrono::NaiveDateTime,
+        layers_checked: usize,
+        layers_rewritten: usize,
+        bytes_before: u64,
+        bytes_after: u64,
+    },
+    Done {
+        started_at: chrono::NaiveDateTime,
+        finished_at: chrono::NaiveDateTime,
+        layers_checked: usize,
+        layers_rewritten: usize,
+        bytes_before: u64,
+        bytes_after: u64,
+    },
+    Failed {
+        started_at: chrono::NaiveDateTime,
+        finished_at: chrono::NaiveDateTime,
+        error: String,
+    },
+}
+
+impl LayerRewriteStatus {
+    pub fn is_finished(&self) -> bool {
+        !matches!(self, Self::InProgress { .. })
+    }
+}
+
+/// The layer rewrite status of one timeline, as returned by the pageserver-wide endpoints.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TimelineLayerRewriteStatus {
+    pub tenant_shard_id: TenantShardId,
+    pub timeline_id: TimelineId,
+    pub status: LayerRewriteStatus,
+}
+
+/// Request to reset a timeline to an earlier LSN of its own history, keeping the history after
+/// that LSN on a backup branch.
+///
+/// On the storage controller, this creates the backup branch, detaches it and resets the
+/// timeline's safekeepers before resetting the timeline on all shards. On a pageserver, the
+/// backup branch must already exist and be detached from the timeline.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TimelineResetRequest {
+    /// The LSN to reset to. Must be a record boundary within the timeline's PITR window.
+    pub lsn: Lsn,
+    /// The branch that keeps the history of the timeline from before the reset.
+    pub backup_timeline_id: TimelineId,
+}
+
 #[derive(Serialize, Deserialize, Clone)]
 pub struct LsnLeaseRequest {
     pub lsn: Lsn,
@@ -597,6 +715,8 @@ pub struct TenantConfigPatch {
     pub gc_period: FieldPatch<String>,
     #[serde(skip_serializing_if = "FieldPatch::is_noop")]
     pub image_creation_threshold: FieldPatch<usize>,
+    #[serde(skip_serializing_if = "FieldPatch::is_noop")]
+    pub image_creation_policy: FieldPatch<ImageCreationPolicy>,
     // HADRON
     #[serde(skip_serializing_if = "FieldPatch::is_noop")]
     pub image_layer_force_creation_period: FieldPatch<String>,
@@ -646,6 +766,18 @@ pub struct TenantConfigPatch {
     pub relsize_snapshot_cache_capacity: FieldPatch<usize>,
     #[serde(skip_serializing_if = "FieldPatch::is_noop")]
     pub basebackup_cache_enabled: FieldPatch<bool>,
+    #[serde(skip_serializing_if = "FieldPatch::is_noop")]
+    pub timeline_logical_size_limit: FieldPatch<u64>,
+    #[serde(skip_serializing_if = "FieldPatch::is_noop")]
+    pub timeline_physical_size_limit: FieldPatch<u64>,
+    #[serde(skip_serializing_if = "FieldPatch::is_noop")]
+    pub tenant_logical_size_limit: FieldPatch<u64>,
+    #[serde(skip_serializing_if = "FieldPatch::is_noop")]
+    pub tenant_physical_size_limit: FieldPatch<u64>,
+    #[serde(skip_serializing_if = "FieldPatch::is_noop")]
+    pub timeline_delete_grace_period: FieldPatch<String>,
+    #[serde(skip_serializing_if = "FieldPatch::is_noop")]
+    pub eviction_weight_pct: FieldPatch<u32>,
 }
 
 /// Like [`crate::config::TenantConfigToml`], but preserves the information
@@ -703,6 +835,9 @@ pub struct TenantConfig {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub image_creation_threshold: Option<usize>,
 
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image_creation_policy: Option<ImageCreationPolicy>,
+
     // HADRON
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(with = "humantime_serde")]
@@ -783,6 +918,25 @@ pub struct TenantConfig {
 
     #[serde(skip_serializing_if = "Option::is_none")]
     pub basebackup_cache_enabled: Option<bool>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeline_logical_size_limit: Option<u64>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeline_physical_size_limit: Option<u64>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tenant_logical_size_limit: Option<u64>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tenant_physical_size_limit: Option<u64>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(with = "humantime_serde")]
+    pub timeline_delete_grace_period: Option<Duration>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub eviction_weight_pct: Option<u32>,
 }
 
 impl TenantConfig {
@@ -806,6 +960,7 @@ pub fn apply_patch(
             mut gc_horizon,
             mut gc_period,
             mut image_creation_threshold,
+            mut image_creation_policy,
             mut image_layer_force_creation_period,
             mut pitr_interval,
             mut walreceiver_connect_timeout,
@@ -830,6 +985,12 @@ pub fn apply_patch(
             mut sampling_ratio,
             mut relsize_snapshot_cache_capacity,
             mut basebackup_cache_enabled,
+            mut timeline_logical_size_limit,
+            mut timeline_physical_size_limit,
+            mut tenant_logical_size_limit,
+            mut tenant_physical_size_limit,
+            mut timeline_delete_grace_period,
+            mut eviction_weight_pct,
         } = self;
 
         patch.checkpoint_distance.apply(&mut checkpoint_distance);
@@ -870,6 +1031,9 @@ pub fn apply_patch(
         patch
             .image_creation_threshold
             .apply(&mut image_creation_threshold);
+        patch
+            .image_creation_policy
+            .apply(&mut image_creation_policy);
         // HADRON
         patch
             .image_layer_force_creation_period
@@ -939,6 +1103,23 @@ pub fn apply_patch(
         patch
             .basebackup_cache_enabled
             .apply(&mut basebackup_cache_enabled);
+        patch
+            .timeline_logical_size_limit
+            .apply(&mut timeline_logical_size_limit);
+        patch
+            .timeline_physical_size_limit
+            .apply(&mut timeline_physical_size_limit);
+        patch
+            .tenant_logical_size_limit
+            .apply(&mut tenant_logical_size_limit);
+        patch
+            .tenant_physical_size_limit
+            .apply(&mut tenant_physical_size_limit);
+        patch
+            .timeline_delete_grace_period
+            .map(|v| humantime::parse_duration(&v))?
+            .apply(&mut timeline_delete_grace_period);
+        patch.eviction_weight_pct.apply(&mut eviction_weight_pct);
 
         Ok(Self {
             checkpoint_distance,
@@ -956,6 +1137,7 @@ pub fn apply_patch(
             gc_horizon,
             gc_period,
             image_creation_threshold,
+            image_creation_policy,
             image_layer_force_creation_period,
             pitr_interval,
             walreceiver_connect_timeout,
@@ -980,6 +1162,12 @@ pub fn apply_patch(
             sampling_ratio,
             relsize_snapshot_cache_capacity,
             basebackup_cache_enabled,
+            timeline_logical_size_limit,
+            timeline_physical_size_limit,
+            tenant_logical_size_limit,
+            tenant_physical_size_limit,
+            timeline_delete_grace_period,
+            eviction_weight_pct,
         })
     }
 
@@ -1031,6 +1219,9 @@ pub fn merge(
             image_creation_threshold: self
                 .image_creation_threshold
                 .unwrap_or(global_conf.image_creation_threshold),
+            image_creation_policy: self
+                .image_creation_policy
+                .unwrap_or(global_conf.image_creation_policy),
             image_layer_force_creation_period: self
                 .image_layer_force_creation_period
                 .or(global_conf.image_layer_force_creation_period),
@@ -1094,6 +1285,24 @@ pub fn merge(
             basebackup_cache_enabled: self
                 .basebackup_cache_enabled
                 .unwrap_or(global_conf.basebackup_cache_enabled),
+            timeline_logical_size_limit: self
+                .timeline_logical_size_limit
+                .or(global_conf.timeline_logical_size_limit),
+            timeline_physical_size_limit: self
+                .timeline_physical_size_limit
+                .or(global_conf.timeline_physical_size_limit),
+            tenant_logical_size_limit: self
+                .tenant_logical_size_limit
+                .or(global_conf.tenant_logical_size_limit),
+            tenant_physical_size_limit: self
+                .tenant_physical_size_limit
+                .or(global_conf.tenant_physical_size_limit),
+            timeline_delete_grace_period: self
+                .timeline_delete_grace_period
+                .unwrap_or(global_conf.timeline_delete_grace_period),
+            eviction_weight_pct: self
+                .eviction_weight_pct
+                .unwrap_or(global_conf.eviction_weight_pct),
         }
     }
 }
@@ -1152,6 +1361,88 @@ pub fn discriminant_str(&self) -> &'static str {
     }
 }
 
+/// How compaction picks the partitions to create image layers for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind")]
+pub enum ImageCreationPolicy {
+    /// Create image layers for partitions with at least `image_creation_threshold` delta layers
+    /// on top of their latest image, whether or not they are read.
+    DeltaCount,
+    /// Create image layers for partitions in which GetPage requests visit many layers and that
+    /// have any delta layers on top of their latest image. Partitions that are not read are
+    /// skipped.
+    ReadAmplification(ImageCreationPolicyReadAmplification),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImageCreationPolicyReadAmplification {
+    /// Minimum average number of layers visited per read in a partition.
+    pub layers_per_read_threshold: u32,
+    /// Minimum number of sampled reads in a partition. Samples lose half of their weight every
+    /// hour, so this filters out partitions that are rarely or no longer read.
+    pub min_sampled_reads: u32,
+}
+
+/// Read amplification of GetPage requests in a key range, see [`ImageCreationPolicy`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReadAmpRangeInfo {
+    pub key_range: CompactKeyRange,
+    /// Number of sampled reads, decayed with a one hour half-life.
+    pub sampled_reads: f64,
+    /// Average number of layers visited per read.
+    pub layers_per_read: f64,
+}
+
+/// The per-node compaction queue, as returned by `GET /v1/compaction_queue`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompactionQueueInfo {
+    pub max_concurrent_compactions: usize,
+    pub max_l0_bytes_per_second: Option<u64>,
+    /// How long until the IO budget allows starting another compaction, 0 if it isn't exhausted.
+    pub io_budget_wait_ms: u64,
+    /// Tenant shards in the order they will compact: running ones first, then due ones by
+    /// decreasing urgency, then the others by their next periodic run.
+    pub entries: Vec<CompactionQueueEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompactionQueueEntry {
+    pub tenant_shard_id: TenantShardId,
+    /// The most urgent timeline of the tenant shard, if it has any active timelines.
+    pub timeline_id: Option<TimelineId>,
+    pub state: CompactionQueueState,
+    /// Urgency of the most urgent timeline. Compaction is due at 1.0, see `reason`.
+    pub score: f64,
+    /// Why the tenant shard is (or isn't) due for compaction.
+    pub reason: String,
+    pub l0_count: usize,
+    /// Size of the L0 layers across all timelines, which is charged against the IO budget.
+    pub l0_bytes: u64,
+    pub compaction_threshold: usize,
+    /// The L0 count at which layer flushes stall, or are delayed if stalls are disabled.
+    pub l0_backpressure_threshold: Option<usize>,
+    pub layers_per_read: f64,
+    pub next_run_in_ms: Option<u64>,
+    pub running_for_ms: Option<u64>,
+    pub consecutive_errors: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CompactionQueueState {
+    Running,
+    /// Waiting for a free slot or IO budget.
+    Due,
+    /// Waiting for the next periodic run or an L0 trigger.
+    Waiting,
+    /// Waiting to retry after errors.
+    Backoff,
+    /// Compaction is disabled by `compaction_period = 0`.
+    Disabled,
+    /// The tenant shard is not active yet.
+    Inactive,
+}
+
 #[derive(
     Eq,
     PartialEq,
@@ -1221,6 +1512,26 @@ fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
     }
 }
 
+/// Compression of a basebackup tarball sent to computes.
+#[derive(
+    Eq,
+    PartialEq,
+    Debug,
+    Copy,
+    Clone,
+    strum_macros::EnumString,
+    strum_macros::Display,
+    serde_with::DeserializeFromStr,
+    serde_with::SerializeDisplay,
+)]
+#[strum(serialize_all = "kebab-case")]
+pub enum BasebackupCompression {
+    Gzip,
+    /// Decompresses several times faster than gzip, which matters for cold starts of small
+    /// computes.
+    Zstd,
+}
+
 #[derive(Eq, PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub struct CompactionAlgorithmSettings {
     pub kind: CompactionAlgorithm,
@@ -1627,6 +1938,35 @@ pub struct TimelineInfo {
     // HADRON: the largest LSN below which all page updates have been included in the image layers.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub image_consistent_lsn: Option<Lsn>,
+
+    /// Set if the timeline currently exceeds one of the configured size limits, in which case
+    /// the compute is asked to enter read-only mode.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size_limit_exceeded: Option<SizeLimitExceeded>,
+}
+
+/// The size limit from [`TenantConfig`] that a timeline is exceeding.
+///
+/// If several limits are exceeded at once, the timeline-level ones take precedence, then the
+/// logical ones.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Serialize,
+    Deserialize,
+    strum_macros::IntoStaticStr,
+    strum_macros::Display,
+)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum SizeLimitExceeded {
+    TimelineLogical,
+    TimelinePhysical,
+    TenantLogical,
+    TenantPhysical,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -1655,6 +1995,11 @@ pub struct LayerAccessStats {
     pub residence_time: SystemTime,
 
     pub visible: bool,
+
+    /// Estimated number of accesses per hour, from a counter in which each access loses half of
+    /// its weight every day.
+    #[serde(default)]
+    pub access_rate: f32,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -1760,6 +2105,71 @@ pub struct TimelineGcRequest {
     pub gc_horizon: Option<u64>,
 }
 
+/// A constraint for which GC keeps a layer. GC checks them in this order, and a layer is attributed
+/// to the first one that applies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GcRetainReason {
+    /// The layer is newer than the cutoff from `gc_horizon`.
+    GcHorizon,
+    /// The layer is newer than the cutoff from `pitr_interval`.
+    Pitr,
+    /// A child branch might read from the layer.
+    Branch,
+    /// The layer is needed for an LSN lease.
+    Lease,
+    /// No newer image layer covers the layer's keys below the GC cutoff.
+    NoNewerImage,
+    /// GC would remove the layer, but GC is blocked for the tenant.
+    GcBlock,
+    /// GC skips the timeline because the cutoff from `pitr_interval` was not computed yet.
+    TimeCutoffPending,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GcRetainSummary {
+    pub reason: GcRetainReason,
+    pub layers: u64,
+    /// Total size of the layer files retained for this reason.
+    pub bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GcRetainedLayer {
+    pub layer_file_name: String,
+    pub layer_file_size: u64,
+    pub reason: GcRetainReason,
+}
+
+/// Result of a GC dry run: what the next GC of a timeline would keep, and why.
+///
+/// The cutoffs are those of the last GC iteration, which recomputes them before collecting.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimelineGcDryRunResponse {
+    /// The cutoff from `gc_horizon`, capped at `disk_consistent_lsn`.
+    pub space_cutoff: Lsn,
+    /// The cutoff from `pitr_interval`. None if it was not computed yet, in which case GC keeps
+    /// everything: layers it would otherwise remove are retained with
+    /// [`GcRetainReason::TimeCutoffPending`].
+    pub time_cutoff: Option<Lsn>,
+    /// The branch points of child timelines.
+    pub retain_lsns: Vec<Lsn>,
+    /// The highest LSN with a valid lease.
+    pub max_lease_lsn: Option<Lsn>,
+    /// The GC cutoff applied by the last GC.
+    pub applied_gc_cutoff: Lsn,
+    /// The GC cutoff the next GC would apply, taking standby replicas into account.
+    pub gc_cutoff: Lsn,
+    /// Why GC is skipped for the whole tenant, if it is.
+    pub blocked_by: Option<String>,
+    pub layers_total: u64,
+    pub layers_removable: u64,
+    pub bytes_removable: u64,
+    /// Per-constraint totals of the retained layers.
+    pub retained_by: Vec<GcRetainSummary>,
+    pub retained_layers: Vec<GcRetainedLayer>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WalRedoManagerProcessStatus {
     pub pid: u32,
@@ -1790,6 +2200,53 @@ pub struct SecondaryProgress {
     pub bytes_downloaded: u64,
     /// The number of layer bytes in the most recently seen heatmap
     pub bytes_total: u64,
+
+    /// The estimated accesses per hour to layers currently on-disk, as of the heatmap's upload
+    #[serde(default)]
+    pub access_rate_downloaded: f64,
+    /// The estimated accesses per hour to layers in the most recently seen heatmap
+    #[serde(default)]
+    pub access_rate_total: f64,
+
+    /// Moving average of the time it took to download a layer to this location
+    #[serde(default)]
+    pub layer_download_ms: Option<u64>,
+    /// Estimated time that reads would spend waiting for on-demand downloads in the first hour
+    /// after attaching this location, see [`Self::refresh_read_latency_impact`].
+    #[serde(default)]
+    pub est_read_latency_impact_ms: Option<u64>,
+}
+
+impl SecondaryProgress {
+    /// How ready the location is to be attached, between 0 and 1: the share of accesses to the
+    /// heatmap's layers that would be served by layers already on disk.
+    ///
+    /// Heatmaps without access rates (from older attached locations) fall back to the share of
+    /// bytes downloaded.
+    pub fn readiness(&self) -> f64 {
+        if self.heatmap_mtime.is_none() {
+            0.0
+        } else if self.access_rate_total > 0.0 {
+            (self.access_rate_downloaded / self.access_rate_total).clamp(0.0, 1.0)
+        } else if self.bytes_total > 0 {
+            self.bytes_downloaded as f64 / self.bytes_total as f64
+        } else {
+            1.0
+        }
+    }
+
+    /// Recompute [`Self::est_read_latency_impact_ms`] from the other fields.
+    ///
+    /// Every layer that is not on disk yet costs one download on its first access, so the number
+    /// of downloads in the first hour is bounded both by the accesses per hour to such layers and
+    /// by their count.
+    pub fn refresh_read_latency_impact(&mut self) {
+        self.est_read_latency_impact_ms = self.layer_download_ms.map(|download_ms| {
+            let accesses = (self.access_rate_total - self.access_rate_downloaded).max(0.0);
+            let layers = self.layers_total.saturating_sub(self.layers_downloaded) as f64;
+            (accesses.min(layers) * download_ms as f64) as u64
+        });
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -2176,4 +2633,40 @@ fn test_tenant_config_patch_request_serde() {
 
         assert_eq!(patched, expected);
     }
+
+    #[test]
+    fn test_secondary_progress_readiness() {
+        let mut progress = SecondaryProgress {
+            layers_total: 10,
+            layers_downloaded: 2,
+            bytes_total: 1000,
+            bytes_downloaded: 500,
+            ..Default::default()
+        };
+        // Nothing is known before a heatmap was seen.
+        assert_eq!(progress.readiness(), 0.0);
+
+        // Without access rates, bytes are the measure.
+        progress.heatmap_mtime = Some(serde_system_time::SystemTime(std::time::UNIX_EPOCH));
+        assert_eq!(progress.readiness(), 0.5);
+
+        progress.access_rate_total = 100.0;
+        progress.access_rate_downloaded = 90.0;
+        assert_eq!(progress.readiness(), 0.9);
+
+        // Unknown until a layer was downloaded.
+        progress.refresh_read_latency_impact();
+        assert_eq!(progress.est_read_latency_impact_ms, None);
+
+        // 5 accesses per hour to missing layers.
+        progress.access_rate_downloaded = 95.0;
+        progress.layer_download_ms = Some(100);
+        progress.refresh_read_latency_impact();
+        assert_eq!(progress.est_read_latency_impact_ms, Some(500));
+
+        // A missing layer is downloaded at most once.
+        progress.access_rate_downloaded = 0.0;
+        progress.refresh_read_latency_impact();
+        assert_eq!(progress.est_read_latency_impact_ms, Some(800));
+    }
 }
@@ -26,6 +26,10 @@
 use tokio_util::sync::CancellationToken;
 use tracing::{debug, error, info, trace, warn};
 
+/// `server_version` reported to clients if the handler doesn't know the version
+/// of the data it serves.
+pub const DEFAULT_SERVER_VERSION: &str = "14.1";
+
 /// An error, occurred during query processing:
 /// either during the connection ([`ConnectionError`]) or before/after it.
 #[derive(thiserror::Error, Debug)]
@@ -120,6 +124,21 @@ fn check_auth_jwt(
     ) -> Result<(), QueryError> {
         Err(QueryError::Other(anyhow::anyhow!("JWT auth failed")))
     }
+
+    /// Postgres version of the data served on this connection, reported to the
+    /// client as `server_version`. Called after [`Handler::startup`], and
+    /// [`Handler::check_auth_jwt`] with JWT auth. If None,
+    /// [`DEFAULT_SERVER_VERSION`] is reported.
+    fn server_version(&self) -> impl Future<Output = Option<String>> {
+        std::future::ready(None)
+    }
+
+    /// Whether to report server parameters a stock postgres client (e.g.
+    /// walreceiver of a standby) requires, like for trust auth, after
+    /// successful JWT auth. Called after [`Handler::check_auth_jwt`].
+    fn report_server_params_after_jwt(&self) -> bool {
+        false
+    }
 }
 
 /// PostgresBackend protocol state.
@@ -626,9 +645,17 @@ async fn handshake(&mut self, handler: &mut impl Handler<IO>) -> Result<(), Quer
                     }
 
                     self.write_message_noflush(&BeMessage::AuthenticationOk)?
-                        .write_message_noflush(&BeMessage::CLIENT_ENCODING)?
-                        .write_message(&BeMessage::ReadyForQuery)
-                        .await?;
+                        .write_message_noflush(&BeMessage::CLIENT_ENCODING)?;
+                    if handler.report_server_params_after_jwt() {
+                        // Same as for trust: libpq reports server version 0
+                        // otherwise, which e.g. walreceiver of a standby rejects.
+                        let version = handler.server_version().await;
+                        self.write_message_noflush(&BeMessage::INTEGER_DATETIMES)?
+                            .write_message_noflush(&BeMessage::server_version(
+                                version.as_deref().unwrap_or(DEFAULT_SERVER_VERSION),
+                            ))?;
+                    }
+                    self.write_message(&BeMessage::ReadyForQuery).await?;
                     self.state = ProtoState::Established;
                 }
                 Some(m) => {
@@ -703,11 +730,14 @@ async fn process_startup_message(
 
                 match self.auth_type {
                     AuthType::Trust => {
+                        let version = handler.server_version().await;
                         self.write_message_noflush(&BeMessage::AuthenticationOk)?
                             .write_message_noflush(&BeMessage::CLIENT_ENCODING)?
                             .write_message_noflush(&BeMessage::INTEGER_DATETIMES)?
                             // The async python driver requires a valid server_version
-                            .write_message_noflush(&BeMessage::server_version("14.1"))?
+                            .write_message_noflush(&BeMessage::server_version(
+                                version.as_deref().unwrap_or(DEFAULT_SERVER_VERSION),
+                            ))?
                             .write_message(&BeMessage::ReadyForQuery)
                             .await?;
                         self.state = ProtoState::Established;
@@ -30,6 +30,11 @@ pub fn from_full_pg_version(version: u32) -> PgVersionId {
             _ => panic!("Invalid full PostgreSQL version ID {version}"),
         }
     }
+
+    /// The version as postgres reports it in `server_version`, e.g. "17.4".
+    pub fn server_version(&self) -> String {
+        format!("{}.{}", self.0 / 10000, self.0 % 10000)
+    }
 }
 
 impl Display for PgVersionId {
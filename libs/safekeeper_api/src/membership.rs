@@ -48,6 +48,26 @@ fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
     }
 }
 
+/// Role of the safekeeper in the member set.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MemberRole {
+    /// Stores WAL.
+    #[default]
+    Data,
+    /// Participates in elections and acknowledges WAL like a data member, but
+    /// keeps only the position of WAL, not WAL itself. Thus it can't serve
+    /// WAL, e.g. be a donor in recovery or pull_timeline, or upload WAL to
+    /// remote storage. Allows to have 2 copies of WAL instead of 3 while
+    /// still tolerating failure of any member.
+    ///
+    /// Roles are sent to walproposer since protocol version 4; it doesn't
+    /// take witness as a donor and, if witness is ahead of all data members
+    /// which voted, waits for more votes. Safekeepers refuse older protocol
+    /// versions on timelines with witnesses.
+    Witness,
+}
+
 /// Membership is defined by ids so e.g. walproposer uses them to figure out
 /// quorums, but we also carry host and port to give wp idea where to connect.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -57,11 +77,17 @@ pub struct SafekeeperId {
     /// We include here only port for computes -- that is, pg protocol tenant
     /// only port, or wide pg protocol port if the former is not configured.
     pub pg_port: u16,
+    #[serde(default)]
+    pub role: MemberRole,
 }
 
 impl Display for SafekeeperId {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "[id={}, ep={}:{}]", self.id, self.host, self.pg_port)
+        write!(f, "[id={}, ep={}:{}", self.id, self.host, self.pg_port)?;
+        if self.role == MemberRole::Witness {
+            write!(f, ", witness")?;
+        }
+        write!(f, "]")
     }
 }
 
@@ -82,6 +108,9 @@ pub fn new(members: Vec<SafekeeperId>) -> anyhow::Result<Self> {
         if hs.len() != members.len() {
             bail!("duplicate safekeeper id in the set {:?}", members);
         }
+        if !members.is_empty() && members.iter().all(|sk| sk.role == MemberRole::Witness) {
+            bail!("no data members in the set {:?}", members);
+        }
         Ok(MemberSet { m: members })
     }
 
@@ -89,6 +118,18 @@ pub fn contains(&self, sk: NodeId) -> bool {
         self.m.iter().any(|m| m.id == sk)
     }
 
+    /// Is `sk` witness member of the set?
+    pub fn is_witness(&self, sk: NodeId) -> bool {
+        self.m
+            .iter()
+            .any(|m| m.id == sk && m.role == MemberRole::Witness)
+    }
+
+    /// Members storing WAL.
+    pub fn data_members(&self) -> impl Iterator<Item = &SafekeeperId> {
+        self.m.iter().filter(|m| m.role == MemberRole::Data)
+    }
+
     pub fn add(&mut self, sk: SafekeeperId) -> anyhow::Result<()> {
         if self.contains(sk.id) {
             bail!(format!(
@@ -143,6 +184,35 @@ pub fn new(members: MemberSet) -> Self {
     pub fn contains(&self, sk_id: NodeId) -> bool {
         self.members.contains(sk_id) || self.new_members.as_ref().is_some_and(|m| m.contains(sk_id))
     }
+
+    /// Is `sk_id` witness member of the configuration? Role of a safekeeper
+    /// is the same in both member sets of joint configuration.
+    pub fn is_witness(&self, sk_id: NodeId) -> bool {
+        self.members.is_witness(sk_id)
+            || self
+                .new_members
+                .as_ref()
+                .is_some_and(|m| m.is_witness(sk_id))
+    }
+
+    /// Are there witness members in the configuration?
+    pub fn has_witnesses(&self) -> bool {
+        std::iter::once(&self.members)
+            .chain(self.new_members.as_ref())
+            .any(|s| s.m.iter().any(|sk| sk.role == MemberRole::Witness))
+    }
+
+    /// Take roles of members from `other`. Configuration received from
+    /// walproposer using protocol older than 4 doesn't have them, all members
+    /// there are data ones.
+    pub fn inherit_roles(&mut self, other: &Configuration) {
+        let sets = std::iter::once(&mut self.members).chain(self.new_members.as_mut());
+        for sk in sets.flat_map(|s| s.m.iter_mut()) {
+            if other.is_witness(sk.id) {
+                sk.role = MemberRole::Witness;
+            }
+        }
+    }
 }
 
 impl Display for Configuration {
@@ -164,7 +234,7 @@ fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
 mod tests {
     use utils::id::NodeId;
 
-    use super::{MemberSet, SafekeeperId};
+    use super::{Configuration, MemberRole, MemberSet, SafekeeperId};
 
     #[test]
     fn test_member_set() {
@@ -174,6 +244,7 @@ fn test_member_set() {
                 id: NodeId(42),
                 host: String::from("lala.org"),
                 pg_port: 5432,
+                role: MemberRole::Data,
             })
             .unwrap();
 
@@ -182,6 +253,7 @@ fn test_member_set() {
                 id: NodeId(42),
                 host: String::from("lala.org"),
                 pg_port: 5432,
+                role: MemberRole::Data,
             })
             .expect_err("duplicate must not be allowed");
 
@@ -190,6 +262,7 @@ fn test_member_set() {
                 id: NodeId(43),
                 host: String::from("bubu.org"),
                 pg_port: 5432,
+                role: MemberRole::Witness,
             })
             .unwrap();
 
@@ -199,7 +272,59 @@ fn test_member_set() {
         println!("members json: {j}");
         assert_eq!(
             j,
-            r#"[{"id":42,"host":"lala.org","pg_port":5432},{"id":43,"host":"bubu.org","pg_port":5432}]"#
+            r#"[{"id":42,"host":"lala.org","pg_port":5432,"role":"data"},{"id":43,"host":"bubu.org","pg_port":5432,"role":"witness"}]"#
         );
+
+        // role defaults to data
+        let members: MemberSet =
+            serde_json::from_str(r#"[{"id":42,"host":"lala.org","pg_port":5432}]"#).unwrap();
+        assert_eq!(members.m[0].role, MemberRole::Data);
+    }
+
+    #[test]
+    fn test_witness() {
+        let sk = |id, role| SafekeeperId {
+            id: NodeId(id),
+            host: String::from("lala.org"),
+            pg_port: 5432,
+            role,
+        };
+        MemberSet::new(vec![sk(1, MemberRole::Witness)]).expect_err("no data members");
+
+        let mconf = Configuration::new(
+            MemberSet::new(vec![
+                sk(1, MemberRole::Data),
+                sk(2, MemberRole::Data),
+                sk(3, MemberRole::Witness),
+            ])
+            .unwrap(),
+        );
+        assert!(mconf.is_witness(NodeId(3)));
+        assert!(!mconf.is_witness(NodeId(1)));
+        assert!(!mconf.is_witness(NodeId(4)));
+        assert_eq!(mconf.members.data_members().count(), 2);
+
+        // as received from walproposer
+        let mut wp_mconf = Configuration {
+            generation: mconf.generation.next(),
+            members: MemberSet::new(vec![
+                sk(1, MemberRole::Data),
+                sk(2, MemberRole::Data),
+                sk(3, MemberRole::Data),
+            ])
+            .unwrap(),
+            new_members: Some(
+                MemberSet::new(vec![
+                    sk(1, MemberRole::Data),
+                    sk(3, MemberRole::Data),
+                    sk(4, MemberRole::Data),
+                ])
+                .unwrap(),
+            ),
+        };
+        wp_mconf.inherit_roles(&mconf);
+        assert!(wp_mconf.members.is_witness(NodeId(3)));
+        assert!(wp_mconf.new_members.as_ref().unwrap().is_witness(NodeId(3)));
+        assert!(!wp_mconf.is_witness(NodeId(4)));
     }
 }
@@ -1,6 +1,8 @@
 //! Types used in safekeeper http API. Many of them are also reused internally.
 
+use std::collections::HashMap;
 use std::net::SocketAddr;
+use std::num::NonZeroU64;
 
 use pageserver_api::shard::ShardIdentity;
 use postgres_ffi_types::TimestampTz;
@@ -195,6 +197,9 @@ pub struct TimelineStatus {
     pub tenant_id: TenantId,
     pub timeline_id: TimelineId,
     pub mconf: Configuration,
+    /// Whether the safekeeper is a witness member, i.e. doesn't store WAL.
+    #[serde(default)]
+    pub witness: bool,
     pub acceptor_state: AcceptorStateStatus,
     pub pg_info: ServerInfo,
     pub flush_lsn: Lsn,
@@ -290,11 +295,166 @@ pub struct TimelineTermBumpResponse {
     pub current_term: u64,
 }
 
+/// Request to discard WAL after `lsn` and continue the timeline from it in `term`, as part of a
+/// reset of the timeline to an earlier LSN.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TimelineResetWalRequest {
+    pub lsn: Lsn,
+    /// Must be higher than the current term of the safekeeper.
+    pub term: u64,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TimelineResetWalResponse {
+    // before the request
+    pub previous_flush_lsn: Lsn,
+    pub current_term: u64,
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct SafekeeperUtilization {
     pub timeline_count: u64,
 }
 
+/// Bandwidth limits for WAL received from computes, in bytes per second.
+/// Returned by and accepted by `/v1/wal_ingest_limits`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct WalIngestLimits {
+    /// Limit for a single timeline. None means unlimited.
+    #[serde(default)]
+    pub timeline_bytes_per_second: Option<NonZeroU64>,
+    /// Limit for all timelines of a tenant together. None means unlimited.
+    #[serde(default)]
+    pub tenant_bytes_per_second: Option<NonZeroU64>,
+    /// Per tenant overrides of `tenant_bytes_per_second`.
+    #[serde(default)]
+    pub tenant_overrides: HashMap<TenantId, NonZeroU64>,
+}
+
+impl WalIngestLimits {
+    /// Effective limit for all timelines of the tenant.
+    pub fn tenant_limit(&self, tenant_id: &TenantId) -> Option<NonZeroU64> {
+        self.tenant_overrides
+            .get(tenant_id)
+            .copied()
+            .or(self.tenant_bytes_per_second)
+    }
+}
+
+/// Where a safekeeper reads WAL from for `/wal_summary`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WalSummarySource {
+    /// Segments on the local disk.
+    Local,
+    /// Completed segments in remote storage.
+    Remote,
+    /// The last uploaded partial segment in remote storage.
+    RemotePartial,
+}
+
+impl std::str::FromStr for WalSummarySource {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "local" => Ok(Self::Local),
+            "remote" => Ok(Self::Remote),
+            "remote_partial" => Ok(Self::RemotePartial),
+            _ => anyhow::bail!("unknown WAL source {s:?}"),
+        }
+    }
+}
+
+impl std::fmt::Display for WalSummarySource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Local => "local",
+            Self::Remote => "remote",
+            Self::RemotePartial => "remote_partial",
+        })
+    }
+}
+
+/// Checksum of WAL in `[start_lsn, end_lsn)`. Chunks are aligned to the chunk
+/// size of the request, except where the source has no more WAL.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct WalChunkSummary {
+    pub start_lsn: Lsn,
+    pub end_lsn: Lsn,
+    pub crc32c: u32,
+    /// End of the last complete record before `start_lsn`. None if records
+    /// can't be decoded: the source doesn't have WAL at `from_lsn` of the
+    /// request, or it is not a valid WAL stream.
+    pub record_boundary: Option<Lsn>,
+}
+
+/// WAL of one timeline on one source, summarized in chunks. Ranges the source
+/// doesn't have are skipped.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct WalSummary {
+    /// Name of the source, e.g. `sk-1/local` or `remote`.
+    pub source: String,
+    pub chunks: Vec<WalChunkSummary>,
+    /// Why record boundaries are missing from some chunks, if they are.
+    pub decode_error: Option<String>,
+}
+
+/// Request to compare WAL of the timeline on all sources: local disk, remote
+/// storage and other members of the configuration.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct WalVerifyRequest {
+    /// Should be a record boundary, otherwise record boundaries are not
+    /// reported.
+    pub from_lsn: Lsn,
+    pub until_lsn: Lsn,
+    /// Size of compared chunks, must divide WAL segment size. Defaults to 1MiB.
+    #[serde(default)]
+    pub chunk_size: Option<u64>,
+    /// Http addresses of additional safekeepers to include, e.g. ones which
+    /// are not members of the configuration anymore.
+    #[serde(default)]
+    pub http_hosts: Vec<String>,
+}
+
+/// Result of comparing WAL of the timeline on all sources.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct WalVerifyResponse {
+    pub from_lsn: Lsn,
+    pub until_lsn: Lsn,
+    pub sources: Vec<WalSourceCoverage>,
+    /// Ranges where sources having WAL disagree on its content. Empty if all
+    /// sources agree.
+    pub divergences: Vec<WalDivergence>,
+}
+
+/// Which part of the requested range a source has.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct WalSourceCoverage {
+    pub source: String,
+    /// Contiguous ranges of WAL present on the source.
+    pub ranges: Vec<(Lsn, Lsn)>,
+    /// Error fetching the summary, e.g. the peer is unreachable.
+    pub error: Option<String>,
+    pub decode_error: Option<String>,
+}
+
+/// Range of WAL where sources split into groups with different content.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct WalDivergence {
+    pub start_lsn: Lsn,
+    pub end_lsn: Lsn,
+    pub groups: Vec<WalDivergenceGroup>,
+}
+
+/// Sources agreeing with each other within a [`WalDivergence`].
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct WalDivergenceGroup {
+    pub sources: Vec<String>,
+    /// Last record boundary before the divergence, if known.
+    pub record_boundary: Option<Lsn>,
+}
+
 /// pull_timeline request body.
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct PullTimelineRequest {
@@ -307,13 +467,24 @@ pub struct PullTimelineRequest {
     /// Storage controller always sets this field.
     /// None is only allowed for manual pull_timeline requests.
     pub mconf: Option<Configuration>,
+    /// If none of `http_hosts` responds, bootstrap the timeline from the most
+    /// advanced snapshot uploaded to remote storage by safekeepers and WAL
+    /// backup segments instead. The snapshot may be behind the members, so
+    /// this is only for the case when a quorum of them is lost.
+    #[serde(default)]
+    pub allow_remote_snapshot: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct PullTimelineResponse {
     /// Donor safekeeper host.
-    /// None if no pull happened because the timeline already exists.
+    /// None if no pull happened because the timeline already exists or it
+    /// was bootstrapped from a remote snapshot.
     pub safekeeper_host: Option<String>,
+    /// Safekeeper whose snapshot in remote storage the timeline was
+    /// bootstrapped from.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remote_snapshot: Option<NodeId>,
     // TODO: add more fields?
 }
 
@@ -31,6 +31,10 @@ pub enum Scope {
     /// token authorizing access to all data of a tenant, so the spec-fetch API requires a TenantEndpoint
     /// scope token to ensure that untrusted compute nodes can't fetch spec for arbitrary endpoints.
     TenantEndpoint,
+    /// Provides read-only access to WAL of a specific tenant on safekeepers, e.g. for
+    /// streaming it to a physical standby running outside Neon.
+    #[serde(rename = "tenant_read_only")]
+    TenantReadOnly,
     /// Provides blanket access to all tenants on the pageserver plus pageserver-wide APIs.
     /// Should only be used e.g. for status check/tenant creation/list.
     PageServerApi,
@@ -35,6 +35,10 @@ pub struct PageserverFeedback {
     /// If true, the pageserver has detected corruption and the safekeeper and postgres
     /// should stop sending WAL.
     pub corruption_detected: bool,
+    /// If true, the timeline exceeds one of its configured size limits, and postgres should
+    /// enter read-only mode. The pageserver keeps ingesting WAL regardless.
+    #[serde(default)]
+    pub size_limit_exceeded: bool,
 }
 
 impl PageserverFeedback {
@@ -47,6 +51,7 @@ pub fn empty() -> PageserverFeedback {
             replytime: *PG_EPOCH,
             shard_number: 0,
             corruption_detected: false,
+            size_limit_exceeded: false,
         }
     }
 
@@ -112,6 +117,13 @@ pub fn serialize(&self, buf: &mut BytesMut) {
             buf.put_u8(1);
         }
 
+        if self.size_limit_exceeded {
+            nkeys += 1;
+            buf.put_slice(b"size_limit_exceeded\0");
+            buf.put_i32(1);
+            buf.put_u8(1);
+        }
+
         buf[buf_ptr] = nkeys;
     }
 
@@ -163,6 +175,11 @@ pub fn parse(mut buf: Bytes) -> PageserverFeedback {
                     assert_eq!(len, 1);
                     rf.corruption_detected = buf.get_u8() != 0;
                 }
+                b"size_limit_exceeded" => {
+                    let len = buf.get_i32();
+                    assert_eq!(len, 1);
+                    rf.size_limit_exceeded = buf.get_u8() != 0;
+                }
                 _ => {
                     let len = buf.get_i32();
                     warn!(
@@ -234,6 +251,7 @@ fn test_replication_feedback_databricks_fields() {
         rf.replytime = *PG_EPOCH + Duration::from_secs(100_000_000);
         rf.shard_number = 1;
         rf.corruption_detected = true;
+        rf.size_limit_exceeded = true;
 
         let mut data = BytesMut::new();
         rf.serialize(&mut data);
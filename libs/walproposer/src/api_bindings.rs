@@ -459,6 +459,7 @@ pub fn empty_shmem() -> crate::bindings::WalproposerShmemState {
         replytime: 0,
         shard_number: 0,
         corruption_detected: false,
+        size_limit_exceeded: false,
     };
 
     let empty_wal_rate_limiter = crate::bindings::WalRateLimiter {
@@ -478,6 +479,7 @@ pub fn empty_shmem() -> crate::bindings::WalproposerShmemState {
         mineLastElectedTerm: crate::bindings::pg_atomic_uint64 { value: 0 },
         backpressureThrottlingTime: crate::bindings::pg_atomic_uint64 { value: 0 },
         currentClusterSize: crate::bindings::pg_atomic_uint64 { value: 0 },
+        sizeLimitExceeded: crate::bindings::pg_atomic_uint32 { value: 0 },
         shard_ps_feedback: [empty_feedback; 128],
         num_shards: 0,
         replica_promote: false,
@@ -485,6 +485,19 @@ pub async fn timeline_delete(
         }
     }
 
+    pub async fn timeline_undelete(
+        &self,
+        tenant_shard_id: TenantShardId,
+        timeline_id: TimelineId,
+    ) -> Result<()> {
+        let uri = format!(
+            "{}/v1/tenant/{tenant_shard_id}/timeline/{timeline_id}/undelete",
+            self.mgmt_api_endpoint
+        );
+
+        self.request(Method::PUT, &uri, ()).await.map(|_| ())
+    }
+
     pub async fn timeline_detail(
         &self,
         tenant_shard_id: TenantShardId,
@@ -545,6 +558,83 @@ pub async fn timeline_detach_ancestor(
             .map_err(Error::ReceiveBody)
     }
 
+    pub async fn timeline_reset(
+        &self,
+        tenant_shard_id: TenantShardId,
+        timeline_id: TimelineId,
+        req: &TimelineResetRequest,
+    ) -> Result<()> {
+        let uri = format!(
+            "{}/v1/tenant/{tenant_shard_id}/timeline/{timeline_id}/reset",
+            self.mgmt_api_endpoint
+        );
+
+        self.request(Method::PUT, &uri, req)
+            .await?
+            .json()
+            .await
+            .map_err(Error::ReceiveBody)
+    }
+
+    pub async fn timeline_export(
+        &self,
+        tenant_shard_id: TenantShardId,
+        timeline_id: TimelineId,
+        req: &TimelineExportRequest,
+    ) -> Result<TimelineExportStatus> {
+        let uri = format!(
+            "{}/v1/tenant/{tenant_shard_id}/timeline/{timeline_id}/export",
+            self.mgmt_api_endpoint
+        );
+
+        self.request(Method::PUT, &uri, req)
+            .await?
+            .json()
+            .await
+            .map_err(Error::ReceiveBody)
+    }
+
+    pub async fn timeline_export_status(
+        &self,
+        tenant_shard_id: TenantShardId,
+        timeline_id: TimelineId,
+    ) -> Result<TimelineExportStatus> {
+        let uri = format!(
+            "{}/v1/tenant/{tenant_shard_id}/timeline/{timeline_id}/export",
+            self.mgmt_api_endpoint
+        );
+
+        self.request(Method::GET, &uri, ())
+            .await?
+            .json()
+            .await
+            .map_err(Error::ReceiveBody)
+    }
+
+    /// Start a layer rewrite on all timelines of the tenants attached to the pageserver.
+    pub async fn layer_rewrite(
+        &self,
+        req: &LayerRewriteRequest,
+    ) -> Result<Vec<TimelineLayerRewriteStatus>> {
+        let uri = format!("{}/v1/layer_rewrite", self.mgmt_api_endpoint);
+
+        self.request(Method::PUT, &uri, req)
+            .await?
+            .json()
+            .await
+            .map_err(Error::ReceiveBody)
+    }
+
+    pub async fn layer_rewrite_status(&self) -> Result<Vec<TimelineLayerRewriteStatus>> {
+        let uri = format!("{}/v1/layer_rewrite", self.mgmt_api_endpoint);
+
+        self.request(Method::GET, &uri, ())
+            .await?
+            .json()
+            .await
+            .map_err(Error::ReceiveBody)
+    }
+
     pub async fn timeline_block_unblock_gc(
         &self,
         tenant_shard_id: TenantShardId,
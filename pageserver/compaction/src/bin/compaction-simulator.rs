@@ -3,8 +3,10 @@
 use std::sync::OnceLock;
 
 use clap::{Parser, Subcommand};
+use pageserver_api::config::tenant_conf_defaults;
 use pageserver_compaction::helpers::PAGE_SZ;
-use pageserver_compaction::simulator::MockTimeline;
+use pageserver_compaction::simulator::replay::{self, Replay};
+use pageserver_compaction::simulator::{CompactionPolicy, MockTimeline};
 use rand::Rng;
 use utils::project_git_version;
 
@@ -26,6 +28,7 @@ struct CliOpts {
 enum Commands {
     RunSuite,
     Simulate(SimulateCmd),
+    Replay(ReplayCmd),
 }
 
 #[derive(Clone, clap::ValueEnum)]
@@ -48,6 +51,134 @@ struct SimulateCmd {
     logical_size: u64,
 }
 
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum Policy {
+    Legacy,
+    Tiered,
+    GcCompaction,
+}
+
+impl From<Policy> for CompactionPolicy {
+    fn from(value: Policy) -> Self {
+        match value {
+            Policy::Legacy => CompactionPolicy::Legacy,
+            Policy::Tiered => CompactionPolicy::Tiered,
+            Policy::GcCompaction => CompactionPolicy::GcCompaction,
+        }
+    }
+}
+
+/// Replay a recorded write-rate trace on top of a real layer map, and compare the
+/// write amplification, read amplification and space usage of compaction policies.
+///
+/// The layer map is either a local timeline directory, or a file with one
+/// `<layer file name> <file size>` line per layer, e.g. from
+/// `pagectl index-part list-layers --path index_part.json`. The trace has one
+/// `<seconds> <WAL bytes>` line per interval.
+#[derive(Parser)]
+struct ReplayCmd {
+    /// Layer map to start from
+    #[arg(long)]
+    layers: PathBuf,
+    /// Write-rate trace to replay
+    #[arg(long)]
+    trace: PathBuf,
+    /// Compaction policies to compare
+    #[arg(long, value_enum, num_args = 1.., default_values_t = [Policy::Legacy, Policy::Tiered, Policy::GcCompaction])]
+    policy: Vec<Policy>,
+    /// Size of the simulated WAL records
+    #[arg(long, default_value_t = PAGE_SZ)]
+    record_len: u64,
+    /// Directory to write the results to
+    #[arg(long)]
+    results_path: Option<PathBuf>,
+
+    #[arg(long, default_value_t = tenant_conf_defaults::DEFAULT_CHECKPOINT_DISTANCE)]
+    checkpoint_distance: u64,
+    #[arg(long, default_value_t = tenant_conf_defaults::DEFAULT_COMPACTION_TARGET_SIZE)]
+    compaction_target_size: u64,
+    #[arg(long, default_value_t = tenant_conf_defaults::DEFAULT_COMPACTION_THRESHOLD)]
+    compaction_threshold: usize,
+    #[arg(long, default_value_t = tenant_conf_defaults::DEFAULT_IMAGE_CREATION_THRESHOLD)]
+    image_creation_threshold: usize,
+    #[arg(long, default_value_t = tenant_conf_defaults::DEFAULT_GC_HORIZON)]
+    gc_horizon: u64,
+    #[arg(long, default_value_t = tenant_conf_defaults::DEFAULT_GC_COMPACTION_RATIO_PERCENT)]
+    gc_compaction_ratio_percent: u64,
+}
+
+async fn replay(cmd: &ReplayCmd) -> anyhow::Result<()> {
+    let results_path = match &cmd.results_path {
+        Some(path) => path.clone(),
+        None => PathBuf::from(format!(
+            "compaction-replay-results.{}",
+            std::time::SystemTime::UNIX_EPOCH.elapsed()?.as_secs()
+        )),
+    };
+    std::fs::create_dir_all(&results_path)?;
+
+    let layers = replay::load_layer_map(&cmd.layers)?;
+    let trace = replay::load_write_trace(&cmd.trace)?;
+    println!(
+        "loaded {} layers, {} trace intervals, {} MiB of WAL",
+        layers.len(),
+        trace.len(),
+        trace.iter().map(|i| i.wal_bytes).sum::<u64>() / (1024 * 1024)
+    );
+    let replay = Replay::new(layers)?;
+
+    let mut runs = Vec::new();
+    for policy in cmd.policy.iter().copied().map(CompactionPolicy::from) {
+        let policy_path = results_path.join(policy.to_string());
+        std::fs::create_dir_all(&policy_path)?;
+        set_log_file(File::create(policy_path.join("log"))?);
+
+        let mut executor = replay.new_timeline(cmd.record_len);
+        executor.policy = policy;
+        executor.target_file_size = cmd.compaction_target_size;
+        executor.checkpoint_distance = cmd.checkpoint_distance;
+        executor.compaction_threshold = cmd.compaction_threshold;
+        executor.image_creation_threshold = cmd.image_creation_threshold;
+        executor.gc_horizon = Some(cmd.gc_horizon);
+        executor.gc_compaction_ratio_percent = cmd.gc_compaction_ratio_percent;
+
+        let mut samples = vec![replay.sample(&executor, 0.0)];
+        for (i, interval) in trace.iter().enumerate() {
+            samples.push(
+                replay
+                    .ingest_interval(&mut executor, interval, cmd.record_len)
+                    .await?,
+            );
+            print!(
+                "\r{policy}: replayed {} / {} intervals...",
+                i + 1,
+                trace.len()
+            );
+            std::io::stdout().flush()?;
+        }
+        executor.flush_l0();
+        executor.compact().await?;
+        set_log_stdout();
+
+        let last = samples.last().unwrap();
+        println!(
+            "\r{policy}: write amp {:.2}, read amp {:.2}, {} MiB",
+            last.write_amp(),
+            last.read_amp,
+            last.live_bytes / (1024 * 1024)
+        );
+        std::fs::write(policy_path.join("stats.txt"), executor.stats()?)?;
+        executor.draw_history(File::create(policy_path.join("compaction-animation.html"))?)?;
+        runs.push((policy.to_string(), samples));
+    }
+
+    let metrics_path = results_path.join("metrics.svg");
+    replay::draw_metrics(&runs, File::create(&metrics_path)?)?;
+    println!("metrics: file://{}", metrics_path.canonicalize()?.display());
+
+    Ok(())
+}
+
 async fn simulate(cmd: &SimulateCmd, results_path: &Path) -> anyhow::Result<()> {
     let mut executor = MockTimeline::new();
 
@@ -211,6 +342,9 @@ async fn main() -> anyhow::Result<()> {
         Commands::RunSuite => {
             run_suite().await?;
         }
+        Commands::Replay(cmd) => {
+            replay(&cmd).await?;
+        }
     };
     Ok(())
 }
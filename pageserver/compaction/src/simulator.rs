@@ -1,4 +1,5 @@
 mod draw;
+pub mod replay;
 
 use std::fmt::Write;
 use std::ops::Range;
@@ -11,18 +12,54 @@
 use tracing::info;
 use utils::lsn::Lsn;
 
-use crate::helpers::{PAGE_SZ, merge_delta_keys, overlaps_with};
+use crate::helpers::{PAGE_SZ, fully_contains, merge_delta_keys, overlaps_with};
 use crate::interface;
 use crate::interface::CompactionLayer;
 
+/// Compaction algorithm to simulate.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CompactionPolicy {
+    /// L0 -> L1 compaction followed by image layer creation, like the pageserver's
+    /// `CompactionAlgorithm::Legacy`.
+    Legacy,
+    /// The tiered compaction implemented in this crate.
+    Tiered,
+    /// Legacy compaction, plus gc-compaction rewriting everything below the GC horizon
+    /// into image layers once the deltas there grow large compared to the images.
+    GcCompaction,
+}
+
+impl std::fmt::Display for CompactionPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            CompactionPolicy::Legacy => "legacy",
+            CompactionPolicy::Tiered => "tiered",
+            CompactionPolicy::GcCompaction => "gc-compaction",
+        })
+    }
+}
+
 //
 // Implementation for the CompactionExecutor interface
 //
 pub struct MockTimeline {
+    pub policy: CompactionPolicy,
+
     // Parameters for the compaction algorithm
     pub target_file_size: u64,
     tiers_per_level: u64,
 
+    // Parameters for the legacy and gc-compaction policies. These mirror the
+    // `TenantConfig` settings of the same name.
+    pub checkpoint_distance: u64,
+    pub compaction_threshold: usize,
+    pub image_creation_threshold: usize,
+    pub gc_compaction_ratio_percent: u64,
+
+    /// If set, layers that are below the GC horizon and fully covered by newer image
+    /// layers are deleted after every compaction.
+    pub gc_horizon: Option<u64>,
+
     num_l0_flushes: u64,
     last_compact_at_flush: u64,
     last_flush_lsn: Lsn,
@@ -181,9 +218,17 @@ fn is_delta(&self) -> bool {
 impl MockTimeline {
     pub fn new() -> Self {
         MockTimeline {
+            policy: CompactionPolicy::Tiered,
+
             target_file_size: 256 * 1024 * 1024,
             tiers_per_level: 4,
 
+            checkpoint_distance: 256 * 1024 * 1024,
+            compaction_threshold: 10,
+            image_creation_threshold: 3,
+            gc_compaction_ratio_percent: 100,
+            gc_horizon: None,
+
             num_l0_flushes: 0,
             last_compact_at_flush: 0,
             last_flush_lsn: Lsn(0),
@@ -214,18 +259,226 @@ pub fn new() -> Self {
     pub async fn compact(&mut self) -> anyhow::Result<()> {
         let ctx = MockRequestContext {};
 
-        crate::compact_tiered::compact_tiered(
-            self,
-            self.last_flush_lsn,
-            self.target_file_size,
-            self.tiers_per_level,
-            &ctx,
-        )
-        .await?;
+        match self.policy {
+            CompactionPolicy::Tiered => {
+                crate::compact_tiered::compact_tiered(
+                    self,
+                    self.last_flush_lsn,
+                    self.target_file_size,
+                    self.tiers_per_level,
+                    &ctx,
+                )
+                .await?;
+            }
+            CompactionPolicy::Legacy => {
+                self.compact_l0(&ctx).await?;
+                self.create_images_if_needed(&ctx).await?;
+            }
+            CompactionPolicy::GcCompaction => {
+                self.compact_l0(&ctx).await?;
+                self.gc_compact_if_needed(&ctx).await?;
+            }
+        }
+
+        if let Some(gc_horizon) = self.gc_horizon {
+            self.gc(gc_horizon, &ctx).await?;
+        }
 
         Ok(())
     }
 
+    fn l0_layers(&self) -> Vec<Arc<MockDeltaLayer>> {
+        self.live_layers
+            .iter()
+            .filter_map(|l| match l {
+                MockLayer::Delta(d) if !l.is_deleted() && d.key_range == (Key::MIN..Key::MAX) => {
+                    Some(d.clone())
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Merge all L0 layers into L1 layers of roughly `target_file_size`, split by key.
+    async fn compact_l0(&mut self, ctx: &MockRequestContext) -> anyhow::Result<()> {
+        let l0 = self.l0_layers();
+        if l0.len() < self.compaction_threshold {
+            return Ok(());
+        }
+        let lsn_range = l0.iter().map(|l| l.lsn_range.start).min().unwrap()
+            ..l0.iter().map(|l| l.lsn_range.end).max().unwrap();
+
+        // Find the split points. Never split the history of a single key.
+        let mut split_keys = Vec::new();
+        {
+            let mut key_value_stream = std::pin::pin!(merge_delta_keys::<MockTimeline>(&l0, ctx));
+            let mut accum_size = 0;
+            let mut prev_key = None;
+            while let Some(entry) = key_value_stream.next().await {
+                let entry = entry?;
+                if accum_size >= self.target_file_size && prev_key != Some(entry.key) {
+                    split_keys.push(entry.key);
+                    accum_size = 0;
+                }
+                accum_size += entry.len;
+                prev_key = Some(entry.key);
+            }
+        }
+
+        let mut start = Key::MIN;
+        for end in split_keys.into_iter().chain(std::iter::once(Key::MAX)) {
+            self.create_delta(&lsn_range, &(start..end), &l0, ctx)
+                .await?;
+            start = end;
+        }
+        for l in l0 {
+            self.delete_layer(&MockLayer::Delta(l), ctx).await?;
+        }
+        Ok(())
+    }
+
+    /// Split the keyspace into partitions that produce image layers of roughly
+    /// `target_file_size`.
+    fn image_partitions(&self) -> Vec<Range<Key>> {
+        let keys_per_partition = std::cmp::max(self.target_file_size / PAGE_SZ, 1);
+        let mut partitions = Vec::new();
+        for range in self.keyspace.iter() {
+            let mut start = range.start;
+            while start < range.end {
+                let end = std::cmp::min(start.saturating_add(keys_per_partition), range.end);
+                partitions.push(start..end);
+                start = end;
+            }
+        }
+        partitions
+    }
+
+    /// The LSN of the newest image layer that fully covers `key_range`, if any.
+    fn latest_image_lsn(&self, key_range: &Range<Key>) -> Option<Lsn> {
+        self.live_layers
+            .iter()
+            .filter(|l| {
+                !l.is_deleted() && !l.is_delta() && fully_contains(l.key_range(), key_range)
+            })
+            .map(|l| l.lsn_range().start)
+            .max()
+    }
+
+    /// Like the pageserver's image layer creation: create a new image layer for every
+    /// partition that has at least `image_creation_threshold` delta layers on top of its
+    /// latest image.
+    async fn create_images_if_needed(&mut self, ctx: &MockRequestContext) -> anyhow::Result<()> {
+        let lsn = self.last_flush_lsn;
+        for partition in self.image_partitions() {
+            let image_lsn = self.latest_image_lsn(&partition).unwrap_or(Lsn(0));
+            if image_lsn >= lsn {
+                continue;
+            }
+            let num_deltas = self
+                .live_layers
+                .iter()
+                .filter(|l| {
+                    !l.is_deleted()
+                        && l.is_delta()
+                        && l.lsn_range().end > image_lsn
+                        && overlaps_with(l.key_range(), &partition)
+                })
+                .count();
+            if num_deltas >= self.image_creation_threshold {
+                self.create_image(lsn, &partition, ctx).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Once the deltas below the GC horizon have grown to `gc_compaction_ratio_percent`
+    /// of the image layers, rewrite the whole keyspace as image layers at the horizon.
+    /// The layers below it then become garbage.
+    ///
+    /// This is an approximation of the pageserver's gc-compaction: it does not retain
+    /// deltas for branch points, and leaves the layers crossing the horizon alone.
+    async fn gc_compact_if_needed(&mut self, ctx: &MockRequestContext) -> anyhow::Result<()> {
+        let Some(gc_horizon) = self.gc_horizon else {
+            return Ok(());
+        };
+        let horizon = Lsn(self.last_flush_lsn.0.saturating_sub(gc_horizon));
+
+        let mut image_size = 0;
+        let mut delta_size = 0;
+        for l in self.live_layers.iter().filter(|l| !l.is_deleted()) {
+            if l.is_delta() {
+                if l.lsn_range().end <= horizon && l.key_range() != &(Key::MIN..Key::MAX) {
+                    delta_size += l.file_size();
+                }
+            } else if l.lsn_range().start <= horizon {
+                image_size += l.file_size();
+            }
+        }
+        if delta_size == 0 || delta_size * 100 < image_size * self.gc_compaction_ratio_percent {
+            return Ok(());
+        }
+
+        info!(
+            "gc-compacting below {horizon}: {delta_size} bytes of deltas, {image_size} bytes of images"
+        );
+        for partition in self.image_partitions() {
+            if self.latest_image_lsn(&partition) != Some(horizon) {
+                self.create_image(horizon, &partition, ctx).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Delete the layers that are entirely below `last_flush_lsn - gc_horizon` and whose
+    /// key range is covered by newer image layers that are also below the horizon.
+    async fn gc(&mut self, gc_horizon: u64, ctx: &MockRequestContext) -> anyhow::Result<()> {
+        let horizon = Lsn(self.last_flush_lsn.0.saturating_sub(gc_horizon));
+        self.live_layers.retain(|l| !l.is_deleted());
+
+        let mut garbage = Vec::new();
+        for l in self.live_layers.iter() {
+            let layer_end = if l.is_delta() {
+                l.lsn_range().end
+            } else {
+                l.lsn_range().start
+            };
+            if layer_end > horizon {
+                continue;
+            }
+            let mut covering: Vec<Range<Key>> = self
+                .live_layers
+                .iter()
+                .filter(|img| {
+                    // A delta layer's end LSN is exclusive, so an image layer at that
+                    // LSN already supersedes it.
+                    let img_lsn = img.lsn_range().start;
+                    !img.is_delta()
+                        && img_lsn <= horizon
+                        && (img_lsn > layer_end || (l.is_delta() && img_lsn == layer_end))
+                })
+                .map(|img| img.key_range().clone())
+                .collect();
+            covering.sort_by_key(|r| r.start);
+
+            // Sweep over the covering image layers to check that there are no gaps.
+            let mut covered_until = l.key_range().start;
+            for r in covering {
+                if r.start > covered_until {
+                    break;
+                }
+                covered_until = std::cmp::max(covered_until, r.end);
+            }
+            if covered_until >= l.key_range().end {
+                garbage.push(l.clone());
+            }
+        }
+
+        for l in garbage {
+            self.delete_layer(&l, ctx).await?;
+        }
+        Ok(())
+    }
+
     // Ingest one record to the timeline
     pub fn ingest_record(&mut self, key: Key, len: u64) {
         self.records.push(MockRecord {
@@ -236,13 +489,23 @@ pub fn ingest_record(&mut self, key: Key, len: u64) {
         self.total_len += len;
         self.end_lsn += len;
 
-        if self.total_len > self.target_file_size {
+        let flush_threshold = match self.policy {
+            CompactionPolicy::Tiered => self.target_file_size,
+            CompactionPolicy::Legacy | CompactionPolicy::GcCompaction => self.checkpoint_distance,
+        };
+        if self.total_len > flush_threshold {
             self.flush_l0();
         }
     }
 
     pub async fn compact_if_needed(&mut self) -> anyhow::Result<()> {
-        if self.num_l0_flushes - self.last_compact_at_flush >= self.tiers_per_level {
+        let compact_every = match self.policy {
+            CompactionPolicy::Tiered => self.tiers_per_level,
+            // Legacy compaction runs after every flush, and decides itself whether there
+            // are enough L0 layers.
+            CompactionPolicy::Legacy | CompactionPolicy::GcCompaction => 1,
+        };
+        if self.num_l0_flushes - self.last_compact_at_flush >= compact_every {
             self.compact().await?;
             self.last_compact_at_flush = self.num_l0_flushes;
         }
@@ -8,6 +8,7 @@
 use utils::lsn::Lsn;
 
 use super::Key;
+use super::replay::MetricsSample;
 
 // Map values to their compressed coordinate - the index the value
 // would have in a sorted and deduplicated list of all values.
@@ -51,6 +52,8 @@ fn max(&self) -> f32 {
 
 #[derive(PartialEq, Hash, Eq)]
 pub enum LayerTraceOp {
+    /// Layer that existed when the simulation started
+    Load,
     Flush,
     CreateDelta,
     CreateImage,
@@ -60,6 +63,7 @@ pub enum LayerTraceOp {
 impl std::fmt::Display for LayerTraceOp {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
         let op_str = match self {
+            LayerTraceOp::Load => "load",
             LayerTraceOp::Flush => "flush",
             LayerTraceOp::CreateDelta => "create_delta",
             LayerTraceOp::CreateImage => "create_image",
@@ -314,6 +318,7 @@ pub fn draw_history<W: std::io::Write>(history: &[LayerTraceEvent], mut output:
       function redoLayerEvent(n, dir) {{
           var layer = document.getElementById("layer_" + layer_events[n].filename);
           switch (layer_events[n].op) {{
+              case "load":
               case "flush":
                   layer.style.visibility = "visible";
                   break;
@@ -331,6 +336,7 @@ pub fn draw_history<W: std::io::Write>(history: &[LayerTraceEvent], mut output:
       function undoLayerEvent(n) {{
           var layer = document.getElementById("layer_" + layer_events[n].filename);
           switch (layer_events[n].op) {{
+              case "load":
               case "flush":
                   layer.style.visibility = "hidden";
                   break;
@@ -408,3 +414,127 @@ pub fn draw_history<W: std::io::Write>(history: &[LayerTraceEvent], mut output:
 
     Ok(())
 }
+
+/// Draw line charts of write amplification, read amplification and space usage over
+/// time, with one line per simulation run.
+pub fn draw_metrics<W: std::io::Write>(
+    runs: &[(String, Vec<MetricsSample>)],
+    mut output: W,
+) -> Result<()> {
+    const PLOT_W: f32 = 800.0;
+    const PLOT_H: f32 = 200.0;
+    const MARGIN: f32 = 60.0;
+    const COLORS: [(u8, u8, u8); 6] = [
+        (0xd6, 0x27, 0x28),
+        (0x1f, 0x77, 0xb4),
+        (0x2c, 0xa0, 0x2c),
+        (0xff, 0x7f, 0x0e),
+        (0x94, 0x67, 0xbd),
+        (0x8c, 0x56, 0x4b),
+    ];
+
+    let charts: [(&str, fn(&MetricsSample) -> f64); 3] = [
+        ("write amplification", MetricsSample::write_amp),
+        ("read amplification (layers per GetPage)", |s| s.read_amp),
+        ("space usage (MiB)", |s| {
+            s.live_bytes as f64 / (1024.0 * 1024.0)
+        }),
+    ];
+
+    let max_time = runs
+        .iter()
+        .flat_map(|(_, samples)| samples.iter().map(|s| s.time_secs))
+        .fold(1.0, f64::max);
+
+    let mut svg = String::new();
+    writeln!(
+        svg,
+        "{}",
+        BeginSvg {
+            w: PLOT_W + 2.0 * MARGIN,
+            h: (PLOT_H + MARGIN) * charts.len() as f32 + MARGIN,
+        }
+    )?;
+
+    for (chart_idx, (title, metric)) in charts.iter().enumerate() {
+        let top = MARGIN + chart_idx as f32 * (PLOT_H + MARGIN);
+        let max_value = runs
+            .iter()
+            .flat_map(|(_, samples)| samples.iter().map(metric))
+            .filter(|v| v.is_finite())
+            .fold(0.0, f64::max);
+        let max_value = if max_value > 0.0 { max_value } else { 1.0 };
+
+        writeln!(
+            svg,
+            r#"<text x="{}" y="{}" font-size="14">{}</text>"#,
+            MARGIN,
+            top - 10.0,
+            title
+        )?;
+        writeln!(
+            svg,
+            r#"<rect x="{}" y="{}" width="{}" height="{}" style="fill:none;stroke:rgb(0,0,0);stroke-width:1"/>"#,
+            MARGIN, top, PLOT_W, PLOT_H
+        )?;
+        writeln!(
+            svg,
+            r#"<text x="{}" y="{}" font-size="10" text-anchor="end">{:.2}</text>"#,
+            MARGIN - 4.0,
+            top + 10.0,
+            max_value
+        )?;
+        writeln!(
+            svg,
+            r#"<text x="{}" y="{}" font-size="10" text-anchor="end">{:.0}s</text>"#,
+            MARGIN + PLOT_W,
+            top + PLOT_H + 12.0,
+            max_time
+        )?;
+
+        for (run_idx, (_, samples)) in runs.iter().enumerate() {
+            let (r, g, b) = COLORS[run_idx % COLORS.len()];
+            let mut points = String::new();
+            for sample in samples {
+                let value = metric(sample);
+                if !value.is_finite() {
+                    continue;
+                }
+                let x = MARGIN + (sample.time_secs / max_time) as f32 * PLOT_W;
+                let y = top + PLOT_H - (value / max_value) as f32 * PLOT_H;
+                write!(points, "{x:.1},{y:.1} ")?;
+            }
+            writeln!(
+                svg,
+                r#"<polyline points="{}" style="{}"/>"#,
+                points.trim_end(),
+                Style {
+                    fill: Fill::None,
+                    stroke: Stroke::Color(rgb(r, g, b), 1.5),
+                    opacity: 1.0,
+                    stroke_opacity: 1.0,
+                }
+            )?;
+        }
+    }
+
+    // Legend
+    for (run_idx, (name, _)) in runs.iter().enumerate() {
+        let (r, g, b) = COLORS[run_idx % COLORS.len()];
+        let y = 20.0 + run_idx as f32 * 14.0;
+        writeln!(
+            svg,
+            r#"<text x="{}" y="{}" font-size="12" fill="rgb({},{},{})">{}</text>"#,
+            PLOT_W + MARGIN - 100.0,
+            y,
+            r,
+            g,
+            b,
+            name
+        )?;
+    }
+
+    writeln!(svg, "{EndSvg}")?;
+    output.write_all(svg.as_bytes())?;
+    Ok(())
+}
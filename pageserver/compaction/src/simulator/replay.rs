@@ -0,0 +1,422 @@
+//! Replay a recorded workload against a real layer map.
+//!
+//! The synthetic workloads of the simulator start from an empty timeline. To tune the
+//! compaction settings for a particular tenant, it is more useful to start from the
+//! layers the timeline actually has, and to ingest WAL at the rate it actually sees.
+//!
+//! The layer map is read in a plain text format with one `<layer file name> <file size>`
+//! line per layer, as printed by `pagectl index-part list-layers`, or directly from a
+//! local timeline directory.
+//!
+//! The write-rate trace has one `<seconds> <bytes>` line per interval, with the number
+//! of WAL bytes ingested in the interval ending at that many seconds since the start of
+//! the trace. Empty lines and lines starting with `#` are ignored.
+//!
+//! The simulator does not know the real keys that were written, so each layer is
+//! filled with synthetic records spread over its key and LSN range, and the traced WAL
+//! is written to keys chosen in proportion to how many delta bytes the loaded layer map
+//! has for them. Real keys are mapped to simulator keys so that one simulator key
+//! corresponds to roughly one page, estimated from the image layer sizes.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::ops::Range;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use anyhow::{Context, bail};
+use pageserver_api::key::Key as RealKey;
+use rand::Rng;
+use rand::distr::Distribution;
+use rand::distr::weighted::WeightedIndex;
+use utils::lsn::Lsn;
+
+use super::draw::{LayerTraceEvent, LayerTraceFile, LayerTraceOp};
+use super::{Key, MockDeltaLayer, MockImageLayer, MockLayer, MockRecord, MockTimeline};
+use crate::helpers::{PAGE_SZ, union_to_keyspace};
+use crate::interface::CompactionLayer;
+
+/// A layer from a real layer map.
+#[derive(Clone, Debug)]
+pub struct LayerFileDesc {
+    pub key_range: Range<RealKey>,
+    pub lsn_range: Range<Lsn>,
+    pub is_delta: bool,
+    pub file_size: u64,
+}
+
+impl LayerFileDesc {
+    /// Parse a layer file name, with or without the generation suffix.
+    pub fn from_file_name(name: &str, file_size: u64) -> anyhow::Result<Self> {
+        let (keys, lsns) = name
+            .split_once("__")
+            .with_context(|| format!("invalid layer file name {name}"))?;
+        let (key_start, key_end) = keys
+            .split_once('-')
+            .with_context(|| format!("invalid key range in layer file name {name}"))?;
+
+        // Remove the temporary file extension and the `-v1-00000001` version and
+        // generation suffixes.
+        let lsns = lsns.split('.').next().unwrap_or_default();
+        let mut lsns: Vec<&str> = lsns.split('-').collect();
+        if lsns.len() > 1 && lsns.last().is_some_and(|s| s.len() == 8) {
+            lsns.pop();
+        }
+        if lsns.len() > 1 && lsns.last().is_some_and(|s| s.starts_with('v')) {
+            lsns.pop();
+        }
+
+        let key_range = RealKey::from_hex(key_start)?..RealKey::from_hex(key_end)?;
+        let (lsn_range, is_delta) = match lsns[..] {
+            [lsn] => {
+                let lsn = Lsn::from_hex(lsn)?;
+                (lsn..lsn, false)
+            }
+            [start, end] => (Lsn::from_hex(start)?..Lsn::from_hex(end)?, true),
+            _ => bail!("invalid LSN range in layer file name {name}"),
+        };
+        Ok(Self {
+            key_range,
+            lsn_range,
+            is_delta,
+            file_size,
+        })
+    }
+}
+
+/// Load a layer map from a local timeline directory, or from a file with one
+/// `<layer file name> <file size>` line per layer.
+pub fn load_layer_map(path: &Path) -> anyhow::Result<Vec<LayerFileDesc>> {
+    let mut layers = Vec::new();
+    if path.is_dir() {
+        for entry in std::fs::read_dir(path)? {
+            let entry = entry?;
+            let name = entry.file_name();
+            let Some(name) = name.to_str() else {
+                continue;
+            };
+            if !name.contains("__") || name.ends_with("___temp") {
+                continue;
+            }
+            layers.push(LayerFileDesc::from_file_name(
+                name,
+                entry.metadata()?.len(),
+            )?);
+        }
+    } else {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("read layer map {}", path.display()))?;
+        for line in contents.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (name, size) = line
+                .split_once(char::is_whitespace)
+                .with_context(|| format!("expected `<layer file name> <file size>`: {line}"))?;
+            let size = size
+                .trim()
+                .parse()
+                .with_context(|| format!("invalid file size: {line}"))?;
+            layers.push(LayerFileDesc::from_file_name(name, size)?);
+        }
+    }
+    if layers.is_empty() {
+        bail!("no layers found in {}", path.display());
+    }
+    Ok(layers)
+}
+
+/// One interval of a write-rate trace.
+#[derive(Clone, Copy, Debug)]
+pub struct TraceInterval {
+    /// End of the interval, in seconds since the start of the trace
+    pub time_secs: f64,
+    /// WAL bytes ingested during the interval
+    pub wal_bytes: u64,
+}
+
+pub fn load_write_trace(path: &Path) -> anyhow::Result<Vec<TraceInterval>> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("read write trace {}", path.display()))?;
+    let mut trace: Vec<TraceInterval> = Vec::new();
+    for line in contents.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut fields = line.split_whitespace();
+        let (Some(time_secs), Some(wal_bytes), None) =
+            (fields.next(), fields.next(), fields.next())
+        else {
+            bail!("expected `<seconds> <bytes>`: {line}");
+        };
+        let interval = TraceInterval {
+            time_secs: time_secs
+                .parse()
+                .with_context(|| format!("invalid time: {line}"))?,
+            wal_bytes: wal_bytes
+                .parse()
+                .with_context(|| format!("invalid byte count: {line}"))?,
+        };
+        if trace
+            .last()
+            .is_some_and(|prev| prev.time_secs > interval.time_secs)
+        {
+            bail!("write trace is not sorted by time: {line}");
+        }
+        trace.push(interval);
+    }
+    Ok(trace)
+}
+
+/// Maps real keys to simulator keys.
+///
+/// Only the layer boundaries need to be mapped. The distance between two adjacent
+/// boundaries is the estimated number of pages in between, so that image layers
+/// created by the simulator get realistic sizes.
+struct KeyMapper {
+    boundaries: BTreeMap<RealKey, Key>,
+    keyspace_end: Key,
+}
+
+impl KeyMapper {
+    fn new(layers: &[LayerFileDesc]) -> Self {
+        let bounds: Vec<RealKey> = layers
+            .iter()
+            .flat_map(|l| [l.key_range.start, l.key_range.end])
+            .chain([RealKey::MIN, RealKey::MAX])
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect();
+        let index = |key: &RealKey| bounds.binary_search(key).unwrap();
+
+        // Estimate the number of pages in every interval between two boundaries from the
+        // image layers. Fall back to the delta layers for intervals that have no images.
+        let mut image_pages = vec![0u64; bounds.len() - 1];
+        let mut delta_pages = vec![0u64; bounds.len() - 1];
+        for l in layers {
+            if l.key_range == (RealKey::MIN..RealKey::MAX) {
+                continue;
+            }
+            let (start, end) = (index(&l.key_range.start), index(&l.key_range.end));
+            let pages = (l.file_size / PAGE_SZ) / (end - start) as u64;
+            let estimate = if l.is_delta {
+                &mut delta_pages
+            } else {
+                &mut image_pages
+            };
+            for e in &mut estimate[start..end] {
+                *e = std::cmp::max(*e, pages);
+            }
+        }
+
+        let mut boundaries = BTreeMap::new();
+        let mut pos: Key = 0;
+        for (i, key) in bounds.iter().enumerate() {
+            if *key == RealKey::MAX {
+                boundaries.insert(*key, Key::MAX);
+                break;
+            }
+            boundaries.insert(*key, pos);
+            let pages = if image_pages[i] > 0 {
+                image_pages[i]
+            } else {
+                delta_pages[i]
+            };
+            pos += std::cmp::max(pages, 1);
+        }
+        Self {
+            boundaries,
+            keyspace_end: pos,
+        }
+    }
+
+    fn map(&self, key: &RealKey) -> Key {
+        self.boundaries[key]
+    }
+
+    fn map_range(&self, range: &Range<RealKey>) -> Range<Key> {
+        self.map(&range.start)..self.map(&range.end)
+    }
+}
+
+/// Statistics of a simulation run at a point in time.
+#[derive(Clone, Debug)]
+pub struct MetricsSample {
+    pub time_secs: f64,
+    pub wal_ingested: u64,
+    pub bytes_written: u64,
+    pub live_bytes: u64,
+    /// Average number of layers visited to reconstruct a page at the latest LSN
+    pub read_amp: f64,
+}
+
+impl MetricsSample {
+    pub fn write_amp(&self) -> f64 {
+        self.bytes_written as f64 / self.wal_ingested as f64
+    }
+}
+
+/// Number of keys sampled to estimate the read amplification.
+const READ_AMP_SAMPLES: u64 = 128;
+
+pub struct Replay {
+    layers: Vec<LayerFileDesc>,
+    key_mapper: KeyMapper,
+    /// Key ranges the traced writes go to, and their weights
+    write_ranges: Vec<Range<Key>>,
+    write_weights: WeightedIndex<u64>,
+}
+
+impl Replay {
+    pub fn new(layers: Vec<LayerFileDesc>) -> anyhow::Result<Self> {
+        let key_mapper = KeyMapper::new(&layers);
+
+        // Direct the writes to where the deltas are. The L0 layers span the whole
+        // keyspace and don't tell anything about it.
+        let mut write_ranges = Vec::new();
+        let mut weights = Vec::new();
+        for l in layers.iter().filter(|l| l.is_delta) {
+            let range = key_mapper.map_range(&l.key_range);
+            if range.end == Key::MAX {
+                continue;
+            }
+            write_ranges.push(range);
+            weights.push(std::cmp::max(l.file_size, 1));
+        }
+        if write_ranges.is_empty() {
+            write_ranges.push(0..key_mapper.keyspace_end);
+            weights.push(1);
+        }
+
+        Ok(Self {
+            layers,
+            key_mapper,
+            write_ranges,
+            write_weights: WeightedIndex::new(weights)?,
+        })
+    }
+
+    /// Create a timeline with the loaded layers. The delta layers are filled with
+    /// synthetic records of `record_len` bytes.
+    pub fn new_timeline(&self, record_len: u64) -> MockTimeline {
+        let mut tline = MockTimeline::new();
+        let mut rng = rand::rng();
+
+        let keyspace_end = self.key_mapper.keyspace_end;
+        union_to_keyspace(&mut tline.keyspace, vec![0..keyspace_end]);
+
+        let mut end_lsn = Lsn(0);
+        for l in self.layers.iter() {
+            let key_range = self.key_mapper.map_range(&l.key_range);
+            end_lsn = std::cmp::max(end_lsn, l.lsn_range.end);
+
+            let layer = if l.is_delta {
+                let key_end = std::cmp::min(key_range.end, keyspace_end);
+                let num_records = std::cmp::max(l.file_size / record_len, 1);
+                let mut records: Vec<MockRecord> = (0..num_records)
+                    .map(|_| MockRecord {
+                        lsn: Lsn(rng.random_range(l.lsn_range.start.0..l.lsn_range.end.0)),
+                        key: rng.random_range(key_range.start..key_end),
+                        len: record_len,
+                    })
+                    .collect();
+                records.sort_by_key(|rec| (rec.key, rec.lsn));
+                MockLayer::Delta(Arc::new(MockDeltaLayer {
+                    key_range,
+                    lsn_range: l.lsn_range.clone(),
+                    file_size: l.file_size,
+                    deleted: Mutex::new(false),
+                    records,
+                }))
+            } else {
+                MockLayer::Image(Arc::new(MockImageLayer {
+                    key_range,
+                    lsn_range: l.lsn_range.clone(),
+                    file_size: l.file_size,
+                    deleted: Mutex::new(false),
+                }))
+            };
+
+            tline.history.push(LayerTraceEvent {
+                time_rel: tline.time,
+                op: LayerTraceOp::Load,
+                file: LayerTraceFile {
+                    filename: layer.short_id(),
+                    key_range: layer.key_range().clone(),
+                    lsn_range: layer.lsn_range().clone(),
+                },
+            });
+            tline.live_layers.push(layer);
+        }
+
+        tline.start_lsn = end_lsn;
+        tline.end_lsn = end_lsn;
+        tline.last_flush_lsn = end_lsn;
+        tline
+    }
+
+    /// Ingest the WAL of one trace interval, compacting as needed, and return the
+    /// statistics at the end of the interval.
+    pub async fn ingest_interval(
+        &self,
+        tline: &mut MockTimeline,
+        interval: &TraceInterval,
+        record_len: u64,
+    ) -> anyhow::Result<MetricsSample> {
+        let mut rng = rand::rng();
+        let mut remaining = interval.wal_bytes;
+        while remaining > 0 {
+            let len = std::cmp::min(remaining, record_len);
+            let range = &self.write_ranges[self.write_weights.sample(&mut rng)];
+            tline.ingest_record(rng.random_range(range.clone()), len);
+            tline.wal_ingested += len;
+            tline.compact_if_needed().await?;
+            remaining -= len;
+        }
+        Ok(self.sample(tline, interval.time_secs))
+    }
+
+    pub fn sample(&self, tline: &MockTimeline, time_secs: f64) -> MetricsSample {
+        let live_layers: Vec<&MockLayer> = tline
+            .live_layers
+            .iter()
+            .filter(|l| !l.is_deleted())
+            .collect();
+
+        // Walk the layers of each sampled key from newest to oldest, until an image
+        // layer is found. This includes the in-memory layer, if there is one.
+        let keyspace_end = self.key_mapper.keyspace_end;
+        let step = std::cmp::max(keyspace_end / READ_AMP_SAMPLES, 1);
+        let mut num_keys = 0;
+        let mut layers_visited = 0;
+        for key in (0..keyspace_end).step_by(step as usize) {
+            let mut covering: Vec<&&MockLayer> = live_layers
+                .iter()
+                .filter(|l| l.key_range().contains(&key))
+                .collect();
+            covering.sort_by_key(|l| std::cmp::Reverse((l.lsn_range().end, !l.is_delta())));
+            let visited = covering
+                .iter()
+                .position(|l| !l.is_delta())
+                .map(|pos| pos + 1)
+                .unwrap_or(covering.len());
+            layers_visited += visited + usize::from(!tline.records.is_empty());
+            num_keys += 1;
+        }
+
+        MetricsSample {
+            time_secs,
+            wal_ingested: tline.wal_ingested,
+            bytes_written: tline.bytes_written,
+            live_bytes: live_layers.iter().map(|l| l.file_size()).sum(),
+            read_amp: layers_visited as f64 / std::cmp::max(num_keys, 1) as f64,
+        }
+    }
+}
+
+/// Draw the metrics of several simulation runs as an SVG.
+pub fn draw_metrics<W: std::io::Write>(
+    runs: &[(String, Vec<MetricsSample>)],
+    output: W,
+) -> anyhow::Result<()> {
+    super::draw::draw_metrics(runs, output)
+}
@@ -1,6 +1,7 @@
 use once_cell::sync::OnceCell;
 use pageserver_compaction::interface::CompactionLayer;
-use pageserver_compaction::simulator::MockTimeline;
+use pageserver_compaction::simulator::replay::{LayerFileDesc, Replay, TraceInterval};
+use pageserver_compaction::simulator::{CompactionPolicy, MockTimeline};
 use utils::logging;
 
 static LOG_HANDLE: OnceCell<()> = OnceCell::new();
@@ -68,3 +69,78 @@ async fn test_simple_updates() {
         println!("layer {}: {}", l.short_id(), l.file_size());
     }
 }
+
+/// Replay some WAL on top of a small layer map with every policy, and check that the
+/// statistics make sense.
+#[tokio::test]
+async fn test_replay_layer_map() {
+    setup_logging();
+    let layers = [
+        (
+            "000000067F000000010000000A0000000000-000000067F000000010000000B0000000000__0000000001000000",
+            8 * 1024 * 1024,
+        ),
+        (
+            "000000067F000000010000000A0000000000-000000067F000000010000000A8000000000__0000000001000000-0000000001800000-v1-00000001",
+            2 * 1024 * 1024,
+        ),
+        (
+            "000000067F000000010000000A8000000000-000000067F000000010000000B0000000000__0000000001000000-0000000001800000-v1-00000001",
+            2 * 1024 * 1024,
+        ),
+        (
+            "000000000000000000000000000000000000-FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF__0000000001800000-0000000001900000",
+            1024 * 1024,
+        ),
+    ]
+    .into_iter()
+    .map(|(name, size)| LayerFileDesc::from_file_name(name, size).unwrap())
+    .collect::<Vec<_>>();
+    let trace: Vec<TraceInterval> = (1..=20)
+        .map(|i| TraceInterval {
+            time_secs: i as f64,
+            wal_bytes: 1024 * 1024,
+        })
+        .collect();
+
+    let replay = Replay::new(layers).unwrap();
+    for policy in [
+        CompactionPolicy::Legacy,
+        CompactionPolicy::Tiered,
+        CompactionPolicy::GcCompaction,
+    ] {
+        let mut executor = replay.new_timeline(8192);
+        executor.policy = policy;
+        executor.target_file_size = 1024 * 1024;
+        executor.checkpoint_distance = 1024 * 1024;
+        executor.compaction_threshold = 4;
+        executor.gc_horizon = Some(4 * 1024 * 1024);
+
+        let initial = replay.sample(&executor, 0.0);
+        assert_eq!(initial.live_bytes, 13 * 1024 * 1024);
+        // L0, one of the L1 deltas and the image layer. The keys before the first
+        // image layer only have the L0.
+        assert!(
+            (2.9..=3.0).contains(&initial.read_amp),
+            "{}",
+            initial.read_amp
+        );
+
+        let mut last = initial;
+        for interval in trace.iter() {
+            last = replay
+                .ingest_interval(&mut executor, interval, 8192)
+                .await
+                .unwrap();
+        }
+        println!(
+            "{policy}: write amp {:.2}, read amp {:.2}, live {}",
+            last.write_amp(),
+            last.read_amp,
+            last.live_bytes
+        );
+        assert_eq!(last.wal_ingested, 20 * 1024 * 1024);
+        assert!(last.write_amp() >= 1.0, "{policy}: {}", last.write_amp());
+        assert!(last.read_amp >= 1.0, "{policy}: {}", last.read_amp);
+    }
+}
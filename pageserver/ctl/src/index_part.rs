@@ -40,6 +40,12 @@ pub(crate) enum IndexPartCmd {
         #[arg(long)]
         path: Utf8PathBuf,
     },
+    /// Print a `<layer file name> <file size>` line for every layer, e.g. as input for
+    /// `compaction-simulator replay`.
+    ListLayers {
+        #[arg(long)]
+        path: Utf8PathBuf,
+    },
 }
 
 fn create_layer_map_from_index_part(
@@ -177,6 +183,15 @@ async fn list_visible_layers(path: &Utf8PathBuf) -> anyhow::Result<()> {
     Ok(())
 }
 
+async fn list_layers(path: &Utf8PathBuf) -> anyhow::Result<()> {
+    let bytes = tokio::fs::read(path).await.context("read file")?;
+    let index_part = IndexPart::from_json_bytes(&bytes).context("deserialize")?;
+    for (name, metadata) in index_part.layer_metadata.iter() {
+        println!("{name} {}", metadata.file_size);
+    }
+    Ok(())
+}
+
 pub(crate) async fn main(cmd: &IndexPartCmd) -> anyhow::Result<()> {
     match cmd {
         IndexPartCmd::Dump { path } => {
@@ -194,5 +209,6 @@ pub(crate) async fn main(cmd: &IndexPartCmd) -> anyhow::Result<()> {
             lsn,
         } => search_layers(tenant_id, timeline_id, path, key, lsn).await,
         IndexPartCmd::ListVisibleLayers { path } => list_visible_layers(path).await,
+        IndexPartCmd::ListLayers { path } => list_layers(path).await,
     }
 }
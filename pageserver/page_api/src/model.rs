@@ -182,6 +182,7 @@ fn from(request: GetBaseBackupRequest) -> Self {
 pub enum BaseBackupCompression {
     None,
     Gzip,
+    Zstd,
 }
 
 impl TryFrom<proto::BaseBackupCompression> for BaseBackupCompression {
@@ -192,6 +193,7 @@ fn try_from(pb: proto::BaseBackupCompression) -> Result<Self, Self::Error> {
             proto::BaseBackupCompression::Unknown => Err(ProtocolError::invalid("compression", pb)),
             proto::BaseBackupCompression::None => Ok(Self::None),
             proto::BaseBackupCompression::Gzip => Ok(Self::Gzip),
+            proto::BaseBackupCompression::Zstd => Ok(Self::Zstd),
         }
     }
 }
@@ -211,6 +213,7 @@ fn from(compression: BaseBackupCompression) -> Self {
         match compression {
             BaseBackupCompression::None => Self::None,
             BaseBackupCompression::Gzip => Self::Gzip,
+            BaseBackupCompression::Zstd => Self::Zstd,
         }
     }
 }
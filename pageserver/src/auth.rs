@@ -21,7 +21,8 @@ pub fn check_permission(claims: &Claims, tenant_id: Option<TenantId>) -> Result<
             | Scope::Infra
             | Scope::Scrubber
             | Scope::ControllerPeer
-            | Scope::TenantEndpoint,
+            | Scope::TenantEndpoint
+            | Scope::TenantReadOnly,
             _,
         ) => Err(AuthError(
             format!(
@@ -15,10 +15,11 @@
 use std::time::{Instant, SystemTime};
 
 use anyhow::{Context, anyhow};
-use async_compression::tokio::write::GzipEncoder;
+use async_compression::tokio::write::{GzipEncoder, ZstdEncoder};
 use bytes::{BufMut, Bytes, BytesMut};
 use fail::fail_point;
 use pageserver_api::key::{Key, rel_block_to_key};
+use pageserver_api::models::BasebackupCompression;
 use pageserver_api::reltag::{RelTag, SlruKind};
 use postgres_ffi::pg_constants::{PG_HBA, PGDATA_SPECIAL_FILES};
 use postgres_ffi::{
@@ -106,7 +107,7 @@ pub async fn send_basebackup_tarball<'a, W>(
     prev_lsn: Option<Lsn>,
     full_backup: bool,
     replica: bool,
-    gzip_level: Option<async_compression::Level>,
+    compression: Option<(BasebackupCompression, async_compression::Level)>,
     ctx: &'a RequestContext,
 ) -> Result<(), BasebackupError>
 where
@@ -159,7 +160,7 @@ pub async fn send_basebackup_tarball<'a, W>(
 
     info!(
         "taking basebackup lsn={lsn}, prev_lsn={prev_record_lsn} \
-        (full_backup={full_backup}, replica={replica}, gzip={gzip_level:?})",
+        (full_backup={full_backup}, replica={replica}, compression={compression:?})",
     );
     let span = info_span!("send_tarball", backup_lsn=%lsn);
 
@@ -171,44 +172,120 @@ pub async fn send_basebackup_tarball<'a, W>(
             .map_err(|_| BasebackupError::Shutdown)?,
     );
 
-    if let Some(gzip_level) = gzip_level {
-        let mut encoder = GzipEncoder::with_quality(write, gzip_level);
-        Basebackup {
-            ar: Builder::new_non_terminated(&mut encoder),
-            timeline,
-            lsn,
-            prev_record_lsn,
-            full_backup,
-            replica,
-            ctx,
-            io_concurrency,
+    match compression {
+        Some((BasebackupCompression::Gzip, level)) => {
+            let mut encoder = GzipEncoder::with_quality(write, level);
+            Basebackup {
+                ar: Builder::new_non_terminated(&mut encoder),
+                timeline,
+                lsn,
+                prev_record_lsn,
+                full_backup,
+                skip_rel_data: false,
+                replica,
+                ctx,
+                io_concurrency,
+            }
+            .send_tarball()
+            .instrument(span)
+            .await?;
+            encoder
+                .shutdown()
+                .await
+                .map_err(|err| BasebackupError::Client(err, "gzip"))?;
         }
-        .send_tarball()
-        .instrument(span)
-        .await?;
-        encoder
-            .shutdown()
-            .await
-            .map_err(|err| BasebackupError::Client(err, "gzip"))?;
-    } else {
-        Basebackup {
-            ar: Builder::new_non_terminated(write),
-            timeline,
-            lsn,
-            prev_record_lsn,
-            full_backup,
-            replica,
-            ctx,
-            io_concurrency,
+        Some((BasebackupCompression::Zstd, level)) => {
+            let mut encoder = ZstdEncoder::with_quality(write, level);
+            Basebackup {
+                ar: Builder::new_non_terminated(&mut encoder),
+                timeline,
+                lsn,
+                prev_record_lsn,
+                full_backup,
+                skip_rel_data: false,
+                replica,
+                ctx,
+                io_concurrency,
+            }
+            .send_tarball()
+            .instrument(span)
+            .await?;
+            encoder
+                .shutdown()
+                .await
+                .map_err(|err| BasebackupError::Client(err, "zstd"))?;
+        }
+        None => {
+            Basebackup {
+                ar: Builder::new_non_terminated(write),
+                timeline,
+                lsn,
+                prev_record_lsn,
+                full_backup,
+                skip_rel_data: false,
+                replica,
+                ctx,
+                io_concurrency,
+            }
+            .send_tarball()
+            .instrument(span)
+            .await?;
         }
-        .send_tarball()
-        .instrument(span)
-        .await?;
     }
 
     Ok(())
 }
 
+/// Create the non-relational part of a full basebackup at `lsn`, for
+/// [`crate::tenant::timeline::export_pgdata`].
+///
+/// This is a full backup, except that relation files other than init forks are left out: the
+/// export writes those itself, so that it can split them across many objects and resume after
+/// a restart.
+pub(crate) async fn send_export_base_tarball<W>(
+    write: &mut W,
+    timeline: &Timeline,
+    lsn: Lsn,
+    ctx: &RequestContext,
+) -> Result<(), BasebackupError>
+where
+    W: AsyncWrite + Send + Sync + Unpin,
+{
+    // As in send_basebackup_tarball, we only know the previous record at the end of the timeline.
+    let end_of_timeline = timeline.get_last_record_rlsn();
+    let prev_record_lsn = if lsn == end_of_timeline.last {
+        end_of_timeline.prev
+    } else {
+        Lsn(0)
+    };
+
+    info!("taking export basebackup lsn={lsn}, prev_lsn={prev_record_lsn}");
+    let span = info_span!("send_tarball", backup_lsn=%lsn);
+
+    let io_concurrency = IoConcurrency::spawn_from_conf(
+        timeline.conf.get_vectored_concurrent_io,
+        timeline
+            .gate
+            .enter()
+            .map_err(|_| BasebackupError::Shutdown)?,
+    );
+
+    Basebackup {
+        ar: Builder::new_non_terminated(write),
+        timeline,
+        lsn,
+        prev_record_lsn,
+        full_backup: true,
+        skip_rel_data: true,
+        replica: false,
+        ctx,
+        io_concurrency,
+    }
+    .send_tarball()
+    .instrument(span)
+    .await
+}
+
 /// This is short-living object only for the time of tarball creation,
 /// created mostly to avoid passing a lot of parameters between various functions
 /// used for constructing tarball.
@@ -221,6 +298,8 @@ struct Basebackup<'a, W>
     lsn: Lsn,
     prev_record_lsn: Lsn,
     full_backup: bool,
+    /// Leave out relation files even though `full_backup` is set, see [`send_export_base_tarball`].
+    skip_rel_data: bool,
     replica: bool,
     ctx: &'a RequestContext,
     io_concurrency: IoConcurrency,
@@ -450,7 +529,7 @@ async fn send_tarball(mut self) -> Result<(), BasebackupError> {
                     continue;
                 }
 
-                if self.full_backup {
+                if self.full_backup && !self.skip_rel_data {
                     if rel.forknum == MAIN_FORKNUM && rels.contains(&rel.with_forknum(INIT_FORKNUM))
                     {
                         // skip this, will include it when we reach the init fork
@@ -832,7 +911,7 @@ async fn add_pgcontrol_file(
 //
 // Create new tarball entry header
 //
-fn new_tar_header(path: &str, size: u64) -> anyhow::Result<Header> {
+pub(crate) fn new_tar_header(path: &str, size: u64) -> anyhow::Result<Header> {
     let mut header = Header::new_gnu();
     header.set_size(size);
     header.set_path(path)?;
@@ -3,7 +3,10 @@
 use anyhow::Context;
 use camino::{Utf8Path, Utf8PathBuf};
 use metrics::core::{AtomicU64, GenericCounter};
-use pageserver_api::{config::BasebackupCacheConfig, models::TenantState};
+use pageserver_api::{
+    config::BasebackupCacheConfig,
+    models::{BasebackupCompression, TenantState},
+};
 use tokio::{
     io::{AsyncWriteExt, BufWriter},
     sync::mpsc::{Receiver, Sender, error::TrySendError},
@@ -51,7 +54,8 @@ struct CacheEntry {
 /// The main purpose of this cache is to speed up the startup process of compute nodes
 /// after scaling to zero.
 /// Thus, the basebackup is stored only for the latest LSN of the timeline and with
-/// fixed set of parameters (gzip=true, full_backup=false, replica=false, prev_lsn=none).
+/// fixed set of parameters (full_backup=false, replica=false, prev_lsn=none), compressed as
+/// configured in [`BasebackupCacheConfig::compression`].
 ///
 /// The cache receives prepare requests through the `BasebackupPrepareSender` channel,
 /// generates a basebackup from the timeline in the background, and stores it on disk.
@@ -219,18 +223,42 @@ pub fn is_enabled(&self) -> bool {
         self.config.is_some()
     }
 
+    /// The compression of cached basebackups, or None if the cache is disabled.
+    pub fn compression(&self) -> Option<BasebackupCompression> {
+        self.config.as_ref().map(|c| c.compression)
+    }
+
     // Private methods.
 
-    fn entry_filename(tenant_id: TenantId, timeline_id: TimelineId, lsn: Lsn) -> String {
+    fn entry_extension(compression: BasebackupCompression) -> &'static str {
+        match compression {
+            BasebackupCompression::Gzip => ".tar.gz",
+            BasebackupCompression::Zstd => ".tar.zst",
+        }
+    }
+
+    fn entry_filename(
+        tenant_id: TenantId,
+        timeline_id: TimelineId,
+        lsn: Lsn,
+        compression: BasebackupCompression,
+    ) -> String {
         // The default format for LSN is 0/ABCDEF.
         // The backslash is not filename friendly, so serialize it as plain hex.
         let lsn = lsn.0;
-        format!("basebackup_{tenant_id}_{timeline_id}_{lsn:016X}.tar.gz")
+        let extension = Self::entry_extension(compression);
+        format!("basebackup_{tenant_id}_{timeline_id}_{lsn:016X}{extension}")
     }
 
     fn entry_path(&self, tenant_id: TenantId, timeline_id: TimelineId, lsn: Lsn) -> Utf8PathBuf {
-        self.data_dir
-            .join(Self::entry_filename(tenant_id, timeline_id, lsn))
+        // Entries are only accessed while the cache is enabled.
+        let compression = self.compression().unwrap_or(BasebackupCompression::Gzip);
+        self.data_dir.join(Self::entry_filename(
+            tenant_id,
+            timeline_id,
+            lsn,
+            compression,
+        ))
     }
 }
 
@@ -275,16 +303,26 @@ fn entry_tmp_path(
         timeline_id: TimelineId,
         lsn: Lsn,
     ) -> Utf8PathBuf {
-        self.tmp_dir()
-            .join(BasebackupCache::entry_filename(tenant_id, timeline_id, lsn))
+        self.tmp_dir().join(BasebackupCache::entry_filename(
+            tenant_id,
+            timeline_id,
+            lsn,
+            self.config.compression,
+        ))
     }
 
-    fn parse_entry_filename(filename: &str) -> Option<(TenantId, TimelineId, Lsn)> {
-        let parts: Vec<&str> = filename
-            .strip_prefix("basebackup_")?
-            .strip_suffix(".tar.gz")?
-            .split('_')
-            .collect();
+    fn parse_entry_filename(
+        filename: &str,
+    ) -> Option<(TenantId, TimelineId, Lsn, BasebackupCompression)> {
+        let filename = filename.strip_prefix("basebackup_")?;
+        let (stem, compression) = [BasebackupCompression::Gzip, BasebackupCompression::Zstd]
+            .into_iter()
+            .find_map(|c| {
+                filename
+                    .strip_suffix(BasebackupCache::entry_extension(c))
+                    .map(|stem| (stem, c))
+            })?;
+        let parts: Vec<&str> = stem.split('_').collect();
         if parts.len() != 3 {
             return None;
         }
@@ -292,7 +330,7 @@ fn parse_entry_filename(filename: &str) -> Option<(TenantId, TimelineId, Lsn)> {
         let timeline_id = parts[1].parse::<TimelineId>().ok()?;
         let lsn = Lsn(u64::from_str_radix(parts[2], 16).ok()?);
 
-        Some((tenant_id, timeline_id, lsn))
+        Some((tenant_id, timeline_id, lsn, compression))
     }
 
     // Recreate the tmp directory to clear all files in it.
@@ -366,6 +404,23 @@ async fn on_startup(&mut self) -> anyhow::Result<()> {
                 continue;
             }
 
+            let parsed = Self::parse_entry_filename(filename.to_string_lossy().as_ref());
+
+            // Entries with a different compression were written before the configured compression
+            // was changed, and will never be served. Remove them.
+            if let Some((_, _, _, compression)) = parsed {
+                if compression != self.config.compression {
+                    match tokio::fs::remove_file(dir_entry.path()).await {
+                        Ok(()) => continue,
+                        Err(e) => tracing::warn!(
+                            "Failed to remove basebackup cache file {:?}: {:#}",
+                            filename,
+                            e
+                        ),
+                    }
+                }
+            }
+
             let size_bytes = dir_entry
                 .metadata()
                 .await
@@ -380,11 +435,14 @@ async fn on_startup(&mut self) -> anyhow::Result<()> {
             self.total_size_bytes += size_bytes;
             BASEBACKUP_CACHE_SIZE.set(self.total_size_bytes);
 
-            let parsed = Self::parse_entry_filename(filename.to_string_lossy().as_ref());
-            let Some((tenant_id, timeline_id, lsn)) = parsed else {
+            let Some((tenant_id, timeline_id, lsn, compression)) = parsed else {
                 tracing::warn!("Invalid basebackup cache file name: {:?}", filename);
                 continue;
             };
+            if compression != self.config.compression {
+                // Failed to remove it above, keep counting it towards the limits.
+                continue;
+            }
 
             let cur_entry = CacheEntry { lsn, size_bytes };
 
@@ -650,6 +708,16 @@ async fn prepare_basebackup(
         Ok(())
     }
 
+    /// High compression levels because compression is not on the hot path of basebackup requests.
+    /// The decompression is almost not affected by the compression level, except for zstd levels
+    /// above 19, which need a large decompression window that small computes can't afford.
+    fn compression_level(compression: BasebackupCompression) -> async_compression::Level {
+        match compression {
+            BasebackupCompression::Gzip => async_compression::Level::Best,
+            BasebackupCompression::Zstd => async_compression::Level::Precise(19),
+        }
+    }
+
     /// Prepares a basebackup in a temporary file.
     /// Guarantees that the tmp file is fsynced before returning.
     async fn prepare_basebackup_tmp(
@@ -682,9 +750,10 @@ async fn prepare_basebackup_tmp(
             None,
             false,
             false,
-            // Level::Best because compression is not on the hot path of basebackup requests.
-            // The decompression is almost not affected by the compression level.
-            Some(async_compression::Level::Best),
+            Some((
+                self.config.compression,
+                Self::compression_level(self.config.compression),
+            )),
             &ctx,
         )
         .await?;
@@ -27,7 +27,7 @@
 use pageserver::task_mgr::{
     BACKGROUND_RUNTIME, COMPUTE_REQUEST_RUNTIME, MGMT_REQUEST_RUNTIME, WALRECEIVER_RUNTIME,
 };
-use pageserver::tenant::{TenantSharedResources, mgr, secondary};
+use pageserver::tenant::{TenantSharedResources, compaction_scheduler, mgr, secondary};
 use pageserver::{
     CancellableTask, ConsumptionMetricsTasks, HttpEndpointListener, HttpsEndpointListener,
     MetricsCollectionTask, http, page_cache, page_service, task_mgr, virtual_file,
@@ -704,6 +704,8 @@ fn start_pageserver(
         background_jobs_barrier.clone(),
     );
 
+    let compaction_scheduler = compaction_scheduler::launch_compaction_scheduler(conf);
+
     // Start up the service to handle HTTP mgmt API request. We created the
     // listener earlier already.
     let (http_endpoint_listener, https_endpoint_listener) = {
@@ -886,6 +888,7 @@ fn start_pageserver(
             metrics_collection_task,
             consumption_metrics_tasks,
             disk_usage_eviction_task,
+            compaction_scheduler,
             &tenant_manager,
             background_purges,
             deletion_queue.clone(),
@@ -260,6 +260,12 @@ pub struct PageServerConf {
     /// Controls whether to collect all metrics on each scrape or to return potentially stale
     /// results.
     pub force_metric_collection_on_scrape: bool,
+
+    /// Budget of the per-node compaction scheduler, see [`crate::tenant::compaction_scheduler`].
+    pub compaction_scheduler: pageserver_api::config::CompactionSchedulerConfig,
+
+    /// Optional slow local directory for demoted layers, see [`crate::tenant::storage_layer::tier`].
+    pub slow_layer_tier: Option<pageserver_api::config::SlowLayerTierConfig>,
 }
 
 /// Token for authentication to safekeepers
@@ -343,6 +349,21 @@ pub fn timeline_path(
             .join(timeline_id.to_string())
     }
 
+    /// Maps a path inside the workdir to its place in the slow layer tier, which mirrors the
+    /// workdir layout. Returns `None` if there is no slow tier or the path is outside the workdir.
+    pub fn slow_tier_path(&self, local_path: &Utf8Path) -> Option<Utf8PathBuf> {
+        let slow_tier = self.slow_layer_tier.as_ref()?;
+        let relative = local_path.strip_prefix(&self.workdir).ok()?;
+        Some(slow_tier.path.join(relative))
+    }
+
+    /// Inverse of [`Self::slow_tier_path`].
+    pub fn fast_tier_path(&self, slow_tier_path: &Utf8Path) -> Option<Utf8PathBuf> {
+        let slow_tier = self.slow_layer_tier.as_ref()?;
+        let relative = slow_tier_path.strip_prefix(&slow_tier.path).ok()?;
+        Some(self.workdir.join(relative))
+    }
+
     /// Turns storage remote path of a file into its local path.
     pub fn local_path(&self, remote_path: &RemotePath) -> Utf8PathBuf {
         remote_path.with_base(&self.workdir)
@@ -442,6 +463,8 @@ pub fn parse_and_validate(
             basebackup_cache_config,
             image_layer_generation_large_timeline_threshold,
             force_metric_collection_on_scrape,
+            compaction_scheduler,
+            slow_layer_tier,
         } = config_toml;
 
         let mut conf = PageServerConf {
@@ -502,6 +525,8 @@ pub fn parse_and_validate(
             basebackup_cache_config,
             image_layer_generation_large_timeline_threshold,
             force_metric_collection_on_scrape,
+            compaction_scheduler,
+            slow_layer_tier,
 
             // ------------------------------------------------------------
             // fields that require additional validation or custom handling
@@ -629,6 +654,21 @@ pub fn parse_and_validate(
             }
         };
 
+        if let Some(slow_layer_tier) = conf.slow_layer_tier.as_ref() {
+            ensure!(
+                slow_layer_tier.path.is_absolute(),
+                "slow layer tier path must be absolute: {}",
+                slow_layer_tier.path
+            );
+            ensure!(
+                !slow_layer_tier.path.starts_with(&conf.workdir)
+                    && !conf.workdir.starts_with(&slow_layer_tier.path),
+                "slow layer tier path {} must not overlap with the workdir {}",
+                slow_layer_tier.path,
+                conf.workdir
+            );
+        }
+
         Ok(conf)
     }
 
@@ -726,7 +766,7 @@ mod tests {
 
     use std::time::Duration;
 
-    use camino::Utf8PathBuf;
+    use camino::{Utf8Path, Utf8PathBuf};
     use pageserver_api::config::{DiskUsageEvictionTaskConfig, EvictionOrder};
     use rstest::rstest;
     use utils::{id::NodeId, serde_percent::Percent};
@@ -894,4 +934,34 @@ fn test_config_disk_usage_based_eviction_is_valid(
             disk_usage_based_eviction
         );
     }
+
+    #[test]
+    fn test_slow_layer_tier_paths() {
+        let input = r#"
+            control_plane_api = "http://localhost:6666"
+            slow_layer_tier = { path = "/slow", max_usage_pct = 90, min_avail_bytes = 0 }
+        "#;
+        let config_toml = toml_edit::de::from_str::<pageserver_api::config::ConfigToml>(input)
+            .expect("slow_layer_tier is valid");
+        let workdir = Utf8PathBuf::from("/nonexistent");
+        let config = PageServerConf::parse_and_validate(NodeId(0), config_toml, &workdir).unwrap();
+
+        let fast = Utf8PathBuf::from("/nonexistent/tenants/a/timelines/b/layer");
+        let slow = config.slow_tier_path(&fast).unwrap();
+        assert_eq!(slow, "/slow/tenants/a/timelines/b/layer");
+        assert_eq!(config.fast_tier_path(&slow).unwrap(), fast);
+        assert_eq!(
+            config.slow_tier_path(Utf8Path::new("/elsewhere/layer")),
+            None
+        );
+
+        let input = r#"
+            control_plane_api = "http://localhost:6666"
+            slow_layer_tier = { path = "/nonexistent/slow", max_usage_pct = 90, min_avail_bytes = 0 }
+        "#;
+        let config_toml = toml_edit::de::from_str::<pageserver_api::config::ConfigToml>(input)
+            .expect("slow_layer_tier is valid");
+        PageServerConf::parse_and_validate(NodeId(0), config_toml, &workdir)
+            .expect_err("slow tier inside the workdir is rejected");
+    }
 }
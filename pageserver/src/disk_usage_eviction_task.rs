@@ -36,6 +36,19 @@
 //! during page reconstruction.
 //! An alternative default for all tenants can be specified in the `tenant_config` section of the config.
 //! Lastly, each tenant can have an override in their respective tenant config (`min_resident_size_override`).
+//!
+//! Within each partition, the order of layers is determined by the configured [`EvictionOrder`].
+//! `RelativeAccessed` is the LRU order described above. `CostAware` instead evicts the layers
+//! that are cheapest to lose per byte freed, taking access frequency, size, visibility, the cost
+//! of downloading the layer again and the tenant's `eviction_weight_pct` into account.
+//!
+//! # Slow Tier
+//!
+//! With a [slow tier](crate::tenant::storage_layer::tier) configured, each iteration first
+//! relieves pressure on the slow tier by evicting demoted layers, using the slow tier's own
+//! thresholds. Pressure on the fast tier is then relieved by demoting the selected attached layers
+//! as long as the slow tier has room, evicting the rest. Finally, if the fast tier has room,
+//! demoted layers accessed often enough are promoted back to it, hottest first.
 
 // Implementation notes:
 // - The `#[allow(dead_code)]` above various structs are to suppress warnings about only the Debug impl
@@ -45,6 +58,7 @@
 use std::time::SystemTime;
 
 use anyhow::Context;
+use camino::Utf8Path;
 use pageserver_api::config::DiskUsageEvictionTaskConfig;
 use pageserver_api::shard::TenantShardId;
 use remote_storage::GenericRemoteStorage;
@@ -61,6 +75,7 @@
 use crate::tenant::mgr::TenantManager;
 use crate::tenant::remote_timeline_client::LayerFileMetadata;
 use crate::tenant::secondary::SecondaryTenant;
+use crate::tenant::storage_layer::tier::LayerTier;
 use crate::tenant::storage_layer::{
     AsLayerDesc, EvictionError, Layer, LayerName, LayerVisibilityHint,
 };
@@ -82,8 +97,18 @@ pub enum EvictionOrder {
         /// `relative_last_activity==0.0` ties.
         highest_layer_count_loses_first: bool,
     },
+    /// Order the layers by the estimated cost of evicting them, per byte freed: see
+    /// [`EvictionOrder::cost`].
+    CostAware {
+        /// Fixed cost of an on-demand download, in bytes that could have been downloaded in the
+        /// same time.
+        redownload_overhead_bytes: u64,
+    },
 }
 
+/// Covered layers are unlikely to be read, so they are much cheaper to evict than visible ones.
+const COVERED_LAYER_COST_FACTOR: f32 = 0.1;
+
 impl From<pageserver_api::config::EvictionOrder> for EvictionOrder {
     fn from(value: pageserver_api::config::EvictionOrder) -> Self {
         match value {
@@ -92,6 +117,11 @@ fn from(value: pageserver_api::config::EvictionOrder) -> Self {
             } => Self::RelativeAccessed {
                 highest_layer_count_loses_first,
             },
+            pageserver_api::config::EvictionOrder::CostAware {
+                redownload_overhead_bytes,
+            } => Self::CostAware {
+                redownload_overhead_bytes,
+            },
         }
     }
 }
@@ -104,6 +134,75 @@ fn sort(&self, candidates: &mut [(EvictionPartition, EvictionCandidate)]) {
             RelativeAccessed { .. } => candidates.sort_unstable_by_key(|(partition, candidate)| {
                 (*partition, candidate.relative_last_activity)
             }),
+            CostAware { .. } => candidates
+                .sort_unstable_by_key(|(partition, candidate)| (*partition, candidate.cost)),
+        }
+    }
+
+    /// Called to fill in the [`EvictionCandidate::cost`]. Only meaningful for
+    /// [`EvictionOrder::CostAware`].
+    fn cost(
+        &self,
+        candidate: &EvictionCandidate,
+        now: SystemTime,
+        weight_pct: u32,
+    ) -> finite_f32::FiniteF32 {
+        let idle_for = now
+            .duration_since(candidate.last_activity_ts)
+            .unwrap_or_default();
+        self.cost_of(
+            candidate.layer.get_file_size(),
+            idle_for,
+            candidate.access_rate,
+            &candidate.visibility,
+            weight_pct,
+        )
+        .unwrap_or_else(|val| {
+            tracing::warn!(
+                "calculated invalid eviction cost for {}: {val}",
+                candidate.layer
+            );
+            finite_f32::FiniteF32::ZERO
+        })
+    }
+
+    /// The expected cost of evicting a layer, per byte freed.
+    ///
+    /// The expected number of downloads after an eviction is estimated from how often and how
+    /// recently the layer was accessed, and each download costs the layer size plus a fixed
+    /// overhead. Small hot layers are therefore kept, while large cold ones go first.
+    fn cost_of(
+        &self,
+        file_size: u64,
+        idle_for: std::time::Duration,
+        access_rate: f32,
+        visibility: &LayerVisibilityHint,
+        weight_pct: u32,
+    ) -> Result<finite_f32::FiniteF32, f32> {
+        use EvictionOrder::*;
+
+        match self {
+            RelativeAccessed { .. } => Ok(finite_f32::FiniteF32::ZERO),
+            CostAware {
+                redownload_overhead_bytes,
+            } => {
+                // A layer accessed a minute ago is as hot as one accessed 60 times per hour.
+                let idle_hours = idle_for.as_secs_f32().max(60.0) / 3600.0;
+                let accesses_per_hour = access_rate + 1.0 / idle_hours;
+
+                let size = file_size.max(1) as f32;
+                let redownload_cost = (size + *redownload_overhead_bytes as f32) / size;
+
+                let visibility = match visibility {
+                    LayerVisibilityHint::Visible => 1.0,
+                    LayerVisibilityHint::Covered => COVERED_LAYER_COST_FACTOR,
+                };
+                let weight = weight_pct as f32 / 100.0;
+
+                finite_f32::FiniteF32::try_from(
+                    accesses_per_hour * redownload_cost * visibility * weight,
+                )
+            }
         }
     }
 
@@ -115,46 +214,54 @@ fn relative_last_activity(&self, total: usize, index: usize) -> finite_f32::Fini
         match self {
             RelativeAccessed {
                 highest_layer_count_loses_first,
-            } => {
-                // keeping the -1 or not decides if every tenant should lose their least recently accessed
-                // layer OR if this should happen in the order of having highest layer count:
-                let fudge = if *highest_layer_count_loses_first {
-                    // relative_last_activity vs. tenant layer count:
-                    // - 0.1..=1.0 (10 layers)
-                    // - 0.01..=1.0 (100 layers)
-                    // - 0.001..=1.0 (1000 layers)
-                    //
-                    // leading to evicting less of the smallest tenants.
-                    0
-                } else {
-                    // use full 0.0..=1.0 range, which means even the smallest tenants could always lose a
-                    // layer. the actual ordering is unspecified: for 10k tenants on a pageserver it could
-                    // be that less than 10k layer evictions is enough, so we would not need to evict from
-                    // all tenants.
-                    //
-                    // as the tenant ordering is now deterministic this could hit the same tenants
-                    // disproportionetly on multiple invocations. alternative could be to remember how many
-                    // layers did we evict last time from this tenant, and inject that as an additional
-                    // fudge here.
-                    1
-                };
+            } => Self::relative_last_activity_impl(*highest_layer_count_loses_first, total, index),
+            // Not used for ordering, but still useful in the logs.
+            CostAware { .. } => Self::relative_last_activity_impl(true, total, index),
+        }
+    }
 
-                let total = total.checked_sub(fudge).filter(|&x| x > 1).unwrap_or(1);
-                let divider = total as f32;
+    fn relative_last_activity_impl(
+        highest_layer_count_loses_first: bool,
+        total: usize,
+        index: usize,
+    ) -> finite_f32::FiniteF32 {
+        // keeping the -1 or not decides if every tenant should lose their least recently accessed
+        // layer OR if this should happen in the order of having highest layer count:
+        let fudge = if highest_layer_count_loses_first {
+            // relative_last_activity vs. tenant layer count:
+            // - 0.1..=1.0 (10 layers)
+            // - 0.01..=1.0 (100 layers)
+            // - 0.001..=1.0 (1000 layers)
+            //
+            // leading to evicting less of the smallest tenants.
+            0
+        } else {
+            // use full 0.0..=1.0 range, which means even the smallest tenants could always lose a
+            // layer. the actual ordering is unspecified: for 10k tenants on a pageserver it could
+            // be that less than 10k layer evictions is enough, so we would not need to evict from
+            // all tenants.
+            //
+            // as the tenant ordering is now deterministic this could hit the same tenants
+            // disproportionetly on multiple invocations. alternative could be to remember how many
+            // layers did we evict last time from this tenant, and inject that as an additional
+            // fudge here.
+            1
+        };
 
-                // most recently used is always (total - 0) / divider == 1.0
-                // least recently used depends on the fudge:
-                // -       (total - 1) - (total - 1) / total => 0 / total
-                // -             total - (total - 1) / total => 1 / total
-                let distance = (total - index) as f32;
+        let total = total.checked_sub(fudge).filter(|&x| x > 1).unwrap_or(1);
+        let divider = total as f32;
 
-                finite_f32::FiniteF32::try_from_normalized(distance / divider)
-                    .unwrap_or_else(|val| {
-                        tracing::warn!(%fudge, "calculated invalid relative_last_activity for i={index}, total={total}: {val}");
-                        finite_f32::FiniteF32::ZERO
-                    })
-            }
-        }
+        // most recently used is always (total - 0) / divider == 1.0
+        // least recently used depends on the fudge:
+        // -       (total - 1) - (total - 1) / total => 0 / total
+        // -             total - (total - 1) / total => 1 / total
+        let distance = (total - index) as f32;
+
+        finite_f32::FiniteF32::try_from_normalized(distance / divider)
+            .unwrap_or_else(|val| {
+                tracing::warn!(%fudge, "calculated invalid relative_last_activity for i={index}, total={total}: {val}");
+                finite_f32::FiniteF32::ZERO
+            })
     }
 }
 
@@ -258,6 +365,25 @@ pub trait Usage: Clone + Copy + std::fmt::Debug {
     fn add_available_bytes(&mut self, bytes: u64);
 }
 
+/// Which local tier an iteration relieves the pressure on.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum EvictionTarget {
+    /// The workdir. Up to `demote_bytes` of the selected attached layers are demoted to the slow
+    /// tier instead of being evicted.
+    FastTier { demote_bytes: u64 },
+    /// The slow tier, where demoted layers are evicted.
+    SlowTier,
+}
+
+impl EvictionTarget {
+    fn tier(&self) -> LayerTier {
+        match self {
+            EvictionTarget::FastTier { .. } => LayerTier::Fast,
+            EvictionTarget::SlowTier => LayerTier::Slow,
+        }
+    }
+}
+
 async fn disk_usage_eviction_task_iteration(
     state: &State,
     task_config: &DiskUsageEvictionTaskConfig,
@@ -265,8 +391,70 @@ async fn disk_usage_eviction_task_iteration(
     tenant_manager: &Arc<TenantManager>,
     cancel: &CancellationToken,
 ) -> anyhow::Result<()> {
-    let tenants_dir = tenant_manager.get_conf().tenants_path();
-    let usage_pre = filesystem_level_usage::get(&tenants_dir, task_config)
+    let conf = tenant_manager.get_conf();
+
+    // The slow tier goes first, to know how much can be demoted to it from the fast tier.
+    let mut demote_bytes = 0;
+    if let Some(slow_layer_tier) = conf.slow_layer_tier.as_ref() {
+        let slow_task_config = DiskUsageEvictionTaskConfig {
+            max_usage_pct: slow_layer_tier.max_usage_pct,
+            min_avail_bytes: slow_layer_tier.min_avail_bytes,
+            ..task_config.clone()
+        };
+        let usage = tier_iteration(
+            state,
+            storage,
+            &slow_layer_tier.path,
+            &slow_task_config,
+            tenant_manager,
+            EvictionTarget::SlowTier,
+            cancel,
+        )
+        .await?;
+        demote_bytes = usage.headroom_bytes();
+    }
+
+    let usage = tier_iteration(
+        state,
+        storage,
+        &conf.tenants_path(),
+        task_config,
+        tenant_manager,
+        EvictionTarget::FastTier { demote_bytes },
+        cancel,
+    )
+    .await?;
+
+    let promote_min_accesses_per_hour = conf
+        .slow_layer_tier
+        .as_ref()
+        .and_then(|slow_layer_tier| slow_layer_tier.promote_min_accesses_per_hour);
+    if let Some(min_accesses_per_hour) = promote_min_accesses_per_hour {
+        if !usage.has_pressure() {
+            promote_hot_layers(
+                tenant_manager,
+                min_accesses_per_hour.get() as f32,
+                usage.headroom_bytes(),
+                cancel,
+            )
+            .await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Relieves the pressure on the filesystem of `dir`, returning the last known usage.
+async fn tier_iteration<'a>(
+    state: &State,
+    storage: &GenericRemoteStorage,
+    dir: &Utf8Path,
+    task_config: &'a DiskUsageEvictionTaskConfig,
+    tenant_manager: &Arc<TenantManager>,
+    target: EvictionTarget,
+    cancel: &CancellationToken,
+) -> anyhow::Result<filesystem_level_usage::Usage<'a>> {
+    let usage_pre = filesystem_level_usage::get(dir, task_config)
         .context("get filesystem-level disk usage before evictions")?;
     let res = disk_usage_eviction_task_iteration_impl(
         state,
@@ -274,8 +462,10 @@ async fn disk_usage_eviction_task_iteration(
         usage_pre,
         tenant_manager,
         task_config.eviction_order.into(),
+        target,
         cancel,
     )
+    .instrument(tracing::info_span!("tier", tier = target.tier().as_str()))
     .await;
     match res {
         Ok(outcome) => {
@@ -286,7 +476,7 @@ async fn disk_usage_eviction_task_iteration(
                 }
                 IterationOutcome::Finished(outcome) => {
                     // Verify with statvfs whether we made any real progress
-                    let after = filesystem_level_usage::get(&tenants_dir, task_config)
+                    let after = filesystem_level_usage::get(dir, task_config)
                         // It's quite unlikely to hit the error here. Keep the code simple and bail out.
                         .context("get filesystem-level disk usage after evictions")?;
 
@@ -302,6 +492,7 @@ async fn disk_usage_eviction_task_iteration(
                     } else {
                         info!(?outcome, ?after, "disk usage pressure relieved");
                     }
+                    return Ok(after);
                 }
             }
         }
@@ -310,6 +501,81 @@ async fn disk_usage_eviction_task_iteration(
         }
     }
 
+    Ok(usage_pre)
+}
+
+/// Promotes layers demoted to the slow tier and accessed at least `min_accesses_per_hour` back to
+/// the fast tier, hottest first, as long as they fit into `budget` bytes.
+async fn promote_hot_layers(
+    tenant_manager: &Arc<TenantManager>,
+    min_accesses_per_hour: f32,
+    mut budget: u64,
+    cancel: &CancellationToken,
+) -> anyhow::Result<()> {
+    let tenants = tenant_manager
+        .list_tenants()
+        .context("get list of tenants")?;
+
+    let mut candidates = Vec::new();
+    for (tenant_id, _state, _gen) in tenants {
+        let Ok(tenant) = tenant_manager.get_attached_tenant_shard(tenant_id) else {
+            continue;
+        };
+        if !tenant.is_active() {
+            continue;
+        }
+        for tl in tenant.list_timelines() {
+            if !tl.is_active() {
+                continue;
+            }
+            let info = tl.get_local_layers_for_disk_usage_eviction().await;
+            candidates.extend(info.resident_layers.into_iter().filter_map(|candidate| {
+                match candidate.layer {
+                    EvictionLayer::Attached(layer)
+                        if layer.tier() == LayerTier::Slow
+                            && candidate.access_rate >= min_accesses_per_hour =>
+                    {
+                        Some((
+                            finite_f32::FiniteF32::try_from(candidate.access_rate).ok()?,
+                            layer,
+                        ))
+                    }
+                    _ => None,
+                }
+            }));
+        }
+        if cancel.is_cancelled() {
+            return Ok(());
+        }
+    }
+
+    candidates.sort_unstable_by_key(|(access_rate, _)| std::cmp::Reverse(*access_rate));
+
+    let mut promoted = 0;
+    for (access_rate, layer) in candidates {
+        if cancel.is_cancelled() {
+            break;
+        }
+        let file_size = layer.layer_desc().file_size;
+        if file_size > budget {
+            continue;
+        }
+        match layer.move_to_tier(LayerTier::Fast).await {
+            Ok(()) => {
+                budget -= file_size;
+                promoted += 1;
+                METRICS.layers_promoted.inc();
+            }
+            Err(e) => {
+                debug!(%layer, %access_rate, "failed to promote layer: {e:#}");
+            }
+        }
+    }
+
+    if promoted > 0 {
+        info!(promoted, "promoted hot layers from the slow tier");
+    }
+
     Ok(())
 }
 
@@ -360,6 +626,7 @@ pub(crate) async fn disk_usage_eviction_task_iteration_impl<U: Usage>(
     usage_pre: U,
     tenant_manager: &Arc<TenantManager>,
     eviction_order: EvictionOrder,
+    target: EvictionTarget,
     cancel: &CancellationToken,
 ) -> anyhow::Result<IterationOutcome<U>> {
     // use tokio's mutex to get a Sync guard (instead of std::sync::Mutex)
@@ -381,7 +648,9 @@ pub(crate) async fn disk_usage_eviction_task_iteration_impl<U: Usage>(
 
     let (candidates, collection_time) = {
         let started_at = std::time::Instant::now();
-        match collect_eviction_candidates(tenant_manager, eviction_order, cancel).await? {
+        match collect_eviction_candidates(tenant_manager, eviction_order, target.tier(), cancel)
+            .await?
+        {
             EvictionCandidates::Cancelled => {
                 return Ok(IterationOutcome::Cancelled);
             }
@@ -404,8 +673,9 @@ pub(crate) async fn disk_usage_eviction_task_iteration_impl<U: Usage>(
         let total_candidates = candidates.len();
         let size = candidate.layer.get_file_size();
         let rel = candidate.relative_last_activity;
+        let cost = candidate.cost;
         debug!(
-            "cand {nth}/{total_candidates}: size={size}, rel_last_activity={rel}, no_access_for={}us, partition={partition:?}, {}/{}/{}",
+            "cand {nth}/{total_candidates}: size={size}, rel_last_activity={rel}, cost={cost}, no_access_for={}us, partition={partition:?}, {}/{}/{}",
             now.duration_since(candidate.last_activity_ts)
                 .unwrap()
                 .as_micros(),
@@ -444,6 +714,11 @@ pub(crate) async fn disk_usage_eviction_task_iteration_impl<U: Usage>(
     let mut usage_assumed = usage_pre;
     let mut evictions_failed = LayerCount::default();
 
+    let mut demote_bytes = match target {
+        EvictionTarget::FastTier { demote_bytes } => demote_bytes,
+        EvictionTarget::SlowTier => 0,
+    };
+
     let evict_layers = async move {
         loop {
             let next = if js.len() >= limit || consumed_all {
@@ -457,8 +732,12 @@ pub(crate) async fn disk_usage_eviction_task_iteration_impl<U: Usage>(
 
             if let Some(next) = next {
                 match next {
-                    Ok(Ok(file_size)) => {
-                        METRICS.layers_evicted.inc();
+                    Ok(Ok((file_size, demoted))) => {
+                        if demoted {
+                            METRICS.layers_demoted.inc();
+                        } else {
+                            METRICS.layers_evicted.inc();
+                        }
                         /*BEGIN_HADRON */
                         METRICS.bytes_evicted.inc_by(file_size);
                         /*END_HADRON */
@@ -495,14 +774,27 @@ pub(crate) async fn disk_usage_eviction_task_iteration_impl<U: Usage>(
             match candidate.layer {
                 EvictionLayer::Attached(layer) => {
                     let file_size = layer.layer_desc().file_size;
+                    let demote = file_size <= demote_bytes;
+                    if demote {
+                        demote_bytes -= file_size;
+                    }
                     js.spawn(async move {
+                        if demote {
+                            match layer.move_to_tier(LayerTier::Slow).await {
+                                Ok(()) => return Ok((file_size, true)),
+                                Err(e) => {
+                                    debug!(%layer, "failed to demote layer, evicting instead: {e:#}")
+                                }
+                            }
+                        }
+
                         // have a low eviction waiting timeout because our LRU calculations go stale fast;
                         // also individual layer evictions could hang because of bugs and we do not want to
                         // pause disk_usage_based_eviction for such.
                         let timeout = std::time::Duration::from_secs(5);
 
                         match layer.evict_and_wait(timeout).await {
-                            Ok(()) => Ok(file_size),
+                            Ok(()) => Ok((file_size, false)),
                             Err(e) => Err((file_size, e)),
                         }
                     });
@@ -515,7 +807,7 @@ pub(crate) async fn disk_usage_eviction_task_iteration_impl<U: Usage>(
                             .secondary_tenant
                             .evict_layer(layer.timeline_id, layer.name)
                             .await;
-                        Ok(file_size)
+                        Ok((file_size, false))
                     });
                 }
             }
@@ -618,6 +910,14 @@ pub(crate) fn get_file_size(&self) -> u64 {
             Self::Secondary(sl) => sl.metadata.file_size,
         }
     }
+
+    /// Secondary locations only use the fast tier.
+    pub(crate) fn tier(&self) -> LayerTier {
+        match self {
+            Self::Attached(l) => l.tier(),
+            Self::Secondary(_) => LayerTier::Fast,
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -625,6 +925,9 @@ pub(crate) struct EvictionCandidate {
     pub(crate) layer: EvictionLayer,
     pub(crate) last_activity_ts: SystemTime,
     pub(crate) relative_last_activity: finite_f32::FiniteF32,
+    /// Estimated accesses per hour, see [`Layer::access_rate`].
+    pub(crate) access_rate: f32,
+    pub(crate) cost: finite_f32::FiniteF32,
     pub(crate) visibility: LayerVisibilityHint,
 }
 
@@ -790,6 +1093,7 @@ enum EvictionCandidates {
 async fn collect_eviction_candidates(
     tenant_manager: &Arc<TenantManager>,
     eviction_order: EvictionOrder,
+    tier: LayerTier,
     cancel: &CancellationToken,
 ) -> anyhow::Result<EvictionCandidates> {
     const LOG_DURATION_THRESHOLD: std::time::Duration = std::time::Duration::from_secs(10);
@@ -799,6 +1103,12 @@ async fn collect_eviction_candidates(
         .list_tenants()
         .context("get list of tenants")?;
 
+    let now = SystemTime::now();
+    let default_eviction_weight_pct = tenant_manager
+        .get_conf()
+        .default_tenant_conf
+        .eviction_weight_pct;
+
     // TODO: avoid listing every layer in every tenant: this loop can block the executor,
     // and the resulting data structure can be huge.
     // (https://github.com/neondatabase/neon/issues/6224)
@@ -903,6 +1213,11 @@ async fn collect_eviction_candidates(
             max_layer_size
         };
 
+        // Only the layers on the tier under pressure free up space on it.
+        tenant_candidates.retain(|candidate| candidate.layer.tier() == tier);
+
+        let eviction_weight_pct = tenant.get_eviction_weight_pct();
+
         // Sort layers most-recently-used first, then calculate [`EvictionPartition`] for each layer,
         // where the inputs are:
         //  - whether the layer is visible
@@ -922,6 +1237,7 @@ async fn collect_eviction_candidates(
                     // be 1.0; this is for us to evict it last.
                     candidate.relative_last_activity =
                         eviction_order.relative_last_activity(total, i);
+                    candidate.cost = eviction_order.cost(&candidate, now, eviction_weight_pct);
 
                     let partition = match candidate.visibility {
                         LayerVisibilityHint::Covered => {
@@ -971,11 +1287,13 @@ async fn collect_eviction_candidates(
     // will not delete anything.
 
     let mut secondary_tenants = Vec::new();
-    tenant_manager.foreach_secondary_tenants(
-        |_tenant_shard_id: &TenantShardId, state: &Arc<SecondaryTenant>| {
-            secondary_tenants.push(state.clone());
-        },
-    );
+    if tier == LayerTier::Fast {
+        tenant_manager.foreach_secondary_tenants(
+            |_tenant_shard_id: &TenantShardId, state: &Arc<SecondaryTenant>| {
+                secondary_tenants.push(state.clone());
+            },
+        );
+    }
 
     for tenant in secondary_tenants {
         // for secondary tenants we use a sum of on_disk layers and already evicted layers. this is
@@ -991,6 +1309,10 @@ async fn collect_eviction_candidates(
 
         let started_at = std::time::Instant::now();
 
+        let eviction_weight_pct = tenant
+            .get_eviction_weight_pct()
+            .unwrap_or(default_eviction_weight_pct);
+
         layer_info
             .resident_layers
             .sort_unstable_by_key(|layer_info| std::cmp::Reverse(layer_info.last_activity_ts));
@@ -1003,6 +1325,7 @@ async fn collect_eviction_candidates(
                 .map(|(i, mut candidate)| {
                     candidate.relative_last_activity =
                         eviction_order.relative_last_activity(total_layers, i);
+                    candidate.cost = eviction_order.cost(&candidate, now, eviction_weight_pct);
                     (
                         // Secondary locations' layers are always considered above the min resident size,
                         // i.e. secondary locations are permitted to be trimmed to zero layers if all
@@ -1226,6 +1549,18 @@ fn add_available_bytes(&mut self, bytes: u64) {
         }
     }
 
+    impl Usage<'_> {
+        /// How many bytes can be used up before there is pressure.
+        pub fn headroom_bytes(&self) -> u64 {
+            let max_used_bytes = self.total_bytes * self.config.max_usage_pct.get() as u64 / 100;
+            let min_avail_bytes = self
+                .config
+                .min_avail_bytes
+                .max(self.total_bytes - max_used_bytes + 1);
+            self.avail_bytes.saturating_sub(min_avail_bytes)
+        }
+    }
+
     pub fn get<'a>(
         tenants_dir: &Utf8Path,
         config: &'a DiskUsageEvictionTaskConfig,
@@ -1295,6 +1630,61 @@ fn max_usage_pct_pressure() {
         usage.add_available_bytes(16_000);
         assert!(!usage.has_pressure());
     }
+
+    #[test]
+    fn headroom_bytes() {
+        use std::time::Duration;
+
+        use utils::serde_percent::Percent;
+
+        use super::Usage as _;
+
+        let config = DiskUsageEvictionTaskConfig {
+            max_usage_pct: Percent::new(85).unwrap(),
+            min_avail_bytes: 0,
+            period: Duration::MAX,
+            #[cfg(feature = "testing")]
+            mock_statvfs: None,
+            eviction_order: pageserver_api::config::EvictionOrder::default(),
+            enabled: true,
+        };
+        let usage = Usage {
+            config: &config,
+            total_bytes: 100_000,
+            avail_bytes: 50_000,
+        };
+
+        let headroom = usage.headroom_bytes();
+        assert_eq!(headroom, 34_999);
+
+        let mut used = usage;
+        used.avail_bytes -= headroom;
+        assert!(
+            !used.has_pressure(),
+            "using up the headroom causes no pressure"
+        );
+        used.avail_bytes -= 1;
+        assert!(
+            used.has_pressure(),
+            "using more than the headroom causes pressure"
+        );
+
+        let config = DiskUsageEvictionTaskConfig {
+            min_avail_bytes: 20_000,
+            ..config.clone()
+        };
+        let usage = Usage {
+            config: &config,
+            ..usage
+        };
+        assert_eq!(usage.headroom_bytes(), 30_000);
+
+        let usage = Usage {
+            avail_bytes: 10_000,
+            ..usage
+        };
+        assert_eq!(usage.headroom_bytes(), 0);
+    }
 }
 
 #[cfg(test)]
@@ -1332,4 +1722,64 @@ fn relative_spare_bounds() {
         assert_eq!(v.last(), Some(&0.1));
         assert!(v.windows(2).all(|slice| slice[0] > slice[1]));
     }
+
+    #[test]
+    fn cost_aware_ordering() {
+        use std::time::Duration;
+
+        let order = EvictionOrder::CostAware {
+            redownload_overhead_bytes: 8 * 1024 * 1024,
+        };
+        let visible = LayerVisibilityHint::Visible;
+        let cost = |size: u64, idle_secs: u64, rate: f32, visibility, weight| {
+            order
+                .cost_of(
+                    size,
+                    Duration::from_secs(idle_secs),
+                    rate,
+                    visibility,
+                    weight,
+                )
+                .unwrap()
+                .into_inner()
+        };
+
+        let mib = 1024 * 1024;
+        let hour = 3600;
+
+        // A large image layer read once an hour ago loses to a small, hot L0 layer read just now.
+        let large_cold = cost(256 * mib, hour, 1.0, &visible, 100);
+        let small_hot = cost(8 * mib, 10, 100.0, &visible, 100);
+        assert!(large_cold < small_hot);
+
+        // Same recency, but the frequently read layer is more expensive to evict.
+        assert!(
+            cost(64 * mib, hour, 1.0, &visible, 100) < cost(64 * mib, hour, 50.0, &visible, 100)
+        );
+
+        // Same access pattern: larger layers free more space per re-download.
+        assert!(cost(256 * mib, hour, 1.0, &visible, 100) < cost(mib, hour, 1.0, &visible, 100));
+
+        // Covered layers are cheap to evict.
+        assert!(
+            cost(64 * mib, hour, 1.0, &LayerVisibilityHint::Covered, 100)
+                < cost(64 * mib, hour, 1.0, &visible, 100)
+        );
+
+        // Tenant weights scale the cost.
+        let premium = cost(64 * mib, hour, 1.0, &visible, 200);
+        let regular = cost(64 * mib, hour, 1.0, &visible, 100);
+        assert!((premium - 2.0 * regular).abs() < regular * 1e-3);
+
+        // Relative access ordering does not use the cost.
+        let order = EvictionOrder::RelativeAccessed {
+            highest_layer_count_loses_first: true,
+        };
+        assert_eq!(
+            order
+                .cost_of(mib, Duration::ZERO, 1.0, &visible, 100)
+                .unwrap(),
+            finite_f32::FiniteF32::ZERO
+        );
+    }
 }
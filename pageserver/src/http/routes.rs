@@ -32,16 +32,17 @@
 use pageserver_api::models::virtual_file::IoMode;
 use pageserver_api::models::{
     DetachBehavior, DownloadRemoteLayersTaskSpawnRequest, IngestAuxFilesRequest,
-    ListAuxFilesRequest, LocationConfig, LocationConfigListResponse, LocationConfigMode, LsnLease,
-    LsnLeaseRequest, OffloadedTimelineInfo, PageTraceEvent, ShardParameters, StatusResponse,
-    TenantConfigPatchRequest, TenantConfigRequest, TenantDetails, TenantInfo,
-    TenantLocationConfigRequest, TenantLocationConfigResponse, TenantScanRemoteStorageResponse,
-    TenantScanRemoteStorageShard, TenantShardLocation, TenantShardSplitRequest,
-    TenantShardSplitResponse, TenantSorting, TenantState, TenantWaitLsnRequest,
-    TimelineArchivalConfigRequest, TimelineCreateRequest, TimelineCreateRequestMode,
-    TimelineCreateRequestModeImportPgdata, TimelineGcRequest, TimelineInfo,
-    TimelinePatchIndexPartRequest, TimelineVisibilityState, TimelinesInfoAndOffloaded,
-    TopTenantShardItem, TopTenantShardsRequest, TopTenantShardsResponse,
+    LayerRewriteRequest, ListAuxFilesRequest, LocationConfig, LocationConfigListResponse,
+    LocationConfigMode, LsnLease, LsnLeaseRequest, OffloadedTimelineInfo, PageTraceEvent,
+    ShardParameters, StatusResponse, TenantConfigPatchRequest, TenantConfigRequest, TenantDetails,
+    TenantInfo, TenantLocationConfigRequest, TenantLocationConfigResponse,
+    TenantScanRemoteStorageResponse, TenantScanRemoteStorageShard, TenantShardLocation,
+    TenantShardSplitRequest, TenantShardSplitResponse, TenantSorting, TenantState,
+    TenantWaitLsnRequest, TimelineArchivalConfigRequest, TimelineCreateRequest,
+    TimelineCreateRequestMode, TimelineCreateRequestModeImportPgdata, TimelineExportRequest,
+    TimelineGcRequest, TimelineInfo, TimelineLayerRewriteStatus, TimelinePatchIndexPartRequest,
+    TimelineResetRequest, TimelineVisibilityState, TimelinesInfoAndOffloaded, TopTenantShardItem,
+    TopTenantShardsRequest, TopTenantShardsResponse,
 };
 use pageserver_api::shard::{ShardCount, TenantShardId};
 use postgres_ffi::PgMajorVersion;
@@ -89,8 +90,8 @@
     WaitLsnWaiter, import_pgdata,
 };
 use crate::tenant::{
-    GetTimelineError, LogicalSizeCalculationCause, OffloadedTimeline, PageReconstructError,
-    remote_timeline_client,
+    GcError, GetTimelineError, LogicalSizeCalculationCause, OffloadedTimeline,
+    PageReconstructError, remote_timeline_client,
 };
 use crate::{DEFAULT_PG_VERSION, disk_usage_eviction_task, tenant};
 
@@ -524,6 +525,7 @@ async fn build_timeline_info_common(
         walreceiver_status,
         // HADRON
         image_consistent_lsn: None,
+        size_limit_exceeded: timeline.get_size_limit_exceeded(),
     };
     Ok(info)
 }
@@ -626,23 +628,7 @@ async fn timeline_create_handler(
                 idempotency_key.0,
             ),
             new_timeline_id,
-            location: {
-                use import_pgdata::index_part_format::Location;
-                use pageserver_api::models::ImportPgdataLocation;
-                match location {
-                    #[cfg(feature = "testing")]
-                    ImportPgdataLocation::LocalFs { path } => Location::LocalFs { path },
-                    ImportPgdataLocation::AwsS3 {
-                        region,
-                        bucket,
-                        key,
-                    } => Location::AwsS3 {
-                        region,
-                        bucket,
-                        key,
-                    },
-                }
-            },
+            location: location.into(),
         }),
     };
 
@@ -1183,6 +1169,40 @@ async fn timeline_delete_handler(
     json_response(StatusCode::ACCEPTED, ())
 }
 
+/// Restores a timeline that was soft-deleted and hasn't been purged yet. See the tenant's
+/// `timeline_delete_grace_period`.
+async fn timeline_undelete_handler(
+    request: Request<Body>,
+    _cancel: CancellationToken,
+) -> Result<Response<Body>, ApiError> {
+    let tenant_shard_id: TenantShardId = parse_request_param(&request, "tenant_shard_id")?;
+    let timeline_id: TimelineId = parse_request_param(&request, "timeline_id")?;
+    check_permission(&request, Some(tenant_shard_id.tenant_id))?;
+
+    let ctx = RequestContext::new(TaskKind::MgmtRequest, DownloadBehavior::Warn);
+    let state = get_state(&request);
+
+    async {
+        let tenant = state
+            .tenant_manager
+            .get_attached_tenant_shard(tenant_shard_id)?;
+
+        tenant.wait_to_become_active(ACTIVE_TENANT_TIMEOUT).await?;
+
+        tenant
+            .undelete_timeline(timeline_id, state.broker_client.clone(), ctx)
+            .await?;
+        Ok::<_, ApiError>(())
+    }
+    .instrument(info_span!("timeline_undelete",
+                tenant_id = %tenant_shard_id.tenant_id,
+                shard_id = %tenant_shard_id.shard_slug(),
+                %timeline_id))
+    .await?;
+
+    json_response(StatusCode::OK, ())
+}
+
 async fn tenant_reset_handler(
     request: Request<Body>,
     _cancel: CancellationToken,
@@ -1460,6 +1480,47 @@ async fn layer_map_info_handler(
     json_response(StatusCode::OK, layer_map_info)
 }
 
+async fn timeline_hottest_layers_handler(
+    request: Request<Body>,
+    _cancel: CancellationToken,
+) -> Result<Response<Body>, ApiError> {
+    let tenant_shard_id: TenantShardId = parse_request_param(&request, "tenant_shard_id")?;
+    let timeline_id: TimelineId = parse_request_param(&request, "timeline_id")?;
+    let limit: usize = parse_query_param(&request, "limit")?.unwrap_or(10);
+    let state = get_state(&request);
+
+    check_permission(&request, Some(tenant_shard_id.tenant_id))?;
+
+    let timeline =
+        active_timeline_of_active_tenant(&state.tenant_manager, tenant_shard_id, timeline_id)
+            .await?;
+    let layers = timeline
+        .hottest_layers(limit)
+        .await
+        .map_err(|_shutdown| ApiError::ShuttingDown)?;
+
+    json_response(StatusCode::OK, layers)
+}
+
+/// Read amplification of GetPage requests per compaction partition, as used by the
+/// `ReadAmplification` image creation policy.
+async fn timeline_read_amp_handler(
+    request: Request<Body>,
+    _cancel: CancellationToken,
+) -> Result<Response<Body>, ApiError> {
+    let tenant_shard_id: TenantShardId = parse_request_param(&request, "tenant_shard_id")?;
+    let timeline_id: TimelineId = parse_request_param(&request, "timeline_id")?;
+    let state = get_state(&request);
+
+    check_permission(&request, Some(tenant_shard_id.tenant_id))?;
+
+    let timeline =
+        active_timeline_of_active_tenant(&state.tenant_manager, tenant_shard_id, timeline_id)
+            .await?;
+
+    json_response(StatusCode::OK, timeline.read_amp_by_partition())
+}
+
 #[instrument(skip_all, fields(tenant_id, shard_id, timeline_id, layer_name))]
 async fn timeline_layer_scan_disposable_keys(
     request: Request<Body>,
@@ -1594,6 +1655,183 @@ async fn timeline_download_heatmap_layers_handler(
     json_response(StatusCode::ACCEPTED, ())
 }
 
+async fn timeline_export_handler(
+    mut request: Request<Body>,
+    _cancel: CancellationToken,
+) -> Result<Response<Body>, ApiError> {
+    let tenant_shard_id: TenantShardId = parse_request_param(&request, "tenant_shard_id")?;
+    let timeline_id: TimelineId = parse_request_param(&request, "timeline_id")?;
+    let request_data: TimelineExportRequest = json_request(&mut request).await?;
+
+    check_permission(&request, Some(tenant_shard_id.tenant_id))?;
+
+    let state = get_state(&request);
+    let timeline =
+        active_timeline_of_active_tenant(&state.tenant_manager, tenant_shard_id, timeline_id)
+            .await?;
+    let ctx = RequestContext::new(TaskKind::MgmtRequest, DownloadBehavior::Download)
+        .with_scope_timeline(&timeline);
+
+    let status = timeline
+        .start_export_pgdata(request_data, &ctx)
+        .instrument(info_span!("timeline_export",
+                tenant_id = %tenant_shard_id.tenant_id,
+                shard_id = %tenant_shard_id.shard_slug(),
+                %timeline_id))
+        .await?;
+
+    json_response(StatusCode::ACCEPTED, status)
+}
+
+async fn timeline_export_status_handler(
+    request: Request<Body>,
+    _cancel: CancellationToken,
+) -> Result<Response<Body>, ApiError> {
+    let tenant_shard_id: TenantShardId = parse_request_param(&request, "tenant_shard_id")?;
+    let timeline_id: TimelineId = parse_request_param(&request, "timeline_id")?;
+
+    check_permission(&request, Some(tenant_shard_id.tenant_id))?;
+
+    let state = get_state(&request);
+    let timeline =
+        active_timeline_of_active_tenant(&state.tenant_manager, tenant_shard_id, timeline_id)
+            .await?;
+
+    match timeline.export_pgdata_status() {
+        Some(status) => json_response(StatusCode::OK, status),
+        None => Err(ApiError::NotFound(
+            anyhow::anyhow!("timeline {timeline_id} has not been exported").into(),
+        )),
+    }
+}
+
+async fn timeline_layer_rewrite_handler(
+    mut request: Request<Body>,
+    _cancel: CancellationToken,
+) -> Result<Response<Body>, ApiError> {
+    let tenant_shard_id: TenantShardId = parse_request_param(&request, "tenant_shard_id")?;
+    let timeline_id: TimelineId = parse_request_param(&request, "timeline_id")?;
+    let request_data: LayerRewriteRequest = json_request(&mut request).await?;
+
+    check_permission(&request, Some(tenant_shard_id.tenant_id))?;
+
+    let state = get_state(&request);
+    let timeline =
+        active_timeline_of_active_tenant(&state.tenant_manager, tenant_shard_id, timeline_id)
+            .await?;
+    let ctx = RequestContext::new(TaskKind::MgmtRequest, DownloadBehavior::Download)
+        .with_scope_timeline(&timeline);
+
+    let status = timeline
+        .start_layer_rewrite(request_data, &ctx)
+        .instrument(info_span!("timeline_layer_rewrite",
+                tenant_id = %tenant_shard_id.tenant_id,
+                shard_id = %tenant_shard_id.shard_slug(),
+                %timeline_id))
+        .await?;
+
+    json_response(StatusCode::ACCEPTED, status)
+}
+
+async fn timeline_layer_rewrite_status_handler(
+    request: Request<Body>,
+    _cancel: CancellationToken,
+) -> Result<Response<Body>, ApiError> {
+    let tenant_shard_id: TenantShardId = parse_request_param(&request, "tenant_shard_id")?;
+    let timeline_id: TimelineId = parse_request_param(&request, "timeline_id")?;
+
+    check_permission(&request, Some(tenant_shard_id.tenant_id))?;
+
+    let state = get_state(&request);
+    let timeline =
+        active_timeline_of_active_tenant(&state.tenant_manager, tenant_shard_id, timeline_id)
+            .await?;
+
+    match timeline.layer_rewrite_status() {
+        Some(status) => json_response(StatusCode::OK, status),
+        None => Err(ApiError::NotFound(
+            anyhow::anyhow!("timeline {timeline_id} has no layer rewrite").into(),
+        )),
+    }
+}
+
+/// Start a layer rewrite on all active timelines of the attached tenants on this pageserver.
+/// Timelines with a rewrite in progress keep it. Returns the status of all timelines.
+async fn layer_rewrite_handler(
+    mut request: Request<Body>,
+    _cancel: CancellationToken,
+) -> Result<Response<Body>, ApiError> {
+    check_permission(&request, None)?;
+    let request_data: LayerRewriteRequest = json_request(&mut request).await?;
+    let state = get_state(&request);
+
+    let timelines = state
+        .tenant_manager
+        .get_attached_active_tenant_shards()
+        .into_iter()
+        .flat_map(|tenant| tenant.list_timelines())
+        .filter(|timeline| timeline.is_active());
+    let results = join_all(timelines.map(|timeline| {
+        let request_data = request_data.clone();
+        async move {
+            let ctx = RequestContext::new(TaskKind::MgmtRequest, DownloadBehavior::Download)
+                .with_scope_timeline(&timeline);
+            let span = info_span!("timeline_layer_rewrite",
+                tenant_id = %timeline.tenant_shard_id.tenant_id,
+                shard_id = %timeline.tenant_shard_id.shard_slug(),
+                timeline_id = %timeline.timeline_id);
+            let status = timeline
+                .start_layer_rewrite(request_data, &ctx)
+                .instrument(span)
+                .await?;
+            Ok::<_, ApiError>(TimelineLayerRewriteStatus {
+                tenant_shard_id: timeline.tenant_shard_id,
+                timeline_id: timeline.timeline_id,
+                status,
+            })
+        }
+    }))
+    .await;
+
+    // Timelines that shut down in the meantime are not an error, they will be picked up again
+    // when the caller retries on the pageserver they moved to.
+    let mut statuses = Vec::new();
+    for result in results {
+        match result {
+            Ok(status) => statuses.push(status),
+            Err(ApiError::ShuttingDown) => {}
+            Err(e) => return Err(e),
+        }
+    }
+
+    json_response(StatusCode::ACCEPTED, statuses)
+}
+
+/// The layer rewrite status of all timelines of the attached tenants that ever had one.
+async fn layer_rewrite_status_handler(
+    request: Request<Body>,
+    _cancel: CancellationToken,
+) -> Result<Response<Body>, ApiError> {
+    check_permission(&request, None)?;
+    let state = get_state(&request);
+
+    let statuses: Vec<_> = state
+        .tenant_manager
+        .get_attached_active_tenant_shards()
+        .into_iter()
+        .flat_map(|tenant| tenant.list_timelines())
+        .filter_map(|timeline| {
+            Some(TimelineLayerRewriteStatus {
+                tenant_shard_id: timeline.tenant_shard_id,
+                timeline_id: timeline.timeline_id,
+                status: timeline.layer_rewrite_status()?,
+            })
+        })
+        .collect();
+
+    json_response(StatusCode::OK, statuses)
+}
+
 async fn timeline_shutdown_download_heatmap_layers_handler(
     request: Request<Body>,
     _cancel: CancellationToken,
@@ -2274,6 +2512,33 @@ async fn timeline_gc_handler(
     json_response(StatusCode::OK, gc_result)
 }
 
+// Report which layers GC would keep on given timeline and why, without running GC.
+async fn timeline_gc_dry_run_handler(
+    request: Request<Body>,
+    _cancel: CancellationToken,
+) -> Result<Response<Body>, ApiError> {
+    let tenant_shard_id: TenantShardId = parse_request_param(&request, "tenant_shard_id")?;
+    let timeline_id: TimelineId = parse_request_param(&request, "timeline_id")?;
+    check_permission(&request, Some(tenant_shard_id.tenant_id))?;
+    let state = get_state(&request);
+    async {
+        let tenant = state
+            .tenant_manager
+            .get_attached_tenant_shard(tenant_shard_id)?;
+        let timeline = tenant.get_timeline(timeline_id, true)?;
+        let resp = timeline
+            .gc_dry_run(tenant.gc_blocked_by())
+            .await
+            .map_err(|e| match e {
+                GcError::TenantCancelled | GcError::TimelineCancelled => ApiError::ShuttingDown,
+                other => ApiError::InternalServerError(anyhow::anyhow!(other)),
+            })?;
+        json_response(StatusCode::OK, resp)
+    }
+    .instrument(info_span!("timeline_gc_dry_run", tenant_id = %tenant_shard_id.tenant_id, shard_id = %tenant_shard_id.shard_slug(), %timeline_id))
+    .await
+}
+
 // Cancel scheduled compaction tasks
 async fn timeline_cancel_compact_handler(
     request: Request<Body>,
@@ -2699,6 +2964,53 @@ async fn timeline_detach_ancestor_handler(
     .await
 }
 
+/// Reset a timeline to an earlier LSN, onto a backup branch which was detached from it.
+async fn timeline_reset_handler(
+    mut request: Request<Body>,
+    _cancel: CancellationToken,
+) -> Result<Response<Body>, ApiError> {
+    let tenant_shard_id: TenantShardId = parse_request_param(&request, "tenant_shard_id")?;
+    check_permission(&request, Some(tenant_shard_id.tenant_id))?;
+    let timeline_id: TimelineId = parse_request_param(&request, "timeline_id")?;
+    let request_data: TimelineResetRequest = json_request(&mut request).await?;
+
+    let span = tracing::info_span!("timeline_reset", tenant_id=%tenant_shard_id.tenant_id, shard_id=%tenant_shard_id.shard_slug(), %timeline_id);
+
+    async move {
+        let state = get_state(&request);
+
+        let tenant = state
+            .tenant_manager
+            .get_attached_tenant_shard(tenant_shard_id)?;
+
+        tenant.wait_to_become_active(ACTIVE_TENANT_TIMEOUT).await?;
+
+        let timeline = tenant.get_timeline(timeline_id, true)?;
+        let backup = tenant.get_timeline(request_data.backup_timeline_id, true)?;
+
+        let needs_reset = timeline
+            .reset_onto_backup(&tenant, request_data.lsn, &backup)
+            .await?;
+
+        drop(backup);
+        drop(timeline);
+        drop(tenant);
+
+        if needs_reset {
+            let ctx = RequestContext::new(TaskKind::MgmtRequest, DownloadBehavior::Warn);
+            state
+                .tenant_manager
+                .reset_tenant(tenant_shard_id, false, &ctx)
+                .await
+                .map_err(ApiError::InternalServerError)?;
+        }
+
+        json_response(StatusCode::OK, ())
+    }
+    .instrument(span)
+    .await
+}
+
 async fn deletion_queue_flush(
     r: Request<Body>,
     cancel: CancellationToken,
@@ -2913,6 +3225,7 @@ fn add_available_bytes(&mut self, bytes: u64) {
         usage,
         &state.tenant_manager,
         config.eviction_order.into(),
+        crate::disk_usage_eviction_task::EvictionTarget::FastTier { demote_bytes: 0 },
         &cancel,
     )
     .await;
@@ -3051,7 +3364,7 @@ async fn secondary_download_handler(
     )
     .await;
 
-    let progress = secondary_tenant.progress.lock().unwrap().clone();
+    let progress = secondary_tenant.get_progress();
 
     let status = match result {
         Ok(Ok(())) => {
@@ -3148,7 +3461,7 @@ async fn secondary_status_handler(
         ));
     };
 
-    let progress = secondary_tenant.progress.lock().unwrap().clone();
+    let progress = secondary_tenant.get_progress();
 
     json_response(StatusCode::OK, progress)
 }
@@ -3216,6 +3529,18 @@ async fn put_io_mode_handler(
 /// Polled by control plane.
 ///
 /// See [`crate::utilization`].
+/// Returns the per-node compaction queue, in the order in which tenant shards will compact.
+async fn compaction_queue_handler(
+    request: Request<Body>,
+    _cancel: CancellationToken,
+) -> Result<Response<Body>, ApiError> {
+    check_permission(&request, None)?;
+    json_response(
+        StatusCode::OK,
+        crate::tenant::compaction_scheduler::queue_info(),
+    )
+}
+
 async fn get_utilization(
     r: Request<Body>,
     _cancel: CancellationToken,
@@ -4110,6 +4435,10 @@ pub fn make_router(
             "/v1/tenant/:tenant_shard_id/timeline/:timeline_id/do_gc",
             |r| api_handler(r, timeline_gc_handler),
         )
+        .get(
+            "/v1/tenant/:tenant_shard_id/timeline/:timeline_id/gc_dry_run",
+            |r| api_handler(r, timeline_gc_dry_run_handler),
+        )
         .get(
             "/v1/tenant/:tenant_shard_id/timeline/:timeline_id/compact",
             |r| api_handler(r, timeline_compact_info_handler),
@@ -4146,13 +4475,29 @@ pub fn make_router(
             "/v1/tenant/:tenant_shard_id/timeline/:timeline_id/detach_ancestor",
             |r| api_handler(r, timeline_detach_ancestor_handler),
         )
+        .put(
+            "/v1/tenant/:tenant_shard_id/timeline/:timeline_id/reset",
+            |r| api_handler(r, timeline_reset_handler),
+        )
         .delete("/v1/tenant/:tenant_shard_id/timeline/:timeline_id", |r| {
             api_handler(r, timeline_delete_handler)
         })
+        .put(
+            "/v1/tenant/:tenant_shard_id/timeline/:timeline_id/undelete",
+            |r| api_handler(r, timeline_undelete_handler),
+        )
         .get(
             "/v1/tenant/:tenant_shard_id/timeline/:timeline_id/layer",
             |r| api_handler(r, layer_map_info_handler),
         )
+        .get(
+            "/v1/tenant/:tenant_shard_id/timeline/:timeline_id/hottest_layers",
+            |r| api_handler(r, timeline_hottest_layers_handler),
+        )
+        .get(
+            "/v1/tenant/:tenant_shard_id/timeline/:timeline_id/read_amp",
+            |r| api_handler(r, timeline_read_amp_handler),
+        )
         .post(
             "/v1/tenant/:tenant_shard_id/timeline/:timeline_id/download_heatmap_layers",
             |r| api_handler(r, timeline_download_heatmap_layers_handler),
@@ -4161,6 +4506,20 @@ pub fn make_router(
             "/v1/tenant/:tenant_shard_id/timeline/:timeline_id/download_heatmap_layers",
             |r| api_handler(r, timeline_shutdown_download_heatmap_layers_handler),
         )
+        .put("/v1/tenant/:tenant_shard_id/timeline/:timeline_id/export", |r| {
+            api_handler(r, timeline_export_handler)
+        })
+        .get("/v1/tenant/:tenant_shard_id/timeline/:timeline_id/export", |r| {
+            api_handler(r, timeline_export_status_handler)
+        })
+        .put(
+            "/v1/tenant/:tenant_shard_id/timeline/:timeline_id/layer_rewrite",
+            |r| api_handler(r, timeline_layer_rewrite_handler),
+        )
+        .get(
+            "/v1/tenant/:tenant_shard_id/timeline/:timeline_id/layer_rewrite",
+            |r| api_handler(r, timeline_layer_rewrite_status_handler),
+        )
         .get(
             "/v1/tenant/:tenant_shard_id/timeline/:timeline_id/layer/:layer_file_name",
             |r| api_handler(r, layer_download_handler),
@@ -4228,6 +4587,9 @@ pub fn make_router(
         .put("/v1/io_engine", |r| api_handler(r, put_io_engine_handler))
         .put("/v1/io_mode", |r| api_handler(r, put_io_mode_handler))
         .get("/v1/utilization", |r| api_handler(r, get_utilization))
+        .get("/v1/compaction_queue", |r| api_handler(r, compaction_queue_handler))
+        .put("/v1/layer_rewrite", |r| api_handler(r, layer_rewrite_handler))
+        .get("/v1/layer_rewrite", |r| api_handler(r, layer_rewrite_status_handler))
         .get("/v1/list_tenant_visible_size", |r| api_handler(r, list_tenant_visible_size_handler))
         .post(
             "/v1/tenant/:tenant_shard_id/timeline/:timeline_id/ingest_aux_files",
@@ -93,6 +93,7 @@ pub async fn shutdown_pageserver(
     metrics_collection_task: MetricsCollectionTask,
     consumption_metrics_worker: ConsumptionMetricsTasks,
     disk_usage_eviction_task: Option<DiskUsageEvictionTask>,
+    compaction_scheduler: CancellableTask,
     tenant_manager: &TenantManager,
     background_purges: BackgroundPurges,
     mut deletion_queue: DeletionQueue,
@@ -237,6 +238,13 @@ struct Results {
     )
     .await;
 
+    timed(
+        compaction_scheduler.shutdown(),
+        "shutdown compaction scheduler",
+        Duration::from_secs(1),
+    )
+    .await;
+
     timed(
         background_purges.shutdown(),
         "shutdown background purges",
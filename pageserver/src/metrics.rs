@@ -816,6 +816,15 @@ pub(crate) enum LayerLevel {
     .expect("failed to define a metric")
 });
 
+static SIZE_LIMIT_EXCEEDED: Lazy<UIntGaugeVec> = Lazy::new(|| {
+    register_uint_gauge_vec!(
+        "pageserver_size_limit_exceeded",
+        "1 if the timeline exceeds one of its configured size limits and the compute is asked to be read-only, 0 otherwise.",
+        &["tenant_id", "shard_id", "timeline_id"],
+    )
+    .expect("failed to define a metric")
+});
+
 pub(crate) static CIRCUIT_BREAKERS_BROKEN: Lazy<IntCounter> = Lazy::new(|| {
     register_int_counter!(
         "pageserver_circuit_breaker_broken",
@@ -3257,6 +3266,7 @@ pub(crate) struct TimelineMetrics {
     pub evictions_with_low_residence_duration: std::sync::RwLock<EvictionsWithLowResidenceDuration>,
     /// Number of valid LSN leases.
     pub valid_lsn_lease_count_gauge: UIntGauge,
+    pub size_limit_exceeded_gauge: UIntGauge,
     pub wal_records_received: IntCounter,
     pub storage_io_size: StorageIoSizeMetrics,
     pub wait_lsn_in_progress_micros: GlobalAndPerTenantIntCounter,
@@ -3390,6 +3400,10 @@ pub fn new(
             .get_metric_with_label_values(&[&tenant_id, &shard_id, &timeline_id])
             .unwrap();
 
+        let size_limit_exceeded_gauge = SIZE_LIMIT_EXCEEDED
+            .get_metric_with_label_values(&[&tenant_id, &shard_id, &timeline_id])
+            .unwrap();
+
         let wal_records_received = PAGESERVER_TIMELINE_WAL_RECORDS_RECEIVED
             .get_metric_with_label_values(&[&tenant_id, &shard_id, &timeline_id])
             .unwrap();
@@ -3446,6 +3460,7 @@ pub fn new(
             ),
             storage_io_size,
             valid_lsn_lease_count_gauge,
+            size_limit_exceeded_gauge,
             wal_records_received,
             wait_lsn_in_progress_micros,
             wait_lsn_start_finish_counterpair,
@@ -3605,6 +3620,7 @@ pub(crate) fn shutdown(&self) {
         let _ = EVICTIONS.remove_label_values(&[tenant_id, shard_id, timeline_id]);
         let _ = AUX_FILE_SIZE.remove_label_values(&[tenant_id, shard_id, timeline_id]);
         let _ = VALID_LSN_LEASE_COUNT.remove_label_values(&[tenant_id, shard_id, timeline_id]);
+        let _ = SIZE_LIMIT_EXCEEDED.remove_label_values(&[tenant_id, shard_id, timeline_id]);
 
         self.evictions_with_low_residence_duration
             .write()
@@ -4429,6 +4445,8 @@ pub(crate) struct Metrics {
         pub(crate) layers_collected: IntCounter,
         pub(crate) layers_selected: IntCounter,
         pub(crate) layers_evicted: IntCounter,
+        pub(crate) layers_demoted: IntCounter,
+        pub(crate) layers_promoted: IntCounter,
         /*BEGIN_HADRON */
         pub(crate) bytes_evicted: IntCounter,
         /*END_HADRON */
@@ -4468,6 +4486,18 @@ fn default() -> Self {
             )
             .unwrap();
 
+            let layers_demoted = register_int_counter!(
+                "pageserver_disk_usage_based_eviction_demoted_layers_total",
+                "Amount of layers successfully demoted to the slow tier instead of being evicted"
+            )
+            .unwrap();
+
+            let layers_promoted = register_int_counter!(
+                "pageserver_disk_usage_based_eviction_promoted_layers_total",
+                "Amount of layers successfully promoted from the slow tier"
+            )
+            .unwrap();
+
             /*BEGIN_HADRON */
             let bytes_evicted = register_int_counter!(
                 "pageserver_disk_usage_based_eviction_evicted_bytes_total",
@@ -4482,6 +4512,8 @@ fn default() -> Self {
                 layers_collected,
                 layers_selected,
                 layers_evicted,
+                layers_demoted,
+                layers_promoted,
                 bytes_evicted,
             }
         }
@@ -26,7 +26,7 @@
     PageServiceProtocolPipelinedBatchingStrategy, PageServiceProtocolPipelinedExecutionStrategy,
 };
 use pageserver_api::key::rel_block_to_key;
-use pageserver_api::models::{PageTraceEvent, TenantState};
+use pageserver_api::models::{BasebackupCompression, PageTraceEvent, TenantState};
 use pageserver_api::pagestream_api::{
     self, PagestreamBeMessage, PagestreamDbSizeRequest, PagestreamDbSizeResponse,
     PagestreamErrorResponse, PagestreamExistsRequest, PagestreamExistsResponse,
@@ -2670,7 +2670,7 @@ async fn handle_basebackup_request<IO>(
         lsn: Option<Lsn>,
         prev_lsn: Option<Lsn>,
         full_backup: bool,
-        gzip: bool,
+        compression: Option<BasebackupCompression>,
         replica: bool,
         ctx: &RequestContext,
     ) -> Result<(), QueryError>
@@ -2740,7 +2740,7 @@ async fn handle_basebackup_request<IO>(
             let mut writer = BufWriter::new(pgb.copyout_writer());
 
             let cached = timeline
-                .get_cached_basebackup_if_enabled(lsn, prev_lsn, full_backup, replica, gzip)
+                .get_cached_basebackup_if_enabled(lsn, prev_lsn, full_backup, replica, compression)
                 .await;
 
             if let Some(mut cached) = cached {
@@ -2762,7 +2762,7 @@ async fn handle_basebackup_request<IO>(
                     // startup. For an empty database, we get <100KB with this method. The
                     // Level::Best compression method gives us <20KB, but maybe we should add
                     // basebackup caching on compute shutdown first.
-                    gzip.then_some(async_compression::Level::Fastest),
+                    compression.map(|c| (c, async_compression::Level::Fastest)),
                     &ctx,
                 )
                 .await?;
@@ -2810,13 +2810,13 @@ fn check_permission(&self, tenant_id: Option<TenantId>) -> Result<(), QueryError
     }
 }
 
-/// `basebackup tenant timeline [lsn] [--gzip] [--replica]`
+/// `basebackup tenant timeline [lsn] [--gzip | --zstd] [--replica]`
 #[derive(Debug, Clone, Eq, PartialEq)]
 struct BaseBackupCmd {
     tenant_id: TenantId,
     timeline_id: TimelineId,
     lsn: Option<Lsn>,
-    gzip: bool,
+    compression: Option<BasebackupCompression>,
     replica: bool,
 }
 
@@ -2948,16 +2948,26 @@ fn parse(query: &str) -> anyhow::Result<Self> {
             flags_parse_from = 2;
         }
 
-        let mut gzip = false;
+        let mut compression = None;
         let mut replica = false;
 
         for &param in &parameters[flags_parse_from..] {
             match param {
-                "--gzip" => {
-                    if gzip {
-                        bail!("duplicate parameter for basebackup command: {param}")
+                "--gzip" | "--zstd" => {
+                    if param == "--zstd" {
+                        // Lets tests emulate a pageserver that predates zstd basebackups.
+                        fail::fail_point!("basebackup-reject-zstd", |_| Err(anyhow::anyhow!(
+                            "invalid parameter for basebackup command: {param}"
+                        )));
+                    }
+                    if compression.is_some() {
+                        bail!("duplicate compression parameter for basebackup command: {param}")
                     }
-                    gzip = true
+                    compression = Some(if param == "--gzip" {
+                        BasebackupCompression::Gzip
+                    } else {
+                        BasebackupCompression::Zstd
+                    });
                 }
                 "--replica" => {
                     if replica {
@@ -2972,7 +2982,7 @@ fn parse(query: &str) -> anyhow::Result<Self> {
             tenant_id,
             timeline_id,
             lsn,
-            gzip,
+            compression,
             replica,
         })
     }
@@ -3186,7 +3196,7 @@ async fn process_query(
                 tenant_id,
                 timeline_id,
                 lsn,
-                gzip,
+                compression,
                 replica,
             }) => {
                 tracing::Span::current()
@@ -3207,7 +3217,7 @@ async fn process_query(
                         lsn,
                         None,
                         false,
-                        gzip,
+                        compression,
                         replica,
                         &ctx,
                     )
@@ -3244,7 +3254,7 @@ async fn process_query(
                     lsn,
                     prev_lsn,
                     true,
-                    false,
+                    None,
                     false,
                     &ctx,
                 )
@@ -3755,6 +3765,16 @@ async fn get_base_backup(
             return Err(tonic::Status::failed_precondition("timeline is archived"));
         }
         let req: page_api::GetBaseBackupRequest = req.into_inner().try_into()?;
+        if matches!(req.compression, page_api::BaseBackupCompression::Zstd) {
+            // Lets tests emulate a pageserver that predates zstd basebackups.
+            fail::fail_point!("basebackup-reject-zstd", |_| Err(
+                page_api::ProtocolError::invalid(
+                    "compression",
+                    proto::BaseBackupCompression::Unknown
+                )
+                .into()
+            ));
+        }
 
         span_record!(lsn=?req.lsn);
 
@@ -3786,24 +3806,15 @@ async fn get_base_backup(
         let jh = tokio::spawn(async move {
             let _gate_guard = gate_guard; // keep gate open until task completes
 
-            let gzip_level = match req.compression {
+            let compression = match req.compression {
                 page_api::BaseBackupCompression::None => None,
-                // NB: using fast compression because it's on the critical path for compute
-                // startup. For an empty database, we get <100KB with this method. The
-                // Level::Best compression method gives us <20KB, but maybe we should add
-                // basebackup caching on compute shutdown first.
-                page_api::BaseBackupCompression::Gzip => Some(async_compression::Level::Fastest),
+                page_api::BaseBackupCompression::Gzip => Some(BasebackupCompression::Gzip),
+                page_api::BaseBackupCompression::Zstd => Some(BasebackupCompression::Zstd),
             };
 
             // Check for a cached basebackup.
             let cached = timeline
-                .get_cached_basebackup_if_enabled(
-                    req.lsn,
-                    None,
-                    req.full,
-                    req.replica,
-                    gzip_level.is_some(),
-                )
+                .get_cached_basebackup_if_enabled(req.lsn, None, req.full, req.replica, compression)
                 .await;
 
             let result = if let Some(mut cached) = cached {
@@ -3820,7 +3831,11 @@ async fn get_base_backup(
                     None,
                     req.full,
                     req.replica,
-                    gzip_level,
+                    // NB: using fast compression because it's on the critical path for compute
+                    // startup. For an empty database, we get <100KB with this method. The
+                    // Level::Best compression method gives us <20KB, but maybe we should add
+                    // basebackup caching on compute shutdown first.
+                    compression.map(|c| (c, async_compression::Level::Fastest)),
                     &ctx,
                 )
                 .instrument(span) // propagate request span
@@ -4467,7 +4482,7 @@ fn pageservice_cmd_parse() {
                 tenant_id,
                 timeline_id,
                 lsn: None,
-                gzip: false,
+                compression: None,
                 replica: false
             })
         );
@@ -4479,7 +4494,19 @@ fn pageservice_cmd_parse() {
                 tenant_id,
                 timeline_id,
                 lsn: None,
-                gzip: true,
+                compression: Some(BasebackupCompression::Gzip),
+                replica: false
+            })
+        );
+        let cmd =
+            PageServiceCmd::parse(&format!("basebackup {tenant_id} {timeline_id} --zstd")).unwrap();
+        assert_eq!(
+            cmd,
+            PageServiceCmd::BaseBackup(BaseBackupCmd {
+                tenant_id,
+                timeline_id,
+                lsn: None,
+                compression: Some(BasebackupCompression::Zstd),
                 replica: false
             })
         );
@@ -4491,7 +4518,7 @@ fn pageservice_cmd_parse() {
                 tenant_id,
                 timeline_id,
                 lsn: None,
-                gzip: false,
+                compression: None,
                 replica: false
             })
         );
@@ -4503,7 +4530,7 @@ fn pageservice_cmd_parse() {
                 tenant_id,
                 timeline_id,
                 lsn: Some(Lsn::from_str("0/16ABCDE").unwrap()),
-                gzip: false,
+                compression: None,
                 replica: false
             })
         );
@@ -4517,7 +4544,7 @@ fn pageservice_cmd_parse() {
                 tenant_id,
                 timeline_id,
                 lsn: None,
-                gzip: true,
+                compression: Some(BasebackupCompression::Gzip),
                 replica: true
             })
         );
@@ -4531,7 +4558,7 @@ fn pageservice_cmd_parse() {
                 tenant_id,
                 timeline_id,
                 lsn: Some(Lsn::from_str("0/16ABCDE").unwrap()),
-                gzip: true,
+                compression: Some(BasebackupCompression::Gzip),
                 replica: true
             })
         );
@@ -4606,6 +4633,10 @@ fn pageservice_cmd_err_handling() {
             "basebackup {tenant_id} {timeline_id} --gzip --gzip"
         ));
         assert!(cmd.is_err());
+        let cmd = PageServiceCmd::parse(&format!(
+            "basebackup {tenant_id} {timeline_id} --gzip --zstd"
+        ));
+        assert!(cmd.is_err());
         let cmd = PageServiceCmd::parse(&format!(
             "basebackup {tenant_id} {timeline_id} --gzip --unknown"
         ));
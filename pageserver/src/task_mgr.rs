@@ -382,6 +382,11 @@ pub enum TaskKind {
 
     ImportPgdata,
 
+    ExportPgdata,
+
+    /// See [`crate::tenant::timeline::layer_rewrite`].
+    LayerRewrite,
+
     /// Background task of [`crate::basebackup_cache::BasebackupCache`].
     /// Prepares basebackups and clears outdated entries.
     BasebackupCache,
@@ -128,6 +128,7 @@
 pub mod storage_layer;
 
 pub mod checks;
+pub mod compaction_scheduler;
 pub mod config;
 pub mod mgr;
 pub mod secondary;
@@ -137,6 +138,7 @@
 pub(crate) mod timeline;
 
 pub mod size;
+pub(crate) mod size_limits;
 
 mod gc_block;
 mod gc_result;
@@ -546,6 +548,10 @@ pub struct OffloadedTimeline {
     /// Present for future flattening deliberations.
     pub archived_at: NaiveDateTime,
 
+    /// When the timeline was soft-deleted, see [`DeleteTimelineFlow::run_soft`]. Soft-deleted
+    /// timelines are hidden from the API, and purged once the delete grace period has passed.
+    soft_deleted_at: std::sync::Mutex<Option<NaiveDateTime>>,
+
     /// Prevent two tasks from deleting the timeline at the same time. If held, the
     /// timeline is being deleted. If 'true', the timeline has already been deleted.
     pub delete_progress: TimelineDeleteProgress,
@@ -597,12 +603,14 @@ fn from_timeline(timeline: &Timeline) -> Result<Self, UploadQueueNotReadyError>
             .remote_client
             .archived_at_stopped_queue()?
             .expect("must be called on an archived timeline");
+        let soft_deleted_at = timeline.remote_client.soft_deleted_at_stopped_queue()?;
         Ok(Self {
             tenant_shard_id: timeline.tenant_shard_id,
             timeline_id: timeline.timeline_id,
             ancestor_timeline_id,
             ancestor_retain_lsn,
             archived_at,
+            soft_deleted_at: std::sync::Mutex::new(soft_deleted_at),
 
             delete_progress: timeline.delete_progress.clone(),
             deleted_from_ancestor: AtomicBool::new(false),
@@ -618,6 +626,7 @@ fn from_manifest(tenant_shard_id: TenantShardId, manifest: &OffloadedTimelineMan
             ancestor_timeline_id,
             ancestor_retain_lsn,
             archived_at,
+            soft_deleted_at,
         } = *manifest;
         Self {
             tenant_shard_id,
@@ -625,6 +634,7 @@ fn from_manifest(tenant_shard_id: TenantShardId, manifest: &OffloadedTimelineMan
             ancestor_timeline_id,
             ancestor_retain_lsn,
             archived_at,
+            soft_deleted_at: std::sync::Mutex::new(soft_deleted_at),
             delete_progress: TimelineDeleteProgress::default(),
             deleted_from_ancestor: AtomicBool::new(false),
             _metrics_guard: OffloadedTimelineMetricsGuard::new(),
@@ -643,8 +653,15 @@ fn manifest(&self) -> OffloadedTimelineManifest {
             ancestor_timeline_id: *ancestor_timeline_id,
             ancestor_retain_lsn: *ancestor_retain_lsn,
             archived_at: *archived_at,
+            soft_deleted_at: self.soft_deleted_at(),
         }
     }
+    pub(crate) fn soft_deleted_at(&self) -> Option<NaiveDateTime> {
+        *self.soft_deleted_at.lock().unwrap()
+    }
+    pub(crate) fn set_soft_deleted_at(&self, soft_deleted_at: Option<NaiveDateTime>) {
+        *self.soft_deleted_at.lock().unwrap() = soft_deleted_at;
+    }
     /// Delete this timeline's retain_lsn from its ancestor, if present in the given tenant
     fn delete_from_ancestor_with_timelines(
         &self,
@@ -2363,7 +2380,10 @@ pub(crate) async fn apply_timeline_archival_config(
 
             let Some(timeline) = timelines.get(&timeline_id) else {
                 let offloaded_timelines = self.timelines_offloaded.lock().unwrap();
-                let Some(offloaded) = offloaded_timelines.get(&timeline_id) else {
+                let Some(offloaded) = offloaded_timelines
+                    .get(&timeline_id)
+                    .filter(|offloaded| offloaded.soft_deleted_at().is_none())
+                else {
                     return Err(TimelineArchivalError::NotFound);
                 };
                 if new_state == TimelineArchivalState::Archived {
@@ -2447,6 +2467,7 @@ pub fn get_offloaded_timeline(
             .lock()
             .unwrap()
             .get(&timeline_id)
+            .filter(|offloaded| offloaded.soft_deleted_at().is_none())
             .map(Arc::clone)
             .ok_or(GetTimelineError::NotFound {
                 tenant_id: self.tenant_shard_id,
@@ -2506,7 +2527,7 @@ pub fn list_importing_timelines(&self) -> Vec<Arc<ImportingTimeline>> {
             .collect()
     }
 
-    /// Lists timelines the tenant manages, including offloaded ones.
+    /// Lists timelines the tenant manages, including offloaded ones, but not soft-deleted ones.
     ///
     /// It's up to callers to omit certain timelines that are not considered ready for use.
     pub fn list_timelines_and_offloaded(
@@ -2524,6 +2545,7 @@ pub fn list_timelines_and_offloaded(
             .lock()
             .unwrap()
             .values()
+            .filter(|offloaded| offloaded.soft_deleted_at().is_none())
             .map(Arc::clone)
             .collect();
         (timelines, offloaded)
@@ -3061,11 +3083,78 @@ pub(crate) async fn delete_timeline(
         self: Arc<Self>,
         timeline_id: TimelineId,
     ) -> Result<(), DeleteTimelineError> {
-        DeleteTimelineFlow::run(&self, timeline_id).await?;
+        if self.get_timeline_delete_grace_period().is_some() {
+            DeleteTimelineFlow::run_soft(&self, timeline_id).await?;
+        } else {
+            DeleteTimelineFlow::run(&self, timeline_id).await?;
+        }
 
         Ok(())
     }
 
+    /// Restores a soft-deleted timeline, see [`DeleteTimelineFlow::run_soft`]. The timeline is
+    /// restored as it was persisted at the time of deletion.
+    pub(crate) async fn undelete_timeline(
+        self: &Arc<Self>,
+        timeline_id: TimelineId,
+        broker_client: storage_broker::BrokerClientChannel,
+        ctx: RequestContext,
+    ) -> Result<(), TimelineArchivalError> {
+        info!("undeleting timeline");
+        DeleteTimelineFlow::undelete(self, timeline_id, broker_client, ctx).await
+    }
+
+    /// Finishes soft-deletions that were interrupted before the timeline was offloaded, and purges
+    /// soft-deleted timelines whose grace period has passed.
+    async fn purge_soft_deleted_timelines(self: &Arc<Self>) {
+        let interrupted = self
+            .timelines
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|tli| tli.remote_client.is_soft_deleted() == Some(true))
+            .map(|tli| tli.timeline_id)
+            .collect_vec();
+        for timeline_id in interrupted {
+            let span = info_span!("timeline_soft_delete", %timeline_id);
+            if let Err(e) = DeleteTimelineFlow::run_soft(self, timeline_id)
+                .instrument(span)
+                .await
+            {
+                warn!(%timeline_id, "failed to finish soft-deletion: {e:#}");
+            }
+        }
+
+        // A grace period that was disabled in the meantime doesn't prevent purging.
+        let grace_period = self.get_timeline_delete_grace_period().unwrap_or_default();
+        let grace_period =
+            chrono::Duration::from_std(grace_period).unwrap_or(chrono::Duration::MAX);
+        let now = chrono::Utc::now().naive_utc();
+        let expired = self
+            .timelines_offloaded
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|offloaded| {
+                offloaded.soft_deleted_at().is_some_and(|soft_deleted_at| {
+                    soft_deleted_at
+                        .checked_add_signed(grace_period)
+                        .is_some_and(|purge_at| purge_at <= now)
+                })
+            })
+            .map(|offloaded| offloaded.timeline_id)
+            .collect_vec();
+        for timeline_id in expired {
+            let span = info_span!("timeline_purge", %timeline_id);
+            if let Err(e) = DeleteTimelineFlow::purge(self, timeline_id)
+                .instrument(span)
+                .await
+            {
+                warn!(%timeline_id, "failed to purge soft-deleted timeline: {e:#}");
+            }
+        }
+    }
+
     /// perform one garbage collection iteration, removing old data files from disk.
     /// this function is periodically called by gc task.
     /// also it can be explicitly requested through page server api 'do_gc' command.
@@ -3127,7 +3216,19 @@ pub(crate) async fn gc_iteration(
             .await
     }
 
-    /// Performs one compaction iteration. Called periodically from the compaction loop. Returns
+    /// Describe why [`Self::gc_iteration`] would currently skip GC for this tenant, if it would.
+    pub(crate) fn gc_blocked_by(&self) -> Option<String> {
+        let conf = self.tenant_conf.load();
+        if !conf.location.may_delete_layers_hint() {
+            return Some(format!("location state {:?}", conf.location));
+        }
+        if conf.is_gc_blocked_by_lsn_lease_deadline() {
+            return Some("lsn lease deadline is not reached".to_string());
+        }
+        self.gc_block.summary().map(|reasons| reasons.to_string())
+    }
+
+    /// Performs one compaction iteration. Called by the compaction scheduler. Returns
     /// whether another compaction is needed, if we still have pending work or if we yield for
     /// immediate L0 compaction.
     ///
@@ -3378,7 +3479,7 @@ pub(crate) async fn schedule_compaction(
     }
 
     /// Performs periodic housekeeping, via the tenant housekeeping background task.
-    async fn housekeeping(&self) {
+    async fn housekeeping(self: &Arc<Self>) {
         // Call through to all timelines to freeze ephemeral layers as needed. This usually happens
         // during ingest, but we don't want idle timelines to hold open layers for too long.
         //
@@ -3406,6 +3507,12 @@ async fn housekeeping(&self) {
             }
         }
 
+        self.refresh_size_limits().await;
+
+        if self.tenant_conf.load().location.may_delete_layers_hint() {
+            self.purge_soft_deleted_timelines().await;
+        }
+
         // Shut down walredo if idle.
         const WALREDO_IDLE_TIMEOUT: Duration = Duration::from_secs(180);
         if let Some(ref walredo_mgr) = self.walredo_mgr {
@@ -3416,6 +3523,55 @@ async fn housekeeping(&self) {
         self.feature_resolver.refresh_properties_and_flags(self);
     }
 
+    /// Refreshes the size limit baselines of all active timelines with accurate sizes, see
+    /// [`size_limits`]. The walreceiver enforces the limits between refreshes.
+    async fn refresh_size_limits(&self) {
+        let limits = self.get_size_limits().for_shard(self.shard_identity.count);
+
+        let timelines = self
+            .timelines
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|tli| tli.is_active())
+            .cloned()
+            .collect_vec();
+
+        if limits.is_unlimited() {
+            for timeline in timelines {
+                timeline.set_size_limit_baseline(None);
+            }
+            return;
+        }
+
+        let mut tenant_sizes = size_limits::TimelineSizes::default();
+        let mut timeline_sizes = Vec::with_capacity(timelines.len());
+        for timeline in timelines {
+            // Take the LSN first: WAL ingested while we sum up the layers is then counted twice,
+            // which errs on the side of enforcing the limit.
+            let lsn = timeline.get_last_record_lsn();
+            let sizes = timeline.size_limit_sizes().await;
+            tenant_sizes.logical += sizes.logical;
+            tenant_sizes.physical += sizes.physical;
+            timeline_sizes.push((timeline, lsn, sizes));
+        }
+
+        for (timeline, lsn, sizes) in timeline_sizes {
+            let baseline = size_limits::IngestBaseline {
+                limits,
+                physical: sizes.physical,
+                lsn,
+                others: size_limits::TimelineSizes {
+                    logical: tenant_sizes.logical - sizes.logical,
+                    physical: tenant_sizes.physical - sizes.physical,
+                },
+                shard_count: self.shard_identity.count,
+            };
+            let span = info_span!("size_limits", timeline_id = %timeline.timeline_id);
+            span.in_scope(|| timeline.set_size_limit_baseline(Some(baseline)));
+        }
+    }
+
     pub fn timeline_has_no_attached_children(&self, timeline_id: TimelineId) -> bool {
         let timelines = self.timelines.lock().unwrap();
         !timelines
@@ -4203,6 +4359,45 @@ pub fn get_min_resident_size_override(&self) -> Option<u64> {
             .or(self.conf.default_tenant_conf.min_resident_size_override)
     }
 
+    pub fn get_eviction_weight_pct(&self) -> u32 {
+        let tenant_conf = self.tenant_conf.load().tenant_conf.clone();
+        tenant_conf
+            .eviction_weight_pct
+            .unwrap_or(self.conf.default_tenant_conf.eviction_weight_pct)
+    }
+
+    pub(crate) fn get_size_limits(&self) -> size_limits::SizeLimits {
+        let tenant_conf = self.tenant_conf.load().tenant_conf.clone();
+        let defaults = &self.conf.default_tenant_conf;
+        size_limits::SizeLimits {
+            timeline_logical: tenant_conf
+                .timeline_logical_size_limit
+                .or(defaults.timeline_logical_size_limit),
+            timeline_physical: tenant_conf
+                .timeline_physical_size_limit
+                .or(defaults.timeline_physical_size_limit),
+            tenant_logical: tenant_conf
+                .tenant_logical_size_limit
+                .or(defaults.tenant_logical_size_limit),
+            tenant_physical: tenant_conf
+                .tenant_physical_size_limit
+                .or(defaults.tenant_physical_size_limit),
+        }
+    }
+
+    /// How long soft-deleted timelines are kept, or None if timelines are deleted right away.
+    pub fn get_timeline_delete_grace_period(&self) -> Option<Duration> {
+        let tenant_conf = self.tenant_conf.load().tenant_conf.clone();
+        let grace_period = tenant_conf
+            .timeline_delete_grace_period
+            .unwrap_or(self.conf.default_tenant_conf.timeline_delete_grace_period);
+        if grace_period.is_zero() {
+            None
+        } else {
+            Some(grace_period)
+        }
+    }
+
     pub fn get_heatmap_period(&self) -> Option<Duration> {
         let tenant_conf = self.tenant_conf.load().tenant_conf.clone();
         let heatmap_period = tenant_conf
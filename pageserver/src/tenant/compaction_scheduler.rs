@@ -0,0 +1,824 @@
+//! Per-node compaction scheduler.
+//!
+//! Rather than having each tenant shard compact in its own loop, which can saturate the node's CPU
+//! and disks when it hosts many busy shards, tenant shards register with the scheduler when they
+//! activate, and a single scheduler task decides which of them compacts next within a global
+//! budget:
+//!
+//! * At most `max_concurrent_compactions` tenant shards compact at the same time.
+//! * The L0 bytes of every tenant shard that starts compacting are charged against
+//!   `max_l0_bytes_per_second`, since L0 compaction reads and rewrites all L0 layers.
+//!
+//! A tenant shard is due for compaction once per `compaction_period`, when a layer flush triggers
+//! L0 compaction, when the previous iteration left pending work, or when a timeline has reached
+//! `compaction_threshold` L0 layers. Due tenant shards compact in order of urgency, see
+//! [`CompactionUrgency::score`]. The urgency doesn't make a tenant shard due by itself: below the
+//! threshold, L0 compaction does nothing, so the urgency wouldn't change and the tenant shard
+//! would be due again right away.
+//!
+//! Urgency depends on the layer maps, so it is only re-collected for tenant shards whose layer maps
+//! changed since: when they register, when a timeline flushes a layer (see [`urgency_changed`]),
+//! and when a compaction iteration finishes. Other tenant shards cost the scheduler tick a few
+//! atomic loads, no matter how many timelines they have.
+//!
+//! The queue can be inspected via `GET /v1/compaction_queue`.
+
+use std::cmp::{Ordering, max};
+use std::collections::HashMap;
+use std::num::NonZeroU64;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use futures::FutureExt;
+use once_cell::sync::Lazy;
+use pageserver_api::models::{CompactionQueueEntry, CompactionQueueInfo, CompactionQueueState};
+use pageserver_api::shard::TenantShardId;
+use rand::Rng;
+use tokio::sync::Notify;
+use tokio_util::sync::CancellationToken;
+use tracing::*;
+use utils::backoff::exponential_backoff_duration;
+use utils::completion::Barrier;
+use utils::id::TimelineId;
+
+use crate::CancellableTask;
+use crate::config::PageServerConf;
+use crate::context::{DownloadBehavior, RequestContext};
+use crate::task_mgr::{self, BACKGROUND_RUNTIME, TOKIO_WORKER_THREADS, TaskKind};
+use crate::tenant::TenantShard;
+use crate::tenant::tasks::{BackgroundLoopKind, Iteration, IterationResult, log_compaction_error};
+use crate::tenant::timeline::compaction::CompactionOutcome;
+
+/// How often the scheduler re-evaluates the queue, unless woken up by a finished compaction.
+const TICK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Layers visited per GetPage read at which read amplification weighs as much as reaching the
+/// compaction threshold.
+const READ_AMP_TARGET: f64 = 20.0;
+
+/// The IO budget may be overdrawn by this much, to allow bursts after idle periods.
+const IO_BURST: Duration = Duration::from_secs(1);
+
+const BASE_BACKOFF_SECS: f64 = 1.0;
+const MAX_BACKOFF_SECS: f64 = 300.0;
+
+static SCHEDULER: Lazy<CompactionScheduler> = Lazy::new(|| CompactionScheduler {
+    inner: Mutex::new(Inner {
+        budget: Budget::new(default_max_concurrent(), None, Instant::now()),
+        tenants: HashMap::new(),
+        schedules: HashMap::new(),
+    }),
+    wake: Notify::new(),
+});
+
+/// Like the background task semaphores, use 3/4 of the Tokio threads to avoid blocking all
+/// threads with CPU-heavy compaction.
+fn default_max_concurrent() -> usize {
+    max(
+        1,
+        (TOKIO_WORKER_THREADS.get() * 3).checked_div(4).unwrap_or(0),
+    )
+}
+
+/// Urgency inputs of a single timeline, see `Timeline::compaction_urgency`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub(crate) struct CompactionUrgency {
+    pub(crate) l0_count: usize,
+    pub(crate) l0_bytes: u64,
+    pub(crate) compaction_threshold: usize,
+    /// `l0_flush_stall_threshold`, or `l0_flush_delay_threshold` if stalls are disabled.
+    pub(crate) l0_backpressure_threshold: Option<usize>,
+    pub(crate) layers_per_read: f64,
+}
+
+impl CompactionUrgency {
+    /// Returns the urgency score and a description of its largest component. The score orders
+    /// tenant shards that are due, and is the sum of:
+    ///
+    /// * The L0 count relative to `compaction_threshold`, so L0 compaction alone is due at 1.0.
+    /// * The proximity to L0 flush backpressure: 1.0 when the L0 count is `compaction_threshold`
+    ///   layers away from the backpressure threshold, doubling as the distance halves.
+    /// * The layers visited per read relative to [`READ_AMP_TARGET`].
+    pub(crate) fn score(&self) -> (f64, String) {
+        let threshold = self.compaction_threshold.max(1) as f64;
+        let l0 = (
+            self.l0_count as f64 / threshold,
+            format!(
+                "{} L0 layers, compaction threshold {}",
+                self.l0_count, self.compaction_threshold
+            ),
+        );
+        let backpressure = match self.l0_backpressure_threshold {
+            Some(limit) => {
+                let headroom = limit.saturating_sub(self.l0_count) as f64;
+                (
+                    threshold / headroom.max(0.5),
+                    format!("{} L0 layers, flush backpressure at {limit}", self.l0_count),
+                )
+            }
+            None => (0.0, String::new()),
+        };
+        let read_amp = (
+            self.layers_per_read / READ_AMP_TARGET,
+            format!("{:.1} layers visited per read", self.layers_per_read),
+        );
+
+        let score = l0.0 + backpressure.0 + read_amp.0;
+        let (_, reason) = [l0, backpressure, read_amp]
+            .into_iter()
+            .max_by(|a, b| a.0.total_cmp(&b.0))
+            .expect("not empty");
+        (score, reason)
+    }
+
+    /// Whether L0 compaction would do anything, which makes compaction due regardless of the
+    /// compaction period.
+    pub(crate) fn l0_compaction_due(&self) -> bool {
+        self.l0_count >= self.compaction_threshold.max(1)
+    }
+}
+
+/// Urgency of a tenant shard, which is that of its most urgent timeline.
+#[derive(Debug, Clone, Default)]
+struct TenantUrgency {
+    timeline_id: Option<TimelineId>,
+    urgency: CompactionUrgency,
+    score: f64,
+    reason: String,
+    /// L0 bytes across all timelines, charged against the IO budget.
+    l0_bytes: u64,
+    /// Some timeline reached its L0 compaction threshold.
+    l0_compaction_due: bool,
+}
+
+impl TenantUrgency {
+    async fn collect(tenant: &TenantShard) -> Self {
+        let timelines = tenant
+            .timelines
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|timeline| timeline.is_active())
+            .cloned()
+            .collect::<Vec<_>>();
+
+        let mut result = TenantUrgency::default();
+        for timeline in timelines {
+            result.add(timeline.timeline_id, timeline.compaction_urgency().await);
+        }
+        result
+    }
+
+    fn add(&mut self, timeline_id: TimelineId, urgency: CompactionUrgency) {
+        let (score, reason) = urgency.score();
+        self.l0_bytes += urgency.l0_bytes;
+        self.l0_compaction_due |= urgency.l0_compaction_due();
+        if self.timeline_id.is_none() || score > self.score {
+            self.timeline_id = Some(timeline_id);
+            self.urgency = urgency;
+            self.score = score;
+            self.reason = reason;
+        }
+    }
+}
+
+struct CompactionScheduler {
+    inner: Mutex<Inner>,
+    /// Wakes up the scheduler when a compaction finishes.
+    wake: Notify,
+}
+
+struct Inner {
+    budget: Budget,
+    tenants: HashMap<TenantShardId, Registration>,
+    schedules: HashMap<TenantShardId, Schedule>,
+}
+
+struct Registration {
+    tenant: Arc<TenantShard>,
+    can_start: Option<Barrier>,
+}
+
+impl Registration {
+    fn is_active(&self) -> bool {
+        self.tenant.is_active() && self.can_start.as_ref().is_none_or(Barrier::is_ready)
+    }
+}
+
+/// The global compaction budget.
+#[derive(Debug)]
+struct Budget {
+    max_concurrent: usize,
+    max_l0_bytes_per_second: Option<NonZeroU64>,
+    running: usize,
+    /// The IO budget is exhausted until then. Starting a compaction pushes this forward by the
+    /// time it takes to compact its L0 bytes at `max_l0_bytes_per_second`.
+    io_busy_until: Instant,
+}
+
+impl Budget {
+    fn new(
+        max_concurrent: usize,
+        max_l0_bytes_per_second: Option<NonZeroU64>,
+        now: Instant,
+    ) -> Self {
+        Self {
+            max_concurrent,
+            max_l0_bytes_per_second,
+            running: 0,
+            io_busy_until: now,
+        }
+    }
+
+    /// How long until the IO budget allows starting another compaction.
+    fn io_wait(&self, now: Instant) -> Duration {
+        self.io_busy_until.saturating_duration_since(now + IO_BURST)
+    }
+
+    fn try_start(&mut self, now: Instant, l0_bytes: u64) -> bool {
+        if self.running >= self.max_concurrent || self.io_wait(now) > Duration::ZERO {
+            return false;
+        }
+        self.running += 1;
+        if let Some(rate) = self.max_l0_bytes_per_second {
+            let cost = Duration::from_secs_f64(l0_bytes as f64 / rate.get() as f64);
+            self.io_busy_until = max(self.io_busy_until, now) + cost;
+        }
+        true
+    }
+}
+
+/// The scheduling state of a tenant shard.
+#[derive(Debug, Clone)]
+struct Schedule {
+    state: CompactionQueueState,
+    /// The next periodic run, or retry when in backoff.
+    next_run: Instant,
+    /// L0 compaction was triggered by a layer flush, or the previous iteration has pending work.
+    triggered: bool,
+    error_run: u32,
+    running_since: Option<Instant>,
+    urgency: TenantUrgency,
+    /// The layer maps changed since `urgency` was collected.
+    urgency_stale: bool,
+}
+
+impl Schedule {
+    fn new(next_run: Instant) -> Self {
+        Self {
+            state: CompactionQueueState::Inactive,
+            next_run,
+            triggered: false,
+            error_run: 0,
+            running_since: None,
+            urgency: TenantUrgency::default(),
+            urgency_stale: true,
+        }
+    }
+
+    fn update_state(&mut self, now: Instant, active: bool, period: Duration) {
+        self.state = if self.running_since.is_some() {
+            CompactionQueueState::Running
+        } else if period == Duration::ZERO {
+            CompactionQueueState::Disabled
+        } else if !active {
+            CompactionQueueState::Inactive
+        } else if self.error_run > 0 && now < self.next_run {
+            CompactionQueueState::Backoff
+        } else if self.triggered || now >= self.next_run || self.urgency.l0_compaction_due {
+            CompactionQueueState::Due
+        } else {
+            CompactionQueueState::Waiting
+        };
+    }
+
+    fn reason(&self) -> String {
+        match self.state {
+            CompactionQueueState::Disabled => "compaction_period is 0".to_string(),
+            CompactionQueueState::Inactive => "tenant shard is not active".to_string(),
+            CompactionQueueState::Backoff => format!("{} consecutive errors", self.error_run),
+            _ if self.triggered => format!("L0 compaction triggered, {}", self.urgency.reason),
+            _ if self.urgency.l0_compaction_due => self.urgency.reason.clone(),
+            CompactionQueueState::Running | CompactionQueueState::Due => {
+                format!("periodic run, {}", self.urgency.reason)
+            }
+            CompactionQueueState::Waiting => format!("not urgent, {}", self.urgency.reason),
+        }
+    }
+}
+
+/// Orders schedules the way the queue is worked off: running first, then due ones by decreasing
+/// urgency, then the others by their next run.
+fn queue_order(a: &Schedule, b: &Schedule) -> Ordering {
+    fn rank(state: CompactionQueueState) -> u8 {
+        match state {
+            CompactionQueueState::Running => 0,
+            CompactionQueueState::Due => 1,
+            CompactionQueueState::Waiting => 2,
+            CompactionQueueState::Backoff => 3,
+            CompactionQueueState::Inactive => 4,
+            CompactionQueueState::Disabled => 5,
+        }
+    }
+    rank(a.state)
+        .cmp(&rank(b.state))
+        .then_with(|| match a.state {
+            CompactionQueueState::Due => b
+                .urgency
+                .score
+                .total_cmp(&a.urgency.score)
+                .then(a.next_run.cmp(&b.next_run)),
+            CompactionQueueState::Running => a.running_since.cmp(&b.running_since),
+            _ => a.next_run.cmp(&b.next_run),
+        })
+}
+
+/// Picks the due tenant shards that start compacting now, within the budget.
+fn pick(
+    budget: &mut Budget,
+    schedules: &mut HashMap<TenantShardId, Schedule>,
+    now: Instant,
+) -> Vec<TenantShardId> {
+    let mut due = schedules
+        .iter_mut()
+        .filter(|(_, schedule)| schedule.state == CompactionQueueState::Due)
+        .collect::<Vec<_>>();
+    due.sort_by(|(_, a), (_, b)| queue_order(a, b));
+
+    let mut picked = Vec::new();
+    for (tenant_shard_id, schedule) in due {
+        if !budget.try_start(now, schedule.urgency.l0_bytes) {
+            break;
+        }
+        schedule.state = CompactionQueueState::Running;
+        schedule.running_since = Some(now);
+        picked.push(*tenant_shard_id);
+    }
+    picked
+}
+
+/// Registers an activated tenant shard with the compaction scheduler. It is unregistered when the
+/// tenant shard shuts down.
+pub(crate) fn register(tenant: &Arc<TenantShard>, can_start: Option<&Barrier>) {
+    // Stagger the first compaction across tenants.
+    let period = tenant.get_compaction_period();
+    let delay = rand::rng().random_range(Duration::ZERO..=period);
+
+    let mut inner = SCHEDULER.inner.lock().unwrap();
+    inner.tenants.insert(
+        tenant.tenant_shard_id,
+        Registration {
+            tenant: tenant.clone(),
+            can_start: can_start.cloned(),
+        },
+    );
+    // A previous incarnation of the tenant shard may still be compacting. Its completion is
+    // ignored, but it still counts against the budget until then.
+    let running_since = inner
+        .schedules
+        .get(&tenant.tenant_shard_id)
+        .and_then(|schedule| schedule.running_since);
+    let mut schedule = Schedule::new(Instant::now() + delay);
+    schedule.running_since = running_since;
+    inner.schedules.insert(tenant.tenant_shard_id, schedule);
+}
+
+/// Notifies the scheduler that the layer maps of a tenant shard changed, e.g. because a timeline
+/// flushed an L0 layer, such that its urgency is re-collected on the next tick.
+pub(crate) fn urgency_changed(tenant_shard_id: TenantShardId) {
+    let mut inner = SCHEDULER.inner.lock().unwrap();
+    if let Some(schedule) = inner.schedules.get_mut(&tenant_shard_id) {
+        schedule.urgency_stale = true;
+    }
+}
+
+impl CompactionScheduler {
+    async fn run(&'static self, cancel: CancellationToken) {
+        loop {
+            self.refresh().await;
+            self.start_due();
+            tokio::select! {
+                _ = tokio::time::sleep(TICK_INTERVAL) => {},
+                _ = self.wake.notified() => {},
+                _ = cancel.cancelled() => return,
+            }
+        }
+    }
+
+    /// Drops shut down tenant shards, and updates the state of the others. The urgency is only
+    /// re-collected for tenant shards whose layer maps changed.
+    async fn refresh(&self) {
+        let now = Instant::now();
+        let stale = {
+            let mut inner = self.inner.lock().unwrap();
+            let Inner {
+                tenants, schedules, ..
+            } = &mut *inner;
+            tenants.retain(|tenant_shard_id, registration| {
+                if !registration.tenant.cancel.is_cancelled() {
+                    return true;
+                }
+                // A running compaction removes the schedule when it finishes.
+                let running = schedules
+                    .get(tenant_shard_id)
+                    .is_some_and(|s| s.running_since.is_some());
+                if !running {
+                    schedules.remove(tenant_shard_id);
+                }
+                false
+            });
+
+            let mut stale = Vec::new();
+            for (tenant_shard_id, registration) in tenants.iter() {
+                let schedule = schedules.get_mut(tenant_shard_id).expect("registered");
+                // Running compactions are left alone: they consume the L0 trigger to yield for L0
+                // compaction.
+                if schedule.running_since.is_some() {
+                    continue;
+                }
+                let tenant = &registration.tenant;
+                schedule.triggered |= tenant
+                    .l0_compaction_trigger
+                    .notified()
+                    .now_or_never()
+                    .is_some();
+                let period = tenant.get_compaction_period();
+                let active = registration.is_active();
+                if active && period != Duration::ZERO && schedule.urgency_stale {
+                    schedule.urgency_stale = false;
+                    stale.push((*tenant_shard_id, tenant.clone()));
+                }
+                schedule.update_state(now, active, period);
+            }
+            stale
+        };
+
+        // Collect urgencies without holding the lock, since it waits for layer map locks.
+        let mut updates = Vec::with_capacity(stale.len());
+        for (tenant_shard_id, tenant) in stale {
+            let urgency = TenantUrgency::collect(&tenant).await;
+            updates.push((tenant_shard_id, tenant, urgency));
+        }
+
+        let now = Instant::now();
+        let mut inner = self.inner.lock().unwrap();
+        for (tenant_shard_id, tenant, urgency) in updates {
+            let Some(registration) = inner.tenants.get(&tenant_shard_id) else {
+                continue;
+            };
+            if !Arc::ptr_eq(&registration.tenant, &tenant) {
+                continue;
+            }
+            let active = registration.is_active();
+            let Some(schedule) = inner.schedules.get_mut(&tenant_shard_id) else {
+                continue;
+            };
+            if schedule.running_since.is_some() {
+                continue;
+            }
+            schedule.urgency = urgency;
+            schedule.update_state(now, active, tenant.get_compaction_period());
+        }
+    }
+
+    fn start_due(&'static self) {
+        let now = Instant::now();
+        let mut inner = self.inner.lock().unwrap();
+        let Inner {
+            budget,
+            tenants,
+            schedules,
+        } = &mut *inner;
+        for tenant_shard_id in pick(budget, schedules, now) {
+            let schedule = schedules.get_mut(&tenant_shard_id).expect("picked");
+            schedule.triggered = false;
+            let tenant = tenants[&tenant_shard_id].tenant.clone();
+            self.spawn_compaction(tenant);
+        }
+    }
+
+    fn spawn_compaction(&'static self, tenant: Arc<TenantShard>) {
+        let tenant_shard_id = tenant.tenant_shard_id;
+        task_mgr::spawn(
+            BACKGROUND_RUNTIME.handle(),
+            TaskKind::Compaction,
+            tenant_shard_id,
+            None,
+            &format!("compaction for tenant {tenant_shard_id}"),
+            async move {
+                let cancel = task_mgr::shutdown_token(); // NB: must be in async context
+                self.compact(tenant, cancel)
+                    // If you rename this span, change the RUST_LOG env variable in test_runner/performance/test_branch_creation.py
+                    .instrument(info_span!("compaction_loop", tenant_id = %tenant_shard_id.tenant_id, shard_id = %tenant_shard_id.shard_slug()))
+                    .await;
+                Ok(())
+            },
+        );
+    }
+
+    /// Runs one compaction iteration of a tenant shard, and reports back to the scheduler.
+    async fn compact(&self, tenant: Arc<TenantShard>, cancel: CancellationToken) {
+        // Release the budget also if the iteration panics.
+        let guard = scopeguard::guard(tenant.clone(), |tenant| {
+            self.finish(&tenant, None);
+        });
+
+        let ctx = RequestContext::todo_child(TaskKind::Compaction, DownloadBehavior::Download);
+        let iteration = Iteration {
+            started_at: Instant::now(),
+            period: tenant.get_compaction_period(),
+            kind: BackgroundLoopKind::Compaction,
+        };
+        let IterationResult { output, elapsed } = iteration
+            .run(tenant.compaction_iteration(&cancel, &ctx))
+            .await;
+        scopeguard::ScopeGuard::into_inner(guard);
+
+        match output {
+            Ok(outcome) => {
+                self.finish(&tenant, Some(outcome));
+                // NB: this log entry is recorded by performance tests.
+                debug!(
+                    elapsed_ms = elapsed.as_millis(),
+                    "compaction iteration complete"
+                );
+            }
+            Err(err) => {
+                let retry_info = self.finish(&tenant, None);
+                log_compaction_error(&err, retry_info, cancel.is_cancelled(), false);
+            }
+        }
+    }
+
+    /// Releases the budget of a finished compaction iteration and schedules the next one. `None`
+    /// means that the iteration failed, in which case the number of consecutive failures and the
+    /// retry backoff are returned.
+    fn finish(
+        &self,
+        tenant: &Arc<TenantShard>,
+        outcome: Option<CompactionOutcome>,
+    ) -> Option<(u32, Duration)> {
+        let now = Instant::now();
+        let mut inner = self.inner.lock().unwrap();
+        inner.budget.running -= 1;
+
+        let registration = inner.tenants.get(&tenant.tenant_shard_id);
+        let registered = registration.is_some_and(|r| Arc::ptr_eq(&r.tenant, tenant));
+        if registration.is_none() {
+            // The tenant shard shut down while compacting.
+            inner.schedules.remove(&tenant.tenant_shard_id);
+            return None;
+        }
+        let schedule = inner.schedules.get_mut(&tenant.tenant_shard_id)?;
+        schedule.running_since = None;
+        // Compaction rewrote the layer maps.
+        schedule.urgency_stale = true;
+        if !registered {
+            // The tenant shard was re-attached while compacting, ignore the old incarnation.
+            return None;
+        }
+
+        let retry_info = match outcome {
+            Some(outcome) => {
+                schedule.error_run = 0;
+                schedule.next_run = now + tenant.get_compaction_period();
+                // If there's more compaction work, L0 or not, schedule an immediate run.
+                schedule.triggered = match outcome {
+                    CompactionOutcome::Done | CompactionOutcome::Skipped => false,
+                    CompactionOutcome::YieldForL0 | CompactionOutcome::Pending => true,
+                };
+                None
+            }
+            None => {
+                schedule.error_run += 1;
+                let backoff = exponential_backoff_duration(
+                    schedule.error_run,
+                    BASE_BACKOFF_SECS,
+                    MAX_BACKOFF_SECS,
+                );
+                schedule.next_run = now + backoff;
+                schedule.triggered = false;
+                Some((schedule.error_run, backoff))
+            }
+        };
+        schedule.state = CompactionQueueState::Waiting;
+        drop(inner);
+
+        self.wake.notify_one();
+        retry_info
+    }
+}
+
+/// Returns the compaction queue, in the order it is worked off.
+pub(crate) fn queue_info() -> CompactionQueueInfo {
+    let now = Instant::now();
+    let inner = SCHEDULER.inner.lock().unwrap();
+
+    let mut schedules = inner.schedules.iter().collect::<Vec<_>>();
+    schedules.sort_by(|(_, a), (_, b)| queue_order(a, b));
+
+    let entries = schedules
+        .into_iter()
+        .map(|(tenant_shard_id, schedule)| {
+            let urgency = &schedule.urgency;
+            CompactionQueueEntry {
+                tenant_shard_id: *tenant_shard_id,
+                timeline_id: urgency.timeline_id,
+                state: schedule.state,
+                score: urgency.score,
+                reason: schedule.reason(),
+                l0_count: urgency.urgency.l0_count,
+                l0_bytes: urgency.l0_bytes,
+                compaction_threshold: urgency.urgency.compaction_threshold,
+                l0_backpressure_threshold: urgency.urgency.l0_backpressure_threshold,
+                layers_per_read: urgency.urgency.layers_per_read,
+                next_run_in_ms: match schedule.state {
+                    CompactionQueueState::Waiting | CompactionQueueState::Backoff => {
+                        Some(schedule.next_run.saturating_duration_since(now).as_millis() as u64)
+                    }
+                    _ => None,
+                },
+                running_for_ms: schedule
+                    .running_since
+                    .map(|since| now.duration_since(since).as_millis() as u64),
+                consecutive_errors: schedule.error_run,
+            }
+        })
+        .collect();
+
+    CompactionQueueInfo {
+        max_concurrent_compactions: inner.budget.max_concurrent,
+        max_l0_bytes_per_second: inner.budget.max_l0_bytes_per_second.map(NonZeroU64::get),
+        io_budget_wait_ms: inner.budget.io_wait(now).as_millis() as u64,
+        entries,
+    }
+}
+
+/// Launches the compaction scheduler, which compacts all tenant shards on this node.
+pub fn launch_compaction_scheduler(conf: &'static PageServerConf) -> CancellableTask {
+    let config = &conf.compaction_scheduler;
+    {
+        let mut inner = SCHEDULER.inner.lock().unwrap();
+        inner.budget.max_concurrent = config
+            .max_concurrent_compactions
+            .map(|n| n.get())
+            .unwrap_or_else(default_max_concurrent);
+        inner.budget.max_l0_bytes_per_second = config.max_l0_bytes_per_second;
+        info!(budget = ?inner.budget, "launching compaction scheduler");
+    }
+
+    let cancel = CancellationToken::new();
+    let task =
+        BACKGROUND_RUNTIME.spawn(task_mgr::exit_on_panic_or_error("compaction scheduler", {
+            let cancel = cancel.clone();
+            async move {
+                SCHEDULER.run(cancel).await;
+                anyhow::Ok(())
+            }
+        }));
+
+    CancellableTask { cancel, task }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schedule(score: f64, l0_compaction_due: bool, l0_bytes: u64, now: Instant) -> Schedule {
+        let mut schedule = Schedule::new(now + Duration::from_secs(60));
+        schedule.urgency.score = score;
+        schedule.urgency.l0_compaction_due = l0_compaction_due;
+        schedule.urgency.l0_bytes = l0_bytes;
+        schedule.update_state(now, true, Duration::from_secs(20));
+        schedule
+    }
+
+    #[test]
+    fn urgency_score() {
+        let urgency = CompactionUrgency {
+            l0_count: 5,
+            l0_bytes: 0,
+            compaction_threshold: 10,
+            l0_backpressure_threshold: None,
+            layers_per_read: 0.0,
+        };
+        assert_eq!(urgency.score().0, 0.5);
+
+        // Approaching backpressure dominates.
+        let urgency = CompactionUrgency {
+            l0_count: 18,
+            compaction_threshold: 8,
+            l0_backpressure_threshold: Some(20),
+            ..urgency
+        };
+        let (score, reason) = urgency.score();
+        assert_eq!(score, 2.25 + 4.0);
+        assert!(reason.contains("backpressure"), "{reason}");
+
+        // Reaching backpressure is still finite.
+        let urgency = CompactionUrgency {
+            l0_count: 24,
+            ..urgency
+        };
+        assert_eq!(urgency.score().0, 3.0 + 16.0);
+
+        // Read amplification raises the urgency.
+        let urgency = CompactionUrgency {
+            l0_count: 0,
+            l0_backpressure_threshold: None,
+            layers_per_read: 30.0,
+            ..urgency
+        };
+        let (score, reason) = urgency.score();
+        assert_eq!(score, 1.5);
+        assert!(reason.contains("per read"), "{reason}");
+    }
+
+    #[test]
+    fn pick_most_urgent_within_budget() {
+        let now = Instant::now();
+        let shard = |n: u8| TenantShardId::unsharded(utils::id::TenantId::from_array([n; 16]));
+
+        let mut schedules = HashMap::from([
+            (shard(1), schedule(1.5, true, 0, now)),
+            (shard(2), schedule(8.0, true, 0, now)),
+            (shard(3), schedule(9.0, false, 0, now)), // not due
+            (shard(4), schedule(3.0, true, 0, now)),
+        ]);
+        assert_eq!(schedules[&shard(3)].state, CompactionQueueState::Waiting);
+
+        let mut budget = Budget::new(2, None, now);
+        assert_eq!(
+            pick(&mut budget, &mut schedules, now),
+            vec![shard(2), shard(4)]
+        );
+        assert_eq!(pick(&mut budget, &mut schedules, now), vec![]);
+
+        budget.running = 0;
+        assert_eq!(pick(&mut budget, &mut schedules, now), vec![shard(1)]);
+    }
+
+    #[test]
+    fn io_budget() {
+        let now = Instant::now();
+        let shard = |n: u8| TenantShardId::unsharded(utils::id::TenantId::from_array([n; 16]));
+        let mut schedules = HashMap::from([
+            (shard(1), schedule(3.0, true, 2 << 20, now)),
+            (shard(2), schedule(2.0, true, 1 << 20, now)),
+        ]);
+
+        // 2 MiB at 1 MiB/s exhausts the budget for 2 seconds, minus the burst.
+        let mut budget = Budget::new(10, NonZeroU64::new(1 << 20), now);
+        assert_eq!(pick(&mut budget, &mut schedules, now), vec![shard(1)]);
+        assert_eq!(budget.io_wait(now), Duration::from_secs(1));
+        assert_eq!(pick(&mut budget, &mut schedules, now), vec![]);
+
+        let later = now + Duration::from_secs(1);
+        assert_eq!(budget.io_wait(later), Duration::ZERO);
+        assert_eq!(pick(&mut budget, &mut schedules, later), vec![shard(2)]);
+    }
+
+    /// With default settings, 7 L0 layers score above 1.0 due to the backpressure term, but L0
+    /// compaction won't do anything below the threshold of 10. The shard must not be picked again
+    /// until its next periodic run.
+    #[test]
+    fn below_threshold_waits_for_period() {
+        let now = Instant::now();
+        let period = Duration::from_secs(20);
+        let urgency = CompactionUrgency {
+            l0_count: 7,
+            l0_bytes: 0,
+            compaction_threshold: 10,
+            l0_backpressure_threshold: Some(30),
+            layers_per_read: 30.0,
+        };
+
+        // As left behind by a finished iteration.
+        let mut schedule = Schedule::new(now + period);
+        schedule.urgency.add(TimelineId::generate(), urgency);
+        assert!(schedule.urgency.score >= 1.0);
+        schedule.update_state(now, true, period);
+        assert_eq!(schedule.state, CompactionQueueState::Waiting);
+
+        let shard = TenantShardId::unsharded(utils::id::TenantId::from_array([1; 16]));
+        let mut schedules = HashMap::from([(shard, schedule)]);
+        let mut budget = Budget::new(10, None, now);
+        assert_eq!(pick(&mut budget, &mut schedules, now), vec![]);
+
+        let schedule = schedules.get_mut(&shard).unwrap();
+        schedule.update_state(now + period, true, period);
+        assert_eq!(schedule.state, CompactionQueueState::Due);
+
+        // Reaching the threshold makes it due right away.
+        let mut schedule = Schedule::new(now + period);
+        schedule.urgency.add(
+            TimelineId::generate(),
+            CompactionUrgency {
+                l0_count: 10,
+                ..urgency
+            },
+        );
+        schedule.update_state(now, true, period);
+        assert_eq!(schedule.state, CompactionQueueState::Due);
+    }
+}
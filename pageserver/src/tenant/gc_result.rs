@@ -2,6 +2,7 @@
 use std::time::Duration;
 
 use anyhow::Result;
+use pageserver_api::models::GcRetainReason;
 use serde::Serialize;
 
 ///
@@ -29,6 +30,21 @@ pub struct GcResult {
     pub(crate) doomed_layers: Vec<crate::tenant::storage_layer::Layer>,
 }
 
+impl GcResult {
+    /// Count a layer which GC kept for the given reason.
+    pub(crate) fn add_retained(&mut self, reason: GcRetainReason) {
+        match reason {
+            GcRetainReason::GcHorizon => self.layers_needed_by_cutoff += 1,
+            GcRetainReason::Pitr => self.layers_needed_by_pitr += 1,
+            GcRetainReason::Branch => self.layers_needed_by_branches += 1,
+            GcRetainReason::Lease => self.layers_needed_by_leases += 1,
+            GcRetainReason::NoNewerImage => self.layers_not_updated += 1,
+            // GC doesn't look at layers when it is blocked or skips the timeline.
+            GcRetainReason::GcBlock | GcRetainReason::TimeCutoffPending => {}
+        }
+    }
+}
+
 // helper function for `GcResult`, serializing a `Duration` as an integer number of milliseconds
 fn serialize_duration_as_millis<S>(d: &Duration, serializer: S) -> Result<S::Ok, S::Error>
 where
@@ -234,6 +234,27 @@ async fn safe_rename_tenant_dir(path: impl AsRef<Utf8Path>) -> std::io::Result<U
     Ok(tmp_path)
 }
 
+/// Removes the tenant's directory on the slow layer tier, if there is one, in the background.
+///
+/// Layer files there are only known to the tenant's layers, so this goes along with removing the
+/// tenant's directory in the workdir, see [`crate::tenant::storage_layer::tier`].
+async fn purge_slow_tier_tenant_dir(
+    conf: &PageServerConf,
+    background_purges: &BackgroundPurges,
+    tenant_shard_id: &TenantShardId,
+) {
+    let Some(slow_tier_path) = conf.slow_tier_path(&conf.tenant_path(tenant_shard_id)) else {
+        return;
+    };
+    match safe_rename_tenant_dir(&slow_tier_path).await {
+        Ok(tmp_path) => background_purges.spawn(tmp_path),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => {
+            warn!("Failed to move slow tier tenant directory '{slow_tier_path}': {e}");
+        }
+    }
+}
+
 /// See [`Self::spawn`].
 #[derive(Clone, Default)]
 pub struct BackgroundPurges(tokio_util::task::TaskTracker);
@@ -385,6 +406,62 @@ async fn init_load_generations(
     Ok(Some(generations))
 }
 
+/// Purges the directories on the slow layer tier of tenants which have no directory in the
+/// workdir, because the process stopped before [`purge_slow_tier_tenant_dir`] got to them.
+async fn init_purge_slow_tier_tenant_dirs(
+    conf: &'static PageServerConf,
+    background_purges: &BackgroundPurges,
+) {
+    let tenants_dir = conf.tenants_path();
+    let Some(slow_tenants_dir) = conf.slow_tier_path(&tenants_dir) else {
+        return;
+    };
+
+    let mut dentries = match fs::read_dir(&slow_tenants_dir).await {
+        Ok(dentries) => dentries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return,
+        Err(e) => {
+            warn!("Failed to read slow tier tenants dir '{slow_tenants_dir}': {e}");
+            return;
+        }
+    };
+
+    loop {
+        let dentry = match dentries.next_entry().await {
+            Ok(Some(dentry)) => dentry,
+            Ok(None) => break,
+            Err(e) => {
+                warn!("Failed to read slow tier tenants dir '{slow_tenants_dir}': {e}");
+                break;
+            }
+        };
+        let Ok(path) = Utf8PathBuf::try_from(dentry.path()) else {
+            continue;
+        };
+        let file_name = path.file_name().expect("dir entries have a file name");
+        if fs::try_exists(tenants_dir.join(file_name))
+            .await
+            .unwrap_or(true)
+        {
+            continue;
+        }
+
+        info!("Found slow tier tenant directory without a tenant, removing: {path}");
+        let tmp_path = if crate::is_temporary(&path) {
+            path
+        } else {
+            match safe_rename_tenant_dir(&path).await {
+                Ok(tmp_path) => tmp_path,
+                Err(e) => {
+                    warn!("Failed to move slow tier tenant directory '{path}': {e}");
+                    continue;
+                }
+            }
+        };
+        background_purges.spawn(tmp_path);
+    }
+}
+
 /// Given a directory discovered in the pageserver's tenants/ directory, attempt
 /// to load a tenant config from it.
 ///
@@ -535,6 +612,7 @@ pub async fn init_tenant_mgr(
 
     // Scan local filesystem for attached tenants
     let tenant_configs = init_load_tenant_configs(conf).await;
+    init_purge_slow_tier_tenant_dirs(conf, background_purges).await;
 
     // Determine which tenants are to be secondary or attached, and in which generation
     let tenant_modes = init_load_generations(conf, &tenant_configs, resources, cancel).await?;
@@ -600,6 +678,7 @@ pub async fn init_tenant_mgr(
                             "Failed to move detached tenant directory '{tenant_dir_path}': {e:?}");
                         }
                     };
+                    purge_slow_tier_tenant_dir(conf, background_purges, &tenant_shard_id).await;
 
                     // We deleted local content: move on to next tenant, don't try and spawn this one.
                     continue;
@@ -1381,6 +1460,7 @@ async fn delete_local(
                     format!("local tenant directory {local_tenant_directory:?} rename")
                 })?;
             background_purges.spawn(tmp_dir);
+            purge_slow_tier_tenant_dir(conf, background_purges, tenant_shard_id).await;
             Ok(())
         }
 
@@ -1707,6 +1787,7 @@ pub(crate) async fn do_shard_split(
     /// to avoid the children downloading them again.
     ///
     /// For each resident layer in the parent shard, we will hard link it into all of the child shards.
+    /// Hard links cannot cross the local tiers, so layers on the slow tier are linked within it.
     async fn shard_split_hardlink(
         &self,
         parent_shard: &TenantShard,
@@ -1715,6 +1796,7 @@ async fn shard_split_hardlink(
         debug_assert_current_span_has_tenant_id();
 
         let parent_path = self.conf.tenant_path(parent_shard.get_tenant_shard_id());
+        let parent_slow_path = self.conf.slow_tier_path(&parent_path);
         let (parent_timelines, parent_layers) = {
             let mut parent_layers = Vec::new();
             let timelines = parent_shard.timelines.lock().unwrap().clone();
@@ -1727,11 +1809,18 @@ async fn shard_split_hardlink(
                     .await;
 
                 for layer in layers.likely_resident_layers() {
-                    let relative_path = layer
-                        .local_path()
-                        .strip_prefix(&parent_path)
+                    let local_path = layer.local_path();
+                    let on_slow_tier = parent_slow_path
+                        .as_ref()
+                        .is_some_and(|slow_path| local_path.starts_with(slow_path));
+                    let relative_path = local_path
+                        .strip_prefix(if on_slow_tier {
+                            parent_slow_path.as_ref().unwrap()
+                        } else {
+                            &parent_path
+                        })
                         .context("Removing prefix from parent layer path")?;
-                    parent_layers.push(relative_path.to_owned());
+                    parent_layers.push((on_slow_tier, relative_path.to_owned()));
                 }
             }
 
@@ -1754,7 +1843,15 @@ async fn shard_split_hardlink(
                     .map(|t| self.conf.timeline_path(&child, t)),
             );
 
-            child_prefixes.push(child_prefix);
+            if parent_layers.iter().any(|(on_slow_tier, _)| *on_slow_tier) {
+                create_dirs.extend(parent_timelines.iter().filter_map(|t| {
+                    self.conf
+                        .slow_tier_path(&self.conf.timeline_path(&child, t))
+                }));
+            }
+
+            let child_slow_prefix = self.conf.slow_tier_path(&child_prefix);
+            child_prefixes.push((child_prefix, child_slow_prefix));
         }
 
         // Since we will do a large number of small filesystem metadata operations, batch them into
@@ -1777,15 +1874,24 @@ async fn shard_split_hardlink(
                 }
             }
 
-            for child_prefix in child_prefixes {
+            for (child_prefix, child_slow_prefix) in child_prefixes {
                 tracing::info!(
                     "Hard-linking {} parent layers into child path {}",
                     parent_layers.len(),
                     child_prefix
                 );
-                for relative_layer in &parent_layers {
-                    let parent_path = parent_path.join(relative_layer);
-                    let child_path = child_prefix.join(relative_layer);
+                for (on_slow_tier, relative_layer) in &parent_layers {
+                    let (parent_path, child_path) = if *on_slow_tier {
+                        (
+                            parent_slow_path.as_ref().unwrap().join(relative_layer),
+                            child_slow_prefix.as_ref().unwrap().join(relative_layer),
+                        )
+                    } else {
+                        (
+                            parent_path.join(relative_layer),
+                            child_prefix.join(relative_layer),
+                        )
+                    };
                     if let Err(e) = std::fs::hard_link(&parent_path, &child_path) {
                         match e.kind() {
                             std::io::ErrorKind::AlreadyExists => {}
@@ -2010,6 +2116,7 @@ pub(crate) async fn detach_tenant(
         {
             self.background_purges.spawn(tmp_path);
         }
+        purge_slow_tier_tenant_dir(conf, &self.background_purges, &tenant_shard_id).await;
 
         Ok(())
     }
@@ -216,7 +216,7 @@
 use super::config::AttachedLocationConfig;
 use super::metadata::MetadataUpdate;
 use super::storage_layer::{Layer, LayerName, ResidentLayer};
-use super::timeline::import_pgdata;
+use super::timeline::{export_pgdata, import_pgdata, layer_rewrite};
 use super::upload_queue::{NotInitialized, SetDeletedFlagProgress};
 use super::{DeleteTimelineError, Generation};
 use crate::config::PageServerConf;
@@ -564,6 +564,17 @@ pub(crate) fn is_previous_ancestor_lsn(&self, lsn: Lsn) -> bool {
             .unwrap_or(false)
     }
 
+    /// Returns the ancestor and branch point this timeline was detached from, if it was detached
+    /// and the remote timeline client is currently initialized.
+    pub(crate) fn detached_previous_ancestor(&self) -> Option<(TimelineId, Lsn)> {
+        self.upload_queue
+            .lock()
+            .unwrap()
+            .initialized_mut()
+            .ok()
+            .and_then(|uq| uq.clean.0.lineage.detached_previous_ancestor())
+    }
+
     /// Returns whether the timeline is archived.
     /// Return None if the remote index_part hasn't been downloaded yet.
     pub(crate) fn is_archived(&self) -> Option<bool> {
@@ -585,6 +596,29 @@ pub(crate) fn is_invisible(&self) -> Option<bool> {
             .ok()
     }
 
+    /// Returns whether the timeline is soft-deleted.
+    /// Return None if the remote index_part hasn't been downloaded yet.
+    pub(crate) fn is_soft_deleted(&self) -> Option<bool> {
+        self.upload_queue
+            .lock()
+            .unwrap()
+            .initialized_mut()
+            .map(|q| q.clean.0.soft_deleted_at.is_some())
+            .ok()
+    }
+
+    /// Returns whether the timeline is soft-deleted and was archived by that, see
+    /// [`IndexPart::archived_by_soft_delete`].
+    /// Return None if the remote index_part hasn't been downloaded yet.
+    pub(crate) fn is_archived_by_soft_delete(&self) -> Option<bool> {
+        self.upload_queue
+            .lock()
+            .unwrap()
+            .initialized_mut()
+            .map(|q| q.clean.0.archived_by_soft_delete())
+            .ok()
+    }
+
     /// Returns `Ok(Some(timestamp))` if the timeline has been archived, `Ok(None)` if the timeline hasn't been archived.
     ///
     /// Return Err(_) if the remote index_part hasn't been downloaded yet, or the timeline hasn't been stopped yet.
@@ -599,6 +633,18 @@ pub(crate) fn archived_at_stopped_queue(
             .map_err(|_| UploadQueueNotReadyError)
     }
 
+    /// Like [`Self::archived_at_stopped_queue`], but for the `soft_deleted_at` timestamp.
+    pub(crate) fn soft_deleted_at_stopped_queue(
+        &self,
+    ) -> Result<Option<NaiveDateTime>, UploadQueueNotReadyError> {
+        self.upload_queue
+            .lock()
+            .unwrap()
+            .stopped_mut()
+            .map(|q| q.upload_queue_for_deletion.clean.0.soft_deleted_at)
+            .map_err(|_| UploadQueueNotReadyError)
+    }
+
     fn update_remote_physical_size_gauge(&self, current_remote_index_part: Option<&IndexPart>) {
         let size: u64 = if let Some(current_remote_index_part) = current_remote_index_part {
             current_remote_index_part
@@ -890,6 +936,52 @@ fn need_change(
         Ok(())
     }
 
+    /// Launch an index-file upload operation in the background that marks the timeline as
+    /// soft-deleted. A soft-deleted timeline is always archived: if it isn't yet, it is archived
+    /// with the same timestamp, so that [`Self::schedule_index_upload_for_undelete`] can tell that
+    /// it has to be unarchived again.
+    ///
+    /// Call [`Self::wait_completion`] afterwards to ensure that the change is uploaded.
+    pub(crate) fn schedule_index_upload_for_soft_delete(self: &Arc<Self>) -> anyhow::Result<()> {
+        let mut guard = self.upload_queue.lock().unwrap();
+        let upload_queue = guard.initialized_mut()?;
+
+        if upload_queue.dirty.soft_deleted_at.is_some() {
+            tracing::info!("timeline is already soft-deleted");
+            return Ok(());
+        }
+
+        let now = Utc::now().naive_utc();
+        upload_queue.dirty.soft_deleted_at = Some(now);
+        if upload_queue.dirty.archived_at.is_none() {
+            upload_queue.dirty.archived_at = Some(now);
+        }
+        self.schedule_index_upload(upload_queue);
+
+        Ok(())
+    }
+
+    /// Launch an index-file upload operation in the background that reverts
+    /// [`Self::schedule_index_upload_for_soft_delete`]. Returns whether the timeline was
+    /// soft-deleted.
+    ///
+    /// Call [`Self::wait_completion`] afterwards to ensure that the change is uploaded.
+    pub(crate) fn schedule_index_upload_for_undelete(self: &Arc<Self>) -> anyhow::Result<bool> {
+        let mut guard = self.upload_queue.lock().unwrap();
+        let upload_queue = guard.initialized_mut()?;
+
+        if upload_queue.dirty.soft_deleted_at.is_none() {
+            return Ok(false);
+        }
+        if upload_queue.dirty.archived_by_soft_delete() {
+            upload_queue.dirty.archived_at = None;
+        }
+        upload_queue.dirty.soft_deleted_at = None;
+        self.schedule_index_upload(upload_queue);
+
+        Ok(true)
+    }
+
     /// Shuts the timeline client down, but only if the timeline is archived.
     ///
     /// This function and [`Self::schedule_index_upload_for_timeline_archival_state`] use the
@@ -980,6 +1072,52 @@ pub(crate) fn schedule_index_upload_for_import_pgdata_finalize(
         Ok(())
     }
 
+    /// Launch an index-file upload operation in the background, setting `export_pgdata` field.
+    pub(crate) fn schedule_index_upload_for_export_pgdata_state_update(
+        self: &Arc<Self>,
+        state: export_pgdata::index_part_format::Root,
+    ) -> anyhow::Result<()> {
+        let mut guard = self.upload_queue.lock().unwrap();
+        let upload_queue = guard.initialized_mut()?;
+        upload_queue.dirty.export_pgdata = Some(state);
+        self.schedule_index_upload(upload_queue);
+        Ok(())
+    }
+
+    /// Returns the export state as last scheduled for upload, or None if the upload queue is not
+    /// initialized or the timeline was never exported.
+    pub(crate) fn export_pgdata_state(&self) -> Option<export_pgdata::index_part_format::Root> {
+        self.upload_queue
+            .lock()
+            .unwrap()
+            .initialized_mut()
+            .ok()
+            .and_then(|q| q.dirty.export_pgdata.clone())
+    }
+
+    /// Launch an index-file upload operation in the background, setting `layer_rewrite` field.
+    pub(crate) fn schedule_index_upload_for_layer_rewrite_state_update(
+        self: &Arc<Self>,
+        state: layer_rewrite::index_part_format::Root,
+    ) -> anyhow::Result<()> {
+        let mut guard = self.upload_queue.lock().unwrap();
+        let upload_queue = guard.initialized_mut()?;
+        upload_queue.dirty.layer_rewrite = Some(state);
+        self.schedule_index_upload(upload_queue);
+        Ok(())
+    }
+
+    /// Returns the layer rewrite state as last scheduled for upload, or None if the upload queue
+    /// is not initialized or no rewrite was ever started.
+    pub(crate) fn layer_rewrite_state(&self) -> Option<layer_rewrite::index_part_format::Root> {
+        self.upload_queue
+            .lock()
+            .unwrap()
+            .initialized_mut()
+            .ok()
+            .and_then(|q| q.dirty.layer_rewrite.clone())
+    }
+
     /// Launch an index-file upload operation in the background, setting `gc_compaction_state` field.
     pub(crate) fn schedule_index_upload_for_gc_compaction_state_update(
         self: &Arc<Self>,
@@ -1145,6 +1283,55 @@ pub(crate) async fn schedule_adding_existing_layers_to_index_detach_and_wait(
         Ok(())
     }
 
+    /// Schedules uploading an `index_part.json` with the given metadata and no layers, deleting
+    /// all layers of the timeline, and waits for it to complete.
+    ///
+    /// This is used with `Timeline::reset_onto_backup`: the layers were already copied to the
+    /// backup branch by detach ancestor, which the new metadata points to as the ancestor.
+    pub(crate) async fn schedule_reset_and_wait(
+        self: &Arc<Self>,
+        metadata: &TimelineMetadata,
+    ) -> anyhow::Result<()> {
+        let barrier = {
+            let mut guard = self.upload_queue.lock().unwrap();
+            if let UploadQueue::Stopped(UploadQueueStopped::Deletable(stopped)) = &*guard {
+                // A previous attempt uploaded the reset and fenced the queue, but the tenant was
+                // not reloaded yet.
+                if stopped.upload_queue_for_deletion.clean.0.metadata == *metadata {
+                    return Ok(());
+                }
+            }
+            let upload_queue = guard.initialized_mut()?;
+
+            if upload_queue.clean.0.metadata == *metadata {
+                None
+            } else {
+                upload_queue.dirty.metadata = metadata.clone();
+
+                let names = upload_queue
+                    .dirty
+                    .layer_metadata
+                    .keys()
+                    .cloned()
+                    .collect::<Vec<_>>();
+                let unlinked =
+                    self.schedule_unlinking_of_layers_from_index_part0(upload_queue, names);
+                if unlinked.is_empty() {
+                    // Unlinking schedules the index upload only if there was something to unlink.
+                    self.schedule_index_upload(upload_queue);
+                }
+                self.schedule_deletion_of_unlinked0(upload_queue, unlinked);
+
+                Some(self.schedule_barrier0(upload_queue))
+            }
+        };
+
+        if let Some(barrier) = barrier {
+            Self::wait_completion0(barrier).await?;
+        }
+        Ok(())
+    }
+
     /// Adds a gc blocking reason for this timeline if one does not exist already.
     ///
     /// A retryable step of timeline detach ancestor.
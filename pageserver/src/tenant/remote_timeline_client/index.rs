@@ -17,7 +17,7 @@
 use crate::tenant::Generation;
 use crate::tenant::metadata::TimelineMetadata;
 use crate::tenant::storage_layer::LayerName;
-use crate::tenant::timeline::import_pgdata;
+use crate::tenant::timeline::{export_pgdata, import_pgdata, layer_rewrite};
 
 /// In-memory representation of an `index_part.json` file
 ///
@@ -39,6 +39,13 @@ pub struct IndexPart {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub archived_at: Option<NaiveDateTime>,
 
+    /// Set when the timeline was soft-deleted: it is no longer served, but its remote objects are
+    /// kept until the tenant's `timeline_delete_grace_period` has passed, and it can be undeleted
+    /// until then. Unlike `deleted_at`, this is not a point of no return.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub soft_deleted_at: Option<NaiveDateTime>,
+
     /// This field supports import-from-pgdata ("fast imports" platform feature).
     /// We don't currently use fast imports, so, this field is None for all production timelines.
     /// See <https://github.com/neondatabase/neon/pull/9218> for more information.
@@ -46,6 +53,18 @@ pub struct IndexPart {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub import_pgdata: Option<import_pgdata::index_part_format::Root>,
 
+    /// State of the most recent export of this timeline into an external location, see
+    /// [`export_pgdata`]. Kept after the export finishes so that its outcome can be queried.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub export_pgdata: Option<export_pgdata::index_part_format::Root>,
+
+    /// State of the most recent rewrite of old image layers, see [`layer_rewrite`]. Kept after
+    /// the rewrite finishes so that its outcome can be queried.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub layer_rewrite: Option<layer_rewrite::index_part_format::Root>,
+
     /// Layer filenames and metadata. For an index persisted in remote storage, all layers must
     /// exist in remote storage.
     pub layer_metadata: HashMap<LayerName, LayerFileMetadata>,
@@ -148,11 +167,15 @@ impl IndexPart {
     /// - 13: +gc_compaction
     /// - 14: +marked_invisible_at
     /// - 15: +rel_size_migrated_at
-    const LATEST_VERSION: usize = 15;
+    /// - 16: +export_pgdata
+    /// - 17: +soft_deleted_at
+    /// - 18: +layer_rewrite
+    const LATEST_VERSION: usize = 18;
 
     // Versions we may see when reading from a bucket.
-    pub const KNOWN_VERSIONS: &'static [usize] =
-        &[1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15];
+    pub const KNOWN_VERSIONS: &'static [usize] = &[
+        1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18,
+    ];
 
     pub const FILE_NAME: &'static str = "index_part.json";
 
@@ -163,11 +186,14 @@ pub fn empty(metadata: TimelineMetadata) -> Self {
             disk_consistent_lsn: metadata.disk_consistent_lsn(),
             metadata,
             deleted_at: None,
+            soft_deleted_at: None,
             archived_at: None,
             lineage: Default::default(),
             gc_blocking: None,
             last_aux_file_policy: None,
             import_pgdata: None,
+            export_pgdata: None,
+            layer_rewrite: None,
             rel_size_migration: None,
             l2_lsn: None,
             gc_compaction: None,
@@ -180,6 +206,12 @@ pub fn version(&self) -> usize {
         self.version
     }
 
+    /// Whether the timeline was archived by its soft-deletion rather than before it, in which case
+    /// undeleting it unarchives it again.
+    pub(crate) fn archived_by_soft_delete(&self) -> bool {
+        self.soft_deleted_at.is_some() && self.archived_at == self.soft_deleted_at
+    }
+
     /// If you want this under normal operations, read it from self.metadata:
     /// this method is just for the scrubber to use when validating an index.
     pub fn duplicated_disk_consistent_lsn(&self) -> Lsn {
@@ -474,11 +506,14 @@ fn v1_indexpart_is_parsed() {
             disk_consistent_lsn: "0/16960E8".parse::<Lsn>().unwrap(),
             metadata: TimelineMetadata::from_bytes(&[113,11,159,210,0,54,0,4,0,0,0,0,1,105,96,232,1,0,0,0,0,1,105,96,112,0,0,0,0,0,0,0,0,0,0,0,0,0,1,105,96,112,0,0,0,0,1,105,96,112,0,0,0,14,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0]).unwrap(),
             deleted_at: None,
+            soft_deleted_at: None,
             archived_at: None,
             lineage: Lineage::default(),
             gc_blocking: None,
             last_aux_file_policy: None,
             import_pgdata: None,
+            export_pgdata: None,
+            layer_rewrite: None,
             rel_size_migration: None,
             l2_lsn: None,
             gc_compaction: None,
@@ -524,11 +559,14 @@ fn v1_indexpart_is_parsed_with_optional_missing_layers() {
             disk_consistent_lsn: "0/16960E8".parse::<Lsn>().unwrap(),
             metadata: TimelineMetadata::from_bytes(&[113,11,159,210,0,54,0,4,0,0,0,0,1,105,96,232,1,0,0,0,0,1,105,96,112,0,0,0,0,0,0,0,0,0,0,0,0,0,1,105,96,112,0,0,0,0,1,105,96,112,0,0,0,14,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0]).unwrap(),
             deleted_at: None,
+            soft_deleted_at: None,
             archived_at: None,
             lineage: Lineage::default(),
             gc_blocking: None,
             last_aux_file_policy: None,
             import_pgdata: None,
+            export_pgdata: None,
+            layer_rewrite: None,
             rel_size_migration: None,
             l2_lsn: None,
             gc_compaction: None,
@@ -575,11 +613,14 @@ fn v2_indexpart_is_parsed_with_deleted_at() {
             disk_consistent_lsn: "0/16960E8".parse::<Lsn>().unwrap(),
             metadata: TimelineMetadata::from_bytes(&[113,11,159,210,0,54,0,4,0,0,0,0,1,105,96,232,1,0,0,0,0,1,105,96,112,0,0,0,0,0,0,0,0,0,0,0,0,0,1,105,96,112,0,0,0,0,1,105,96,112,0,0,0,14,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0]).unwrap(),
             deleted_at: Some(parse_naive_datetime("2023-07-31T09:00:00.123000000")),
+            soft_deleted_at: None,
             archived_at: None,
             lineage: Lineage::default(),
             gc_blocking: None,
             last_aux_file_policy: None,
             import_pgdata: None,
+            export_pgdata: None,
+            layer_rewrite: None,
             rel_size_migration: None,
             l2_lsn: None,
             gc_compaction: None,
@@ -629,11 +670,14 @@ fn empty_layers_are_parsed() {
             ])
             .unwrap(),
             deleted_at: None,
+            soft_deleted_at: None,
             archived_at: None,
             lineage: Lineage::default(),
             gc_blocking: None,
             last_aux_file_policy: None,
             import_pgdata: None,
+            export_pgdata: None,
+            layer_rewrite: None,
             rel_size_migration: None,
             l2_lsn: None,
             gc_compaction: None,
@@ -678,11 +722,14 @@ fn v4_indexpart_is_parsed() {
             disk_consistent_lsn: "0/16960E8".parse::<Lsn>().unwrap(),
             metadata: TimelineMetadata::from_bytes(&[113,11,159,210,0,54,0,4,0,0,0,0,1,105,96,232,1,0,0,0,0,1,105,96,112,0,0,0,0,0,0,0,0,0,0,0,0,0,1,105,96,112,0,0,0,0,1,105,96,112,0,0,0,14,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0]).unwrap(),
             deleted_at: Some(parse_naive_datetime("2023-07-31T09:00:00.123000000")),
+            soft_deleted_at: None,
             archived_at: None,
             lineage: Lineage::default(),
             gc_blocking: None,
             last_aux_file_policy: None,
             import_pgdata: None,
+            export_pgdata: None,
+            layer_rewrite: None,
             rel_size_migration: None,
             l2_lsn: None,
             gc_compaction: None,
@@ -726,6 +773,7 @@ fn v5_indexpart_is_parsed() {
             disk_consistent_lsn: Lsn::from_str("0/15A7618").unwrap(),
             metadata: TimelineMetadata::from_bytes(&[226,88,25,241,0,46,0,4,0,0,0,0,1,90,118,24,0,0,0,0,0,0,0,0,0,0,0,0,0,0,1,78,244,32,0,0,0,0,1,78,244,32,0,0,0,16,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0]).unwrap(),
             deleted_at: None,
+            soft_deleted_at: None,
             archived_at: None,
             lineage: Lineage {
                 reparenting_history_truncated: false,
@@ -735,6 +783,8 @@ fn v5_indexpart_is_parsed() {
             gc_blocking: None,
             last_aux_file_policy: None,
             import_pgdata: None,
+            export_pgdata: None,
+            layer_rewrite: None,
             rel_size_migration: None,
             l2_lsn: None,
             gc_compaction: None,
@@ -783,6 +833,7 @@ fn v6_indexpart_is_parsed() {
             disk_consistent_lsn: "0/16960E8".parse::<Lsn>().unwrap(),
             metadata: TimelineMetadata::from_bytes(&[113,11,159,210,0,54,0,4,0,0,0,0,1,105,96,232,1,0,0,0,0,1,105,96,112,0,0,0,0,0,0,0,0,0,0,0,0,0,1,105,96,112,0,0,0,0,1,105,96,112,0,0,0,14,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0]).unwrap(),
             deleted_at: Some(parse_naive_datetime("2023-07-31T09:00:00.123000000")),
+            soft_deleted_at: None,
             archived_at: None,
             lineage: Lineage {
                 reparenting_history_truncated: false,
@@ -792,6 +843,8 @@ fn v6_indexpart_is_parsed() {
             gc_blocking: None,
             last_aux_file_policy: Some(AuxFilePolicy::V2),
             import_pgdata: None,
+            export_pgdata: None,
+            layer_rewrite: None,
             rel_size_migration: None,
             l2_lsn: None,
             gc_compaction: None,
@@ -849,11 +902,14 @@ fn v7_indexpart_is_parsed() {
                 PgMajorVersion::PG14,
             ).with_recalculated_checksum().unwrap(),
             deleted_at: Some(parse_naive_datetime("2023-07-31T09:00:00.123000000")),
+            soft_deleted_at: None,
             archived_at: None,
             lineage: Default::default(),
             gc_blocking: None,
             last_aux_file_policy: Default::default(),
             import_pgdata: None,
+            export_pgdata: None,
+            layer_rewrite: None,
             rel_size_migration: None,
             l2_lsn: None,
             gc_compaction: None,
@@ -912,11 +968,14 @@ fn v8_indexpart_is_parsed() {
                 PgMajorVersion::PG14,
             ).with_recalculated_checksum().unwrap(),
             deleted_at: Some(parse_naive_datetime("2023-07-31T09:00:00.123000000")),
+            soft_deleted_at: None,
             archived_at: Some(parse_naive_datetime("2023-04-29T09:00:00.123000000")),
             lineage: Default::default(),
             gc_blocking: None,
             last_aux_file_policy: Default::default(),
             import_pgdata: None,
+            export_pgdata: None,
+            layer_rewrite: None,
             rel_size_migration: None,
             l2_lsn: None,
             gc_compaction: None,
@@ -977,6 +1036,7 @@ fn v9_indexpart_is_parsed() {
                 PgMajorVersion::PG14,
             ).with_recalculated_checksum().unwrap(),
             deleted_at: None,
+            soft_deleted_at: None,
             lineage: Default::default(),
             gc_blocking: Some(GcBlocking {
                 started_at: parse_naive_datetime("2024-07-19T09:00:00.123000000"),
@@ -985,6 +1045,8 @@ fn v9_indexpart_is_parsed() {
             last_aux_file_policy: Default::default(),
             archived_at: None,
             import_pgdata: None,
+            export_pgdata: None,
+            layer_rewrite: None,
             rel_size_migration: None,
             l2_lsn: None,
             gc_compaction: None,
@@ -1054,6 +1116,7 @@ fn v10_importpgdata_is_parsed() {
                 PgMajorVersion::PG14,
             ).with_recalculated_checksum().unwrap(),
             deleted_at: None,
+            soft_deleted_at: None,
             lineage: Default::default(),
             gc_blocking: Some(GcBlocking {
                 started_at: parse_naive_datetime("2024-07-19T09:00:00.123000000"),
@@ -1066,6 +1129,8 @@ fn v10_importpgdata_is_parsed() {
                 finished_at: parse_naive_datetime("2024-11-13T09:42:23.123000000"),
                 idempotency_key: import_pgdata::index_part_format::IdempotencyKey::new("specified-by-client-218a5213-5044-4562-a28d-d024c5f057f5".to_string()),
             }))),
+            export_pgdata: None,
+            layer_rewrite: None,
             rel_size_migration: None,
             l2_lsn: None,
             gc_compaction: None,
@@ -1136,6 +1201,7 @@ fn v11_rel_size_migration_is_parsed() {
                 PgMajorVersion::PG14,
             ).with_recalculated_checksum().unwrap(),
             deleted_at: None,
+            soft_deleted_at: None,
             lineage: Default::default(),
             gc_blocking: Some(GcBlocking {
                 started_at: parse_naive_datetime("2024-07-19T09:00:00.123000000"),
@@ -1148,6 +1214,8 @@ fn v11_rel_size_migration_is_parsed() {
                 finished_at: parse_naive_datetime("2024-11-13T09:42:23.123000000"),
                 idempotency_key: import_pgdata::index_part_format::IdempotencyKey::new("specified-by-client-218a5213-5044-4562-a28d-d024c5f057f5".to_string()),
             }))),
+            export_pgdata: None,
+            layer_rewrite: None,
             rel_size_migration: Some(RelSizeMigration::Legacy),
             l2_lsn: None,
             gc_compaction: None,
@@ -1222,6 +1290,7 @@ fn v12_v13_l2_gc_ompaction_is_parsed() {
                 PgMajorVersion::PG14,
             ).with_recalculated_checksum().unwrap(),
             deleted_at: None,
+            soft_deleted_at: None,
             lineage: Default::default(),
             gc_blocking: Some(GcBlocking {
                 started_at: parse_naive_datetime("2024-07-19T09:00:00.123000000"),
@@ -1234,6 +1303,8 @@ fn v12_v13_l2_gc_ompaction_is_parsed() {
                 finished_at: parse_naive_datetime("2024-11-13T09:42:23.123000000"),
                 idempotency_key: import_pgdata::index_part_format::IdempotencyKey::new("specified-by-client-218a5213-5044-4562-a28d-d024c5f057f5".to_string()),
             }))),
+            export_pgdata: None,
+            layer_rewrite: None,
             rel_size_migration: Some(RelSizeMigration::Legacy),
             l2_lsn: Some("0/16960E8".parse::<Lsn>().unwrap()),
             gc_compaction: Some(GcCompactionState {
@@ -1311,6 +1382,7 @@ fn v14_marked_invisible_at_is_parsed() {
                 PgMajorVersion::PG14,
             ).with_recalculated_checksum().unwrap(),
             deleted_at: None,
+            soft_deleted_at: None,
             lineage: Default::default(),
             gc_blocking: Some(GcBlocking {
                 started_at: parse_naive_datetime("2024-07-19T09:00:00.123000000"),
@@ -1323,6 +1395,8 @@ fn v14_marked_invisible_at_is_parsed() {
                 finished_at: parse_naive_datetime("2024-11-13T09:42:23.123000000"),
                 idempotency_key: import_pgdata::index_part_format::IdempotencyKey::new("specified-by-client-218a5213-5044-4562-a28d-d024c5f057f5".to_string()),
             }))),
+            export_pgdata: None,
+            layer_rewrite: None,
             rel_size_migration: Some(RelSizeMigration::Legacy),
             l2_lsn: Some("0/16960E8".parse::<Lsn>().unwrap()),
             gc_compaction: Some(GcCompactionState {
@@ -1401,6 +1475,7 @@ fn v15_rel_size_migrated_at_is_parsed() {
                 PgMajorVersion::PG14,
             ).with_recalculated_checksum().unwrap(),
             deleted_at: None,
+            soft_deleted_at: None,
             lineage: Default::default(),
             gc_blocking: Some(GcBlocking {
                 started_at: parse_naive_datetime("2024-07-19T09:00:00.123000000"),
@@ -1413,6 +1488,8 @@ fn v15_rel_size_migrated_at_is_parsed() {
                 finished_at: parse_naive_datetime("2024-11-13T09:42:23.123000000"),
                 idempotency_key: import_pgdata::index_part_format::IdempotencyKey::new("specified-by-client-218a5213-5044-4562-a28d-d024c5f057f5".to_string()),
             }))),
+            export_pgdata: None,
+            layer_rewrite: None,
             rel_size_migration: Some(RelSizeMigration::Legacy),
             l2_lsn: Some("0/16960E8".parse::<Lsn>().unwrap()),
             gc_compaction: Some(GcCompactionState {
@@ -1426,6 +1503,249 @@ fn v15_rel_size_migrated_at_is_parsed() {
         assert_eq!(part, expected);
     }
 
+    #[test]
+    fn v16_export_pgdata_is_parsed() {
+        let example = r#"{
+            "version": 16,
+            "layer_metadata":{
+                "000000000000000000000000000000000000-FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF__0000000001696070-00000000016960E9": { "file_size": 25600000 }
+            },
+            "disk_consistent_lsn":"0/16960E8",
+            "metadata": {
+                "disk_consistent_lsn": "0/16960E8",
+                "prev_record_lsn": "0/1696070",
+                "ancestor_timeline": "e45a7f37d3ee2ff17dc14bf4f4e3f52e",
+                "ancestor_lsn": "0/0",
+                "latest_gc_cutoff_lsn": "0/1696070",
+                "initdb_lsn": "0/1696070",
+                "pg_version": 14
+            },
+            "export_pgdata": {
+                "V1": {
+                    "InProgress": {
+                        "location": {
+                            "AwsS3": {
+                                "region": "us-east-2",
+                                "bucket": "exports",
+                                "key": "tenant-a/timeline-b"
+                            }
+                        },
+                        "lsn": "0/16960E8",
+                        "part_size": 67108864,
+                        "started_at": "2025-07-21T09:23:42.123",
+                        "progress": {
+                            "base_done": true,
+                            "segments_done": 42,
+                            "parts_uploaded": 3,
+                            "bytes_uploaded": 123456
+                        }
+                    }
+                }
+            },
+            "rel_size_migration": "legacy"
+        }"#;
+
+        let expected = IndexPart {
+            version: 16,
+            layer_metadata: HashMap::from([
+                ("000000000000000000000000000000000000-FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF__0000000001696070-00000000016960E9".parse().unwrap(), LayerFileMetadata {
+                    file_size: 25600000,
+                    generation: Generation::none(),
+                    shard: ShardIndex::unsharded()
+                }),
+            ]),
+            disk_consistent_lsn: "0/16960E8".parse::<Lsn>().unwrap(),
+            metadata: TimelineMetadata::new(
+                Lsn::from_str("0/16960E8").unwrap(),
+                Some(Lsn::from_str("0/1696070").unwrap()),
+                Some(TimelineId::from_str("e45a7f37d3ee2ff17dc14bf4f4e3f52e").unwrap()),
+                Lsn::INVALID,
+                Lsn::from_str("0/1696070").unwrap(),
+                Lsn::from_str("0/1696070").unwrap(),
+                PgMajorVersion::PG14,
+            ).with_recalculated_checksum().unwrap(),
+            deleted_at: None,
+            soft_deleted_at: None,
+            lineage: Default::default(),
+            gc_blocking: None,
+            last_aux_file_policy: Default::default(),
+            archived_at: None,
+            import_pgdata: None,
+            export_pgdata: Some(export_pgdata::index_part_format::Root::V1(export_pgdata::index_part_format::V1::InProgress(export_pgdata::index_part_format::InProgress {
+                location: import_pgdata::index_part_format::Location::AwsS3 {
+                    region: "us-east-2".to_string(),
+                    bucket: "exports".to_string(),
+                    key: "tenant-a/timeline-b".to_string(),
+                },
+                lsn: "0/16960E8".parse::<Lsn>().unwrap(),
+                part_size: 67108864,
+                started_at: parse_naive_datetime("2025-07-21T09:23:42.123000000"),
+                progress: export_pgdata::index_part_format::Progress {
+                    base_done: true,
+                    segments_done: 42,
+                    parts_uploaded: 3,
+                    bytes_uploaded: 123456,
+                },
+            }))),
+            layer_rewrite: None,
+            rel_size_migration: Some(RelSizeMigration::Legacy),
+            l2_lsn: None,
+            gc_compaction: None,
+            marked_invisible_at: None,
+            rel_size_migrated_at: None,
+        };
+
+        let part = IndexPart::from_json_bytes(example.as_bytes()).unwrap();
+        assert_eq!(part, expected);
+    }
+
+    #[test]
+    fn v17_soft_deleted_at_is_parsed() {
+        let example = r#"{
+            "version": 17,
+            "layer_metadata":{
+                "000000000000000000000000000000000000-FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF__0000000001696070-00000000016960E9": { "file_size": 25600000 }
+            },
+            "disk_consistent_lsn":"0/16960E8",
+            "metadata": {
+                "disk_consistent_lsn": "0/16960E8",
+                "prev_record_lsn": "0/1696070",
+                "ancestor_timeline": "e45a7f37d3ee2ff17dc14bf4f4e3f52e",
+                "ancestor_lsn": "0/0",
+                "latest_gc_cutoff_lsn": "0/1696070",
+                "initdb_lsn": "0/1696070",
+                "pg_version": 14
+            },
+            "archived_at": "2025-08-04T10:00:00.000",
+            "soft_deleted_at": "2025-08-04T10:00:00.000",
+            "rel_size_migration": "legacy"
+        }"#;
+
+        let expected = IndexPart {
+            version: 17,
+            layer_metadata: HashMap::from([
+                ("000000000000000000000000000000000000-FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF__0000000001696070-00000000016960E9".parse().unwrap(), LayerFileMetadata {
+                    file_size: 25600000,
+                    generation: Generation::none(),
+                    shard: ShardIndex::unsharded()
+                }),
+            ]),
+            disk_consistent_lsn: "0/16960E8".parse::<Lsn>().unwrap(),
+            metadata: TimelineMetadata::new(
+                Lsn::from_str("0/16960E8").unwrap(),
+                Some(Lsn::from_str("0/1696070").unwrap()),
+                Some(TimelineId::from_str("e45a7f37d3ee2ff17dc14bf4f4e3f52e").unwrap()),
+                Lsn::INVALID,
+                Lsn::from_str("0/1696070").unwrap(),
+                Lsn::from_str("0/1696070").unwrap(),
+                PgMajorVersion::PG14,
+            ).with_recalculated_checksum().unwrap(),
+            deleted_at: None,
+            soft_deleted_at: Some(parse_naive_datetime("2025-08-04T10:00:00.000000000")),
+            lineage: Default::default(),
+            gc_blocking: None,
+            last_aux_file_policy: Default::default(),
+            archived_at: Some(parse_naive_datetime("2025-08-04T10:00:00.000000000")),
+            import_pgdata: None,
+            export_pgdata: None,
+            layer_rewrite: None,
+            rel_size_migration: Some(RelSizeMigration::Legacy),
+            l2_lsn: None,
+            gc_compaction: None,
+            marked_invisible_at: None,
+            rel_size_migrated_at: None,
+        };
+
+        let part = IndexPart::from_json_bytes(example.as_bytes()).unwrap();
+        assert_eq!(part, expected);
+    }
+
+    #[test]
+    fn v18_layer_rewrite_is_parsed() {
+        let example = r#"{
+            "version": 18,
+            "layer_metadata":{
+                "000000000000000000000000000000000000-FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF__0000000001696070-00000000016960E9": { "file_size": 25600000 }
+            },
+            "disk_consistent_lsn":"0/16960E8",
+            "metadata": {
+                "disk_consistent_lsn": "0/16960E8",
+                "prev_record_lsn": "0/1696070",
+                "ancestor_timeline": "e45a7f37d3ee2ff17dc14bf4f4e3f52e",
+                "ancestor_lsn": "0/0",
+                "latest_gc_cutoff_lsn": "0/1696070",
+                "initdb_lsn": "0/1696070",
+                "pg_version": 14
+            },
+            "layer_rewrite": {
+                "V1": {
+                    "InProgress": {
+                        "generation": 7,
+                        "max_bytes_per_second": 10485760,
+                        "started_at": "2025-09-01T10:00:00.000",
+                        "progress": {
+                            "last_visited": "000000000000000000000000000000000000-FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF__0000000001696070",
+                            "layers_checked": 12,
+                            "layers_rewritten": 4,
+                            "bytes_before": 1073741824,
+                            "bytes_after": 268435456
+                        }
+                    }
+                }
+            },
+            "rel_size_migration": "legacy"
+        }"#;
+
+        let expected = IndexPart {
+            version: 18,
+            layer_metadata: HashMap::from([
+                ("000000000000000000000000000000000000-FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF__0000000001696070-00000000016960E9".parse().unwrap(), LayerFileMetadata {
+                    file_size: 25600000,
+                    generation: Generation::none(),
+                    shard: ShardIndex::unsharded()
+                }),
+            ]),
+            disk_consistent_lsn: "0/16960E8".parse::<Lsn>().unwrap(),
+            metadata: TimelineMetadata::new(
+                Lsn::from_str("0/16960E8").unwrap(),
+                Some(Lsn::from_str("0/1696070").unwrap()),
+                Some(TimelineId::from_str("e45a7f37d3ee2ff17dc14bf4f4e3f52e").unwrap()),
+                Lsn::INVALID,
+                Lsn::from_str("0/1696070").unwrap(),
+                Lsn::from_str("0/1696070").unwrap(),
+                PgMajorVersion::PG14,
+            ).with_recalculated_checksum().unwrap(),
+            deleted_at: None,
+            soft_deleted_at: None,
+            lineage: Default::default(),
+            gc_blocking: None,
+            last_aux_file_policy: Default::default(),
+            archived_at: None,
+            import_pgdata: None,
+            export_pgdata: None,
+            layer_rewrite: Some(layer_rewrite::index_part_format::Root::V1(layer_rewrite::index_part_format::V1::InProgress(layer_rewrite::index_part_format::InProgress {
+                generation: Generation::new(7),
+                max_bytes_per_second: Some(10485760),
+                started_at: parse_naive_datetime("2025-09-01T10:00:00.000000000"),
+                progress: layer_rewrite::index_part_format::Progress {
+                    last_visited: Some("000000000000000000000000000000000000-FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF__0000000001696070".parse().unwrap()),
+                    layers_checked: 12,
+                    layers_rewritten: 4,
+                    bytes_before: 1073741824,
+                    bytes_after: 268435456,
+                },
+            }))),
+            rel_size_migration: Some(RelSizeMigration::Legacy),
+            l2_lsn: None,
+            gc_compaction: None,
+            marked_invisible_at: None,
+            rel_size_migrated_at: None,
+        };
+
+        let part = IndexPart::from_json_bytes(example.as_bytes()).unwrap();
+        assert_eq!(part, expected);
+    }
+
     fn parse_naive_datetime(s: &str) -> NaiveDateTime {
         chrono::NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S.%f").unwrap()
     }
@@ -45,6 +45,10 @@ pub struct OffloadedTimelineManifest {
     pub ancestor_retain_lsn: Option<Lsn>,
     /// The time point when the timeline was archived
     pub archived_at: NaiveDateTime,
+    /// The time point when the timeline was soft-deleted, if it was. Soft-deleted timelines are
+    /// purged once the tenant's delete grace period has passed, unless undeleted before.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub soft_deleted_at: Option<NaiveDateTime>,
 }
 
 /// The newest manifest version. This should be incremented on changes, even non-breaking ones. We
@@ -52,9 +56,10 @@ pub struct OffloadedTimelineManifest {
 ///
 /// 1: initial version
 /// 2: +stripe_size
+/// 3: +offloaded_timelines.soft_deleted_at
 ///
 /// When adding new versions, also add a parse_vX test case below.
-pub const LATEST_TENANT_MANIFEST_VERSION: usize = 2;
+pub const LATEST_TENANT_MANIFEST_VERSION: usize = 3;
 
 impl TenantManifest {
     /// Returns true if the manifests are equal, ignoring the version number. This avoids
@@ -152,6 +157,7 @@ fn parse_v1() -> anyhow::Result<()> {
                     ancestor_timeline_id: None,
                     ancestor_retain_lsn: None,
                     archived_at: NaiveDateTime::from_str("2025-03-07T11:07:11.373105434")?,
+                    soft_deleted_at: None,
                 },
                 OffloadedTimelineManifest {
                     timeline_id: TimelineId::from_str("f3def5823ad7080d2ea538d8e12163fa")?,
@@ -160,6 +166,7 @@ fn parse_v1() -> anyhow::Result<()> {
                     )?),
                     ancestor_retain_lsn: Some(Lsn::from_str("0/1F79038")?),
                     archived_at: NaiveDateTime::from_str("2025-03-05T11:10:22.257901390")?,
+                    soft_deleted_at: None,
                 },
             ],
         };
@@ -195,6 +202,7 @@ fn parse_v2() -> anyhow::Result<()> {
                     ancestor_timeline_id: None,
                     ancestor_retain_lsn: None,
                     archived_at: NaiveDateTime::from_str("2025-03-07T11:07:11.373105434")?,
+                    soft_deleted_at: None,
                 },
                 OffloadedTimelineManifest {
                     timeline_id: TimelineId::from_str("f3def5823ad7080d2ea538d8e12163fa")?,
@@ -203,6 +211,55 @@ fn parse_v2() -> anyhow::Result<()> {
                     )?),
                     ancestor_retain_lsn: Some(Lsn::from_str("0/1F79038")?),
                     archived_at: NaiveDateTime::from_str("2025-03-05T11:10:22.257901390")?,
+                    soft_deleted_at: None,
+                },
+            ],
+        };
+        assert_eq!(expected, TenantManifest::from_json_bytes(json.as_bytes())?);
+        Ok(())
+    }
+
+    /// v3 manifests should be parsed, for backwards compatibility.
+    #[test]
+    fn parse_v3() -> anyhow::Result<()> {
+        let json = r#"{
+             "version": 3,
+             "stripe_size": 32768,
+             "offloaded_timelines": [
+                 {
+                     "timeline_id": "5c4df612fd159e63c1b7853fe94d97da",
+                     "archived_at": "2025-03-07T11:07:11.373105434"
+                 },
+                 {
+                     "timeline_id": "f3def5823ad7080d2ea538d8e12163fa",
+                     "ancestor_timeline_id": "5c4df612fd159e63c1b7853fe94d97da",
+                     "ancestor_retain_lsn": "0/1F79038",
+                     "archived_at": "2025-03-05T11:10:22.257901390",
+                     "soft_deleted_at": "2025-03-08T09:12:45.000000000"
+                 }
+             ]
+         }"#;
+        let expected = TenantManifest {
+            version: 3,
+            stripe_size: Some(ShardStripeSize(32768)),
+            offloaded_timelines: vec![
+                OffloadedTimelineManifest {
+                    timeline_id: TimelineId::from_str("5c4df612fd159e63c1b7853fe94d97da")?,
+                    ancestor_timeline_id: None,
+                    ancestor_retain_lsn: None,
+                    archived_at: NaiveDateTime::from_str("2025-03-07T11:07:11.373105434")?,
+                    soft_deleted_at: None,
+                },
+                OffloadedTimelineManifest {
+                    timeline_id: TimelineId::from_str("f3def5823ad7080d2ea538d8e12163fa")?,
+                    ancestor_timeline_id: Some(TimelineId::from_str(
+                        "5c4df612fd159e63c1b7853fe94d97da",
+                    )?),
+                    ancestor_retain_lsn: Some(Lsn::from_str("0/1F79038")?),
+                    archived_at: NaiveDateTime::from_str("2025-03-05T11:10:22.257901390")?,
+                    soft_deleted_at: Some(NaiveDateTime::from_str(
+                        "2025-03-08T09:12:45.000000000",
+                    )?),
                 },
             ],
         };
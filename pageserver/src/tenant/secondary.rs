@@ -159,6 +159,13 @@ pub(crate) fn tenant_shard_id(&self) -> TenantShardId {
         self.tenant_shard_id
     }
 
+    /// For API access: the download progress, with its estimates brought up to date.
+    pub(crate) fn get_progress(&self) -> models::SecondaryProgress {
+        let mut progress = self.progress.lock().unwrap().clone();
+        progress.refresh_read_latency_impact();
+        progress
+    }
+
     pub(crate) async fn shutdown(&self) {
         self.cancel.cancel();
 
@@ -212,6 +219,11 @@ pub(crate) fn get_tenant_shard_id(&self) -> &TenantShardId {
         &self.tenant_shard_id
     }
 
+    /// The tenant's override of `eviction_weight_pct`, if any.
+    pub(crate) fn get_eviction_weight_pct(&self) -> Option<u32> {
+        self.tenant_conf.lock().unwrap().eviction_weight_pct
+    }
+
     pub(crate) fn get_layers_for_eviction(self: &Arc<Self>) -> (DiskUsageEvictionInfo, usize) {
         self.detail.lock().unwrap().get_layers_for_eviction(self)
     }
@@ -45,6 +45,7 @@
     remote_heatmap_path,
 };
 use crate::tenant::span::debug_assert_current_span_has_tenant_id;
+use crate::tenant::storage_layer::access_rate::DecayedAccessCounter;
 use crate::tenant::storage_layer::layer::local_layer_path;
 use crate::tenant::storage_layer::{LayerName, LayerVisibilityHint};
 use crate::tenant::tasks::{BackgroundLoopKind, warn_when_period_overrun};
@@ -88,6 +89,8 @@ struct SecondaryDownloader {
 pub(super) struct OnDiskState {
     metadata: LayerFileMetadata,
     access_time: SystemTime,
+    /// Access rate as of `access_time`, see [`HeatMapLayer::access_rate`].
+    access_rate: f32,
     local_path: Utf8PathBuf,
 }
 
@@ -99,11 +102,13 @@ fn new(
         _ame: LayerName,
         metadata: LayerFileMetadata,
         access_time: SystemTime,
+        access_rate: f32,
         local_path: Utf8PathBuf,
     ) -> Self {
         Self {
             metadata,
             access_time,
+            access_rate,
             local_path,
         }
     }
@@ -200,6 +205,7 @@ fn touch_layer<F>(
         match self.on_disk_layers.entry(touched.name.clone()) {
             Entry::Occupied(mut v) => {
                 v.get_mut().access_time = touched.access_time;
+                v.get_mut().access_rate = touched.access_rate;
             }
             Entry::Vacant(e) => {
                 e.insert(OnDiskState::new(
@@ -209,6 +215,7 @@ fn touch_layer<F>(
                     touched.name.clone(),
                     touched.metadata.clone(),
                     touched.access_time,
+                    touched.access_rate,
                     local_path(),
                 ));
                 resident_metric.add(touched.metadata.file_size);
@@ -243,6 +250,19 @@ fn strftime(t: &'_ SystemTime) -> DelayedFormat<StrftimeItems<'_>> {
     datetime.format("%d/%m/%Y %T")
 }
 
+/// A layer's estimated accesses per hour as of the heatmap's upload. Decaying all rates to the
+/// same point in time keeps the sums in [`SecondaryProgress`] consistent while downloads go on.
+fn heatmap_access_rate(layer: &HeatMapLayer, heatmap_mtime: SystemTime) -> f64 {
+    layer.current_access_rate(heatmap_mtime) as f64
+}
+
+fn progress_access_rate(progress: &SecondaryProgress, layer: &HeatMapLayer) -> f64 {
+    match progress.heatmap_mtime {
+        Some(mtime) => heatmap_access_rate(layer, mtime.0),
+        None => 0.0,
+    }
+}
+
 /// Information returned from download function when it detects the heatmap has changed
 struct HeatMapModified {
     etag: Etag,
@@ -352,6 +372,7 @@ pub(super) fn get_layers_for_eviction(
     ) -> (DiskUsageEvictionInfo, usize) {
         let mut result = DiskUsageEvictionInfo::default();
         let mut total_layers = 0;
+        let now = SystemTime::now();
 
         for (timeline_id, timeline_detail) in &self.timelines {
             result
@@ -366,6 +387,12 @@ pub(super) fn get_layers_for_eviction(
                         }),
                         last_activity_ts: ods.access_time,
                         relative_last_activity: finite_f32::FiniteF32::ZERO,
+                        access_rate: DecayedAccessCounter::decay_rate(
+                            ods.access_rate,
+                            ods.access_time,
+                            now,
+                        ),
+                        cost: finite_f32::FiniteF32::ZERO,
                         // Secondary location layers are presumed visible, because Covered layers
                         // are excluded from the heatmap
                         visibility: LayerVisibilityHint::Visible,
@@ -899,6 +926,8 @@ async fn download(&self, ctx: &RequestContext) -> Result<(), UpdateError> {
             progress.layers_downloaded = progress.layers_total;
             progress.bytes_downloaded = progress.bytes_total;
         }
+        // Sums of access rates drift with the order of additions: snap to the total.
+        progress.access_rate_downloaded = progress.access_rate_total;
 
         Ok(())
     }
@@ -920,6 +949,21 @@ async fn prepare_timelines(
             heatmap_mtime: Some(serde_system_time::SystemTime(heatmap_mtime)),
             layers_downloaded: 0,
             bytes_downloaded: 0,
+            access_rate_total: heatmap
+                .timelines
+                .iter()
+                .flat_map(|t| t.hot_layers())
+                .map(|l| heatmap_access_rate(l, heatmap_mtime))
+                .sum(),
+            access_rate_downloaded: 0.0,
+            // Download latency is a property of the location rather than of the heatmap: carry it over.
+            layer_download_ms: self
+                .secondary_state
+                .progress
+                .lock()
+                .unwrap()
+                .layer_download_ms,
+            est_read_latency_impact_ms: None,
         };
 
         // Also expose heatmap bytes_total as a metric
@@ -949,6 +993,15 @@ async fn prepare_timelines(
                     .hot_layers()
                     .map(|l| (&l.name, l.metadata.generation))
                     .collect::<HashSet<_>>();
+                let access_rates = heatmap_timeline
+                    .hot_layers()
+                    .map(|l| {
+                        (
+                            (&l.name, l.metadata.generation),
+                            heatmap_access_rate(l, heatmap_mtime),
+                        )
+                    })
+                    .collect::<HashMap<_, _>>();
                 let layers_on_disk = timeline_state
                     .on_disk_layers
                     .iter()
@@ -985,6 +1038,10 @@ async fn prepare_timelines(
 
                 progress.bytes_downloaded += layer_byte_count;
                 progress.layers_downloaded += layer_count;
+                progress.access_rate_downloaded += layers_on_disk
+                    .intersection(&layers_in_heatmap)
+                    .map(|l| access_rates[l])
+                    .sum::<f64>();
             }
 
             for delete_timeline in &delete_timelines {
@@ -1094,8 +1151,16 @@ async fn download_timeline_layers(
         // Accumulate updates to the state
         let mut touched = Vec::new();
 
+        // Download the most frequently accessed layers first. The sort is stable, so layers without
+        // access rates (e.g. from older attached locations) keep the heatmap's order.
+        let now = SystemTime::now();
         let timeline_id = timeline.timeline_id;
-        for layer in timeline.into_hot_layers() {
+        let mut layers = timeline.into_hot_layers().collect::<Vec<_>>();
+        layers.sort_by(|a, b| {
+            b.current_access_rate(now)
+                .total_cmp(&a.current_access_rate(now))
+        });
+        for layer in layers {
             if self.secondary_state.cancel.is_cancelled() {
                 tracing::debug!("Cancelled -- dropping out of layer loop");
                 return (Err(UpdateError::Cancelled), touched);
@@ -1184,7 +1249,10 @@ async fn layer_action(
                 );
                 return LayerAction::Download;
             }
-            if on_disk.metadata != layer.metadata || on_disk.access_time != layer.access_time {
+            if on_disk.metadata != layer.metadata
+                || on_disk.access_time != layer.access_time
+                || on_disk.access_rate != layer.access_rate
+            {
                 // We already have this layer on disk.  Update its access time.
                 tracing::debug!(
                     "Access time updated for layer {}: {} -> {}",
@@ -1278,6 +1346,7 @@ fn skip_layer(&self, layer: HeatMapLayer) {
         progress.bytes_total = progress
             .bytes_total
             .saturating_sub(layer.metadata.file_size);
+        progress.access_rate_total -= progress_access_rate(&progress, &layer);
     }
 
     async fn download_layer(
@@ -1309,6 +1378,7 @@ async fn download_layer(
             layer.name,
             layer.metadata.file_size
         );
+        let started_at = Instant::now();
         let downloaded_bytes = download_layer_file(
             self.conf,
             self.remote_storage,
@@ -1361,9 +1431,15 @@ async fn download_layer(
                 .or_else(fs_ext::ignore_not_found)?;
         } else {
             tracing::info!("Downloaded layer {}, size {}", layer.name, downloaded_bytes);
+            let download_ms = started_at.elapsed().as_millis() as u64;
             let mut progress = self.secondary_state.progress.lock().unwrap();
             progress.bytes_downloaded += downloaded_bytes;
             progress.layers_downloaded += 1;
+            progress.access_rate_downloaded += progress_access_rate(&progress, &layer);
+            progress.layer_download_ms = Some(match progress.layer_download_ms {
+                Some(avg) => (avg * 7 + download_ms) / 8,
+                None => download_ms,
+            });
         }
 
         SECONDARY_MODE.download_layer.inc();
@@ -8,6 +8,7 @@
 
 use crate::tenant::remote_timeline_client::index::LayerFileMetadata;
 use crate::tenant::storage_layer::LayerName;
+use crate::tenant::storage_layer::access_rate::DecayedAccessCounter;
 
 #[derive(Serialize, Deserialize)]
 pub(crate) struct HeatMapTenant {
@@ -55,22 +56,38 @@ pub(crate) struct HeatMapLayer {
     pub(crate) access_time: SystemTime,
 
     #[serde(default)]
-    pub(crate) cold: bool, // TODO: an actual 'heat' score that would let secondary locations prioritize downloading
-                           // the hottest layers, rather than trying to simply mirror whatever layers are on-disk on the primary.
+    pub(crate) cold: bool,
+
+    /// Estimated accesses per hour on the attached location as of the most recent access, used by
+    /// secondary locations to prioritize downloads and evictions. It decays after that, see
+    /// [`DecayedAccessCounter::decay_rate`]. Absent in heatmaps written by older versions.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub(crate) access_rate: f32,
+}
+
+fn is_zero(v: &f32) -> bool {
+    *v == 0.0
 }
 
 impl HeatMapLayer {
+    /// Estimated accesses per hour at `now`.
+    pub(crate) fn current_access_rate(&self, now: SystemTime) -> f32 {
+        DecayedAccessCounter::decay_rate(self.access_rate, self.access_time, now)
+    }
+
     pub(crate) fn new(
         name: LayerName,
         metadata: LayerFileMetadata,
         access_time: SystemTime,
         cold: bool,
+        access_rate: f32,
     ) -> Self {
         Self {
             name,
             metadata,
             access_time,
             cold,
+            access_rate,
         }
     }
 }
@@ -117,6 +134,7 @@ pub(crate) fn get_stats(&self) -> HeatMapStats {
         stats
     }
 
+    /// Drops access times and rates, which change with every read, leaving only the set of layers.
     pub(crate) fn strip_atimes(self) -> Self {
         Self {
             timelines: self
@@ -125,6 +143,7 @@ pub(crate) fn strip_atimes(self) -> Self {
                 .map(|mut tl| {
                     for layer in &mut tl.layers {
                         layer.access_time = SystemTime::UNIX_EPOCH;
+                        layer.access_rate = 0.0;
                     }
                     tl
                 })
@@ -0,0 +1,215 @@
+//! Enforcement of the per-timeline and per-tenant size limits from the tenant config.
+//!
+//! The pageserver never drops WAL because of a size limit: it keeps ingesting, and reports the
+//! exceeded limit to the safekeepers through [`utils::pageserver_feedback::PageserverFeedback`].
+//! Safekeepers forward the feedback to the compute, which then refuses to extend relations,
+//! i.e. enters read-only mode, until the size drops below the limit again.
+//!
+//! Limits are enforced on the ingest path: the walreceiver re-evaluates them for every status
+//! update it sends, using an [`IngestBaseline`] that tenant housekeeping refreshes with accurate
+//! sizes. Between refreshes, the physical size of the timeline is estimated from the WAL
+//! ingested since, so a timeline can't overshoot its limit by a whole housekeeping period.
+//!
+//! Logical sizes are only maintained on shard zero, so other shards only ever trip the physical
+//! limits, which they evaluate against their own layers. Physical limits are split evenly across
+//! the shards of a tenant, see [`SizeLimits::for_shard`].
+
+use pageserver_api::models::SizeLimitExceeded;
+use utils::lsn::Lsn;
+use utils::shard::ShardCount;
+
+/// The size limits in effect for a tenant, in bytes. `None` means unlimited.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct SizeLimits {
+    pub(crate) timeline_logical: Option<u64>,
+    pub(crate) timeline_physical: Option<u64>,
+    pub(crate) tenant_logical: Option<u64>,
+    pub(crate) tenant_physical: Option<u64>,
+}
+
+/// Sizes of a single timeline, as used for limit evaluation.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct TimelineSizes {
+    pub(crate) logical: u64,
+    pub(crate) physical: u64,
+}
+
+impl SizeLimits {
+    pub(crate) fn is_unlimited(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// The limits that apply to one shard of a tenant with `shard_count` shards.
+    ///
+    /// Logical sizes are only tracked by shard zero, which sees the whole logical size, so
+    /// logical limits apply as is. Each shard only holds its own share of the layers, so
+    /// physical limits are divided by the number of shards.
+    pub(crate) fn for_shard(self, shard_count: ShardCount) -> Self {
+        let count = shard_count.count() as u64;
+        Self {
+            timeline_physical: self.timeline_physical.map(|limit| limit / count),
+            tenant_physical: self.tenant_physical.map(|limit| limit / count),
+            ..self
+        }
+    }
+
+    /// Evaluates the limits for one timeline, given its own sizes and the sum of the sizes of
+    /// all timelines of the tenant (shard).
+    pub(crate) fn evaluate(
+        &self,
+        timeline: TimelineSizes,
+        tenant: TimelineSizes,
+    ) -> Option<SizeLimitExceeded> {
+        let exceeds = |size: u64, limit: Option<u64>| limit.is_some_and(|limit| size > limit);
+
+        if exceeds(timeline.logical, self.timeline_logical) {
+            Some(SizeLimitExceeded::TimelineLogical)
+        } else if exceeds(timeline.physical, self.timeline_physical) {
+            Some(SizeLimitExceeded::TimelinePhysical)
+        } else if exceeds(tenant.logical, self.tenant_logical) {
+            Some(SizeLimitExceeded::TenantLogical)
+        } else if exceeds(tenant.physical, self.tenant_physical) {
+            Some(SizeLimitExceeded::TenantPhysical)
+        } else {
+            None
+        }
+    }
+}
+
+/// Accurate sizes computed by tenant housekeeping, which the ingest path extrapolates from.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct IngestBaseline {
+    pub(crate) limits: SizeLimits,
+    /// Physical size of the timeline when the baseline was taken.
+    pub(crate) physical: u64,
+    /// Last record LSN of the timeline when the baseline was taken.
+    pub(crate) lsn: Lsn,
+    /// Sum of the sizes of the other timelines of the tenant (shard).
+    pub(crate) others: TimelineSizes,
+    /// Number of shards the ingested WAL is spread across.
+    pub(crate) shard_count: ShardCount,
+}
+
+impl IngestBaseline {
+    /// Evaluates the limits at `last_record_lsn`, given the current logical size.
+    ///
+    /// Every byte of WAL ingested since the baseline is assumed to grow the physical size by
+    /// one byte, split evenly across the shards. That overestimates growth of a timeline that
+    /// mostly rewrites pages, which housekeeping corrects on its next refresh.
+    pub(crate) fn evaluate(&self, logical: u64, last_record_lsn: Lsn) -> Option<SizeLimitExceeded> {
+        let ingested = last_record_lsn.0.saturating_sub(self.lsn.0);
+        let timeline = TimelineSizes {
+            logical,
+            physical: self.physical + ingested / self.shard_count.count() as u64,
+        };
+        let tenant = TimelineSizes {
+            logical: self.others.logical + timeline.logical,
+            physical: self.others.physical + timeline.physical,
+        };
+        self.limits.evaluate(timeline, tenant)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sizes(logical: u64, physical: u64) -> TimelineSizes {
+        TimelineSizes { logical, physical }
+    }
+
+    #[test]
+    fn unlimited_never_trips() {
+        let limits = SizeLimits::default();
+        assert!(limits.is_unlimited());
+        assert_eq!(
+            limits.evaluate(sizes(u64::MAX, u64::MAX), sizes(u64::MAX, u64::MAX)),
+            None
+        );
+    }
+
+    #[test]
+    fn limits_are_exclusive() {
+        let limits = SizeLimits {
+            timeline_logical: Some(100),
+            ..Default::default()
+        };
+        assert_eq!(limits.evaluate(sizes(100, 0), sizes(100, 0)), None);
+        assert_eq!(
+            limits.evaluate(sizes(101, 0), sizes(101, 0)),
+            Some(SizeLimitExceeded::TimelineLogical)
+        );
+    }
+
+    #[test]
+    fn timeline_limits_take_precedence() {
+        let limits = SizeLimits {
+            timeline_logical: Some(100),
+            timeline_physical: Some(100),
+            tenant_logical: Some(100),
+            tenant_physical: Some(100),
+        };
+        assert_eq!(
+            limits.evaluate(sizes(0, 200), sizes(200, 200)),
+            Some(SizeLimitExceeded::TimelinePhysical)
+        );
+        assert_eq!(
+            limits.evaluate(sizes(50, 50), sizes(200, 200)),
+            Some(SizeLimitExceeded::TenantLogical)
+        );
+        assert_eq!(
+            limits.evaluate(sizes(50, 50), sizes(50, 200)),
+            Some(SizeLimitExceeded::TenantPhysical)
+        );
+    }
+
+    #[test]
+    fn physical_limits_are_split_across_shards() {
+        let limits = SizeLimits {
+            timeline_logical: Some(1000),
+            timeline_physical: Some(1000),
+            tenant_logical: Some(4000),
+            tenant_physical: Some(4000),
+        };
+        assert_eq!(limits.for_shard(ShardCount::new(0)), limits);
+        assert_eq!(
+            limits.for_shard(ShardCount::new(4)),
+            SizeLimits {
+                timeline_logical: Some(1000),
+                timeline_physical: Some(250),
+                tenant_logical: Some(4000),
+                tenant_physical: Some(1000),
+            }
+        );
+    }
+
+    #[test]
+    fn ingest_extrapolates_physical_size() {
+        let baseline = IngestBaseline {
+            limits: SizeLimits {
+                timeline_physical: Some(1000),
+                tenant_physical: Some(1500),
+                ..Default::default()
+            },
+            physical: 900,
+            lsn: Lsn(0x1000),
+            others: sizes(0, 400),
+            shard_count: ShardCount::new(2),
+        };
+        assert_eq!(baseline.evaluate(0, Lsn(0x1000)), None);
+        // 300 bytes of WAL split across two shards grow this shard by 150 bytes.
+        assert_eq!(
+            baseline.evaluate(0, Lsn(0x1000 + 300)),
+            Some(SizeLimitExceeded::TimelinePhysical)
+        );
+        let baseline = IngestBaseline {
+            others: sizes(0, 600),
+            physical: 800,
+            ..baseline
+        };
+        assert_eq!(
+            baseline.evaluate(0, Lsn(0x1000 + 300)),
+            Some(SizeLimitExceeded::TenantPhysical)
+        );
+    }
+}
@@ -1,5 +1,6 @@
 //! Common traits and structs for layers
 
+pub(crate) mod access_rate;
 pub mod batch_split_writer;
 pub mod delta_layer;
 pub mod errors;
@@ -10,6 +11,7 @@
 mod layer_desc;
 mod layer_name;
 pub mod merge_iterator;
+pub(crate) mod tier;
 
 use std::cmp::Ordering;
 use std::collections::hash_map::Entry;
@@ -28,7 +30,7 @@
 use futures::stream::FuturesUnordered;
 pub use image_layer::{ImageLayer, ImageLayerWriter};
 pub use inmemory_layer::InMemoryLayer;
-pub(crate) use layer::{EvictionError, Layer, ResidentLayer};
+pub(crate) use layer::{EvictionError, Layer, ResidentLayer, TierMoveError};
 pub use layer_desc::{PersistentLayerDesc, PersistentLayerKey};
 pub use layer_name::{DeltaLayerName, ImageLayerName, LayerName};
 use pageserver_api::config::GetVectoredConcurrentIo;
@@ -1148,6 +1150,8 @@ fn as_api_model(
                 .read_low_res_timestamp(Self::RTIME_SHIFT)
                 .unwrap_or(UNIX_EPOCH),
             visible: matches!(self.visibility(), LayerVisibilityHint::Visible),
+            // Filled in by the layer, which owns the access counter.
+            access_rate: 0.0,
         };
         match reset {
             LayerAccessStatsReset::NoReset => {}
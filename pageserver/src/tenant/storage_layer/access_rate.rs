@@ -0,0 +1,170 @@
+//! Exponentially decayed access counters for layers.
+//!
+//! The last access time alone does not tell a layer that is read once an hour apart from one that
+//! is read thousands of times an hour. [`DecayedAccessCounter`] keeps a count of accesses in which
+//! each access loses half of its weight every [`DecayedAccessCounter::HALF_LIFE`], from which an
+//! access rate can be estimated without keeping any history.
+//!
+//! Accesses are recorded on the GetPage path, so recording one must stay a plain atomic
+//! increment of a monotonic access count. Decay is applied when the rate is read instead: the
+//! reader folds the accesses counted since the previous read into the decayed count, as if they
+//! all happened at the time of the read. Rates are read by the eviction task and heatmap uploads
+//! every few minutes, which is negligible next to the half-life.
+
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Decayed view of a monotonic access count, see the module docs.
+#[derive(Default)]
+pub(crate) struct DecayedAccessCounter(Mutex<Folded>);
+
+#[derive(Default, Clone, Copy)]
+struct Folded {
+    /// Decayed count as of `at`.
+    value: f32,
+    /// Seconds since the epoch.
+    at: u32,
+    /// The access count that was folded into `value` last.
+    count: u64,
+}
+
+impl DecayedAccessCounter {
+    /// How long it takes for an access to lose half of its weight. Long enough to remember daily
+    /// access patterns, short enough for a formerly hot layer to cool down within a few days.
+    pub(crate) const HALF_LIFE: Duration = Duration::from_secs(24 * 3600);
+
+    fn to_secs(time: SystemTime) -> u32 {
+        time.duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs()
+            .min(u32::MAX as u64) as u32
+    }
+
+    /// The counter value decayed from `ts` to `now`.
+    fn decayed(value: f32, ts: u32, now: u32) -> f32 {
+        if value == 0.0 {
+            return 0.0;
+        }
+        // Time going backwards (clock adjustments) does not increase the value.
+        let elapsed = now.saturating_sub(ts) as f32;
+        value * (-elapsed / Self::HALF_LIFE.as_secs_f32()).exp2()
+    }
+
+    /// Folds the accesses since the previous call into the decayed count, given the current
+    /// value of the monotonic access count.
+    fn fold(&self, count: u64, now: u32) -> Folded {
+        let mut folded = self.0.lock().unwrap();
+        let new_accesses = count.saturating_sub(folded.count);
+        *folded = Folded {
+            value: Self::decayed(folded.value, folded.at, now) + new_accesses as f32,
+            at: now.max(folded.at),
+            count: count.max(folded.count),
+        };
+        *folded
+    }
+
+    fn value_to_rate(value: f32) -> f32 {
+        // For a steady rate r, the counter converges to r * HALF_LIFE / ln(2).
+        value * std::f32::consts::LN_2 * 3600.0 / Self::HALF_LIFE.as_secs_f32()
+    }
+
+    /// Estimated number of accesses per hour, given the current access count.
+    pub(crate) fn rate(&self, count: u64) -> f32 {
+        self.rate_at(count, SystemTime::now())
+    }
+
+    pub(crate) fn rate_at(&self, count: u64, now: SystemTime) -> f32 {
+        let folded = self.fold(count, Self::to_secs(now));
+        Self::value_to_rate(folded.value)
+    }
+
+    /// The current rate, expressed as of the earlier time `at`, e.g. the last access. Unlike
+    /// [`Self::rate`], this doesn't decay while the layer isn't accessed, which makes it suitable
+    /// for persisting next to the access time: use [`Self::decay_rate`] to get the current rate
+    /// from it.
+    pub(crate) fn rate_as_of(&self, count: u64, at: SystemTime) -> f32 {
+        let folded = self.fold(count, Self::to_secs(SystemTime::now()));
+        let since = folded.at.saturating_sub(Self::to_secs(at)) as f32;
+        Self::value_to_rate(folded.value * (since / Self::HALF_LIFE.as_secs_f32()).exp2())
+    }
+
+    /// Decays a rate that was observed at `observed_at` to `now`.
+    pub(crate) fn decay_rate(rate: f32, observed_at: SystemTime, now: SystemTime) -> f32 {
+        Self::decayed(rate, Self::to_secs(observed_at), Self::to_secs(now))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unused_counter_has_zero_rate() {
+        let counter = DecayedAccessCounter::default();
+        assert_eq!(counter.rate(0), 0.0);
+    }
+
+    #[test]
+    fn frequent_access_has_higher_rate() {
+        let start = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let hourly = DecayedAccessCounter::default();
+        let busy = DecayedAccessCounter::default();
+
+        // Three days of access, read once an hour: once an hour vs. 10k times an hour.
+        let (mut hourly_count, mut busy_count) = (0, 0);
+        for hour in 0..72 {
+            let at = start + Duration::from_secs(hour * 3600);
+            hourly_count += 1;
+            busy_count += 10_000;
+            hourly.rate_at(hourly_count, at);
+            busy.rate_at(busy_count, at);
+        }
+        let now = start + Duration::from_secs(72 * 3600);
+
+        let hourly_rate = hourly.rate_at(hourly_count, now);
+        let busy_rate = busy.rate_at(busy_count, now);
+        assert!(
+            (0.5..2.0).contains(&hourly_rate),
+            "hourly rate: {hourly_rate}"
+        );
+        assert!(
+            (5_000.0..20_000.0).contains(&busy_rate),
+            "busy rate: {busy_rate}"
+        );
+    }
+
+    #[test]
+    fn rate_decays() {
+        let start = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let counter = DecayedAccessCounter::default();
+
+        let rate = counter.rate_at(100, start);
+        let later = counter.rate_at(100, start + DecayedAccessCounter::HALF_LIFE);
+        assert!((later - rate / 2.0).abs() < rate * 1e-3);
+
+        // A clock going backwards does not make the layer any hotter.
+        assert_eq!(
+            counter.rate_at(100, start - Duration::from_secs(3600)),
+            later
+        );
+
+        // Accesses are only counted once.
+        assert_eq!(
+            counter.rate_at(100, start + DecayedAccessCounter::HALF_LIFE),
+            later
+        );
+    }
+
+    #[test]
+    fn rate_as_of_roundtrips_through_decay_rate() {
+        let now = SystemTime::now();
+        let counter = DecayedAccessCounter::default();
+        let rate = counter.rate(100);
+
+        let an_hour_ago = now - Duration::from_secs(3600);
+        let persisted = counter.rate_as_of(100, an_hour_ago);
+        assert!(persisted > rate);
+        let decayed = DecayedAccessCounter::decay_rate(persisted, an_hour_ago, now);
+        assert!((decayed - rate).abs() < rate * 1e-2);
+    }
+}
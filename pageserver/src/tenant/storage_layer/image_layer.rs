@@ -175,6 +175,7 @@ pub struct ImageLayerInner {
 
     key_range: Range<Key>,
     lsn: Lsn,
+    format_version: u16,
 
     file: Arc<VirtualFile>,
     file_id: FileId,
@@ -182,6 +183,15 @@ pub struct ImageLayerInner {
     max_vectored_read_bytes: Option<MaxVectoredReadBytes>,
 }
 
+/// How an image layer's contents are stored, see [`ImageLayerInner::format`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct ImageLayerFormat {
+    /// The [`STORAGE_FORMAT_VERSION`] the layer was written with.
+    pub(crate) format_version: u16,
+    /// Whether any of the sampled images is compressed.
+    pub(crate) compressed: bool,
+}
+
 impl ImageLayerInner {
     pub(crate) fn layer_dbg_info(&self) -> String {
         format!(
@@ -442,6 +452,7 @@ pub(super) async fn load(
             index_start_blk: actual_summary.index_start_blk,
             index_root_blk: actual_summary.index_root_blk,
             lsn,
+            format_version: actual_summary.format_version,
             file,
             file_id,
             max_vectored_read_bytes,
@@ -584,6 +595,74 @@ pub(super) async fn filter(
         Ok(key_count)
     }
 
+    /// The format of this layer. Compression is sampled from the first vectored read only: the
+    /// writer compresses all images or none, except for those that don't shrink, which are rare.
+    pub(super) async fn format(&self, ctx: &RequestContext) -> anyhow::Result<ImageLayerFormat> {
+        let plan = self
+            .plan_reads(
+                KeySpace {
+                    ranges: vec![Key::MIN..Key::MAX],
+                },
+                None,
+                ctx,
+            )
+            .await?;
+
+        let mut compressed = false;
+        if let Some(read) = plan.into_iter().next() {
+            let buf = IoBufferMut::with_capacity(read.size());
+            let blobs_buf = VectoredBlobReader::new(&self.file)
+                .read_blobs(&read, buf, ctx)
+                .await?;
+            compressed = blobs_buf.blobs.iter().any(|meta| meta.is_compressed());
+        }
+
+        Ok(ImageLayerFormat {
+            format_version: self.format_version,
+            compressed,
+        })
+    }
+
+    /// Write all images of this layer to the writer, decoding and re-encoding them, so that the
+    /// writer's compression settings apply. Returns the number of keys written.
+    pub(super) async fn rewrite(
+        &self,
+        writer: &mut ImageLayerWriter,
+        ctx: &RequestContext,
+    ) -> anyhow::Result<usize> {
+        let plan = self
+            .plan_reads(
+                KeySpace {
+                    ranges: vec![Key::MIN..Key::MAX],
+                },
+                None,
+                ctx,
+            )
+            .await?;
+
+        let vectored_blob_reader = VectoredBlobReader::new(&self.file);
+        let mut key_count = 0;
+        for read in plan.into_iter() {
+            let buf = IoBufferMut::with_capacity(read.size());
+            let blobs_buf = vectored_blob_reader.read_blobs(&read, buf, ctx).await?;
+            let view = BufView::new_slice(&blobs_buf.buf);
+
+            for meta in blobs_buf.blobs.iter() {
+                let img = meta
+                    .read(&view)
+                    .await
+                    .with_context(|| format!("Reading key {}", meta.meta.key))?;
+                key_count += 1;
+                writer
+                    .put_image(meta.meta.key, img.into_bytes(), ctx)
+                    .await
+                    .with_context(|| format!("Storing key {}", meta.meta.key))?;
+            }
+        }
+
+        Ok(key_count)
+    }
+
     async fn do_reads_and_update_state(
         &self,
         this: ResidentLayer,
@@ -1,5 +1,5 @@
 use std::ops::Range;
-use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
 use std::sync::{Arc, Weak};
 use std::time::{Duration, SystemTime};
 
@@ -16,14 +16,16 @@
 use utils::lsn::Lsn;
 use utils::sync::{gate, heavier_once_cell};
 
+use super::access_rate::DecayedAccessCounter;
 use super::delta_layer::{self};
 use super::image_layer::{self};
+use super::tier::{self, LayerTier};
 use super::{
     AsLayerDesc, ImageLayerWriter, LayerAccessStats, LayerAccessStatsReset, LayerName,
     LayerVisibilityHint, PerfInstrumentFutureExt, PersistentLayerDesc, ValuesReconstructState,
 };
 use crate::config::PageServerConf;
-use crate::context::{RequestContext, RequestContextBuilder};
+use crate::context::{AccessStatsBehavior, RequestContext, RequestContextBuilder};
 use crate::span::debug_assert_current_span_has_tenant_and_timeline_id;
 use crate::task_mgr::TaskKind;
 use crate::tenant::Timeline;
@@ -181,6 +183,7 @@ pub(crate) fn for_evicted(
             conf,
             timeline,
             local_path,
+            LayerTier::Fast,
             desc,
             None,
             metadata.generation,
@@ -207,6 +210,12 @@ pub(crate) fn for_resident(
             metadata.file_size,
         );
 
+        // layers found on the slow tier are given by their path there
+        let (local_path, tier) = match conf.fast_tier_path(&local_path) {
+            Some(fast_path) => (fast_path, LayerTier::Slow),
+            None => (local_path, LayerTier::Fast),
+        };
+
         let mut resident = None;
 
         let owner = Layer(Arc::new_cyclic(|owner| {
@@ -214,6 +223,8 @@ pub(crate) fn for_resident(
                 owner: owner.clone(),
                 kind: tokio::sync::OnceCell::default(),
                 version: 0,
+                tier,
+                superseded: AtomicBool::new(false),
             });
             resident = Some(inner.clone());
 
@@ -221,6 +232,7 @@ pub(crate) fn for_resident(
                 conf,
                 timeline,
                 local_path,
+                tier,
                 desc,
                 Some(inner),
                 metadata.generation,
@@ -254,6 +266,8 @@ pub(crate) fn finish_creating(
                 owner: owner.clone(),
                 kind: tokio::sync::OnceCell::default(),
                 version: 0,
+                tier: LayerTier::Fast,
+                superseded: AtomicBool::new(false),
             });
             resident = Some(inner.clone());
 
@@ -269,6 +283,7 @@ pub(crate) fn finish_creating(
                 conf,
                 timeline,
                 local_path,
+                LayerTier::Fast,
                 desc,
                 Some(inner),
                 timeline.generation,
@@ -442,8 +457,38 @@ pub(crate) fn visibility(&self) -> LayerVisibilityHint {
         self.0.access_stats.visibility()
     }
 
+    /// Estimated number of accesses per hour, see [`DecayedAccessCounter`].
+    pub(crate) fn access_rate(&self) -> f32 {
+        let accesses = self.0.access_count.load(Ordering::Relaxed);
+        self.0.access_rate.rate(accesses)
+    }
+
+    /// The access rate as of the latest access, see [`DecayedAccessCounter::rate_as_of`].
+    pub(crate) fn access_rate_at_last_access(&self) -> f32 {
+        let accesses = self.0.access_count.load(Ordering::Relaxed);
+        let at = self.0.access_stats.latest_activity();
+        self.0.access_rate.rate_as_of(accesses, at)
+    }
+
     pub(crate) fn local_path(&self) -> &Utf8Path {
-        &self.0.path
+        self.0.local_path()
+    }
+
+    /// The local tier of the layer file, see [`tier`].
+    pub(crate) fn tier(&self) -> LayerTier {
+        self.0.tier()
+    }
+
+    /// Moves the resident layer file to the given local tier, see [`tier`].
+    ///
+    /// The layer is kept resident for the duration of the move, so it cannot be evicted
+    /// concurrently. Reads are not blocked by the move. Until the copy on the previous tier has
+    /// been removed, further moves fail with [`TierMoveError::AlreadyMoving`].
+    pub(crate) async fn move_to_tier(&self, tier: LayerTier) -> Result<(), TierMoveError> {
+        let Some(resident) = self.keep_resident().await else {
+            return Err(TierMoveError::NotResident);
+        };
+        self.0.move_to_tier(tier, resident).await
     }
 
     pub(crate) fn metadata(&self) -> LayerFileMetadata {
@@ -501,6 +546,10 @@ pub(crate) fn wait_drop(&self) -> impl std::future::Future<Output = ()> + 'stati
     }
 
     fn record_access(&self, ctx: &RequestContext) {
+        if ctx.access_stats_behavior() != AccessStatsBehavior::Skip {
+            self.0.access_count.fetch_add(1, Ordering::Relaxed);
+        }
+
         if self.0.access_stats.record_access(ctx) {
             // Visibility was modified to Visible: maybe log about this
             match ctx.task_kind() {
@@ -647,9 +696,21 @@ struct LayerInner {
     /// [`Self::path`].
     conf: &'static PageServerConf,
 
-    /// Full path to the file; unclear if this should exist anymore.
+    /// Full path to the file in the workdir; unclear if this should exist anymore.
+    ///
+    /// The file is on the tier recorded in `on_slow_tier`, see [`Self::local_path`].
     path: Utf8PathBuf,
 
+    /// Full path to the file on the slow tier, if one is configured.
+    slow_path: Option<Utf8PathBuf>,
+
+    /// Is the layer file on the slow tier? Only updated while holding the InitPermit.
+    on_slow_tier: AtomicBool,
+
+    /// Set while [`Self::move_to_tier`] is moving the file, until the copy on the previous tier
+    /// has been removed.
+    tier_moving: AtomicBool,
+
     desc: PersistentLayerDesc,
 
     /// Timeline access is needed for remote timeline client and metrics.
@@ -660,6 +721,12 @@ struct LayerInner {
 
     access_stats: LayerAccessStats,
 
+    /// Number of accesses over the lifetime of this layer, kept across evictions and downloads.
+    access_count: AtomicU64,
+
+    /// Decayed view of `access_count`, see [`Layer::access_rate`].
+    access_rate: DecayedAccessCounter,
+
     /// This custom OnceCell is backed by std mutex, but only held for short time periods.
     ///
     /// Filesystem changes (download, evict) are only done while holding a permit which the
@@ -777,6 +844,7 @@ fn drop(&mut self) {
         let span = tracing::info_span!(parent: None, "layer_delete", tenant_id = %self.layer_desc().tenant_shard_id.tenant_id, shard_id=%self.layer_desc().tenant_shard_id.shard_slug(), timeline_id = %self.layer_desc().timeline_id);
 
         let path = std::mem::take(&mut self.path);
+        let slow_path = self.slow_path.take();
         let file_name = self.layer_desc().layer_name();
         let file_size = self.layer_desc().file_size;
         let meta = self.metadata();
@@ -800,7 +868,8 @@ fn drop(&mut self) {
                 return;
             };
 
-            let removed = match std::fs::remove_file(path) {
+            // a move between the tiers might have left a copy on both
+            let removed = match tier::remove_from_all_tiers(&path, slow_path.as_deref()) {
                 Ok(()) => true,
                 Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
                     // until we no longer do detaches by removing all local files before removing the
@@ -849,6 +918,7 @@ fn new(
         conf: &'static PageServerConf,
         timeline: &Arc<Timeline>,
         local_path: Utf8PathBuf,
+        tier: LayerTier,
         desc: PersistentLayerDesc,
         downloaded: Option<Arc<DownloadedLayer>>,
         generation: Generation,
@@ -877,10 +947,15 @@ fn new(
 
         LayerInner {
             conf,
+            slow_path: conf.slow_tier_path(&local_path),
             path: local_path,
+            on_slow_tier: AtomicBool::new(tier == LayerTier::Slow),
+            tier_moving: AtomicBool::new(false),
             desc,
             timeline: Arc::downgrade(timeline),
             access_stats: Default::default(),
+            access_count: AtomicU64::new(0),
+            access_rate: Default::default(),
             wanted_deleted: AtomicBool::new(false),
             inner,
             version: AtomicUsize::new(version),
@@ -904,6 +979,138 @@ fn delete_on_drop(&self) {
         }
     }
 
+    fn tier(&self) -> LayerTier {
+        if self.on_slow_tier.load(Ordering::Relaxed) {
+            LayerTier::Slow
+        } else {
+            LayerTier::Fast
+        }
+    }
+
+    /// Path of the layer file on the given tier.
+    fn path_of(&self, tier: LayerTier) -> &Utf8Path {
+        match tier {
+            LayerTier::Fast => &self.path,
+            LayerTier::Slow => self
+                .slow_path
+                .as_deref()
+                .expect("layers are only moved to the slow tier when it is configured"),
+        }
+    }
+
+    /// Path of the layer file on its current tier.
+    fn local_path(&self) -> &Utf8Path {
+        self.path_of(self.tier())
+    }
+
+    #[tracing::instrument(skip_all, fields(tenant_id = %self.desc.tenant_shard_id.tenant_id, shard_id = %self.desc.tenant_shard_id.shard_slug(), timeline_id = %self.desc.timeline_id, layer = %self))]
+    async fn move_to_tier(
+        self: &Arc<Self>,
+        tier: LayerTier,
+        resident: ResidentLayer,
+    ) -> Result<(), TierMoveError> {
+        if resident.downloaded.tier == tier {
+            return Ok(());
+        }
+
+        let timeline = self
+            .timeline
+            .upgrade()
+            .ok_or(TierMoveError::TimelineShutdown)?;
+        let gate = timeline
+            .gate
+            .enter()
+            .map_err(|_| TierMoveError::TimelineShutdown)?;
+
+        if self
+            .tier_moving
+            .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            return Err(TierMoveError::AlreadyMoving);
+        }
+
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let this = self.clone();
+        let started_at = std::time::Instant::now();
+
+        // the move completes even if this future is dropped, keeping the layer resident until then
+        Self::spawn(
+            async move {
+                let _gate = gate;
+
+                let res = this.copy_and_switch_tier(tier, resident).await;
+                if res.is_err() {
+                    this.tier_moving.store(false, Ordering::Release);
+                }
+                let _ = tx.send(res);
+            }
+            .in_current_span(),
+        );
+
+        rx.await.map_err(|_| TierMoveError::TimelineShutdown)??;
+
+        tracing::info!(
+            tier = tier.as_str(),
+            elapsed_ms = started_at.elapsed().as_millis(),
+            "moved layer to tier"
+        );
+
+        Ok(())
+    }
+
+    /// Copies the file of `resident` to `tier` and makes the layer use the copy from now on. The
+    /// previous copy is removed once `resident` and any other holders of it are dropped, see
+    /// [`Self::on_superseded_layer_drop`].
+    async fn copy_and_switch_tier(
+        self: &Arc<Self>,
+        tier: LayerTier,
+        resident: ResidentLayer,
+    ) -> Result<(), TierMoveError> {
+        let src = self.path_of(resident.downloaded.tier).to_owned();
+        let dst = self.path_of(tier).to_owned();
+
+        let conf = self.conf;
+        tokio::task::spawn_blocking({
+            let dst = dst.clone();
+            move || tier::copy_blocking(conf, &src, &dst)
+        })
+        .await
+        .expect("spawn_blocking")?;
+
+        // reads are not blocked by the switch: they either get the previous copy or the new one
+        let guard = match self.inner.get_or_init_detached().await {
+            Ok(guard) => {
+                let current = match &*guard {
+                    ResidentOrWantedEvicted::Resident(strong) => Arc::as_ptr(strong),
+                    ResidentOrWantedEvicted::WantedEvicted(weak, _) => weak.as_ptr(),
+                };
+                // holding `resident` keeps it from being evicted and replaced
+                (current == Arc::as_ptr(&resident.downloaded)).then_some(guard)
+            }
+            Err(_permit) => None,
+        };
+
+        let Some(guard) = guard else {
+            if let Err(e) = std::fs::remove_file(&dst) {
+                tracing::warn!(path=%dst, "failed to remove layer file copy: {e}");
+            }
+            return Err(TierMoveError::NotResident);
+        };
+
+        let (_previous, permit) = guard.take_and_deinit();
+        resident
+            .downloaded
+            .superseded
+            .store(true, Ordering::Relaxed);
+        self.on_slow_tier
+            .store(tier == LayerTier::Slow, Ordering::Relaxed);
+        // requested evictions of the previous copy see the layer as downloaded again
+        let _ = self.initialize_after_layer_is_on_disk(permit);
+
+        Ok(())
+    }
+
     /// Cancellation safe, however dropping the future and calling this method again might result
     /// in a new attempt to evict OR join the previously started attempt.
     #[tracing::instrument(level = tracing::Level::DEBUG, skip_all, ret, err(level = tracing::Level::DEBUG), fields(layer=%self))]
@@ -1207,7 +1414,7 @@ async fn download_and_init(
             .download_layer_file(
                 &self.desc.layer_name(),
                 &self.metadata(),
-                &self.path,
+                self.local_path(),
                 &timeline.gate,
                 &timeline.cancel,
                 ctx,
@@ -1309,6 +1516,8 @@ fn initialize_after_layer_is_on_disk(
             owner: Arc::downgrade(self),
             kind: tokio::sync::OnceCell::default(),
             version: next_version,
+            tier: self.tier(),
+            superseded: AtomicBool::new(false),
         });
 
         let waiters = self.inner.initializer_count();
@@ -1324,7 +1533,7 @@ fn initialize_after_layer_is_on_disk(
     }
 
     async fn needs_download(&self) -> Result<Option<NeedsDownload>, std::io::Error> {
-        match tokio::fs::metadata(&self.path).await {
+        match tokio::fs::metadata(self.local_path()).await {
             Ok(m) => Ok(self.is_file_present_and_good_size(&m).err()),
             Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Some(NeedsDownload::NotFound)),
             Err(e) => Err(e),
@@ -1332,7 +1541,7 @@ async fn needs_download(&self) -> Result<Option<NeedsDownload>, std::io::Error>
     }
 
     fn needs_download_blocking(&self) -> Result<Option<NeedsDownload>, std::io::Error> {
-        match self.path.metadata() {
+        match self.local_path().metadata() {
             Ok(m) => Ok(self.is_file_present_and_good_size(&m).err()),
             Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Some(NeedsDownload::NotFound)),
             Err(e) => Err(e),
@@ -1362,7 +1571,12 @@ fn info(&self, reset: LayerAccessStatsReset) -> HistoricLayerInfo {
             .map(|rowe| rowe.is_likely_resident())
             .unwrap_or(false);
 
-        let access_stats = self.access_stats.as_api_model(reset);
+        let access_stats = pageserver_api::models::LayerAccessStats {
+            access_rate: self
+                .access_rate
+                .rate(self.access_count.load(Ordering::Relaxed)),
+            ..self.access_stats.as_api_model(reset)
+        };
 
         if self.desc.is_delta {
             let lsn_range = &self.desc.lsn_range;
@@ -1420,6 +1634,24 @@ fn on_downloaded_layer_drop(self: Arc<LayerInner>, only_version: usize) {
         Self::spawn(start_evicting.instrument(span));
     }
 
+    /// `DownloadedLayer` replaced by [`Self::move_to_tier`] is being dropped, so nothing reads the
+    /// copy on its tier anymore.
+    fn on_superseded_layer_drop(self: Arc<LayerInner>, tier: LayerTier) {
+        let span = tracing::info_span!(parent: None, "layer_tier_cleanup", tenant_id = %self.desc.tenant_shard_id.tenant_id, shard_id = %self.desc.tenant_shard_id.shard_slug(), timeline_id = %self.desc.timeline_id, layer=%self, tier=tier.as_str());
+
+        Self::spawn_blocking(move || {
+            let _span = span.entered();
+
+            let path = self.path_of(tier);
+            if let Err(e) = std::fs::remove_file(path).or_else(utils::fs_ext::ignore_not_found) {
+                // the copy is replaced by the next move to this tier, or removed on restart
+                tracing::warn!(%path, "failed to remove layer file on previous tier: {e}");
+            }
+
+            self.tier_moving.store(false, Ordering::Release);
+        });
+    }
+
     async fn wait_for_turn_and_evict(
         self: Arc<LayerInner>,
         only_version: usize,
@@ -1565,8 +1797,13 @@ fn evict_blocking(
     ) -> Result<(), EvictionCancelled> {
         // now accesses to `self.inner.get_or_init*` wait on the semaphore or the `_permit`
 
-        match capture_mtime_and_remove(&self.path) {
+        match capture_mtime_and_remove(self.local_path()) {
             Ok(local_layer_mtime) => {
+                // downloads go to the fast tier, unless the copy left there by a move to the slow
+                // tier is yet to be removed
+                if !self.tier_moving.load(Ordering::Acquire) {
+                    self.on_slow_tier.store(false, Ordering::Relaxed);
+                }
                 let duration = SystemTime::now().duration_since(local_layer_mtime);
                 match duration {
                     Ok(elapsed) => {
@@ -1671,6 +1908,21 @@ pub(crate) enum EvictionError {
     Timeout,
 }
 
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum TierMoveError {
+    #[error("layer is not resident")]
+    NotResident,
+
+    #[error("layer is already being moved")]
+    AlreadyMoving,
+
+    #[error("timeline has already shutdown")]
+    TimelineShutdown,
+
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
 /// Error internal to the [`LayerInner::get_or_maybe_download`]
 #[derive(Debug, thiserror::Error)]
 pub(crate) enum DownloadError {
@@ -1728,6 +1980,10 @@ pub(crate) struct DownloadedLayer {
     // DownloadedLayer
     kind: tokio::sync::OnceCell<anyhow::Result<LayerKind>>,
     version: usize,
+    /// The tier of the file this was loaded from, kept until dropped.
+    tier: LayerTier,
+    /// Set when [`LayerInner::move_to_tier`] has replaced this with a copy on the other tier.
+    superseded: AtomicBool,
 }
 
 impl std::fmt::Debug for DownloadedLayer {
@@ -1736,6 +1992,7 @@ fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
             // owner omitted because it is always "Weak"
             .field("kind", &self.kind)
             .field("version", &self.version)
+            .field("tier", &self.tier)
             .finish()
     }
 }
@@ -1743,7 +2000,11 @@ fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
 impl Drop for DownloadedLayer {
     fn drop(&mut self) {
         if let Some(owner) = self.owner.upgrade() {
-            owner.on_downloaded_layer_drop(self.version);
+            if *self.superseded.get_mut() {
+                owner.on_superseded_layer_drop(self.tier);
+            } else {
+                owner.on_downloaded_layer_drop(self.version);
+            }
         } else {
             // Layer::drop will handle cancelling the eviction; because of drop order and
             // `DownloadedLayer` never leaking, we cannot know here if eviction was requested.
@@ -1771,6 +2032,8 @@ async fn get<'a>(
                 "these are the same, just avoiding the upgrade"
             );
 
+            let path = owner.path_of(self.tier);
+
             let res = if owner.desc.is_delta {
                 let ctx = RequestContextBuilder::from(ctx)
                     .page_content_kind(crate::context::PageContentKind::DeltaLayerSummary)
@@ -1782,7 +2045,7 @@ async fn get<'a>(
                     owner.desc.lsn_range.clone(),
                 ));
                 delta_layer::DeltaLayerInner::load(
-                    &owner.path,
+                    path,
                     summary,
                     Some(owner.conf.max_vectored_read_bytes),
                     &ctx,
@@ -1801,7 +2064,7 @@ async fn get<'a>(
                     lsn,
                 ));
                 image_layer::ImageLayerInner::load(
-                    &owner.path,
+                    path,
                     lsn,
                     summary,
                     Some(owner.conf.max_vectored_read_bytes),
@@ -1818,8 +2081,7 @@ async fn get<'a>(
                     // We log this message once over the lifetime of `Self`
                     // => Ok and good to log backtrace and path here.
                     tracing::error!(
-                        "layer load failed, assuming permanent failure: {}: {err:?}",
-                        owner.path
+                        "layer load failed, assuming permanent failure: {path}: {err:?}"
                     );
                     Err(err)
                 }
@@ -1956,6 +2218,35 @@ pub(crate) async fn filter(
         }
     }
 
+    /// The storage format of an image layer, see [`image_layer::ImageLayerInner::format`].
+    pub(crate) async fn image_format(
+        &self,
+        ctx: &RequestContext,
+    ) -> anyhow::Result<image_layer::ImageLayerFormat> {
+        use LayerKind::*;
+
+        match self.downloaded.get(&self.owner.0, ctx).await? {
+            Delta(_) => anyhow::bail!("cannot get the image format of a delta layer {self}"),
+            Image(i) => i.format(ctx).await,
+        }
+    }
+
+    /// Re-encode all images of this layer into the provided writer. Return the number of keys
+    /// written.
+    #[tracing::instrument(level = tracing::Level::DEBUG, skip_all, fields(layer=%self))]
+    pub(crate) async fn rewrite_image(
+        &self,
+        writer: &mut ImageLayerWriter,
+        ctx: &RequestContext,
+    ) -> anyhow::Result<usize> {
+        use LayerKind::*;
+
+        match self.downloaded.get(&self.owner.0, ctx).await? {
+            Delta(_) => anyhow::bail!("cannot rewrite_image() on a delta layer {self}"),
+            Image(i) => i.rewrite(writer, ctx).await,
+        }
+    }
+
     /// Returns the amount of keys and values written to the writer.
     pub(crate) async fn copy_delta_prefix(
         &self,
@@ -1976,8 +2267,10 @@ pub(crate) async fn copy_delta_prefix(
         }
     }
 
+    /// Path of the file this resident layer reads, which stays in place while it is held even if
+    /// the layer is moved to another tier.
     pub(crate) fn local_path(&self) -> &Utf8Path {
-        &self.owner.0.path
+        self.owner.0.path_of(self.downloaded.tier)
     }
 
     pub(crate) fn metadata(&self) -> LayerFileMetadata {
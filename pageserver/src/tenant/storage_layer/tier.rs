@@ -0,0 +1,137 @@
+//! Tiered local storage for layer files.
+//!
+//! The workdir is the fast tier: layer files are downloaded and written there. When a
+//! [slow tier](pageserver_api::config::SlowLayerTierConfig) is configured, the disk usage eviction
+//! task demotes cold resident layers to it instead of evicting them to remote storage, and promotes
+//! them back once they are accessed often enough again.
+//!
+//! The slow tier mirrors the workdir layout, see [`PageServerConf::slow_tier_path`]. Each layer
+//! records the tier its file is on, and resolves its local path through it. A move copies the file
+//! to the other tier and switches the layer over to the copy; readers which already hold the layer
+//! resident keep reading the previous copy, which is removed once the last of them is done.
+//!
+//! On startup both tiers are scanned for layer files. A layer found on both, because the process
+//! stopped before the previous copy was removed, keeps the workdir copy.
+
+use camino::Utf8Path;
+use utils::crashsafe::{fsync, fsync_file_and_parent, path_with_suffix_extension};
+
+use crate::TEMP_FILE_SUFFIX;
+use crate::config::PageServerConf;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum LayerTier {
+    Fast,
+    Slow,
+}
+
+impl LayerTier {
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            LayerTier::Fast => "fast",
+            LayerTier::Slow => "slow",
+        }
+    }
+}
+
+/// Copies the layer file at `src` to `dst` on another tier, creating the parent directories.
+///
+/// The copy is written to a temporary file first, so that `dst` is either absent or complete.
+pub(crate) fn copy_blocking(
+    conf: &PageServerConf,
+    src: &Utf8Path,
+    dst: &Utf8Path,
+) -> std::io::Result<()> {
+    let parent = dst.parent().expect("layer paths have a parent");
+    utils::crashsafe::create_dir_all(parent)?;
+
+    let temp_path = path_with_suffix_extension(dst, TEMP_FILE_SUFFIX);
+    let res = (|| {
+        std::fs::copy(src, &temp_path)?;
+        if !conf.no_sync {
+            fsync(&temp_path)?;
+        }
+        std::fs::rename(&temp_path, dst)?;
+        if !conf.no_sync {
+            fsync_file_and_parent(dst)?;
+        }
+        Ok(())
+    })();
+
+    if res.is_err() {
+        let _ = std::fs::remove_file(&temp_path);
+    }
+    res
+}
+
+/// Removes the layer file from both tiers.
+///
+/// Fails with [`std::io::ErrorKind::NotFound`] if there was no file to remove on either tier.
+pub(crate) fn remove_from_all_tiers(
+    path: &Utf8Path,
+    slow_path: Option<&Utf8Path>,
+) -> std::io::Result<()> {
+    let mut removed = false;
+    for path in std::iter::once(path).chain(slow_path) {
+        match std::fs::remove_file(path) {
+            Ok(()) => removed = true,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+    }
+
+    if removed {
+        Ok(())
+    } else {
+        Err(std::io::ErrorKind::NotFound.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use camino::Utf8PathBuf;
+
+    use super::*;
+
+    fn test_conf(test_name: &str) -> (PageServerConf, Utf8PathBuf, Utf8PathBuf) {
+        let workdir = PageServerConf::test_repo_dir(test_name);
+        let slow = PageServerConf::test_repo_dir(&format!("{test_name}_slow"));
+        let mut conf = PageServerConf::dummy_conf(workdir.clone());
+        conf.slow_layer_tier = Some(pageserver_api::config::SlowLayerTierConfig {
+            path: slow.clone(),
+            max_usage_pct: utils::serde_percent::Percent::new(90).unwrap(),
+            min_avail_bytes: 0,
+            promote_min_accesses_per_hour: None,
+        });
+        (conf, workdir, slow)
+    }
+
+    #[test]
+    fn copy_between_tiers_and_remove() {
+        let (conf, workdir, slow) = test_conf("tier_copy");
+
+        let timeline_dir = workdir.join("tenants/t/timelines/tl");
+        std::fs::create_dir_all(&timeline_dir).unwrap();
+        let path = timeline_dir.join("layer");
+        std::fs::write(&path, b"layer contents").unwrap();
+
+        let slow_path = conf.slow_tier_path(&path).unwrap();
+        assert_eq!(slow_path, slow.join("tenants/t/timelines/tl/layer"));
+
+        copy_blocking(&conf, &path, &slow_path).unwrap();
+        assert_eq!(std::fs::read(&slow_path).unwrap(), b"layer contents");
+        assert!(!path_with_suffix_extension(&slow_path, TEMP_FILE_SUFFIX).exists());
+
+        // copying over an existing copy replaces it
+        std::fs::write(&path, b"other contents").unwrap();
+        copy_blocking(&conf, &path, &slow_path).unwrap();
+        assert_eq!(std::fs::read(&slow_path).unwrap(), b"other contents");
+
+        remove_from_all_tiers(&path, Some(&slow_path)).unwrap();
+        assert!(!path.exists());
+        assert!(!slow_path.exists());
+
+        let e = remove_from_all_tiers(&path, Some(&slow_path)).unwrap_err();
+        assert_eq!(e.kind(), std::io::ErrorKind::NotFound);
+    }
+}
@@ -1,4 +1,4 @@
-//! This module contains per-tenant background processes, e.g. compaction and GC.
+//! This module contains per-tenant background processes, e.g. GC and housekeeping.
 
 use std::cmp::max;
 use std::future::Future;
@@ -23,8 +23,7 @@
 use crate::task_mgr::{self, BACKGROUND_RUNTIME, TOKIO_WORKER_THREADS, TaskKind};
 use crate::tenant::throttle::Stats;
 use crate::tenant::timeline::CompactionError;
-use crate::tenant::timeline::compaction::CompactionOutcome;
-use crate::tenant::{TenantShard, TenantState};
+use crate::tenant::{TenantShard, TenantState, compaction_scheduler};
 
 /// Semaphore limiting concurrent background tasks (across all tenants).
 ///
@@ -131,35 +130,12 @@ pub(crate) async fn acquire_concurrency_permit(
     }
 }
 
-/// Start per tenant background loops: compaction, GC, and ingest housekeeping.
+/// Start per tenant background loops: GC and ingest housekeeping. Compaction is run by the per-node
+/// [`compaction_scheduler`], which the tenant registers with here.
 pub fn start_background_loops(tenant: &Arc<TenantShard>, can_start: Option<&Barrier>) {
     let tenant_shard_id = tenant.tenant_shard_id;
 
-    task_mgr::spawn(
-        BACKGROUND_RUNTIME.handle(),
-        TaskKind::Compaction,
-        tenant_shard_id,
-        None,
-        &format!("compactor for tenant {tenant_shard_id}"),
-        {
-            let tenant = Arc::clone(tenant);
-            let can_start = can_start.cloned();
-            async move {
-                let cancel = task_mgr::shutdown_token(); // NB: must be in async context
-                tokio::select! {
-                    _ = cancel.cancelled() => return Ok(()),
-                    _ = Barrier::maybe_wait(can_start) => {}
-                };
-                TENANT_TASK_EVENTS.with_label_values(&["start"]).inc();
-                defer!(TENANT_TASK_EVENTS.with_label_values(&["stop"]).inc());
-                compaction_loop(tenant, cancel)
-                    // If you rename this span, change the RUST_LOG env variable in test_runner/performance/test_branch_creation.py
-                    .instrument(info_span!("compaction_loop", tenant_id = %tenant_shard_id.tenant_id, shard_id = %tenant_shard_id.shard_slug()))
-                    .await;
-                Ok(())
-            }
-        },
-    );
+    compaction_scheduler::register(tenant, can_start);
 
     task_mgr::spawn(
         BACKGROUND_RUNTIME.handle(),
@@ -212,95 +188,6 @@ pub fn start_background_loops(tenant: &Arc<TenantShard>, can_start: Option<&Barr
     );
 }
 
-/// Compaction task's main loop.
-async fn compaction_loop(tenant: Arc<TenantShard>, cancel: CancellationToken) {
-    const BASE_BACKOFF_SECS: f64 = 1.0;
-    const MAX_BACKOFF_SECS: f64 = 300.0;
-    const RECHECK_CONFIG_INTERVAL: Duration = Duration::from_secs(10);
-
-    let ctx = RequestContext::todo_child(TaskKind::Compaction, DownloadBehavior::Download);
-    let mut period = tenant.get_compaction_period();
-    let mut error_run = 0; // consecutive errors
-
-    // Stagger the compaction loop across tenants.
-    if wait_for_active_tenant(&tenant, &cancel).await.is_break() {
-        return;
-    }
-    if sleep_random(period, &cancel).await.is_err() {
-        return;
-    }
-
-    loop {
-        // Recheck that we're still active.
-        if wait_for_active_tenant(&tenant, &cancel).await.is_break() {
-            return;
-        }
-
-        // Refresh the period. If compaction is disabled, check again in a bit.
-        period = tenant.get_compaction_period();
-        if period == Duration::ZERO {
-            #[cfg(not(feature = "testing"))]
-            info!("automatic compaction is disabled");
-            tokio::select! {
-                _ = tokio::time::sleep(RECHECK_CONFIG_INTERVAL) => {},
-                _ = cancel.cancelled() => return,
-            }
-            continue;
-        }
-
-        // Wait for the next compaction run.
-        let backoff = exponential_backoff_duration(error_run, BASE_BACKOFF_SECS, MAX_BACKOFF_SECS);
-        tokio::select! {
-            _ = tokio::time::sleep(backoff), if error_run > 0 => {},
-            _ = tokio::time::sleep(period), if error_run == 0 => {},
-            _ = tenant.l0_compaction_trigger.notified(), if error_run == 0 => {},
-            _ = cancel.cancelled() => return,
-        }
-
-        // Run compaction.
-        let iteration = Iteration {
-            started_at: Instant::now(),
-            period,
-            kind: BackgroundLoopKind::Compaction,
-        };
-        let IterationResult { output, elapsed } = iteration
-            .run(tenant.compaction_iteration(&cancel, &ctx))
-            .await;
-
-        match output {
-            Ok(outcome) => {
-                error_run = 0;
-                // If there's more compaction work, L0 or not, schedule an immediate run.
-                match outcome {
-                    CompactionOutcome::Done => {}
-                    CompactionOutcome::Skipped => {}
-                    CompactionOutcome::YieldForL0 => tenant.l0_compaction_trigger.notify_one(),
-                    CompactionOutcome::Pending => tenant.l0_compaction_trigger.notify_one(),
-                }
-            }
-
-            Err(err) => {
-                error_run += 1;
-                let backoff =
-                    exponential_backoff_duration(error_run, BASE_BACKOFF_SECS, MAX_BACKOFF_SECS);
-                log_compaction_error(
-                    &err,
-                    Some((error_run, backoff)),
-                    cancel.is_cancelled(),
-                    false,
-                );
-                continue;
-            }
-        }
-
-        // NB: this log entry is recorded by performance tests.
-        debug!(
-            elapsed_ms = elapsed.as_millis(),
-            "compaction iteration complete"
-        );
-    }
-}
-
 pub(crate) fn log_compaction_error(
     err: &CompactionError,
     retry_info: Option<(u32, Duration)>,
@@ -536,15 +423,15 @@ pub(crate) async fn sleep_jitter(
     sleep_random_range(from..=to, cancel).await
 }
 
-struct Iteration {
-    started_at: Instant,
-    period: Duration,
-    kind: BackgroundLoopKind,
+pub(crate) struct Iteration {
+    pub(crate) started_at: Instant,
+    pub(crate) period: Duration,
+    pub(crate) kind: BackgroundLoopKind,
 }
 
-struct IterationResult<O> {
-    output: O,
-    elapsed: Duration,
+pub(crate) struct IterationResult<O> {
+    pub(crate) output: O,
+    pub(crate) elapsed: Duration,
 }
 
 impl Iteration {
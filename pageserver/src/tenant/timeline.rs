@@ -3,13 +3,17 @@
 pub mod delete;
 pub(crate) mod detach_ancestor;
 mod eviction_task;
+pub(crate) mod export_pgdata;
 pub(crate) mod handle;
 mod heatmap_layers_downloader;
 pub(crate) mod import_pgdata;
 mod init;
 pub mod layer_manager;
+pub(crate) mod layer_rewrite;
 pub(crate) mod logical_size;
 pub mod offload;
+pub(crate) mod read_amp;
+pub(crate) mod reset;
 pub mod span;
 pub mod uninit;
 mod walreceiver;
@@ -48,10 +52,12 @@
 };
 use pageserver_api::keyspace::{KeySpaceAccum, KeySpaceRandomAccum, SparseKeyPartitioning};
 use pageserver_api::models::{
-    CompactKeyRange, CompactLsnRange, CompactionAlgorithm, CompactionAlgorithmSettings,
-    DetachBehavior, DownloadRemoteLayersTaskInfo, DownloadRemoteLayersTaskSpawnRequest,
-    EvictionPolicy, InMemoryLayerInfo, LayerMapInfo, LsnLease, PageTraceEvent, RelSizeMigration,
-    TimelineState,
+    BasebackupCompression, CompactKeyRange, CompactLsnRange, CompactionAlgorithm,
+    CompactionAlgorithmSettings, DetachBehavior, DownloadRemoteLayersTaskInfo,
+    DownloadRemoteLayersTaskSpawnRequest, EvictionPolicy, GcRetainReason, GcRetainSummary,
+    GcRetainedLayer, HistoricLayerInfo, ImageCreationPolicy, ImageCreationPolicyReadAmplification,
+    InMemoryLayerInfo, LayerMapInfo, LsnLease, PageTraceEvent, ReadAmpRangeInfo, RelSizeMigration,
+    SizeLimitExceeded, TimelineGcDryRunResponse, TimelineState,
 };
 use pageserver_api::reltag::{BlockNumber, RelTag};
 use pageserver_api::shard::{ShardIdentity, ShardIndex, ShardNumber, TenantShardId};
@@ -118,6 +124,7 @@
     MAX_AUX_FILE_V2_DELTAS, MetricsUpdate,
 };
 use crate::task_mgr::TaskKind;
+use crate::tenant::compaction_scheduler;
 use crate::tenant::gc_result::GcResult;
 use crate::tenant::layer_map::LayerMap;
 use crate::tenant::metadata::TimelineMetadata;
@@ -348,6 +355,10 @@ pub struct Timeline {
     /// Configuration: how often should the partitioning be recalculated.
     repartition_threshold: u64,
 
+    /// Read amplification sampled from GetPage requests, for
+    /// [`ImageCreationPolicy::ReadAmplification`].
+    pub(crate) read_amp_stats: read_amp::ReadAmpStats,
+
     last_image_layer_creation_check_at: AtomicLsn,
     last_image_layer_creation_check_instant: std::sync::Mutex<Option<Instant>>,
 
@@ -402,6 +413,15 @@ pub struct Timeline {
     /// appropriate action.
     corruption_detected: AtomicBool,
 
+    /// The size limit this timeline currently exceeds, if any. Reported to safekeepers and the
+    /// compute via `PageserverFeedback`, so that the compute enters read-only mode. Re-evaluated
+    /// by the walreceiver on every status update, see [`super::size_limits`].
+    size_limit_exceeded: Mutex<Option<SizeLimitExceeded>>,
+
+    /// Accurate sizes from the last tenant housekeeping, which [`Self::check_size_limits`]
+    /// extrapolates from. `None` if the tenant has no size limits.
+    size_limit_baseline: Mutex<Option<super::size_limits::IngestBaseline>>,
+
     /// Notifies the tenant compaction loop that there is pending L0 compaction work.
     l0_compaction_trigger: Arc<Notify>,
 
@@ -446,6 +466,12 @@ pub struct Timeline {
     /// heatmap on demand.
     heatmap_layers_downloader: Mutex<Option<heatmap_layers_downloader::HeatmapLayersDownloader>>,
 
+    /// The running export task, see [`export_pgdata`].
+    export_pgdata_task: Mutex<Option<tokio::task::JoinHandle<()>>>,
+
+    /// The running layer rewrite task, see [`layer_rewrite`].
+    layer_rewrite_task: Mutex<Option<tokio::task::JoinHandle<()>>>,
+
     pub(crate) rel_size_v2_status: ArcSwap<(Option<RelSizeMigration>, Option<Lsn>)>,
 
     wait_lsn_log_slow: tokio::sync::Semaphore,
@@ -569,6 +595,89 @@ fn select_min(&self) -> Lsn {
     }
 }
 
+/// The constraints for which GC keeps layers, see [`Timeline::gc_retention`].
+struct GcRetention {
+    space_cutoff: Lsn,
+    /// None if uninitialized.
+    time_cutoff: Option<Lsn>,
+    retain_lsns: Vec<Lsn>,
+    max_lsn_with_valid_lease: Option<Lsn>,
+    new_gc_cutoff: Lsn,
+}
+
+impl GcRetention {
+    /// Returns the first constraint that keeps GC from removing the layer, if any.
+    ///
+    /// GC removes the layer if all conditions are satisfied:
+    /// 1. it is older than cutoff LSN;
+    /// 2. it is older than PITR interval;
+    /// 3. it doesn't need to be retained for 'retain_lsns';
+    /// 4. it does not need to be kept for LSNs holding valid leases.
+    /// 5. newer on-disk image layers cover the layer's whole key range
+    fn retain_reason(&self, layers: &LayerMap, l: &PersistentLayerDesc) -> Option<GcRetainReason> {
+        // 1. Is it newer than GC horizon cutoff point?
+        if l.get_lsn_range().end > self.space_cutoff {
+            return Some(GcRetainReason::GcHorizon);
+        }
+
+        // 2. It is newer than PiTR cutoff point? GC skips the timeline if it isn't computed yet,
+        // see `Timeline::gc_dry_run`.
+        if let Some(time_cutoff) = self.time_cutoff {
+            if l.get_lsn_range().end > time_cutoff {
+                return Some(GcRetainReason::Pitr);
+            }
+        }
+
+        // 3. Is it needed by a child branch?
+        // NOTE With that we would keep data that
+        // might be referenced by child branches forever.
+        // We can track this in child timeline GC and delete parent layers when
+        // they are no longer needed. This might be complicated with long inheritance chains.
+        if let Some(retain_lsn) = self.retain_lsns.iter().max() {
+            // start_lsn is inclusive
+            if &l.get_lsn_range().start <= retain_lsn {
+                return Some(GcRetainReason::Branch);
+            }
+        }
+
+        // 4. Is there a valid lease that requires us to keep this layer?
+        if let Some(lsn) = &self.max_lsn_with_valid_lease {
+            // keep if layer start <= any of the lease
+            if &l.get_lsn_range().start <= lsn {
+                return Some(GcRetainReason::Lease);
+            }
+        }
+
+        // 5. Is there a later on-disk layer for this relation?
+        //
+        // The end-LSN is exclusive, while disk_consistent_lsn is
+        // inclusive. For example, if disk_consistent_lsn is 100, it is
+        // OK for a delta layer to have end LSN 101, but if the end LSN
+        // is 102, then it might not have been fully flushed to disk
+        // before crash.
+        //
+        // For example, imagine that the following layers exist:
+        //
+        // 1000      - image (A)
+        // 1000-2000 - delta (B)
+        // 2000      - image (C)
+        // 2000-3000 - delta (D)
+        // 3000      - image (E)
+        //
+        // If GC horizon is at 2500, we can remove layers A and B, but
+        // we cannot remove C, even though it's older than 2500, because
+        // the delta layer 2000-3000 depends on it.
+        if !layers.image_layer_exists(
+            &l.get_key_range(),
+            &(l.get_lsn_range().end..self.new_gc_cutoff),
+        ) {
+            return Some(GcRetainReason::NoNewerImage);
+        }
+
+        None
+    }
+}
+
 pub(crate) struct TimelineVisitOutcome {
     completed_keyspace: KeySpace,
     image_covered_keyspace: KeySpace,
@@ -1611,6 +1720,12 @@ pub(super) async fn get_vectored_impl(
             //
             // * LAYERS_PER_READ_AMORTIZED: the average layer count per read, to get the amortized
             //   read amplification after batching.
+            if ctx.task_kind() == TaskKind::PageRequestHandler {
+                let first_key = *results.keys().next().expect("results are not empty");
+                self.read_amp_stats
+                    .maybe_record(first_key, layers_visited, results.len());
+            }
+
             let layers_visited = layers_visited as f64;
             let avg_layers_visited = layers_visited / results.len() as f64;
             LAYERS_PER_READ_BATCH_GLOBAL.observe(layers_visited);
@@ -1769,6 +1884,66 @@ pub(crate) fn resident_physical_size(&self) -> u64 {
         self.metrics.resident_physical_size_get()
     }
 
+    /// Sizes of this timeline as used for size limit enforcement.
+    ///
+    /// The logical size is only maintained on shard zero, other shards report zero.
+    pub(crate) async fn size_limit_sizes(&self) -> super::size_limits::TimelineSizes {
+        super::size_limits::TimelineSizes {
+            logical: self.size_limit_logical_size(),
+            physical: self.layer_size_sum().await,
+        }
+    }
+
+    fn size_limit_logical_size(&self) -> u64 {
+        if self.tenant_shard_id.is_shard_zero() {
+            self.current_logical_size
+                .current_size()
+                .size_dont_care_about_accuracy()
+        } else {
+            0
+        }
+    }
+
+    pub(crate) fn get_size_limit_exceeded(&self) -> Option<SizeLimitExceeded> {
+        *self.size_limit_exceeded.lock().unwrap()
+    }
+
+    /// Replaces the baseline the ingest path enforces the size limits against, and re-evaluates
+    /// the limits with it.
+    pub(crate) fn set_size_limit_baseline(
+        &self,
+        baseline: Option<super::size_limits::IngestBaseline>,
+    ) {
+        *self.size_limit_baseline.lock().unwrap() = baseline;
+        self.check_size_limits();
+    }
+
+    /// Re-evaluates the size limits against the current logical size and last record LSN.
+    /// Cheap enough to be called on the ingest path.
+    pub(crate) fn check_size_limits(&self) -> Option<SizeLimitExceeded> {
+        let baseline = *self.size_limit_baseline.lock().unwrap();
+        let exceeded = baseline.and_then(|baseline| {
+            baseline.evaluate(self.size_limit_logical_size(), self.get_last_record_lsn())
+        });
+        self.set_size_limit_exceeded(exceeded);
+        exceeded
+    }
+
+    fn set_size_limit_exceeded(&self, exceeded: Option<SizeLimitExceeded>) {
+        let prev = std::mem::replace(&mut *self.size_limit_exceeded.lock().unwrap(), exceeded);
+        if prev != exceeded {
+            match exceeded {
+                Some(limit) => {
+                    warn!(%limit, "size limit exceeded, asking compute to enter read-only mode")
+                }
+                None => info!("size limits no longer exceeded, compute may leave read-only mode"),
+            }
+        }
+        self.metrics
+            .size_limit_exceeded_gauge
+            .set(exceeded.is_some() as u64);
+    }
+
     pub(crate) fn get_directory_metrics(&self) -> [u64; DirectoryKind::KINDS_NUM] {
         array::from_fn(|idx| self.directory_metrics[idx].load(AtomicOrdering::Relaxed))
     }
@@ -2317,6 +2492,8 @@ pub(crate) fn activate(
         self.launch_wal_receiver(ctx, broker_client);
         self.set_state(TimelineState::Active);
         self.launch_eviction_task(parent, background_jobs_can_start);
+        self.resume_export_pgdata(ctx);
+        self.resume_layer_rewrite(ctx);
     }
 
     /// After this function returns, there are no timeline-scoped tasks are left running.
@@ -2619,6 +2796,33 @@ pub(crate) async fn layer_map_info(
         })
     }
 
+    /// The `limit` historic layers with the highest access rate, hottest first.
+    pub(crate) async fn hottest_layers(
+        &self,
+        limit: usize,
+    ) -> Result<Vec<HistoricLayerInfo>, layer_manager::Shutdown> {
+        let guard = self
+            .layers
+            .read(LayerManagerLockHolder::GetLayerMapInfo)
+            .await;
+        let layer_map = guard.layer_map()?;
+
+        let mut layers = layer_map
+            .iter_historic_layers()
+            .map(|desc| {
+                let layer = guard.get_from_desc(&desc);
+                (layer.access_rate(), layer)
+            })
+            .collect::<Vec<_>>();
+        layers.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+
+        Ok(layers
+            .into_iter()
+            .take(limit)
+            .map(|(_, layer)| layer.info(LayerAccessStatsReset::NoReset))
+            .collect())
+    }
+
     #[instrument(skip_all, fields(tenant_id = %self.tenant_shard_id.tenant_id, shard_id = %self.tenant_shard_id.shard_slug(), timeline_id = %self.timeline_id))]
     pub(crate) async fn download_layer(
         &self,
@@ -2743,15 +2947,20 @@ pub async fn get_cached_basebackup_if_enabled(
         prev_lsn: Option<Lsn>,
         full: bool,
         replica: bool,
-        gzip: bool,
+        compression: Option<BasebackupCompression>,
     ) -> Option<tokio::fs::File> {
         if !self.is_basebackup_cache_enabled() || !self.basebackup_cache.is_enabled() {
             return None;
         }
         // We have to know which LSN to fetch the basebackup for.
         let lsn = lsn?;
-        // We only cache gzipped, non-full basebackups for primary computes with automatic prev_lsn.
-        if prev_lsn.is_some() || full || replica || !gzip {
+        // We only cache compressed, non-full basebackups for primary computes with automatic
+        // prev_lsn, in the compression the cache is configured for.
+        if prev_lsn.is_some()
+            || full
+            || replica
+            || compression != self.basebackup_cache.compression()
+        {
             return None;
         }
         self.get_cached_basebackup(lsn).await
@@ -3015,6 +3224,14 @@ fn get_image_creation_threshold(&self) -> usize {
             .unwrap_or(self.conf.default_tenant_conf.image_creation_threshold)
     }
 
+    fn get_image_creation_policy(&self) -> ImageCreationPolicy {
+        let tenant_conf = self.tenant_conf.load();
+        tenant_conf
+            .tenant_conf
+            .image_creation_policy
+            .unwrap_or(self.conf.default_tenant_conf.image_creation_policy)
+    }
+
     // HADRON
     fn get_image_layer_force_creation_period(&self) -> Option<Duration> {
         let tenant_conf = self.tenant_conf.load();
@@ -3295,6 +3512,7 @@ pub(super) fn new(
                     Lsn(0),
                 )),
                 repartition_threshold: 0,
+                read_amp_stats: Default::default(),
                 last_image_layer_creation_check_at: AtomicLsn::new(0),
                 last_image_layer_creation_check_instant: Mutex::new(None),
                 last_received_wal: Mutex::new(None),
@@ -3316,6 +3534,8 @@ pub(super) fn new(
                 compaction_lock: tokio::sync::Mutex::default(),
                 compaction_failed: AtomicBool::default(),
                 corruption_detected: AtomicBool::default(),
+                size_limit_exceeded: Mutex::new(None),
+                size_limit_baseline: Mutex::new(None),
                 l0_compaction_trigger: resources.l0_compaction_trigger,
                 gc_lock: tokio::sync::Mutex::default(),
 
@@ -3341,6 +3561,8 @@ pub(super) fn new(
                 previous_heatmap: ArcSwapOption::from_pointee(previous_heatmap),
 
                 heatmap_layers_downloader: Mutex::new(None),
+                export_pgdata_task: Mutex::new(None),
+                layer_rewrite_task: Mutex::new(None),
 
                 rel_size_v2_status: ArcSwap::from_pointee((
                     rel_size_v2_status,
@@ -3552,7 +3774,10 @@ pub(super) async fn load_layer_map(
         let (loaded_layers, needs_cleanup, total_physical_size) = tokio::task::spawn_blocking({
             move || {
                 let _g = span.entered();
-                let discovered = init::scan_timeline_dir(&timeline_path)?;
+                let mut discovered = init::scan_timeline_dir(&timeline_path)?;
+                if let Some(slow_timeline_path) = conf.slow_tier_path(&timeline_path) {
+                    init::scan_slow_tier_timeline_dir(&slow_timeline_path, &mut discovered)?;
+                }
                 let mut discovered_layers = Vec::with_capacity(discovered.len());
                 let mut unrecognized_files = Vec::new();
 
@@ -4217,7 +4442,13 @@ pub(crate) async fn generate_heatmap(&self) -> Option<HeatMapTimeline> {
                     return None;
                 }
 
-                Some((desc, hl.metadata.clone(), hl.access_time, hl.cold))
+                Some((
+                    desc,
+                    hl.metadata.clone(),
+                    hl.access_time,
+                    hl.cold,
+                    hl.access_rate,
+                ))
             })),
             Some(PreviousHeatmap::Obsolete) => None,
             None => None,
@@ -4234,6 +4465,7 @@ pub(crate) async fn generate_heatmap(&self) -> Option<HeatMapTimeline> {
                         layer.metadata(),
                         last_activity_ts,
                         false, // these layers are not cold
+                        layer.access_rate_at_last_access(),
                     ))
                 }
                 LayerVisibilityHint::Covered => {
@@ -4265,7 +4497,7 @@ pub(crate) async fn generate_heatmap(&self) -> Option<HeatMapTimeline> {
         //   only exist for a few minutes before being compacted into L1s.
         // - For L1 & image layers, download most recent LSNs first: the older the LSN, the sooner
         //   the layer is likely to be covered by an image layer during compaction.
-        layers.sort_by_key(|(desc, _meta, _atime, cold)| {
+        layers.sort_by_key(|(desc, _meta, _atime, cold, _rate)| {
             std::cmp::Reverse((
                 *cold,
                 !LayerMap::is_l0(&desc.key_range, desc.is_delta),
@@ -4275,8 +4507,8 @@ pub(crate) async fn generate_heatmap(&self) -> Option<HeatMapTimeline> {
 
         let layers = layers
             .into_iter()
-            .map(|(desc, meta, atime, cold)| {
-                HeatMapLayer::new(desc.layer_name(), meta, atime, cold)
+            .map(|(desc, meta, atime, cold, rate)| {
+                HeatMapLayer::new(desc.layer_name(), meta, atime, cold, rate)
             })
             .collect();
 
@@ -4301,6 +4533,7 @@ pub(super) async fn generate_unarchival_heatmap(&self, end_lsn: Lsn) -> Previous
                 metadata: vl.metadata(),
                 access_time: now,
                 cold: true,
+                access_rate: 0.0,
             };
             heatmap_layers.push(hl);
         }
@@ -5055,6 +5288,7 @@ async fn flush_loop(
                 if l0_count >= self.get_compaction_threshold() {
                     self.l0_compaction_trigger.notify_one();
                 }
+                compaction_scheduler::urgency_changed(self.tenant_shard_id);
 
                 // Delay the next flush to backpressure if compaction can't keep up. We delay by the
                 // flush duration such that the flush takes 2x as long. This is propagated up to WAL
@@ -5503,6 +5737,49 @@ async fn repartition(
         Ok(result)
     }
 
+    /// Whether GetPage requests in `partition` visit enough layers to warrant a new image layer
+    /// under [`ImageCreationPolicy::ReadAmplification`].
+    fn partition_read_amp_exceeds(
+        &self,
+        partition: &KeySpace,
+        policy: ImageCreationPolicyReadAmplification,
+    ) -> bool {
+        let read_amp = self.read_amp_stats.get(&partition.ranges);
+        let exceeds = read_amp.reads >= policy.min_sampled_reads as f64
+            && read_amp.layers_per_read() >= policy.layers_per_read_threshold as f64;
+        if exceeds {
+            debug!(
+                "key range {}-{} has {:.1} layers per read over {:.1} sampled reads",
+                partition.ranges[0].start,
+                partition.ranges[0].end,
+                read_amp.layers_per_read(),
+                read_amp.reads
+            );
+        }
+        exceeds
+    }
+
+    /// Sampled read amplification of the current partitions that have been read.
+    pub(crate) fn read_amp_by_partition(&self) -> Vec<ReadAmpRangeInfo> {
+        let partitioning = self.partitioning.read();
+        let ((dense_partitioning, _), _) = partitioning.as_ref();
+        let mut result = Vec::new();
+        for partition in &dense_partitioning.parts {
+            let (Some(start), Some(end)) = (partition.start(), partition.end()) else {
+                continue;
+            };
+            let read_amp = self.read_amp_stats.get(&partition.ranges);
+            if read_amp.reads > 0.0 {
+                result.push(ReadAmpRangeInfo {
+                    key_range: (start..end).into(),
+                    sampled_reads: read_amp.reads,
+                    layers_per_read: read_amp.layers_per_read(),
+                });
+            }
+        }
+        result
+    }
+
     // Is it time to create a new image layer for the given partition? True if we want to generate.
     async fn time_for_new_image_layer(
         &self,
@@ -5511,6 +5788,14 @@ async fn time_for_new_image_layer(
         force_image_creation_lsn: Option<Lsn>,
     ) -> bool {
         let threshold = self.get_image_creation_threshold();
+        // With the read amplification policy, any delta on top of the latest image is enough if
+        // reads in the partition visit too many layers, and the delta count alone never is.
+        let read_amp_exceeds = match self.get_image_creation_policy() {
+            ImageCreationPolicy::DeltaCount => None,
+            ImageCreationPolicy::ReadAmplification(policy) => {
+                Some(self.partition_read_amp_exceeds(partition, policy))
+            }
+        };
 
         let guard = self.layers.read(LayerManagerLockHolder::Compaction).await;
         let Ok(layers) = guard.layer_map() else {
@@ -5543,7 +5828,11 @@ async fn time_for_new_image_layer(
                         layers.count_deltas(&img_range, &(img_lsn..lsn), Some(threshold));
 
                     max_deltas = max_deltas.max(num_deltas);
-                    if num_deltas >= threshold {
+                    let triggered = match read_amp_exceeds {
+                        None => num_deltas >= threshold,
+                        Some(exceeds) => exceeds && num_deltas > 0,
+                    };
+                    if triggered {
                         debug!(
                             "key range {}-{}, has {} deltas on this timeline in LSN range {}..{}",
                             img_range.start, img_range.end, num_deltas, img_lsn, lsn
@@ -6065,6 +6354,8 @@ async fn create_image_layers(
                         img_range.clone(),
                         lsn,
                     );
+                    // The reads in this range will visit fewer layers from now on.
+                    self.read_amp_stats.reset(&img_range);
                     // The next image layer should be generated right after this one.
                     start = img_range.end;
                 }
@@ -6798,6 +7089,33 @@ pub(super) async fn gc(&self) -> Result<GcResult, GcError> {
             return Err(GcError::TimelineCancelled);
         }
 
+        let retention = self.gc_retention();
+        let new_gc_cutoff = retention.new_gc_cutoff;
+
+        // Reset standby horizon to ignore it if it is not updated till next GC.
+        // It is an easy way to unset it when standby disappears without adding
+        // more conf options.
+        self.standby_horizon.store(Lsn::INVALID);
+        self.metrics
+            .standby_horizon_gauge
+            .set(Lsn::INVALID.0 as i64);
+
+        let res = self
+            .gc_timeline(retention)
+            .instrument(
+                info_span!("gc_timeline", timeline_id = %self.timeline_id, cutoff = %new_gc_cutoff),
+            )
+            .await?;
+
+        // only record successes
+        timer.stop_and_record();
+
+        Ok(res)
+    }
+
+    /// Collect the constraints for which GC keeps layers, from the cutoffs computed by the last
+    /// GC iteration and the standby horizon.
+    fn gc_retention(&self) -> GcRetention {
         let (space_cutoff, time_cutoff, retain_lsns, max_lsn_with_valid_lease) = {
             let gc_info = self.gc_info.read().unwrap();
 
@@ -6842,41 +7160,85 @@ pub(super) async fn gc(&self) -> Result<GcResult, GcError> {
             }
         }
 
-        // Reset standby horizon to ignore it if it is not updated till next GC.
-        // It is an easy way to unset it when standby disappears without adding
-        // more conf options.
-        self.standby_horizon.store(Lsn::INVALID);
-        self.metrics
-            .standby_horizon_gauge
-            .set(Lsn::INVALID.0 as i64);
+        GcRetention {
+            space_cutoff,
+            time_cutoff,
+            retain_lsns,
+            max_lsn_with_valid_lease,
+            new_gc_cutoff,
+        }
+    }
 
-        let res = self
-            .gc_timeline(
-                space_cutoff,
-                time_cutoff,
-                retain_lsns,
-                max_lsn_with_valid_lease,
-                new_gc_cutoff,
-            )
-            .instrument(
-                info_span!("gc_timeline", timeline_id = %self.timeline_id, cutoff = %new_gc_cutoff),
-            )
-            .await?;
+    /// Report which layers GC would keep and why, without removing anything.
+    ///
+    /// `blocked_by` is the reason GC is skipped for the whole tenant, if it is: layers which GC
+    /// would otherwise remove are then reported as retained by it.
+    pub(crate) async fn gc_dry_run(
+        &self,
+        blocked_by: Option<String>,
+    ) -> Result<TimelineGcDryRunResponse, GcError> {
+        let retention = self.gc_retention();
+        let applied_gc_cutoff = *self.get_applied_gc_cutoff_lsn();
+
+        let mut response = TimelineGcDryRunResponse {
+            space_cutoff: retention.space_cutoff,
+            time_cutoff: retention.time_cutoff,
+            retain_lsns: retention.retain_lsns.clone(),
+            max_lease_lsn: retention.max_lsn_with_valid_lease,
+            applied_gc_cutoff,
+            gc_cutoff: retention.new_gc_cutoff,
+            blocked_by,
+            layers_total: 0,
+            layers_removable: 0,
+            bytes_removable: 0,
+            retained_by: Vec::new(),
+            retained_layers: Vec::new(),
+        };
 
-        // only record successes
-        timer.stop_and_record();
+        let guard = self
+            .layers
+            .read(LayerManagerLockHolder::GarbageCollection)
+            .await;
+        let layers = guard.layer_map()?;
+        for l in layers.iter_historic_layers() {
+            response.layers_total += 1;
+
+            let reason = match retention.retain_reason(layers, &l) {
+                Some(reason) => reason,
+                None if response.blocked_by.is_some() => GcRetainReason::GcBlock,
+                // Like `gc_timeline`, which returns early in this case.
+                None if retention.time_cutoff.is_none() => GcRetainReason::TimeCutoffPending,
+                None => {
+                    response.layers_removable += 1;
+                    response.bytes_removable += l.file_size;
+                    continue;
+                }
+            };
 
-        Ok(res)
+            match response.retained_by.iter_mut().find(|r| r.reason == reason) {
+                Some(summary) => {
+                    summary.layers += 1;
+                    summary.bytes += l.file_size;
+                }
+                None => response.retained_by.push(GcRetainSummary {
+                    reason,
+                    layers: 1,
+                    bytes: l.file_size,
+                }),
+            }
+            response.retained_layers.push(GcRetainedLayer {
+                layer_file_name: l.layer_name().to_string(),
+                layer_file_size: l.file_size,
+                reason,
+            });
+        }
+        response.retained_by.sort_by_key(|r| r.reason);
+
+        Ok(response)
     }
 
-    async fn gc_timeline(
-        &self,
-        space_cutoff: Lsn,
-        time_cutoff: Option<Lsn>, // None if uninitialized
-        retain_lsns: Vec<Lsn>,
-        max_lsn_with_valid_lease: Option<Lsn>,
-        new_gc_cutoff: Lsn,
-    ) -> Result<GcResult, GcError> {
+    async fn gc_timeline(&self, retention: GcRetention) -> Result<GcResult, GcError> {
+        let new_gc_cutoff = retention.new_gc_cutoff;
         // FIXME: if there is an ongoing detach_from_ancestor, we should just skip gc
 
         let now = SystemTime::now();
@@ -6891,7 +7253,7 @@ async fn gc_timeline(
             return Ok(result);
         }
 
-        let Some(time_cutoff) = time_cutoff else {
+        if retention.time_cutoff.is_none() {
             // The GC cutoff should have been computed by now, but let's be defensive.
             info!("Nothing to GC: time_cutoff not yet computed");
             return Ok(result);
@@ -6925,18 +7287,10 @@ async fn gc_timeline(
 
         info!("GC starting");
 
-        debug!("retain_lsns: {:?}", retain_lsns);
+        debug!("retain_lsns: {:?}", retention.retain_lsns);
 
-        let max_retain_lsn = retain_lsns.iter().max();
-
-        // Scan all layers in the timeline (remote or on-disk).
-        //
-        // Garbage collect the layer if all conditions are satisfied:
-        // 1. it is older than cutoff LSN;
-        // 2. it is older than PITR interval;
-        // 3. it doesn't need to be retained for 'retain_lsns';
-        // 4. it does not need to be kept for LSNs holding valid leases.
-        // 5. newer on-disk image layers cover the layer's whole key range
+        // Scan all layers in the timeline (remote or on-disk), and garbage collect the layers
+        // which no constraint retains.
         let layers_to_remove = {
             let mut layers_to_remove = Vec::new();
 
@@ -6945,89 +7299,13 @@ async fn gc_timeline(
                 .read(LayerManagerLockHolder::GarbageCollection)
                 .await;
             let layers = guard.layer_map()?;
-            'outer: for l in layers.iter_historic_layers() {
+            for l in layers.iter_historic_layers() {
                 result.layers_total += 1;
 
-                // 1. Is it newer than GC horizon cutoff point?
-                if l.get_lsn_range().end > space_cutoff {
-                    debug!(
-                        "keeping {} because it's newer than space_cutoff {}",
-                        l.layer_name(),
-                        space_cutoff,
-                    );
-                    result.layers_needed_by_cutoff += 1;
-                    continue 'outer;
-                }
-
-                // 2. It is newer than PiTR cutoff point?
-                if l.get_lsn_range().end > time_cutoff {
-                    debug!(
-                        "keeping {} because it's newer than time_cutoff {}",
-                        l.layer_name(),
-                        time_cutoff,
-                    );
-                    result.layers_needed_by_pitr += 1;
-                    continue 'outer;
-                }
-
-                // 3. Is it needed by a child branch?
-                // NOTE With that we would keep data that
-                // might be referenced by child branches forever.
-                // We can track this in child timeline GC and delete parent layers when
-                // they are no longer needed. This might be complicated with long inheritance chains.
-                if let Some(retain_lsn) = max_retain_lsn {
-                    // start_lsn is inclusive
-                    if &l.get_lsn_range().start <= retain_lsn {
-                        debug!(
-                            "keeping {} because it's still might be referenced by child branch forked at {} is_dropped: xx is_incremental: {}",
-                            l.layer_name(),
-                            retain_lsn,
-                            l.is_incremental(),
-                        );
-                        result.layers_needed_by_branches += 1;
-                        continue 'outer;
-                    }
-                }
-
-                // 4. Is there a valid lease that requires us to keep this layer?
-                if let Some(lsn) = &max_lsn_with_valid_lease {
-                    // keep if layer start <= any of the lease
-                    if &l.get_lsn_range().start <= lsn {
-                        debug!(
-                            "keeping {} because there is a valid lease preventing GC at {}",
-                            l.layer_name(),
-                            lsn,
-                        );
-                        result.layers_needed_by_leases += 1;
-                        continue 'outer;
-                    }
-                }
-
-                // 5. Is there a later on-disk layer for this relation?
-                //
-                // The end-LSN is exclusive, while disk_consistent_lsn is
-                // inclusive. For example, if disk_consistent_lsn is 100, it is
-                // OK for a delta layer to have end LSN 101, but if the end LSN
-                // is 102, then it might not have been fully flushed to disk
-                // before crash.
-                //
-                // For example, imagine that the following layers exist:
-                //
-                // 1000      - image (A)
-                // 1000-2000 - delta (B)
-                // 2000      - image (C)
-                // 2000-3000 - delta (D)
-                // 3000      - image (E)
-                //
-                // If GC horizon is at 2500, we can remove layers A and B, but
-                // we cannot remove C, even though it's older than 2500, because
-                // the delta layer 2000-3000 depends on it.
-                if !layers
-                    .image_layer_exists(&l.get_key_range(), &(l.get_lsn_range().end..new_gc_cutoff))
-                {
-                    debug!("keeping {} because it is the latest layer", l.layer_name());
-                    result.layers_not_updated += 1;
-                    continue 'outer;
+                if let Some(reason) = retention.retain_reason(layers, &l) {
+                    debug!("keeping {} because of {reason:?}", l.layer_name());
+                    result.add_retained(reason);
+                    continue;
                 }
 
                 // We didn't find any reason to keep this file, so remove it.
@@ -7392,6 +7670,8 @@ pub(crate) async fn get_local_layers_for_disk_usage_eviction(&self) -> DiskUsage
                     layer: layer.to_owned().into(),
                     last_activity_ts,
                     relative_last_activity: finite_f32::FiniteF32::ZERO,
+                    access_rate: layer.access_rate(),
+                    cost: finite_f32::FiniteF32::ZERO,
                     visibility: layer.visibility(),
                 }
             })
@@ -48,6 +48,7 @@
 use crate::page_cache;
 use crate::statvfs::Statvfs;
 use crate::tenant::checks::check_valid_layermap;
+use crate::tenant::compaction_scheduler::CompactionUrgency;
 use crate::tenant::gc_block::GcBlock;
 use crate::tenant::layer_map::LayerMap;
 use crate::tenant::remote_timeline_client::WaitCompletionError;
@@ -1200,6 +1201,34 @@ pub enum CompactionOutcome {
 }
 
 impl Timeline {
+    /// Inputs for ordering this timeline in the per-node compaction scheduler.
+    pub(crate) async fn compaction_urgency(&self) -> CompactionUrgency {
+        let (l0_count, l0_bytes) = match self
+            .layers
+            .read(LayerManagerLockHolder::Compaction)
+            .await
+            .layer_map()
+        {
+            Ok(lm) => {
+                let l0 = lm.level0_deltas();
+                (l0.len(), l0.iter().map(|l| l.file_size).sum())
+            }
+            Err(_) => (0, 0),
+        };
+        CompactionUrgency {
+            l0_count,
+            l0_bytes,
+            compaction_threshold: self.get_compaction_threshold(),
+            l0_backpressure_threshold: self
+                .get_l0_flush_stall_threshold()
+                .or(self.get_l0_flush_delay_threshold()),
+            layers_per_read: self
+                .read_amp_stats
+                .get(&[Key::MIN..Key::MAX])
+                .layers_per_read(),
+        }
+    }
+
     /// TODO: cancellation
     ///
     /// Returns whether the compaction has pending tasks.
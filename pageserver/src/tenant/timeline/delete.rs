@@ -10,16 +10,18 @@
 use utils::id::TimelineId;
 use utils::{crashsafe, fs_ext, pausable_failpoint};
 
+use super::FlushLayerError;
+use super::offload::{OffloadError, offload_archived_timeline};
 use crate::config::PageServerConf;
 use crate::context::RequestContext;
 use crate::task_mgr::{self, TaskKind};
 use crate::tenant::metadata::TimelineMetadata;
 use crate::tenant::remote_timeline_client::{
-    PersistIndexPartWithDeletedFlagError, RemoteTimelineClient,
+    PersistIndexPartWithDeletedFlagError, RemoteTimelineClient, WaitCompletionError,
 };
 use crate::tenant::{
-    CreateTimelineCause, DeleteTimelineError, MaybeDeletedIndexPart, TenantManifestError,
-    TenantShard, Timeline, TimelineOrOffloaded,
+    CreateTimelineCause, DeleteTimelineError, IndexPart, MaybeDeletedIndexPart,
+    TenantManifestError, TenantShard, Timeline, TimelineArchivalError, TimelineOrOffloaded,
 };
 use crate::virtual_file::MaybeFatalIo;
 
@@ -91,11 +93,22 @@ pub(super) async fn delete_local_timeline_directory(
     //
     // ErrorKind::NotFound can happen e.g. if we race with tenant detach, because,
     // no locks are shared.
-    tokio::fs::remove_dir_all(local_timeline_directory)
+    tokio::fs::remove_dir_all(&local_timeline_directory)
         .await
         .or_else(fs_ext::ignore_not_found)
         .fatal_err("removing timeline directory");
 
+    // Layer files on the slow tier are not needed for resuming the deletion either, but nothing
+    // else would remove them.
+    if let Some(slow_tier_directory) = conf.slow_tier_path(&local_timeline_directory) {
+        if let Err(e) = tokio::fs::remove_dir_all(&slow_tier_directory)
+            .await
+            .or_else(fs_ext::ignore_not_found)
+        {
+            tracing::warn!("failed to remove slow tier timeline directory: {e}");
+        }
+    }
+
     // Make sure previous deletions are ordered before mark removal.
     // Otherwise there is no guarantee that they reach the disk before mark deletion.
     // So its possible for mark to reach disk first and for other deletions
@@ -110,6 +123,43 @@ pub(super) async fn delete_local_timeline_directory(
     info!("finished deleting layer files, releasing locks");
 }
 
+/// Builds a remote client with an initialized upload queue for an offloaded timeline. Returns None
+/// if the timeline's index part is gone or marks it as deleted.
+async fn load_offloaded_remote_client(
+    tenant: &TenantShard,
+    timeline_id: TimelineId,
+) -> anyhow::Result<Option<(Arc<RemoteTimelineClient>, IndexPart)>> {
+    let remote_client = tenant.build_timeline_client(timeline_id, tenant.remote_storage.clone());
+    let index_part = match remote_client
+        .download_index_file(&tenant.cancel)
+        .instrument(info_span!("download_index_file"))
+        .await
+    {
+        Ok(MaybeDeletedIndexPart::IndexPart(index_part)) => index_part,
+        Ok(MaybeDeletedIndexPart::Deleted(_)) | Err(DownloadError::NotFound) => return Ok(None),
+        Err(e) => return Err(anyhow::Error::new(e).context("downloading index_part")),
+    };
+    let remote_client = Arc::new(remote_client);
+    remote_client.init_upload_queue(&index_part)?;
+    Ok(Some((remote_client, index_part)))
+}
+
+async fn wait_index_upload(
+    remote_client: &Arc<RemoteTimelineClient>,
+) -> Result<(), DeleteTimelineError> {
+    remote_client.wait_completion().await.map_err(|e| match e {
+        WaitCompletionError::UploadQueueShutDownOrStopped => DeleteTimelineError::Cancelled,
+        e => DeleteTimelineError::Other(anyhow::anyhow!(e)),
+    })
+}
+
+fn manifest_upload_error(err: TenantManifestError) -> DeleteTimelineError {
+    match err {
+        TenantManifestError::Cancelled => DeleteTimelineError::Cancelled,
+        err => DeleteTimelineError::Other(err.into()),
+    }
+}
+
 /// It is important that this gets called when DeletionGuard is being held.
 /// For more context see comments in [`make_timeline_delete_guard`]
 async fn remove_maybe_offloaded_timeline_from_tenant(
@@ -202,9 +252,17 @@ pub async fn run(
     ) -> Result<(), DeleteTimelineError> {
         super::debug_assert_current_span_has_tenant_and_timeline_id();
 
-        let (timeline, mut guard) =
+        let (timeline, guard) =
             make_timeline_delete_guard(tenant, timeline_id, TimelineDeleteGuardKind::Delete)?;
 
+        Self::run_with_guard(tenant, timeline, guard).await
+    }
+
+    async fn run_with_guard(
+        tenant: &Arc<TenantShard>,
+        timeline: TimelineOrOffloaded,
+        mut guard: DeletionGuard,
+    ) -> Result<(), DeleteTimelineError> {
         guard.mark_in_progress()?;
 
         // Now that the Timeline is in Stopping state, request all the related tasks to shut down.
@@ -296,6 +354,213 @@ pub async fn run(
         Ok(())
     }
 
+    /// Soft-deletes the timeline: ingest is stopped, everything received so far is flushed, and the
+    /// timeline is marked as soft-deleted in its index part and offloaded. It is no longer served,
+    /// but its remote objects are kept until the tenant's `timeline_delete_grace_period` has
+    /// passed, after which [`Self::purge`] deletes it for good. Until then, [`Self::undelete`]
+    /// restores it.
+    ///
+    /// Timelines that are still being imported have nothing worth keeping and are deleted right
+    /// away.
+    #[instrument(skip_all)]
+    pub async fn run_soft(
+        tenant: &Arc<TenantShard>,
+        timeline_id: TimelineId,
+    ) -> Result<(), DeleteTimelineError> {
+        super::debug_assert_current_span_has_tenant_and_timeline_id();
+
+        let (timeline, guard) =
+            make_timeline_delete_guard(tenant, timeline_id, TimelineDeleteGuardKind::SoftDelete)?;
+
+        match timeline {
+            TimelineOrOffloaded::Timeline(timeline) => {
+                // Stop ingest before the final flush, so that the flush covers all WAL received.
+                let walreceiver = timeline.walreceiver.lock().unwrap().take();
+                if let Some(walreceiver) = walreceiver {
+                    walreceiver.cancel().await;
+                }
+                timeline.freeze_and_flush().await.map_err(|e| match e {
+                    FlushLayerError::Cancelled => DeleteTimelineError::Cancelled,
+                    e => DeleteTimelineError::Other(anyhow::anyhow!(e)),
+                })?;
+
+                timeline
+                    .remote_client
+                    .schedule_index_upload_for_soft_delete()?;
+                wait_index_upload(&timeline.remote_client).await?;
+
+                offload_archived_timeline(tenant, &timeline, &guard)
+                    .await
+                    .map_err(|e| match e {
+                        OffloadError::Cancelled => DeleteTimelineError::Cancelled,
+                        e => DeleteTimelineError::Other(anyhow::anyhow!(e)),
+                    })?;
+            }
+            TimelineOrOffloaded::Offloaded(offloaded) => {
+                if offloaded.soft_deleted_at().is_some() {
+                    // From the API's point of view, soft-deleted timelines are gone.
+                    return Err(DeleteTimelineError::NotFound);
+                }
+                let Some((remote_client, _)) =
+                    load_offloaded_remote_client(tenant, timeline_id).await?
+                else {
+                    // Nothing left to keep, just finish the deletion.
+                    let timeline = TimelineOrOffloaded::Offloaded(offloaded);
+                    return Self::run_with_guard(tenant, timeline, guard).await;
+                };
+                remote_client.schedule_index_upload_for_soft_delete()?;
+                let res = wait_index_upload(&remote_client).await;
+                remote_client.shutdown().await;
+                res?;
+
+                let soft_deleted_at = remote_client
+                    .soft_deleted_at_stopped_queue()
+                    .map_err(|e| DeleteTimelineError::Other(anyhow::anyhow!(e)))?;
+                offloaded.set_soft_deleted_at(soft_deleted_at);
+                tenant
+                    .maybe_upload_tenant_manifest()
+                    .await
+                    .map_err(manifest_upload_error)?;
+            }
+            timeline @ TimelineOrOffloaded::Importing(_) => {
+                return Self::run_with_guard(tenant, timeline, guard).await;
+            }
+        }
+
+        info!("timeline soft-deleted");
+        Ok(())
+    }
+
+    /// Deletes a soft-deleted timeline for good, once its grace period has passed.
+    #[instrument(skip_all)]
+    pub(crate) async fn purge(
+        tenant: &Arc<TenantShard>,
+        timeline_id: TimelineId,
+    ) -> Result<(), DeleteTimelineError> {
+        let (timeline, guard) =
+            make_timeline_delete_guard(tenant, timeline_id, TimelineDeleteGuardKind::SoftDelete)?;
+        let TimelineOrOffloaded::Offloaded(offloaded) = &timeline else {
+            return Err(DeleteTimelineError::Other(anyhow::anyhow!(
+                "only offloaded timelines can be purged"
+            )));
+        };
+
+        // The index part is authoritative: an undeletion may have been interrupted after updating
+        // it, but before updating the tenant manifest.
+        if let Some((remote_client, index_part)) =
+            load_offloaded_remote_client(tenant, timeline_id).await?
+        {
+            remote_client.shutdown().await;
+            if index_part.soft_deleted_at.is_none() {
+                info!("timeline was undeleted, not purging it");
+                offloaded.set_soft_deleted_at(None);
+                return tenant
+                    .maybe_upload_tenant_manifest()
+                    .await
+                    .map_err(manifest_upload_error);
+            }
+        }
+
+        info!("grace period passed, purging soft-deleted timeline");
+        Self::run_with_guard(tenant, timeline, guard).await
+    }
+
+    /// Reverts [`Self::run_soft`], as long as the timeline hasn't been purged yet. Timelines that
+    /// were archived by their soft-deletion are unarchived and loaded again, others stay archived.
+    /// Undeleting a timeline that isn't deleted is a no-op.
+    #[instrument(skip_all)]
+    pub(crate) async fn undelete(
+        tenant: &Arc<TenantShard>,
+        timeline_id: TimelineId,
+        broker_client: storage_broker::BrokerClientChannel,
+        ctx: RequestContext,
+    ) -> Result<(), TimelineArchivalError> {
+        let undelete_error = |e: DeleteTimelineError| match e {
+            DeleteTimelineError::NotFound => TimelineArchivalError::NotFound,
+            DeleteTimelineError::AlreadyInProgress(_) => TimelineArchivalError::AlreadyInProgress,
+            DeleteTimelineError::Cancelled => TimelineArchivalError::Cancelled,
+            e => TimelineArchivalError::Other(anyhow::anyhow!(e)),
+        };
+
+        let (timeline, _guard) =
+            make_timeline_delete_guard(tenant, timeline_id, TimelineDeleteGuardKind::Undelete)
+                .map_err(undelete_error)?;
+
+        match timeline {
+            TimelineOrOffloaded::Timeline(timeline) => {
+                // Either not deleted, or the soft-deletion was interrupted before offloading.
+                if timeline.remote_client.is_archived_by_soft_delete() == Some(true) {
+                    TenantShard::check_to_be_unarchived_timeline_has_no_archived_parent(&timeline)?;
+                }
+                if timeline
+                    .remote_client
+                    .schedule_index_upload_for_undelete()
+                    .map_err(TimelineArchivalError::Other)?
+                {
+                    wait_index_upload(&timeline.remote_client)
+                        .await
+                        .map_err(undelete_error)?;
+                    info!("timeline undeleted");
+                }
+                Ok(())
+            }
+            TimelineOrOffloaded::Offloaded(offloaded) => {
+                let Some((remote_client, index_part)) =
+                    load_offloaded_remote_client(tenant, timeline_id)
+                        .await
+                        .map_err(TimelineArchivalError::Other)?
+                else {
+                    return Err(TimelineArchivalError::NotFound);
+                };
+
+                let unarchive = index_part.archived_by_soft_delete();
+                let ancestor_check = match offloaded.ancestor_timeline_id.filter(|_| unarchive) {
+                    Some(ancestor_timeline_id) => {
+                        let timelines = tenant.timelines.lock().unwrap();
+                        let offloaded_timelines = tenant.timelines_offloaded.lock().unwrap();
+                        TenantShard::check_ancestor_of_to_be_unarchived_is_not_archived(
+                            ancestor_timeline_id,
+                            &timelines,
+                            &offloaded_timelines,
+                        )
+                    }
+                    None => Ok(()),
+                };
+
+                // Update the index part first, see the comment in [`Self::purge`].
+                let res = match ancestor_check {
+                    Ok(()) => match remote_client.schedule_index_upload_for_undelete() {
+                        Ok(true) => wait_index_upload(&remote_client)
+                            .await
+                            .map_err(undelete_error),
+                        Ok(false) => Ok(()),
+                        Err(e) => Err(TimelineArchivalError::Other(e)),
+                    },
+                    Err(e) => Err(e),
+                };
+                remote_client.shutdown().await;
+                res?;
+
+                if offloaded.soft_deleted_at().is_none() && !unarchive {
+                    // Neither deleted in the manifest nor in the index part.
+                    return Ok(());
+                }
+                offloaded.set_soft_deleted_at(None);
+                if unarchive {
+                    // This also uploads the tenant manifest.
+                    tenant
+                        .unoffload_timeline(timeline_id, broker_client, ctx)
+                        .await?;
+                } else {
+                    tenant.maybe_upload_tenant_manifest().await?;
+                }
+                info!("timeline undeleted");
+                Ok(())
+            }
+            TimelineOrOffloaded::Importing(_) => Ok(()),
+        }
+    }
+
     fn mark_in_progress(&mut self) -> anyhow::Result<()> {
         match self {
             Self::Finished => anyhow::bail!("Bug. Is in finished state"),
@@ -464,6 +729,8 @@ pub(crate) fn is_not_started(&self) -> bool {
 pub(super) enum TimelineDeleteGuardKind {
     Offload,
     Delete,
+    SoftDelete,
+    Undelete,
 }
 
 pub(super) fn make_timeline_delete_guard(
@@ -498,15 +765,22 @@ pub(super) fn make_timeline_delete_guard(
 
     // Ensure that there are no child timelines, because we are about to remove files,
     // which will break child branches
+    // Soft-deleted timelines are purged later, so they must not have children either. Undeletion
+    // doesn't remove anything.
     let mut children = Vec::new();
-    if guard_kind == TimelineDeleteGuardKind::Delete {
+    if matches!(
+        guard_kind,
+        TimelineDeleteGuardKind::Delete | TimelineDeleteGuardKind::SoftDelete
+    ) {
         children.extend(timelines_offloaded.iter().filter_map(|(id, entry)| {
             (entry.ancestor_timeline_id == Some(timeline_id)).then_some(*id)
         }));
     }
-    children.extend(timelines.iter().filter_map(|(id, entry)| {
-        (entry.get_ancestor_timeline_id() == Some(timeline_id)).then_some(*id)
-    }));
+    if guard_kind != TimelineDeleteGuardKind::Undelete {
+        children.extend(timelines.iter().filter_map(|(id, entry)| {
+            (entry.get_ancestor_timeline_id() == Some(timeline_id)).then_some(*id)
+        }));
+    }
 
     if !children.is_empty() {
         return Err(DeleteTimelineError::HasChildren(children));
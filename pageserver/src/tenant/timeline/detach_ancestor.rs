@@ -880,6 +880,18 @@ async fn remote_copy(
             &metadata.generation,
         );
 
+        // hard links cannot cross the tiers, so the adoptee's file goes on the adopted one's tier
+        let adoptee_path = if conf.fast_tier_path(adopted_path).is_some() {
+            let slow_path = conf
+                .slow_tier_path(&adoptee_path)
+                .expect("slow tier is configured");
+            std::fs::create_dir_all(slow_path.parent().expect("layer paths have a parent"))
+                .map_err(|e| Error::launder(e.into(), Error::Prepare))?;
+            slow_path
+        } else {
+            adoptee_path
+        };
+
         match std::fs::hard_link(adopted_path, &adoptee_path) {
             Ok(()) => {}
             Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
@@ -36,6 +36,12 @@
 use crate::tenant::timeline::layer_manager::LayerManagerLockHolder;
 use crate::tenant::{LogicalSizeCalculationCause, TenantShard};
 
+/// Layers accessed at least this many times per hour (see [`Layer::access_rate`]) are kept
+/// resident for twice the configured threshold.
+///
+/// [`Layer::access_rate`]: crate::tenant::storage_layer::Layer::access_rate
+const HOT_LAYER_ACCESS_RATE: f32 = 60.0;
+
 #[derive(Default)]
 pub struct EvictionTaskTimelineState {
     last_layer_access_imitation: Option<tokio::time::Instant>,
@@ -241,8 +247,15 @@ struct EvictionStats {
                     match layer.visibility() {
                         LayerVisibilityHint::Visible => {
                             // Usual case: a visible layer might be read any time, and we will keep it
-                            // resident until it hits our configured TTL threshold.
-                            no_activity_for > p.threshold
+                            // resident until it hits our configured TTL threshold. Frequently read
+                            // layers are likely to be read again even after a quiet period, so
+                            // they get some extra time.
+                            let threshold = if layer.access_rate() >= HOT_LAYER_ACCESS_RATE {
+                                p.threshold.saturating_mul(2)
+                            } else {
+                                p.threshold
+                            };
+                            no_activity_for > threshold
                         }
                         LayerVisibilityHint::Covered => {
                             // Covered layers: this is probably a layer that was recently covered by
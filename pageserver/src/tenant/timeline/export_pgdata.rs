@@ -0,0 +1,636 @@
+//! Export of a timeline into an external location as a full basebackup, the inverse of
+//! [`super::import_pgdata`].
+//!
+//! Each shard writes zstd-compressed tar parts below `<location>/<shard index>/`:
+//! - `part-NNNNNN.tar.zst`: numbered from zero. On shard zero, part zero holds everything but
+//!   relation data, i.e. what a compute basebackup contains. All other parts hold relation
+//!   segment files, in (relation, segment) order.
+//! - `manifest.json`: written last, lists the parts and the shard layout, see [`Manifest`].
+//! - `rel_sizes.json`: only on shard zero of a sharded tenant, see [`RelSizes`].
+//!
+//! Extracting all parts of an unsharded tenant yields a PGDATA that vanilla Postgres can start
+//! from. On a sharded tenant, shards need to be merged:
+//! - Files other than relation segments only exist in part zero of shard zero.
+//! - Only shard zero knows the exact size of a relation, other shards' sizes just reflect the
+//!   highest block they have ingested. So shard zero publishes the relations and their sizes in
+//!   `rel_sizes.json` before exporting relation data, and the other shards wait for it and
+//!   export exactly those segment files. Thus every shard exports every relation segment file
+//!   at its full size, with the blocks stored on other shards zeroed. Each block is non-zero on
+//!   at most one shard, so the merged file is the byte-wise OR of the shards' files. Shards may
+//!   split a relation's segments into different parts, so merge by file name after extracting
+//!   all parts.
+//!
+//! Parts are staged in a temporary file in the timeline directory before upload, so the memory
+//! an export takes doesn't depend on the part size. The part size is capped at
+//! [`MAX_PART_SIZE`] to bound the disk space.
+//!
+//! Progress is recorded in the index part after each uploaded part, so an export continues where
+//! it left off after a restart or migration. Part boundaries only depend on the exported data,
+//! which makes re-uploading a part after a crash, or from two generations at once, harmless. An
+//! LSN lease keeps the exported LSN readable while the export runs.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Context;
+use async_compression::tokio::write::ZstdEncoder;
+use bytes::Bytes;
+use camino::Utf8PathBuf;
+use http_utils::error::ApiError;
+use pageserver_api::key::rel_block_to_key;
+use pageserver_api::keyspace::KeySpaceAccum;
+use pageserver_api::models::{TimelineExportRequest, TimelineExportStatus};
+use pageserver_api::reltag::RelTag;
+use pageserver_api::shard::{ShardCount, ShardNumber, ShardStripeSize, TenantShardId};
+use postgres_ffi::{BLCKSZ, PgMajorVersion, RELSEG_SIZE};
+use postgres_ffi_types::forknum::{INIT_FORKNUM, MAIN_FORKNUM};
+use remote_storage::{GenericRemoteStorage, RemotePath, TimeoutOrCancel};
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncWrite, AsyncWriteExt, BufWriter};
+use tokio_util::sync::CancellationToken;
+use tracing::{Instrument, info, info_span, warn};
+use utils::backoff;
+use utils::lsn::Lsn;
+
+use super::{Timeline, VersionedKeySpaceQuery, WaitLsnTimeout, WaitLsnWaiter};
+use crate::TEMP_FILE_SUFFIX;
+use crate::basebackup;
+use crate::context::{DownloadBehavior, RequestContext};
+use crate::pgdatadir_mapping::Version;
+use crate::task_mgr::TaskKind;
+use crate::tenant::remote_timeline_client::{
+    FAILED_REMOTE_OP_RETRIES, FAILED_UPLOAD_WARN_THRESHOLD,
+};
+use crate::tenant::storage_layer::IoConcurrency;
+use crate::tenant::timeline::import_pgdata::importbucket_client;
+
+pub(crate) mod index_part_format;
+
+/// Default for [`TimelineExportRequest::part_size`]. Part sizes are measured before compression
+/// so that part boundaries don't depend on the compressor.
+const DEFAULT_PART_SIZE: u64 = 256 * 1024 * 1024;
+
+/// Upper bound for [`TimelineExportRequest::part_size`], larger values are clamped to it. A part
+/// may exceed it by up to one relation segment.
+const MAX_PART_SIZE: u64 = 1024 * 1024 * 1024;
+
+/// Length of the LSN lease that keeps the exported LSN readable. Renewed with every part.
+const LSN_LEASE_LENGTH: Duration = Duration::from_secs(30 * 60);
+
+/// How often shards other than zero check whether shard zero has published [`RelSizes`].
+const REL_SIZES_POLL_INTERVAL: Duration = Duration::from_secs(10);
+
+const REL_SIZES_OBJECT: &str = "rel_sizes.json";
+
+const ZERO_PAGE: [u8; BLCKSZ as usize] = [0; BLCKSZ as usize];
+
+/// Written to `<location>/<shard index>/manifest.json` once a shard has uploaded all its parts.
+#[derive(Serialize)]
+struct Manifest {
+    lsn: Lsn,
+    pg_version: PgMajorVersion,
+    shard_number: ShardNumber,
+    shard_count: ShardCount,
+    stripe_size: ShardStripeSize,
+    /// Object names of the parts, relative to the manifest.
+    parts: Vec<String>,
+}
+
+/// Written to `<location>/<shard zero index>/rel_sizes.json` by shard zero of a sharded tenant:
+/// the relations to export and their sizes, which all shards use to list segments.
+#[derive(Serialize, Deserialize)]
+struct RelSizes {
+    lsn: Lsn,
+    /// In export order.
+    rels: Vec<(RelTag, u32)>,
+}
+
+/// A relation segment file, the unit in which relation data is exported.
+#[derive(Debug, Clone, Copy)]
+struct RelSegment {
+    rel: RelTag,
+    segno: u32,
+    nblocks: u32,
+    /// Size of the relation as ingested by this shard. Blocks past it are not stored here.
+    local_rel_nblocks: u32,
+}
+
+impl Timeline {
+    /// Start exporting this timeline. Starting an export with the same location (and LSN, if
+    /// given) as the one in progress is a no-op, so that callers can safely retry.
+    pub(crate) async fn start_export_pgdata(
+        self: &Arc<Self>,
+        req: TimelineExportRequest,
+        ctx: &RequestContext,
+    ) -> Result<TimelineExportStatus, ApiError> {
+        let location = req.location.into();
+
+        if let Some(current) = self.remote_client.export_pgdata_state() {
+            if let Some(in_progress) = current.in_progress() {
+                if req.lsn.is_some_and(|lsn| lsn != in_progress.lsn)
+                    || in_progress.location != location
+                {
+                    return Err(ApiError::Conflict(format!(
+                        "an export at LSN {} is already in progress",
+                        in_progress.lsn
+                    )));
+                }
+                self.spawn_export_pgdata_task(in_progress.clone(), ctx);
+                return Ok(current.status());
+            }
+        }
+
+        let lsn = req.lsn.unwrap_or_else(|| self.get_last_record_lsn());
+        self.wait_lsn(
+            lsn,
+            WaitLsnWaiter::HttpEndpoint,
+            WaitLsnTimeout::Default,
+            ctx,
+        )
+        .await
+        .map_err(|e| {
+            if e.is_cancel() {
+                ApiError::ShuttingDown
+            } else {
+                ApiError::InternalServerError(anyhow::anyhow!(e))
+            }
+        })?;
+        self.init_lsn_lease(lsn, LSN_LEASE_LENGTH, ctx)
+            .map_err(ApiError::BadRequest)?;
+
+        let in_progress = index_part_format::InProgress {
+            location,
+            lsn,
+            part_size: req
+                .part_size
+                .unwrap_or(DEFAULT_PART_SIZE)
+                .min(MAX_PART_SIZE),
+            started_at: chrono::Utc::now().naive_utc(),
+            progress: Default::default(),
+        };
+        let state =
+            index_part_format::Root::V1(index_part_format::V1::InProgress(in_progress.clone()));
+        self.remote_client
+            .schedule_index_upload_for_export_pgdata_state_update(state.clone())
+            .map_err(ApiError::InternalServerError)?;
+        self.remote_client
+            .wait_completion()
+            .await
+            .map_err(|_| ApiError::ShuttingDown)?;
+
+        info!(%lsn, "starting export");
+        self.spawn_export_pgdata_task(in_progress, ctx);
+        Ok(state.status())
+    }
+
+    pub(crate) fn export_pgdata_status(&self) -> Option<TimelineExportStatus> {
+        self.remote_client
+            .export_pgdata_state()
+            .map(|state| state.status())
+    }
+
+    /// Continue an export that was in progress when the timeline was last shut down.
+    pub(super) fn resume_export_pgdata(self: &Arc<Self>, ctx: &RequestContext) {
+        let in_progress = self
+            .remote_client
+            .export_pgdata_state()
+            .and_then(|state| state.in_progress().cloned());
+        if let Some(in_progress) = in_progress {
+            info!(lsn=%in_progress.lsn, "resuming export");
+            self.spawn_export_pgdata_task(in_progress, ctx);
+        }
+    }
+
+    fn spawn_export_pgdata_task(
+        self: &Arc<Self>,
+        in_progress: index_part_format::InProgress,
+        ctx: &RequestContext,
+    ) {
+        let mut task = self.export_pgdata_task.lock().unwrap();
+        if task.as_ref().is_some_and(|t| !t.is_finished()) {
+            return;
+        }
+        let Ok(guard) = self.gate.enter() else {
+            return;
+        };
+
+        let timeline = self.clone();
+        let ctx = ctx.detached_child(TaskKind::ExportPgdata, DownloadBehavior::Download);
+        let span = info_span!("export_pgdata", lsn=%in_progress.lsn);
+        *task = Some(tokio::spawn(
+            async move {
+                let _guard = guard;
+                timeline.export_pgdata_task(in_progress, ctx).await;
+            }
+            .instrument(span),
+        ));
+    }
+
+    async fn export_pgdata_task(
+        self: Arc<Self>,
+        in_progress: index_part_format::InProgress,
+        ctx: RequestContext,
+    ) {
+        let cancel = self.cancel.clone();
+        let result = run(&self, in_progress.clone(), &ctx, &cancel).await;
+
+        let state = match result {
+            Ok(done) => {
+                info!("export done");
+                done
+            }
+            Err(_) if cancel.is_cancelled() => {
+                info!("export interrupted by shutdown, will resume on next activation");
+                return;
+            }
+            Err(err) => {
+                warn!("export failed: {err:#}");
+                in_progress.failed(format!("{err:#}"))
+            }
+        };
+
+        if let Err(e) = self
+            .remote_client
+            .schedule_index_upload_for_export_pgdata_state_update(state)
+        {
+            warn!("failed to record export outcome: {e:#}");
+            return;
+        }
+        if let Err(e) = self.remote_client.wait_completion().await {
+            info!("shut down while recording export outcome: {e:#}");
+        }
+    }
+}
+
+/// Upload all remaining parts of the export and the manifest. Returns the final state.
+async fn run(
+    timeline: &Arc<Timeline>,
+    mut in_progress: index_part_format::InProgress,
+    ctx: &RequestContext,
+    cancel: &CancellationToken,
+) -> anyhow::Result<index_part_format::Root> {
+    let lsn = in_progress.lsn;
+    let storage =
+        importbucket_client::remote_storage_for_location(timeline.conf, &in_progress.location)
+            .await?;
+    let shard_prefix =
+        RemotePath::from_string(&timeline.tenant_shard_id.to_index().to_string()).unwrap();
+
+    // Hold the LSN for the duration of the export. After a restart this fails if GC went past
+    // the LSN before we got to renew the lease, in which case the export can't be completed.
+    timeline
+        .init_lsn_lease(lsn, LSN_LEASE_LENGTH, ctx)
+        .context("lease export LSN")?;
+
+    let part_path = timeline
+        .conf
+        .timeline_path(&timeline.tenant_shard_id, &timeline.timeline_id)
+        .join(format!("export-part.{TEMP_FILE_SUFFIX}"));
+
+    if timeline.tenant_shard_id.is_shard_zero() && !in_progress.progress.base_done {
+        let mut part = create_part(&part_path).await?;
+        basebackup::send_export_base_tarball(&mut part, timeline, lsn, ctx).await?;
+        finish_part(part).await?;
+        upload_part(
+            &storage,
+            &shard_prefix,
+            &mut in_progress,
+            &part_path,
+            cancel,
+        )
+        .await?;
+        in_progress.progress.base_done = true;
+        persist_progress(timeline, &in_progress).await?;
+    }
+
+    let local_sizes = list_rel_sizes(timeline, lsn, ctx).await?;
+    let rel_sizes = if timeline.tenant_shard_id.shard_count.count() == 1 {
+        local_sizes.clone()
+    } else {
+        let path = RemotePath::from_string(&format!(
+            "{}/{REL_SIZES_OBJECT}",
+            TenantShardId {
+                shard_number: ShardNumber(0),
+                ..timeline.tenant_shard_id
+            }
+            .to_index()
+        ))
+        .unwrap();
+        if timeline.tenant_shard_id.is_shard_zero() {
+            // Only depends on the data at the LSN, so uploading it again on resume is harmless.
+            let rel_sizes = RelSizes {
+                lsn,
+                rels: local_sizes.clone(),
+            };
+            let data = Bytes::from(serde_json::to_vec(&rel_sizes)?);
+            upload(&storage, &path, data, cancel).await?;
+            local_sizes.clone()
+        } else {
+            wait_rel_sizes(&storage, &path, lsn, cancel).await?
+        }
+    };
+    let segments = list_rel_segments(&rel_sizes, &local_sizes);
+    info!(
+        segments = segments.len(),
+        segments_done = in_progress.progress.segments_done,
+        "exporting relation data"
+    );
+
+    let mut next = in_progress.progress.segments_done;
+    while next < segments.len() {
+        if cancel.is_cancelled() {
+            anyhow::bail!("cancelled");
+        }
+        timeline
+            .renew_lsn_lease(lsn, LSN_LEASE_LENGTH, ctx)
+            .context("renew export LSN lease")?;
+
+        let mut part = create_part(&part_path).await?;
+        let mut part_size = 0;
+        while next < segments.len() && part_size < in_progress.part_size {
+            part_size += write_segment(&mut part, timeline, &segments[next], lsn, ctx).await?;
+            next += 1;
+        }
+        finish_part(part).await?;
+        upload_part(
+            &storage,
+            &shard_prefix,
+            &mut in_progress,
+            &part_path,
+            cancel,
+        )
+        .await?;
+        in_progress.progress.segments_done = next;
+        persist_progress(timeline, &in_progress).await?;
+    }
+
+    let shard = timeline.get_shard_identity();
+    let manifest = Manifest {
+        lsn,
+        pg_version: timeline.pg_version,
+        shard_number: shard.number,
+        shard_count: shard.count,
+        stripe_size: shard.stripe_size,
+        parts: (0..in_progress.progress.parts_uploaded)
+            .map(part_name)
+            .collect(),
+    };
+    let manifest = Bytes::from(serde_json::to_vec_pretty(&manifest)?);
+    upload(
+        &storage,
+        &shard_prefix.join("manifest.json"),
+        manifest,
+        cancel,
+    )
+    .await?;
+
+    Ok(in_progress.done())
+}
+
+fn part_name(n: usize) -> String {
+    format!("part-{n:06}.tar.zst")
+}
+
+type PartWriter = ZstdEncoder<BufWriter<tokio::fs::File>>;
+
+/// Start a part in the local staging file, replacing any leftover from an interrupted attempt.
+async fn create_part(path: &Utf8PathBuf) -> std::io::Result<PartWriter> {
+    let file = tokio::fs::File::create(path).await?;
+    Ok(ZstdEncoder::new(BufWriter::new(file)))
+}
+
+/// Terminate the tar stream and the compression of a part.
+async fn finish_part(mut part: PartWriter) -> std::io::Result<()> {
+    // Two empty blocks mark the end of a tar archive.
+    part.write_all(&[0; 1024]).await?;
+    part.shutdown().await
+}
+
+/// Upload the staged part at `local_path` and remove it.
+async fn upload_part(
+    storage: &GenericRemoteStorage,
+    shard_prefix: &RemotePath,
+    in_progress: &mut index_part_format::InProgress,
+    local_path: &Utf8PathBuf,
+    cancel: &CancellationToken,
+) -> anyhow::Result<()> {
+    let name = part_name(in_progress.progress.parts_uploaded);
+    let path = shard_prefix.join(&name);
+    let size = tokio::fs::metadata(local_path).await?.len();
+    backoff::retry(
+        || async {
+            let file = tokio::fs::File::open(local_path)
+                .await
+                .with_context(|| format!("open staged export part {local_path}"))?;
+            let data = tokio_util::io::ReaderStream::new(file);
+            storage
+                .upload(data, size as usize, &path, None, cancel)
+                .await
+        },
+        TimeoutOrCancel::caused_by_cancel,
+        FAILED_UPLOAD_WARN_THRESHOLD,
+        FAILED_REMOTE_OP_RETRIES,
+        &format!("upload export object {path}"),
+        cancel,
+    )
+    .await
+    .ok_or_else(|| anyhow::Error::new(TimeoutOrCancel::Cancel))
+    .and_then(|x| x)?;
+    info!(%name, size, "uploaded export part");
+    tokio::fs::remove_file(local_path)
+        .await
+        .with_context(|| format!("remove staged export part {local_path}"))?;
+
+    in_progress.progress.parts_uploaded += 1;
+    in_progress.progress.bytes_uploaded += size;
+    Ok(())
+}
+
+async fn upload(
+    storage: &GenericRemoteStorage,
+    path: &RemotePath,
+    data: Bytes,
+    cancel: &CancellationToken,
+) -> anyhow::Result<()> {
+    backoff::retry(
+        || async {
+            let size = data.len();
+            let data = futures::stream::once(futures::future::ready(Ok(data.clone())));
+            storage.upload(data, size, path, None, cancel).await
+        },
+        TimeoutOrCancel::caused_by_cancel,
+        FAILED_UPLOAD_WARN_THRESHOLD,
+        FAILED_REMOTE_OP_RETRIES,
+        &format!("upload export object {path}"),
+        cancel,
+    )
+    .await
+    .ok_or_else(|| anyhow::Error::new(TimeoutOrCancel::Cancel))
+    .and_then(|x| x)
+}
+
+async fn persist_progress(
+    timeline: &Timeline,
+    in_progress: &index_part_format::InProgress,
+) -> anyhow::Result<()> {
+    timeline
+        .remote_client
+        .schedule_index_upload_for_export_pgdata_state_update(index_part_format::Root::V1(
+            index_part_format::V1::InProgress(in_progress.clone()),
+        ))?;
+    timeline.remote_client.wait_completion().await?;
+    Ok(())
+}
+
+/// Wait until shard zero has published the relation sizes at `path` and return them.
+async fn wait_rel_sizes(
+    storage: &GenericRemoteStorage,
+    path: &RemotePath,
+    lsn: Lsn,
+    cancel: &CancellationToken,
+) -> anyhow::Result<Vec<(RelTag, u32)>> {
+    let storage = importbucket_client::RemoteStorageWrapper::new(storage.clone(), cancel.clone());
+    loop {
+        match storage.get_json::<RelSizes>(path).await? {
+            // Left over from an earlier export to the same location.
+            Some(rel_sizes) if rel_sizes.lsn != lsn => {}
+            Some(rel_sizes) => return Ok(rel_sizes.rels),
+            None => {}
+        }
+        info!("waiting for shard zero to publish relation sizes");
+        tokio::select! {
+            _ = tokio::time::sleep(REL_SIZES_POLL_INTERVAL) => {}
+            _ = cancel.cancelled() => anyhow::bail!("cancelled"),
+        }
+    }
+}
+
+/// All relations to export and their sizes on this shard, in export order. On shard zero the
+/// result only depends on the data at `lsn`.
+///
+/// Unlogged relations are left out: the base part contains their init forks, and their main
+/// forks are reset from those during recovery anyway.
+async fn list_rel_sizes(
+    timeline: &Timeline,
+    lsn: Lsn,
+    ctx: &RequestContext,
+) -> anyhow::Result<Vec<(RelTag, u32)>> {
+    let mut rels = Vec::new();
+    for (spcnode, dbnode) in timeline.list_dbdirs(lsn, ctx).await?.into_keys() {
+        let db_rels = timeline
+            .list_rels(spcnode, dbnode, Version::at(lsn), ctx)
+            .await?;
+        rels.extend(db_rels.iter().copied().filter(|rel| {
+            rel.forknum != INIT_FORKNUM
+                && !(rel.forknum == MAIN_FORKNUM
+                    && db_rels.contains(&rel.with_forknum(INIT_FORKNUM)))
+        }));
+    }
+    rels.sort();
+
+    let mut sizes = Vec::with_capacity(rels.len());
+    for rel in rels {
+        let nblocks = timeline.get_rel_size(rel, Version::at(lsn), ctx).await?;
+        sizes.push((rel, nblocks));
+    }
+    Ok(sizes)
+}
+
+/// All relation segments to export, in export order, given the relation sizes of shard zero
+/// and of this shard. Since the former only depend on the data at the LSN, so does the result,
+/// which is what lets an export resume by counting segments.
+fn list_rel_segments(
+    rel_sizes: &[(RelTag, u32)],
+    local_sizes: &[(RelTag, u32)],
+) -> Vec<RelSegment> {
+    let local_sizes: HashMap<RelTag, u32> = local_sizes.iter().copied().collect();
+    let mut segments = Vec::new();
+    for &(rel, nblocks) in rel_sizes {
+        let local_rel_nblocks = local_sizes.get(&rel).copied().unwrap_or(0);
+        if nblocks == 0 {
+            segments.push(RelSegment {
+                rel,
+                segno: 0,
+                nblocks: 0,
+                local_rel_nblocks,
+            });
+        }
+        for (segno, startblk) in (0..nblocks).step_by(RELSEG_SIZE as usize).enumerate() {
+            segments.push(RelSegment {
+                rel,
+                segno: segno as u32,
+                nblocks: std::cmp::min(RELSEG_SIZE, nblocks - startblk),
+                local_rel_nblocks,
+            });
+        }
+    }
+    segments
+}
+
+/// Write one segment file as a tar entry. Blocks stored on other shards are written as zeroes.
+/// Returns the number of bytes written.
+async fn write_segment<W>(
+    out: &mut W,
+    timeline: &Timeline,
+    segment: &RelSegment,
+    lsn: Lsn,
+    ctx: &RequestContext,
+) -> anyhow::Result<u64>
+where
+    W: AsyncWrite + Unpin,
+{
+    let shard = timeline.get_shard_identity();
+    let size = segment.nblocks as u64 * BLCKSZ as u64;
+    let header = basebackup::new_tar_header(&segment.rel.to_segfile_name(segment.segno), size)?;
+    out.write_all(header.as_bytes()).await?;
+
+    // Read the local blocks in batches of up to `max_get_vectored_keys`, in block order.
+    let startblk = segment.segno * RELSEG_SIZE;
+    let endblk = startblk + segment.nblocks;
+    let max_keys = timeline.conf.max_get_vectored_keys.get() as u64;
+    let mut batches = Vec::new();
+    let mut batch = KeySpaceAccum::new();
+    for blknum in startblk..std::cmp::min(endblk, segment.local_rel_nblocks) {
+        let key = rel_block_to_key(segment.rel, blknum);
+        if shard.is_key_local(&key) {
+            batch.add_key(key);
+            if batch.raw_size() >= max_keys {
+                batches.push(batch.consume_keyspace());
+            }
+        }
+    }
+    if batch.raw_size() > 0 {
+        batches.push(batch.to_keyspace());
+    }
+
+    let io_concurrency = IoConcurrency::spawn_from_conf(
+        timeline.conf.get_vectored_concurrent_io,
+        timeline
+            .gate
+            .enter()
+            .map_err(|_| anyhow::anyhow!("cancelled"))?,
+    );
+
+    // No padding needed: BLCKSZ is a multiple of the tar block size.
+    let mut next_blknum = startblk;
+    for batch in batches {
+        let query = VersionedKeySpaceQuery::uniform(batch, lsn);
+        let blocks = timeline
+            .get_vectored(query, io_concurrency.clone(), ctx)
+            .await?;
+        for (key, img) in blocks {
+            // Blocks in between are stored on other shards.
+            for _ in next_blknum..key.field6 {
+                out.write_all(&ZERO_PAGE).await?;
+            }
+            out.write_all(&img?).await?;
+            next_blknum = key.field6 + 1;
+        }
+    }
+    for _ in next_blknum..endblk {
+        out.write_all(&ZERO_PAGE).await?;
+    }
+
+    Ok(header.as_bytes().len() as u64 + size)
+}
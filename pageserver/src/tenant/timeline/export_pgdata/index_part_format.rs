@@ -0,0 +1,114 @@
+use chrono::NaiveDateTime;
+use pageserver_api::models::TimelineExportStatus;
+use serde::{Deserialize, Serialize};
+use utils::lsn::Lsn;
+
+use crate::tenant::timeline::import_pgdata::index_part_format::Location;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub enum Root {
+    V1(V1),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub enum V1 {
+    InProgress(InProgress),
+    Done(Done),
+    Failed(Failed),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct InProgress {
+    pub location: Location,
+    pub lsn: Lsn,
+    pub part_size: u64,
+    pub started_at: NaiveDateTime,
+    pub progress: Progress,
+}
+
+/// How far an export got. Parts are uploaded in order and only recorded here once uploaded, so
+/// after a restart the export continues with the next part.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct Progress {
+    /// Whether the part with all non-relational files has been uploaded.
+    pub base_done: bool,
+    /// Number of relation segments, in export order, contained in the uploaded parts.
+    pub segments_done: usize,
+    pub parts_uploaded: usize,
+    pub bytes_uploaded: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Done {
+    pub location: Location,
+    pub lsn: Lsn,
+    pub started_at: NaiveDateTime,
+    pub finished_at: NaiveDateTime,
+    pub parts_uploaded: usize,
+    pub bytes_uploaded: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Failed {
+    pub location: Location,
+    pub lsn: Lsn,
+    pub started_at: NaiveDateTime,
+    pub finished_at: NaiveDateTime,
+    pub error: String,
+}
+
+impl Root {
+    pub fn in_progress(&self) -> Option<&InProgress> {
+        match self {
+            Root::V1(V1::InProgress(in_progress)) => Some(in_progress),
+            Root::V1(V1::Done(_) | V1::Failed(_)) => None,
+        }
+    }
+
+    pub fn status(&self) -> TimelineExportStatus {
+        match self {
+            Root::V1(V1::InProgress(in_progress)) => TimelineExportStatus::InProgress {
+                lsn: in_progress.lsn,
+                started_at: in_progress.started_at,
+                parts_uploaded: in_progress.progress.parts_uploaded,
+                bytes_uploaded: in_progress.progress.bytes_uploaded,
+            },
+            Root::V1(V1::Done(done)) => TimelineExportStatus::Done {
+                lsn: done.lsn,
+                started_at: done.started_at,
+                finished_at: done.finished_at,
+                parts_uploaded: done.parts_uploaded,
+                bytes_uploaded: done.bytes_uploaded,
+            },
+            Root::V1(V1::Failed(failed)) => TimelineExportStatus::Failed {
+                lsn: failed.lsn,
+                started_at: failed.started_at,
+                finished_at: failed.finished_at,
+                error: failed.error.clone(),
+            },
+        }
+    }
+}
+
+impl InProgress {
+    pub fn done(&self) -> Root {
+        Root::V1(V1::Done(Done {
+            location: self.location.clone(),
+            lsn: self.lsn,
+            started_at: self.started_at,
+            finished_at: chrono::Utc::now().naive_utc(),
+            parts_uploaded: self.progress.parts_uploaded,
+            bytes_uploaded: self.progress.bytes_uploaded,
+        }))
+    }
+
+    pub fn failed(&self, error: String) -> Root {
+        Root::V1(V1::Failed(Failed {
+            location: self.location.clone(),
+            lsn: self.lsn,
+            started_at: self.started_at,
+            finished_at: chrono::Utc::now().naive_utc(),
+            error,
+        }))
+    }
+}
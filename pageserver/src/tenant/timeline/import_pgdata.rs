@@ -18,7 +18,7 @@
 use crate::tenant::timeline::layer_manager::LayerManagerLockHolder;
 
 mod flow;
-mod importbucket_client;
+pub(crate) mod importbucket_client;
 mod importbucket_format;
 pub(crate) mod index_part_format;
 
@@ -22,6 +22,16 @@ pub async fn new(
     location: &index_part_format::Location,
     cancel: CancellationToken,
 ) -> Result<RemoteStorageWrapper, anyhow::Error> {
+    let location_storage = remote_storage_for_location(conf, location).await?;
+    let storage_wrapper = RemoteStorageWrapper::new(location_storage, cancel);
+    Ok(storage_wrapper)
+}
+
+/// Set up a remote storage client for an import or export location.
+pub(crate) async fn remote_storage_for_location(
+    conf: &'static PageServerConf,
+    location: &index_part_format::Location,
+) -> Result<GenericRemoteStorage, anyhow::Error> {
     // Downloads should be reasonably sized. We do ranged reads for relblock raw data
     // and full reads for SLRU segments which are bounded by Postgres.
     let timeout = RemoteStorageConfig::DEFAULT_TIMEOUT;
@@ -62,8 +72,7 @@ pub async fn new(
             ))
         }
     };
-    let storage_wrapper = RemoteStorageWrapper::new(location_storage, cancel);
-    Ok(storage_wrapper)
+    Ok(location_storage)
 }
 
 /// Wrap [`remote_storage`] APIs to make it look a bit more like a filesystem API
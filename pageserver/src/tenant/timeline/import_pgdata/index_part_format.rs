@@ -47,6 +47,25 @@ pub enum Location {
     },
 }
 
+impl From<pageserver_api::models::ImportPgdataLocation> for Location {
+    fn from(location: pageserver_api::models::ImportPgdataLocation) -> Self {
+        use pageserver_api::models::ImportPgdataLocation;
+        match location {
+            #[cfg(feature = "testing")]
+            ImportPgdataLocation::LocalFs { path } => Location::LocalFs { path },
+            ImportPgdataLocation::AwsS3 {
+                region,
+                bucket,
+                key,
+            } => Location::AwsS3 {
+                region,
+                bucket,
+                key,
+            },
+        }
+    }
+}
+
 impl Root {
     pub fn is_done(&self) -> bool {
         match self {
@@ -1,4 +1,4 @@
-use std::collections::{HashMap, hash_map};
+use std::collections::{HashMap, HashSet, hash_map};
 use std::str::FromStr;
 
 use anyhow::Context;
@@ -65,6 +65,52 @@ pub(super) fn scan_timeline_dir(path: &Utf8Path) -> anyhow::Result<Vec<Discovere
     Ok(ret)
 }
 
+/// Adds the layer files in the timeline directory on the slow tier to the ones `discovered` in
+/// the workdir, see [`crate::tenant::storage_layer::tier`].
+///
+/// A layer found in both keeps the workdir copy, and the slow tier copy is removed: a move between
+/// the tiers stopped before removing the previous copy. Temporary files of interrupted moves are
+/// removed too.
+pub(super) fn scan_slow_tier_timeline_dir(
+    path: &Utf8Path,
+    discovered: &mut Vec<Discovered>,
+) -> anyhow::Result<()> {
+    let entries = match path.read_dir_utf8() {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e.into()),
+    };
+
+    let fast_file_names = discovered
+        .iter()
+        .filter_map(|discovered| match discovered {
+            Discovered::Layer(_, local) => local.local_path.file_name().map(str::to_owned),
+            _ => None,
+        })
+        .collect::<HashSet<_>>();
+
+    for direntry in entries {
+        let direntry = direntry?;
+        let file_name = direntry.file_name();
+
+        if fast_file_names.contains(file_name) {
+            cleanup(direntry.path(), "slow tier copy of a workdir layer")?;
+        } else if is_temporary(direntry.path()) {
+            cleanup(direntry.path(), "temporary slow tier file")?;
+        } else if let Ok(layer_name) = LayerName::from_str(file_name) {
+            let file_size = direntry.metadata()?.len();
+            discovered.push(Discovered::Layer(
+                layer_name,
+                LocalLayerFileMetadata::new(direntry.path().to_owned(), file_size),
+            ));
+        } else {
+            tracing::warn!(path=%direntry.path(), "ignoring unrecognized file on the slow tier");
+        }
+    }
+
+    Ok(())
+}
+
 /// Whereas `LayerFileMetadata` describes the metadata we would store in remote storage,
 /// this structure extends it with metadata describing the layer's presence in local storage.
 #[derive(Clone, Debug)]
@@ -0,0 +1,333 @@
+//! Rewrite of a timeline's old image layers in the current storage format.
+//!
+//! Changes of [`STORAGE_FORMAT_VERSION`] or of the `image_compression` setting only apply to
+//! newly written layers, so image layers below the PITR window may stay in their original format
+//! for as long as the timeline exists. A layer rewrite visits all image layers of a timeline and
+//! rewrites those that are not in the current format under the same name, e.g. uncompressed ones
+//! when compression is enabled. Delta layers are left to compaction.
+//!
+//! Only layers written by a generation before the one the rewrite was started in are eligible.
+//! Like the shard ancestor rewrites of compaction, this guarantees that the new layer has another
+//! local and remote path than the one it replaces, and that the old layer is durable in remote
+//! storage. It also means that a rewrite that resumes in a later generation does not pick up the
+//! layers it already rewrote.
+//!
+//! Progress is recorded in the index part along with each rewritten layer, so a rewrite continues
+//! where it left off after a restart or migration.
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use http_utils::error::ApiError;
+use pageserver_api::models::{ImageCompressionAlgorithm, LayerRewriteRequest, LayerRewriteStatus};
+use tokio_util::sync::CancellationToken;
+use tracing::{Instrument, debug, info, info_span, warn};
+
+use super::Timeline;
+use super::layer_manager::LayerManagerLockHolder;
+use crate::STORAGE_FORMAT_VERSION;
+use crate::context::{DownloadBehavior, RequestContext};
+use crate::task_mgr::TaskKind;
+use crate::tenant::storage_layer::{ImageLayerWriter, Layer, LayerVisibilityHint};
+
+pub(crate) mod index_part_format;
+
+/// Progress is persisted with every rewritten layer, and at least this often while checking
+/// layers that don't need a rewrite.
+const PERSIST_PROGRESS_INTERVAL: Duration = Duration::from_secs(60);
+
+impl Timeline {
+    /// Start rewriting this timeline's old image layers. Starting a rewrite while one is in
+    /// progress is a no-op, so that callers can safely retry.
+    pub(crate) async fn start_layer_rewrite(
+        self: &Arc<Self>,
+        req: LayerRewriteRequest,
+        ctx: &RequestContext,
+    ) -> Result<LayerRewriteStatus, ApiError> {
+        if let Some(current) = self.remote_client.layer_rewrite_state() {
+            if let Some(in_progress) = current.in_progress() {
+                self.spawn_layer_rewrite_task(in_progress.clone(), ctx);
+                return Ok(current.status());
+            }
+        }
+
+        if self.generation.is_none() {
+            return Err(ApiError::PreconditionFailed(
+                "layer rewrites require generations".into(),
+            ));
+        }
+
+        let in_progress = index_part_format::InProgress {
+            generation: self.generation,
+            max_bytes_per_second: req.max_bytes_per_second,
+            started_at: chrono::Utc::now().naive_utc(),
+            progress: Default::default(),
+        };
+        let state =
+            index_part_format::Root::V1(index_part_format::V1::InProgress(in_progress.clone()));
+        self.remote_client
+            .schedule_index_upload_for_layer_rewrite_state_update(state.clone())
+            .map_err(ApiError::InternalServerError)?;
+        self.remote_client
+            .wait_completion()
+            .await
+            .map_err(|_| ApiError::ShuttingDown)?;
+
+        info!(max_bytes_per_second=?req.max_bytes_per_second, "starting layer rewrite");
+        self.spawn_layer_rewrite_task(in_progress, ctx);
+        Ok(state.status())
+    }
+
+    pub(crate) fn layer_rewrite_status(&self) -> Option<LayerRewriteStatus> {
+        self.remote_client
+            .layer_rewrite_state()
+            .map(|state| state.status())
+    }
+
+    /// Continue a layer rewrite that was in progress when the timeline was last shut down.
+    pub(super) fn resume_layer_rewrite(self: &Arc<Self>, ctx: &RequestContext) {
+        let in_progress = self
+            .remote_client
+            .layer_rewrite_state()
+            .and_then(|state| state.in_progress().cloned());
+        if let Some(in_progress) = in_progress {
+            info!("resuming layer rewrite");
+            self.spawn_layer_rewrite_task(in_progress, ctx);
+        }
+    }
+
+    fn spawn_layer_rewrite_task(
+        self: &Arc<Self>,
+        in_progress: index_part_format::InProgress,
+        ctx: &RequestContext,
+    ) {
+        let mut task = self.layer_rewrite_task.lock().unwrap();
+        if task.as_ref().is_some_and(|t| !t.is_finished()) {
+            return;
+        }
+        let Ok(guard) = self.gate.enter() else {
+            return;
+        };
+
+        let timeline = self.clone();
+        let ctx = ctx.detached_child(TaskKind::LayerRewrite, DownloadBehavior::Download);
+        let span = info_span!("layer_rewrite");
+        *task = Some(tokio::spawn(
+            async move {
+                let _guard = guard;
+                timeline.layer_rewrite_task(in_progress, ctx).await;
+            }
+            .instrument(span),
+        ));
+    }
+
+    async fn layer_rewrite_task(
+        self: Arc<Self>,
+        in_progress: index_part_format::InProgress,
+        ctx: RequestContext,
+    ) {
+        let cancel = self.cancel.clone();
+        let result = run(&self, in_progress.clone(), &ctx, &cancel).await;
+
+        let state = match result {
+            Ok(done) => {
+                info!("layer rewrite done");
+                done
+            }
+            Err(_) if cancel.is_cancelled() => {
+                info!("layer rewrite interrupted by shutdown, will resume on next activation");
+                return;
+            }
+            Err(err) => {
+                warn!("layer rewrite failed: {err:#}");
+                in_progress.failed(format!("{err:#}"))
+            }
+        };
+
+        if let Err(e) = self
+            .remote_client
+            .schedule_index_upload_for_layer_rewrite_state_update(state)
+        {
+            warn!("failed to record layer rewrite outcome: {e:#}");
+            return;
+        }
+        if let Err(e) = self.remote_client.wait_completion().await {
+            info!("shut down while recording layer rewrite outcome: {e:#}");
+        }
+    }
+}
+
+/// Visit all remaining image layers in name order, rewriting those in an outdated format.
+/// Returns the final state.
+async fn run(
+    timeline: &Arc<Timeline>,
+    mut in_progress: index_part_format::InProgress,
+    ctx: &RequestContext,
+    cancel: &CancellationToken,
+) -> anyhow::Result<index_part_format::Root> {
+    let layers = {
+        let guard = timeline
+            .layers
+            .read(LayerManagerLockHolder::Compaction)
+            .await;
+        let mut layers: Vec<Layer> = guard
+            .layer_map()?
+            .iter_historic_layers()
+            .filter(|desc| !desc.is_delta())
+            .filter(|desc| {
+                in_progress
+                    .progress
+                    .last_visited
+                    .as_ref()
+                    .is_none_or(|last| desc.layer_name() > *last)
+            })
+            .map(|desc| guard.get_from_desc(&desc))
+            .collect();
+        layers.sort_by_key(|layer| layer.layer_desc().layer_name());
+        layers
+    };
+    info!(
+        layers = layers.len(),
+        layers_checked = in_progress.progress.layers_checked,
+        "checking image layers"
+    );
+
+    let mut persisted_at = Instant::now();
+    for layer in layers {
+        if cancel.is_cancelled() {
+            anyhow::bail!("cancelled");
+        }
+
+        let was_resident = layer.is_likely_resident();
+        let file_size = layer.metadata().file_size;
+        let rewritten = if is_eligible(timeline, &layer, &in_progress) {
+            rewrite_if_outdated(timeline, &layer, ctx, cancel).await?
+        } else {
+            None
+        };
+
+        let progress = &mut in_progress.progress;
+        progress.last_visited = Some(layer.layer_desc().layer_name());
+        progress.layers_checked += 1;
+        if let Some(new_size) = rewritten {
+            progress.layers_rewritten += 1;
+            progress.bytes_before += file_size;
+            progress.bytes_after += new_size;
+        }
+
+        if rewritten.is_some() || persisted_at.elapsed() >= PERSIST_PROGRESS_INTERVAL {
+            persist_progress(timeline, &in_progress).await?;
+            persisted_at = Instant::now();
+        }
+
+        // Downloading a layer to check its format costs as much as reading it for a rewrite.
+        if rewritten.is_some() || !was_resident {
+            throttle(file_size, in_progress.max_bytes_per_second, cancel).await;
+        }
+    }
+
+    Ok(in_progress.done())
+}
+
+/// Whether a layer may be rewritten, without looking at its contents. See the module docs for
+/// why only layers of earlier generations are eligible.
+fn is_eligible(
+    timeline: &Timeline,
+    layer: &Layer,
+    in_progress: &index_part_format::InProgress,
+) -> bool {
+    // Layers that aren't visible won't be needed by reads and will likely be garbage collected.
+    if layer.visibility() != LayerVisibilityHint::Visible {
+        debug!(%layer, "skipping invisible layer");
+        return false;
+    }
+    let generation = layer.metadata().generation;
+    if generation >= in_progress.generation || generation == timeline.generation {
+        debug!(%layer, "skipping layer of a recent generation");
+        return false;
+    }
+    true
+}
+
+/// Rewrite a layer if it isn't in the current format. Returns the size of the new layer if it was
+/// rewritten.
+async fn rewrite_if_outdated(
+    timeline: &Arc<Timeline>,
+    layer: &Layer,
+    ctx: &RequestContext,
+    cancel: &CancellationToken,
+) -> anyhow::Result<Option<u64>> {
+    // Hold the compaction lock for the duration of the rewrite: like shard ancestor compaction,
+    // this relies on not racing with compaction removing or replacing the layer.
+    let _guard = tokio::select! {
+        guard = timeline.compaction_lock.lock() => guard,
+        _ = cancel.cancelled() => anyhow::bail!("cancelled"),
+    };
+    if !timeline
+        .layers
+        .read(LayerManagerLockHolder::Compaction)
+        .await
+        .contains(layer)
+    {
+        return Ok(None);
+    }
+
+    let resident = layer.download_and_keep_resident(ctx).await?;
+    let format = resident.image_format(ctx).await?;
+    let compression = timeline.conf.image_compression;
+    let outdated = format.format_version < STORAGE_FORMAT_VERSION
+        || (compression != ImageCompressionAlgorithm::Disabled && !format.compressed);
+    if !outdated {
+        return Ok(None);
+    }
+
+    info!(%layer, ?format, "rewriting layer");
+    let mut writer = ImageLayerWriter::new(
+        timeline.conf,
+        timeline.timeline_id,
+        timeline.tenant_shard_id,
+        &layer.layer_desc().key_range,
+        layer.layer_desc().image_layer_lsn(),
+        &timeline.gate,
+        timeline.cancel.clone(),
+        ctx,
+    )
+    .await?;
+    if resident.rewrite_image(&mut writer, ctx).await? == 0 {
+        return Ok(None);
+    }
+    let (desc, path) = writer.finish(ctx).await?;
+    let new_layer = Layer::finish_creating(timeline.conf, timeline, desc, &path)?;
+    let new_size = new_layer.metadata().file_size;
+    info!(%layer, "rewrote layer, {} -> {new_size} bytes", layer.metadata().file_size);
+
+    timeline
+        .rewrite_layers(vec![(layer.clone(), new_layer)], Vec::new())
+        .await?;
+    Ok(Some(new_size))
+}
+
+/// Sleep for as long as reading `bytes` takes at the rate limit of the rewrite.
+async fn throttle(bytes: u64, max_bytes_per_second: Option<u64>, cancel: &CancellationToken) {
+    let Some(rate) = max_bytes_per_second.filter(|rate| *rate > 0) else {
+        return;
+    };
+    let delay = Duration::from_secs_f64(bytes as f64 / rate as f64);
+    tokio::select! {
+        _ = tokio::time::sleep(delay) => {},
+        _ = cancel.cancelled() => {},
+    }
+}
+
+async fn persist_progress(
+    timeline: &Timeline,
+    in_progress: &index_part_format::InProgress,
+) -> anyhow::Result<()> {
+    timeline
+        .remote_client
+        .schedule_index_upload_for_layer_rewrite_state_update(index_part_format::Root::V1(
+            index_part_format::V1::InProgress(in_progress.clone()),
+        ))?;
+    timeline.remote_client.wait_completion().await?;
+    Ok(())
+}
@@ -0,0 +1,116 @@
+use chrono::NaiveDateTime;
+use pageserver_api::models::LayerRewriteStatus;
+use serde::{Deserialize, Serialize};
+use utils::generation::Generation;
+
+use crate::tenant::storage_layer::LayerName;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub enum Root {
+    V1(V1),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub enum V1 {
+    InProgress(InProgress),
+    Done(Done),
+    Failed(Failed),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct InProgress {
+    /// The generation the rewrite was started in. Only layers written by earlier generations are
+    /// rewritten, so that layers rewritten before a restart or migration are not picked up again.
+    pub generation: Generation,
+    pub max_bytes_per_second: Option<u64>,
+    pub started_at: NaiveDateTime,
+    pub progress: Progress,
+}
+
+/// How far a rewrite got. Layers are visited in name order, and a rewritten layer keeps its name,
+/// so after a restart the rewrite continues after the last visited layer.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct Progress {
+    pub last_visited: Option<LayerName>,
+    pub layers_checked: usize,
+    pub layers_rewritten: usize,
+    /// Total size of the rewritten layers before and after rewriting them.
+    pub bytes_before: u64,
+    pub bytes_after: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Done {
+    pub generation: Generation,
+    pub started_at: NaiveDateTime,
+    pub finished_at: NaiveDateTime,
+    pub layers_checked: usize,
+    pub layers_rewritten: usize,
+    pub bytes_before: u64,
+    pub bytes_after: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Failed {
+    pub generation: Generation,
+    pub started_at: NaiveDateTime,
+    pub finished_at: NaiveDateTime,
+    pub error: String,
+}
+
+impl Root {
+    pub fn in_progress(&self) -> Option<&InProgress> {
+        match self {
+            Root::V1(V1::InProgress(in_progress)) => Some(in_progress),
+            Root::V1(V1::Done(_) | V1::Failed(_)) => None,
+        }
+    }
+
+    pub fn status(&self) -> LayerRewriteStatus {
+        match self {
+            Root::V1(V1::InProgress(in_progress)) => LayerRewriteStatus::InProgress {
+                started_at: in_progress.started_at,
+                layers_checked: in_progress.progress.layers_checked,
+                layers_rewritten: in_progress.progress.layers_rewritten,
+                bytes_before: in_progress.progress.bytes_before,
+                bytes_after: in_progress.progress.bytes_after,
+            },
+            Root::V1(V1::Done(done)) => LayerRewriteStatus::Done {
+                started_at: done.started_at,
+                finished_at: done.finished_at,
+                layers_checked: done.layers_checked,
+                layers_rewritten: done.layers_rewritten,
+                bytes_before: done.bytes_before,
+                bytes_after: done.bytes_after,
+            },
+            Root::V1(V1::Failed(failed)) => LayerRewriteStatus::Failed {
+                started_at: failed.started_at,
+                finished_at: failed.finished_at,
+                error: failed.error.clone(),
+            },
+        }
+    }
+}
+
+impl InProgress {
+    pub fn done(&self) -> Root {
+        Root::V1(V1::Done(Done {
+            generation: self.generation,
+            started_at: self.started_at,
+            finished_at: chrono::Utc::now().naive_utc(),
+            layers_checked: self.progress.layers_checked,
+            layers_rewritten: self.progress.layers_rewritten,
+            bytes_before: self.progress.bytes_before,
+            bytes_after: self.progress.bytes_after,
+        }))
+    }
+
+    pub fn failed(&self, error: String) -> Root {
+        Root::V1(V1::Failed(Failed {
+            generation: self.generation,
+            started_at: self.started_at,
+            finished_at: chrono::Utc::now().naive_utc(),
+            error,
+        }))
+    }
+}
@@ -70,6 +70,15 @@ pub(crate) async fn offload_timeline(
         return Ok(());
     };
 
+    offload_archived_timeline(tenant, &timeline, &guard).await
+}
+
+/// Offloads an archived timeline while its deletion guard is held by the caller.
+pub(super) async fn offload_archived_timeline(
+    tenant: &TenantShard,
+    timeline: &Arc<Timeline>,
+    guard: &DeletionGuard,
+) -> Result<(), OffloadError> {
     match timeline.remote_client.shutdown_if_archived().await {
         Ok(()) => {}
         Err(ShutdownIfArchivedError::NotInitialized(_)) => {
@@ -87,9 +96,9 @@ pub(crate) async fn offload_timeline(
     // to make deletions possible while offloading is in progress
 
     let conf = &tenant.conf;
-    delete_local_timeline_directory(conf, tenant.tenant_shard_id, &timeline).await;
+    delete_local_timeline_directory(conf, tenant.tenant_shard_id, timeline).await;
 
-    let remaining_refcount = remove_timeline_from_tenant(tenant, &timeline, &guard);
+    let remaining_refcount = remove_timeline_from_tenant(tenant, timeline, guard);
 
     {
         let mut offloaded_timelines = tenant.timelines_offloaded.lock().unwrap();
@@ -105,7 +114,7 @@ pub(crate) async fn offload_timeline(
         offloaded_timelines.insert(
             timeline.timeline_id,
             Arc::new(
-                OffloadedTimeline::from_timeline(&timeline)
+                OffloadedTimeline::from_timeline(timeline)
                     .expect("we checked above that timeline was ready"),
             ),
         );
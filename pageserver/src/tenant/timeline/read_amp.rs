@@ -0,0 +1,151 @@
+//! Read amplification observed on the GetPage path, per key.
+//!
+//! Used by [`ImageCreationPolicy::ReadAmplification`] to create image layers where reads are
+//! expensive, instead of wherever deltas pile up. Only a fraction of the read batches is sampled,
+//! and the samples decay over time, so that ranges that are no longer read cool down.
+//!
+//! [`ImageCreationPolicy::ReadAmplification`]: pageserver_api::models::ImageCreationPolicy::ReadAmplification
+
+use std::collections::BTreeMap;
+use std::ops::Range;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use pageserver_api::key::Key;
+
+/// Sample one in this many read batches.
+const SAMPLE_ONE_IN: u32 = 16;
+
+/// Upper bound on the number of distinct keys we keep samples for. Beyond that, new samples are
+/// merged into the nearest sampled key below them.
+const MAX_SAMPLED_KEYS: usize = 16 * 1024;
+
+/// Samples lose half of their weight after this long.
+const HALF_LIFE: Duration = Duration::from_secs(3600);
+
+#[derive(Default)]
+pub(crate) struct ReadAmpStats {
+    samples: Mutex<BTreeMap<Key, Sample>>,
+}
+
+struct Sample {
+    reads: f64,
+    layers_visited: f64,
+    updated_at: Instant,
+}
+
+impl Sample {
+    fn decay_to(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated_at);
+        let factor = (-elapsed.as_secs_f64() / HALF_LIFE.as_secs_f64()).exp2();
+        self.reads *= factor;
+        self.layers_visited *= factor;
+        self.updated_at = now;
+    }
+}
+
+/// Aggregated read amplification of a key range.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub(crate) struct ReadAmp {
+    /// Decayed number of sampled reads.
+    pub(crate) reads: f64,
+    /// Decayed total number of layers visited by the sampled reads.
+    pub(crate) layers_visited: f64,
+}
+
+impl ReadAmp {
+    pub(crate) fn layers_per_read(&self) -> f64 {
+        if self.reads > 0.0 {
+            self.layers_visited / self.reads
+        } else {
+            0.0
+        }
+    }
+}
+
+impl ReadAmpStats {
+    /// Called for every read batch. `layers_visited` is the number of layers the batch visited,
+    /// which counts towards each of the `reads` pages in it, like the `layers_per_read` metric.
+    pub(crate) fn maybe_record(&self, key: Key, layers_visited: u32, reads: usize) {
+        if rand::random_ratio(1, SAMPLE_ONE_IN) {
+            self.record_at(key, layers_visited, reads, Instant::now());
+        }
+    }
+
+    fn record_at(&self, key: Key, layers_visited: u32, reads: usize, now: Instant) {
+        let mut samples = self.samples.lock().unwrap();
+        let key = if samples.len() >= MAX_SAMPLED_KEYS && !samples.contains_key(&key) {
+            match samples.range(..key).next_back() {
+                Some((nearest, _)) => *nearest,
+                None => *samples.keys().next().expect("map is full"),
+            }
+        } else {
+            key
+        };
+        let sample = samples.entry(key).or_insert(Sample {
+            reads: 0.0,
+            layers_visited: 0.0,
+            updated_at: now,
+        });
+        sample.decay_to(now);
+        sample.reads += reads as f64;
+        sample.layers_visited += layers_visited as f64 * reads as f64;
+    }
+
+    /// The read amplification of the keys in `ranges`.
+    pub(crate) fn get(&self, ranges: &[Range<Key>]) -> ReadAmp {
+        self.get_at(ranges, Instant::now())
+    }
+
+    fn get_at(&self, ranges: &[Range<Key>], now: Instant) -> ReadAmp {
+        let mut samples = self.samples.lock().unwrap();
+        let mut result = ReadAmp::default();
+        for range in ranges {
+            for sample in samples.range_mut(range.clone()).map(|(_, s)| s) {
+                sample.decay_to(now);
+                result.reads += sample.reads;
+                result.layers_visited += sample.layers_visited;
+            }
+        }
+        result
+    }
+
+    /// Forget the samples in `range`, e.g. because a new image layer changed its read
+    /// amplification.
+    pub(crate) fn reset(&self, range: &Range<Key>) {
+        let mut samples = self.samples.lock().unwrap();
+        let keys: Vec<Key> = samples.range(range.clone()).map(|(k, _)| *k).collect();
+        for key in keys {
+            samples.remove(&key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn aggregate_and_decay() {
+        let stats = ReadAmpStats::default();
+        let now = Instant::now();
+        let key = |i| Key::from_i128(i);
+
+        stats.record_at(key(10), 8, 1, now);
+        stats.record_at(key(11), 2, 3, now);
+        stats.record_at(key(100), 1, 1, now);
+
+        let hot = stats.get_at(&[key(0)..key(50)], now);
+        assert_eq!(hot.reads, 4.0);
+        assert_eq!(hot.layers_per_read(), (8.0 + 2.0 * 3.0) / 4.0);
+
+        // Decay does not change the average, only the weight.
+        let later = stats.get_at(&[key(0)..key(50)], now + HALF_LIFE);
+        assert!((later.reads - 2.0).abs() < 1e-9);
+        assert!((later.layers_per_read() - hot.layers_per_read()).abs() < 1e-9);
+
+        stats.reset(&(key(0)..key(50)));
+        assert_eq!(stats.get_at(&[key(0)..key(50)], now), ReadAmp::default());
+        assert_eq!(stats.get_at(&[key(0)..key(200)], now).reads, 1.0);
+    }
+}
@@ -0,0 +1,180 @@
+//! Reset of a timeline to an earlier LSN of its own history.
+//!
+//! The history after the LSN is not thrown away but kept on a backup branch. Before the reset,
+//! the backup branch is created at the tip of the timeline and detached from it, which copies
+//! all of the timeline's layers into the backup branch and reparents the timeline's children
+//! onto it. The reset then turns the timeline into a branch of the backup branch at the LSN,
+//! without any layers of its own.
+//!
+//! Only the pageserver part is done here. The storage controller creates and detaches the backup
+//! branch, and resets the WAL on the safekeepers before asking the pageservers to reset the
+//! timeline, so that the discarded WAL is not ingested again.
+
+use std::sync::Arc;
+
+use http_utils::error::ApiError;
+use tracing::info;
+use utils::lsn::Lsn;
+
+use super::Timeline;
+use crate::tenant::TenantShard;
+use crate::tenant::metadata::TimelineMetadata;
+
+impl Timeline {
+    /// Reset this timeline to `lsn`, making it a branch of `backup`, which must already have been
+    /// branched from the tip of this timeline and detached from it.
+    ///
+    /// Returns true if the timeline was reset and the tenant must be reloaded to pick up the new
+    /// ancestry. Until then, the timeline's remote timeline client is shut down, so that nothing
+    /// of the old history is uploaded again.
+    pub(crate) async fn reset_onto_backup(
+        self: &Arc<Self>,
+        tenant: &TenantShard,
+        lsn: Lsn,
+        backup: &Arc<Timeline>,
+    ) -> Result<bool, ApiError> {
+        if self.get_ancestor_timeline_id() == Some(backup.timeline_id)
+            && self.get_ancestor_lsn() == lsn
+        {
+            // Already reset and reloaded, this is a retry.
+            return Ok(false);
+        }
+
+        if self.get_ancestor_timeline_id().is_some() {
+            return Err(ApiError::BadRequest(anyhow::anyhow!(
+                "resetting timelines with an ancestor is not supported"
+            )));
+        }
+        if !lsn.is_aligned() {
+            return Err(ApiError::BadRequest(anyhow::anyhow!(
+                "reset lsn {lsn} is not aligned"
+            )));
+        }
+        if lsn < self.initdb_lsn {
+            return Err(ApiError::BadRequest(anyhow::anyhow!(
+                "reset lsn {lsn} is before initdb lsn {}",
+                self.initdb_lsn
+            )));
+        }
+
+        // Hold the GC lock while validating, like branch creation, so that the LSN stays valid.
+        let _gc_cs = tenant.gc_cs.lock().await;
+
+        let applied_gc_cutoff_lsn = {
+            let applied_gc_cutoff_lsn = self.get_applied_gc_cutoff_lsn();
+            let gc_info = self.gc_info.read().unwrap();
+            let planned_cutoff = gc_info.min_cutoff();
+            if gc_info.lsn_covered_by_lease(lsn) {
+                info!(
+                    "skipping comparison of {lsn} with gc cutoff {} and planned gc cutoff {planned_cutoff} due to lsn lease",
+                    *applied_gc_cutoff_lsn
+                );
+            } else {
+                self.check_lsn_is_in_scope(lsn, &applied_gc_cutoff_lsn)
+                    .map_err(ApiError::BadRequest)?;
+                if lsn < planned_cutoff {
+                    return Err(ApiError::BadRequest(anyhow::anyhow!(
+                        "reset lsn {lsn} is less than planned GC cutoff {planned_cutoff}"
+                    )));
+                }
+            }
+            *applied_gc_cutoff_lsn
+        };
+
+        if backup.get_ancestor_timeline_id().is_some() {
+            return Err(ApiError::PreconditionFailed(
+                "backup timeline must be detached before resetting".into(),
+            ));
+        }
+        let Some((detached_from, tip)) = backup.remote_client.detached_previous_ancestor() else {
+            return Err(ApiError::PreconditionFailed(
+                "backup timeline was not detached from any timeline".into(),
+            ));
+        };
+        if detached_from != self.timeline_id {
+            return Err(ApiError::PreconditionFailed(
+                format!("backup timeline was detached from {detached_from}").into(),
+            ));
+        }
+
+        let last_record = self.get_last_record_rlsn();
+        if last_record.last > tip {
+            // The backup would not have all of the history.
+            return Err(ApiError::PreconditionFailed(
+                format!(
+                    "timeline has advanced to {} past the backup branch point {tip}",
+                    last_record.last
+                )
+                .into(),
+            ));
+        }
+        if lsn > last_record.last {
+            return Err(ApiError::BadRequest(anyhow::anyhow!(
+                "reset lsn {lsn} is past the last record lsn {}",
+                last_record.last
+            )));
+        }
+
+        let (timelines, offloaded) = tenant.list_timelines_and_offloaded();
+        let has_children = timelines
+            .iter()
+            .any(|tl| tl.get_ancestor_timeline_id() == Some(self.timeline_id))
+            || offloaded
+                .iter()
+                .any(|tl| tl.ancestor_timeline_id == Some(self.timeline_id));
+        if has_children {
+            return Err(ApiError::Conflict(
+                "timeline still has children, which must be reparented onto the backup branch"
+                    .to_string(),
+            ));
+        }
+
+        info!(%lsn, backup_timeline_id=%backup.timeline_id, "resetting timeline");
+
+        // Stop ingesting WAL. The walreceiver is not restarted, the tenant is reloaded after this.
+        let walreceiver = self.walreceiver.lock().unwrap().take();
+        if let Some(walreceiver) = walreceiver {
+            walreceiver.cancel().await;
+        }
+
+        // The safekeepers were reset before us, so we can only have ingested up to the tip.
+        let last_record = self.get_last_record_rlsn();
+        if last_record.last > tip {
+            return Err(ApiError::InternalServerError(anyhow::anyhow!(
+                "timeline advanced to {} past the backup branch point {tip} while resetting",
+                last_record.last
+            )));
+        }
+
+        // Same order as timeline deletion.
+        let _compaction = self.compaction_lock.lock().await;
+        let _gc = self.gc_lock.lock().await;
+
+        let metadata = TimelineMetadata::new(
+            lsn,
+            (lsn == last_record.last).then_some(last_record.prev),
+            Some(backup.timeline_id),
+            lsn,
+            applied_gc_cutoff_lsn,
+            self.initdb_lsn,
+            self.pg_version,
+        );
+
+        self.remote_client
+            .schedule_reset_and_wait(&metadata)
+            .await
+            .map_err(|e| {
+                if self.cancel.is_cancelled() {
+                    ApiError::ShuttingDown
+                } else {
+                    ApiError::InternalServerError(e)
+                }
+            })?;
+
+        // Fence uploads of layers flushed or compacted from the old history until the tenant is
+        // reloaded.
+        self.remote_client.shutdown().await;
+
+        Ok(true)
+    }
+}
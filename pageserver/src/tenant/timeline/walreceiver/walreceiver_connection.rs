@@ -592,6 +592,9 @@ async fn commit(
                 corruption_detected: timeline
                     .corruption_detected
                     .load(std::sync::atomic::Ordering::Relaxed),
+                // Enforced here rather than in housekeeping, so that ingest can't overshoot a
+                // limit by more than one batch of WAL.
+                size_limit_exceeded: timeline.check_size_limits().is_some(),
             };
 
             debug!("neon_status_update {status_update:?}");
@@ -150,6 +150,11 @@ pub(crate) async fn read<'a>(&self, buf: &BufView<'a>) -> Result<BufView<'a>, st
         }
     }
 
+    /// Whether the blob is stored compressed.
+    pub(crate) fn is_compressed(&self) -> bool {
+        self.compression_bits == BYTE_ZSTD
+    }
+
     /// Returns the raw blob including header.
     pub(crate) fn raw_with_header<'a>(&self, buf: &BufView<'a>) -> BufView<'a> {
         buf.view(self.header_start..self.end)
@@ -945,6 +945,18 @@ neon_extend(SMgrRelation reln, ForkNumber forkNum, BlockNumber blkno,
 					 errhint("This limit is defined externally by the project size limit, and internally by neon.max_cluster_size GUC")));
 	}
 
+	/*
+	 * The pageserver asks us to stop growing the database when the timeline
+	 * exceeds one of the size limits configured for the tenant.
+	 */
+	if (reln->smgr_relpersistence == RELPERSISTENCE_PERMANENT &&
+		!AmAutoVacuumWorkerProcess() &&
+		GetNeonSizeLimitExceeded())
+		ereport(ERROR,
+				(errcode(ERRCODE_DISK_FULL),
+				 errmsg("could not extend file because the storage size limit has been exceeded"),
+				 errhint("This limit is defined by the tenant size limits on the pageserver")));
+
 	/*
 	 * Usually Postgres doesn't extend relation on more than one page (leaving
 	 * holes). But this rule is violated in PG-15 where
@@ -1033,6 +1045,18 @@ neon_zeroextend(SMgrRelation reln, ForkNumber forkNum, BlockNumber blocknum,
 					 errhint("This limit is defined by neon.max_cluster_size GUC")));
 	}
 
+	/*
+	 * The pageserver asks us to stop growing the database when the timeline
+	 * exceeds one of the size limits configured for the tenant.
+	 */
+	if (reln->smgr_relpersistence == RELPERSISTENCE_PERMANENT &&
+		!AmAutoVacuumWorkerProcess() &&
+		GetNeonSizeLimitExceeded())
+		ereport(ERROR,
+				(errcode(ERRCODE_DISK_FULL),
+				 errmsg("could not extend file because the storage size limit has been exceeded"),
+				 errhint("This limit is defined by the tenant size limits on the pageserver")));
+
 	/*
 	 * If a relation manages to grow to 2^32-1 blocks, refuse to extend it any
 	 * more --- we mustn't create a block whose number actually is
@@ -83,6 +83,7 @@ static char *FormatSafekeeperState(Safekeeper *sk);
 static void AssertEventsOkForState(uint32 events, Safekeeper *sk);
 static char *FormatEvents(WalProposer *wp, uint32 events);
 static void UpdateDonorShmem(WalProposer *wp);
+static bool SafekeeperIsWitness(WalProposer *wp, Safekeeper *sk);
 static char *MembershipConfigurationToString(MembershipConfiguration *mconf);
 static void MembershipConfigurationCopy(MembershipConfiguration *src, MembershipConfiguration *dst);
 static void MembershipConfigurationFree(MembershipConfiguration *mconf);
@@ -180,7 +181,7 @@ WalProposerCreate(WalProposerConfig *config, walproposer_api api)
 	}
 	wp->quorum = wp->n_safekeepers / 2 + 1;
 
-	if (wp->config->proto_version != 2 && wp->config->proto_version != 3)
+	if (wp->config->proto_version < 2 || wp->config->proto_version > 4)
 		wp_log(FATAL, "unsupported safekeeper protocol version %d", wp->config->proto_version);
 	if (wp->safekeepers_generation > INVALID_GENERATION && wp->config->proto_version < 3)
 		wp_log(FATAL, "enabling generations requires protocol version 3");
@@ -1116,9 +1117,13 @@ RecvVoteResponse(Safekeeper *sk)
  *
  * `msk` is the member -> safekeeper mapping for mset, i.e. members_safekeepers
  * or new_members_safekeepers.
+ *
+ * Witness members don't have WAL, so they can't be donors; the highest of
+ * their votes is put to `witnessLastLogTerm` and `witnessFlushLsn`.
  */
 static bool
-VotesCollectedMset(WalProposer *wp, MemberSet *mset, Safekeeper **msk, StringInfo s)
+VotesCollectedMset(WalProposer *wp, MemberSet *mset, Safekeeper **msk, StringInfo s,
+				   term_t *witnessLastLogTerm, XLogRecPtr *witnessFlushLsn)
 {
 	uint32		n_votes = 0;
 
@@ -1136,10 +1141,20 @@ VotesCollectedMset(WalProposer *wp, MemberSet *mset, Safekeeper **msk, StringInf
 			 * 0 LSN in the vote response; we still want to set donor to
 			 * something in this case.
 			 */
-			if (GetLastLogTerm(sk) > wp->donorLastLogTerm ||
-				(GetLastLogTerm(sk) == wp->donorLastLogTerm &&
-				 sk->voteResponse.flushLsn > wp->propTermStartLsn) ||
-				wp->donor == NULL)
+			if (mset->m[i].role == MEMBER_ROLE_WITNESS)
+			{
+				if (GetLastLogTerm(sk) > *witnessLastLogTerm ||
+					(GetLastLogTerm(sk) == *witnessLastLogTerm &&
+					 sk->voteResponse.flushLsn > *witnessFlushLsn))
+				{
+					*witnessLastLogTerm = GetLastLogTerm(sk);
+					*witnessFlushLsn = sk->voteResponse.flushLsn;
+				}
+			}
+			else if (GetLastLogTerm(sk) > wp->donorLastLogTerm ||
+					 (GetLastLogTerm(sk) == wp->donorLastLogTerm &&
+					  sk->voteResponse.flushLsn > wp->propTermStartLsn) ||
+					 wp->donor == NULL)
 			{
 				wp->donorLastLogTerm = GetLastLogTerm(sk);
 				wp->propTermStartLsn = sk->voteResponse.flushLsn;
@@ -1161,17 +1176,21 @@ VotesCollectedMset(WalProposer *wp, MemberSet *mset, Safekeeper **msk, StringInf
 /*
  * Checks if enough votes has been collected to get elected and if that's the
  * case finds the highest vote, setting donor, donorLastLogTerm,
- * propTermStartLsn fields. Also sets truncateLsn.
+ * propTermStartLsn fields. Also sets truncateLsn. The highest vote must be
+ * given by a data member, as witnesses can't be donors.
  */
 static bool
 VotesCollected(WalProposer *wp)
 {
 	StringInfoData s;			/* str for logging */
 	bool		collected = false;
+	term_t		witnessLastLogTerm = 0;
+	XLogRecPtr	witnessFlushLsn = InvalidXLogRecPtr;
 
 	/* assumed to be called only when not elected yet */
 	Assert(wp->state == WPS_CAMPAIGN);
 
+	wp->donor = NULL;
 	wp->propTermStartLsn = InvalidXLogRecPtr;
 	wp->donorLastLogTerm = 0;
 	wp->truncateLsn = InvalidXLogRecPtr;
@@ -1218,14 +1237,30 @@ VotesCollected(WalProposer *wp)
 	 */
 	initStringInfo(&s);
 	appendStringInfoString(&s, "mset voters: ");
-	if (!VotesCollectedMset(wp, &wp->mconf.members, wp->members_safekeepers, &s))
+	if (!VotesCollectedMset(wp, &wp->mconf.members, wp->members_safekeepers, &s,
+							&witnessLastLogTerm, &witnessFlushLsn))
 		goto res;
 	if (wp->mconf.new_members.len > 0)
 	{
 		appendStringInfoString(&s, ", new_mset voters: ");
-		if (!VotesCollectedMset(wp, &wp->mconf.new_members, wp->new_members_safekeepers, &s))
+		if (!VotesCollectedMset(wp, &wp->mconf.new_members, wp->new_members_safekeepers, &s,
+								&witnessLastLogTerm, &witnessFlushLsn))
 			goto res;
 	}
+
+	/*
+	 * If a witness is ahead of all data members which voted, WAL up to its
+	 * position might be committed, but we can't fetch it from the witness.
+	 * Wait for a data member having it instead.
+	 */
+	if (wp->donor == NULL || witnessLastLogTerm > wp->donorLastLogTerm ||
+		(witnessLastLogTerm == wp->donorLastLogTerm &&
+		 witnessFlushLsn > wp->propTermStartLsn))
+	{
+		wp_log(LOG, "quorum collected, but witness is ahead of data members at term " UINT64_FORMAT ", lsn %X/%X, waiting for more votes, %s",
+			   witnessLastLogTerm, LSN_FORMAT_ARGS(witnessFlushLsn), s.data);
+		goto res;
+	}
 	wp_log(LOG, "walproposer elected, %s", s.data);
 	collected = true;
 
@@ -1907,6 +1942,12 @@ ParsePageserverFeedbackMessage(WalProposer *wp, StringInfo reply_message, Pagese
 			ps_feedback->corruption_detected = pq_getmsgbyte(reply_message) != 0;
 			psfeedback_log("%s", key, ps_feedback->corruption_detected ? "true" : "false");
 		}
+		else if (strcmp(key, "size_limit_exceeded") == 0)
+		{
+			Assert(value_len == 1);
+			ps_feedback->size_limit_exceeded = pq_getmsgbyte(reply_message) != 0;
+			psfeedback_log("%s", key, ps_feedback->size_limit_exceeded ? "true" : "false");
+		}
 		else
 		{
 			/*
@@ -2035,6 +2076,29 @@ GetAcknowledgedByQuorumWALPosition(WalProposer *wp)
 	return committed;
 }
 
+/*
+ * Is `sk` witness member of wp->mconf? Witnesses don't store WAL.
+ */
+static bool
+SafekeeperIsWitness(WalProposer *wp, Safekeeper *sk)
+{
+	/* node id is not known until greeting is received */
+	if (sk->state < SS_WAIT_VOTING)
+		return false;
+
+	for (uint32 i = 0; i < wp->mconf.members.len; i++)
+	{
+		if (wp->mconf.members.m[i].node_id == sk->greetResponse.nodeId)
+			return wp->mconf.members.m[i].role == MEMBER_ROLE_WITNESS;
+	}
+	for (uint32 i = 0; i < wp->mconf.new_members.len; i++)
+	{
+		if (wp->mconf.new_members.m[i].node_id == sk->greetResponse.nodeId)
+			return wp->mconf.new_members.m[i].role == MEMBER_ROLE_WITNESS;
+	}
+	return false;
+}
+
 /*
  * Return safekeeper with active connection from which WAL can be downloaded, or
  * none if it doesn't exist. donor_lsn is set to end position of the donor to
@@ -2073,7 +2137,8 @@ UpdateDonorShmem(WalProposer *wp)
 	{
 		Safekeeper *sk = &wp->safekeeper[i];
 
-		if (sk->state == SS_ACTIVE && sk->appendResponse.flushLsn > donor_lsn)
+		if (sk->state == SS_ACTIVE && sk->appendResponse.flushLsn > donor_lsn &&
+			!SafekeeperIsWitness(wp, sk))
 		{
 			donor = sk;
 			donor_lsn = sk->appendResponse.flushLsn;
@@ -2175,9 +2240,20 @@ HandleSafekeeperResponse(WalProposer *wp, Safekeeper *fromsk)
 	}
 }
 
+/* Serialize SafekeeperId into buf. */
+static void
+SafekeeperIdSerialize(SafekeeperId *sk_id, StringInfo buf, int proto_version)
+{
+	pq_sendint64(buf, sk_id->node_id);
+	pq_send_ascii_string(buf, sk_id->host);
+	pq_sendint16(buf, sk_id->port);
+	if (proto_version >= 4)
+		pq_sendint8(buf, sk_id->role);
+}
+
 /* Serialize MembershipConfiguration into buf. */
 static void
-MembershipConfigurationSerialize(MembershipConfiguration *mconf, StringInfo buf)
+MembershipConfigurationSerialize(MembershipConfiguration *mconf, StringInfo buf, int proto_version)
 {
 	uint32		i;
 
@@ -2185,11 +2261,7 @@ MembershipConfigurationSerialize(MembershipConfiguration *mconf, StringInfo buf)
 
 	pq_sendint32(buf, mconf->members.len);
 	for (i = 0; i < mconf->members.len; i++)
-	{
-		pq_sendint64(buf, mconf->members.m[i].node_id);
-		pq_send_ascii_string(buf, mconf->members.m[i].host);
-		pq_sendint16(buf, mconf->members.m[i].port);
-	}
+		SafekeeperIdSerialize(&mconf->members.m[i], buf, proto_version);
 
 	/*
 	 * There is no special mark for absent new_members; zero members in
@@ -2197,23 +2269,22 @@ MembershipConfigurationSerialize(MembershipConfiguration *mconf, StringInfo buf)
 	 */
 	pq_sendint32(buf, mconf->new_members.len);
 	for (i = 0; i < mconf->new_members.len; i++)
-	{
-		pq_sendint64(buf, mconf->new_members.m[i].node_id);
-		pq_send_ascii_string(buf, mconf->new_members.m[i].host);
-		pq_sendint16(buf, mconf->new_members.m[i].port);
-	}
+		SafekeeperIdSerialize(&mconf->new_members.m[i], buf, proto_version);
 }
 
 /* Serialize proposer -> acceptor message into buf using specified version */
 static void
 PAMessageSerialize(WalProposer *wp, ProposerAcceptorMessage *msg, StringInfo buf, int proto_version)
 {
-	/* both version are supported currently until we fully migrate to 3 */
-	Assert(proto_version == 3 || proto_version == 2);
+	/*
+	 * both version are supported currently until we fully migrate to 3; 4 is
+	 * 3 with roles of members
+	 */
+	Assert(proto_version >= 2 && proto_version <= 4);
 
 	resetStringInfo(buf);
 
-	if (proto_version == 3)
+	if (proto_version >= 3)
 	{
 		/*
 		 * v2 sends structs for some messages as is, so commonly send tag only
@@ -2229,7 +2300,7 @@ PAMessageSerialize(WalProposer *wp, ProposerAcceptorMessage *msg, StringInfo buf
 
 					pq_send_ascii_string(buf, m->tenant_id);
 					pq_send_ascii_string(buf, m->timeline_id);
-					MembershipConfigurationSerialize(&m->mconf, buf);
+					MembershipConfigurationSerialize(&m->mconf, buf, proto_version);
 					pq_sendint32(buf, m->pg_version);
 					pq_sendint64(buf, m->system_id);
 					pq_sendint32(buf, m->wal_seg_size);
@@ -2412,9 +2483,25 @@ AsyncRead(Safekeeper *sk, char **buf, int *buf_size)
 	return false;
 }
 
+/* Deserialize SafekeeperId from buf to sk_id. */
+static void
+SafekeeperIdDeserialize(SafekeeperId *sk_id, StringInfo buf, int proto_version)
+{
+	const char *buf_host;
+
+	sk_id->node_id = pq_getmsgint64(buf);
+	buf_host = pq_getmsgrawstring(buf);
+	strlcpy(sk_id->host, buf_host, sizeof(sk_id->host));
+	sk_id->port = pq_getmsgint16(buf);
+	/* before 4 roles are not sent and all members are data ones */
+	sk_id->role = MEMBER_ROLE_DATA;
+	if (proto_version >= 4)
+		sk_id->role = pq_getmsgbyte(buf);
+}
+
 /* Deserialize membership configuration from buf to mconf. */
 static void
-MembershipConfigurationDeserialize(MembershipConfiguration *mconf, StringInfo buf)
+MembershipConfigurationDeserialize(MembershipConfiguration *mconf, StringInfo buf, int proto_version)
 {
 	uint32		i;
 
@@ -2422,25 +2509,11 @@ MembershipConfigurationDeserialize(MembershipConfiguration *mconf, StringInfo bu
 	mconf->members.len = pq_getmsgint32(buf);
 	mconf->members.m = palloc0(sizeof(SafekeeperId) * mconf->members.len);
 	for (i = 0; i < mconf->members.len; i++)
-	{
-		const char *buf_host;
-
-		mconf->members.m[i].node_id = pq_getmsgint64(buf);
-		buf_host = pq_getmsgrawstring(buf);
-		strlcpy(mconf->members.m[i].host, buf_host, sizeof(mconf->members.m[i].host));
-		mconf->members.m[i].port = pq_getmsgint16(buf);
-	}
+		SafekeeperIdDeserialize(&mconf->members.m[i], buf, proto_version);
 	mconf->new_members.len = pq_getmsgint32(buf);
 	mconf->new_members.m = palloc0(sizeof(SafekeeperId) * mconf->new_members.len);
 	for (i = 0; i < mconf->new_members.len; i++)
-	{
-		const char *buf_host;
-
-		mconf->new_members.m[i].node_id = pq_getmsgint64(buf);
-		buf_host = pq_getmsgrawstring(buf);
-		strlcpy(mconf->new_members.m[i].host, buf_host, sizeof(mconf->new_members.m[i].host));
-		mconf->new_members.m[i].port = pq_getmsgint16(buf);
-	}
+		SafekeeperIdDeserialize(&mconf->new_members.m[i], buf, proto_version);
 }
 
 /*
@@ -2474,7 +2547,7 @@ AsyncReadMessage(Safekeeper *sk, AcceptorProposerMessage *anymsg)
 	s.maxlen = buf_size;
 	s.cursor = 0;
 
-	if (wp->config->proto_version == 3)
+	if (wp->config->proto_version >= 3)
 	{
 		tag = pq_getmsgbyte(&s);
 		if (tag != anymsg->tag)
@@ -2491,7 +2564,7 @@ AsyncReadMessage(Safekeeper *sk, AcceptorProposerMessage *anymsg)
 					AcceptorGreeting *msg = (AcceptorGreeting *) anymsg;
 
 					msg->nodeId = pq_getmsgint64(&s);
-					MembershipConfigurationDeserialize(&msg->mconf, &s);
+					MembershipConfigurationDeserialize(&msg->mconf, &s, wp->config->proto_version);
 					msg->term = pq_getmsgint64(&s);
 					pq_getmsgend(&s);
 					return true;
@@ -3001,7 +3074,10 @@ MembershipConfigurationToString(MembershipConfiguration *mconf)
 			appendStringInfoString(&s, ", ");
 		appendStringInfo(&s, "{node_id = %lu", mconf->members.m[i].node_id);
 		appendStringInfo(&s, ", host = %s", mconf->members.m[i].host);
-		appendStringInfo(&s, ", port = %u }", mconf->members.m[i].port);
+		appendStringInfo(&s, ", port = %u", mconf->members.m[i].port);
+		if (mconf->members.m[i].role == MEMBER_ROLE_WITNESS)
+			appendStringInfoString(&s, ", role = witness");
+		appendStringInfoString(&s, " }");
 	}
 	appendStringInfo(&s, "], new_members = [");
 	for (i = 0; i < mconf->new_members.len; i++)
@@ -3010,7 +3086,10 @@ MembershipConfigurationToString(MembershipConfiguration *mconf)
 			appendStringInfoString(&s, ", ");
 		appendStringInfo(&s, "{node_id = %lu", mconf->new_members.m[i].node_id);
 		appendStringInfo(&s, ", host = %s", mconf->new_members.m[i].host);
-		appendStringInfo(&s, ", port = %u }", mconf->new_members.m[i].port);
+		appendStringInfo(&s, ", port = %u", mconf->new_members.m[i].port);
+		if (mconf->new_members.m[i].role == MEMBER_ROLE_WITNESS)
+			appendStringInfoString(&s, ", role = witness");
+		appendStringInfoString(&s, " }");
 	}
 	appendStringInfoString(&s, "]}");
 	return s.data;
@@ -147,11 +147,22 @@ typedef uint64 NNodeId;
 typedef uint32 Generation;
 #define INVALID_GENERATION 0
 
+/*
+ * Role of safekeeper in the member set. Witness doesn't store WAL, only its
+ * position, so it can't be a donor. Sent since protocol version 4.
+ */
+typedef enum
+{
+	MEMBER_ROLE_DATA = 0,
+	MEMBER_ROLE_WITNESS = 1,
+} MemberRole;
+
 typedef struct SafekeeperId
 {
 	NNodeId		node_id;
 	char		host[MAXCONNINFO];
 	uint16		port;
+	MemberRole	role;
 } SafekeeperId;
 
 /* Set of safekeepers. */
@@ -376,6 +387,8 @@ typedef struct PageserverFeedback
 	uint32		shard_number;
 	/* true if the pageserver has detected data corruption in the timeline */
 	bool		corruption_detected;
+	/* true if the timeline exceeds a size limit and we should be read-only */
+	bool		size_limit_exceeded;
 } PageserverFeedback;
 
 /* BEGIN_HADRON */
@@ -420,6 +433,8 @@ typedef struct WalproposerShmemState
 	pg_atomic_uint64 mineLastElectedTerm;
 	pg_atomic_uint64 backpressureThrottlingTime;
 	pg_atomic_uint64 currentClusterSize;
+	/* 1 if any shard reports that a size limit is exceeded */
+	pg_atomic_uint32 sizeLimitExceeded;
 
 	/* last feedback from each shard */
 	PageserverFeedback shard_ps_feedback[MAX_SHARDS];
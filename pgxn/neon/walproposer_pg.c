@@ -266,7 +266,7 @@ nwp_register_gucs(void)
 	DefineCustomIntVariable(
 							"neon.safekeeper_proto_version",
 							"Version of compute <-> safekeeper protocol.",
-							"Used while migrating from 2 to 3.",
+							"Used while migrating from 2 to 3. Version 4 also carries roles of safekeepers and is required for timelines with witness safekeepers.",
 							&safekeeper_proto_version,
 							3, 0, INT_MAX,
 							PGC_POSTMASTER,
@@ -594,6 +594,7 @@ WalproposerShmemInit(void)
 		pg_atomic_init_u64(&walprop_shared->mineLastElectedTerm, 0);
 		pg_atomic_init_u64(&walprop_shared->backpressureThrottlingTime, 0);
 		pg_atomic_init_u64(&walprop_shared->currentClusterSize, 0);
+		pg_atomic_init_u32(&walprop_shared->sizeLimitExceeded, 0);
 		/* BEGIN_HADRON */
 		pg_atomic_init_u32(&walprop_shared->wal_rate_limiter.effective_max_wal_bytes_per_second, -1);
 		pg_atomic_init_u32(&walprop_shared->wal_rate_limiter.should_limit, 0);
@@ -756,7 +757,7 @@ record_pageserver_feedback(PageserverFeedback *ps_feedback, shardno_t num_shards
 	/* Update the feedback */
 	memcpy(&walprop_shared->shard_ps_feedback[ps_feedback->shard_number], ps_feedback, sizeof(PageserverFeedback));
 
-	/* Calculate min LSNs */
+	/* Calculate min LSNs, and whether any shard exceeds a size limit */
 	memcpy(&min_feedback, ps_feedback, sizeof(PageserverFeedback));
 	for (int i = 0; i < walprop_shared->num_shards; i++)
 	{
@@ -764,6 +765,9 @@ record_pageserver_feedback(PageserverFeedback *ps_feedback, shardno_t num_shards
 
 		if (feedback->present)
 		{
+			if (feedback->size_limit_exceeded)
+				min_feedback.size_limit_exceeded = true;
+
 			if (min_feedback.last_received_lsn == InvalidXLogRecPtr || feedback->last_received_lsn < min_feedback.last_received_lsn)
 				min_feedback.last_received_lsn = feedback->last_received_lsn;
 
@@ -2158,6 +2162,8 @@ walprop_pg_process_safekeeper_feedback(WalProposer *wp, Safekeeper *sk)
 			if (sk->appendResponse.ps_feedback.currentClusterSize > 0)
 				SetNeonCurrentClusterSize(sk->appendResponse.ps_feedback.currentClusterSize);
 
+			SetNeonSizeLimitExceeded(min_feedback.size_limit_exceeded);
+
 			if (min_feedback.disk_consistent_lsn != standby_apply_lsn)
 			{
 				standby_apply_lsn = min_feedback.disk_consistent_lsn;
@@ -2259,6 +2265,18 @@ GetNeonCurrentClusterSize(void)
 {
 	return pg_atomic_read_u64(&walprop_shared->currentClusterSize);
 }
+
+void
+SetNeonSizeLimitExceeded(bool exceeded)
+{
+	pg_atomic_write_u32(&walprop_shared->sizeLimitExceeded, exceeded ? 1 : 0);
+}
+
+bool
+GetNeonSizeLimitExceeded(void)
+{
+	return pg_atomic_read_u32(&walprop_shared->sizeLimitExceeded) != 0;
+}
 uint64		GetNeonCurrentClusterSize(void);
 
 /* BEGIN_HADRON */
@@ -1,6 +1,7 @@
 //! WAL ingestion benchmarks.
 
 use std::io::Write as _;
+use std::time::Duration;
 
 use bytes::BytesMut;
 use camino_tempfile::tempfile;
@@ -8,6 +9,7 @@
 use itertools::Itertools as _;
 use postgres_ffi::v17::wal_generator::{LogicalMessageGenerator, WalGenerator};
 use pprof::criterion::{Output, PProfProfiler};
+use safekeeper::group_commit::{GROUP_COMMIT, GroupCommitConfig};
 use safekeeper::receive_wal::{self, WalAcceptor};
 use safekeeper::safekeeper::{
     AcceptorProposerMessage, AppendRequest, AppendRequestHeader, ProposerAcceptorMessage,
@@ -37,6 +39,7 @@
     targets = bench_process_msg,
     bench_wal_acceptor,
     bench_wal_acceptor_throughput,
+    bench_wal_acceptor_timelines,
     bench_file_write,
     bench_bytes_reserve,
 );
@@ -149,7 +152,7 @@ fn run_bench(b: &mut Bencher, n: usize, fsync: bool) -> anyhow::Result<()> {
                 .await?
                 .wal_residence_guard()
                 .await?;
-            WalAcceptor::spawn(tli, msg_rx, reply_tx, Some(0));
+            WalAcceptor::spawn(tli, msg_rx, reply_tx, Some(0), None);
             anyhow::Ok(())
         })?;
 
@@ -252,7 +255,7 @@ fn run_bench(
                 .await?
                 .wal_residence_guard()
                 .await?;
-            WalAcceptor::spawn(tli, msg_rx, reply_tx, Some(0));
+            WalAcceptor::spawn(tli, msg_rx, reply_tx, Some(0), None);
             anyhow::Ok(())
         })?;
 
@@ -293,6 +296,87 @@ fn run_bench(
     }
 }
 
+/// Benchmarks flushing WAL of many low traffic timelines at once, with and without group commit.
+/// Each iteration sends a single small record to every timeline's WalAcceptor concurrently and
+/// waits for all of them to be flushed, which is dominated by fsyncs. Group commit metrics
+/// (safekeeper_wal_group_commit_*) show the resulting batch sizes.
+fn bench_wal_acceptor_timelines(c: &mut Criterion) {
+    let mut g = c.benchmark_group("wal_acceptor_timelines");
+    g.sample_size(10);
+
+    for group_commit in ["off", "fdatasync", "syncfs"] {
+        for n in [10, 100, 1000] {
+            g.bench_function(format!("group_commit={group_commit}/n={n}"), |b| {
+                run_bench(b, n, group_commit).unwrap()
+            });
+        }
+    }
+    GROUP_COMMIT.configure(GroupCommitConfig::default());
+
+    /// The actual benchmark. n is the number of timelines.
+    fn run_bench(b: &mut Bencher, n: usize, group_commit: &str) -> anyhow::Result<()> {
+        let runtime = tokio::runtime::Runtime::new()?; // needs multithreaded
+
+        GROUP_COMMIT.configure(GroupCommitConfig {
+            window: (group_commit != "off").then_some(Duration::from_millis(1)),
+            syncfs: group_commit == "syncfs",
+        });
+
+        let env = Env::new(true)?;
+        let mut timelines = Vec::with_capacity(n);
+        for _ in 0..n {
+            let (msg_tx, msg_rx) = tokio::sync::mpsc::channel(receive_wal::MSG_QUEUE_SIZE);
+            let (reply_tx, reply_rx) = tokio::sync::mpsc::channel(receive_wal::REPLY_QUEUE_SIZE);
+            runtime.block_on(async {
+                let tli = env
+                    .make_timeline(NodeId(1), TenantTimelineId::generate(), Lsn(0))
+                    .await?
+                    .wal_residence_guard()
+                    .await?;
+                WalAcceptor::spawn(tli, msg_rx, reply_tx, Some(0), None);
+                anyhow::Ok(())
+            })?;
+            let walgen =
+                WalGenerator::new(LogicalMessageGenerator::new(c"prefix", b"message"), Lsn(0));
+            timelines.push((msg_tx, reply_rx, walgen));
+        }
+
+        b.iter(|| {
+            runtime.block_on(async {
+                let flushes = timelines
+                    .iter_mut()
+                    .map(|(msg_tx, reply_rx, walgen)| async move {
+                        let (lsn, record) = walgen.next().expect("endless WAL");
+                        let end_lsn = lsn + record.len() as u64;
+                        let msg = ProposerAcceptorMessage::AppendRequest(AppendRequest {
+                            h: AppendRequestHeader {
+                                generation: Generation::new(0),
+                                term: 1,
+                                begin_lsn: lsn,
+                                end_lsn,
+                                commit_lsn: Lsn(0),
+                                truncate_lsn: Lsn(0),
+                            },
+                            wal_data: record,
+                        });
+                        msg_tx.send(msg).await.expect("send failed");
+                        // Wait for the message to get flushed.
+                        while let Some(reply) = reply_rx.recv().await {
+                            if let AcceptorProposerMessage::AppendResponse(resp) = reply {
+                                if resp.flush_lsn >= end_lsn {
+                                    return;
+                                }
+                            }
+                        }
+                        panic!("disconnected")
+                    });
+                futures::future::join_all(flushes).await;
+            })
+        });
+        Ok(())
+    }
+}
+
 /// Benchmarks OS write throughput by appending blocks of a given size to a file. This is intended
 /// to compare Tokio and stdlib writes, and give a baseline for optimal WAL throughput.
 fn bench_file_write(c: &mut Criterion) {
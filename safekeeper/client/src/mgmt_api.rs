@@ -13,6 +13,7 @@
 };
 use utils::id::{NodeId, TenantId, TimelineId};
 use utils::logging::SecretString;
+use utils::lsn::Lsn;
 
 #[derive(Debug, Clone)]
 pub struct Client {
@@ -157,6 +158,20 @@ pub async fn bump_timeline_term(
         resp.json().await.map_err(Error::ReceiveBody)
     }
 
+    pub async fn reset_timeline_wal(
+        &self,
+        tenant_id: TenantId,
+        timeline_id: TimelineId,
+        req: &models::TimelineResetWalRequest,
+    ) -> Result<models::TimelineResetWalResponse> {
+        let uri = format!(
+            "{}/v1/tenant/{}/timeline/{}/reset_wal",
+            self.mgmt_api_endpoint, tenant_id, timeline_id
+        );
+        let resp = self.post(&uri, req).await?;
+        resp.json().await.map_err(Error::ReceiveBody)
+    }
+
     pub async fn timeline_status(
         &self,
         tenant_id: TenantId,
@@ -182,6 +197,37 @@ pub async fn snapshot(
         self.get(&uri).await
     }
 
+    pub async fn wal_summary(
+        &self,
+        tenant_id: TenantId,
+        timeline_id: TimelineId,
+        source: models::WalSummarySource,
+        from_lsn: Lsn,
+        until_lsn: Lsn,
+        chunk_size: u64,
+    ) -> Result<models::WalSummary> {
+        let uri = format!(
+            "{}/v1/tenant/{}/timeline/{}/wal_summary?source={}&from_lsn={}&until_lsn={}&chunk_size={}",
+            self.mgmt_api_endpoint, tenant_id, timeline_id, source, from_lsn, until_lsn, chunk_size
+        );
+        let resp = self.get(&uri).await?;
+        resp.json().await.map_err(Error::ReceiveBody)
+    }
+
+    pub async fn wal_verify(
+        &self,
+        tenant_id: TenantId,
+        timeline_id: TimelineId,
+        req: &models::WalVerifyRequest,
+    ) -> Result<models::WalVerifyResponse> {
+        let uri = format!(
+            "{}/v1/tenant/{}/timeline/{}/wal_verify",
+            self.mgmt_api_endpoint, tenant_id, timeline_id
+        );
+        let resp = self.post(&uri, req).await?;
+        resp.json().await.map_err(Error::ReceiveBody)
+    }
+
     pub async fn status(&self) -> Result<SafekeeperStatus> {
         let uri = format!("{}/v1/status", self.mgmt_api_endpoint);
         let resp = self.get(&uri).await?;
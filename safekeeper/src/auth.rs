@@ -22,7 +22,8 @@ pub fn check_permission(claims: &Claims, tenant_id: Option<TenantId>) -> Result<
             | Scope::Infra
             | Scope::Scrubber
             | Scope::ControllerPeer
-            | Scope::TenantEndpoint,
+            | Scope::TenantEndpoint
+            | Scope::TenantReadOnly,
             _,
         ) => Err(AuthError(
             format!(
@@ -34,3 +35,20 @@ pub fn check_permission(claims: &Claims, tenant_id: Option<TenantId>) -> Result<
         (Scope::SafekeeperData, _) => Ok(()),
     }
 }
+
+/// Like [`check_permission`], but for operations which only read WAL of the
+/// tenant: these are additionally allowed with TenantReadOnly scope.
+pub fn check_read_permission(
+    claims: &Claims,
+    tenant_id: Option<TenantId>,
+) -> Result<(), AuthError> {
+    match (&claims.scope, tenant_id) {
+        (Scope::TenantReadOnly, Some(tenant_id)) => {
+            if claims.tenant_id != Some(tenant_id) {
+                return Err(AuthError("Tenant id mismatch. Permission denied".into()));
+            }
+            Ok(())
+        }
+        _ => check_permission(claims, tenant_id),
+    }
+}
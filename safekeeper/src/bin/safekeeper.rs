@@ -17,19 +17,21 @@
 use metrics::set_build_info_metric;
 use remote_storage::RemoteStorageConfig;
 use safekeeper::defaults::{
-    DEFAULT_CONTROL_FILE_SAVE_INTERVAL, DEFAULT_EVICTION_MIN_RESIDENT,
-    DEFAULT_GLOBAL_DISK_CHECK_INTERVAL, DEFAULT_HEARTBEAT_TIMEOUT, DEFAULT_HTTP_LISTEN_ADDR,
-    DEFAULT_MAX_GLOBAL_DISK_USAGE_RATIO, DEFAULT_MAX_OFFLOADER_LAG_BYTES,
+    DEFAULT_CONTROL_FILE_SAVE_INTERVAL, DEFAULT_DISK_PRESSURE_EVICTION_RATIO,
+    DEFAULT_EVICTION_MIN_RESIDENT, DEFAULT_GLOBAL_DISK_CHECK_INTERVAL, DEFAULT_HEARTBEAT_TIMEOUT,
+    DEFAULT_HTTP_LISTEN_ADDR, DEFAULT_MAX_GLOBAL_DISK_USAGE_RATIO, DEFAULT_MAX_OFFLOADER_LAG_BYTES,
     DEFAULT_MAX_REELECT_OFFLOADER_LAG_BYTES, DEFAULT_MAX_TIMELINE_DISK_USAGE_BYTES,
     DEFAULT_PARTIAL_BACKUP_CONCURRENCY, DEFAULT_PARTIAL_BACKUP_TIMEOUT, DEFAULT_PG_LISTEN_ADDR,
     DEFAULT_SSL_CERT_FILE, DEFAULT_SSL_CERT_RELOAD_PERIOD, DEFAULT_SSL_KEY_FILE,
 };
+use safekeeper::group_commit::{GROUP_COMMIT, GroupCommitConfig};
 use safekeeper::hadron;
 use safekeeper::wal_backup::WalBackup;
 use safekeeper::{
     BACKGROUND_RUNTIME, BROKER_RUNTIME, GlobalTimelines, HTTP_RUNTIME, SafeKeeperConf,
-    WAL_SERVICE_RUNTIME, broker, control_file, http, wal_service,
+    WAL_SERVICE_RUNTIME, broker, control_file, http, timeline_eviction, wal_service,
 };
+use safekeeper_api::models::WalIngestLimits;
 use sd_notify::NotifyState;
 use storage_broker::{DEFAULT_ENDPOINT, Uri};
 use tokio::runtime::Handle;
@@ -164,6 +166,25 @@ struct Args {
     /// WAL backup horizon.
     #[arg(long)]
     disable_wal_backup: bool,
+    /// If given, the offloading safekeeper also mirrors completed WAL segments
+    /// to a Postgres compatible archive in remote storage:
+    ///   <wal_archive_prefix>/<tenant_id>/<timeline_id>/<segment_file>[.zst]
+    /// which can be restored with the wal_restore binary as restore_command.
+    /// The archive is not cleaned up on timeline deletion, its retention is
+    /// left to the bucket lifecycle policy.
+    #[arg(long, verbatim_doc_comment)]
+    wal_archive_prefix: Option<Utf8PathBuf>,
+    /// Compress archived segments with zstd, storing them as <segment_file>.zst.
+    /// A stock restore_command copying segments can't read those, use the
+    /// wal_restore binary, which decompresses them, as restore_command instead.
+    #[arg(long, verbatim_doc_comment)]
+    wal_archive_compress: bool,
+    /// Compress completed WAL segments with zstd, both on local disk (once
+    /// committed) and when offloading them to remote storage as
+    /// <segment_file>.zst. Reading handles compressed and raw segments
+    /// regardless of this setting, so it can be toggled at any time.
+    #[arg(long, verbatim_doc_comment)]
+    wal_compression: bool,
     /// If given, enables auth on incoming connections to WAL service endpoint
     /// (--listen-pg). Value specifies path to a .pem public key used for
     /// validations of JWT tokens. Empty string is allowed and means disabling
@@ -196,6 +217,12 @@ struct Args {
     /// Controls how long backup will wait until uploading the partial segment.
     #[arg(long, value_parser = humantime::parse_duration, default_value = DEFAULT_PARTIAL_BACKUP_TIMEOUT, verbatim_doc_comment)]
     partial_backup_timeout: Duration,
+    /// Upload a snapshot of each resident timeline (control file and WAL not
+    /// yet in WAL backup) to remote storage at most this often, so that
+    /// pull_timeline can bootstrap a new member from it when no peer can
+    /// serve one. Disabled if not set.
+    #[arg(long, value_parser = humantime::parse_duration, verbatim_doc_comment)]
+    remote_snapshot_interval: Option<Duration>,
     /// Disable task to push messages to broker every second. Supposed to
     /// be used in tests.
     #[arg(long)]
@@ -212,6 +239,28 @@ struct Args {
     /// Number of allowed concurrent uploads of partial segments to remote storage.
     #[arg(long, default_value = DEFAULT_PARTIAL_BACKUP_CONCURRENCY)]
     partial_backup_concurrency: usize,
+    /// Initial bandwidth limits for WAL received from computes, as JSON, e.g.
+    /// '{"timeline_bytes_per_second": 33554432, "tenant_bytes_per_second": 67108864}'.
+    /// Can be changed at runtime with PUT /v1/wal_ingest_limits.
+    #[arg(long, value_parser = parse_wal_ingest_limits, default_value = "{}", verbatim_doc_comment)]
+    wal_ingest_limits: WalIngestLimits,
+    /// Maximum number of WAL flushes running concurrently across all timelines.
+    /// Flushes beyond it are queued and served round-robin across tenants, so a
+    /// few busy tenants can't starve others of fsyncs. Unlimited if not set.
+    /// With --wal-group-commit-window, a flush keeps its slot while waiting for
+    /// its batch, so this also caps the number of fsyncs per batch.
+    #[arg(long, verbatim_doc_comment)]
+    wal_fsync_concurrency: Option<usize>,
+    /// Batch WAL fsyncs of all timelines requested within this window of each
+    /// other, trading up to the window of commit latency for fdatasyncs issued
+    /// concurrently, or a single syncfs with --wal-group-commit-syncfs, with
+    /// many low traffic timelines. Disabled if not set.
+    #[arg(long, value_parser = humantime::parse_duration, verbatim_doc_comment)]
+    wal_group_commit_window: Option<Duration>,
+    /// Make a group commit batch durable with a single syncfs(2) of the data
+    /// filesystem instead of fdatasync per file. Linux only.
+    #[arg(long, verbatim_doc_comment)]
+    wal_group_commit_syncfs: bool,
     /// How long a timeline must be resident before it is eligible for eviction.
     /// Usually, timeline eviction has to wait for `partial_backup_timeout` before being eligible for eviction,
     /// but if a timeline is un-evicted and then _not_ written to, it would immediately flap to evicting again,
@@ -272,6 +321,13 @@ struct Args {
     /// Set to 0 to disable the global disk usage limit.
     #[arg(long, default_value_t = DEFAULT_MAX_GLOBAL_DISK_USAGE_RATIO)]
     max_global_disk_usage_ratio: f64,
+    /// The portion of the filesystem capacity above which timelines ready for
+    /// eviction are evicted to remote storage, largest partial segment first,
+    /// to get usage back under it. Should be below
+    /// --max-global-disk-usage-ratio to evict before WAL writes are rejected.
+    /// Requires --enable-offload. Set to 0 to disable.
+    #[arg(long, default_value_t = DEFAULT_DISK_PRESSURE_EVICTION_RATIO)]
+    disk_pressure_eviction_ratio: f64,
     /* END_HADRON */
 }
 
@@ -434,6 +490,9 @@ async fn main() -> anyhow::Result<()> {
         /* END_HADRON */
         wal_backup_enabled: !args.disable_wal_backup,
         backup_parallel_jobs: args.wal_backup_parallel_jobs,
+        wal_archive_prefix: args.wal_archive_prefix,
+        wal_archive_compress: args.wal_archive_compress,
+        wal_compression: args.wal_compression,
         pg_auth,
         pg_tenant_only_auth,
         http_auth,
@@ -441,11 +500,16 @@ async fn main() -> anyhow::Result<()> {
         current_thread_runtime: args.current_thread_runtime,
         walsenders_keep_horizon: args.walsenders_keep_horizon,
         partial_backup_timeout: args.partial_backup_timeout,
+        remote_snapshot_interval: args.remote_snapshot_interval,
         disable_periodic_broker_push: args.disable_periodic_broker_push,
         enable_offload: args.enable_offload,
         delete_offloaded_wal: args.delete_offloaded_wal,
         control_file_save_interval: args.control_file_save_interval,
         partial_backup_concurrency: args.partial_backup_concurrency,
+        wal_ingest_limits: args.wal_ingest_limits,
+        wal_fsync_concurrency: args.wal_fsync_concurrency,
+        wal_group_commit_window: args.wal_group_commit_window,
+        wal_group_commit_syncfs: args.wal_group_commit_syncfs,
         eviction_min_resident: args.eviction_min_resident,
         wal_reader_fanout: args.wal_reader_fanout,
         max_delta_for_fanout: args.max_delta_for_fanout,
@@ -462,6 +526,7 @@ async fn main() -> anyhow::Result<()> {
         hcc_base_url: None,
         global_disk_check_interval: args.global_disk_check_interval,
         max_global_disk_usage_ratio: args.max_global_disk_usage_ratio,
+        disk_pressure_eviction_ratio: args.disk_pressure_eviction_ratio,
         /* END_HADRON */
     });
 
@@ -490,6 +555,11 @@ async fn start_safekeeper(conf: Arc<SafeKeeperConf>) -> Result<()> {
         );
     }
 
+    GROUP_COMMIT.configure(GroupCommitConfig {
+        window: conf.wal_group_commit_window,
+        syncfs: conf.wal_group_commit_syncfs,
+    });
+
     info!("starting safekeeper WAL service on {}", conf.listen_pg_addr);
     let pg_listener = tcp_listener::bind(conf.listen_pg_addr.clone()).map_err(|e| {
         error!("failed to bind to address {}: {}", conf.listen_pg_addr, e);
@@ -637,7 +707,8 @@ async fn start_safekeeper(conf: Arc<SafeKeeperConf>) -> Result<()> {
     tasks_handles.push(Box::pin(timeline_housekeeping_handle));
 
     /* BEGIN_HADRON */
-    // Spawn global disk usage watcher task, if a global disk usage limit is specified.
+    // Spawn global disk usage watcher task, if a global disk usage limit or disk pressure
+    // eviction is specified.
     let interval = conf.global_disk_check_interval;
     let data_dir = conf.workdir.clone();
     // Use the safekeeper data directory to compute filesystem capacity. This only runs once on startup, so
@@ -645,13 +716,23 @@ async fn start_safekeeper(conf: Arc<SafeKeeperConf>) -> Result<()> {
     let fs_capacity_bytes = get_filesystem_capacity(data_dir.as_std_path())
         .expect("Failed to get filesystem capacity for data directory");
     let limit: u64 = (conf.max_global_disk_usage_ratio * fs_capacity_bytes as f64) as u64;
-    if limit > 0 {
+    // Evicting timelines needs remote storage to offload them to.
+    let eviction_threshold: u64 = if conf.enable_offload && wal_backup.get_storage().is_some() {
+        (conf.disk_pressure_eviction_ratio * fs_capacity_bytes as f64) as u64
+    } else {
+        0
+    };
+    if limit > 0 || eviction_threshold > 0 {
+        let global_timelines_ = global_timelines.clone();
         let disk_usage_watch_handle = BACKGROUND_RUNTIME
             .handle()
             .spawn(async move {
                 // Use Tokio interval to preserve fixed cadence between filesystem utilization checks
                 let mut ticker = tokio::time::interval(interval);
                 ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+                // Eviction pass runs in the background to keep checking the
+                // usage limit meanwhile; at most one pass runs at a time.
+                let mut eviction_pass: Option<tokio::task::JoinHandle<u64>> = None;
 
                 loop {
                     ticker.tick().await;
@@ -666,13 +747,36 @@ async fn start_safekeeper(conf: Arc<SafeKeeperConf>) -> Result<()> {
 
                     let elapsed = check_start.elapsed().as_secs_f64();
                     GLOBAL_DISK_UTIL_CHECK_SECONDS.observe(elapsed);
-                    if usage > limit {
-                        warn!(
-                            "Global disk usage exceeded limit. Usage: {} bytes, limit: {} bytes",
-                            usage, limit
-                        );
+                    if limit > 0 {
+                        if usage > limit {
+                            warn!(
+                                "Global disk usage exceeded limit. Usage: {} bytes, limit: {} bytes",
+                                usage, limit
+                            );
+                        }
+                        GLOBAL_DISK_LIMIT_EXCEEDED.store(usage > limit, Ordering::Relaxed);
+                    }
+                    if eviction_threshold > 0 && usage > eviction_threshold {
+                        if eviction_pass.as_ref().is_some_and(|h| !h.is_finished()) {
+                            info!(
+                                "Disk usage exceeded eviction threshold, previous eviction pass is still running. Usage: {} bytes, threshold: {} bytes",
+                                usage, eviction_threshold
+                            );
+                        } else {
+                            info!(
+                                "Disk usage exceeded eviction threshold. Usage: {} bytes, threshold: {} bytes",
+                                usage, eviction_threshold
+                            );
+                            let global_timelines = global_timelines_.clone();
+                            eviction_pass = Some(tokio::spawn(async move {
+                                timeline_eviction::evict_under_disk_pressure(
+                                    &global_timelines,
+                                    usage - eviction_threshold,
+                                )
+                                .await
+                            }));
+                        }
                     }
-                    GLOBAL_DISK_LIMIT_EXCEEDED.store(usage > limit, Ordering::Relaxed);
                 }
             })
             .map(|res| ("Global disk usage watcher".to_string(), res));
@@ -832,6 +936,10 @@ fn parse_remote_storage(storage_conf: &str) -> anyhow::Result<RemoteStorageConfi
     RemoteStorageConfig::from_toml(&storage_conf.parse()?)
 }
 
+fn parse_wal_ingest_limits(limits: &str) -> anyhow::Result<WalIngestLimits> {
+    serde_json::from_str(limits).context("parsing --wal-ingest-limits")
+}
+
 #[test]
 fn verify_cli() {
     use clap::CommandFactory;
@@ -0,0 +1,79 @@
+//! `restore_command` helper fetching WAL from the archive written by safekeepers
+//! started with `--wal-archive-prefix`, e.g.
+//!
+//!   restore_command = 'wal_restore --remote-storage "{local_path = \'/archive\'}" \
+//!       --wal-archive-prefix wal --tenant-id <tenant_id> --timeline-id <timeline_id> %f %p'
+//!
+//! Exits with 1 if the requested file is not in the archive, which Postgres
+//! treats as end of archived WAL. Any other failure exits with a code above
+//! 125, which makes Postgres abort recovery instead of silently ending it early.
+use std::process::ExitCode;
+
+use anyhow::Context;
+use camino::Utf8PathBuf;
+use clap::Parser;
+use remote_storage::{GenericRemoteStorage, RemoteStorageConfig};
+use safekeeper::wal_archive;
+use tokio_util::sync::CancellationToken;
+use utils::id::{TenantId, TenantTimelineId, TimelineId};
+
+const EXIT_NOT_FOUND: u8 = 1;
+const EXIT_FATAL: u8 = 255;
+
+#[derive(Parser)]
+#[command(about = "Fetch WAL from the safekeeper WAL archive, for use as restore_command")]
+struct Args {
+    /// Remote storage configuration as TOML inline table, same as the
+    /// safekeeper --remote-storage argument.
+    #[arg(long, value_parser = parse_remote_storage)]
+    remote_storage: RemoteStorageConfig,
+    /// Same as the safekeeper --wal-archive-prefix argument.
+    #[arg(long)]
+    wal_archive_prefix: Utf8PathBuf,
+    #[arg(long)]
+    tenant_id: TenantId,
+    #[arg(long)]
+    timeline_id: TimelineId,
+    /// Name of the requested file (%f).
+    file_name: String,
+    /// Where to put it (%p).
+    target_path: Utf8PathBuf,
+}
+
+fn parse_remote_storage(storage_conf: &str) -> anyhow::Result<RemoteStorageConfig> {
+    RemoteStorageConfig::from_toml(&storage_conf.parse()?)
+}
+
+async fn restore(args: Args) -> anyhow::Result<bool> {
+    let storage = GenericRemoteStorage::from_config(&args.remote_storage).await?;
+    let ttid = TenantTimelineId::new(args.tenant_id, args.timeline_id);
+    let timeline_path = wal_archive::archive_timeline_path(&args.wal_archive_prefix, &ttid)?;
+    wal_archive::restore_file(
+        &storage,
+        &timeline_path,
+        &args.file_name,
+        &args.target_path,
+        &CancellationToken::new(),
+    )
+    .await
+    .with_context(|| format!("restoring {}", args.file_name))
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> ExitCode {
+    let args = Args::parse();
+    match restore(args).await {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::from(EXIT_NOT_FOUND),
+        Err(e) => {
+            eprintln!("wal_restore: {e:#}");
+            ExitCode::from(EXIT_FATAL)
+        }
+    }
+}
+
+#[test]
+fn verify_cli() {
+    use clap::CommandFactory;
+    Args::command().debug_assert()
+}
@@ -15,13 +15,15 @@
 use utils::bin_ser::LeSer;
 use utils::crashsafe::durable_rename;
 
-use crate::control_file_upgrade::{downgrade_v10_to_v9, upgrade_control_file};
+use crate::control_file_upgrade::{
+    downgrade_v10_to_v9, downgrade_v11_to_v10, upgrade_control_file,
+};
 use crate::metrics::PERSIST_CONTROL_FILE_SECONDS;
 use crate::metrics::WAL_DISK_IO_ERRORS;
 use crate::state::{EvictionState, TimelinePersistentState};
 
 pub const SK_MAGIC: u32 = 0xcafeceefu32;
-pub const SK_FORMAT_VERSION: u32 = 10;
+pub const SK_FORMAT_VERSION: u32 = 11;
 
 // contains persistent metadata for safekeeper
 pub const CONTROL_FILE_NAME: &str = "safekeeper.control";
@@ -177,6 +179,12 @@ pub(crate) fn write_to_buf(&self) -> Result<Vec<u8>> {
             let prev = downgrade_v10_to_v9(self);
             WriteBytesExt::write_u32::<LittleEndian>(&mut buf, PREV_FORMAT_VERSION)?;
             prev.ser_into(&mut buf)?;
+        } else if !self.mconf.has_witnesses() {
+            // Similarly, member roles are needed only with witnesses.
+            const PREV_FORMAT_VERSION: u32 = 10;
+            let prev = downgrade_v11_to_v10(self);
+            WriteBytesExt::write_u32::<LittleEndian>(&mut buf, PREV_FORMAT_VERSION)?;
+            prev.ser_into(&mut buf)?;
         } else {
             // otherwise, we write the current format version
             WriteBytesExt::write_u32::<LittleEndian>(&mut buf, SK_FORMAT_VERSION)?;
@@ -241,8 +249,11 @@ fn last_persist_at(&self) -> Instant {
 
 #[cfg(test)]
 mod test {
-    use safekeeper_api::membership::{Configuration, MemberSet, SafekeeperGeneration};
+    use safekeeper_api::membership::{
+        Configuration, MemberRole, MemberSet, SafekeeperGeneration, SafekeeperId,
+    };
     use tokio::fs;
+    use utils::id::NodeId;
     use utils::lsn::Lsn;
 
     use super::*;
@@ -270,6 +281,49 @@ async fn test_read_write_safekeeper_state() -> anyhow::Result<()> {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_read_write_witness_members() -> anyhow::Result<()> {
+        let tempdir = camino_tempfile::tempdir()?;
+        let sk = |id, role| SafekeeperId {
+            id: NodeId(id),
+            host: String::from("localhost"),
+            pg_port: 5432,
+            role,
+        };
+        let mut state = TimelinePersistentState::empty();
+        state.mconf = Configuration::new(MemberSet::new(vec![
+            sk(1, MemberRole::Data),
+            sk(2, MemberRole::Data),
+        ])?);
+        // without witnesses file is written in v10 format
+        let mut storage = FileStorage::create_new(tempdir.path(), state.clone(), NO_SYNC).await?;
+        assert_eq!(&state.write_to_buf()?[4..8], &10u32.to_le_bytes());
+        assert_eq!(
+            FileStorage::load_control_file_from_dir(tempdir.path())?,
+            state
+        );
+
+        state.mconf = Configuration {
+            generation: state.mconf.generation.next(),
+            members: MemberSet::new(vec![
+                sk(1, MemberRole::Data),
+                sk(2, MemberRole::Data),
+                sk(3, MemberRole::Witness),
+            ])?,
+            new_members: None,
+        };
+        storage.persist(&state).await?;
+        assert_eq!(
+            &state.write_to_buf()?[4..8],
+            &SK_FORMAT_VERSION.to_le_bytes()
+        );
+        assert_eq!(
+            FileStorage::load_control_file_from_dir(tempdir.path())?,
+            state
+        );
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_safekeeper_state_checksum_mismatch() -> anyhow::Result<()> {
         let tempdir = camino_tempfile::tempdir()?;
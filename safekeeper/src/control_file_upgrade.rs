@@ -4,7 +4,9 @@
 use anyhow::{Result, bail};
 use postgres_versioninfo::PgVersionId;
 use pq_proto::SystemId;
-use safekeeper_api::membership::{Configuration, INVALID_GENERATION};
+use safekeeper_api::membership::{
+    Configuration, INVALID_GENERATION, MemberRole, MemberSet, SafekeeperGeneration, SafekeeperId,
+};
 use safekeeper_api::{ServerInfo, Term};
 use serde::{Deserialize, Serialize};
 use tracing::*;
@@ -13,6 +15,8 @@
 use utils::lsn::Lsn;
 
 use crate::safekeeper::{AcceptorState, PgUuid, TermHistory, TermLsn};
+use std::time::SystemTime;
+
 use crate::state::{EvictionState, TimelinePersistentState};
 use crate::wal_backup_partial;
 
@@ -318,6 +322,80 @@ pub struct TimelinePersistentStateV9 {
     pub eviction_state: EvictionState,
 }
 
+/// Safekeeper in membership configuration before member roles were added.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SafekeeperIdV10 {
+    pub id: NodeId,
+    pub host: String,
+    pub pg_port: u16,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MemberSetV10 {
+    pub m: Vec<SafekeeperIdV10>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ConfigurationV10 {
+    pub generation: SafekeeperGeneration,
+    pub members: MemberSetV10,
+    pub new_members: Option<MemberSetV10>,
+}
+
+impl MemberSetV10 {
+    fn upgrade(self) -> MemberSet {
+        MemberSet {
+            m: self
+                .m
+                .into_iter()
+                .map(|sk| SafekeeperId {
+                    id: sk.id,
+                    host: sk.host,
+                    pg_port: sk.pg_port,
+                    role: MemberRole::Data,
+                })
+                .collect(),
+        }
+    }
+
+    fn downgrade(set: &MemberSet) -> Self {
+        MemberSetV10 {
+            m: set
+                .m
+                .iter()
+                .map(|sk| SafekeeperIdV10 {
+                    id: sk.id,
+                    host: sk.host.clone(),
+                    pg_port: sk.pg_port,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Same as TimelinePersistentState, but without member roles in mconf.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TimelinePersistentStateV10 {
+    #[serde(with = "hex")]
+    pub tenant_id: TenantId,
+    #[serde(with = "hex")]
+    pub timeline_id: TimelineId,
+    pub mconf: ConfigurationV10,
+    pub acceptor_state: AcceptorState,
+    pub server: ServerInfo,
+    #[serde(with = "hex")]
+    pub proposer_uuid: PgUuid,
+    pub timeline_start_lsn: Lsn,
+    pub local_start_lsn: Lsn,
+    pub commit_lsn: Lsn,
+    pub backup_lsn: Lsn,
+    pub peer_horizon_lsn: Lsn,
+    pub remote_consistent_lsn: Lsn,
+    pub partial_backup: wal_backup_partial::State,
+    pub eviction_state: EvictionState,
+    pub creation_ts: SystemTime,
+}
+
 pub fn upgrade_control_file(buf: &[u8], version: u32) -> Result<TimelinePersistentState> {
     // migrate to storing full term history
     if version == 1 {
@@ -514,6 +592,30 @@ pub fn upgrade_control_file(buf: &[u8], version: u32) -> Result<TimelinePersiste
             eviction_state: oldstate.eviction_state,
             creation_ts: std::time::SystemTime::UNIX_EPOCH,
         });
+    } else if version == 10 {
+        // add member roles, all old members store WAL
+        let oldstate = TimelinePersistentStateV10::des(&buf[..buf.len()])?;
+        return Ok(TimelinePersistentState {
+            tenant_id: oldstate.tenant_id,
+            timeline_id: oldstate.timeline_id,
+            mconf: Configuration {
+                generation: oldstate.mconf.generation,
+                members: oldstate.mconf.members.upgrade(),
+                new_members: oldstate.mconf.new_members.map(MemberSetV10::upgrade),
+            },
+            acceptor_state: oldstate.acceptor_state,
+            server: oldstate.server,
+            proposer_uuid: oldstate.proposer_uuid,
+            timeline_start_lsn: oldstate.timeline_start_lsn,
+            local_start_lsn: oldstate.local_start_lsn,
+            commit_lsn: oldstate.commit_lsn,
+            backup_lsn: oldstate.backup_lsn,
+            peer_horizon_lsn: oldstate.peer_horizon_lsn,
+            remote_consistent_lsn: oldstate.remote_consistent_lsn,
+            partial_backup: oldstate.partial_backup,
+            eviction_state: oldstate.eviction_state,
+            creation_ts: oldstate.creation_ts,
+        });
     }
 
     // TODO: persist the file back to the disk after upgrade
@@ -544,6 +646,34 @@ pub fn downgrade_v10_to_v9(state: &TimelinePersistentState) -> TimelinePersisten
     }
 }
 
+/// Like [`downgrade_v10_to_v9`], keeps files readable by older versions as
+/// long as there are no witness members, so roles can be dropped.
+pub fn downgrade_v11_to_v10(state: &TimelinePersistentState) -> TimelinePersistentStateV10 {
+    let mconf = &state.mconf;
+    assert!(!mconf.has_witnesses());
+    TimelinePersistentStateV10 {
+        tenant_id: state.tenant_id,
+        timeline_id: state.timeline_id,
+        mconf: ConfigurationV10 {
+            generation: mconf.generation,
+            members: MemberSetV10::downgrade(&mconf.members),
+            new_members: mconf.new_members.as_ref().map(MemberSetV10::downgrade),
+        },
+        acceptor_state: state.acceptor_state.clone(),
+        server: state.server.clone(),
+        proposer_uuid: state.proposer_uuid,
+        timeline_start_lsn: state.timeline_start_lsn,
+        local_start_lsn: state.local_start_lsn,
+        commit_lsn: state.commit_lsn,
+        backup_lsn: state.backup_lsn,
+        peer_horizon_lsn: state.peer_horizon_lsn,
+        remote_consistent_lsn: state.remote_consistent_lsn,
+        partial_backup: state.partial_backup.clone(),
+        eviction_state: state.eviction_state,
+        creation_ts: state.creation_ts,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::str::FromStr;
@@ -5,7 +5,7 @@
 use postgres_ffi::{MAX_SEND_SIZE, WAL_SEGMENT_SIZE};
 use remote_storage::GenericRemoteStorage;
 use safekeeper_api::membership::Configuration;
-use tokio::fs::OpenOptions;
+use tokio::fs::{File, OpenOptions};
 use tokio::io::{AsyncSeekExt, AsyncWriteExt};
 use tracing::{info, warn};
 use utils::id::TenantTimelineId;
@@ -17,7 +17,7 @@
 use crate::timeline::{TimelineError, WalResidentTimeline};
 use crate::timelines_global_map::{create_temp_timeline_dir, validate_temp_timeline};
 use crate::wal_backup::copy_s3_segments;
-use crate::wal_storage::{WalReader, wal_file_paths};
+use crate::wal_storage::{WalReader, compressed_wal_file_path, wal_file_paths};
 
 // we don't want to have more than 10 segments on disk after copy, because they take space
 const MAX_BACKUP_LAG: u64 = 10 * WAL_SEGMENT_SIZE as u64;
@@ -176,7 +176,9 @@ async fn copy_disk_segments(
     end_lsn: Lsn,
     tli_dir_path: &Utf8PathBuf,
 ) -> Result<()> {
-    let mut wal_reader = tli.get_walreader(start_lsn).await?;
+    let src_dir_path = tli.get_timeline_dir();
+    // Created lazily, and recreated after segments copied as files.
+    let mut wal_reader: Option<WalReader> = None;
 
     let mut buf = vec![0u8; MAX_SEND_SIZE];
 
@@ -193,6 +195,27 @@ async fn copy_disk_segments(
         let copy_start = copy_start - segment_start;
         let copy_end = copy_end - segment_start;
 
+        // Full segments compressed on the source are copied as is, without
+        // decompressing and compressing them again.
+        if segment != last_segment && copy_start == 0 {
+            let src = compressed_wal_file_path(&src_dir_path, segment, wal_seg_size);
+            let dst = compressed_wal_file_path(tli_dir_path, segment, wal_seg_size);
+            match tokio::fs::copy(&src, &dst).await {
+                Ok(_) => {
+                    File::open(&dst).await?.sync_all().await?;
+                    wal_reader = None;
+                    continue;
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        let mut reader = match wal_reader.take() {
+            Some(reader) => reader,
+            None => tli.get_walreader(Lsn(segment_start + copy_start)).await?,
+        };
+
         let wal_file_path = {
             let (normal, partial) = wal_file_paths(tli_dir_path, segment, wal_seg_size);
 
@@ -209,9 +232,10 @@ async fn copy_disk_segments(
             wal_seg_size as u64,
             copy_start,
             copy_end,
-            &mut wal_reader,
+            &mut reader,
         )
         .await?;
+        wal_reader = Some(reader);
     }
 
     Ok(())
@@ -9,7 +9,6 @@
 use anyhow::{Result, bail};
 use camino::{Utf8Path, Utf8PathBuf};
 use chrono::{DateTime, Utc};
-use postgres_ffi::v14::xlog_utils::{IsPartialXLogFileName, IsXLogFileName};
 use postgres_ffi::{MAX_SEND_SIZE, XLogSegNo};
 use safekeeper_api::models::WalSenderState;
 use serde::{Deserialize, Serialize};
@@ -17,9 +16,11 @@
 use utils::id::{NodeId, TenantId, TenantTimelineId, TimelineId};
 use utils::lsn::Lsn;
 
+use crate::rate_limit::WalIngestThrottleState;
 use crate::safekeeper::TermHistory;
 use crate::state::{TimelineMemState, TimelinePersistentState};
 use crate::timeline::{WalResidentTimeline, get_timeline_dir};
+use crate::wal_storage::is_segment_file_name;
 use crate::{GlobalTimelines, SafeKeeperConf, timeline_manager};
 
 /// Various filters that influence the resulting JSON output.
@@ -170,6 +171,7 @@ pub struct Memory {
     pub epoch_start_lsn: Lsn,
     pub mem_state: TimelineMemState,
     pub mgr_status: timeline_manager::Status,
+    pub wal_ingest_throttle: WalIngestThrottleState,
 
     // PhysicalStorage state.
     pub write_lsn: Lsn,
@@ -319,7 +321,7 @@ fn get_wal_last_modified(path: &Utf8Path) -> Result<Option<DateTime<Utc>>> {
         let entry = entry?;
         /* Ignore files that are not XLOG segments */
         let fname = entry.file_name();
-        if !IsXLogFileName(&fname) && !IsPartialXLogFileName(&fname) {
+        if !is_segment_file_name(&fname) {
             continue;
         }
 
@@ -0,0 +1,189 @@
+//! Node-wide batching of WAL fsyncs ("group commit").
+//!
+//! Each timeline flushes its WAL independently, so with thousands of low
+//! traffic timelines on one disk every `flush_wal` costs a separate
+//! fdatasync. When group commit is enabled, syncs requested within
+//! `--wal-group-commit-window` of each other are collected into one batch and
+//! performed together: either as concurrent fdatasyncs, one blocking task per
+//! file so that the device can process them in parallel, or, with
+//! `--wal-group-commit-syncfs`, as one syncfs(2) per filesystem the files live
+//! on. Every requester is released once its batch has been made durable.
+//!
+//! A flush holds its `--wal-fsync-concurrency` permit while waiting for its
+//! batch, so a batch never has more requesters than there are permits.
+
+use std::collections::HashMap;
+use std::io;
+use std::os::unix::fs::MetadataExt;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use once_cell::sync::Lazy;
+use tokio::fs::File;
+use tokio::sync::oneshot;
+use tokio::task::JoinError;
+
+use crate::metrics::{WAL_GROUP_COMMIT_BATCH_SIZE, WAL_GROUP_COMMIT_SECONDS};
+
+/// Group commit instance shared by all timelines of the process.
+pub static GROUP_COMMIT: Lazy<Arc<GroupCommit>> = Lazy::new(Default::default);
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct GroupCommitConfig {
+    /// How long the first sync of a batch waits for others to join it. None
+    /// disables batching: every sync is an immediate fdatasync.
+    pub window: Option<Duration>,
+    /// Make a batch durable with syncfs(2) instead of fdatasync per file.
+    /// Only supported on Linux, elsewhere fdatasync is used regardless.
+    pub syncfs: bool,
+}
+
+#[derive(Default)]
+pub struct GroupCommit {
+    config: Mutex<GroupCommitConfig>,
+    /// Batch collecting syncs, its timer is already running.
+    pending: Mutex<Option<Batch>>,
+}
+
+#[derive(Default)]
+struct Batch {
+    requests: Vec<(std::fs::File, oneshot::Sender<io::Result<()>>)>,
+}
+
+impl GroupCommit {
+    pub fn configure(&self, config: GroupCommitConfig) {
+        *self.config.lock().unwrap() = config;
+    }
+
+    pub fn config(&self) -> GroupCommitConfig {
+        *self.config.lock().unwrap()
+    }
+
+    /// Make written data of `file` durable, like [`File::sync_data`], possibly
+    /// batched with syncs of other files.
+    pub async fn sync_data(self: &Arc<Self>, file: &File) -> io::Result<()> {
+        let config = self.config();
+        let Some(window) = config.window else {
+            return file.sync_data().await;
+        };
+
+        // try_clone waits for in-flight writes, so the clone sees all of them.
+        let std_file = file.try_clone().await?.into_std().await;
+        let (tx, rx) = oneshot::channel();
+        let first = {
+            let mut pending = self.pending.lock().unwrap();
+            let batch = pending.get_or_insert_with(Batch::default);
+            batch.requests.push((std_file, tx));
+            batch.requests.len() == 1
+        };
+        if first {
+            tokio::spawn(self.clone().commit_batch(window, config.syncfs));
+        }
+        rx.await
+            .unwrap_or_else(|_| Err(io::Error::other("group commit batch was dropped")))
+    }
+
+    async fn commit_batch(self: Arc<Self>, window: Duration, syncfs: bool) {
+        tokio::time::sleep(window).await;
+        let Some(batch) = self.pending.lock().unwrap().take() else {
+            return;
+        };
+        WAL_GROUP_COMMIT_BATCH_SIZE.observe(batch.requests.len() as f64);
+        let _timer = WAL_GROUP_COMMIT_SECONDS.start_timer();
+
+        let (files, waiters): (Vec<_>, Vec<_>) = batch.requests.into_iter().unzip();
+        let results = if syncfs && cfg!(target_os = "linux") {
+            let count = files.len();
+            match tokio::task::spawn_blocking(move || syncfs_files(&files)).await {
+                Ok(results) => results,
+                Err(e) => (0..count).map(|_| Err(sync_task_failed(&e))).collect(),
+            }
+        } else {
+            let syncs = files
+                .into_iter()
+                .map(|file| tokio::task::spawn_blocking(move || file.sync_data()));
+            futures::future::join_all(syncs)
+                .await
+                .into_iter()
+                .map(|res| res.unwrap_or_else(|e| Err(sync_task_failed(&e))))
+                .collect()
+        };
+        for (waiter, res) in waiters.into_iter().zip(results) {
+            // The requester might have gone away, nothing to do then.
+            let _ = waiter.send(res);
+        }
+    }
+}
+
+/// Error reported to the requesters of a sync whose blocking task panicked.
+fn sync_task_failed(e: &JoinError) -> io::Error {
+    io::Error::other(anyhow::anyhow!("group commit sync task failed: {e}"))
+}
+
+/// Sync all files with syncfs, returning a result per file. Every filesystem
+/// holding one of the files is synced once. Since Linux 5.8 syncfs reports
+/// writeback errors of the filesystem, which are then reported to all files on
+/// it.
+fn syncfs_files(files: &[std::fs::File]) -> Vec<io::Result<()>> {
+    let mut synced = HashMap::new();
+    files
+        .iter()
+        .map(|file| {
+            let dev = file.metadata()?.dev();
+            synced
+                .entry(dev)
+                .or_insert_with(|| syncfs_file(file).map_err(|e| (e.kind(), e.to_string())))
+                .clone()
+                .map_err(|(kind, msg)| io::Error::new(kind, msg))
+        })
+        .collect()
+}
+
+#[cfg(target_os = "linux")]
+fn syncfs_file(file: &std::fs::File) -> io::Result<()> {
+    nix::unistd::syncfs(file).map_err(io::Error::from)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn syncfs_file(_file: &std::fs::File) -> io::Result<()> {
+    unreachable!("syncfs is only used on linux")
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::AsyncWriteExt;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn batches_concurrent_syncs() {
+        for syncfs in [false, true] {
+            let group_commit = Arc::new(GroupCommit::default());
+            group_commit.configure(GroupCommitConfig {
+                window: Some(Duration::from_millis(100)),
+                syncfs,
+            });
+
+            let tempdir = camino_tempfile::tempdir().unwrap();
+            let mut files = Vec::new();
+            for i in 0..10 {
+                let mut file = File::create(tempdir.path().join(format!("f{i}")))
+                    .await
+                    .unwrap();
+                file.write_all(b"data").await.unwrap();
+                files.push(file);
+            }
+
+            let before = WAL_GROUP_COMMIT_BATCH_SIZE.get_sample_count();
+            let syncs = files.iter().map(|file| group_commit.sync_data(file));
+            for res in futures::future::join_all(syncs).await {
+                res.unwrap();
+            }
+            assert!(group_commit.pending.lock().unwrap().is_none());
+            // All syncs were started well within the window, so normally they
+            // form a single batch; allow for a slow test machine.
+            let batches = WAL_GROUP_COMMIT_BATCH_SIZE.get_sample_count() - before;
+            assert!((1..10).contains(&batches), "{batches} batches");
+        }
+    }
+}
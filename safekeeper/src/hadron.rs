@@ -194,6 +194,7 @@ pub async fn hcc_pull_timeline(
         timeline_id: timeline.timeline_id,
         http_hosts: Vec::new(),
         mconf: None,
+        allow_remote_snapshot: false,
     };
     for host in timeline.peers {
         if host.0 == conf.my_id.0 {
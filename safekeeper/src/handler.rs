@@ -11,6 +11,7 @@
 use pageserver_api::shard::{ShardIdentity, ShardStripeSize};
 use postgres_backend::{PostgresBackend, QueryError};
 use postgres_ffi::PG_TLI;
+use postgres_versioninfo::PgVersionId;
 use pq_proto::{BeMessage, FeStartupPacket, INT4_OID, RowDescriptor, TEXT_OID};
 use regex::Regex;
 use safekeeper_api::Term;
@@ -23,9 +24,10 @@
 use utils::postgres_client::PostgresClientProtocol;
 use utils::shard::{ShardCount, ShardNumber};
 
-use crate::auth::check_permission;
+use crate::auth::{check_permission, check_read_permission};
 use crate::metrics::{PG_QUERIES_GAUGE, TrafficMetrics};
 use crate::timeline::TimelineError;
+use crate::wal_archive::history_file_name;
 use crate::{GlobalTimelines, SafeKeeperConf};
 
 /// Safekeeper handler of postgres commands
@@ -59,11 +61,32 @@ enum SafekeeperPostgresCommand {
     StartReplication {
         start_lsn: Lsn,
         term: Option<Term>,
+        /// Timeline requested by a postgres standby, always PG_TLI for
+        /// safekeepers.
+        timeline: Option<u32>,
     },
     IdentifySystem,
+    TimelineHistory {
+        timeline: u32,
+    },
     TimelineStatus,
 }
 
+impl SafekeeperPostgresCommand {
+    /// Whether the command only reads committed WAL and thus is allowed with
+    /// a read-only token. START_REPLICATION with term is issued by
+    /// walproposer/peer recovery and streams uncommitted WAL.
+    fn is_read_only(&self) -> bool {
+        match self {
+            SafekeeperPostgresCommand::StartWalPush { .. } => false,
+            SafekeeperPostgresCommand::StartReplication { term, .. } => term.is_none(),
+            SafekeeperPostgresCommand::IdentifySystem
+            | SafekeeperPostgresCommand::TimelineHistory { .. }
+            | SafekeeperPostgresCommand::TimelineStatus => true,
+        }
+    }
+}
+
 fn parse_cmd(cmd: &str) -> anyhow::Result<SafekeeperPostgresCommand> {
     if cmd.starts_with("START_WAL_PUSH") {
         // Allow additional options in postgres START_REPLICATION style like
@@ -109,7 +132,7 @@ fn parse_cmd(cmd: &str) -> anyhow::Result<SafekeeperPostgresCommand> {
     } else if cmd.starts_with("START_REPLICATION") {
         let re = Regex::new(
             // We follow postgres START_REPLICATION LOGICAL options to pass term.
-            r"START_REPLICATION(?: SLOT [^ ]+)?(?: PHYSICAL)? ([[:xdigit:]]+/[[:xdigit:]]+)(?: \(term='(\d+)'\))?",
+            r"START_REPLICATION(?: SLOT [^ ]+)?(?: PHYSICAL)? ([[:xdigit:]]+/[[:xdigit:]]+)(?: TIMELINE (\d+))?(?: \(term='(\d+)'\))?",
         )
         .unwrap();
         let caps = re
@@ -117,14 +140,30 @@ fn parse_cmd(cmd: &str) -> anyhow::Result<SafekeeperPostgresCommand> {
             .context(format!("failed to parse START_REPLICATION command {cmd}"))?;
         let start_lsn =
             Lsn::from_str(&caps[1]).context("parse start LSN from START_REPLICATION command")?;
-        let term = if let Some(m) = caps.get(2) {
+        let timeline = if let Some(m) = caps.get(2) {
+            Some(m.as_str().parse::<u32>().context("invalid timeline")?)
+        } else {
+            None
+        };
+        let term = if let Some(m) = caps.get(3) {
             Some(m.as_str().parse::<u64>().context("invalid term")?)
         } else {
             None
         };
-        Ok(SafekeeperPostgresCommand::StartReplication { start_lsn, term })
+        Ok(SafekeeperPostgresCommand::StartReplication {
+            start_lsn,
+            term,
+            timeline,
+        })
     } else if cmd.starts_with("IDENTIFY_SYSTEM") {
         Ok(SafekeeperPostgresCommand::IdentifySystem)
+    } else if cmd.starts_with("TIMELINE_HISTORY") {
+        let re = Regex::new(r"^TIMELINE_HISTORY (\d+)").unwrap();
+        let caps = re
+            .captures(cmd)
+            .context(format!("failed to parse TIMELINE_HISTORY command {cmd}"))?;
+        let timeline = caps[1].parse::<u32>().context("invalid timeline")?;
+        Ok(SafekeeperPostgresCommand::TimelineHistory { timeline })
     } else if cmd.starts_with("TIMELINE_STATUS") {
         Ok(SafekeeperPostgresCommand::TimelineStatus)
     } else {
@@ -138,6 +177,7 @@ fn cmd_to_string(cmd: &SafekeeperPostgresCommand) -> &str {
         SafekeeperPostgresCommand::StartReplication { .. } => "START_REPLICATION",
         SafekeeperPostgresCommand::TimelineStatus => "TIMELINE_STATUS",
         SafekeeperPostgresCommand::IdentifySystem => "IDENTIFY_SYSTEM",
+        SafekeeperPostgresCommand::TimelineHistory { .. } => "TIMELINE_HISTORY",
     }
 }
 
@@ -281,16 +321,22 @@ fn check_auth_jwt(
 
         // The handler might be configured to allow only tenant scope tokens.
         if matches!(allowed_auth_scope, Scope::Tenant)
-            && !matches!(data.claims.scope, Scope::Tenant)
+            && !matches!(data.claims.scope, Scope::Tenant | Scope::TenantReadOnly)
         {
             return Err(QueryError::Unauthorized(
                 "passed JWT token is for full access, but only tenant scope is allowed".into(),
             ));
         }
 
-        if matches!(data.claims.scope, Scope::Tenant) && data.claims.tenant_id.is_none() {
+        if matches!(data.claims.scope, Scope::Tenant | Scope::TenantReadOnly)
+            && data.claims.tenant_id.is_none()
+        {
             return Err(QueryError::Unauthorized(
-                "jwt token scope is Tenant, but tenant id is missing".into(),
+                format!(
+                    "jwt token scope is {:?}, but tenant id is missing",
+                    data.claims.scope
+                )
+                .into(),
             ));
         }
 
@@ -303,6 +349,19 @@ fn check_auth_jwt(
         Ok(())
     }
 
+    async fn server_version(&self) -> Option<String> {
+        let ttid = TenantTimelineId::new(self.tenant_id?, self.timeline_id?);
+        let tli = self.global_timelines.get(ttid).ok()?;
+        let pg_version = tli.get_state().await.1.server.pg_version;
+        (pg_version != PgVersionId::UNKNOWN).then(|| pg_version.server_version())
+    }
+
+    fn report_server_params_after_jwt(&self) -> bool {
+        // Read-only tokens are for physical standbys outside Neon, whose
+        // walreceiver checks the server version.
+        self.has_read_only_token()
+    }
+
     fn process_query(
         &mut self,
         pgb: &mut PostgresBackend<IO>,
@@ -327,7 +386,11 @@ fn process_query(
 
             let tenant_id = self.tenant_id.context("tenantid is required")?;
             let timeline_id = self.timeline_id.context("timelineid is required")?;
-            self.check_permission(Some(tenant_id))?;
+            if cmd.is_read_only() {
+                self.check_read_permission(Some(tenant_id))?;
+            } else {
+                self.check_permission(Some(tenant_id))?;
+            }
             self.ttid = TenantTimelineId::new(tenant_id, timeline_id);
 
             match cmd {
@@ -339,12 +402,22 @@ fn process_query(
                         .instrument(info_span!("WAL receiver"))
                         .await
                 }
-                SafekeeperPostgresCommand::StartReplication { start_lsn, term } => {
+                SafekeeperPostgresCommand::StartReplication {
+                    start_lsn,
+                    term,
+                    timeline,
+                } => {
+                    if let Some(timeline) = timeline {
+                        check_pg_timeline(timeline)?;
+                    }
                     self.handle_start_replication(pgb, start_lsn, term)
                         .instrument(info_span!("WAL sender"))
                         .await
                 }
                 SafekeeperPostgresCommand::IdentifySystem => self.handle_identify_system(pgb).await,
+                SafekeeperPostgresCommand::TimelineHistory { timeline } => {
+                    self.handle_timeline_history(pgb, timeline).await
+                }
                 SafekeeperPostgresCommand::TimelineStatus => self.handle_timeline_status(pgb).await,
             }
         })
@@ -396,6 +469,27 @@ fn check_permission(&self, tenant_id: Option<TenantId>) -> Result<(), QueryError
         check_permission(claims, tenant_id).map_err(|e| QueryError::Unauthorized(e.0))
     }
 
+    /// Like check_permission, but for commands which only read committed WAL.
+    fn check_read_permission(&self, tenant_id: Option<TenantId>) -> Result<(), QueryError> {
+        if self.auth.is_none() {
+            return Ok(());
+        }
+        let claims = self
+            .claims
+            .as_ref()
+            .expect("claims presence already checked");
+        check_read_permission(claims, tenant_id).map_err(|e| QueryError::Unauthorized(e.0))
+    }
+
+    /// True if the connection is authenticated with a token allowing only to
+    /// read WAL.
+    fn has_read_only_token(&self) -> bool {
+        matches!(
+            self.claims.as_ref().map(|c| c.scope),
+            Some(Scope::TenantReadOnly)
+        )
+    }
+
     async fn handle_timeline_status<IO: AsyncRead + AsyncWrite + Unpin>(
         &mut self,
         pgb: &mut PostgresBackend<IO>,
@@ -491,10 +585,42 @@ async fn handle_identify_system<IO: AsyncRead + AsyncWrite + Unpin>(
         Ok(())
     }
 
+    ///
+    /// Handle TIMELINE_HISTORY replication command
+    ///
+    /// Safekeepers keep WAL of a single postgres timeline, which has no
+    /// parents, so its history file has no entries. A standby doesn't request
+    /// it for the first timeline, but other clients may.
+    async fn handle_timeline_history<IO: AsyncRead + AsyncWrite + Unpin>(
+        &mut self,
+        pgb: &mut PostgresBackend<IO>,
+        timeline: u32,
+    ) -> Result<(), QueryError> {
+        check_pg_timeline(timeline)?;
+        // Make sure the timeline exists, like other commands do.
+        self.global_timelines
+            .get(self.ttid)
+            .map_err(|e| QueryError::Other(e.into()))?;
+
+        let filename = history_file_name();
+        pgb.write_message_noflush(&BeMessage::RowDescription(&[
+            RowDescriptor::text_col(b"filename"),
+            RowDescriptor::text_col(b"content"),
+        ]))?
+        .write_message_noflush(&BeMessage::DataRow(&[Some(filename.as_bytes()), Some(b"")]))?
+        .write_message_noflush(&BeMessage::CommandComplete(b"TIMELINE_HISTORY"))?;
+        Ok(())
+    }
+
     /// Returns true if current connection is a replication connection, originating
     /// from a walproposer recovery function. This connection gets a special handling:
     /// safekeeper must stream all local WAL till the flush_lsn, whether committed or not.
     pub fn is_walproposer_recovery(&self) -> bool {
+        // Application name is set by the client, so read-only clients must
+        // not be able to get uncommitted WAL with it.
+        if self.has_read_only_token() {
+            return false;
+        }
         match &self.appname {
             None => false,
             Some(appname) => {
@@ -506,6 +632,17 @@ pub fn is_walproposer_recovery(&self) -> bool {
     }
 }
 
+/// Postgres timeline of WAL on safekeepers is always PG_TLI, error out like
+/// postgres does if a client asks for another one.
+fn check_pg_timeline(timeline: u32) -> Result<(), QueryError> {
+    if timeline != PG_TLI {
+        return Err(QueryError::Other(anyhow::anyhow!(
+            "requested timeline {timeline} is not in this server's history"
+        )));
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::SafekeeperPostgresCommand;
@@ -540,4 +677,61 @@ fn test_start_wal_push_parse() {
             _ => panic!("unexpected command"),
         }
     }
+
+    /// Test parsing of START_REPLICATION command
+    #[test]
+    fn test_start_replication_parse() {
+        let cmd = "START_REPLICATION SLOT \"slot\" PHYSICAL 0/16B9188 (term='5')";
+        let parsed = super::parse_cmd(cmd).expect("failed to parse");
+        match parsed {
+            SafekeeperPostgresCommand::StartReplication {
+                start_lsn,
+                term,
+                timeline,
+            } => {
+                assert_eq!(start_lsn, utils::lsn::Lsn(0x16B9188));
+                assert_eq!(term, Some(5));
+                assert_eq!(timeline, None);
+            }
+            _ => panic!("unexpected command"),
+        }
+
+        // As sent by walreceiver of a postgres standby.
+        let cmd = "START_REPLICATION 0/1000000 TIMELINE 1";
+        let parsed = super::parse_cmd(cmd).expect("failed to parse");
+        match parsed {
+            SafekeeperPostgresCommand::StartReplication {
+                start_lsn,
+                term,
+                timeline,
+            } => {
+                assert_eq!(start_lsn, utils::lsn::Lsn(0x1000000));
+                assert_eq!(term, None);
+                assert_eq!(timeline, Some(1));
+            }
+            _ => panic!("unexpected command"),
+        }
+        assert!(parsed_is_read_only(cmd));
+        assert!(!parsed_is_read_only(
+            "START_REPLICATION PHYSICAL 0/1000000 (term='5')"
+        ));
+    }
+
+    /// Test parsing of TIMELINE_HISTORY command
+    #[test]
+    fn test_timeline_history_parse() {
+        let parsed = super::parse_cmd("TIMELINE_HISTORY 2").expect("failed to parse");
+        match parsed {
+            SafekeeperPostgresCommand::TimelineHistory { timeline } => assert_eq!(timeline, 2),
+            _ => panic!("unexpected command"),
+        }
+        assert!(parsed_is_read_only("TIMELINE_HISTORY 1"));
+        assert!(super::parse_cmd("TIMELINE_HISTORY").is_err());
+    }
+
+    fn parsed_is_read_only(cmd: &str) -> bool {
+        super::parse_cmd(cmd)
+            .expect("failed to parse")
+            .is_read_only()
+    }
 }
@@ -19,7 +19,7 @@
 use safekeeper_api::models::{
     AcceptorStateStatus, PullTimelineRequest, SafekeeperStatus, SkTimelineInfo, TenantDeleteResult,
     TermSwitchApiEntry, TimelineCopyRequest, TimelineCreateRequest, TimelineDeleteResult,
-    TimelineStatus, TimelineTermBumpRequest,
+    TimelineResetWalRequest, TimelineStatus, TimelineTermBumpRequest,
 };
 use safekeeper_api::{ServerInfo, membership, models};
 use storage_broker::proto::{SafekeeperTimelineInfo, TenantTimelineId as ProtoTenantTimelineId};
@@ -35,9 +35,11 @@
 use crate::debug_dump::TimelineDigestRequest;
 use crate::hadron::{get_filesystem_capacity, get_filesystem_usage};
 use crate::safekeeper::TermLsn;
+use crate::timeline_eviction::DISK_PRESSURE_EVICTION_STATS;
 use crate::timelines_global_map::DeleteOrExclude;
 use crate::{
     GlobalTimelines, SafeKeeperConf, copy_timeline, debug_dump, patch_control_file, pull_timeline,
+    wal_verify,
 };
 use serde_json::json;
 
@@ -129,17 +131,46 @@ async fn utilization_handler(request: Request<Body>) -> Result<Response<Body>, A
     json_response(StatusCode::OK, utilization)
 }
 
-/// Returns filesystem capacity and current utilization for the safekeeper data directory.
+/// Returns WAL ingest bandwidth limits currently in effect.
+async fn wal_ingest_limits_handler(request: Request<Body>) -> Result<Response<Body>, ApiError> {
+    check_permission(&request, None)?;
+    let global_timelines = get_global_timelines(&request);
+    let limits = global_timelines
+        .get_global_rate_limiter()
+        .wal_ingest_limits();
+    json_response(StatusCode::OK, limits)
+}
+
+/// Replaces WAL ingest bandwidth limits. Not persisted: after restart the
+/// limits from --wal-ingest-limits apply again.
+async fn wal_ingest_limits_update_handler(
+    mut request: Request<Body>,
+) -> Result<Response<Body>, ApiError> {
+    check_permission(&request, None)?;
+    let limits: models::WalIngestLimits = json_request(&mut request).await?;
+    let global_timelines = get_global_timelines(&request);
+    global_timelines
+        .get_global_rate_limiter()
+        .set_wal_ingest_limits(limits.clone());
+    json_response(StatusCode::OK, limits)
+}
+
+/// Returns filesystem capacity and current utilization for the safekeeper data directory,
+/// along with the space reclaimed by disk pressure eviction.
 async fn filesystem_usage_handler(request: Request<Body>) -> Result<Response<Body>, ApiError> {
     check_permission(&request, None)?;
     let conf = get_conf(&request);
     let path = conf.workdir.as_std_path();
     let capacity = get_filesystem_capacity(path).map_err(ApiError::InternalServerError)?;
     let usage = get_filesystem_usage(path);
+    let eviction_stats = DISK_PRESSURE_EVICTION_STATS.lock().unwrap().clone();
     let resp = json!({
         "data_dir": path,
         "capacity_bytes": capacity,
         "usage_bytes": usage,
+        "disk_pressure_eviction_threshold_bytes":
+            (conf.disk_pressure_eviction_ratio * capacity as f64) as u64,
+        "disk_pressure_eviction": eviction_stats,
     });
     json_response(StatusCode::OK, resp)
 }
@@ -197,11 +228,13 @@ async fn timeline_status_handler(request: Request<Body>) -> Result<Response<Body
     };
 
     let conf = get_conf(&request);
+    let witness = state.mconf.is_witness(conf.my_id);
     // Note: we report in memory values which can be lost.
     let status = TimelineStatus {
         tenant_id: ttid.tenant_id,
         timeline_id: ttid.timeline_id,
         mconf: state.mconf,
+        witness,
         acceptor_state: acc_state,
         pg_info: state.server,
         flush_lsn,
@@ -507,6 +540,87 @@ async fn timeline_digest_handler(request: Request<Body>) -> Result<Response<Body
     json_response(StatusCode::OK, response)
 }
 
+/// Summarize WAL of the timeline on one source, see [`wal_verify`].
+async fn timeline_wal_summary_handler(request: Request<Body>) -> Result<Response<Body>, ApiError> {
+    let ttid = TenantTimelineId::new(
+        parse_request_param(&request, "tenant_id")?,
+        parse_request_param(&request, "timeline_id")?,
+    );
+    check_permission(&request, Some(ttid.tenant_id))?;
+
+    let conf = get_conf(&request);
+    let global_timelines = get_global_timelines(&request);
+    let source: Option<models::WalSummarySource> = parse_query_param(&request, "source")?;
+    let from_lsn: Option<Lsn> = parse_query_param(&request, "from_lsn")?;
+    let until_lsn: Option<Lsn> = parse_query_param(&request, "until_lsn")?;
+    let chunk_size: Option<u64> = parse_query_param(&request, "chunk_size")?;
+    let from_lsn = from_lsn.ok_or(ApiError::BadRequest(anyhow::anyhow!(
+        "from_lsn is required"
+    )))?;
+    let until_lsn = until_lsn.ok_or(ApiError::BadRequest(anyhow::anyhow!(
+        "until_lsn is required"
+    )))?;
+
+    let tli = global_timelines.get(ttid).map_err(ApiError::from)?;
+    let tli = tli
+        .wal_residence_guard()
+        .await
+        .map_err(ApiError::InternalServerError)?;
+    let chunk_size = wal_verify::check_request(
+        from_lsn,
+        until_lsn,
+        chunk_size,
+        tli.get_wal_seg_size().await,
+    )
+    .map_err(ApiError::BadRequest)?;
+
+    let response = wal_verify::summarize(
+        &tli,
+        conf,
+        source.unwrap_or(models::WalSummarySource::Local),
+        from_lsn,
+        until_lsn,
+        chunk_size,
+    )
+    .await
+    .map_err(ApiError::InternalServerError)?;
+    json_response(StatusCode::OK, response)
+}
+
+/// Compare WAL of the timeline on the local disk, in remote storage and on the
+/// other safekeepers of the configuration.
+async fn timeline_wal_verify_handler(
+    mut request: Request<Body>,
+) -> Result<Response<Body>, ApiError> {
+    let ttid = TenantTimelineId::new(
+        parse_request_param(&request, "tenant_id")?,
+        parse_request_param(&request, "timeline_id")?,
+    );
+    check_permission(&request, Some(ttid.tenant_id))?;
+
+    let data: models::WalVerifyRequest = json_request(&mut request).await?;
+    let conf = get_conf(&request);
+    let global_timelines = get_global_timelines(&request);
+
+    let tli = global_timelines.get(ttid).map_err(ApiError::from)?;
+    let tli = tli
+        .wal_residence_guard()
+        .await
+        .map_err(ApiError::InternalServerError)?;
+    let chunk_size = wal_verify::check_request(
+        data.from_lsn,
+        data.until_lsn,
+        data.chunk_size,
+        tli.get_wal_seg_size().await,
+    )
+    .map_err(ApiError::BadRequest)?;
+
+    let response = wal_verify::verify(&tli, conf, data, chunk_size)
+        .await
+        .map_err(ApiError::InternalServerError)?;
+    json_response(StatusCode::OK, response)
+}
+
 /// Unevict timeline and remove uploaded partial segment(s) from the remote storage.
 /// Successfull response returns list of segments existed before the deletion.
 /// Aimed for one-off usage not normally needed.
@@ -550,6 +664,29 @@ async fn timeline_term_bump_handler(
     json_response(StatusCode::OK, response)
 }
 
+/// Discard WAL after the LSN in request and continue the timeline from it in the given term. Used
+/// by the storage controller to reset a timeline to an earlier LSN.
+async fn timeline_reset_wal_handler(
+    mut request: Request<Body>,
+) -> Result<Response<Body>, ApiError> {
+    let ttid = TenantTimelineId::new(
+        parse_request_param(&request, "tenant_id")?,
+        parse_request_param(&request, "timeline_id")?,
+    );
+    check_permission(&request, Some(ttid.tenant_id))?;
+
+    let request_data: TimelineResetWalRequest = json_request(&mut request).await?;
+
+    let global_timelines = get_global_timelines(&request);
+    let tli = global_timelines.get(ttid).map_err(ApiError::from)?;
+    let response = tli
+        .reset_wal(request_data.lsn, request_data.term)
+        .await
+        .map_err(ApiError::InternalServerError)?;
+
+    json_response(StatusCode::OK, response)
+}
+
 /// Used only in tests to hand craft required data.
 async fn record_safekeeper_info(mut request: Request<Body>) -> Result<Response<Body>, ApiError> {
     let ttid = TenantTimelineId::new(
@@ -747,6 +884,12 @@ pub fn make_router(
             })
         })
         .get("/v1/utilization", |r| request_span(r, utilization_handler))
+        .get("/v1/wal_ingest_limits", |r| {
+            request_span(r, wal_ingest_limits_handler)
+        })
+        .put("/v1/wal_ingest_limits", |r| {
+            request_span(r, wal_ingest_limits_update_handler)
+        })
         /* BEGIN_HADRON */
         .get("/v1/debug/filesystem_usage", |r| {
             request_span(r, filesystem_usage_handler)
@@ -797,6 +940,14 @@ pub fn make_router(
         .get("/v1/tenant/:tenant_id/timeline/:timeline_id/digest", |r| {
             request_span(r, timeline_digest_handler)
         })
+        .get(
+            "/v1/tenant/:tenant_id/timeline/:timeline_id/wal_summary",
+            |r| request_span(r, timeline_wal_summary_handler),
+        )
+        .post(
+            "/v1/tenant/:tenant_id/timeline/:timeline_id/wal_verify",
+            |r| request_span(r, timeline_wal_verify_handler),
+        )
         .post(
             "/v1/tenant/:tenant_id/timeline/:timeline_id/backup_partial_reset",
             |r| request_span(r, timeline_backup_partial_reset),
@@ -805,6 +956,10 @@ pub fn make_router(
             "/v1/tenant/:tenant_id/timeline/:timeline_id/term_bump",
             |r| request_span(r, timeline_term_bump_handler),
         )
+        .post(
+            "/v1/tenant/:tenant_id/timeline/:timeline_id/reset_wal",
+            |r| request_span(r, timeline_reset_wal_handler),
+        )
         .post("/v1/record_safekeeper_info/:tenant_id/:timeline_id", |r| {
             request_span(r, record_safekeeper_info)
         })
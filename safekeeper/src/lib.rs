@@ -8,6 +8,7 @@
 use once_cell::sync::Lazy;
 use pem::Pem;
 use remote_storage::RemoteStorageConfig;
+use safekeeper_api::models::WalIngestLimits;
 use storage_broker::Uri;
 use tokio::runtime::Runtime;
 use url::Url;
@@ -21,6 +22,7 @@
 pub mod control_file_upgrade;
 pub mod copy_timeline;
 pub mod debug_dump;
+pub mod group_commit;
 pub mod hadron;
 pub mod handler;
 pub mod http;
@@ -30,6 +32,7 @@
 pub mod rate_limit;
 pub mod receive_wal;
 pub mod recovery;
+pub mod remote_snapshot;
 pub mod remove_wal;
 pub mod safekeeper;
 pub mod send_interpreted_wal;
@@ -40,11 +43,13 @@
 pub mod timeline_guard;
 pub mod timeline_manager;
 pub mod timelines_set;
+pub mod wal_archive;
 pub mod wal_backup;
 pub mod wal_backup_partial;
 pub mod wal_reader_stream;
 pub mod wal_service;
 pub mod wal_storage;
+pub mod wal_verify;
 
 #[cfg(any(test, feature = "benchmarking"))]
 pub mod test_utils;
@@ -88,6 +93,7 @@ pub mod defaults {
     // Global disk watcher defaults
     pub const DEFAULT_GLOBAL_DISK_CHECK_INTERVAL: &str = "60s";
     pub const DEFAULT_MAX_GLOBAL_DISK_USAGE_RATIO: f64 = 0.0;
+    pub const DEFAULT_DISK_PRESSURE_EVICTION_RATIO: f64 = 0.0;
 }
 
 #[derive(Debug, Clone)]
@@ -125,9 +131,18 @@ pub struct SafeKeeperConf {
     pub global_disk_check_interval: Duration,
     /// The portion of the filesystem capacity that can be used by all timelines.
     pub max_global_disk_usage_ratio: f64,
+    /// The portion of the filesystem capacity above which inactive timelines
+    /// are evicted, see [`timeline_eviction::evict_under_disk_pressure`].
+    pub disk_pressure_eviction_ratio: f64,
     /* END_HADRON */
     pub backup_parallel_jobs: usize,
     pub wal_backup_enabled: bool,
+    /// If set, completed segments are also mirrored to a Postgres compatible
+    /// archive under this prefix of remote storage, see [`wal_archive`].
+    pub wal_archive_prefix: Option<Utf8PathBuf>,
+    pub wal_archive_compress: bool,
+    /// Compress completed WAL segments with zstd on disk and in WAL backup.
+    pub wal_compression: bool,
     pub pg_auth: Option<Arc<JwtAuth>>,
     pub pg_tenant_only_auth: Option<Arc<JwtAuth>>,
     pub http_auth: Option<Arc<SwappableJwtAuth>>,
@@ -136,11 +151,18 @@ pub struct SafeKeeperConf {
     pub current_thread_runtime: bool,
     pub walsenders_keep_horizon: bool,
     pub partial_backup_timeout: Duration,
+    /// If set, snapshots of resident timelines are uploaded to remote storage
+    /// at most this often, see [`remote_snapshot`].
+    pub remote_snapshot_interval: Option<Duration>,
     pub disable_periodic_broker_push: bool,
     pub enable_offload: bool,
     pub delete_offloaded_wal: bool,
     pub control_file_save_interval: Duration,
     pub partial_backup_concurrency: usize,
+    pub wal_ingest_limits: WalIngestLimits,
+    pub wal_fsync_concurrency: Option<usize>,
+    pub wal_group_commit_window: Option<Duration>,
+    pub wal_group_commit_syncfs: bool,
     pub eviction_min_resident: Duration,
     pub wal_reader_fanout: bool,
     pub max_delta_for_fanout: Option<u64>,
@@ -173,6 +195,9 @@ pub fn dummy() -> Self {
             peer_recovery_enabled: true,
             wal_backup_enabled: true,
             backup_parallel_jobs: 1,
+            wal_archive_prefix: None,
+            wal_archive_compress: false,
+            wal_compression: false,
             pg_auth: None,
             pg_tenant_only_auth: None,
             http_auth: None,
@@ -184,15 +209,21 @@ pub fn dummy() -> Self {
             max_timeline_disk_usage_bytes: defaults::DEFAULT_MAX_TIMELINE_DISK_USAGE_BYTES,
             global_disk_check_interval: Duration::from_secs(60),
             max_global_disk_usage_ratio: defaults::DEFAULT_MAX_GLOBAL_DISK_USAGE_RATIO,
+            disk_pressure_eviction_ratio: defaults::DEFAULT_DISK_PRESSURE_EVICTION_RATIO,
             /* END_HADRON */
             current_thread_runtime: false,
             walsenders_keep_horizon: false,
             partial_backup_timeout: Duration::from_secs(0),
+            remote_snapshot_interval: None,
             disable_periodic_broker_push: false,
             enable_offload: false,
             delete_offloaded_wal: false,
             control_file_save_interval: Duration::from_secs(1),
             partial_backup_concurrency: 1,
+            wal_ingest_limits: WalIngestLimits::default(),
+            wal_fsync_concurrency: None,
+            wal_group_commit_window: None,
+            wal_group_commit_syncfs: false,
             eviction_min_resident: Duration::ZERO,
             wal_reader_fanout: false,
             max_delta_for_fanout: None,
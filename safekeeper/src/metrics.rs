@@ -203,6 +203,52 @@
     )
     .expect("Failed to register safekeeper_backup_errors_total counter")
 });
+pub static COMPRESSED_WAL_SEGMENTS: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!(
+        "safekeeper_compressed_wal_segments_total",
+        "Number of WAL segments compressed on disk"
+    )
+    .expect("Failed to register safekeeper_compressed_wal_segments_total counter")
+});
+pub static ARCHIVED_SEGMENTS: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!(
+        "safekeeper_archived_segments_total",
+        "Number of WAL segments mirrored to the WAL archive"
+    )
+    .expect("Failed to register safekeeper_archived_segments_total counter")
+});
+pub static WAL_GROUP_COMMIT_BATCH_SIZE: Lazy<Histogram> = Lazy::new(|| {
+    register_histogram!(
+        "safekeeper_wal_group_commit_batch_size",
+        "Number of WAL flushes made durable together by one group commit",
+        pow2_buckets(1, 4096)
+    )
+    .expect("Failed to register safekeeper_wal_group_commit_batch_size histogram")
+});
+pub static WAL_GROUP_COMMIT_SECONDS: Lazy<Histogram> = Lazy::new(|| {
+    register_histogram!(
+        "safekeeper_wal_group_commit_seconds",
+        "Seconds spent syncing a group commit batch to disk",
+        DISK_FSYNC_SECONDS_BUCKETS.to_vec()
+    )
+    .expect("Failed to register safekeeper_wal_group_commit_seconds histogram")
+});
+pub static WAL_INGEST_THROTTLED: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "safekeeper_wal_ingest_throttled_total",
+        "Number of WAL appends delayed by a WAL ingest bandwidth limit",
+        &["scope"]
+    )
+    .expect("Failed to register safekeeper_wal_ingest_throttled_total counter")
+});
+pub static WAL_INGEST_THROTTLED_USECS: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "safekeeper_wal_ingest_throttled_usecs_total",
+        "Microseconds WAL appends were delayed by WAL ingest bandwidth limits",
+        &["scope"]
+    )
+    .expect("Failed to register safekeeper_wal_ingest_throttled_usecs_total counter")
+});
 /* BEGIN_HADRON */
 pub static BACKUP_REELECT_LEADER_COUNT: Lazy<IntCounter> = Lazy::new(|| {
     register_int_counter!(
@@ -253,6 +299,21 @@
     )
     .expect("Failed to register safekeeper_partial_backup_uploaded_bytes_total counter")
 });
+pub static REMOTE_SNAPSHOT_UPLOADS: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "safekeeper_remote_snapshot_uploads_total",
+        "Number of timeline snapshot uploads to the S3",
+        &["result"]
+    )
+    .expect("Failed to register safekeeper_remote_snapshot_uploads_total counter")
+});
+pub static REMOTE_SNAPSHOT_UPLOADED_BYTES: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!(
+        "safekeeper_remote_snapshot_uploaded_bytes_total",
+        "Number of bytes of timeline snapshots uploaded to the S3"
+    )
+    .expect("Failed to register safekeeper_remote_snapshot_uploaded_bytes_total counter")
+});
 pub static MANAGER_ITERATIONS_TOTAL: Lazy<IntCounter> = Lazy::new(|| {
     register_int_counter!(
         "safekeeper_manager_iterations_total",
@@ -357,6 +418,22 @@ pub(crate) enum EvictionEvent {
     .expect("Failed to register metric")
 });
 
+pub static DISK_PRESSURE_EVICTIONS: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!(
+        "safekeeper_disk_pressure_evictions_total",
+        "Number of timelines evicted because of disk pressure"
+    )
+    .expect("Failed to register metric")
+});
+
+pub static DISK_PRESSURE_RECLAIMED_BYTES: Lazy<IntCounter> = Lazy::new(|| {
+    register_int_counter!(
+        "safekeeper_disk_pressure_reclaimed_bytes_total",
+        "Number of bytes reclaimed by evicting timelines because of disk pressure"
+    )
+    .expect("Failed to register metric")
+});
+
 pub const LABEL_UNKNOWN: &str = "unknown";
 
 /// Labels for traffic metrics.
@@ -5,12 +5,12 @@
 
 use anyhow::{Context, Result, anyhow, bail};
 use bytes::Bytes;
-use camino::Utf8PathBuf;
+use camino::{Utf8Path, Utf8PathBuf};
 use chrono::{DateTime, Utc};
 use futures::{SinkExt, StreamExt, TryStreamExt};
 use http::StatusCode;
 use http_utils::error::ApiError;
-use postgres_ffi::{PG_TLI, XLogFileName, XLogSegNo};
+use postgres_ffi::XLogSegNo;
 use remote_storage::GenericRemoteStorage;
 use reqwest::Certificate;
 use safekeeper_api::models::{PullTimelineRequest, PullTimelineResponse, TimelineStatus};
@@ -19,7 +19,7 @@
 use safekeeper_client::mgmt_api::Client;
 use serde::Deserialize;
 use tokio::fs::OpenOptions;
-use tokio::io::AsyncWrite;
+use tokio::io::{AsyncRead, AsyncWrite};
 use tokio::sync::mpsc;
 use tokio::task;
 use tokio::time::sleep;
@@ -38,7 +38,7 @@
 use crate::timeline::{Timeline, TimelineError, WalResidentTimeline};
 use crate::timelines_global_map::{create_temp_timeline_dir, validate_temp_timeline};
 use crate::wal_storage::{open_wal_file, wal_file_paths};
-use crate::{GlobalTimelines, debug_dump, wal_backup};
+use crate::{GlobalTimelines, debug_dump, remote_snapshot, wal_backup};
 
 /// Stream tar archive of timeline to tx.
 #[instrument(name = "snapshot", skip_all, fields(ttid = %tli.ttid))]
@@ -49,6 +49,14 @@ pub async fn stream_snapshot(
     tx: mpsc::Sender<Result<Bytes>>,
     storage: Option<Arc<GenericRemoteStorage>>,
 ) {
+    if tli.is_witness().await {
+        tx.send(Err(anyhow!(
+            "safekeeper {source} is a witness of the timeline and has no WAL"
+        )))
+        .await
+        .ok();
+        return;
+    }
     match tli.try_wal_residence_guard().await {
         Err(e) => {
             tx.send(Err(anyhow!("Error checking residence: {:#}", e)))
@@ -119,7 +127,7 @@ fn drop(&mut self) {
 }
 
 /// Build a tokio_tar stream that sends encoded bytes into a Bytes channel.
-fn prepare_tar_stream(
+pub(crate) fn prepare_tar_stream(
     tx: mpsc::Sender<Result<Bytes>>,
 ) -> tokio_tar::Builder<impl AsyncWrite + Unpin + Send> {
     // tokio-tar wants Write implementor, but we have mpsc tx <Result<Bytes>>;
@@ -189,8 +197,7 @@ pub async fn stream_snapshot_resident_guts(
             bctx.flush_lsn,
         );
         for segno in from_to_segno.clone() {
-            let Some((mut sf, is_partial)) =
-                open_wal_file(&tli_dir, segno, bctx.wal_seg_size).await?
+            let Some((mut sf, format)) = open_wal_file(&tli_dir, segno, bctx.wal_seg_size).await?
             else {
                 // File is not found
                 let (wal_file_path, _wal_file_partial_path) =
@@ -198,10 +205,9 @@ pub async fn stream_snapshot_resident_guts(
                 tracing::warn!("couldn't find WAL segment file {wal_file_path}");
                 bail!("couldn't find WAL segment file {wal_file_path}")
             };
-            let mut wal_file_name = XLogFileName(PG_TLI, segno, bctx.wal_seg_size);
-            if is_partial {
-                wal_file_name.push_str(".partial");
-            }
+            // Compressed segments are sent as is, the receiver stores them
+            // under the same name.
+            let wal_file_name = format.file_name(segno, bctx.wal_seg_size);
             ar.append_file(&wal_file_name, &mut sf).await?;
         }
     } else {
@@ -453,6 +459,7 @@ pub async fn handle_request(
     global_timelines: Arc<GlobalTimelines>,
     wait_for_peer_timeline_status: bool,
 ) -> Result<PullTimelineResponse, ApiError> {
+    let ttid = TenantTimelineId::new(request.tenant_id, request.timeline_id);
     if let Some(mconf) = &request.mconf {
         let sk_id = global_timelines.get_sk_id();
         if !mconf.contains(sk_id) {
@@ -460,12 +467,16 @@ pub async fn handle_request(
                 "refused to pull timeline with {mconf}, node {sk_id} is not member of it",
             )));
         }
+        // Witnesses are placed only on timeline creation: role is fixed for
+        // the lifetime of the timeline on the node.
+        if mconf.is_witness(sk_id) && global_timelines.get(ttid).is_err() {
+            return Err(ApiError::BadRequest(anyhow!(
+                "refused to pull timeline with {mconf}, node {sk_id} is its witness",
+            )));
+        }
     }
 
-    let existing_tli = global_timelines.get(TenantTimelineId::new(
-        request.tenant_id,
-        request.timeline_id,
-    ));
+    let existing_tli = global_timelines.get(ttid);
     if let Ok(timeline) = existing_tli {
         let cur_generation = timeline
             .read_shared_state()
@@ -489,6 +500,7 @@ pub async fn handle_request(
 
         return Ok(PullTimelineResponse {
             safekeeper_host: None,
+            remote_snapshot: None,
         });
     }
 
@@ -549,10 +561,20 @@ pub async fn handle_request(
         // offline and C comes online. Then we want a pull on C with A and B as hosts to work.
         let min_required_successful = (http_hosts.len() - 1).max(1);
         if statuses.len() < min_required_successful {
-            return Err(ApiError::InternalServerError(anyhow::anyhow!(
+            let err = anyhow::anyhow!(
                 "only got {} successful status responses. required: {min_required_successful}",
                 statuses.len()
-            )));
+            );
+            // The snapshot in remote storage may be behind the members, so
+            // use it only if none of them is reachable, i.e. a quorum of
+            // them has failed, not on a transient error of one of them.
+            if request.allow_remote_snapshot && statuses.is_empty() {
+                warn!("{err:#}, bootstrapping from remote snapshot");
+                return pull_timeline_from_remote_snapshot(ttid, global_timelines, request.mconf)
+                    .await
+                    .or_else(|e| map_pull_error(e, ttid));
+            }
+            return Err(ApiError::InternalServerError(err));
         }
     } else {
         let mut retry = true;
@@ -576,6 +598,7 @@ pub async fn handle_request(
                             );
                             return Ok(PullTimelineResponse {
                                 safekeeper_host: None,
+                                remote_snapshot: None,
                             });
                         }
                         let info: TimelineStatus = resp
@@ -599,6 +622,7 @@ pub async fn handle_request(
                                 );
                                 return Ok(PullTimelineResponse {
                                     safekeeper_host: None,
+                                    remote_snapshot: None,
                                 });
                             }
                             _ => {}
@@ -612,6 +636,24 @@ pub async fn handle_request(
         }
     }
 
+    // Witnesses don't store WAL, they can't be donors.
+    statuses.retain(|(status, i)| {
+        if status.witness {
+            info!("not pulling from {}: it is a witness", http_hosts[i]);
+        }
+        !status.witness
+    });
+    if statuses.is_empty() {
+        let err = anyhow!("no data members among {:?}", http_hosts);
+        if request.allow_remote_snapshot {
+            warn!("{err:#}, bootstrapping from remote snapshot");
+            return pull_timeline_from_remote_snapshot(ttid, global_timelines, request.mconf)
+                .await
+                .or_else(|e| map_pull_error(e, ttid));
+        }
+        return Err(ApiError::InternalServerError(err));
+    }
+
     let max_term = statuses
         .iter()
         .map(|(status, _)| status.acceptor_state.term)
@@ -667,22 +709,28 @@ pub async fn handle_request(
     .await
     {
         Ok(resp) => Ok(resp),
-        Err(e) => {
-            match e.downcast_ref::<TimelineError>() {
-                Some(TimelineError::AlreadyExists(_)) => Ok(PullTimelineResponse {
-                    safekeeper_host: None,
-                }),
-                Some(TimelineError::Deleted(_)) => Err(ApiError::Conflict(format!(
-                    "Timeline {}/{} deleted",
-                    request.tenant_id, request.timeline_id
-                ))),
-                Some(TimelineError::CreationInProgress(_)) => {
-                    // We don't return success here because creation might still fail.
-                    Err(ApiError::Conflict("Creation in progress".to_owned()))
-                }
-                _ => Err(ApiError::InternalServerError(e)),
-            }
+        Err(e) => map_pull_error(e, ttid),
+    }
+}
+
+fn map_pull_error(
+    e: anyhow::Error,
+    ttid: TenantTimelineId,
+) -> Result<PullTimelineResponse, ApiError> {
+    match e.downcast_ref::<TimelineError>() {
+        Some(TimelineError::AlreadyExists(_)) => Ok(PullTimelineResponse {
+            safekeeper_host: None,
+            remote_snapshot: None,
+        }),
+        Some(TimelineError::Deleted(_)) => Err(ApiError::Conflict(format!(
+            "Timeline {}/{} deleted",
+            ttid.tenant_id, ttid.timeline_id
+        ))),
+        Some(TimelineError::CreationInProgress(_)) => {
+            // We don't return success here because creation might still fail.
+            Err(ApiError::Conflict("Creation in progress".to_owned()))
         }
+        _ => Err(ApiError::InternalServerError(e)),
     }
 }
 
@@ -719,9 +767,58 @@ async fn pull_timeline(
     // and turn it into StreamReader implementing AsyncRead.
     let bb_reader = tokio_util::io::StreamReader::new(bb_stream);
 
-    // Extract it on the fly to the disk. We don't use simple unpack() to fsync
-    // files.
-    let mut entries = Archive::new(bb_reader).entries()?;
+    unpack_snapshot(bb_reader, &tli_dir_path).await?;
+    // fsync temp timeline directory to remember its contents.
+    fsync_async_opt(&tli_dir_path, !conf.no_sync).await?;
+    assert!(status.commit_lsn <= status.flush_lsn);
+
+    load_pulled_timeline(ttid, &tli_dir_path, &global_timelines, mconf).await?;
+
+    Ok(PullTimelineResponse {
+        safekeeper_host: Some(host),
+        remote_snapshot: None,
+    })
+}
+
+/// Bootstrap the timeline from the most advanced snapshot uploaded to remote
+/// storage by safekeepers, see [`remote_snapshot`]. WAL preceding it is read
+/// from WAL backup segments.
+async fn pull_timeline_from_remote_snapshot(
+    ttid: TenantTimelineId,
+    global_timelines: Arc<GlobalTimelines>,
+    mconf: Option<membership::Configuration>,
+) -> Result<PullTimelineResponse> {
+    let storage = global_timelines
+        .get_wal_backup()
+        .get_storage()
+        .context("remote storage not configured")?;
+    let remote_timeline_path = wal_backup::remote_timeline_path(&ttid)?;
+    let snapshot = remote_snapshot::find_latest(&storage, &remote_timeline_path)
+        .await?
+        .context("no snapshot found in remote storage")?;
+    info!(
+        "bootstrapping timeline {} from remote snapshot of safekeeper {}, term={}, last_log_term={}, flush_lsn={}",
+        ttid, snapshot.sk_id, snapshot.term, snapshot.last_log_term, snapshot.flush_lsn,
+    );
+
+    let conf = &global_timelines.get_global_config();
+    let (_tmp_dir, tli_dir_path) = create_temp_timeline_dir(conf, ttid).await?;
+    let reader = remote_snapshot::download(&storage, &remote_timeline_path, &snapshot).await?;
+    unpack_snapshot(reader, &tli_dir_path).await?;
+    fsync_async_opt(&tli_dir_path, !conf.no_sync).await?;
+
+    load_pulled_timeline(ttid, &tli_dir_path, &global_timelines, mconf).await?;
+
+    Ok(PullTimelineResponse {
+        safekeeper_host: None,
+        remote_snapshot: Some(snapshot.sk_id),
+    })
+}
+
+/// Extract snapshot tar archive into temp timeline directory. We don't use
+/// simple unpack() to fsync files.
+async fn unpack_snapshot(reader: impl AsyncRead + Unpin, tli_dir_path: &Utf8Path) -> Result<()> {
+    let mut entries = Archive::new(reader).entries()?;
     while let Some(base_tar_entry) = entries.next().await {
         let mut entry = base_tar_entry?;
         let header = entry.header();
@@ -750,24 +847,30 @@ async fn pull_timeline(
             }
         }
     }
+    Ok(())
+}
 
-    // fsync temp timeline directory to remember its contents.
-    fsync_async_opt(&tli_dir_path, !conf.no_sync).await?;
-
+/// Validate the timeline extracted into temp directory and load it.
+async fn load_pulled_timeline(
+    ttid: TenantTimelineId,
+    tli_dir_path: &Utf8Path,
+    global_timelines: &GlobalTimelines,
+    mconf: Option<membership::Configuration>,
+) -> Result<()> {
+    let conf = &global_timelines.get_global_config();
     let generation = mconf.as_ref().map(|c| c.generation);
 
     // Let's create timeline from temp directory and verify that it's correct
     let (commit_lsn, flush_lsn) =
-        validate_temp_timeline(conf, ttid, &tli_dir_path, generation).await?;
+        validate_temp_timeline(conf, ttid, tli_dir_path, generation).await?;
     info!(
         "finished downloading timeline {}, commit_lsn={}, flush_lsn={}",
         ttid, commit_lsn, flush_lsn
     );
-    assert!(status.commit_lsn <= status.flush_lsn);
 
     // Finally, load the timeline.
     let timeline = global_timelines
-        .load_temp_timeline(ttid, &tli_dir_path, generation)
+        .load_temp_timeline(ttid, tli_dir_path, generation)
         .await?;
 
     if let Some(mconf) = mconf {
@@ -779,8 +882,5 @@ async fn pull_timeline(
         // ignore switch to older generation.
         timeline.membership_switch(mconf).await?;
     }
-
-    Ok(PullTimelineResponse {
-        safekeeper_host: Some(host),
-    })
+    Ok(())
 }
@@ -1,24 +1,64 @@
+use std::collections::{HashMap, VecDeque};
+use std::num::NonZeroU64;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
 
 use rand::Rng;
+use safekeeper_api::models::WalIngestLimits;
+use serde::{Deserialize, Serialize};
+use tokio::sync::oneshot;
+use tokio::time::Instant;
+use utils::id::{TenantId, TenantTimelineId};
+use utils::leaky_bucket::{self, LeakyBucketConfig};
 
-use crate::metrics::MISC_OPERATION_SECONDS;
+use crate::metrics::{MISC_OPERATION_SECONDS, WAL_INGEST_THROTTLED, WAL_INGEST_THROTTLED_USECS};
 
-/// Global rate limiter for background tasks.
+/// WAL ingest is accounted in units of this many bytes, rounded up, to keep
+/// the per-token cost of the leaky bucket well above timer resolution.
+const WAL_INGEST_TOKEN_BYTES: usize = 1024;
+
+/// Global rate limiter for background tasks and WAL ingest.
 #[derive(Clone)]
 pub struct RateLimiter {
     partial_backup: Arc<tokio::sync::Semaphore>,
     eviction: Arc<tokio::sync::Semaphore>,
+    wal_ingest: Arc<WalIngestLimiter>,
 }
 
 impl RateLimiter {
     /// Create a new rate limiter.
     /// - `partial_backup_max`: maximum number of concurrent partial backups.
     /// - `eviction_max`: maximum number of concurrent timeline evictions.
-    pub fn new(partial_backup_max: usize, eviction_max: usize) -> Self {
+    /// - `wal_ingest_limits`: initial WAL ingest bandwidth limits, can be changed later
+    ///   with [`Self::set_wal_ingest_limits`].
+    /// - `wal_fsync_max`: maximum number of concurrent WAL flushes, None means unlimited.
+    ///   Waiting flushes are served round-robin across tenants, see [`WalFsyncPermit`].
+    pub fn new(
+        partial_backup_max: usize,
+        eviction_max: usize,
+        wal_ingest_limits: WalIngestLimits,
+        wal_fsync_max: Option<usize>,
+    ) -> Self {
         Self {
             partial_backup: Arc::new(tokio::sync::Semaphore::new(partial_backup_max)),
             eviction: Arc::new(tokio::sync::Semaphore::new(eviction_max)),
+            wal_ingest: Arc::new(WalIngestLimiter {
+                buckets: std::sync::Mutex::new(WalIngestBuckets {
+                    limits: wal_ingest_limits,
+                    timelines: HashMap::new(),
+                    tenants: HashMap::new(),
+                }),
+                fsync: wal_fsync_max.map(|max| {
+                    Arc::new(FsyncQueue {
+                        state: std::sync::Mutex::new(FsyncQueueState {
+                            max,
+                            running: 0,
+                            tenants: VecDeque::new(),
+                            waiters: HashMap::new(),
+                        }),
+                    })
+                }),
+            }),
         }
     }
 
@@ -40,6 +80,258 @@ pub async fn acquire_partial_backup(&self) -> tokio::sync::OwnedSemaphorePermit
     pub fn try_acquire_eviction(&self) -> Option<tokio::sync::OwnedSemaphorePermit> {
         self.eviction.clone().try_acquire_owned().ok()
     }
+
+    /// Currently effective WAL ingest limits.
+    pub fn wal_ingest_limits(&self) -> WalIngestLimits {
+        self.wal_ingest.buckets.lock().unwrap().limits.clone()
+    }
+
+    /// Replace WAL ingest limits. Buckets are recreated with the new rates, so
+    /// the change applies to the next append of every connection.
+    pub fn set_wal_ingest_limits(&self, limits: WalIngestLimits) {
+        let mut buckets = self.wal_ingest.buckets.lock().unwrap();
+        buckets.limits = limits;
+        buckets.timelines.clear();
+        buckets.tenants.clear();
+    }
+
+    /// Wait until both the timeline and its tenant are allowed to ingest `bytes`
+    /// more WAL. Waiters on the same bucket are served in FIFO order.
+    pub async fn throttle_wal_ingest(
+        &self,
+        ttid: &TenantTimelineId,
+        bytes: usize,
+        stats: &WalIngestThrottleStats,
+    ) {
+        let (timeline, tenant) = self.wal_ingest.buckets.lock().unwrap().get(ttid);
+        let tokens = bytes.div_ceil(WAL_INGEST_TOKEN_BYTES);
+        for (scope, bucket) in [("timeline", timeline), ("tenant", tenant)] {
+            let Some(bucket) = bucket else {
+                continue;
+            };
+            let started = Instant::now();
+            if bucket.acquire(tokens).await {
+                let waited = started.elapsed();
+                WAL_INGEST_THROTTLED.with_label_values(&[scope]).inc();
+                WAL_INGEST_THROTTLED_USECS
+                    .with_label_values(&[scope])
+                    .inc_by(waited.as_micros() as u64);
+                stats.throttled.fetch_add(1, Ordering::Relaxed);
+                stats
+                    .throttled_usecs
+                    .fetch_add(waited.as_micros() as u64, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Get a permit for a timeline of `tenant_id` to flush WAL to disk. While
+    /// flushes are queued, permits are granted round-robin across tenants, and
+    /// in FIFO order within a tenant, so a tenant with many busy timelines
+    /// can't starve others of fsyncs. Returns None if WAL flushes are not
+    /// limited.
+    pub async fn acquire_wal_fsync(
+        &self,
+        tenant_id: TenantId,
+        stats: &WalIngestThrottleStats,
+    ) -> Option<WalFsyncPermit> {
+        let fsync = self.wal_ingest.fsync.as_ref()?;
+        let _timer = MISC_OPERATION_SECONDS
+            .with_label_values(&["wal_fsync_permit_acquire"])
+            .start_timer();
+        let started = Instant::now();
+        let permit = fsync.acquire(tenant_id).await;
+        stats
+            .fsync_wait_usecs
+            .fetch_add(started.elapsed().as_micros() as u64, Ordering::Relaxed);
+        Some(permit)
+    }
+
+    /// Forget WAL ingest buckets which are empty, i.e. equivalent to new ones.
+    /// Called periodically to not accumulate buckets of idle timelines.
+    pub fn wal_ingest_housekeeping(&self) {
+        let now = Instant::now();
+        let is_busy = |bucket: &Arc<leaky_bucket::RateLimiter>| {
+            Arc::strong_count(bucket) > 1 || !bucket.state.lock().unwrap().bucket_is_empty(now)
+        };
+        let mut buckets = self.wal_ingest.buckets.lock().unwrap();
+        buckets.timelines.retain(|_, bucket| is_busy(bucket));
+        buckets.tenants.retain(|_, bucket| is_busy(bucket));
+    }
+}
+
+struct WalIngestLimiter {
+    buckets: std::sync::Mutex<WalIngestBuckets>,
+    fsync: Option<Arc<FsyncQueue>>,
+}
+
+/// Concurrency limit of WAL flushes with per-tenant fair queueing.
+struct FsyncQueue {
+    state: std::sync::Mutex<FsyncQueueState>,
+}
+
+struct FsyncQueueState {
+    max: usize,
+    running: usize,
+    /// Tenants with waiters, in the order they are served. A tenant goes to
+    /// the back after each permit it is granted.
+    tenants: VecDeque<TenantId>,
+    waiters: HashMap<TenantId, VecDeque<oneshot::Sender<()>>>,
+}
+
+impl FsyncQueueState {
+    /// Hand out free permits to the waiters next in line.
+    fn grant(&mut self) {
+        while self.running < self.max {
+            let Some(tenant_id) = self.tenants.pop_front() else {
+                return;
+            };
+            let waiters = self
+                .waiters
+                .get_mut(&tenant_id)
+                .expect("queued tenants have waiters");
+            // Skip waiters which were cancelled.
+            while let Some(waiter) = waiters.pop_front() {
+                if waiter.send(()).is_ok() {
+                    self.running += 1;
+                    break;
+                }
+            }
+            if waiters.is_empty() {
+                self.waiters.remove(&tenant_id);
+            } else {
+                self.tenants.push_back(tenant_id);
+            }
+        }
+    }
+}
+
+impl FsyncQueue {
+    async fn acquire(self: &Arc<Self>, tenant_id: TenantId) -> WalFsyncPermit {
+        let rx = {
+            let mut guard = self.state.lock().unwrap();
+            let state = &mut *guard;
+            // Don't overtake anyone.
+            if state.running < state.max && state.tenants.is_empty() {
+                state.running += 1;
+                return WalFsyncPermit(self.clone());
+            }
+            let (tx, rx) = oneshot::channel();
+            let waiters = state.waiters.entry(tenant_id).or_default();
+            if waiters.is_empty() {
+                state.tenants.push_back(tenant_id);
+            }
+            waiters.push_back(tx);
+            rx
+        };
+
+        let mut waiter = FsyncWaiter {
+            queue: self.clone(),
+            rx: Some(rx),
+        };
+        waiter
+            .rx
+            .as_mut()
+            .unwrap()
+            .await
+            .expect("fsync queue is never dropped while waited on");
+        waiter.rx = None;
+        WalFsyncPermit(self.clone())
+    }
+
+    fn release(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.running -= 1;
+        state.grant();
+    }
+}
+
+/// Returns the permit if the waiter is cancelled after it was granted one.
+struct FsyncWaiter {
+    queue: Arc<FsyncQueue>,
+    rx: Option<oneshot::Receiver<()>>,
+}
+
+impl Drop for FsyncWaiter {
+    fn drop(&mut self) {
+        if let Some(mut rx) = self.rx.take() {
+            if rx.try_recv().is_ok() {
+                self.queue.release();
+            }
+        }
+    }
+}
+
+/// Permit to flush WAL to disk, see [`RateLimiter::acquire_wal_fsync`].
+pub struct WalFsyncPermit(Arc<FsyncQueue>);
+
+impl Drop for WalFsyncPermit {
+    fn drop(&mut self) {
+        self.0.release();
+    }
+}
+
+struct WalIngestBuckets {
+    limits: WalIngestLimits,
+    timelines: HashMap<TenantTimelineId, Arc<leaky_bucket::RateLimiter>>,
+    tenants: HashMap<TenantId, Arc<leaky_bucket::RateLimiter>>,
+}
+
+type BucketRef = Option<Arc<leaky_bucket::RateLimiter>>;
+
+impl WalIngestBuckets {
+    /// Get timeline and tenant buckets, creating them if the respective limit is set.
+    fn get(&mut self, ttid: &TenantTimelineId) -> (BucketRef, BucketRef) {
+        let timeline = self.limits.timeline_bytes_per_second.map(|limit| {
+            self.timelines
+                .entry(*ttid)
+                .or_insert_with(|| new_bucket(limit))
+                .clone()
+        });
+        let tenant = self.limits.tenant_limit(&ttid.tenant_id).map(|limit| {
+            self.tenants
+                .entry(ttid.tenant_id)
+                .or_insert_with(|| new_bucket(limit))
+                .clone()
+        });
+        (timeline, tenant)
+    }
+}
+
+/// Bucket allowing `bytes_per_second` with bursts of up to one second worth of WAL.
+fn new_bucket(bytes_per_second: NonZeroU64) -> Arc<leaky_bucket::RateLimiter> {
+    let rps = bytes_per_second.get() as f64 / WAL_INGEST_TOKEN_BYTES as f64;
+    let config = LeakyBucketConfig::new(rps, rps.max(1.0));
+    Arc::new(leaky_bucket::RateLimiter::with_initial_tokens(config, 0.0))
+}
+
+/// Per timeline counters of backpressure applied to WAL ingest.
+#[derive(Default)]
+pub struct WalIngestThrottleStats {
+    throttled: AtomicU64,
+    throttled_usecs: AtomicU64,
+    fsync_wait_usecs: AtomicU64,
+}
+
+impl WalIngestThrottleStats {
+    pub fn get(&self) -> WalIngestThrottleState {
+        let secs = |usecs: &AtomicU64| usecs.load(Ordering::Relaxed) as f64 / 1_000_000.0;
+        WalIngestThrottleState {
+            throttled_count: self.throttled.load(Ordering::Relaxed),
+            throttled_seconds: secs(&self.throttled_usecs),
+            fsync_wait_seconds: secs(&self.fsync_wait_usecs),
+        }
+    }
+}
+
+/// Snapshot of [`WalIngestThrottleStats`], reported in debug_dump.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WalIngestThrottleState {
+    /// Number of appends delayed by a bandwidth limit.
+    pub throttled_count: u64,
+    /// Total time appends were delayed by bandwidth limits.
+    pub throttled_seconds: f64,
+    /// Total time spent waiting for a WAL flush permit.
+    pub fsync_wait_seconds: f64,
 }
 
 /// Generate a random duration that is a fraction of the given duration.
@@ -47,3 +339,102 @@ pub fn rand_duration(duration: &std::time::Duration) -> std::time::Duration {
     let randf64 = rand::rng().random_range(0.0..1.0);
     duration.mul_f64(randf64)
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use utils::id::TimelineId;
+
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn wal_ingest_throttle() {
+        let limits = WalIngestLimits {
+            timeline_bytes_per_second: NonZeroU64::new(1024 * 1024),
+            ..Default::default()
+        };
+        let limiter = RateLimiter::new(1, 1, limits, None);
+        let ttid = TenantTimelineId::new(TenantId::generate(), TimelineId::generate());
+        let stats = WalIngestThrottleStats::default();
+
+        // The first second worth of WAL passes as a burst, the next one waits.
+        let started = Instant::now();
+        limiter
+            .throttle_wal_ingest(&ttid, 1024 * 1024, &stats)
+            .await;
+        assert_eq!(stats.get().throttled_count, 0);
+        limiter
+            .throttle_wal_ingest(&ttid, 1024 * 1024, &stats)
+            .await;
+        assert_eq!(stats.get().throttled_count, 1);
+        assert!(started.elapsed() >= Duration::from_secs(1));
+
+        // Other timelines of the tenant are not affected by the timeline limit...
+        let other = TenantTimelineId::new(ttid.tenant_id, TimelineId::generate());
+        let other_stats = WalIngestThrottleStats::default();
+        limiter
+            .throttle_wal_ingest(&other, 1024 * 1024, &other_stats)
+            .await;
+        assert_eq!(other_stats.get().throttled_count, 0);
+
+        // ... but are by a tenant limit.
+        limiter.set_wal_ingest_limits(WalIngestLimits {
+            tenant_overrides: [(ttid.tenant_id, NonZeroU64::new(1024 * 1024).unwrap())].into(),
+            ..Default::default()
+        });
+        limiter
+            .throttle_wal_ingest(&ttid, 1024 * 1024, &stats)
+            .await;
+        limiter
+            .throttle_wal_ingest(&other, 1024 * 1024, &other_stats)
+            .await;
+        assert_eq!(other_stats.get().throttled_count, 1);
+
+        // Buckets are dropped once drained.
+        tokio::time::advance(Duration::from_secs(5)).await;
+        limiter.wal_ingest_housekeeping();
+        let buckets = limiter.wal_ingest.buckets.lock().unwrap();
+        assert!(buckets.tenants.is_empty() && buckets.timelines.is_empty());
+    }
+
+    #[tokio::test]
+    async fn wal_fsync_fair_queueing() {
+        let limiter = RateLimiter::new(1, 1, WalIngestLimits::default(), Some(1));
+        let (busy, quiet) = (TenantId::generate(), TenantId::generate());
+        let stats = Arc::new(WalIngestThrottleStats::default());
+        let held = limiter.acquire_wal_fsync(busy, &stats).await.unwrap();
+
+        // Three flushes of the busy tenant queue up before one of the quiet
+        // tenant, which still gets the second turn.
+        let order = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mut tasks = Vec::new();
+        for (name, tenant_id) in [
+            ("busy1", busy),
+            ("busy2", busy),
+            ("busy3", busy),
+            ("quiet", quiet),
+        ] {
+            let (limiter, stats, order) = (limiter.clone(), stats.clone(), order.clone());
+            tasks.push(tokio::spawn(async move {
+                let _permit = limiter.acquire_wal_fsync(tenant_id, &stats).await;
+                order.lock().unwrap().push(name);
+            }));
+            tokio::task::yield_now().await;
+        }
+        drop(held);
+        for task in tasks {
+            task.await.unwrap();
+        }
+        assert_eq!(*order.lock().unwrap(), ["busy1", "quiet", "busy2", "busy3"]);
+
+        // A waiter cancelled after it was granted a permit returns it.
+        let held = limiter.acquire_wal_fsync(busy, &stats).await.unwrap();
+        let mut waiter = Box::pin(limiter.acquire_wal_fsync(quiet, &stats));
+        assert!(futures::poll!(&mut waiter).is_pending());
+        drop(held);
+        drop(waiter);
+        let mut next = Box::pin(limiter.acquire_wal_fsync(busy, &stats));
+        assert!(futures::poll!(&mut next).is_ready());
+    }
+}
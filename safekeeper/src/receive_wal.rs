@@ -31,7 +31,8 @@
     WAL_RECEIVER_QUEUE_DEPTH, WAL_RECEIVER_QUEUE_DEPTH_TOTAL, WAL_RECEIVER_QUEUE_SIZE_TOTAL,
     WAL_RECEIVERS,
 };
-use crate::safekeeper::{AcceptorProposerMessage, ProposerAcceptorMessage};
+use crate::rate_limit::RateLimiter;
+use crate::safekeeper::{AcceptorProposerMessage, ProposerAcceptorMessage, SK_PROTO_VERSION_4};
 use crate::timeline::{TimelineError, WalResidentTimeline};
 
 const DEFAULT_FEEDBACK_CAPACITY: usize = 8;
@@ -366,6 +367,15 @@ async fn read_first_message(
                         other => other.context("get_timeline")?,
                     }
                 };
+                // Older walproposers don't know member roles and might e.g.
+                // take a witness as donor, which doesn't have WAL.
+                if self.proto_version < SK_PROTO_VERSION_4 && tli.has_witnesses().await {
+                    return Err(CopyStreamHandlerEnd::Other(anyhow::anyhow!(
+                        "timeline has witness members, which requires protocol version {}, got {}",
+                        SK_PROTO_VERSION_4,
+                        self.proto_version
+                    )));
+                }
                 tli.wal_residence_guard().await?
             }
             _ => {
@@ -391,6 +401,7 @@ async fn run(
             msg_rx,
             reply_tx,
             Some(self.conn_id),
+            Some(self.global_timelines.get_global_rate_limiter()),
         ));
 
         // Forward all messages to WalAcceptor
@@ -522,6 +533,7 @@ pub struct WalAcceptor {
     msg_rx: Receiver<ProposerAcceptorMessage>,
     reply_tx: Sender<AcceptorProposerMessage>,
     conn_id: Option<ConnectionId>,
+    rate_limiter: Option<RateLimiter>,
 }
 
 impl WalAcceptor {
@@ -530,11 +542,15 @@ impl WalAcceptor {
     /// message processing is encountered.
     ///
     /// conn_id None means WalAcceptor is used by recovery initiated at this safekeeper.
+    ///
+    /// If rate_limiter is given, appends are subject to WAL ingest bandwidth
+    /// limits and flushes to the global fsync queue.
     pub fn spawn(
         tli: WalResidentTimeline,
         msg_rx: Receiver<ProposerAcceptorMessage>,
         reply_tx: Sender<AcceptorProposerMessage>,
         conn_id: Option<ConnectionId>,
+        rate_limiter: Option<RateLimiter>,
     ) -> JoinHandle<anyhow::Result<()>> {
         task::spawn(async move {
             let mut wa = WalAcceptor {
@@ -542,6 +558,7 @@ pub fn spawn(
                 msg_rx,
                 reply_tx,
                 conn_id,
+                rate_limiter,
             };
 
             let span_ttid = wa.tli.ttid; // satisfy borrow checker
@@ -598,6 +615,17 @@ async fn run(&mut self) -> anyhow::Result<()> {
                     // Note that a flush can still happen on segment bounds, which will result
                     // in an AppendResponse.
                     if let ProposerAcceptorMessage::AppendRequest(append_request) = msg {
+                        // Apply backpressure before writing; while we wait msg_rx
+                        // fills up and eventually stops reading from the socket.
+                        if let Some(rate_limiter) = &self.rate_limiter {
+                            rate_limiter
+                                .throttle_wal_ingest(
+                                    &self.tli.ttid,
+                                    append_request.wal_data.len(),
+                                    &self.tli.wal_ingest_throttle,
+                                )
+                                .await;
+                        }
                         msg = ProposerAcceptorMessage::NoFlushAppendRequest(append_request);
                         dirty = true;
                     }
@@ -609,9 +637,7 @@ async fn run(&mut self) -> anyhow::Result<()> {
                 // AppendResponse to let walproposer know we're still alive.
                 _ = flush_ticker.tick(), if dirty => {
                     dirty = false;
-                    self.tli
-                        .process_msg(&ProposerAcceptorMessage::FlushWAL)
-                        .await?
+                    self.flush().await?
                 }
 
                 // If there are no pending messages, flush the WAL immediately.
@@ -621,9 +647,7 @@ async fn run(&mut self) -> anyhow::Result<()> {
                 _ = future::ready(()), if dirty && self.msg_rx.is_empty() => {
                     dirty = false;
                     flush_ticker.reset();
-                    self.tli
-                        .process_msg(&ProposerAcceptorMessage::FlushWAL)
-                        .await?
+                    self.flush().await?
                 }
 
                 // Update histogram metrics periodically.
@@ -647,13 +671,29 @@ async fn run(&mut self) -> anyhow::Result<()> {
 
         // Flush WAL on disconnect, see https://github.com/neondatabase/neon/issues/9259.
         if dirty && !self.tli.cancel.is_cancelled() {
-            self.tli
-                .process_msg(&ProposerAcceptorMessage::FlushWAL)
-                .await?;
+            self.flush().await?;
         }
 
         Ok(())
     }
+
+    /// Flush WAL to disk, waiting for the tenant's turn in the fsync queue if it
+    /// is limited. The permit is held until the flush is durable, including the
+    /// wait for its group commit batch, so batches are at most as large as the
+    /// fsync concurrency.
+    async fn flush(&self) -> anyhow::Result<Option<AcceptorProposerMessage>> {
+        let _permit = match &self.rate_limiter {
+            Some(rate_limiter) => {
+                rate_limiter
+                    .acquire_wal_fsync(self.tli.ttid.tenant_id, &self.tli.wal_ingest_throttle)
+                    .await
+            }
+            None => None,
+        };
+        self.tli
+            .process_msg(&ProposerAcceptorMessage::FlushWAL)
+            .await
+    }
 }
 
 /// On drop, drain msg_rx and update metrics to avoid leaks.
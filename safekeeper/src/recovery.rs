@@ -72,6 +72,7 @@ async fn recovery_needed(
     let term = ss.sk.state().acceptor_state.term;
     let last_log_term = ss.sk.last_log_term();
     let flush_lsn = ss.sk.flush_lsn();
+    let mconf = &ss.sk.state().mconf;
     // note that peers contain myself, but that's ok -- we are interested only in peers which are strictly ahead of us.
     let mut peers = ss.get_peers(heartbeat_timeout);
     // Sort by <last log term, lsn> pairs.
@@ -102,6 +103,11 @@ async fn recovery_needed(
                     term: last_log_term,
                     lsn: flush_lsn,
                 };
+                // Witness doesn't have WAL to give; normally it isn't
+                // advertised in the broker at all, but be explicit.
+                if mconf.is_witness(candidate.sk_id) {
+                    return None;
+                }
                 if my_tl < candidate_tl {
                     // Yes, we are interested. Can we pull from it without
                     // (re)running elections? It is possible if 1) his term
@@ -410,7 +416,14 @@ async fn recovery_stream(
     // As in normal walreceiver, do networking and writing to disk in parallel.
     let (msg_tx, msg_rx) = channel(MSG_QUEUE_SIZE);
     let (reply_tx, reply_rx) = channel(REPLY_QUEUE_SIZE);
-    let wa = WalAcceptor::spawn(tli.wal_residence_guard().await?, msg_rx, reply_tx, None);
+    // Recovery from peers is not subject to WAL ingest limits.
+    let wa = WalAcceptor::spawn(
+        tli.wal_residence_guard().await?,
+        msg_rx,
+        reply_tx,
+        None,
+        None,
+    );
 
     let res = tokio::select! {
         r = network_io(physical_stream, msg_tx, donor.clone(), tli, conf.clone()) => r,
@@ -0,0 +1,364 @@
+//! Periodic snapshots of timelines in remote storage.
+//!
+//! `pull_timeline` normally streams a snapshot of the timeline from a peer,
+//! which is impossible while peers are down. To be able to bootstrap a new
+//! member anyway, safekeepers periodically upload a snapshot of each resident
+//! timeline to remote storage. It is a tar archive in the same format as the
+//! streamed one, with the control file and the WAL tail which is not yet
+//! covered by WAL backup segments, i.e. segments from min(backup_lsn,
+//! commit_lsn) up to flush_lsn. A member bootstrapped from it reads older WAL
+//! from the uploaded segments.
+//!
+//! Snapshots are stored under `snapshots/` in the timeline prefix, the object
+//! name format is `Term_LastLogTerm_Flush_skNN.tar`, where:
+//! - `Term` – current term
+//! - `LastLogTerm` – term of the last WAL record
+//! - `Flush` – flush_lsn in hex format `{:016X}`, e.g. `00000000346BC568`
+//! - `NN` – safekeeper_id, like `1`
+//!
+//! Each safekeeper keeps only its latest snapshot, older ones are removed
+//! after a newer one is uploaded.
+
+use std::cmp::min;
+use std::io;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use anyhow::{Context, Result, anyhow, bail};
+use bytes::Bytes;
+use futures::StreamExt;
+use remote_storage::{GenericRemoteStorage, ListingMode, RemotePath};
+use safekeeper_api::Term;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_tar::Header;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, instrument};
+use utils::id::NodeId;
+use utils::lsn::Lsn;
+
+use crate::SafeKeeperConf;
+use crate::control_file::CONTROL_FILE_NAME;
+use crate::metrics::{REMOTE_SNAPSHOT_UPLOADED_BYTES, REMOTE_SNAPSHOT_UPLOADS};
+use crate::pull_timeline::prepare_tar_stream;
+use crate::rate_limit::RateLimiter;
+use crate::state::TimelinePersistentState;
+use crate::timeline::WalResidentTimeline;
+use crate::timeline_manager::StateSnapshot;
+use crate::wal_storage::open_wal_file;
+use crate::{wal_backup, wal_backup_partial};
+
+/// Snapshot uploaded to remote storage, parsed from its object name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoteSnapshot {
+    pub term: Term,
+    pub last_log_term: Term,
+    pub flush_lsn: Lsn,
+    pub sk_id: NodeId,
+}
+
+impl RemoteSnapshot {
+    fn object_name(&self) -> String {
+        format!(
+            "{}_{}_{:016X}_sk{}.tar",
+            self.term, self.last_log_term, self.flush_lsn.0, self.sk_id.0
+        )
+    }
+
+    fn parse(name: &str) -> Option<Self> {
+        let mut parts = name.strip_suffix(".tar")?.split('_');
+        let snapshot = RemoteSnapshot {
+            term: parts.next()?.parse().ok()?,
+            last_log_term: parts.next()?.parse().ok()?,
+            flush_lsn: Lsn(u64::from_str_radix(parts.next()?, 16).ok()?),
+            sk_id: NodeId(parts.next()?.strip_prefix("sk")?.parse().ok()?),
+        };
+        parts.next().is_none().then_some(snapshot)
+    }
+
+    pub(crate) fn remote_path(&self, remote_timeline_path: &RemotePath) -> RemotePath {
+        snapshots_path(remote_timeline_path).join(self.object_name())
+    }
+
+    /// Snapshots are preferred in the same order as donors in pull_timeline.
+    fn sort_key(&self) -> (Term, Lsn, Term) {
+        (self.last_log_term, self.flush_lsn, self.term)
+    }
+}
+
+fn snapshots_path(remote_timeline_path: &RemotePath) -> RemotePath {
+    remote_timeline_path.join("snapshots")
+}
+
+/// Whether WAL was written since the last uploaded snapshot.
+pub(crate) fn needs_uploading(state: &StateSnapshot, uploaded: &Option<RemoteSnapshot>) -> bool {
+    match uploaded {
+        Some(uploaded) => {
+            uploaded.flush_lsn != state.flush_lsn || uploaded.last_log_term != state.last_log_term
+        }
+        None => state.flush_lsn != Lsn::INVALID,
+    }
+}
+
+/// Take a snapshot of the timeline and upload it, replacing the previous one
+/// of this safekeeper. Returns None if the timeline has no WAL yet.
+#[instrument(name = "remote_snapshot", skip_all, fields(ttid = %tli.ttid))]
+pub(crate) async fn main_task(
+    tli: WalResidentTimeline,
+    conf: SafeKeeperConf,
+    limiter: RateLimiter,
+    storage: Arc<GenericRemoteStorage>,
+) -> Result<Option<RemoteSnapshot>> {
+    // Snapshots are uploaded at the same pace as partial segments.
+    let _upload_permit = tokio::select! {
+        acq = limiter.acquire_partial_backup() => acq,
+        _ = tli.cancel.cancelled() => return Ok(None),
+    };
+
+    let res = upload_snapshot(&tli, &conf, &storage).await;
+    let result = if res.is_ok() { "ok" } else { "error" };
+    REMOTE_SNAPSHOT_UPLOADS.with_label_values(&[result]).inc();
+    res
+}
+
+async fn upload_snapshot(
+    tli: &WalResidentTimeline,
+    conf: &SafeKeeperConf,
+    storage: &GenericRemoteStorage,
+) -> Result<Option<RemoteSnapshot>> {
+    let Some(prepared) = prepare_snapshot(tli, conf.my_id).await? else {
+        return Ok(None);
+    };
+    let snapshot = prepared.snapshot.clone();
+    let size = prepared.archive_size();
+
+    // Stream the archive to remote storage as it is built, like pull_timeline
+    // streams it to the peer. An error of building it is sent down the
+    // stream and fails the upload.
+    let (tx, rx) = mpsc::channel(1);
+    let build = async move {
+        let res = write_snapshot(tli, prepared, tx.clone()).await;
+        if let Err(e) = &res {
+            tx.send(Err(anyhow!("snapshot failed: {e:#}"))).await.ok();
+        }
+        res
+    };
+    let stream = ReceiverStream::new(rx).map(|r| r.map_err(io::Error::other));
+    let remote_timeline_path = &tli.remote_path;
+    let path = snapshot.remote_path(remote_timeline_path);
+    let upload = storage.upload_storage_object(stream, size, &path, &CancellationToken::new());
+    let (build_res, upload_res) = tokio::join!(build, upload);
+    build_res?;
+    upload_res?;
+    REMOTE_SNAPSHOT_UPLOADED_BYTES.inc_by(size as u64);
+    info!("uploaded snapshot {path} of {size} bytes");
+
+    let outdated = list_snapshots(storage, remote_timeline_path)
+        .await?
+        .into_iter()
+        .filter(|s| s.sk_id == conf.my_id && *s != snapshot)
+        .map(|s| s.remote_path(remote_timeline_path))
+        .collect::<Vec<_>>();
+    if !outdated.is_empty() {
+        info!("removing outdated snapshots {outdated:?}");
+        wal_backup::delete_objects(storage, &outdated).await?;
+    }
+    Ok(Some(snapshot))
+}
+
+/// Contents of the snapshot, see the module comment.
+struct PreparedSnapshot {
+    snapshot: RemoteSnapshot,
+    control_file: Vec<u8>,
+    /// Opened WAL segments with their names in the archive and sizes.
+    segments: Vec<(String, tokio::fs::File, u64)>,
+}
+
+impl PreparedSnapshot {
+    /// Size of the tar archive, which must be known before the upload: each
+    /// entry is a header block followed by data padded to the block size,
+    /// and the archive ends with two zero blocks.
+    fn archive_size(&self) -> usize {
+        const BLOCK_SIZE: u64 = 512;
+        let entry_size = |size: u64| BLOCK_SIZE + size.div_ceil(BLOCK_SIZE) * BLOCK_SIZE;
+        let size = entry_size(self.control_file.len() as u64)
+            + self
+                .segments
+                .iter()
+                .map(|(_, _, size)| entry_size(*size))
+                .sum::<u64>()
+            + 2 * BLOCK_SIZE;
+        size as usize
+    }
+}
+
+/// Serialize the control file and open the WAL tail of the timeline.
+async fn prepare_snapshot(
+    tli: &WalResidentTimeline,
+    sk_id: NodeId,
+) -> Result<Option<PreparedSnapshot>> {
+    let (snapshot, control_file, segments, wal_seg_size) = {
+        let shared_state = tli.read_shared_state().await;
+        let state = shared_state.sk.state();
+        if state.commit_lsn == Lsn::INVALID {
+            return Ok(None);
+        }
+        let wal_seg_size = shared_state.get_wal_seg_size();
+        let snapshot = RemoteSnapshot {
+            term: state.acceptor_state.term,
+            last_log_term: shared_state.sk.last_log_term(),
+            flush_lsn: shared_state.sk.flush_lsn(),
+            sk_id,
+        };
+
+        let mut control_file = TimelinePersistentState::clone(state);
+        // Partial segments in remote storage are owned by the safekeeper
+        // which uploaded them, the new member uploads its own ones.
+        control_file.partial_backup = wal_backup_partial::State::default();
+
+        // Everything before backup_lsn is in WAL backup, and WAL from
+        // commit_lsn is needed to find the end of WAL on load.
+        let from_lsn = min(state.backup_lsn, state.commit_lsn);
+        let segments =
+            from_lsn.segment_number(wal_seg_size)..=snapshot.flush_lsn.segment_number(wal_seg_size);
+        (snapshot, control_file, segments, wal_seg_size)
+    };
+
+    let control_file = control_file
+        .write_to_buf()
+        .context("failed to serialize control store")?;
+
+    // WAL removal doesn't go past backup_lsn, so the segments can disappear
+    // only if it has advanced meanwhile; the next snapshot will succeed then.
+    // Once opened, they are readable even if removed.
+    let tli_dir = tli.get_timeline_dir();
+    let mut opened = Vec::new();
+    for segno in segments {
+        let Some((file, format)) = open_wal_file(&tli_dir, segno, wal_seg_size).await? else {
+            bail!("WAL segment {segno:#X} is not found");
+        };
+        let size = file.metadata().await?.len();
+        opened.push((format.file_name(segno, wal_seg_size), file, size));
+    }
+
+    Ok(Some(PreparedSnapshot {
+        snapshot,
+        control_file,
+        segments: opened,
+    }))
+}
+
+/// Write the snapshot tar archive into tx.
+async fn write_snapshot(
+    tli: &WalResidentTimeline,
+    prepared: PreparedSnapshot,
+    tx: mpsc::Sender<Result<Bytes>>,
+) -> Result<()> {
+    let mut ar = prepare_tar_stream(tx);
+    let mut header = Header::new_gnu();
+    header.set_size(prepared.control_file.len() as u64);
+    ar.append_data(
+        &mut header,
+        CONTROL_FILE_NAME,
+        prepared.control_file.as_slice(),
+    )
+    .await
+    .context("failed to append to archive")?;
+
+    for (name, file, size) in prepared.segments {
+        // Sizes are fixed in the archive size, so don't read past them.
+        let mut header = Header::new_gnu();
+        header.set_size(size);
+        ar.append_data(&mut header, name, file.take(size))
+            .await
+            .context("failed to append to archive")?;
+    }
+
+    // Like in pull_timeline snapshot, WAL must not be truncated while we read
+    // it. Check it before finishing the archive to fail the upload otherwise.
+    let snapshot = &prepared.snapshot;
+    let shared_state = tli.read_shared_state().await;
+    let term = shared_state.sk.state().acceptor_state.term;
+    let last_log_term = shared_state.sk.last_log_term();
+    if snapshot.term != term || snapshot.last_log_term != last_log_term {
+        bail!(
+            "term(s) changed during snapshot: were term={}, last_log_term={}, now term={}, last_log_term={}",
+            snapshot.term,
+            snapshot.last_log_term,
+            term,
+            last_log_term
+        );
+    }
+    drop(shared_state);
+
+    ar.finish().await?;
+    Ok(())
+}
+
+/// List snapshots of the timeline in remote storage.
+pub async fn list_snapshots(
+    storage: &GenericRemoteStorage,
+    remote_timeline_path: &RemotePath,
+) -> Result<Vec<RemoteSnapshot>> {
+    let cancel = CancellationToken::new(); // not really used
+    let listing = storage
+        .list(
+            Some(&snapshots_path(remote_timeline_path)),
+            ListingMode::NoDelimiter,
+            None,
+            &cancel,
+        )
+        .await?;
+    Ok(listing
+        .keys
+        .iter()
+        .filter_map(|o| o.key.object_name())
+        .filter_map(RemoteSnapshot::parse)
+        .collect())
+}
+
+/// Find the most advanced snapshot of the timeline in remote storage.
+pub async fn find_latest(
+    storage: &GenericRemoteStorage,
+    remote_timeline_path: &RemotePath,
+) -> Result<Option<RemoteSnapshot>> {
+    Ok(list_snapshots(storage, remote_timeline_path)
+        .await?
+        .into_iter()
+        .max_by_key(RemoteSnapshot::sort_key))
+}
+
+/// Open the snapshot tar archive for reading.
+pub async fn download(
+    storage: &GenericRemoteStorage,
+    remote_timeline_path: &RemotePath,
+    snapshot: &RemoteSnapshot,
+) -> Result<Pin<Box<dyn AsyncRead + Send + Sync>>> {
+    wal_backup::read_object(storage, &snapshot.remote_path(remote_timeline_path), 0).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_object_name_roundtrip() {
+        let snapshot = RemoteSnapshot {
+            term: 5,
+            last_log_term: 4,
+            flush_lsn: Lsn(0x346BC568),
+            sk_id: NodeId(12),
+        };
+        let name = snapshot.object_name();
+        assert_eq!(name, "5_4_00000000346BC568_sk12.tar");
+        assert_eq!(RemoteSnapshot::parse(&name), Some(snapshot));
+
+        assert_eq!(RemoteSnapshot::parse("5_4_00000000346BC568_sk12"), None);
+        assert_eq!(RemoteSnapshot::parse("5_4_00000000346BC568_12.tar"), None);
+        assert_eq!(
+            RemoteSnapshot::parse("5_4_00000000346BC568_sk12_1.tar"),
+            None
+        );
+    }
+}
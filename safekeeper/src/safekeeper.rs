@@ -12,7 +12,7 @@
 use postgres_versioninfo::{PgMajorVersion, PgVersionId};
 use pq_proto::SystemId;
 use safekeeper_api::membership::{
-    INVALID_GENERATION, MemberSet, SafekeeperGeneration as Generation, SafekeeperId,
+    INVALID_GENERATION, MemberRole, MemberSet, SafekeeperGeneration as Generation, SafekeeperId,
 };
 use safekeeper_api::models::HotStandbyFeedback;
 use safekeeper_api::{Term, membership};
@@ -30,6 +30,8 @@
 
 pub const SK_PROTO_VERSION_2: u32 = 2;
 pub const SK_PROTO_VERSION_3: u32 = 3;
+/// Same as 3, but members in membership configuration carry their role.
+pub const SK_PROTO_VERSION_4: u32 = 4;
 pub const UNKNOWN_SERVER_VERSION: PgVersionId = PgVersionId::UNKNOWN;
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
@@ -424,8 +426,21 @@ fn get_cstr(buf: &mut Bytes) -> Result<String> {
         }
     }
 
+    /// Read member role, sent since protocol version 4.
+    fn get_role(buf: &mut Bytes, proto_version: u32) -> Result<MemberRole> {
+        if proto_version < SK_PROTO_VERSION_4 {
+            // see Configuration::inherit_roles
+            return Ok(MemberRole::Data);
+        }
+        match buf.get_u8_f()? {
+            0 => Ok(MemberRole::Data),
+            1 => Ok(MemberRole::Witness),
+            r => bail!("unknown member role {}", r),
+        }
+    }
+
     /// Read membership::Configuration from Bytes.
-    fn get_mconf(buf: &mut Bytes) -> Result<membership::Configuration> {
+    fn get_mconf(buf: &mut Bytes, proto_version: u32) -> Result<membership::Configuration> {
         let generation = Generation::new(buf.get_u32_f().with_context(|| "reading generation")?);
         let members_len = buf.get_u32_f().with_context(|| "reading members_len")?;
         // Main member set must have at least someone in valid configuration.
@@ -442,10 +457,13 @@ fn get_mconf(buf: &mut Bytes) -> Result<membership::Configuration> {
             let pg_port = buf
                 .get_u16_f()
                 .with_context(|| format!("reading member {i} port"))?;
+            let role = Self::get_role(buf, proto_version)
+                .with_context(|| format!("reading member {i} role"))?;
             let sk = SafekeeperId {
                 id: NodeId(id),
                 host,
                 pg_port,
+                role,
             };
             members.add(sk)?;
         }
@@ -468,10 +486,13 @@ fn get_mconf(buf: &mut Bytes) -> Result<membership::Configuration> {
                 let pg_port = buf
                     .get_u16_f()
                     .with_context(|| format!("reading new member {i} port"))?;
+                let role = Self::get_role(buf, proto_version)
+                    .with_context(|| format!("reading new member {i} role"))?;
                 let sk = SafekeeperId {
                     id: NodeId(id),
                     host,
                     pg_port,
+                    role,
                 };
                 new_members.add(sk)?;
             }
@@ -485,7 +506,7 @@ fn get_mconf(buf: &mut Bytes) -> Result<membership::Configuration> {
 
     /// Parse proposer message.
     pub fn parse(mut msg_bytes: Bytes, proto_version: u32) -> Result<ProposerAcceptorMessage> {
-        if proto_version == SK_PROTO_VERSION_3 {
+        if proto_version == SK_PROTO_VERSION_3 || proto_version == SK_PROTO_VERSION_4 {
             if msg_bytes.is_empty() {
                 bail!("ProposerAcceptorMessage is not complete: missing tag");
             }
@@ -500,7 +521,7 @@ pub fn parse(mut msg_bytes: Bytes, proto_version: u32) -> Result<ProposerAccepto
                     let timeline_id_str =
                         Self::get_cstr(&mut msg_bytes).with_context(|| "reading timeline_id")?;
                     let timeline_id = TimelineId::from_str(&timeline_id_str)?;
-                    let mconf = Self::get_mconf(&mut msg_bytes)?;
+                    let mconf = Self::get_mconf(&mut msg_bytes, proto_version)?;
                     let pg_version = msg_bytes
                         .get_u32_f()
                         .with_context(|| "reading pg_version")?;
@@ -755,21 +776,30 @@ fn put_cstr(buf: &mut BytesMut, s: &str) {
         buf.put_u8(0); // null terminator
     }
 
+    /// Serialize SafekeeperId into buf.
+    fn serialize_sk_id(buf: &mut BytesMut, sk: &SafekeeperId, proto_version: u32) {
+        buf.put_u64(sk.id.0);
+        Self::put_cstr(buf, &sk.host);
+        buf.put_u16(sk.pg_port);
+        if proto_version >= SK_PROTO_VERSION_4 {
+            buf.put_u8(match sk.role {
+                MemberRole::Data => 0,
+                MemberRole::Witness => 1,
+            });
+        }
+    }
+
     /// Serialize membership::Configuration into buf.
-    fn serialize_mconf(buf: &mut BytesMut, mconf: &membership::Configuration) {
+    fn serialize_mconf(buf: &mut BytesMut, mconf: &membership::Configuration, proto_version: u32) {
         buf.put_u32(mconf.generation.into_inner());
         buf.put_u32(mconf.members.m.len() as u32);
         for sk in &mconf.members.m {
-            buf.put_u64(sk.id.0);
-            Self::put_cstr(buf, &sk.host);
-            buf.put_u16(sk.pg_port);
+            Self::serialize_sk_id(buf, sk, proto_version);
         }
         if let Some(ref new_members) = mconf.new_members {
             buf.put_u32(new_members.m.len() as u32);
             for sk in &new_members.m {
-                buf.put_u64(sk.id.0);
-                Self::put_cstr(buf, &sk.host);
-                buf.put_u16(sk.pg_port);
+                Self::serialize_sk_id(buf, sk, proto_version);
             }
         } else {
             buf.put_u32(0);
@@ -778,12 +808,12 @@ fn serialize_mconf(buf: &mut BytesMut, mconf: &membership::Configuration) {
 
     /// Serialize acceptor -> proposer message.
     pub fn serialize(&self, buf: &mut BytesMut, proto_version: u32) -> Result<()> {
-        if proto_version == SK_PROTO_VERSION_3 {
+        if proto_version == SK_PROTO_VERSION_3 || proto_version == SK_PROTO_VERSION_4 {
             match self {
                 AcceptorProposerMessage::Greeting(msg) => {
                     buf.put_u8(b'g');
                     buf.put_u64(msg.node_id.0);
-                    Self::serialize_mconf(buf, &msg.mconf);
+                    Self::serialize_mconf(buf, &msg.mconf, proto_version);
                     buf.put_u64(msg.term)
                 }
                 AcceptorProposerMessage::VoteResponse(msg) => {
@@ -1033,8 +1063,12 @@ async fn handle_greeting(
                 self.node_id,
             );
         }
-        // Switch into conf given by proposer conf if it is higher.
-        self.state.membership_switch(msg.mconf.clone()).await?;
+        // Switch into conf given by proposer conf if it is higher. Before
+        // protocol version 4 it doesn't carry member roles, so keep the ones we
+        // know.
+        let mut mconf = msg.mconf.clone();
+        mconf.inherit_roles(&self.state.mconf);
+        self.state.membership_switch(mconf).await?;
 
         let apg = AcceptorGreeting {
             node_id: self.node_id,
@@ -1259,6 +1293,68 @@ async fn handle_elected(
         Ok(None)
     }
 
+    /// Discard WAL after `lsn` and make the timeline continue from it in `term`, as part of
+    /// resetting the timeline to an earlier LSN.
+    ///
+    /// Unlike the truncation in [`Self::handle_elected`], this throws away committed WAL. That's
+    /// only safe because the storage controller fences computes by bumping the term first, and
+    /// resets all safekeepers of the timeline to the same point: the new term makes proposers
+    /// prefer our history over that of any safekeeper which missed the reset.
+    ///
+    /// If the segment holding `lsn` was already removed, `reinit_segment` must be set, and it is
+    /// initialized like the first segment of a new timeline.
+    pub async fn reset_wal(&mut self, lsn: Lsn, term: Term, reinit_segment: bool) -> Result<()> {
+        if self.state.acceptor_state.term == term
+            && self.state.acceptor_state.term_history.0.last() == Some(&TermLsn { term, lsn })
+            && self.flush_lsn() == lsn
+        {
+            // Retry of a reset which already went through.
+            return Ok(());
+        }
+        if term <= self.state.acceptor_state.term {
+            bail!(
+                "refusing to reset WAL in term {}, current term is {}",
+                term,
+                self.state.acceptor_state.term
+            );
+        }
+        if lsn > self.flush_lsn() || lsn < self.state.timeline_start_lsn {
+            bail!(
+                "cannot reset WAL to {}, timeline_start_lsn={} flush_lsn={}",
+                lsn,
+                self.state.timeline_start_lsn,
+                self.flush_lsn()
+            );
+        }
+
+        if reinit_segment {
+            self.wal_store.initialize_first_segment(lsn).await?;
+        }
+        self.wal_store.truncate_wal(lsn).await?;
+
+        let mut state = self.state.start_change();
+        state.acceptor_state.term = term;
+        state.acceptor_state.term_history.0.retain(|e| e.lsn < lsn);
+        state
+            .acceptor_state
+            .term_history
+            .0
+            .push(TermLsn { term, lsn });
+        if reinit_segment {
+            state.local_start_lsn = lsn;
+        }
+        // In-memory values may be ahead of the persisted ones.
+        state.commit_lsn = min(self.state.inmem.commit_lsn, lsn);
+        state.backup_lsn = min(self.state.inmem.backup_lsn, lsn);
+        state.peer_horizon_lsn = min(self.state.inmem.peer_horizon_lsn, lsn);
+        state.remote_consistent_lsn = min(self.state.inmem.remote_consistent_lsn, lsn);
+        self.state.finish_change(&state).await?;
+
+        self.term_start_lsn = lsn;
+        info!("reset WAL to {} in term {}", lsn, term);
+        Ok(())
+    }
+
     /// Advance commit_lsn taking into account what we have locally.
     ///
     /// Note: it is assumed that 'WAL we have is from the right term' check has
@@ -1427,7 +1523,7 @@ mod tests {
     use postgres_ffi::{WAL_SEGMENT_SIZE, XLogSegNo};
     use safekeeper_api::ServerInfo;
     use safekeeper_api::membership::{
-        Configuration, MemberSet, SafekeeperGeneration, SafekeeperId,
+        Configuration, MemberRole, MemberSet, SafekeeperGeneration, SafekeeperId,
     };
 
     use super::*;
@@ -1684,6 +1780,56 @@ async fn test_non_consecutive_write() {
             .unwrap_err();
     }
 
+    #[tokio::test]
+    async fn test_reset_wal() {
+        let storage = InMemoryState {
+            persisted_state: test_sk_state(),
+        };
+        let wal_store = DummyWalStore { lsn: Lsn(0) };
+        let mut sk = SafeKeeper::new(TimelineState::new(storage), wal_store, NodeId(0)).unwrap();
+
+        let pem = ProposerElected {
+            generation: Generation::new(0),
+            term: 1,
+            start_streaming_at: Lsn(1),
+            term_history: TermHistory(vec![(1, Lsn(1)).into()]),
+        };
+        sk.process_msg(&ProposerAcceptorMessage::Elected(pem))
+            .await
+            .unwrap();
+        let append_request = AppendRequest {
+            h: AppendRequestHeader {
+                generation: Generation::new(0),
+                term: 1,
+                begin_lsn: Lsn(1),
+                end_lsn: Lsn(5),
+                commit_lsn: Lsn(5),
+                truncate_lsn: Lsn(0),
+            },
+            wal_data: Bytes::from_static(b"abcd"),
+        };
+        sk.process_msg(&ProposerAcceptorMessage::AppendRequest(append_request))
+            .await
+            .unwrap();
+        assert_eq!(sk.state.inmem.commit_lsn, Lsn(5));
+
+        // The term must be higher than the current one, and the LSN within the WAL we have.
+        sk.reset_wal(Lsn(3), 1, false).await.unwrap_err();
+        sk.reset_wal(Lsn(6), 2, false).await.unwrap_err();
+
+        sk.reset_wal(Lsn(3), 2, false).await.unwrap();
+        // Retries are fine.
+        sk.reset_wal(Lsn(3), 2, false).await.unwrap();
+        assert_eq!(sk.flush_lsn(), Lsn(3));
+        assert_eq!(sk.state.inmem.commit_lsn, Lsn(3));
+        assert_eq!(sk.state.acceptor_state.term, 2);
+        assert_eq!(sk.get_last_log_term(), 2);
+        assert_eq!(
+            sk.state.acceptor_state.term_history.0,
+            vec![(1, Lsn(1)).into(), (2, Lsn(3)).into()]
+        );
+    }
+
     #[test]
     fn test_find_highest_common_point_none() {
         let prop_th = TermHistory(vec![(0, Lsn(1)).into()]);
@@ -1758,6 +1904,7 @@ fn test_sk_state_bincode_serde_roundtrip() {
                     id: NodeId(1),
                     host: "hehe.org".to_owned(),
                     pg_port: 5432,
+                    role: MemberRole::Data,
                 }])
                 .expect("duplicate member"),
                 new_members: None,
@@ -1796,4 +1943,35 @@ fn test_sk_state_bincode_serde_roundtrip() {
 
         assert_eq!(deser, state);
     }
+
+    #[test]
+    fn test_mconf_wire_roles() {
+        let sk = |id, role| SafekeeperId {
+            id: NodeId(id),
+            host: "hehe.org".to_owned(),
+            pg_port: 5432,
+            role,
+        };
+        let mconf = Configuration {
+            generation: SafekeeperGeneration::new(2),
+            members: MemberSet::new(vec![sk(1, MemberRole::Data), sk(2, MemberRole::Witness)])
+                .unwrap(),
+            new_members: Some(
+                MemberSet::new(vec![sk(1, MemberRole::Data), sk(3, MemberRole::Witness)]).unwrap(),
+            ),
+        };
+
+        let mut buf = BytesMut::new();
+        AcceptorProposerMessage::serialize_mconf(&mut buf, &mconf, SK_PROTO_VERSION_4);
+        let deser =
+            ProposerAcceptorMessage::get_mconf(&mut buf.freeze(), SK_PROTO_VERSION_4).unwrap();
+        assert_eq!(deser, mconf);
+
+        // v3 doesn't carry roles
+        let mut buf = BytesMut::new();
+        AcceptorProposerMessage::serialize_mconf(&mut buf, &mconf, SK_PROTO_VERSION_3);
+        let deser =
+            ProposerAcceptorMessage::get_mconf(&mut buf.freeze(), SK_PROTO_VERSION_3).unwrap();
+        assert!(!deser.has_witnesses());
+    }
 }
@@ -459,6 +459,13 @@ pub async fn handle_start_replication<IO: AsyncRead + AsyncWrite + Unpin + Send>
             .global_timelines
             .get(self.ttid)
             .map_err(|e| QueryError::Other(e.into()))?;
+        if tli.is_witness().await {
+            return Err(QueryError::Other(anyhow::anyhow!(
+                "safekeeper {} is a witness of timeline {} and doesn't store WAL",
+                self.conf.my_id,
+                self.ttid
+            )));
+        }
         let residence_guard = tli.wal_residence_guard().await?;
 
         if let Err(end) = self
@@ -67,7 +67,8 @@ pub async fn make_safekeeper(
         pstate.tenant_id = ttid.tenant_id;
         pstate.timeline_id = ttid.timeline_id;
 
-        let wal = wal_storage::PhysicalStorage::new(&ttid, &timeline_dir, &pstate, conf.no_sync)?;
+        let wal =
+            wal_storage::PhysicalStorage::new(&ttid, &timeline_dir, &pstate, conf.no_sync, false)?;
         let ctrl =
             control_file::FileStorage::create_new(&timeline_dir, pstate, conf.no_sync).await?;
         let state = TimelineState::new(ctrl);
@@ -115,7 +116,7 @@ pub async fn make_timeline(
             &mut timeline.write_shared_state().await,
             &conf,
             Arc::new(TimelinesSet::default()), // ignored for now
-            RateLimiter::new(0, 0),
+            RateLimiter::new(0, 0, Default::default(), None),
             wal_backup,
         );
         Ok(timeline)
@@ -137,7 +138,13 @@ pub(crate) async fn write_wal(
 
         let end_watch = EndWatch::Commit(tli.get_commit_lsn_watch_rx());
 
-        WalAcceptor::spawn(tli.wal_residence_guard().await?, msg_rx, reply_tx, Some(0));
+        WalAcceptor::spawn(
+            tli.wal_residence_guard().await?,
+            msg_rx,
+            reply_tx,
+            Some(0),
+            None,
+        );
 
         let prefixlen = prefix.to_bytes_with_nul().len();
         assert!(msg_size >= prefixlen);
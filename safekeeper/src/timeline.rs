@@ -10,11 +10,12 @@
 use anyhow::{Result, anyhow, bail};
 use camino::{Utf8Path, Utf8PathBuf};
 use http_utils::error::ApiError;
+use postgres_ffi::XLogSegNo;
 use remote_storage::RemotePath;
 use safekeeper_api::Term;
 use safekeeper_api::membership::Configuration;
 use safekeeper_api::models::{
-    PeerInfo, TimelineMembershipSwitchResponse, TimelineTermBumpResponse,
+    PeerInfo, TimelineMembershipSwitchResponse, TimelineResetWalResponse, TimelineTermBumpResponse,
 };
 use storage_broker::proto::{SafekeeperTimelineInfo, TenantTimelineId as ProtoTenantTimelineId};
 use tokio::fs::{self};
@@ -31,7 +32,7 @@
 };
 
 use crate::hadron::GLOBAL_DISK_LIMIT_EXCEEDED;
-use crate::rate_limit::RateLimiter;
+use crate::rate_limit::{RateLimiter, WalIngestThrottleStats};
 use crate::receive_wal::WalReceivers;
 use crate::safekeeper::{AcceptorProposerMessage, ProposerAcceptorMessage, SafeKeeper, TermLsn};
 use crate::send_wal::{WalSenders, WalSendersTimelineMetricValues};
@@ -190,6 +191,52 @@ pub async fn term_bump(&mut self, to: Option<Term>) -> Result<TimelineTermBumpRe
         self.state_mut().term_bump(to).await
     }
 
+    /// Compress completed raw WAL segments <= `segno_up_to` on disk.
+    ///
+    /// Segments below the persisted commit_lsn are only rewritten by a timeline
+    /// reset, which may happen while a segment is compressed. Installing it then
+    /// would leave stale WAL in place of the truncated segment, so this is done
+    /// under the shared state lock, bailing out if a reset happened since start.
+    pub(crate) async fn compress_wal(
+        &self,
+        wal_seg_size: usize,
+        segno_up_to: XLogSegNo,
+        no_sync: bool,
+    ) -> anyhow::Result<()> {
+        let wal_resets = self.wal_resets.load(Ordering::Relaxed);
+        let timeline_dir = &self.timeline_dir;
+        let segnos =
+            wal_storage::raw_segments_on_disk(timeline_dir, wal_seg_size, segno_up_to).await?;
+        for &segno in &segnos {
+            let tmp_path =
+                wal_storage::compress_segment_on_disk(timeline_dir, wal_seg_size, segno, no_sync)
+                    .await?;
+
+            let _shared_state = self.read_shared_state().await;
+            if self.wal_resets.load(Ordering::Relaxed) != wal_resets {
+                bail!("WAL was reset during compression of segment {segno}");
+            }
+            wal_storage::install_compressed_segment(
+                &tmp_path,
+                timeline_dir,
+                wal_seg_size,
+                segno,
+                no_sync,
+            )
+            .await?;
+        }
+
+        if let (Some(first), Some(last)) = (segnos.first(), segnos.last()) {
+            info!(
+                "compressed {} WAL segments [{}; {}]",
+                segnos.len(),
+                first,
+                last
+            );
+        }
+        Ok(())
+    }
+
     pub async fn membership_switch(
         &mut self,
         to: Configuration,
@@ -336,6 +383,7 @@ pub fn restore(conf: &SafeKeeperConf, ttid: &TenantTimelineId) -> Result<Self> {
                     &timeline_dir,
                     &control_store,
                     conf.no_sync,
+                    control_store.mconf.is_witness(conf.my_id),
                 )?;
                 StateSK::Loaded(SafeKeeper::new(
                     TimelineState::new(control_store),
@@ -484,9 +532,18 @@ pub struct Timeline {
 
     // timeline_manager controlled state
     pub(crate) broker_active: AtomicBool,
+    /// Size of the local partial segment if the timeline is ready for
+    /// eviction, published by the manager for disk pressure eviction.
+    pub(crate) evictable_partial_size: std::sync::Mutex<Option<u64>>,
     pub(crate) wal_backup_active: AtomicBool,
     pub(crate) last_removed_segno: AtomicU64,
+    /// Bumped by [`Timeline::reset_wal`] under the shared state lock, so WAL
+    /// compression can tell a segment was truncated while it compressed it.
+    pub(crate) wal_resets: AtomicU64,
     pub(crate) mgr_status: AtomicStatus,
+
+    /// Backpressure applied to WAL received from computes.
+    pub(crate) wal_ingest_throttle: WalIngestThrottleStats,
 }
 
 impl Timeline {
@@ -528,9 +585,12 @@ pub fn new(
             manager_ctl: ManagerCtl::new(),
             conf,
             broker_active: AtomicBool::new(false),
+            evictable_partial_size: std::sync::Mutex::new(None),
             wal_backup_active: AtomicBool::new(false),
             last_removed_segno: AtomicU64::new(0),
+            wal_resets: AtomicU64::new(0),
             mgr_status: AtomicStatus::new(),
+            wal_ingest_throttle: WalIngestThrottleStats::default(),
             wal_backup,
         })
     }
@@ -828,6 +888,19 @@ pub fn get_walreceivers(&self) -> &Arc<WalReceivers> {
         &self.walreceivers
     }
 
+    /// Returns true if this safekeeper is a witness member of the timeline,
+    /// i.e. it doesn't store WAL.
+    pub async fn is_witness(&self) -> bool {
+        let shared_state = self.read_shared_state().await;
+        shared_state.sk.state().mconf.is_witness(self.conf.my_id)
+    }
+
+    /// Returns true if the timeline has witness members.
+    pub async fn has_witnesses(&self) -> bool {
+        let shared_state = self.read_shared_state().await;
+        shared_state.sk.state().mconf.has_witnesses()
+    }
+
     /// Returns flush_lsn.
     pub async fn get_flush_lsn(&self) -> Lsn {
         self.read_shared_state().await.sk.flush_lsn()
@@ -883,6 +956,7 @@ pub async fn memory_dump(&self) -> debug_dump::Memory {
             epoch_start_lsn: state.sk.term_start_lsn(),
             mem_state: state.sk.state().inmem.clone(),
             mgr_status: self.mgr_status.get(),
+            wal_ingest_throttle: self.wal_ingest_throttle.get(),
             write_lsn,
             write_record_lsn,
             flush_lsn,
@@ -913,6 +987,39 @@ pub async fn term_bump(self: &Arc<Self>, to: Option<Term>) -> Result<TimelineTer
         state.sk.term_bump(to).await
     }
 
+    /// Discard WAL after `lsn` and continue the timeline from it in `term`. See
+    /// [`SafeKeeper::reset_wal`].
+    pub async fn reset_wal(
+        self: &Arc<Self>,
+        lsn: Lsn,
+        term: Term,
+    ) -> Result<TimelineResetWalResponse> {
+        // Keep WAL on disk while we truncate it.
+        let _guard = self.wal_residence_guard().await?;
+        let mut state = self.write_shared_state().await;
+        if self.is_cancelled() {
+            bail!(TimelineError::Cancelled(self.ttid));
+        }
+        self.wal_resets.fetch_add(1, Ordering::Relaxed);
+
+        let previous_flush_lsn = state.sk.flush_lsn();
+        // If the segment with `lsn` is gone locally, start over from it as from a new timeline.
+        let last_removed_segno = self.last_removed_segno.load(Ordering::Relaxed);
+        let reinit_segment = lsn < state.sk.state().local_start_lsn
+            || (last_removed_segno != 0
+                && lsn.segment_number(state.get_wal_seg_size()) <= last_removed_segno);
+        state
+            .sk
+            .safekeeper()
+            .reset_wal(lsn, term, reinit_segment)
+            .await?;
+
+        Ok(TimelineResetWalResponse {
+            previous_flush_lsn,
+            current_term: term,
+        })
+    }
+
     pub async fn membership_switch(
         self: &Arc<Self>,
         to: Configuration,
@@ -925,6 +1032,22 @@ pub async fn membership_switch(
         if self.is_cancelled() {
             bail!(TimelineError::Cancelled(self.ttid));
         }
+        // WAL storage mode is fixed when the timeline is loaded, so role of
+        // this safekeeper can't change. In particular, a data member can't be
+        // turned into a witness and a witness can't gain WAL it doesn't have.
+        let cur = &state.sk.state().mconf;
+        let my_id = self.conf.my_id;
+        if to.generation > cur.generation
+            && to.contains(my_id)
+            && to.is_witness(my_id) != cur.is_witness(my_id)
+        {
+            bail!(
+                "refusing to switch membership conf to {}: role of safekeeper {} differs from current conf {}",
+                to,
+                my_id,
+                cur
+            );
+        }
         state.sk.membership_switch(to).await
     }
 
@@ -1014,6 +1137,13 @@ pub(crate) async fn try_wal_residence_guard(
     pub async fn backup_partial_reset(self: &Arc<Self>) -> Result<Vec<String>> {
         self.manager_ctl.backup_partial_reset().await
     }
+
+    /// Evict the timeline to free disk space, see
+    /// [`crate::timeline_eviction::evict_under_disk_pressure`]. Returns the
+    /// number of bytes reclaimed, 0 if the timeline is not ready for eviction.
+    pub async fn disk_pressure_evict(self: &Arc<Self>) -> Result<u64> {
+        self.manager_ctl.disk_pressure_evict().await
+    }
 }
 
 /// This is a guard that allows to read/write disk timeline state.
@@ -1254,6 +1384,7 @@ pub(crate) async fn switch_to_present(&self) -> anyhow::Result<()> {
             &self.timeline_dir,
             shared.sk.state(),
             self.conf.no_sync,
+            shared.sk.state().mconf.is_witness(self.conf.my_id),
         )?;
 
         // updating control file
@@ -3,17 +3,31 @@
 //! The actual upload is done by the partial WAL backup code. This file has
 //! code to delete and re-download WAL files, cross-validate with partial WAL
 //! backup if local file is still present.
+//!
+//! Timelines are normally evicted by their managers once they are inactive
+//! for a while. When the disk is running out of space, the disk usage watcher
+//! additionally runs [`evict_under_disk_pressure`] to evict timelines ready
+//! for eviction right away.
+
+use std::cmp::Reverse;
+use std::os::unix::fs::MetadataExt;
+use std::sync::Mutex;
 
 use anyhow::Context;
 use camino::Utf8PathBuf;
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
 use remote_storage::{GenericRemoteStorage, RemotePath};
+use serde::Serialize;
 use tokio::fs::File;
 use tokio::io::{AsyncRead, AsyncWriteExt};
 use tracing::{debug, info, instrument, warn};
 use utils::crashsafe::durable_rename;
 
+use crate::GlobalTimelines;
 use crate::metrics::{
-    EVICTION_EVENTS_COMPLETED, EVICTION_EVENTS_STARTED, EvictionEvent, NUM_EVICTED_TIMELINES,
+    DISK_PRESSURE_EVICTIONS, DISK_PRESSURE_RECLAIMED_BYTES, EVICTION_EVENTS_COMPLETED,
+    EVICTION_EVENTS_STARTED, EvictionEvent, NUM_EVICTED_TIMELINES,
 };
 use crate::rate_limit::rand_duration;
 use crate::timeline_manager::{Manager, StateSnapshot};
@@ -35,9 +49,12 @@ pub(crate) fn ready_for_eviction(
         next_event: &Option<tokio::time::Instant>,
         state: &StateSnapshot,
     ) -> bool {
-        self.backup_task.is_none()
+        // witness has no WAL to offload
+        !state.is_witness
+            && self.backup_task.is_none()
             && self.recovery_task.is_none()
             && self.wal_removal_task.is_none()
+            && self.wal_compression_task.is_none()
             && self.partial_backup_task.is_none()
             && next_event.is_none()
             && self.access_service.is_empty()
@@ -64,19 +81,21 @@ pub(crate) fn ready_for_eviction(
                 == self.last_removed_segno + 1
     }
 
-    /// Evict the timeline to remote storage. Returns whether the eviction was successful.
+    /// Evict the timeline to remote storage. The local segment is deleted if
+    /// `delete_local_wal` or `delete_offloaded_wal` is set. Returns the number
+    /// of bytes reclaimed, or None if the eviction failed.
     #[instrument(name = "evict_timeline", skip_all)]
-    pub(crate) async fn evict_timeline(&mut self) -> bool {
+    pub(crate) async fn evict_timeline(&mut self, delete_local_wal: bool) -> Option<u64> {
         assert!(!self.is_offloaded);
         let Some(storage) = self.wal_backup.get_storage() else {
             warn!("no remote storage configured, skipping uneviction");
-            return false;
+            return None;
         };
         let partial_backup_uploaded = match &self.partial_backup_uploaded {
             Some(p) => p.clone(),
             None => {
                 warn!("no partial backup uploaded, skipping eviction");
-                return false;
+                return None;
             }
         };
 
@@ -91,14 +110,21 @@ pub(crate) async fn evict_timeline(&mut self) -> bool {
                 .inc();
         });
 
-        if let Err(e) = do_eviction(self, &partial_backup_uploaded, &storage).await {
-            warn!("failed to evict timeline: {:?}", e);
-            return false;
-        }
-
-        info!("successfully evicted timeline");
+        let reclaimed =
+            match do_eviction(self, &partial_backup_uploaded, &storage, delete_local_wal).await {
+                Ok(reclaimed) => reclaimed,
+                Err(e) => {
+                    warn!("failed to evict timeline: {:?}", e);
+                    return None;
+                }
+            };
+
+        info!(
+            "successfully evicted timeline, reclaimed {} bytes",
+            reclaimed
+        );
         NUM_EVICTED_TIMELINES.inc();
-        true
+        Some(reclaimed)
     }
 
     /// Attempt to restore evicted timeline from remote storage; it must be
@@ -143,24 +169,95 @@ pub(crate) async fn unevict_timeline(&mut self) {
 }
 
 /// Ensure that content matches the remote partial backup, if local segment exists.
-/// Then change state in control file and in-memory. If `delete_offloaded_wal` is set,
-/// delete the local segment.
+/// Then change state in control file and in-memory. If `delete_offloaded_wal` or
+/// `delete_local_wal` is set, delete the local segment and return its size.
 async fn do_eviction(
     mgr: &mut Manager,
     partial: &PartialRemoteSegment,
     storage: &GenericRemoteStorage,
-) -> anyhow::Result<()> {
+    delete_local_wal: bool,
+) -> anyhow::Result<u64> {
     compare_local_segment_with_remote(mgr, partial, storage).await?;
 
     mgr.tli.switch_to_offloaded(partial).await?;
     // switch manager state as soon as possible
     mgr.is_offloaded = true;
 
-    if mgr.conf.delete_offloaded_wal {
-        delete_local_segment(mgr, partial).await?;
+    if mgr.conf.delete_offloaded_wal || delete_local_wal {
+        return delete_local_segment(mgr, partial).await;
     }
 
-    Ok(())
+    Ok(0)
+}
+
+/// Outcome of disk pressure eviction passes, reported in `/v1/debug/filesystem_usage`.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DiskPressureEvictionStats {
+    pub last_pass_at: Option<DateTime<Utc>>,
+    pub last_pass_evicted_timelines: u64,
+    pub last_pass_reclaimed_bytes: u64,
+    pub evicted_timelines_total: u64,
+    pub reclaimed_bytes_total: u64,
+}
+
+pub static DISK_PRESSURE_EVICTION_STATS: Lazy<Mutex<DiskPressureEvictionStats>> =
+    Lazy::new(|| Mutex::new(DiskPressureEvictionStats::default()));
+
+/// Evict timelines ready for eviction until at least `bytes_to_reclaim` bytes
+/// are freed, deleting their local WAL. Such timelines have only the partial
+/// segment left on disk, so the ones with the largest partial segment go
+/// first; the manager makes the final decision whether a timeline can be
+/// evicted. Returns the number of bytes reclaimed.
+#[instrument(name = "disk_pressure_eviction", skip_all)]
+pub async fn evict_under_disk_pressure(
+    global_timelines: &GlobalTimelines,
+    bytes_to_reclaim: u64,
+) -> u64 {
+    let mut candidates = global_timelines
+        .get_all()
+        .into_iter()
+        .filter_map(|tli| {
+            let size = (*tli.evictable_partial_size.lock().unwrap())?;
+            Some((size, tli))
+        })
+        .collect::<Vec<_>>();
+    candidates.sort_by_key(|(size, _)| Reverse(*size));
+    info!(
+        "{} candidates to reclaim {} bytes",
+        candidates.len(),
+        bytes_to_reclaim
+    );
+
+    let mut reclaimed = 0;
+    let mut evicted = 0;
+    for (_, tli) in candidates {
+        if reclaimed >= bytes_to_reclaim {
+            break;
+        }
+        match tli.disk_pressure_evict().await {
+            Ok(0) => debug!("timeline {} is not ready for eviction", tli.ttid),
+            Ok(bytes) => {
+                info!("evicted timeline {}, reclaimed {} bytes", tli.ttid, bytes);
+                reclaimed += bytes;
+                evicted += 1;
+                DISK_PRESSURE_EVICTIONS.inc();
+                DISK_PRESSURE_RECLAIMED_BYTES.inc_by(bytes);
+            }
+            Err(e) => warn!("failed to evict timeline {}: {:?}", tli.ttid, e),
+        }
+    }
+    info!(
+        "evicted {} timelines, reclaimed {} bytes",
+        evicted, reclaimed
+    );
+
+    let mut stats = DISK_PRESSURE_EVICTION_STATS.lock().unwrap();
+    stats.last_pass_at = Some(Utc::now());
+    stats.last_pass_evicted_timelines = evicted;
+    stats.last_pass_reclaimed_bytes = reclaimed;
+    stats.evicted_timelines_total += evicted;
+    stats.reclaimed_bytes_total += reclaimed;
+    reclaimed
 }
 
 /// Ensure that content matches the remote partial backup, if local segment exists.
@@ -183,13 +280,19 @@ async fn do_uneviction(
     Ok(())
 }
 
-/// Delete local WAL segment.
-async fn delete_local_segment(mgr: &Manager, partial: &PartialRemoteSegment) -> anyhow::Result<()> {
+/// Delete local WAL segment, returning the disk space it occupied.
+async fn delete_local_segment(
+    mgr: &Manager,
+    partial: &PartialRemoteSegment,
+) -> anyhow::Result<u64> {
     let local_path = local_segment_path(mgr, partial);
 
     info!("deleting WAL file to evict: {}", local_path);
+    // The segment is extended with set_len, so count allocated blocks
+    // rather than its length.
+    let size = tokio::fs::metadata(&local_path).await?.blocks() * 512;
     tokio::fs::remove_file(&local_path).await?;
-    Ok(())
+    Ok(size)
 }
 
 /// Redownload partial segment from remote storage.
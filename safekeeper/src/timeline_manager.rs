@@ -20,6 +20,7 @@
 use tokio::time::Instant;
 use tokio_util::sync::CancellationToken;
 use tracing::{Instrument, debug, info, info_span, instrument, warn};
+use utils::id::NodeId;
 use utils::lsn::Lsn;
 
 use crate::SafeKeeperConf;
@@ -29,6 +30,7 @@
 };
 use crate::rate_limit::{RateLimiter, rand_duration};
 use crate::recovery::recovery_main;
+use crate::remote_snapshot::{self, RemoteSnapshot};
 use crate::remove_wal::calc_horizon_lsn;
 use crate::send_wal::WalSenders;
 use crate::state::TimelineState;
@@ -58,11 +60,13 @@ pub(crate) struct StateSnapshot {
     pub(crate) inmem_flush_pending: bool,
     pub(crate) wal_removal_on_hold: bool,
     pub(crate) peers: Vec<PeerInfo>,
+    /// This safekeeper is a witness member and doesn't store WAL.
+    pub(crate) is_witness: bool,
 }
 
 impl StateSnapshot {
     /// Create a new snapshot of the timeline state.
-    fn new(read_guard: ReadGuardSharedState, heartbeat_timeout: Duration) -> Self {
+    fn new(read_guard: ReadGuardSharedState, heartbeat_timeout: Duration, my_id: NodeId) -> Self {
         let state = read_guard.sk.state();
         Self {
             commit_lsn: state.inmem.commit_lsn,
@@ -77,6 +81,7 @@ fn new(read_guard: ReadGuardSharedState, heartbeat_timeout: Duration) -> Self {
             inmem_flush_pending: Self::has_unflushed_inmem_state(state),
             wal_removal_on_hold: read_guard.wal_removal_on_hold,
             peers: read_guard.get_peers(heartbeat_timeout),
+            is_witness: state.mconf.is_witness(my_id),
         }
     }
 
@@ -101,6 +106,9 @@ pub enum ManagerCtlMessage {
     GuardDrop(GuardId),
     /// Request to reset uploaded partial backup state.
     BackupPartialReset(oneshot::Sender<anyhow::Result<Vec<String>>>),
+    /// Request to evict the timeline now because disk is running out of space.
+    /// Responds with the number of bytes reclaimed.
+    DiskPressureEvict(oneshot::Sender<u64>),
 }
 
 impl std::fmt::Debug for ManagerCtlMessage {
@@ -110,6 +118,7 @@ fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
             ManagerCtlMessage::TryGuardRequest(_) => write!(f, "TryGuardRequest"),
             ManagerCtlMessage::GuardDrop(id) => write!(f, "GuardDrop({id:?})"),
             ManagerCtlMessage::BackupPartialReset(_) => write!(f, "BackupPartialReset"),
+            ManagerCtlMessage::DiskPressureEvict(_) => write!(f, "DiskPressureEvict"),
         }
     }
 }
@@ -176,6 +185,16 @@ pub async fn backup_partial_reset(&self) -> anyhow::Result<Vec<String>> {
         }
     }
 
+    /// Request timeline manager to evict the timeline, bypassing the
+    /// anti-flapping delay, and wait for the number of bytes reclaimed.
+    pub async fn disk_pressure_evict(&self) -> anyhow::Result<u64> {
+        let (tx, rx) = oneshot::channel();
+        self.manager_tx
+            .send(ManagerCtlMessage::DiskPressureEvict(tx))?;
+        rx.await
+            .map_err(|e| anyhow::anyhow!("response read fail: {:?}", e))
+    }
+
     /// Must be called exactly once to bootstrap the manager.
     pub fn bootstrap_manager(
         &self,
@@ -207,18 +226,26 @@ pub(crate) struct Manager {
     pub(crate) num_computes_rx: tokio::sync::watch::Receiver<usize>,
     pub(crate) tli_broker_active: TimelineSetGuard,
     pub(crate) last_removed_segno: XLogSegNo,
+    pub(crate) last_compressed_segno: XLogSegNo,
+    pub(crate) wal_resets: u64,
     pub(crate) is_offloaded: bool,
 
     // background tasks
     pub(crate) backup_task: Option<WalBackupTaskHandle>,
     pub(crate) recovery_task: Option<JoinHandle<()>>,
     pub(crate) wal_removal_task: Option<JoinHandle<anyhow::Result<u64>>>,
+    pub(crate) wal_compression_task: Option<JoinHandle<anyhow::Result<u64>>>,
 
     // partial backup
     pub(crate) partial_backup_task:
         Option<(JoinHandle<Option<PartialRemoteSegment>>, CancellationToken)>,
     pub(crate) partial_backup_uploaded: Option<PartialRemoteSegment>,
 
+    // remote snapshot
+    pub(crate) remote_snapshot_task: Option<JoinHandle<anyhow::Result<Option<RemoteSnapshot>>>>,
+    pub(crate) remote_snapshot_uploaded: Option<RemoteSnapshot>,
+    pub(crate) next_remote_snapshot: Instant,
+
     // misc
     pub(crate) access_service: AccessService,
     pub(crate) global_rate_limiter: RateLimiter,
@@ -226,6 +253,8 @@ pub(crate) struct Manager {
     // Anti-flapping state: we evict timelines eagerly if they are inactive, but should not
     // evict them if they go inactive very soon after being restored.
     pub(crate) evict_not_before: Instant,
+    // Pending eviction request of the disk pressure eviction pass.
+    pub(crate) disk_pressure_evict_request: Option<oneshot::Sender<u64>>,
 }
 
 /// This task gets spawned alongside each timeline and is responsible for managing the timeline's
@@ -296,9 +325,39 @@ pub async fn main_task(
             mgr.set_status(Status::UpdateWalRemoval);
             mgr.update_wal_removal(&state_snapshot).await;
 
+            mgr.set_status(Status::UpdateWalCompression);
+            mgr.update_wal_compression(&state_snapshot);
+
             mgr.set_status(Status::UpdatePartialBackup);
             mgr.update_partial_backup(&state_snapshot).await;
 
+            mgr.set_status(Status::UpdateRemoteSnapshot);
+            mgr.update_remote_snapshot(&state_snapshot, &mut next_event);
+
+            let ready_for_eviction = mgr.ready_for_eviction(&next_event, &state_snapshot);
+            mgr.update_evictable_partial_size(ready_for_eviction);
+
+            if let Some(tx) = mgr.disk_pressure_evict_request.take() {
+                // The disk pressure eviction pass doesn't wait for evict_not_before
+                // and the rate limiter, it evicts timelines one at a time.
+                let mut reclaimed = 0;
+                if ready_for_eviction {
+                    mgr.set_status(Status::EvictTimeline);
+                    reclaimed = mgr.evict_timeline(true).await.unwrap_or(0);
+                }
+                let _ = tx.send(reclaimed);
+            }
+        }
+        if mgr.is_offloaded {
+            mgr.update_evictable_partial_size(false);
+        }
+        if let Some(tx) = mgr.disk_pressure_evict_request.take() {
+            // the timeline is offloaded, nothing to reclaim
+            let _ = tx.send(0);
+        }
+
+        // disk pressure eviction above might have offloaded the timeline
+        if !mgr.is_offloaded {
             let now = Instant::now();
             if mgr.evict_not_before > now {
                 // we should wait until evict_not_before
@@ -313,7 +372,7 @@ pub async fn main_task(
                 match mgr.global_rate_limiter.try_acquire_eviction() {
                     Some(_permit) => {
                         mgr.set_status(Status::EvictTimeline);
-                        if !mgr.evict_timeline().await {
+                        if mgr.evict_timeline(false).await.is_none() {
                             // eviction failed, try again later
                             mgr.evict_not_before =
                                 Instant::now() + rand_duration(&mgr.conf.eviction_min_resident);
@@ -356,11 +415,21 @@ pub async fn main_task(
                 mgr.wal_removal_task = None;
                 mgr.update_wal_removal_end(res);
             }
+            res = await_task_finish(mgr.wal_compression_task.as_mut()) => {
+                // WAL compression task finished
+                mgr.wal_compression_task = None;
+                mgr.update_wal_compression_end(res);
+            }
             res = await_task_finish(mgr.partial_backup_task.as_mut().map(|(handle, _)| handle)) => {
                 // partial backup task finished
                 mgr.partial_backup_task = None;
                 mgr.update_partial_backup_end(res);
             }
+            res = await_task_finish(mgr.remote_snapshot_task.as_mut()) => {
+                // remote snapshot task finished
+                mgr.remote_snapshot_task = None;
+                mgr.update_remote_snapshot_end(res);
+            }
 
             msg = manager_rx.recv() => {
                 mgr.set_status(Status::HandleMessage);
@@ -398,11 +467,21 @@ pub async fn main_task(
         }
     }
 
+    if let Some(remote_snapshot_task) = &mut mgr.remote_snapshot_task {
+        let res = remote_snapshot_task.await;
+        mgr.update_remote_snapshot_end(res);
+    }
+
     if let Some(wal_removal_task) = &mut mgr.wal_removal_task {
         let res = wal_removal_task.await;
         mgr.update_wal_removal_end(res);
     }
 
+    if let Some(wal_compression_task) = &mut mgr.wal_compression_task {
+        let res = wal_compression_task.await;
+        mgr.update_wal_compression_end(res);
+    }
+
     // If timeline is deleted while evicted decrement the gauge.
     if mgr.tli.is_cancelled() && mgr.is_offloaded {
         NUM_EVICTED_TIMELINES.dec();
@@ -429,17 +508,30 @@ async fn new(
             num_computes_rx: tli.get_walreceivers().get_num_rx(),
             tli_broker_active: broker_active_set.guard(tli.clone()),
             last_removed_segno: 0,
+            last_compressed_segno: 0,
+            wal_resets: 0,
             is_offloaded,
             backup_task: None,
             recovery_task: None,
             wal_removal_task: None,
+            wal_compression_task: None,
             partial_backup_task: None,
             partial_backup_uploaded,
+            remote_snapshot_task: None,
+            remote_snapshot_uploaded: None,
+            // to smooth out uploads spike after restart
+            next_remote_snapshot: Instant::now()
+                + conf
+                    .remote_snapshot_interval
+                    .as_ref()
+                    .map(rand_duration)
+                    .unwrap_or_default(),
             access_service: AccessService::new(manager_tx),
             tli,
             global_rate_limiter,
             // to smooth out evictions spike after restart
             evict_not_before: Instant::now() + rand_duration(&conf.eviction_min_resident),
+            disk_pressure_evict_request: None,
             conf,
         }
     }
@@ -474,13 +566,15 @@ async fn state_snapshot(&self) -> StateSnapshot {
         StateSnapshot::new(
             self.tli.read_shared_state().await,
             self.conf.heartbeat_timeout,
+            self.conf.my_id,
         )
     }
 
     /// Spawns/kills backup task and returns true if backup is required.
     async fn update_backup(&mut self, num_computes: usize, state: &StateSnapshot) -> bool {
-        let is_wal_backup_required =
-            wal_backup::is_wal_backup_required(self.wal_seg_size, num_computes, state);
+        // witness has nothing to upload
+        let is_wal_backup_required = !state.is_witness
+            && wal_backup::is_wal_backup_required(self.wal_seg_size, num_computes, state);
 
         if let Some(storage) = self.wal_backup.get_storage() {
             wal_backup::update_task(self, storage, is_wal_backup_required, state).await;
@@ -501,9 +595,12 @@ fn update_is_active(
         num_computes: usize,
         state: &StateSnapshot,
     ) {
-        let is_active = is_wal_backup_required
-            || num_computes > 0
-            || state.remote_consistent_lsn < state.commit_lsn;
+        // Witness is never advertised in the broker, so neither pageservers
+        // nor peers pick it as a WAL source or offloader.
+        let is_active = !state.is_witness
+            && (is_wal_backup_required
+                || num_computes > 0
+                || state.remote_consistent_lsn < state.commit_lsn);
 
         // update the broker timeline set
         if self.tli_broker_active.set(is_active) {
@@ -522,6 +619,20 @@ fn update_is_active(
             .store(is_active, std::sync::atomic::Ordering::Relaxed);
     }
 
+    /// Publish the size of the local partial segment, the only WAL left on
+    /// disk, if the timeline is ready for eviction. Disk pressure eviction
+    /// picks timelines by it.
+    fn update_evictable_partial_size(&self, ready_for_eviction: bool) {
+        let size = ready_for_eviction.then(|| {
+            let partial = self
+                .partial_backup_uploaded
+                .as_ref()
+                .expect("ready for eviction timeline has partial segment uploaded");
+            partial.flush_lsn.segment_offset(self.wal_seg_size) as u64
+        });
+        *self.tli.evictable_partial_size.lock().unwrap() = size;
+    }
+
     /// Save control file if needed. Returns Instant if we should persist the control file in the future.
     async fn update_control_file_save(
         &self,
@@ -555,8 +666,13 @@ async fn update_control_file_save(
 
     /// Spawns WAL removal task if needed.
     async fn update_wal_removal(&mut self, state: &StateSnapshot) {
-        if self.wal_removal_task.is_some() || state.wal_removal_on_hold {
-            // WAL removal is already in progress or hold off
+        if self.wal_removal_task.is_some()
+            || self.wal_compression_task.is_some()
+            || state.wal_removal_on_hold
+            || state.is_witness
+        {
+            // WAL removal is already in progress, hold off, or segments are being
+            // compressed; witness has no segments at all
             return;
         }
 
@@ -626,6 +742,70 @@ fn update_wal_removal_end(&mut self, res: Result<anyhow::Result<u64>, JoinError>
             .store(new_last_removed_segno, std::sync::atomic::Ordering::Relaxed);
     }
 
+    /// Spawns task compressing completed WAL segments on disk if needed.
+    fn update_wal_compression(&mut self, state: &StateSnapshot) {
+        if !self.conf.wal_compression
+            || self.wal_compression_task.is_some()
+            || self.wal_removal_task.is_some()
+            || state.is_witness
+        {
+            return;
+        }
+
+        // A reset decompresses the segment it truncates into, which must be
+        // compressed again once it is committed.
+        let wal_resets = self
+            .tli
+            .wal_resets
+            .load(std::sync::atomic::Ordering::Relaxed);
+        if wal_resets != self.wal_resets {
+            self.wal_resets = wal_resets;
+            self.last_compressed_segno = 0;
+        }
+
+        // Only segments entirely below the persisted commit_lsn are compressed:
+        // WAL is written above it, and after restart find_end_of_wal starts
+        // reading raw segments at it. A timeline reset may still truncate below
+        // it, which decompresses the segment it truncates into first.
+        let compression_horizon_segno = state
+            .cfile_commit_lsn
+            .segment_number(self.wal_seg_size)
+            .saturating_sub(1);
+        if compression_horizon_segno <= self.last_compressed_segno
+            || compression_horizon_segno <= self.last_removed_segno
+        {
+            return;
+        }
+
+        let Ok(timeline_gate_guard) = self.tli.gate.enter() else {
+            tracing::info!("Timeline shutdown, not spawning WAL compression task");
+            return;
+        };
+        let tli = self.tli.clone();
+        let wal_seg_size = self.wal_seg_size;
+        let no_sync = self.conf.no_sync;
+
+        self.wal_compression_task = Some(tokio::spawn(
+            async move {
+                let _timeline_gate_guard = timeline_gate_guard;
+
+                tli.compress_wal(wal_seg_size, compression_horizon_segno, no_sync)
+                    .await?;
+                Ok(compression_horizon_segno)
+            }
+            .instrument(info_span!("WAL compression", ttid=%self.tli.ttid)),
+        ));
+    }
+
+    /// Update the state after WAL compression task finished.
+    fn update_wal_compression_end(&mut self, res: Result<anyhow::Result<u64>, JoinError>) {
+        match res {
+            Ok(Ok(segno)) => self.last_compressed_segno = segno,
+            Ok(Err(e)) => warn!("WAL compression task failed: {:?}", e),
+            Err(e) => warn!("WAL compression task failed: {:?}", e),
+        }
+    }
+
     /// Spawns partial WAL backup task if needed.
     async fn update_partial_backup(&mut self, state: &StateSnapshot) {
         // check if WAL backup is enabled and should be started
@@ -633,8 +813,8 @@ async fn update_partial_backup(&mut self, state: &StateSnapshot) {
             return;
         };
 
-        if self.partial_backup_task.is_some() {
-            // partial backup is already running
+        if self.partial_backup_task.is_some() || state.is_witness {
+            // partial backup is already running, or there is no WAL to upload
             return;
         }
 
@@ -672,6 +852,52 @@ fn update_partial_backup_end(&mut self, res: Result<Option<PartialRemoteSegment>
         }
     }
 
+    /// Spawns task uploading timeline snapshot to remote storage if the
+    /// interval has passed and WAL was written since the last one.
+    fn update_remote_snapshot(&mut self, state: &StateSnapshot, next_event: &mut Option<Instant>) {
+        let Some(interval) = self.conf.remote_snapshot_interval else {
+            return;
+        };
+        let Some(storage) = self.wal_backup.get_storage() else {
+            return;
+        };
+        if self.remote_snapshot_task.is_some()
+            || state.is_witness
+            || !remote_snapshot::needs_uploading(state, &self.remote_snapshot_uploaded)
+        {
+            return;
+        }
+        if self.next_remote_snapshot > Instant::now() {
+            update_next_event(next_event, self.next_remote_snapshot);
+            return;
+        }
+
+        let Ok(resident) = self.wal_resident_timeline() else {
+            // Shutting down
+            return;
+        };
+        self.next_remote_snapshot = Instant::now() + interval;
+        self.remote_snapshot_task = Some(tokio::spawn(remote_snapshot::main_task(
+            resident,
+            self.conf.clone(),
+            self.global_rate_limiter.clone(),
+            storage,
+        )));
+    }
+
+    /// Update the state after remote snapshot task finished.
+    fn update_remote_snapshot_end(
+        &mut self,
+        res: Result<anyhow::Result<Option<RemoteSnapshot>>, JoinError>,
+    ) {
+        match res {
+            Ok(Ok(Some(snapshot))) => self.remote_snapshot_uploaded = Some(snapshot),
+            Ok(Ok(None)) => {}
+            Ok(Err(e)) => warn!("remote snapshot task failed: {:?}", e),
+            Err(e) => warn!("remote snapshot task failed: {:?}", e),
+        }
+    }
+
     /// Reset partial backup state and remove its remote storage data. Since it
     /// might concurrently uploading something, cancel the task first.
     async fn backup_partial_reset(&mut self) -> anyhow::Result<Vec<String>> {
@@ -751,6 +977,12 @@ async fn handle_message(&mut self, msg: Option<ManagerCtlMessage>) {
             Some(ManagerCtlMessage::GuardDrop(guard_id)) => {
                 self.access_service.drop_guard(guard_id);
             }
+            Some(ManagerCtlMessage::DiskPressureEvict(tx)) => {
+                // handled in the main loop, where the state snapshot is available
+                if let Some(prev) = self.disk_pressure_evict_request.replace(tx) {
+                    let _ = prev.send(0);
+                }
+            }
             Some(ManagerCtlMessage::BackupPartialReset(tx)) => {
                 info!("resetting uploaded partial backup state");
                 let res = self.backup_partial_reset().await;
@@ -810,7 +1042,9 @@ pub enum Status {
     UpdateBackup,
     UpdateControlFile,
     UpdateWalRemoval,
+    UpdateWalCompression,
     UpdatePartialBackup,
+    UpdateRemoteSnapshot,
     EvictTimeline,
     Wait,
     HandleMessage,
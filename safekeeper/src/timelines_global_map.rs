@@ -157,7 +157,7 @@ pub fn new(conf: Arc<SafeKeeperConf>, wal_backup: Arc<WalBackup>) -> Self {
                 tenant_tombstones: HashMap::new(),
                 conf,
                 broker_active_set: Arc::new(TimelinesSet::default()),
-                global_rate_limiter: RateLimiter::new(1, 1),
+                global_rate_limiter: RateLimiter::new(1, 1, Default::default(), None),
                 wal_backup,
             }),
         }
@@ -172,6 +172,8 @@ pub async fn init(&self) -> Result<()> {
             state.global_rate_limiter = RateLimiter::new(
                 state.conf.partial_backup_concurrency,
                 DEFAULT_EVICTION_CONCURRENCY,
+                state.conf.wal_ingest_limits.clone(),
+                state.conf.wal_fsync_concurrency,
             );
 
             // Iterate through all directories and load tenants for all directories
@@ -284,6 +286,10 @@ pub fn get_wal_backup(&self) -> Arc<WalBackup> {
         self.state.lock().unwrap().wal_backup.clone()
     }
 
+    pub fn get_global_rate_limiter(&self) -> RateLimiter {
+        self.state.lock().unwrap().global_rate_limiter.clone()
+    }
+
     /// Create a new timeline with the given id. If the timeline already exists, returns
     /// an existing timeline.
     pub(crate) async fn create(
@@ -679,6 +685,8 @@ pub fn housekeeping(&self, tombstone_ttl: &Duration) {
         state
             .tenant_tombstones
             .retain(|_, v| now.duration_since(*v) < *tombstone_ttl);
+
+        state.global_rate_limiter.wal_ingest_housekeeping();
     }
 
     pub fn get_sk_id(&self) -> NodeId {
@@ -742,7 +750,13 @@ pub async fn validate_temp_timeline(
         }
     }
 
-    let wal_store = wal_storage::PhysicalStorage::new(&ttid, path, &control_store, conf.no_sync)?;
+    let wal_store = wal_storage::PhysicalStorage::new(
+        &ttid,
+        path,
+        &control_store,
+        conf.no_sync,
+        control_store.mconf.is_witness(conf.my_id),
+    )?;
 
     let commit_lsn = control_store.commit_lsn;
     let flush_lsn = wal_store.flush_lsn();
@@ -0,0 +1,280 @@
+//! Mirror of completed WAL segments in a layout consumable by vanilla Postgres
+//! `restore_command`.
+//!
+//! When `--wal-archive-prefix` is set, the WAL backup task additionally uploads
+//! every completed segment to
+//!
+//!   `<prefix>/<tenant_id>/<timeline_id>/<segment_file>[.zst]`
+//!
+//! along with a `<tli>.history` file, so standard restore tooling (and the
+//! `wal_restore` binary shipped with safekeeper) can do PITR without the
+//! pageserver. Only completed segments are archived; the partial segment at the
+//! end of WAL is not.
+//!
+//! With `--wal-archive-compress`, segments are stored with a `.zst` suffix,
+//! which a plain copying `restore_command` can't use: `wal_restore` is needed
+//! to decompress them. Without it, objects are plain segment files.
+
+use anyhow::{Context, Result};
+use async_compression::tokio::bufread::{ZstdDecoder, ZstdEncoder};
+use bytes::Bytes;
+use camino::{Utf8Path, Utf8PathBuf};
+use postgres_ffi::{PG_TLI, XLogFileName, XLogSegNo};
+use remote_storage::{DownloadError, DownloadOpts, GenericRemoteStorage, RemotePath};
+use tokio::fs::File;
+use tokio::io::{AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio_util::sync::CancellationToken;
+use utils::id::TenantTimelineId;
+
+use crate::SafeKeeperConf;
+use crate::metrics::ARCHIVED_SEGMENTS;
+use crate::wal_backup::upload_bytes;
+pub use crate::wal_storage::COMPRESSED_SUFFIX;
+use crate::wal_storage::{SegmentFormat, open_wal_file};
+
+/// Name of the timeline history file in the archive. Neon timelines always use
+/// Postgres timeline 1, so the file carries no switch points, only a comment
+/// identifying the timeline.
+pub fn history_file_name() -> String {
+    format!("{PG_TLI:08X}.history")
+}
+
+/// Path of a timeline's directory in the archive.
+pub fn archive_timeline_path(prefix: &Utf8Path, ttid: &TenantTimelineId) -> Result<RemotePath> {
+    RemotePath::new(
+        &prefix
+            .join(ttid.tenant_id.to_string())
+            .join(ttid.timeline_id.to_string()),
+    )
+}
+
+/// Per timeline archive target of the WAL backup task.
+pub(crate) struct WalArchive {
+    timeline_path: RemotePath,
+    compress: bool,
+    ttid: TenantTimelineId,
+}
+
+impl WalArchive {
+    /// Returns None if archiving is disabled.
+    pub(crate) fn new(conf: &SafeKeeperConf, ttid: &TenantTimelineId) -> Result<Option<Self>> {
+        let Some(prefix) = conf.wal_archive_prefix.as_ref() else {
+            return Ok(None);
+        };
+        Ok(Some(Self {
+            timeline_path: archive_timeline_path(prefix, ttid)?,
+            compress: conf.wal_archive_compress,
+            ttid: *ttid,
+        }))
+    }
+
+    /// Upload the history file. Idempotent.
+    pub(crate) async fn upload_history(&self, storage: &GenericRemoteStorage) -> Result<()> {
+        let content = format!(
+            "# neon safekeeper WAL archive\n# tenant {} timeline {}\n",
+            self.ttid.tenant_id, self.ttid.timeline_id
+        );
+        let path = self.timeline_path.join(history_file_name());
+        upload_bytes(storage, Bytes::from(content), &path).await
+    }
+
+    /// Upload a completed segment from local disk, compressing it if
+    /// configured. A segment already compressed on disk is uploaded as is or
+    /// decompressed, depending on the archive setting.
+    pub(crate) async fn upload_segment(
+        &self,
+        storage: &GenericRemoteStorage,
+        timeline_dir: &Utf8Path,
+        segno: XLogSegNo,
+        wal_seg_size: usize,
+    ) -> Result<()> {
+        let segment_name = XLogFileName(PG_TLI, segno, wal_seg_size);
+        let Some((file, format)) = open_wal_file(timeline_dir, segno, wal_seg_size).await? else {
+            anyhow::bail!("segment {segment_name} not found for wal archive");
+        };
+        let mut buf = Vec::with_capacity(wal_seg_size);
+        let on_disk_compressed = format == SegmentFormat::Compressed;
+        match (on_disk_compressed, self.compress) {
+            (true, true) => {
+                BufReader::new(file).read_to_end(&mut buf).await?;
+            }
+            (true, false) => {
+                ZstdDecoder::new(BufReader::new(file))
+                    .read_to_end(&mut buf)
+                    .await?;
+            }
+            (false, true) => {
+                ZstdEncoder::new(BufReader::new(file.take(wal_seg_size as u64)))
+                    .read_to_end(&mut buf)
+                    .await?;
+            }
+            (false, false) => {
+                file.take(wal_seg_size as u64).read_to_end(&mut buf).await?;
+            }
+        }
+        let object_name = if self.compress {
+            format!("{segment_name}{COMPRESSED_SUFFIX}")
+        } else {
+            segment_name
+        };
+        upload_bytes(
+            storage,
+            Bytes::from(buf),
+            &self.timeline_path.join(object_name),
+        )
+        .await?;
+        ARCHIVED_SEGMENTS.inc();
+        Ok(())
+    }
+}
+
+/// Fetch `file_name` (as requested by Postgres via `%f`) from the archive
+/// timeline directory into `target`, decompressing it if it was archived
+/// compressed. The target is written atomically.
+///
+/// Returns false if the file is not in the archive, which `restore_command`
+/// must report with a non-zero exit code.
+pub async fn restore_file(
+    storage: &GenericRemoteStorage,
+    archive_timeline_path: &RemotePath,
+    file_name: &str,
+    target: &Utf8Path,
+    cancel: &CancellationToken,
+) -> Result<bool> {
+    let opts = DownloadOpts::default();
+    let candidates = [
+        (format!("{file_name}{COMPRESSED_SUFFIX}"), true),
+        (file_name.to_owned(), false),
+    ];
+    for (object_name, compressed) in candidates {
+        let path = archive_timeline_path.join(&object_name);
+        let download = match storage.download(&path, &opts, cancel).await {
+            Ok(download) => download,
+            Err(DownloadError::NotFound) => continue,
+            Err(e) => return Err(e).with_context(|| format!("downloading {path}")),
+        };
+        let mut reader =
+            BufReader::new(tokio_util::io::StreamReader::new(download.download_stream));
+
+        let tmp_path = Utf8PathBuf::from(format!("{target}.partial"));
+        let mut tmp = File::create(&tmp_path)
+            .await
+            .with_context(|| format!("creating {tmp_path}"))?;
+        if compressed {
+            tokio::io::copy(&mut ZstdDecoder::new(reader), &mut tmp).await?;
+        } else {
+            tokio::io::copy(&mut reader, &mut tmp).await?;
+        }
+        tmp.flush().await?;
+        tmp.sync_all().await?;
+        drop(tmp);
+        tokio::fs::rename(&tmp_path, target)
+            .await
+            .with_context(|| format!("renaming {tmp_path} to {target}"))?;
+        return Ok(true);
+    }
+    Ok(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use postgres_ffi::WAL_SEGMENT_SIZE;
+    use remote_storage::LocalFs;
+    use utils::id::{TenantId, TimelineId};
+
+    use super::*;
+    use crate::wal_storage::compress_segments_on_disk;
+
+    async fn roundtrip(compress: bool, compressed_on_disk: bool) {
+        let tempdir = camino_tempfile::tempdir().unwrap();
+        let storage = GenericRemoteStorage::LocalFs(
+            LocalFs::new(tempdir.path().join("remote"), Duration::from_secs(10)).unwrap(),
+        );
+        let ttid = TenantTimelineId::new(TenantId::generate(), TimelineId::generate());
+        let mut conf = SafeKeeperConf::dummy();
+        conf.wal_archive_prefix = Some(Utf8PathBuf::from("wal_archive"));
+        conf.wal_archive_compress = compress;
+        let archive = WalArchive::new(&conf, &ttid).unwrap().unwrap();
+
+        let segment_name = "000000010000000000000001";
+        let segment: Vec<u8> = (0..64 * 1024).map(|i| (i % 7) as u8).collect();
+        let local_dir = tempdir.path().join("timeline");
+        tokio::fs::create_dir(&local_dir).await.unwrap();
+        tokio::fs::write(local_dir.join(segment_name), &segment)
+            .await
+            .unwrap();
+        if compressed_on_disk {
+            compress_segments_on_disk(&local_dir, WAL_SEGMENT_SIZE, 1, true)
+                .await
+                .unwrap();
+            assert!(!local_dir.join(segment_name).exists());
+        }
+
+        archive.upload_history(&storage).await.unwrap();
+        archive
+            .upload_segment(&storage, &local_dir, 1, WAL_SEGMENT_SIZE)
+            .await
+            .unwrap();
+
+        let timeline_path = archive_timeline_path(Utf8Path::new("wal_archive"), &ttid).unwrap();
+        let cancel = CancellationToken::new();
+        let restored = tempdir.path().join("RECOVERYXLOG");
+        assert!(
+            restore_file(&storage, &timeline_path, segment_name, &restored, &cancel)
+                .await
+                .unwrap()
+        );
+        assert_eq!(tokio::fs::read(&restored).await.unwrap(), segment);
+
+        // Uncompressed objects can be restored by a plain copy too.
+        let object = tempdir
+            .path()
+            .join("remote")
+            .join(timeline_path.get_path())
+            .join(segment_name);
+        if compress {
+            assert!(!object.exists());
+        } else {
+            assert_eq!(tokio::fs::read(&object).await.unwrap(), segment);
+        }
+
+        let history = tempdir.path().join("RECOVERYHISTORY");
+        assert!(
+            restore_file(
+                &storage,
+                &timeline_path,
+                &history_file_name(),
+                &history,
+                &cancel
+            )
+            .await
+            .unwrap()
+        );
+
+        assert!(
+            !restore_file(
+                &storage,
+                &timeline_path,
+                "000000010000000000000002",
+                &restored,
+                &cancel
+            )
+            .await
+            .unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn archive_and_restore() {
+        roundtrip(false, false).await;
+        roundtrip(false, true).await;
+    }
+
+    #[tokio::test]
+    async fn archive_and_restore_compressed() {
+        roundtrip(true, false).await;
+        roundtrip(true, true).await;
+    }
+}
@@ -6,6 +6,8 @@
 use std::time::Duration;
 
 use anyhow::{Context, Result};
+use async_compression::tokio::bufread::ZstdEncoder;
+use bytes::Bytes;
 use camino::{Utf8Path, Utf8PathBuf};
 use futures::StreamExt;
 use futures::stream::{self, FuturesOrdered};
@@ -16,6 +18,7 @@
 };
 use safekeeper_api::models::PeerInfo;
 use tokio::fs::File;
+use tokio::io::{AsyncReadExt, BufReader};
 use tokio::select;
 use tokio::sync::mpsc::{self, Receiver, Sender};
 use tokio::sync::watch;
@@ -31,6 +34,8 @@
 };
 use crate::timeline::WalResidentTimeline;
 use crate::timeline_manager::{Manager, StateSnapshot};
+use crate::wal_archive::WalArchive;
+use crate::wal_storage::{COMPRESSED_SUFFIX, decompress_segment};
 use crate::{SafeKeeperConf, WAL_BACKUP_RUNTIME};
 
 const UPLOAD_FAILURE_RETRY_MIN_MS: u64 = 10;
@@ -91,9 +96,19 @@ pub(crate) async fn update_task(
                 return;
             };
 
+            let archive = match WalArchive::new(&mgr.conf, &mgr.tli.ttid) {
+                Ok(archive) => archive,
+                Err(e) => {
+                    error!("invalid WAL archive configuration: {e:#}");
+                    None
+                }
+            };
+
             let async_task = backup_task_main(
                 resident,
                 storage,
+                archive,
+                mgr.conf.wal_compression,
                 mgr.conf.backup_parallel_jobs,
                 shutdown_rx,
             );
@@ -282,6 +297,9 @@ struct WalBackupTask {
     parallel_jobs: usize,
     commit_lsn_watch_rx: watch::Receiver<Lsn>,
     storage: Arc<GenericRemoteStorage>,
+    archive: Option<WalArchive>,
+    archive_history_uploaded: bool,
+    compress: bool,
 }
 
 /// Offload single timeline.
@@ -289,6 +307,8 @@ struct WalBackupTask {
 async fn backup_task_main(
     tli: WalResidentTimeline,
     storage: Arc<GenericRemoteStorage>,
+    archive: Option<WalArchive>,
+    compress: bool,
     parallel_jobs: usize,
     mut shutdown_rx: Receiver<()>,
 ) {
@@ -303,6 +323,9 @@ async fn backup_task_main(
         timeline: tli,
         parallel_jobs,
         storage,
+        archive,
+        archive_history_uploaded: false,
+        compress,
     };
 
     // task is spinned up only when wal_seg_size already initialized
@@ -371,9 +394,24 @@ async fn run(&mut self) {
                 continue;
             }
 
+            if let Some(archive) = self
+                .archive
+                .as_ref()
+                .filter(|_| !self.archive_history_uploaded)
+            {
+                if let Err(e) = archive.upload_history(&self.storage).await {
+                    error!("failed to upload WAL archive history file: {:?}", e);
+                    retry_attempt = retry_attempt.saturating_add(1);
+                    continue;
+                }
+                self.archive_history_uploaded = true;
+            }
+
             match backup_lsn_range(
                 &self.timeline,
                 self.storage.clone(),
+                self.archive.as_ref(),
+                self.compress,
                 &mut backup_lsn,
                 commit_lsn,
                 self.wal_seg_size,
@@ -404,6 +442,8 @@ async fn run(&mut self) {
 async fn backup_lsn_range(
     timeline: &WalResidentTimeline,
     storage: Arc<GenericRemoteStorage>,
+    archive: Option<&WalArchive>,
+    compress: bool,
     backup_lsn: &mut Lsn,
     end_lsn: Lsn,
     wal_seg_size: usize,
@@ -438,6 +478,8 @@ async fn backup_lsn_range(
             Some(s) => {
                 uploads.push_back(backup_single_segment(
                     &storage,
+                    archive,
+                    compress,
                     s,
                     timeline_dir,
                     remote_timeline_path,
@@ -478,20 +520,22 @@ async fn backup_lsn_range(
 
 async fn backup_single_segment(
     storage: &GenericRemoteStorage,
+    archive: Option<&WalArchive>,
+    compress: bool,
     seg: &Segment,
     timeline_dir: &Utf8Path,
     remote_timeline_path: &RemotePath,
 ) -> Result<Segment> {
     let segment_file_path = seg.file_path(timeline_dir)?;
-    let remote_segment_path = seg.remote_path(remote_timeline_path);
 
-    let res = backup_object(
-        storage,
-        &segment_file_path,
-        &remote_segment_path,
-        seg.size(),
-    )
-    .await;
+    let mut res =
+        backup_segment_file(storage, compress, seg, timeline_dir, remote_timeline_path).await;
+    if let (Ok(()), Some(archive)) = (&res, archive) {
+        res = archive
+            .upload_segment(storage, timeline_dir, seg.seg_no, seg.size())
+            .await
+            .context("archiving segment");
+    }
     if res.is_ok() {
         BACKED_UP_SEGMENTS.inc();
     } else {
@@ -503,6 +547,58 @@ async fn backup_single_segment(
     Ok(*seg)
 }
 
+/// Upload a completed segment, which may be stored raw or compressed on disk.
+/// A segment compressed on disk is uploaded as is; a raw one is compressed
+/// before upload if `compress` is set.
+async fn backup_segment_file(
+    storage: &GenericRemoteStorage,
+    compress: bool,
+    seg: &Segment,
+    timeline_dir: &Utf8Path,
+    remote_timeline_path: &RemotePath,
+) -> Result<()> {
+    let segment_file_path = seg.file_path(timeline_dir)?;
+    match File::open(&segment_file_path).await {
+        Ok(file) if compress => {
+            let mut compressed = Vec::new();
+            ZstdEncoder::new(BufReader::new(file.take(seg.size() as u64)))
+                .read_to_end(&mut compressed)
+                .await?;
+            upload_bytes(
+                storage,
+                Bytes::from(compressed),
+                &seg.remote_compressed_path(remote_timeline_path),
+            )
+            .await
+        }
+        Ok(_) => {
+            backup_object(
+                storage,
+                &segment_file_path,
+                &seg.remote_path(remote_timeline_path),
+                seg.size(),
+            )
+            .await
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            let compressed_file_path = seg.compressed_file_path(timeline_dir);
+            let size = tokio::fs::metadata(&compressed_file_path)
+                .await
+                .with_context(|| format!("Failed to stat {compressed_file_path} for wal backup"))?
+                .len();
+            backup_object(
+                storage,
+                &compressed_file_path,
+                &seg.remote_compressed_path(remote_timeline_path),
+                size as usize,
+            )
+            .await
+        }
+        Err(e) => Err(e)
+            .with_context(|| format!("Failed to open file {segment_file_path:?} for wal backup")),
+    }
+}
+
 #[derive(Debug, Copy, Clone)]
 pub struct Segment {
     seg_no: XLogSegNo,
@@ -531,6 +627,14 @@ pub fn remote_path(self, remote_timeline_path: &RemotePath) -> RemotePath {
         remote_timeline_path.join(self.object_name())
     }
 
+    pub fn compressed_file_path(self, timeline_dir: &Utf8Path) -> Utf8PathBuf {
+        timeline_dir.join(self.object_name() + COMPRESSED_SUFFIX)
+    }
+
+    pub fn remote_compressed_path(self, remote_timeline_path: &RemotePath) -> RemotePath {
+        remote_timeline_path.join(self.object_name() + COMPRESSED_SUFFIX)
+    }
+
     pub fn size(self) -> usize {
         (u64::from(self.end_lsn) - u64::from(self.start_lsn)) as usize
     }
@@ -569,6 +673,18 @@ async fn backup_object(
         .await
 }
 
+pub(crate) async fn upload_bytes(
+    storage: &GenericRemoteStorage,
+    data: Bytes,
+    to: &RemotePath,
+) -> Result<()> {
+    let size = data.len();
+    let stream = stream::once(futures::future::ready(Ok::<_, std::io::Error>(data)));
+    storage
+        .upload_storage_object(stream, size, to, &CancellationToken::new())
+        .await
+}
+
 pub(crate) async fn backup_partial_segment(
     storage: &GenericRemoteStorage,
     source_file: &Utf8Path,
@@ -648,6 +764,28 @@ pub async fn read_object(
     Ok(Box::pin(reader))
 }
 
+/// Open a completed segment in remote storage at `offset`, whether it was
+/// uploaded raw or compressed.
+pub async fn read_segment_object(
+    storage: &GenericRemoteStorage,
+    remote_timeline_path: &RemotePath,
+    segment_name: &str,
+    offset: u64,
+) -> Result<Pin<Box<dyn tokio::io::AsyncRead + Send + Sync>>> {
+    let raw_path = remote_timeline_path.join(segment_name);
+    match read_object(storage, &raw_path, offset).await {
+        Err(e)
+            if matches!(
+                e.downcast_ref::<DownloadError>(),
+                Some(DownloadError::NotFound)
+            ) => {}
+        res => return res,
+    }
+    let compressed_path = remote_timeline_path.join(format!("{segment_name}{COMPRESSED_SUFFIX}"));
+    let reader = read_object(storage, &compressed_path, 0).await?;
+    decompress_segment(reader, offset).await
+}
+
 /// Delete WAL files for the given timeline. Remote storage must be configured
 /// when called.
 pub async fn delete_timeline(
@@ -737,24 +875,15 @@ pub async fn copy_s3_segments(
     from_segment: XLogSegNo,
     to_segment: XLogSegNo,
 ) -> Result<()> {
+    let remote_src_path = &remote_timeline_path(src_ttid)?;
     let remote_dst_path = remote_timeline_path(dst_ttid)?;
 
     let cancel = CancellationToken::new();
 
-    let files = storage
-        .list(
-            Some(&remote_dst_path),
-            ListingMode::NoDelimiter,
-            None,
-            &cancel,
-        )
-        .await?
-        .keys;
-
-    let uploaded_segments = &files
-        .iter()
-        .filter_map(|o| o.key.object_name().map(ToOwned::to_owned))
-        .collect::<HashSet<_>>();
+    let uploaded_segments = &list_object_names(storage, &remote_dst_path, &cancel).await?;
+    // Segments may be stored compressed; they are copied in whatever form the
+    // source has them.
+    let source_segments = &list_object_names(storage, remote_src_path, &cancel).await?;
 
     info!(
         "these segments have already been uploaded: {:?}",
@@ -765,7 +894,10 @@ pub async fn copy_s3_segments(
     // Copying multiple segments async.
     let mut copy_stream = stream::iter(from_segment..to_segment)
         .map(|segno| {
-            let segment_name = XLogFileName(PG_TLI, segno, wal_seg_size);
+            let mut segment_name = XLogFileName(PG_TLI, segno, wal_seg_size);
+            if !source_segments.contains(&segment_name) {
+                segment_name.push_str(COMPRESSED_SUFFIX);
+            }
             let remote_dst_path = remote_dst_path.clone();
             let cancel = cancel.clone();
 
@@ -778,7 +910,7 @@ pub async fn copy_s3_segments(
                     info!("copying segment {} {}", segno, segment_name);
                 }
 
-                let from = remote_timeline_path(src_ttid)?.join(&segment_name);
+                let from = remote_src_path.join(&segment_name);
                 let to = remote_dst_path.join(&segment_name);
 
                 // Retry logic: retry up to 10 times with 1 second delay
@@ -827,6 +959,22 @@ pub async fn copy_s3_segments(
     Ok(())
 }
 
+async fn list_object_names(
+    storage: &GenericRemoteStorage,
+    path: &RemotePath,
+    cancel: &CancellationToken,
+) -> Result<HashSet<String>> {
+    let files = storage
+        .list(Some(path), ListingMode::NoDelimiter, None, cancel)
+        .await?
+        .keys;
+
+    Ok(files
+        .iter()
+        .filter_map(|o| o.key.object_name().map(ToOwned::to_owned))
+        .collect())
+}
+
 /// Get S3 (remote_storage) prefix path used for timeline files.
 pub fn remote_timeline_path(ttid: &TenantTimelineId) -> Result<RemotePath> {
     RemotePath::new(&Utf8Path::new(&ttid.tenant_id.to_string()).join(ttid.timeline_id.to_string()))
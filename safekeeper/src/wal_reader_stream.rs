@@ -228,12 +228,15 @@ mod tests {
     use std::str::FromStr;
 
     use futures::StreamExt;
-    use postgres_ffi::MAX_SEND_SIZE;
+    use postgres_ffi::{MAX_SEND_SIZE, WAL_SEGMENT_SIZE};
     use utils::id::{NodeId, TenantTimelineId};
     use utils::lsn::Lsn;
 
+    use crate::send_wal::EndWatch;
     use crate::test_utils::Env;
+    use crate::timeline::WalResidentTimeline;
     use crate::wal_reader_stream::StreamingWalReader;
+    use crate::wal_storage::{compress_segments_on_disk, wal_file_paths};
 
     #[tokio::test]
     async fn test_streaming_wal_reader_reset() {
@@ -296,4 +299,62 @@ async fn test_streaming_wal_reader_reset() {
 
         assert_eq!(before_reset, after_reset);
     }
+
+    async fn read_all(
+        tli: WalResidentTimeline,
+        start_lsn: Lsn,
+        end_pos: Lsn,
+        end_watch: EndWatch,
+    ) -> Vec<u8> {
+        let mut streaming_wal_reader =
+            StreamingWalReader::new(tli, None, start_lsn, end_pos, end_watch, MAX_SEND_SIZE);
+        let mut wal = Vec::new();
+        while let Some(wor) = streaming_wal_reader.next().await {
+            let batch = wor.get_wal().unwrap().unwrap();
+            let stop = batch.available_wal_end_lsn == batch.wal_end_lsn;
+            wal.extend_from_slice(&batch.wal);
+            if stop {
+                break;
+            }
+        }
+        wal
+    }
+
+    #[tokio::test]
+    async fn test_streaming_wal_reader_compressed_segment() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        const SIZE: usize = 8 * 1024;
+        // Enough to complete the first segment.
+        const MSG_COUNT: usize = 2200;
+
+        let start_lsn = Lsn::from_str("0/149FD18").unwrap();
+        let env = Env::new(false).unwrap();
+        let tli = env
+            .make_timeline(NodeId(1), TenantTimelineId::generate(), start_lsn)
+            .await
+            .unwrap();
+
+        let resident_tli = tli.wal_residence_guard().await.unwrap();
+        let resident_tli2 = tli.wal_residence_guard().await.unwrap();
+        let end_watch = Env::write_wal(tli, start_lsn, SIZE, MSG_COUNT, c"neon-file:", None)
+            .await
+            .unwrap();
+        let end_pos = end_watch.get();
+        let first_segno = start_lsn.segment_number(WAL_SEGMENT_SIZE);
+        assert!(end_pos.segment_number(WAL_SEGMENT_SIZE) > first_segno);
+
+        let timeline_dir = resident_tli.get_timeline_dir();
+        let raw = read_all(resident_tli, start_lsn, end_pos, end_watch.clone()).await;
+
+        compress_segments_on_disk(&timeline_dir, WAL_SEGMENT_SIZE, first_segno, true)
+            .await
+            .unwrap();
+        let (raw_path, _) = wal_file_paths(&timeline_dir, first_segno, WAL_SEGMENT_SIZE);
+        assert!(!raw_path.exists());
+
+        let decompressed = read_all(resident_tli2, start_lsn, end_pos, end_watch).await;
+        assert_eq!(raw.len() as u64, end_pos.0 - start_lsn.0);
+        assert_eq!(raw, decompressed);
+    }
 }
@@ -6,13 +6,29 @@
 //! - 000000010000000000000002.partial
 //!
 //! Note that last file has `.partial` suffix, that's different from postgres.
+//!
+//! With `--wal-compression`, completed segments below the persisted commit_lsn
+//! are compressed with zstd in the background and stored as e.g.
+//! - 000000010000000000000001.zst
+//!
+//! Readers handle both forms transparently. Segments at or above commit_lsn are
+//! never compressed, so writing and `find_end_of_wal` only ever deal with raw
+//! files. Truncation may go below commit_lsn (timeline reset): the segment it
+//! truncates into is decompressed back to a raw file first.
+//!
+//! A witness member doesn't store WAL at all: records are only decoded to find
+//! their boundaries, and the flushed record LSN is durably kept in the
+//! `witness_lsn` file instead of segments.
 
 use std::cmp::{max, min};
+use std::ffi::OsStr;
 use std::future::Future;
 use std::io::{ErrorKind, SeekFrom};
 use std::pin::Pin;
+use std::task::{Context as TaskContext, Poll};
 
 use anyhow::{Context, Result, bail};
+use async_compression::tokio::bufread::{ZstdDecoder, ZstdEncoder};
 use bytes::Bytes;
 use camino::{Utf8Path, Utf8PathBuf};
 use futures::future::BoxFuture;
@@ -23,19 +39,28 @@
 use pq_proto::SystemId;
 use remote_storage::RemotePath;
 use std::sync::Arc;
+use sync_wrapper::SyncWrapper;
 use tokio::fs::{self, File, OpenOptions, remove_file};
-use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeekExt, AsyncWriteExt, BufReader, ReadBuf};
 use tracing::*;
 use utils::crashsafe::durable_rename;
 use utils::id::TenantTimelineId;
 use utils::lsn::Lsn;
 
+use crate::group_commit::GROUP_COMMIT;
 use crate::metrics::{
-    REMOVED_WAL_SEGMENTS, WAL_DISK_IO_ERRORS, WAL_STORAGE_OPERATION_SECONDS, WalStorageMetrics,
-    time_io_closure,
+    COMPRESSED_WAL_SEGMENTS, REMOVED_WAL_SEGMENTS, WAL_DISK_IO_ERRORS,
+    WAL_STORAGE_OPERATION_SECONDS, WalStorageMetrics, time_io_closure,
 };
 use crate::state::TimelinePersistentState;
-use crate::wal_backup::{WalBackup, read_object, remote_timeline_path};
+use crate::wal_backup::{WalBackup, read_segment_object, remote_timeline_path};
+
+/// Suffix of zstd compressed WAL segments, both on disk and in remote storage.
+pub const COMPRESSED_SUFFIX: &str = ".zst";
+
+/// File in the timeline directory holding flush_lsn of a witness member.
+pub const WITNESS_LSN_FILE_NAME: &str = "witness_lsn";
+const WITNESS_LSN_FILE_SIZE: usize = 8 + 4; // lsn + crc32c
 
 pub trait Storage {
     // Last written LSN.
@@ -92,6 +117,10 @@ pub struct PhysicalStorage {
     /// Disables fsync if true.
     no_sync: bool,
 
+    /// If true, this safekeeper is a witness of the timeline: WAL is decoded
+    /// to track record boundaries, but only the LSN is persisted.
+    witness: bool,
+
     /// Size of WAL segment in bytes.
     wal_seg_size: usize,
     pg_version: PgVersionId,
@@ -137,6 +166,10 @@ pub struct PhysicalStorage {
     /// - doesn't point to the end of the segment
     file: Option<File>,
 
+    /// Cached open witness LSN file, if this is a witness. It has constant
+    /// size and is overwritten in place.
+    witness_file: Option<File>,
+
     /// When true, WAL truncation potentially has been interrupted and we need
     /// to finish it before allowing WAL writes; see truncate_wal for details.
     /// In this case [`write_lsn`] can be less than actually written WAL on
@@ -165,11 +198,15 @@ pub struct PhysicalStorage {
 impl PhysicalStorage {
     /// Create new storage. If commit_lsn is not zero, flush_lsn is tried to be restored from
     /// the disk. Otherwise, all LSNs are set to zero.
+    ///
+    /// `witness` must be true if this safekeeper is a witness member of the
+    /// timeline; then flush_lsn is restored from the witness LSN file.
     pub fn new(
         ttid: &TenantTimelineId,
         timeline_dir: &Utf8Path,
         state: &TimelinePersistentState,
         no_sync: bool,
+        witness: bool,
     ) -> Result<PhysicalStorage> {
         let wal_seg_size = state.server.wal_seg_size as usize;
 
@@ -179,7 +216,9 @@ pub fn new(
         // NB: find_end_of_wal MUST be backwards compatible with the previously
         // written WAL. If find_end_of_wal fails to read any WAL written by an
         // older version of the code, we could lose data forever.
-        let write_lsn = if state.commit_lsn == Lsn(0) {
+        let write_lsn = if witness {
+            max(read_witness_lsn(timeline_dir)?, state.commit_lsn)
+        } else if state.commit_lsn == Lsn(0) {
             Lsn(0)
         } else {
             let version = PgMajorVersion::try_from(state.server.pg_version).unwrap();
@@ -223,6 +262,8 @@ pub fn new(
             metrics: WalStorageMetrics::default(),
             timeline_dir: timeline_dir.to_path_buf(),
             no_sync,
+            witness,
+            witness_file: None,
             wal_seg_size,
             pg_version: state.server.pg_version,
             system_id: state.server.system_id,
@@ -239,6 +280,51 @@ pub fn new(
         })
     }
 
+    /// Returns true if WAL is not stored, see [`PhysicalStorage::new`].
+    pub fn is_witness(&self) -> bool {
+        self.witness
+    }
+
+    /// Durably store `lsn` as the witness flush position. The file is created
+    /// once and then overwritten in place, so this costs a single fdatasync.
+    /// Like pg_control in postgres, we rely on writes of such a small record
+    /// being atomic; crc detects if they are not.
+    async fn persist_witness_lsn(&mut self, lsn: Lsn) -> Result<()> {
+        let mut buf = Vec::with_capacity(WITNESS_LSN_FILE_SIZE);
+        buf.extend_from_slice(&lsn.0.to_le_bytes());
+        buf.extend_from_slice(&crc32c::crc32c(&buf).to_le_bytes());
+
+        let mut file = if let Some(file) = self.witness_file.take() {
+            file
+        } else {
+            let path = self.timeline_dir.join(WITNESS_LSN_FILE_NAME);
+            match OpenOptions::new().write(true).open(&path).await {
+                Ok(file) => file,
+                Err(e) if e.kind() == ErrorKind::NotFound => {
+                    // Bake the file under tmp name to never have it
+                    // partially written.
+                    let tmp_path = self
+                        .timeline_dir
+                        .join(format!("{WITNESS_LSN_FILE_NAME}.partial"));
+                    let mut file = File::create(&tmp_path)
+                        .await
+                        .with_context(|| format!("Failed to create {tmp_path}"))?;
+                    file.write_all(&buf).await?;
+                    self.fdatasync_file(&file).await?;
+                    durable_rename(&tmp_path, &path, !self.no_sync).await?;
+                    self.witness_file = Some(file);
+                    return Ok(());
+                }
+                Err(e) => return Err(e).with_context(|| format!("Failed to open {path}")),
+            }
+        };
+        file.seek(SeekFrom::Start(0)).await?;
+        file.write_all(&buf).await?;
+        self.fdatasync_file(&file).await?;
+        self.witness_file = Some(file);
+        Ok(())
+    }
+
     /// Get all known state of the storage.
     pub fn internal_state(&self) -> (Lsn, Lsn, Lsn, bool) {
         (
@@ -258,11 +344,12 @@ async fn fsync_file(&mut self, file: &File) -> Result<()> {
         Ok(())
     }
 
-    /// Call fdatasync if config requires so.
+    /// Call fdatasync if config requires so, batched with other timelines if
+    /// group commit is enabled.
     async fn fdatasync_file(&mut self, file: &File) -> Result<()> {
         if !self.no_sync {
             self.metrics
-                .observe_flush_seconds(time_io_closure(file.sync_data()).await?);
+                .observe_flush_seconds(time_io_closure(GROUP_COMMIT.sync_data(file)).await?);
         }
         Ok(())
     }
@@ -273,6 +360,18 @@ async fn open_or_create(&mut self, segno: XLogSegNo) -> Result<(File, bool)> {
         let (wal_file_path, wal_file_partial_path) =
             wal_file_paths(&self.timeline_dir, segno, self.wal_seg_size);
 
+        // A compressed segment is only written to after truncation below
+        // commit_lsn. Restore the raw file, or the new .partial file would
+        // shadow the compressed WAL.
+        let compressed_path =
+            compressed_wal_file_path(&self.timeline_dir, segno, self.wal_seg_size);
+        if fs::try_exists(&compressed_path).await?
+            && !fs::try_exists(&wal_file_path).await?
+            && !fs::try_exists(&wal_file_partial_path).await?
+        {
+            decompress_segment_on_disk(&compressed_path, &wal_file_path, self.no_sync).await?;
+        }
+
         // Try to open already completed segment
         if let Ok(file) = OpenOptions::new().write(true).open(&wal_file_path).await {
             Ok((file, false))
@@ -416,6 +515,10 @@ fn flush_lsn(&self) -> Lsn {
     }
 
     async fn initialize_first_segment(&mut self, init_lsn: Lsn) -> Result<()> {
+        if self.witness {
+            // Nothing to initialize, segments are never created.
+            return Ok(());
+        }
         let _timer = WAL_STORAGE_OPERATION_SECONDS
             .with_label_values(&["initialize_first_segment"])
             .start_timer();
@@ -459,10 +562,15 @@ async fn write_wal(&mut self, startpos: Lsn, buf: &[u8]) -> Result<()> {
             );
         }
 
-        let write_seconds = time_io_closure(self.write_exact(startpos, buf)).await?;
-        // WAL is written, updating write metrics
-        self.metrics.observe_write_seconds(write_seconds);
-        self.metrics.observe_write_bytes(buf.len());
+        if self.witness {
+            // Only move the position; flush_wal persists it.
+            self.write_lsn = startpos + buf.len() as u64;
+        } else {
+            let write_seconds = time_io_closure(self.write_exact(startpos, buf)).await?;
+            // WAL is written, updating write metrics
+            self.metrics.observe_write_seconds(write_seconds);
+            self.metrics.observe_write_bytes(buf.len());
+        }
 
         // Figure out the last record's end LSN and update `write_record_lsn`
         // (if we got a whole record). The write may also have closed and
@@ -498,6 +606,13 @@ async fn flush_wal(&mut self) -> Result<()> {
             return Ok(());
         }
 
+        if self.witness {
+            self.persist_witness_lsn(self.write_record_lsn).await?;
+            self.flush_lsn = self.write_record_lsn;
+            self.flush_record_lsn = self.write_record_lsn;
+            return Ok(());
+        }
+
         if let Some(unflushed_file) = self.file.take() {
             self.fdatasync_file(&unflushed_file)
                 .await
@@ -567,6 +682,13 @@ async fn truncate_wal(&mut self, end_pos: Lsn) -> Result<()> {
         self.write_record_lsn = end_pos;
         self.flush_record_lsn = end_pos;
 
+        if self.witness {
+            self.persist_witness_lsn(end_pos).await?;
+            self.pending_wal_truncation = false;
+            info!("truncated witness WAL position to {}", end_pos);
+            return Ok(());
+        }
+
         // Close previously opened file, if any
         if let Some(unflushed_file) = self.file.take() {
             self.fdatasync_file(&unflushed_file).await?;
@@ -615,6 +737,24 @@ fn get_metrics(&self) -> WalStorageMetrics {
     }
 }
 
+/// Read flush_lsn persisted by a witness, or 0 if it was never written.
+fn read_witness_lsn(timeline_dir: &Utf8Path) -> Result<Lsn> {
+    let path = timeline_dir.join(WITNESS_LSN_FILE_NAME);
+    let buf = match std::fs::read(&path) {
+        Ok(buf) => buf,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Lsn(0)),
+        Err(e) => return Err(e).with_context(|| format!("reading {path}")),
+    };
+    if buf.len() != WITNESS_LSN_FILE_SIZE {
+        bail!("{path} has unexpected size {}", buf.len());
+    }
+    let (lsn_bytes, crc_bytes) = buf.split_at(8);
+    if crc32c::crc32c(lsn_bytes) != u32::from_le_bytes(crc_bytes.try_into().unwrap()) {
+        bail!("{path} checksum mismatch");
+    }
+    Ok(Lsn(u64::from_le_bytes(lsn_bytes.try_into().unwrap())))
+}
+
 /// Remove all WAL segments in timeline_dir that match the given predicate.
 async fn remove_segments_from_disk(
     timeline_dir: &Utf8Path,
@@ -634,7 +774,7 @@ async fn remove_segments_from_disk(
         let entry_path = entry.path();
         let fname = entry_path.file_name().unwrap();
         /* Ignore files that are not XLOG segments */
-        if !IsXLogFileName(fname) && !IsPartialXLogFileName(fname) {
+        if !is_segment_file_name(fname) {
             continue;
         }
         let (segno, _) = XLogFromFileName(fname, wal_seg_size)?;
@@ -656,6 +796,187 @@ async fn remove_segments_from_disk(
     Ok(())
 }
 
+/// Returns true for (possibly partial or compressed) WAL segment file names.
+pub(crate) fn is_segment_file_name(fname: &OsStr) -> bool {
+    match fname
+        .to_str()
+        .and_then(|f| f.strip_suffix(COMPRESSED_SUFFIX))
+    {
+        Some(uncompressed) => IsXLogFileName(OsStr::new(uncompressed)),
+        None => IsXLogFileName(fname) || IsPartialXLogFileName(fname),
+    }
+}
+
+/// Returns completed raw segments <= `segno_up_to` in `timeline_dir`, in
+/// ascending order.
+pub(crate) async fn raw_segments_on_disk(
+    timeline_dir: &Utf8Path,
+    wal_seg_size: usize,
+    segno_up_to: XLogSegNo,
+) -> Result<Vec<XLogSegNo>> {
+    let mut segnos = Vec::new();
+    let mut entries = fs::read_dir(timeline_dir).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        let fname = entry.file_name();
+        if !IsXLogFileName(&fname) {
+            continue;
+        }
+        let (segno, _) = XLogFromFileName(&fname, wal_seg_size)?;
+        if segno <= segno_up_to {
+            segnos.push(segno);
+        }
+    }
+    segnos.sort_unstable();
+    Ok(segnos)
+}
+
+/// Compress the completed raw segment `segno` into a durable temp file, whose
+/// path is returned. It becomes the segment with
+/// [`install_compressed_segment`].
+pub(crate) async fn compress_segment_on_disk(
+    timeline_dir: &Utf8Path,
+    wal_seg_size: usize,
+    segno: XLogSegNo,
+    no_sync: bool,
+) -> Result<Utf8PathBuf> {
+    let _timer = WAL_STORAGE_OPERATION_SECONDS
+        .with_label_values(&["compress_segment_on_disk"])
+        .start_timer();
+
+    let (wal_file_path, _) = wal_file_paths(timeline_dir, segno, wal_seg_size);
+    let raw = fs::read(&wal_file_path)
+        .await
+        .with_context(|| format!("reading {wal_file_path}"))?;
+    let mut compressed = Vec::with_capacity(raw.len() / 4);
+    ZstdEncoder::new(&raw[..])
+        .read_to_end(&mut compressed)
+        .await?;
+
+    let tmp_path = timeline_dir.join("waltmp.zst");
+    let mut file = File::create(&tmp_path)
+        .await
+        .with_context(|| format!("Failed to open tmp wal file {tmp_path}"))?;
+    file.write_all(&compressed).await?;
+    if !no_sync {
+        file.sync_all().await?;
+    }
+    Ok(tmp_path)
+}
+
+/// Replace raw segment `segno` with its compressed copy `tmp_path`. The copy is
+/// durably renamed to `<segment>.zst` before the raw file is removed, so at any
+/// point at least one complete copy exists; readers prefer the raw file if both
+/// are present.
+///
+/// A `.zst` file next to a raw one is never trusted: it may be left over from
+/// an interrupted run, or predate a truncation that rewrote the raw segment, so
+/// the segment is compressed again.
+///
+/// Caller must ensure the segment isn't truncated or written to since it was
+/// compressed, and can't be until this returns.
+pub(crate) async fn install_compressed_segment(
+    tmp_path: &Utf8Path,
+    timeline_dir: &Utf8Path,
+    wal_seg_size: usize,
+    segno: XLogSegNo,
+    no_sync: bool,
+) -> Result<()> {
+    let (wal_file_path, _) = wal_file_paths(timeline_dir, segno, wal_seg_size);
+    let compressed_path = compressed_wal_file_path(timeline_dir, segno, wal_seg_size);
+    durable_rename(tmp_path, &compressed_path, !no_sync).await?;
+    remove_file(&wal_file_path)
+        .await
+        .or_else(utils::fs_ext::ignore_not_found)?;
+    COMPRESSED_WAL_SEGMENTS.inc();
+    Ok(())
+}
+
+/// Compress completed raw segments <= `segno_up_to` without synchronizing with
+/// WAL writes, for tests.
+#[cfg(test)]
+pub(crate) async fn compress_segments_on_disk(
+    timeline_dir: &Utf8Path,
+    wal_seg_size: usize,
+    segno_up_to: XLogSegNo,
+    no_sync: bool,
+) -> Result<()> {
+    for segno in raw_segments_on_disk(timeline_dir, wal_seg_size, segno_up_to).await? {
+        let tmp_path = compress_segment_on_disk(timeline_dir, wal_seg_size, segno, no_sync).await?;
+        install_compressed_segment(&tmp_path, timeline_dir, wal_seg_size, segno, no_sync).await?;
+    }
+    Ok(())
+}
+
+/// Restore the raw segment `wal_file_path` from `compressed_path`, which is
+/// removed afterwards. The raw file is durably in place before that, and
+/// readers prefer it, so at any point one complete copy is used.
+async fn decompress_segment_on_disk(
+    compressed_path: &Utf8Path,
+    wal_file_path: &Utf8Path,
+    no_sync: bool,
+) -> Result<()> {
+    let _timer = WAL_STORAGE_OPERATION_SECONDS
+        .with_label_values(&["decompress_segment_on_disk"])
+        .start_timer();
+
+    let compressed = File::open(compressed_path)
+        .await
+        .with_context(|| format!("Failed to open {compressed_path}"))?;
+    let tmp_path = wal_file_path.with_file_name("waltmp");
+    let mut file = File::create(&tmp_path)
+        .await
+        .with_context(|| format!("Failed to open tmp wal file {tmp_path}"))?;
+    tokio::io::copy(&mut ZstdDecoder::new(BufReader::new(compressed)), &mut file)
+        .await
+        .with_context(|| format!("decompressing {compressed_path}"))?;
+    if !no_sync {
+        file.sync_all().await?;
+    }
+    drop(file);
+    durable_rename(&tmp_path, wal_file_path, !no_sync).await?;
+    remove_file(compressed_path)
+        .await
+        .or_else(utils::fs_ext::ignore_not_found)?;
+    info!("decompressed {compressed_path} for truncation");
+    Ok(())
+}
+
+/// Stream a compressed segment, returning a reader positioned at `offset`.
+///
+/// A segment is a single zstd frame, so reaching `offset` means decompressing
+/// everything before it. Readers keep the returned reader until the end of the
+/// segment, so a sequential read decompresses each segment once.
+pub(crate) async fn decompress_segment<R>(
+    reader: R,
+    offset: u64,
+) -> Result<Pin<Box<dyn AsyncRead + Send + Sync>>>
+where
+    R: AsyncRead + Unpin + Send + 'static,
+{
+    let mut decoder = ZstdDecoder::new(BufReader::new(reader));
+    let skipped = tokio::io::copy(&mut (&mut decoder).take(offset), &mut tokio::io::sink())
+        .await
+        .context("decompressing WAL segment")?;
+    if skipped != offset {
+        bail!("compressed WAL segment ends at {skipped}, before offset {offset}");
+    }
+    Ok(Box::pin(SyncReader(SyncWrapper::new(decoder))))
+}
+
+/// Makes a reader `Sync`, which [`ZstdDecoder`] is not. Readers are only ever
+/// used through `&mut`, so nothing is actually shared.
+struct SyncReader<R>(SyncWrapper<R>);
+
+impl<R: AsyncRead + Unpin> AsyncRead for SyncReader<R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut TaskContext<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        Pin::new(self.get_mut().0.get_mut()).poll_read(cx, buf)
+    }
+}
+
 pub struct WalReader {
     remote_path: RemotePath,
     timeline_dir: Utf8PathBuf,
@@ -808,56 +1129,99 @@ async fn open_segment(&self) -> Result<Pin<Box<dyn AsyncRead + Send + Sync>>> {
 
         // Try to open local file, if we may have WAL locally
         if self.pos >= self.local_start_lsn {
-            let res = open_wal_file(&self.timeline_dir, segno, self.wal_seg_size).await?;
-            if let Some((mut file, _)) = res {
-                file.seek(SeekFrom::Start(xlogoff as u64)).await?;
-                return Ok(Box::pin(file));
-            } else {
-                // NotFound is expected, fall through to remote read
+            let res =
+                open_local_segment(&self.timeline_dir, segno, self.wal_seg_size, xlogoff as u64)
+                    .await?;
+            if let Some(reader) = res {
+                return Ok(reader);
             }
+            // NotFound is expected, fall through to remote read
         }
 
         // Try to open remote file, if remote reads are enabled
         if let Some(storage) = self.wal_backup.get_storage() {
-            let remote_wal_file_path = self.remote_path.join(&wal_file_name);
-            return read_object(&storage, &remote_wal_file_path, xlogoff as u64).await;
+            return read_segment_object(
+                &storage,
+                &self.remote_path,
+                &wal_file_name,
+                xlogoff as u64,
+            )
+            .await;
         }
 
         bail!("WAL segment is not found")
     }
 }
 
+/// Form in which a WAL segment is stored on disk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SegmentFormat {
+    /// Last segment, `.partial` suffix.
+    Partial,
+    /// Completed segment.
+    Complete,
+    /// Completed segment compressed with zstd, `.zst` suffix.
+    Compressed,
+}
+
+impl SegmentFormat {
+    /// File name of segment `segno` in this format.
+    pub(crate) fn file_name(self, segno: XLogSegNo, wal_seg_size: usize) -> String {
+        let name = XLogFileName(PG_TLI, segno, wal_seg_size);
+        match self {
+            SegmentFormat::Partial => name + ".partial",
+            SegmentFormat::Complete => name,
+            SegmentFormat::Compressed => name + COMPRESSED_SUFFIX,
+        }
+    }
+}
+
 /// Helper function for opening WAL segment `segno` in `dir`. Returns file and
-/// whether it is .partial.
+/// the form it is stored in.
 pub(crate) async fn open_wal_file(
     timeline_dir: &Utf8Path,
     segno: XLogSegNo,
     wal_seg_size: usize,
-) -> Result<Option<(tokio::fs::File, bool)>> {
-    let (wal_file_path, wal_file_partial_path) = wal_file_paths(timeline_dir, segno, wal_seg_size);
-
-    // First try to open the .partial file.
-    let mut partial_path = wal_file_path.to_owned();
-    partial_path.set_extension("partial");
-    if let Ok(opened_file) = tokio::fs::File::open(&wal_file_partial_path).await {
-        return Ok(Some((opened_file, true)));
+) -> Result<Option<(tokio::fs::File, SegmentFormat)>> {
+    // Order matters: a segment is renamed from .partial to completed, and a
+    // completed segment is removed only after its compressed copy is in place.
+    for format in [
+        SegmentFormat::Partial,
+        SegmentFormat::Complete,
+        SegmentFormat::Compressed,
+    ] {
+        let path = timeline_dir.join(format.file_name(segno, wal_seg_size));
+        match tokio::fs::File::open(&path).await {
+            Ok(file) => return Ok(Some((file, format))),
+            Err(e) if e.kind() == ErrorKind::NotFound => continue,
+            Err(e) => {
+                let e = anyhow::Error::new(e).context(format!("failed to open WAL file {path:#}"));
+                warn!("{e}");
+                return Err(e);
+            }
+        }
     }
+    Ok(None)
+}
 
-    // If that failed, try it without the .partial extension.
-    let pf_res = tokio::fs::File::open(&wal_file_path).await;
-    if let Err(e) = &pf_res {
-        if e.kind() == ErrorKind::NotFound {
-            return Ok(None);
+/// Open local WAL segment `segno` in whatever form it is stored, positioned at
+/// `offset`. Returns None if the segment is not on disk.
+pub(crate) async fn open_local_segment(
+    timeline_dir: &Utf8Path,
+    segno: XLogSegNo,
+    wal_seg_size: usize,
+    offset: u64,
+) -> Result<Option<Pin<Box<dyn AsyncRead + Send + Sync>>>> {
+    match open_wal_file(timeline_dir, segno, wal_seg_size).await? {
+        Some((file, SegmentFormat::Compressed)) => {
+            Ok(Some(decompress_segment(file, offset).await?))
         }
+        Some((mut file, _)) => {
+            file.seek(SeekFrom::Start(offset)).await?;
+            Ok(Some(Box::pin(file)))
+        }
+        None => Ok(None),
     }
-    let pf = pf_res
-        .with_context(|| format!("failed to open WAL file {wal_file_path:#}"))
-        .map_err(|e| {
-            warn!("{e}");
-            e
-        })?;
-
-    Ok(Some((pf, false)))
 }
 
 /// Helper returning full path to WAL segment file and its .partial brother.
@@ -871,3 +1235,80 @@ pub fn wal_file_paths(
     let wal_file_partial_path = timeline_dir.join(wal_file_name + ".partial");
     (wal_file_path, wal_file_partial_path)
 }
+
+/// Helper returning full path to compressed WAL segment file.
+pub fn compressed_wal_file_path(
+    timeline_dir: &Utf8Path,
+    segno: XLogSegNo,
+    wal_seg_size: usize,
+) -> Utf8PathBuf {
+    timeline_dir.join(SegmentFormat::Compressed.file_name(segno, wal_seg_size))
+}
+
+#[cfg(test)]
+mod tests {
+    use postgres_ffi::WAL_SEGMENT_SIZE;
+
+    use super::*;
+
+    fn segment_bytes(segno: XLogSegNo) -> Vec<u8> {
+        (0..WAL_SEGMENT_SIZE)
+            .map(|i| (i as u64 % 251 + segno) as u8)
+            .collect()
+    }
+
+    async fn read_local_segment(timeline_dir: &Utf8Path, segno: XLogSegNo, offset: u64) -> Vec<u8> {
+        let mut reader = open_local_segment(timeline_dir, segno, WAL_SEGMENT_SIZE, offset)
+            .await
+            .unwrap()
+            .expect("segment is on disk");
+        let mut buf = Vec::new();
+        reader.read_to_end(&mut buf).await.unwrap();
+        buf
+    }
+
+    /// Truncating into a compressed segment, as a timeline reset below
+    /// commit_lsn does, must keep the WAL before the truncation point.
+    #[tokio::test]
+    async fn truncate_into_compressed_segment() {
+        let tempdir = camino_tempfile::tempdir().unwrap();
+        let timeline_dir = tempdir.path();
+        let ttid = TenantTimelineId::empty();
+
+        let (seg1, _) = wal_file_paths(timeline_dir, 1, WAL_SEGMENT_SIZE);
+        let (_, seg2_partial) = wal_file_paths(timeline_dir, 2, WAL_SEGMENT_SIZE);
+        fs::write(&seg1, segment_bytes(1)).await.unwrap();
+        fs::write(&seg2_partial, segment_bytes(2)).await.unwrap();
+        compress_segments_on_disk(timeline_dir, WAL_SEGMENT_SIZE, 1, true)
+            .await
+            .unwrap();
+        assert!(!seg1.exists());
+
+        // Compressed segments are streamed from any offset.
+        assert_eq!(
+            read_local_segment(timeline_dir, 1, 1000).await,
+            segment_bytes(1)[1000..]
+        );
+
+        let state = TimelinePersistentState::empty();
+        let mut storage = PhysicalStorage::new(&ttid, timeline_dir, &state, true, false).unwrap();
+        let end_pos = Lsn(WAL_SEGMENT_SIZE as u64 + 1000);
+        storage.truncate_wal(end_pos).await.unwrap();
+
+        assert!(!compressed_wal_file_path(timeline_dir, 1, WAL_SEGMENT_SIZE).exists());
+        assert!(!seg2_partial.exists());
+        let mut expected = segment_bytes(1);
+        expected[1000..].fill(0);
+        assert_eq!(read_local_segment(timeline_dir, 1, 0).await, expected);
+
+        // Once the segment is completed again, it is compressed from the new
+        // contents.
+        let (_, seg1_partial) = wal_file_paths(timeline_dir, 1, WAL_SEGMENT_SIZE);
+        fs::rename(&seg1_partial, &seg1).await.unwrap();
+        compress_segments_on_disk(timeline_dir, WAL_SEGMENT_SIZE, 1, true)
+            .await
+            .unwrap();
+        assert!(!seg1.exists());
+        assert_eq!(read_local_segment(timeline_dir, 1, 0).await, expected);
+    }
+}
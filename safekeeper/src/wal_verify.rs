@@ -0,0 +1,529 @@
+//! Verification of WAL consistency across safekeepers and remote storage.
+//!
+//! WAL of a timeline is summarized per source in chunks aligned to a fixed
+//! size: CRC32C of the chunk plus the last record boundary before it, found by
+//! decoding the stream from the requested start LSN. Summaries of the local
+//! disk, of completed and partial segments in remote storage and of the local
+//! disks of other members of the configuration (fetched over http) are then
+//! compared chunk by chunk, and ranges where sources disagree are reported.
+//!
+//! Sources don't need to cover the whole range: WAL might be already removed
+//! locally or not yet uploaded. Only chunks present on several sources are
+//! compared.
+
+use std::cmp::min;
+use std::collections::BTreeMap;
+
+use anyhow::{Context, Result, bail};
+use postgres_ffi::MAX_SEND_SIZE;
+use postgres_ffi::waldecoder::WalStreamDecoder;
+use postgres_versioninfo::PgMajorVersion;
+use remote_storage::DownloadError;
+use reqwest::Certificate;
+use safekeeper_api::models::{
+    WalChunkSummary, WalDivergence, WalDivergenceGroup, WalSourceCoverage, WalSummary,
+    WalSummarySource, WalVerifyRequest, WalVerifyResponse,
+};
+use safekeeper_client::mgmt_api;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use utils::lsn::Lsn;
+
+use crate::SafeKeeperConf;
+use crate::timeline::WalResidentTimeline;
+use crate::wal_backup::{read_object, read_segment_object};
+use crate::wal_storage::open_local_segment;
+
+/// Chunk size used if the request doesn't specify one.
+pub const DEFAULT_CHUNK_SIZE: u64 = 1024 * 1024;
+
+/// Check the requested range and chunk size, returning the chunk size to use.
+pub fn check_request(
+    from_lsn: Lsn,
+    until_lsn: Lsn,
+    chunk_size: Option<u64>,
+    wal_seg_size: usize,
+) -> Result<u64> {
+    if from_lsn > until_lsn {
+        bail!("from_lsn is greater than until_lsn");
+    }
+    let chunk_size = chunk_size.unwrap_or(DEFAULT_CHUNK_SIZE);
+    if chunk_size == 0 || wal_seg_size as u64 % chunk_size != 0 {
+        bail!("chunk_size {chunk_size} doesn't divide WAL segment size {wal_seg_size}");
+    }
+    Ok(chunk_size)
+}
+
+/// Summarize WAL of the timeline in `[from_lsn, until_lsn)` stored on `source`.
+pub async fn summarize(
+    tli: &WalResidentTimeline,
+    conf: &SafeKeeperConf,
+    source: WalSummarySource,
+    from_lsn: Lsn,
+    until_lsn: Lsn,
+    chunk_size: u64,
+) -> Result<WalSummary> {
+    let (_, state) = tli.get_state().await;
+    if state.timeline_start_lsn > from_lsn {
+        bail!("requested LSN is before the start of the timeline");
+    }
+    let wal_seg_size = state.server.wal_seg_size as usize;
+    let pg_version = PgMajorVersion::try_from(state.server.pg_version)?;
+    let mut summarizer = Summarizer::new(from_lsn, chunk_size, pg_version);
+
+    match source {
+        WalSummarySource::Local => {
+            let start = from_lsn.max(state.local_start_lsn);
+            let end = until_lsn.min(tli.get_flush_lsn().await);
+            let mut pos = start;
+            while pos < end {
+                let segno = pos.segment_number(wal_seg_size);
+                let seg_end = min(end, Lsn((segno + 1) * wal_seg_size as u64));
+                let offset = pos.segment_offset(wal_seg_size) as u64;
+                if let Some(reader) =
+                    open_local_segment(tli.timeline_dir(), segno, wal_seg_size, offset).await?
+                {
+                    feed_reader(&mut summarizer, reader, pos, seg_end).await?;
+                }
+                pos = seg_end;
+            }
+        }
+        WalSummarySource::Remote => {
+            let storage = tli
+                .wal_backup
+                .get_storage()
+                .context("remote storage is not configured")?;
+            // Segments are uploaded once completed, up to backup_lsn.
+            let end = until_lsn.min(state.backup_lsn);
+            let mut pos = from_lsn;
+            while pos < end {
+                let segno = pos.segment_number(wal_seg_size);
+                let seg_end = min(end, Lsn((segno + 1) * wal_seg_size as u64));
+                let offset = pos.segment_offset(wal_seg_size) as u64;
+                let name = postgres_ffi::XLogFileName(postgres_ffi::PG_TLI, segno, wal_seg_size);
+                match read_segment_object(&storage, &tli.remote_path, &name, offset).await {
+                    Ok(reader) => feed_reader(&mut summarizer, reader, pos, seg_end).await?,
+                    Err(e) if is_not_found(&e) => {}
+                    Err(e) => return Err(e),
+                }
+                pos = seg_end;
+            }
+        }
+        WalSummarySource::RemotePartial => {
+            let storage = tli
+                .wal_backup
+                .get_storage()
+                .context("remote storage is not configured")?;
+            if let Some(segment) = state.partial_backup.uploaded_segment() {
+                let seg_start = segment.flush_lsn.segment_lsn(wal_seg_size);
+                let start = from_lsn.max(seg_start);
+                let end = until_lsn.min(segment.flush_lsn);
+                if start < end {
+                    let path = segment.remote_path(&tli.remote_path);
+                    let reader = read_object(&storage, &path, start.0 - seg_start.0).await?;
+                    feed_reader(&mut summarizer, reader, start, end).await?;
+                }
+            }
+        }
+    }
+
+    let (chunks, decode_error) = summarizer.finish();
+    Ok(WalSummary {
+        source: source_name(conf, source),
+        chunks,
+        decode_error,
+    })
+}
+
+/// Compare WAL of the timeline on the local disk, in remote storage and on the
+/// other members of the configuration, plus safekeepers in `http_hosts`.
+pub async fn verify(
+    tli: &WalResidentTimeline,
+    conf: &SafeKeeperConf,
+    request: WalVerifyRequest,
+    chunk_size: u64,
+) -> Result<WalVerifyResponse> {
+    let WalVerifyRequest {
+        from_lsn,
+        until_lsn,
+        http_hosts,
+        ..
+    } = request;
+
+    let mut sources = vec![WalSummarySource::Local];
+    if tli.wal_backup.get_storage().is_some() {
+        sources.extend([WalSummarySource::Remote, WalSummarySource::RemotePartial]);
+    }
+    let mut results = Vec::new();
+    for source in sources {
+        let res = summarize(tli, conf, source, from_lsn, until_lsn, chunk_size).await;
+        results.push((source_name(conf, source), res));
+    }
+
+    // Learn http addresses of the other members from the broker.
+    let (_, state) = tli.get_state().await;
+    let peers = tli.get_peers(conf).await;
+    let mut hosts = Vec::new();
+    let members = state
+        .mconf
+        .members
+        .m
+        .iter()
+        .chain(state.mconf.new_members.iter().flat_map(|m| m.m.iter()));
+    for member in members {
+        if member.id == conf.my_id || hosts.iter().any(|(id, _)| *id == Some(member.id)) {
+            continue;
+        }
+        let peer = peers.iter().find(|p| p.sk_id == member.id);
+        let url = match peer {
+            Some(peer) if conf.use_https_safekeeper_api => peer
+                .https_connstr
+                .as_ref()
+                .map(|connstr| format!("https://{connstr}"))
+                .context("https is enabled, but https_connstr is not specified"),
+            Some(peer) => Ok(format!("http://{}", peer.http_connstr)),
+            None => Err(anyhow::anyhow!("http address of {member} is unknown")),
+        };
+        hosts.push((Some(member.id), url));
+    }
+    hosts.extend(http_hosts.into_iter().map(|url| (None, Ok(url))));
+
+    let mut client = reqwest::Client::builder();
+    for cert in &conf.ssl_ca_certs {
+        client = client.add_root_certificate(Certificate::from_der(cert.contents())?);
+    }
+    let client = client
+        .build()
+        .context("Failed to build http client for WAL verification")?;
+    let peer_results = futures::future::join_all(hosts.into_iter().map(|(id, url)| {
+        let client = client.clone();
+        async move {
+            let name = match (id, &url) {
+                (Some(id), _) => format!("sk-{id}"),
+                (None, Ok(url)) => url.clone(),
+                (None, Err(_)) => unreachable!("explicit hosts have urls"),
+            };
+            let res: Result<WalSummary> = async {
+                let client = mgmt_api::Client::new(client, url?, conf.sk_auth_token.clone());
+                let summary = client
+                    .wal_summary(
+                        tli.ttid.tenant_id,
+                        tli.ttid.timeline_id,
+                        WalSummarySource::Local,
+                        from_lsn,
+                        until_lsn,
+                        chunk_size,
+                    )
+                    .await?;
+                Ok(summary)
+            }
+            .await;
+            (name, res)
+        }
+    }))
+    .await;
+    results.extend(peer_results);
+
+    let mut coverage = Vec::new();
+    let mut summaries: Vec<WalSummary> = Vec::new();
+    for (name, res) in results {
+        match res {
+            // A member might be also listed in http_hosts.
+            Ok(summary) if summaries.iter().any(|s| s.source == summary.source) => {}
+            Ok(summary) => {
+                coverage.push(WalSourceCoverage {
+                    source: summary.source.clone(),
+                    ranges: covered_ranges(&summary),
+                    error: None,
+                    decode_error: summary.decode_error.clone(),
+                });
+                summaries.push(summary);
+            }
+            Err(e) => coverage.push(WalSourceCoverage {
+                source: name,
+                ranges: Vec::new(),
+                error: Some(format!("{e:#}")),
+                decode_error: None,
+            }),
+        }
+    }
+
+    Ok(WalVerifyResponse {
+        from_lsn,
+        until_lsn,
+        sources: coverage,
+        divergences: compare_summaries(&summaries),
+    })
+}
+
+/// Find ranges where summaries disagree. Only chunks with the same bounds are
+/// compared; adjacent divergent chunks where sources split the same way are
+/// reported as one range.
+pub fn compare_summaries(summaries: &[WalSummary]) -> Vec<WalDivergence> {
+    let mut chunks = BTreeMap::<(Lsn, Lsn), Vec<(&str, &WalChunkSummary)>>::new();
+    for summary in summaries {
+        for chunk in &summary.chunks {
+            chunks
+                .entry((chunk.start_lsn, chunk.end_lsn))
+                .or_default()
+                .push((&summary.source, chunk));
+        }
+    }
+
+    let mut divergences: Vec<WalDivergence> = Vec::new();
+    for ((start_lsn, end_lsn), sources) in chunks {
+        let mut by_crc = BTreeMap::<u32, WalDivergenceGroup>::new();
+        for (source, chunk) in sources {
+            by_crc
+                .entry(chunk.crc32c)
+                .or_insert_with(|| WalDivergenceGroup {
+                    sources: Vec::new(),
+                    record_boundary: chunk.record_boundary,
+                })
+                .sources
+                .push(source.to_owned());
+        }
+        if by_crc.len() < 2 {
+            continue;
+        }
+        let mut groups: Vec<_> = by_crc.into_values().collect();
+        groups.sort_by(|a, b| a.sources.cmp(&b.sources));
+
+        if let Some(last) = divergences.last_mut() {
+            let same_split = last
+                .groups
+                .iter()
+                .map(|g| &g.sources)
+                .eq(groups.iter().map(|g| &g.sources));
+            if last.end_lsn == start_lsn && same_split {
+                last.end_lsn = end_lsn;
+                continue;
+            }
+        }
+        divergences.push(WalDivergence {
+            start_lsn,
+            end_lsn,
+            groups,
+        });
+    }
+    divergences
+}
+
+/// Contiguous ranges covered by the chunks of the summary.
+fn covered_ranges(summary: &WalSummary) -> Vec<(Lsn, Lsn)> {
+    let mut ranges: Vec<(Lsn, Lsn)> = Vec::new();
+    for chunk in &summary.chunks {
+        match ranges.last_mut() {
+            Some((_, end)) if *end == chunk.start_lsn => *end = chunk.end_lsn,
+            _ => ranges.push((chunk.start_lsn, chunk.end_lsn)),
+        }
+    }
+    ranges
+}
+
+fn source_name(conf: &SafeKeeperConf, source: WalSummarySource) -> String {
+    match source {
+        WalSummarySource::Local => format!("sk-{}/local", conf.my_id),
+        WalSummarySource::Remote => "remote".to_owned(),
+        WalSummarySource::RemotePartial => "remote_partial".to_owned(),
+    }
+}
+
+fn is_not_found(e: &anyhow::Error) -> bool {
+    matches!(
+        e.downcast_ref::<DownloadError>(),
+        Some(DownloadError::NotFound)
+    )
+}
+
+/// Feed WAL in `[start, end)` from `reader` positioned at `start`.
+async fn feed_reader(
+    summarizer: &mut Summarizer,
+    mut reader: impl AsyncRead + Unpin,
+    start: Lsn,
+    end: Lsn,
+) -> Result<()> {
+    let mut buf = vec![0u8; MAX_SEND_SIZE];
+    let mut pos = start;
+    while pos < end {
+        let len = min(buf.len() as u64, end.0 - pos.0) as usize;
+        reader
+            .read_exact(&mut buf[..len])
+            .await
+            .with_context(|| format!("reading WAL at {pos}"))?;
+        summarizer.feed(pos, &buf[..len]);
+        pos += len as u64;
+    }
+    Ok(())
+}
+
+/// Builds chunk summaries of WAL fed in increasing LSN order, possibly with gaps.
+struct Summarizer {
+    chunk_size: u64,
+    chunks: Vec<WalChunkSummary>,
+    /// Chunk being filled; its end_lsn is where the next byte goes.
+    current: Option<WalChunkSummary>,
+    /// Decodes records from `from_lsn` on; dropped on the first gap or error.
+    decoder: Option<WalStreamDecoder>,
+    decoded_until: Lsn,
+    record_boundary: Option<Lsn>,
+    decode_error: Option<String>,
+}
+
+impl Summarizer {
+    fn new(from_lsn: Lsn, chunk_size: u64, pg_version: PgMajorVersion) -> Self {
+        Self {
+            chunk_size,
+            chunks: Vec::new(),
+            current: None,
+            decoder: Some(WalStreamDecoder::new(from_lsn, pg_version)),
+            decoded_until: from_lsn,
+            record_boundary: Some(from_lsn),
+            decode_error: None,
+        }
+    }
+
+    fn feed(&mut self, mut lsn: Lsn, mut buf: &[u8]) {
+        while !buf.is_empty() {
+            let continues = self
+                .current
+                .as_ref()
+                .is_some_and(|c| c.end_lsn == lsn && lsn.0 % self.chunk_size != 0);
+            if !continues {
+                self.chunks.extend(self.current.take());
+                self.current = Some(WalChunkSummary {
+                    start_lsn: lsn,
+                    end_lsn: lsn,
+                    crc32c: 0,
+                    record_boundary: self.record_boundary,
+                });
+            }
+            let chunk_end = (lsn.0 / self.chunk_size + 1) * self.chunk_size;
+            let len = min(buf.len() as u64, chunk_end - lsn.0) as usize;
+            let (data, rest) = buf.split_at(len);
+
+            let current = self.current.as_mut().unwrap();
+            current.crc32c = crc32c::crc32c_append(current.crc32c, data);
+            current.end_lsn += len as u64;
+            self.decode(lsn, data);
+
+            lsn += len as u64;
+            buf = rest;
+        }
+    }
+
+    fn decode(&mut self, lsn: Lsn, data: &[u8]) {
+        let Some(decoder) = self.decoder.as_mut() else {
+            return;
+        };
+        let error = if lsn != self.decoded_until {
+            Some(format!("no WAL at {}", self.decoded_until))
+        } else {
+            decoder.feed_bytes(data);
+            self.decoded_until += data.len() as u64;
+            loop {
+                match decoder.poll_decode() {
+                    Ok(Some((end_lsn, _))) => self.record_boundary = Some(end_lsn),
+                    Ok(None) => break None,
+                    Err(e) => break Some(format!("failed to decode WAL: {e}")),
+                }
+            }
+        };
+        if let Some(error) = error {
+            self.decoder = None;
+            self.record_boundary = None;
+            self.decode_error = Some(error);
+        }
+    }
+
+    fn finish(mut self) -> (Vec<WalChunkSummary>, Option<String>) {
+        self.chunks.extend(self.current.take());
+        (self.chunks, self.decode_error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summary(source: &str, chunks: &[(u64, u64, u32)]) -> WalSummary {
+        WalSummary {
+            source: source.to_owned(),
+            chunks: chunks
+                .iter()
+                .map(|&(start, end, crc32c)| WalChunkSummary {
+                    start_lsn: Lsn(start),
+                    end_lsn: Lsn(end),
+                    crc32c,
+                    record_boundary: Some(Lsn(start)),
+                })
+                .collect(),
+            decode_error: None,
+        }
+    }
+
+    #[test]
+    fn test_compare_summaries() {
+        let a = summary("a", &[(0, 10, 1), (10, 20, 2), (20, 30, 3), (30, 35, 4)]);
+        // Misses the first chunk, has the rest.
+        let b = summary("b", &[(10, 20, 2), (20, 30, 3), (30, 40, 4)]);
+        // Diverges from 10 on.
+        let c = summary("c", &[(0, 10, 1), (10, 20, 5), (20, 30, 6), (30, 35, 7)]);
+
+        assert!(compare_summaries(&[a.clone(), b.clone()]).is_empty());
+        // Chunk (30, 40) is not compared with anything, adjacent divergent
+        // chunks with the same split are merged.
+        assert_eq!(
+            compare_summaries(&[a, b, c]),
+            vec![
+                WalDivergence {
+                    start_lsn: Lsn(10),
+                    end_lsn: Lsn(30),
+                    groups: vec![
+                        WalDivergenceGroup {
+                            sources: vec!["a".to_owned(), "b".to_owned()],
+                            record_boundary: Some(Lsn(10)),
+                        },
+                        WalDivergenceGroup {
+                            sources: vec!["c".to_owned()],
+                            record_boundary: Some(Lsn(10)),
+                        },
+                    ],
+                },
+                WalDivergence {
+                    start_lsn: Lsn(30),
+                    end_lsn: Lsn(35),
+                    groups: vec![
+                        WalDivergenceGroup {
+                            sources: vec!["a".to_owned()],
+                            record_boundary: Some(Lsn(30)),
+                        },
+                        WalDivergenceGroup {
+                            sources: vec!["c".to_owned()],
+                            record_boundary: Some(Lsn(30)),
+                        },
+                    ],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_summarizer_chunks() {
+        let mut summarizer = Summarizer::new(Lsn(4), 8, PgMajorVersion::PG16);
+        summarizer.feed(Lsn(4), &[1; 6]);
+        // Gap from 10 to 20.
+        summarizer.feed(Lsn(20), &[2; 4]);
+        let (chunks, decode_error) = summarizer.finish();
+
+        let bounds: Vec<_> = chunks
+            .iter()
+            .map(|c| (c.start_lsn.0, c.end_lsn.0))
+            .collect();
+        assert_eq!(bounds, vec![(4, 8), (8, 10), (20, 24)]);
+        assert_eq!(chunks[0].crc32c, crc32c::crc32c(&[1; 4]));
+        assert_eq!(chunks[2].crc32c, crc32c::crc32c(&[2; 4]));
+        assert_eq!(chunks[0].record_boundary, Some(Lsn(4)));
+        assert_eq!(chunks[2].record_boundary, None);
+        assert!(decode_error.is_some());
+    }
+}
@@ -169,6 +169,9 @@ pub fn run_server(os: NodeOs, disk: Arc<SafekeeperDisk>) -> Result<()> {
         availability_zone: None,
         peer_recovery_enabled: false,
         backup_parallel_jobs: 0,
+        wal_archive_prefix: None,
+        wal_archive_compress: false,
+        wal_compression: false,
         pg_auth: None,
         pg_tenant_only_auth: None,
         http_auth: None,
@@ -176,11 +179,16 @@ pub fn run_server(os: NodeOs, disk: Arc<SafekeeperDisk>) -> Result<()> {
         current_thread_runtime: false,
         walsenders_keep_horizon: false,
         partial_backup_timeout: Duration::from_secs(0),
+        remote_snapshot_interval: None,
         disable_periodic_broker_push: false,
         enable_offload: false,
         delete_offloaded_wal: false,
         control_file_save_interval: Duration::from_secs(1),
         partial_backup_concurrency: 1,
+        wal_ingest_limits: Default::default(),
+        wal_fsync_concurrency: None,
+        wal_group_commit_window: None,
+        wal_group_commit_syncfs: false,
         eviction_min_resident: Duration::ZERO,
         wal_reader_fanout: false,
         max_delta_for_fanout: None,
@@ -197,6 +205,7 @@ pub fn run_server(os: NodeOs, disk: Arc<SafekeeperDisk>) -> Result<()> {
         hcc_base_url: None,
         global_disk_check_interval: Duration::from_secs(10),
         max_global_disk_usage_ratio: 0.0,
+        disk_pressure_eviction_ratio: 0.0,
         /* END_HADRON */
     };
 
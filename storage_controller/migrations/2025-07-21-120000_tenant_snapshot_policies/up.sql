@@ -0,0 +1,6 @@
+CREATE TABLE tenant_snapshot_policies (
+  tenant_id VARCHAR NOT NULL,
+  policy JSONB NOT NULL,
+  state JSONB NOT NULL,
+  PRIMARY KEY(tenant_id)
+);
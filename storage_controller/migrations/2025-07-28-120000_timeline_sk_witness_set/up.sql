@@ -0,0 +1 @@
+ALTER TABLE timelines ADD sk_witness_set BIGINT[] NOT NULL DEFAULT '{}';
@@ -22,12 +22,13 @@
     MetadataHealthListUnhealthyResponse, MetadataHealthUpdateRequest, MetadataHealthUpdateResponse,
     NodeAvailability, NodeConfigureRequest, NodeRegisterRequest, SafekeeperSchedulingPolicyRequest,
     ShardsPreferredAzsRequest, TenantCreateRequest, TenantPolicyRequest, TenantShardMigrateRequest,
-    TimelineImportRequest, TimelineSafekeeperMigrateRequest,
+    TenantSnapshotPolicy, TimelineImportRequest, TimelineSafekeeperMigrateRequest,
 };
 use pageserver_api::models::{
-    DetachBehavior, LsnLeaseRequest, TenantConfigPatchRequest, TenantConfigRequest,
-    TenantLocationConfigRequest, TenantShardSplitRequest, TenantTimeTravelRequest,
-    TimelineArchivalConfigRequest, TimelineCreateRequest,
+    DetachBehavior, LayerRewriteRequest, LsnLeaseRequest, TenantConfigPatchRequest,
+    TenantConfigRequest, TenantLocationConfigRequest, TenantShardSplitRequest,
+    TenantTimeTravelRequest, TimelineArchivalConfigRequest, TimelineCreateRequest,
+    TimelineExportRequest, TimelineResetRequest,
 };
 use pageserver_api::shard::TenantShardId;
 use pageserver_api::upcall_api::{
@@ -576,6 +577,82 @@ async fn handle_tenant_timeline_archival_config(
     json_response(StatusCode::OK, ())
 }
 
+async fn handle_tenant_timeline_undelete(
+    service: Arc<Service>,
+    req: Request<Body>,
+) -> Result<Response<Body>, ApiError> {
+    let tenant_id: TenantId = parse_request_param(&req, "tenant_id")?;
+    let timeline_id: TimelineId = parse_request_param(&req, "timeline_id")?;
+
+    check_permissions(&req, Scope::PageServerApi)?;
+    maybe_rate_limit(&req, tenant_id).await;
+
+    match maybe_forward(req).await {
+        ForwardOutcome::Forwarded(res) => {
+            return res;
+        }
+        ForwardOutcome::NotForwarded(_req) => {}
+    };
+
+    service
+        .tenant_timeline_undelete(tenant_id, timeline_id)
+        .await?;
+
+    json_response(StatusCode::OK, ())
+}
+
+async fn handle_tenant_timeline_export(
+    service: Arc<Service>,
+    req: Request<Body>,
+) -> Result<Response<Body>, ApiError> {
+    let tenant_id: TenantId = parse_request_param(&req, "tenant_id")?;
+    let timeline_id: TimelineId = parse_request_param(&req, "timeline_id")?;
+
+    check_permissions(&req, Scope::PageServerApi)?;
+    maybe_rate_limit(&req, tenant_id).await;
+
+    let mut req = match maybe_forward(req).await {
+        ForwardOutcome::Forwarded(res) => {
+            return res;
+        }
+        ForwardOutcome::NotForwarded(req) => req,
+    };
+
+    let export_req = json_request::<TimelineExportRequest>(&mut req).await?;
+
+    json_response(
+        StatusCode::ACCEPTED,
+        service
+            .tenant_timeline_export(tenant_id, timeline_id, export_req)
+            .await?,
+    )
+}
+
+async fn handle_tenant_timeline_export_status(
+    service: Arc<Service>,
+    req: Request<Body>,
+) -> Result<Response<Body>, ApiError> {
+    let tenant_id: TenantId = parse_request_param(&req, "tenant_id")?;
+    let timeline_id: TimelineId = parse_request_param(&req, "timeline_id")?;
+
+    check_permissions(&req, Scope::PageServerApi)?;
+    maybe_rate_limit(&req, tenant_id).await;
+
+    match maybe_forward(req).await {
+        ForwardOutcome::Forwarded(res) => {
+            return res;
+        }
+        ForwardOutcome::NotForwarded(_req) => {}
+    };
+
+    json_response(
+        StatusCode::OK,
+        service
+            .tenant_timeline_export_status(tenant_id, timeline_id)
+            .await?,
+    )
+}
+
 async fn handle_tenant_timeline_detach_ancestor(
     service: Arc<Service>,
     req: Request<Body>,
@@ -601,6 +678,32 @@ async fn handle_tenant_timeline_detach_ancestor(
     json_response(StatusCode::OK, res)
 }
 
+async fn handle_tenant_timeline_reset(
+    service: Arc<Service>,
+    req: Request<Body>,
+) -> Result<Response<Body>, ApiError> {
+    let tenant_id: TenantId = parse_request_param(&req, "tenant_id")?;
+    let timeline_id: TimelineId = parse_request_param(&req, "timeline_id")?;
+
+    check_permissions(&req, Scope::PageServerApi)?;
+    maybe_rate_limit(&req, tenant_id).await;
+
+    let mut req = match maybe_forward(req).await {
+        ForwardOutcome::Forwarded(res) => {
+            return res;
+        }
+        ForwardOutcome::NotForwarded(req) => req,
+    };
+
+    let reset_req = json_request::<TimelineResetRequest>(&mut req).await?;
+
+    service
+        .tenant_timeline_reset(tenant_id, timeline_id, reset_req)
+        .await?;
+
+    json_response(StatusCode::OK, ())
+}
+
 async fn handle_tenant_timeline_block_unblock_gc(
     service: Arc<Service>,
     req: Request<Body>,
@@ -889,7 +992,10 @@ async fn handle_tenant_describe(
         ForwardOutcome::NotForwarded(_req) => {}
     };
 
-    json_response(StatusCode::OK, service.tenant_describe(tenant_id)?)
+    let mut describe = service.tenant_describe(tenant_id)?;
+    describe.snapshot_policy = service.tenant_snapshot_policy_get(tenant_id).await?;
+
+    json_response(StatusCode::OK, describe)
 }
 
 /* BEGIN_HADRON */
@@ -1176,6 +1282,40 @@ async fn handle_node_drain(req: Request<Body>) -> Result<Response<Body>, ApiErro
     json_response(StatusCode::ACCEPTED, ())
 }
 
+async fn handle_layer_rewrite(req: Request<Body>) -> Result<Response<Body>, ApiError> {
+    check_permissions(&req, Scope::Admin)?;
+
+    let mut req = match maybe_forward(req).await {
+        ForwardOutcome::Forwarded(res) => {
+            return res;
+        }
+        ForwardOutcome::NotForwarded(req) => req,
+    };
+
+    let rewrite_req = json_request::<LayerRewriteRequest>(&mut req).await?;
+    let state = get_state(&req);
+
+    json_response(
+        StatusCode::ACCEPTED,
+        state.service.layer_rewrite(rewrite_req).await?,
+    )
+}
+
+async fn handle_layer_rewrite_status(req: Request<Body>) -> Result<Response<Body>, ApiError> {
+    check_permissions(&req, Scope::Admin)?;
+
+    let req = match maybe_forward(req).await {
+        ForwardOutcome::Forwarded(res) => {
+            return res;
+        }
+        ForwardOutcome::NotForwarded(req) => req,
+    };
+
+    let state = get_state(&req);
+
+    json_response(StatusCode::OK, state.service.layer_rewrite_status().await?)
+}
+
 async fn handle_cancel_node_drain(req: Request<Body>) -> Result<Response<Body>, ApiError> {
     check_permissions(&req, Scope::Infra)?;
 
@@ -1429,6 +1569,73 @@ async fn handle_tenant_update_policy(req: Request<Body>) -> Result<Response<Body
     )
 }
 
+async fn handle_tenant_snapshot_policy_set(req: Request<Body>) -> Result<Response<Body>, ApiError> {
+    check_permissions(&req, Scope::Admin)?;
+    // NB: don't rate limit: admin operation.
+
+    let mut req = match maybe_forward(req).await {
+        ForwardOutcome::Forwarded(res) => {
+            return res;
+        }
+        ForwardOutcome::NotForwarded(req) => req,
+    };
+
+    let tenant_id: TenantId = parse_request_param(&req, "tenant_id")?;
+    let policy = json_request::<TenantSnapshotPolicy>(&mut req).await?;
+    let state = get_state(&req);
+
+    json_response(
+        StatusCode::OK,
+        state
+            .service
+            .tenant_snapshot_policy_set(tenant_id, policy)
+            .await?,
+    )
+}
+
+async fn handle_tenant_snapshot_policy_get(req: Request<Body>) -> Result<Response<Body>, ApiError> {
+    check_permissions(&req, Scope::Admin)?;
+
+    let req = match maybe_forward(req).await {
+        ForwardOutcome::Forwarded(res) => {
+            return res;
+        }
+        ForwardOutcome::NotForwarded(req) => req,
+    };
+
+    let tenant_id: TenantId = parse_request_param(&req, "tenant_id")?;
+    let state = get_state(&req);
+
+    match state.service.tenant_snapshot_policy_get(tenant_id).await? {
+        Some(describe) => json_response(StatusCode::OK, describe),
+        None => Err(ApiError::NotFound(
+            anyhow::anyhow!("Tenant {tenant_id} has no snapshot policy").into(),
+        )),
+    }
+}
+
+async fn handle_tenant_snapshot_policy_delete(
+    req: Request<Body>,
+) -> Result<Response<Body>, ApiError> {
+    check_permissions(&req, Scope::Admin)?;
+
+    let req = match maybe_forward(req).await {
+        ForwardOutcome::Forwarded(res) => {
+            return res;
+        }
+        ForwardOutcome::NotForwarded(req) => req,
+    };
+
+    let tenant_id: TenantId = parse_request_param(&req, "tenant_id")?;
+    let state = get_state(&req);
+    state
+        .service
+        .tenant_snapshot_policy_delete(tenant_id)
+        .await?;
+
+    json_response(StatusCode::OK, ())
+}
+
 async fn handle_update_preferred_azs(req: Request<Body>) -> Result<Response<Body>, ApiError> {
     check_permissions(&req, Scope::Admin)?;
 
@@ -2384,6 +2591,20 @@ pub fn make_router(
                 RequestName("control_v1_cancel_node_drain"),
             )
         })
+        .put("/control/v1/layer_rewrite", |r| {
+            named_request_span(
+                r,
+                handle_layer_rewrite,
+                RequestName("control_v1_layer_rewrite"),
+            )
+        })
+        .get("/control/v1/layer_rewrite", |r| {
+            named_request_span(
+                r,
+                handle_layer_rewrite_status,
+                RequestName("control_v1_layer_rewrite_status"),
+            )
+        })
         .put("/control/v1/node/:node_id/fill", |r| {
             named_request_span(r, handle_node_fill, RequestName("control_v1_node_fill"))
         })
@@ -2494,6 +2715,27 @@ pub fn make_router(
                 RequestName("control_v1_tenant_policy"),
             )
         })
+        .put("/control/v1/tenant/:tenant_id/snapshot_policy", |r| {
+            named_request_span(
+                r,
+                handle_tenant_snapshot_policy_set,
+                RequestName("control_v1_tenant_snapshot_policy"),
+            )
+        })
+        .get("/control/v1/tenant/:tenant_id/snapshot_policy", |r| {
+            named_request_span(
+                r,
+                handle_tenant_snapshot_policy_get,
+                RequestName("control_v1_tenant_snapshot_policy"),
+            )
+        })
+        .delete("/control/v1/tenant/:tenant_id/snapshot_policy", |r| {
+            named_request_span(
+                r,
+                handle_tenant_snapshot_policy_delete,
+                RequestName("control_v1_tenant_snapshot_policy"),
+            )
+        })
         .put("/control/v1/preferred_azs", |r| {
             named_request_span(
                 r,
@@ -2579,6 +2821,30 @@ pub fn make_router(
                 )
             },
         )
+        .put(
+            "/v1/tenant/:tenant_id/timeline/:timeline_id/undelete",
+            |r| {
+                tenant_service_handler(
+                    r,
+                    handle_tenant_timeline_undelete,
+                    RequestName("v1_tenant_timeline_undelete"),
+                )
+            },
+        )
+        .put("/v1/tenant/:tenant_id/timeline/:timeline_id/export", |r| {
+            tenant_service_handler(
+                r,
+                handle_tenant_timeline_export,
+                RequestName("v1_tenant_timeline_export"),
+            )
+        })
+        .get("/v1/tenant/:tenant_id/timeline/:timeline_id/export", |r| {
+            tenant_service_handler(
+                r,
+                handle_tenant_timeline_export_status,
+                RequestName("v1_tenant_timeline_export"),
+            )
+        })
         .put(
             "/v1/tenant/:tenant_id/timeline/:timeline_id/detach_ancestor",
             |r| {
@@ -2589,6 +2855,13 @@ pub fn make_router(
                 )
             },
         )
+        .put("/v1/tenant/:tenant_id/timeline/:timeline_id/reset", |r| {
+            tenant_service_handler(
+                r,
+                handle_tenant_timeline_reset,
+                RequestName("v1_tenant_timeline_reset"),
+            )
+        })
         .post(
             "/v1/tenant/:tenant_id/timeline/:timeline_id/block_gc",
             |r| {
@@ -23,6 +23,7 @@
 mod scheduler;
 mod schema;
 pub mod service;
+mod snapshot_policy;
 mod tenant_shard;
 mod timeline_import;
 
@@ -174,6 +174,11 @@ struct Cli {
     #[arg(long)]
     max_secondary_lag_bytes: Option<u64>,
 
+    /// Minimum access-weighted heatmap coverage, in percent, for a secondary location to be
+    /// attached during drains and optimizations
+    #[arg(long)]
+    min_secondary_readiness_pct: Option<u8>,
+
     /// Period with which to send heartbeats to registered nodes
     #[arg(long)]
     heartbeat_interval: Option<humantime::Duration>,
@@ -217,6 +222,13 @@ struct Cli {
     #[arg(long, default_value = "3", value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
     timeline_safekeeper_count: usize,
 
+    /// Make the last of the safekeepers choosen for a new timeline a witness
+    /// member: it participates in elections, but stores only WAL positions, not
+    /// WAL itself. With 3 safekeepers per timeline, that gives 2 data members
+    /// and 1 witness.
+    #[arg(long, default_value = "false", action=ArgAction::Set)]
+    timeline_safekeeper_witness: bool,
+
     /// When set, actively checks and initiates heatmap downloads/uploads during reconciliation.
     /// This speed up migrations by avoiding the default wait for the heatmap download interval.
     /// Primarily useful for testing to reduce test execution time.
@@ -408,6 +420,13 @@ async fn async_main() -> anyhow::Result<()> {
         }
     }
 
+    if args.timeline_safekeeper_witness && args.timeline_safekeeper_count < 3 {
+        // Otherwise the only data member would be a single point of failure.
+        anyhow::bail!(
+            "`--timeline-safekeeper-witness` requires at least 3 safekeepers per timeline"
+        );
+    }
+
     let ssl_ca_certs = match args.ssl_ca_file.as_ref() {
         Some(ssl_ca_file) => {
             tracing::info!("Using ssl root CA file: {ssl_ca_file:?}");
@@ -459,6 +478,7 @@ async fn async_main() -> anyhow::Result<()> {
         initial_split_shards: args.initial_split_shards,
         neon_local_repo_dir: args.neon_local_repo_dir,
         max_secondary_lag_bytes: args.max_secondary_lag_bytes,
+        min_secondary_readiness_pct: args.min_secondary_readiness_pct,
         heartbeat_interval: args
             .heartbeat_interval
             .map(humantime::Duration::into)
@@ -475,6 +495,7 @@ async fn async_main() -> anyhow::Result<()> {
         timelines_onto_safekeepers: args.timelines_onto_safekeepers,
         use_local_compute_notifications: args.use_local_compute_notifications,
         timeline_safekeeper_count: args.timeline_safekeeper_count,
+        timeline_safekeeper_witness: args.timeline_safekeeper_witness,
         posthog_config: posthog_config.clone(),
         kick_secondary_downloads: args.kick_secondary_downloads,
         shard_split_request_timeout: args
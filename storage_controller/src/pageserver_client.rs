@@ -2,10 +2,12 @@
 
 use pageserver_api::models::detach_ancestor::AncestorDetached;
 use pageserver_api::models::{
-    DetachBehavior, LocationConfig, LocationConfigListResponse, LsnLease, PageserverUtilization,
-    SecondaryProgress, TenantScanRemoteStorageResponse, TenantShardSplitRequest,
-    TenantShardSplitResponse, TenantWaitLsnRequest, TimelineArchivalConfigRequest,
-    TimelineCreateRequest, TimelineInfo, TopTenantShardsRequest, TopTenantShardsResponse,
+    DetachBehavior, LayerRewriteRequest, LocationConfig, LocationConfigListResponse, LsnLease,
+    PageserverUtilization, SecondaryProgress, TenantScanRemoteStorageResponse,
+    TenantShardSplitRequest, TenantShardSplitResponse, TenantWaitLsnRequest,
+    TimelineArchivalConfigRequest, TimelineCreateRequest, TimelineExportRequest,
+    TimelineExportStatus, TimelineInfo, TimelineLayerRewriteStatus, TimelineResetRequest,
+    TopTenantShardsRequest, TopTenantShardsResponse,
 };
 use pageserver_api::shard::TenantShardId;
 use pageserver_client::BlockUnblock;
@@ -253,6 +255,21 @@ pub(crate) async fn timeline_delete(
         )
     }
 
+    pub(crate) async fn timeline_undelete(
+        &self,
+        tenant_shard_id: TenantShardId,
+        timeline_id: TimelineId,
+    ) -> Result<()> {
+        measured_request!(
+            "timeline_undelete",
+            crate::metrics::Method::Put,
+            &self.node_id_label,
+            self.inner
+                .timeline_undelete(tenant_shard_id, timeline_id)
+                .await
+        )
+    }
+
     pub(crate) async fn timeline_lease_lsn(
         &self,
         tenant_shard_id: TenantShardId,
@@ -269,7 +286,6 @@ pub(crate) async fn timeline_lease_lsn(
         )
     }
 
-    #[allow(unused)]
     pub(crate) async fn timeline_detail(
         &self,
         tenant_shard_id: TenantShardId,
@@ -342,6 +358,74 @@ pub(crate) async fn timeline_detach_ancestor(
         )
     }
 
+    pub(crate) async fn timeline_reset(
+        &self,
+        tenant_shard_id: TenantShardId,
+        timeline_id: TimelineId,
+        req: &TimelineResetRequest,
+    ) -> Result<()> {
+        measured_request!(
+            "timeline_reset",
+            crate::metrics::Method::Put,
+            &self.node_id_label,
+            self.inner
+                .timeline_reset(tenant_shard_id, timeline_id, req)
+                .await
+        )
+    }
+
+    pub(crate) async fn timeline_export(
+        &self,
+        tenant_shard_id: TenantShardId,
+        timeline_id: TimelineId,
+        req: &TimelineExportRequest,
+    ) -> Result<TimelineExportStatus> {
+        measured_request!(
+            "timeline_export",
+            crate::metrics::Method::Put,
+            &self.node_id_label,
+            self.inner
+                .timeline_export(tenant_shard_id, timeline_id, req)
+                .await
+        )
+    }
+
+    pub(crate) async fn timeline_export_status(
+        &self,
+        tenant_shard_id: TenantShardId,
+        timeline_id: TimelineId,
+    ) -> Result<TimelineExportStatus> {
+        measured_request!(
+            "timeline_export",
+            crate::metrics::Method::Get,
+            &self.node_id_label,
+            self.inner
+                .timeline_export_status(tenant_shard_id, timeline_id)
+                .await
+        )
+    }
+
+    pub(crate) async fn layer_rewrite(
+        &self,
+        req: &LayerRewriteRequest,
+    ) -> Result<Vec<TimelineLayerRewriteStatus>> {
+        measured_request!(
+            "layer_rewrite",
+            crate::metrics::Method::Put,
+            &self.node_id_label,
+            self.inner.layer_rewrite(req).await
+        )
+    }
+
+    pub(crate) async fn layer_rewrite_status(&self) -> Result<Vec<TimelineLayerRewriteStatus>> {
+        measured_request!(
+            "layer_rewrite",
+            crate::metrics::Method::Get,
+            &self.node_id_label,
+            self.inner.layer_rewrite_status().await
+        )
+    }
+
     pub(crate) async fn timeline_block_unblock_gc(
         &self,
         tenant_shard_id: TenantShardId,
@@ -41,6 +41,7 @@
     DatabaseQueryErrorLabelGroup, DatabaseQueryLatencyLabelGroup, METRICS_REGISTRY,
 };
 use crate::node::Node;
+use crate::snapshot_policy::SnapshotPolicy;
 use crate::timeline_import::{
     TimelineImport, TimelineImportUpdateError, TimelineImportUpdateFollowUp,
 };
@@ -143,6 +144,10 @@ pub(crate) enum DatabaseOperation {
     DeleteTimelineImport,
     ListTimelineImports,
     IsTenantImportingTimeline,
+    UpsertSnapshotPolicy,
+    UpdateSnapshotPolicyState,
+    ListSnapshotPolicies,
+    DeleteSnapshotPolicy,
 }
 
 #[must_use]
@@ -625,6 +630,13 @@ pub(crate) async fn delete_tenant(&self, del_tenant_id: TenantId) -> DatabaseRes
                     .filter(tenant_id.eq(del_tenant_id.to_string()))
                     .execute(conn)
                     .await?;
+                diesel::delete(crate::schema::tenant_snapshot_policies::table)
+                    .filter(
+                        crate::schema::tenant_snapshot_policies::tenant_id
+                            .eq(del_tenant_id.to_string()),
+                    )
+                    .execute(conn)
+                    .await?;
                 Ok(())
             })
         })
@@ -1507,6 +1519,7 @@ pub(crate) async fn update_timeline_unsafe(
     /// The `new_generation` must be the next (+1) generation after the one in the database.
     /// Also inserts reconcile_requests to safekeeper_timeline_pending_ops table in the same
     /// transaction.
+    #[allow(clippy::too_many_arguments)]
     pub(crate) async fn update_timeline_membership(
         &self,
         tenant_id: TenantId,
@@ -1514,6 +1527,7 @@ pub(crate) async fn update_timeline_membership(
         new_generation: SafekeeperGeneration,
         sk_set: &[NodeId],
         new_sk_set: Option<&[NodeId]>,
+        sk_witness_set: &[i64],
         reconcile_requests: &[TimelinePendingOpPersistence],
     ) -> DatabaseResult<()> {
         use crate::schema::safekeeper_timeline_pending_ops as stpo;
@@ -1536,6 +1550,7 @@ pub(crate) async fn update_timeline_membership(
                             .eq(sk_set.iter().map(|id| id.0 as i64).collect::<Vec<_>>()),
                         timelines::new_sk_set.eq(new_sk_set
                             .map(|set| set.iter().map(|id| id.0 as i64).collect::<Vec<_>>())),
+                        timelines::sk_witness_set.eq(sk_witness_set.to_vec()),
                     ))
                     .execute(conn)
                     .await?;
@@ -2141,6 +2156,119 @@ pub(crate) async fn is_tenant_importing_timeline(
         })
         .await
     }
+
+    /// Insert the snapshot policy of a tenant, or replace the policy of an existing one. The state
+    /// of an existing policy is owned by the background loop and left untouched.
+    pub(crate) async fn upsert_snapshot_policy(
+        &self,
+        policy: &SnapshotPolicy,
+    ) -> DatabaseResult<()> {
+        use crate::schema::tenant_snapshot_policies::dsl;
+
+        let persistent = policy.to_persistent();
+        self.with_measured_conn(DatabaseOperation::UpsertSnapshotPolicy, move |conn| {
+            Box::pin({
+                let persistent = persistent.clone();
+                async move {
+                    diesel::insert_into(crate::schema::tenant_snapshot_policies::table)
+                        .values(&persistent)
+                        .on_conflict(dsl::tenant_id)
+                        .do_update()
+                        .set(dsl::policy.eq(&persistent.policy))
+                        .execute(conn)
+                        .await?;
+                    Ok(())
+                }
+            })
+        })
+        .await
+    }
+
+    /// Persist the state of a snapshot policy, leaving the policy itself untouched.
+    ///
+    /// Returns false if the policy was removed in the meantime.
+    pub(crate) async fn update_snapshot_policy_state(
+        &self,
+        policy: &SnapshotPolicy,
+    ) -> DatabaseResult<bool> {
+        use crate::schema::tenant_snapshot_policies::dsl;
+
+        let persistent = policy.to_persistent();
+        self.with_measured_conn(DatabaseOperation::UpdateSnapshotPolicyState, move |conn| {
+            Box::pin({
+                let persistent = persistent.clone();
+                async move {
+                    let updated = diesel::update(dsl::tenant_snapshot_policies)
+                        .filter(dsl::tenant_id.eq(&persistent.tenant_id))
+                        .set(dsl::state.eq(&persistent.state))
+                        .execute(conn)
+                        .await?;
+                    Ok(updated == 1)
+                }
+            })
+        })
+        .await
+    }
+
+    pub(crate) async fn list_snapshot_policies(&self) -> DatabaseResult<Vec<SnapshotPolicy>> {
+        use crate::schema::tenant_snapshot_policies::dsl;
+        let persistent = self
+            .with_measured_conn(DatabaseOperation::ListSnapshotPolicies, move |conn| {
+                Box::pin(async move {
+                    let from_db: Vec<TenantSnapshotPolicyPersistence> =
+                        dsl::tenant_snapshot_policies.load(conn).await?;
+                    Ok(from_db)
+                })
+            })
+            .await?;
+
+        persistent
+            .into_iter()
+            .map(SnapshotPolicy::from_persistent)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| DatabaseError::Logical(format!("failed to deserialize policy: {err}")))
+    }
+
+    pub(crate) async fn get_snapshot_policy(
+        &self,
+        tenant_id: TenantId,
+    ) -> DatabaseResult<Option<SnapshotPolicy>> {
+        use crate::schema::tenant_snapshot_policies::dsl;
+        let persistent = self
+            .with_measured_conn(DatabaseOperation::ListSnapshotPolicies, move |conn| {
+                Box::pin(async move {
+                    let from_db: Option<TenantSnapshotPolicyPersistence> =
+                        dsl::tenant_snapshot_policies
+                            .filter(dsl::tenant_id.eq(tenant_id.to_string()))
+                            .first(conn)
+                            .await
+                            .optional()?;
+                    Ok(from_db)
+                })
+            })
+            .await?;
+
+        persistent
+            .map(SnapshotPolicy::from_persistent)
+            .transpose()
+            .map_err(|err| DatabaseError::Logical(format!("failed to deserialize policy: {err}")))
+    }
+
+    /// Returns false if the tenant had no snapshot policy.
+    pub(crate) async fn delete_snapshot_policy(&self, tenant_id: TenantId) -> DatabaseResult<bool> {
+        use crate::schema::tenant_snapshot_policies::dsl;
+
+        self.with_measured_conn(DatabaseOperation::DeleteSnapshotPolicy, move |conn| {
+            Box::pin(async move {
+                let deleted = diesel::delete(dsl::tenant_snapshot_policies)
+                    .filter(dsl::tenant_id.eq(tenant_id.to_string()))
+                    .execute(conn)
+                    .await?;
+                Ok(deleted == 1)
+            })
+        })
+        .await
+    }
 }
 
 pub(crate) fn load_certs() -> anyhow::Result<Arc<rustls::RootCertStore>> {
@@ -2611,6 +2739,8 @@ pub(crate) struct TimelinePersistence {
     pub(crate) cplane_notified_generation: i32,
     pub(crate) deleted_at: Option<chrono::DateTime<chrono::Utc>>,
     pub(crate) sk_set_notified_generation: i32,
+    /// Members of `sk_set` which are witnesses, i.e. don't store WAL.
+    pub(crate) sk_witness_set: Vec<i64>,
 }
 
 /// This is separate from [TimelinePersistence] only because postgres allows NULLs
@@ -2630,6 +2760,7 @@ pub(crate) struct TimelineFromDb {
     pub(crate) cplane_notified_generation: i32,
     pub(crate) deleted_at: Option<chrono::DateTime<chrono::Utc>>,
     pub(crate) sk_set_notified_generation: i32,
+    pub(crate) sk_witness_set: Vec<Option<i64>>,
 }
 
 impl TimelineFromDb {
@@ -2640,6 +2771,11 @@ fn into_persistence(self) -> TimelinePersistence {
         let new_sk_set = self
             .new_sk_set
             .map(|s| s.into_iter().flatten().collect::<Vec<_>>());
+        let sk_witness_set = self
+            .sk_witness_set
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>();
         TimelinePersistence {
             tenant_id: self.tenant_id,
             timeline_id: self.timeline_id,
@@ -2650,6 +2786,7 @@ fn into_persistence(self) -> TimelinePersistence {
             cplane_notified_generation: self.cplane_notified_generation,
             deleted_at: self.deleted_at,
             sk_set_notified_generation: self.sk_set_notified_generation,
+            sk_witness_set,
         }
     }
 }
@@ -2664,6 +2801,7 @@ pub(crate) struct TimelineUpdate {
     pub(crate) start_lsn: LsnWrapper,
     pub(crate) sk_set: Vec<i64>,
     pub(crate) new_sk_set: Option<Vec<i64>>,
+    pub(crate) sk_witness_set: Vec<i64>,
 }
 
 #[derive(Insertable, AsChangeset, Queryable, Selectable, Clone)]
@@ -2717,6 +2855,14 @@ fn to_sql<'b>(
     }
 }
 
+#[derive(Serialize, Deserialize, Queryable, Selectable, Insertable, Eq, PartialEq, Clone)]
+#[diesel(table_name = crate::schema::tenant_snapshot_policies)]
+pub(crate) struct TenantSnapshotPolicyPersistence {
+    pub(crate) tenant_id: String,
+    pub(crate) policy: serde_json::Value,
+    pub(crate) state: serde_json::Value,
+}
+
 #[derive(Serialize, Deserialize, Queryable, Selectable, Insertable, Eq, PartialEq, Clone)]
 #[diesel(table_name = crate::schema::timeline_imports)]
 pub(crate) struct TimelineImportPersistence {
@@ -517,6 +517,24 @@ async fn secondary_download(
                 );
                 return Ok(());
             } else if status == StatusCode::ACCEPTED {
+                if self.service_config.min_secondary_readiness_pct.is_some()
+                    && self.service_config.secondary_is_ready(&progress)
+                {
+                    // The most frequently accessed layers are downloaded first: once they are
+                    // on disk, the rest of the download is not worth delaying the cutover for.
+                    tracing::info!(
+                        "Downloads to {} ready enough (readiness {:.3}, estimated read latency impact {:?}ms): {}/{} layers, {}/{} bytes",
+                        node,
+                        progress.readiness(),
+                        progress.est_read_latency_impact_ms,
+                        progress.layers_downloaded,
+                        progress.layers_total,
+                        progress.bytes_downloaded,
+                        progress.bytes_total
+                    );
+                    return Ok(());
+                }
+
                 let total_runtime = started_at.elapsed();
                 if total_runtime > total_download_timeout {
                     tracing::warn!(
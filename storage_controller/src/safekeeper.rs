@@ -2,7 +2,7 @@
 
 use pageserver_api::controller_api::{SafekeeperDescribeResponse, SkSchedulingPolicy};
 use reqwest::StatusCode;
-use safekeeper_api::membership::SafekeeperId;
+use safekeeper_api::membership::{MemberRole, SafekeeperId};
 use safekeeper_client::mgmt_api;
 use tokio_util::sync::CancellationToken;
 use utils::backoff;
@@ -98,6 +98,7 @@ pub(crate) fn get_safekeeper_id(&self) -> SafekeeperId {
             id: self.id,
             host: self.skp.host.clone(),
             pg_port: self.skp.port as u16,
+            role: MemberRole::Data,
         }
     }
     /// Perform an operation (which is given a [`SafekeeperClient`]) with retries
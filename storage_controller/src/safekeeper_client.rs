@@ -1,7 +1,7 @@
 use safekeeper_api::models::{
     self, PullTimelineRequest, PullTimelineResponse, SafekeeperUtilization, TimelineCreateRequest,
 };
-use safekeeper_client::mgmt_api::{Client, Result};
+use safekeeper_client::mgmt_api::{Client, Error, Result};
 use utils::id::{NodeId, TenantId, TimelineId};
 use utils::logging::SecretString;
 
@@ -143,7 +143,6 @@ pub(crate) async fn pull_timeline(
         )
     }
 
-    #[allow(unused)]
     pub(crate) async fn bump_timeline_term(
         &self,
         tenant_id: TenantId,
@@ -160,6 +159,38 @@ pub(crate) async fn bump_timeline_term(
         )
     }
 
+    pub(crate) async fn reset_timeline_wal(
+        &self,
+        tenant_id: TenantId,
+        timeline_id: TimelineId,
+        req: &models::TimelineResetWalRequest,
+    ) -> Result<models::TimelineResetWalResponse> {
+        measured_request!(
+            "reset_wal",
+            crate::metrics::Method::Post,
+            &self.node_id_label,
+            self.inner
+                .reset_timeline_wal(tenant_id, timeline_id, req)
+                .await
+        )
+    }
+
+    pub(crate) async fn timeline_status(
+        &self,
+        tenant_id: TenantId,
+        timeline_id: TimelineId,
+    ) -> Result<models::TimelineStatus> {
+        measured_request!(
+            "timeline_status",
+            crate::metrics::Method::Get,
+            &self.node_id_label,
+            match self.inner.timeline_status(tenant_id, timeline_id).await {
+                Ok(resp) => resp.json().await.map_err(Error::ReceiveBody),
+                Err(e) => Err(e),
+            }
+        )
+    }
+
     pub(crate) async fn get_utilization(&self) -> Result<SafekeeperUtilization> {
         measured_request!(
             "utilization",
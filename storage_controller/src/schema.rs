@@ -97,6 +97,14 @@ pub mod sql_types {
     }
 }
 
+diesel::table! {
+    tenant_snapshot_policies (tenant_id) {
+        tenant_id -> Varchar,
+        policy -> Jsonb,
+        state -> Jsonb,
+    }
+}
+
 diesel::table! {
     timeline_imports (tenant_id, timeline_id) {
         tenant_id -> Varchar,
@@ -119,6 +127,7 @@ pub mod sql_types {
         cplane_notified_generation -> Int4,
         deleted_at -> Nullable<Timestamptz>,
         sk_set_notified_generation -> Int4,
+        sk_witness_set -> Array<Nullable<Int8>>,
     }
 }
 
@@ -131,6 +140,7 @@ pub mod sql_types {
     safekeeper_timeline_pending_ops,
     safekeepers,
     tenant_shards,
+    tenant_snapshot_policies,
     timeline_imports,
     timelines,
 );
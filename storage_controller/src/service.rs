@@ -2,6 +2,7 @@
 pub mod feature_flag;
 pub(crate) mod safekeeper_reconciler;
 mod safekeeper_service;
+mod snapshot_service;
 mod tenant_shard_iterator;
 
 use std::borrow::Cow;
@@ -27,21 +28,24 @@
 use itertools::Itertools;
 use pageserver_api::config::PostHogConfig;
 use pageserver_api::controller_api::{
-    AvailabilityZone, MetadataHealthRecord, MetadataHealthUpdateRequest, NodeAvailability,
-    NodeRegisterRequest, NodeSchedulingPolicy, NodeShard, NodeShardResponse, PlacementPolicy,
-    ShardSchedulingPolicy, ShardsPreferredAzsRequest, ShardsPreferredAzsResponse,
-    SkSchedulingPolicy, TenantCreateRequest, TenantCreateResponse, TenantCreateResponseShard,
-    TenantDescribeResponse, TenantDescribeResponseShard, TenantLocateResponse, TenantPolicyRequest,
-    TenantShardMigrateRequest, TenantShardMigrateResponse, TenantTimelineDescribeResponse,
+    AvailabilityZone, LayerRewriteResponse, MetadataHealthRecord, MetadataHealthUpdateRequest,
+    NodeAvailability, NodeLayerRewriteStatus, NodeRegisterRequest, NodeSchedulingPolicy, NodeShard,
+    NodeShardResponse, PlacementPolicy, ShardSchedulingPolicy, ShardsPreferredAzsRequest,
+    ShardsPreferredAzsResponse, SkSchedulingPolicy, TenantCreateRequest, TenantCreateResponse,
+    TenantCreateResponseShard, TenantDescribeResponse, TenantDescribeResponseShard,
+    TenantLocateResponse, TenantPolicyRequest, TenantShardMigrateRequest,
+    TenantShardMigrateResponse, TenantTimelineDescribeResponse, TimelineExportResponse,
+    TimelineExportShardStatus,
 };
 use pageserver_api::models::{
-    self, DetachBehavior, LocationConfig, LocationConfigListResponse, LocationConfigMode, LsnLease,
-    PageserverUtilization, SecondaryProgress, ShardImportStatus, ShardParameters, TenantConfig,
-    TenantConfigPatchRequest, TenantConfigRequest, TenantLocationConfigRequest,
-    TenantLocationConfigResponse, TenantShardLocation, TenantShardSplitRequest,
-    TenantShardSplitResponse, TenantSorting, TenantTimeTravelRequest,
-    TimelineArchivalConfigRequest, TimelineCreateRequest, TimelineCreateResponseStorcon,
-    TimelineInfo, TopTenantShardItem, TopTenantShardsRequest,
+    self, DetachBehavior, LayerRewriteRequest, LocationConfig, LocationConfigListResponse,
+    LocationConfigMode, LsnLease, PageserverUtilization, SecondaryProgress, ShardImportStatus,
+    ShardParameters, TenantConfig, TenantConfigPatchRequest, TenantConfigRequest,
+    TenantLocationConfigRequest, TenantLocationConfigResponse, TenantShardLocation,
+    TenantShardSplitRequest, TenantShardSplitResponse, TenantSorting, TenantTimeTravelRequest,
+    TimelineArchivalConfigRequest, TimelineArchivalState, TimelineCreateRequest,
+    TimelineCreateResponseStorcon, TimelineExportRequest, TimelineInfo, TimelineResetRequest,
+    TopTenantShardItem, TopTenantShardsRequest,
 };
 use pageserver_api::shard::{
     DEFAULT_STRIPE_SIZE, ShardCount, ShardIdentity, ShardNumber, ShardStripeSize, TenantShardId,
@@ -120,6 +124,9 @@
 // input generation from future requests as authoritative.
 const INITIAL_GENERATION: Generation = Generation::new(0);
 
+/// How long to wait for a pageserver to start or list the layer rewrites of all its timelines.
+const LAYER_REWRITE_NODE_TIMEOUT: Duration = Duration::from_secs(60);
+
 /// How long [`Service::startup_reconcile`] is allowed to take before it should give
 /// up on unresponsive pageservers and proceed.
 pub(crate) const STARTUP_RECONCILE_TIMEOUT: Duration = Duration::from_secs(30);
@@ -155,9 +162,12 @@ enum TenantOperations {
     SecondaryDownload,
     TimelineCreate,
     TimelineDelete,
+    TimelineUndelete,
     AttachHook,
     TimelineArchivalConfig,
     TimelineDetachAncestor,
+    TimelineExport,
+    TimelineReset,
     TimelineGcBlockUnblock,
     DropDetached,
     DownloadHeatmapLayers,
@@ -457,6 +467,11 @@ pub struct Config {
     // upgraded to primary.
     pub max_secondary_lag_bytes: Option<u64>,
 
+    // Minimum readiness in percent (see [`SecondaryProgress::readiness`]) of a secondary location before
+    // it is attached during a drain or an optimization, and before a live migration cuts over
+    // to it without waiting for its downloads to complete.
+    pub min_secondary_readiness_pct: Option<u8>,
+
     pub heartbeat_interval: Duration,
 
     pub address_for_peers: Option<Uri>,
@@ -479,6 +494,10 @@ pub struct Config {
     /// Safekeepers will be choosen from different availability zones.
     pub timeline_safekeeper_count: usize,
 
+    /// Make one of the safekeepers choosen for a new timeline a witness
+    /// member, which votes but doesn't store WAL.
+    pub timeline_safekeeper_witness: bool,
+
     /// PostHog integration config
     pub posthog_config: Option<PostHogConfig>,
 
@@ -492,6 +511,17 @@ pub struct Config {
     pub handle_ps_local_disk_loss: bool,
 }
 
+impl Config {
+    /// Whether a secondary location with this progress is ready enough to be attached, see
+    /// [`Self::min_secondary_readiness_pct`].
+    pub(crate) fn secondary_is_ready(&self, progress: &SecondaryProgress) -> bool {
+        match self.min_secondary_readiness_pct {
+            Some(pct) => progress.readiness() * 100.0 >= pct as f64,
+            None => true,
+        }
+    }
+}
+
 impl From<DatabaseError> for ApiError {
     fn from(err: DatabaseError) -> ApiError {
         match err {
@@ -2021,6 +2051,15 @@ pub async fn spawn(config: Config, persistence: Arc<Persistence>) -> anyhow::Res
             }
         });
 
+        tokio::task::spawn({
+            let this = this.clone();
+            let startup_complete = startup_complete.clone();
+            async move {
+                startup_complete.wait().await;
+                this.snapshot_policies_background().await;
+            }
+        });
+
         // Check that there is enough safekeepers configured that we can create new timelines
         let test_sk_res_str = match this.safekeepers_for_new_timeline().await {
             Ok(v) => format!("Ok({v:?})"),
@@ -2028,6 +2067,7 @@ pub async fn spawn(config: Config, persistence: Arc<Persistence>) -> anyhow::Res
         };
         tracing::info!(
             timeline_safekeeper_count = config.timeline_safekeeper_count,
+            timeline_safekeeper_witness = config.timeline_safekeeper_witness,
             timelines_onto_safekeepers = config.timelines_onto_safekeepers,
             "viability test result (test timeline creation on safekeepers): {test_sk_res_str}",
         );
@@ -3612,6 +3652,12 @@ pub(crate) async fn tenant_secondary_download(
                     aggregate_progress.layers_total += progress.layers_total;
                     aggregate_progress.bytes_downloaded += progress.bytes_downloaded;
                     aggregate_progress.bytes_total += progress.bytes_total;
+                    aggregate_progress.access_rate_downloaded += progress.access_rate_downloaded;
+                    aggregate_progress.access_rate_total += progress.access_rate_total;
+                    aggregate_progress.layer_download_ms = std::cmp::max(
+                        aggregate_progress.layer_download_ms,
+                        progress.layer_download_ms,
+                    );
                     aggregate_progress.heatmap_mtime =
                         std::cmp::max(aggregate_progress.heatmap_mtime, progress.heatmap_mtime);
                     aggregate_status = match aggregate_status {
@@ -3626,6 +3672,8 @@ pub(crate) async fn tenant_secondary_download(
             }
         }
 
+        aggregate_progress.refresh_read_latency_impact();
+
         // If any of the shards return 202, indicate our result as 202.
         match aggregate_status {
             None => {
@@ -4647,6 +4695,210 @@ async fn config_one(
         }).await?
     }
 
+    /// Starts exporting a timeline to remote storage on all shards. Shard zero goes first, so that
+    /// the other shards export at the LSN it picked if the caller didn't provide one.
+    pub(crate) async fn tenant_timeline_export(
+        &self,
+        tenant_id: TenantId,
+        timeline_id: TimelineId,
+        req: TimelineExportRequest,
+    ) -> Result<TimelineExportResponse, ApiError> {
+        tracing::info!("Exporting timeline {tenant_id}/{timeline_id}");
+
+        let _tenant_lock = trace_shared_lock(
+            &self.tenant_op_locks,
+            tenant_id,
+            TenantOperations::TimelineExport,
+        )
+        .await;
+
+        self.tenant_remote_mutation(tenant_id, move |mut targets| async move {
+            if targets.0.is_empty() {
+                return Err(ApiError::NotFound(
+                    anyhow::anyhow!("Tenant not found").into(),
+                ));
+            }
+
+            let (shard_zero_tid, shard_zero_locations) =
+                targets.0.pop_first().expect("Must have at least one shard");
+            assert!(shard_zero_tid.is_shard_zero());
+
+            async fn export_one(
+                tenant_shard_id: TenantShardId,
+                timeline_id: TimelineId,
+                node: Node,
+                http_client: reqwest::Client,
+                jwt: Option<String>,
+                req: TimelineExportRequest,
+            ) -> Result<TimelineExportShardStatus, ApiError> {
+                tracing::info!(
+                    "Exporting timeline on shard {tenant_shard_id}/{timeline_id}, attached to node {node}",
+                );
+
+                let client = PageserverClient::new(node.get_id(), http_client, node.base_url(), jwt.as_deref());
+
+                let status = client
+                    .timeline_export(tenant_shard_id, timeline_id, &req)
+                    .await
+                    .map_err(|e| passthrough_api_error(&node, e))?;
+
+                Ok(TimelineExportShardStatus { tenant_shard_id, status })
+            }
+
+            let shard_zero_status = export_one(
+                shard_zero_tid,
+                timeline_id,
+                shard_zero_locations.latest.node,
+                self.http_client.clone(),
+                self.config.pageserver_jwt_token.clone(),
+                req.clone(),
+            )
+            .await?;
+
+            let req = TimelineExportRequest {
+                lsn: Some(shard_zero_status.status.lsn()),
+                ..req
+            };
+
+            let locations = targets.0.iter().map(|t| (*t.0, t.1.latest.node.clone())).collect();
+            let mut shards = vec![shard_zero_status];
+            shards.extend(
+                self.tenant_for_shards(locations, |tenant_shard_id, node| {
+                    futures::FutureExt::boxed(export_one(
+                        tenant_shard_id,
+                        timeline_id,
+                        node,
+                        self.http_client.clone(),
+                        self.config.pageserver_jwt_token.clone(),
+                        req.clone(),
+                    ))
+                })
+                .await?,
+            );
+
+            Ok(TimelineExportResponse { shards })
+        }).await?
+    }
+
+    pub(crate) async fn tenant_timeline_export_status(
+        &self,
+        tenant_id: TenantId,
+        timeline_id: TimelineId,
+    ) -> Result<TimelineExportResponse, ApiError> {
+        self.tenant_remote_mutation(tenant_id, move |targets| async move {
+            if targets.0.is_empty() {
+                return Err(ApiError::NotFound(
+                    anyhow::anyhow!("Tenant not found").into(),
+                ));
+            }
+
+            let locations = targets
+                .0
+                .iter()
+                .map(|t| (*t.0, t.1.latest.node.clone()))
+                .collect();
+            let jwt = self.config.pageserver_jwt_token.clone();
+            let shards = self
+                .tenant_for_shards(locations, |tenant_shard_id, node| {
+                    let client = PageserverClient::new(
+                        node.get_id(),
+                        self.http_client.clone(),
+                        node.base_url(),
+                        jwt.as_deref(),
+                    );
+                    Box::pin(async move {
+                        let status = client
+                            .timeline_export_status(tenant_shard_id, timeline_id)
+                            .await
+                            .map_err(|e| passthrough_api_error(&node, e))?;
+                        Ok(TimelineExportShardStatus {
+                            tenant_shard_id,
+                            status,
+                        })
+                    })
+                })
+                .await?;
+
+            Ok(TimelineExportResponse { shards })
+        })
+        .await?
+    }
+
+    /// Start a layer rewrite on all timelines of all pageservers. Pageservers that could not be
+    /// reached are reported in the response: repeating the request is safe, it does not restart
+    /// the rewrites in progress.
+    pub(crate) async fn layer_rewrite(
+        &self,
+        req: LayerRewriteRequest,
+    ) -> Result<LayerRewriteResponse, ApiError> {
+        self.layer_rewrite_on_all_nodes(Some(&req)).await
+    }
+
+    pub(crate) async fn layer_rewrite_status(&self) -> Result<LayerRewriteResponse, ApiError> {
+        self.layer_rewrite_on_all_nodes(None).await
+    }
+
+    /// Start layer rewrites on all nodes if `req` is set, otherwise only collect their status.
+    async fn layer_rewrite_on_all_nodes(
+        &self,
+        req: Option<&LayerRewriteRequest>,
+    ) -> Result<LayerRewriteResponse, ApiError> {
+        let nodes = {
+            let locked = self.inner.read().unwrap();
+            locked.nodes.clone()
+        };
+
+        let mut node_futs = FuturesUnordered::new();
+        for node in nodes.values() {
+            node_futs.push(async move {
+                let result = if node.is_available() {
+                    node.with_client_retries(
+                        |client| async move {
+                            match req {
+                                Some(req) => client.layer_rewrite(req).await,
+                                None => client.layer_rewrite_status().await,
+                            }
+                        },
+                        &self.http_client,
+                        &self.config.pageserver_jwt_token,
+                        1,
+                        3,
+                        LAYER_REWRITE_NODE_TIMEOUT,
+                        &self.cancel,
+                    )
+                    .await
+                    .unwrap_or(Err(mgmt_api::Error::Cancelled))
+                    .map_err(|e| e.to_string())
+                } else {
+                    Err("node is not available".to_string())
+                };
+
+                match result {
+                    Ok(timelines) => NodeLayerRewriteStatus {
+                        node_id: node.get_id(),
+                        error: None,
+                        timelines,
+                    },
+                    Err(error) => {
+                        tracing::warn!("Layer rewrite request to node {node} failed: {error}");
+                        NodeLayerRewriteStatus {
+                            node_id: node.get_id(),
+                            error: Some(error),
+                            timelines: Vec::new(),
+                        }
+                    }
+                }
+            });
+        }
+
+        let mut nodes: Vec<_> = node_futs.collect().await;
+        if self.cancel.is_cancelled() {
+            return Err(ApiError::ShuttingDown);
+        }
+        nodes.sort_by_key(|n| n.node_id);
+        Ok(LayerRewriteResponse { nodes })
+    }
+
     pub(crate) async fn tenant_timeline_detach_ancestor(
         &self,
         tenant_id: TenantId,
@@ -4750,6 +5002,156 @@ async fn detach_one(
         }).await?
     }
 
+    /// Reset a timeline to an earlier LSN of its own history, keeping the history after the LSN on
+    /// a backup branch.
+    ///
+    /// The computes of the timeline must be stopped. Every step is idempotent, so the request can
+    /// be retried until it succeeds:
+    /// 1. fence the safekeepers, which also tells us the tip of the timeline
+    /// 2. create the backup branch at the tip
+    /// 3. detach the backup branch, which copies the layers of the timeline and reparents its
+    ///    children onto the backup branch
+    /// 4. discard the WAL after the LSN on the safekeepers
+    /// 5. reset the timeline on the pageservers, making it a branch of the backup branch
+    ///
+    /// The safekeepers are reset before the pageservers so that the pageservers cannot ingest the
+    /// discarded WAL again.
+    pub(crate) async fn tenant_timeline_reset(
+        self: &Arc<Self>,
+        tenant_id: TenantId,
+        timeline_id: TimelineId,
+        req: TimelineResetRequest,
+    ) -> Result<(), ApiError> {
+        tracing::info!(
+            lsn=%req.lsn,
+            backup_timeline_id=%req.backup_timeline_id,
+            "Resetting timeline {tenant_id}/{timeline_id}"
+        );
+
+        if req.backup_timeline_id == timeline_id {
+            return Err(ApiError::BadRequest(anyhow::anyhow!(
+                "backup timeline must differ from the timeline"
+            )));
+        }
+
+        // No tenant lock is held over the whole operation: timeline creation and ancestor
+        // detach take their own.
+
+        let (node, shard_zero) = self.tenant_shard0_node(tenant_id).await?;
+        let client = PageserverClient::new(
+            node.get_id(),
+            self.http_client.clone(),
+            node.base_url(),
+            self.config.pageserver_jwt_token.as_deref(),
+        );
+        let timeline_info = client
+            .timeline_detail(shard_zero, timeline_id)
+            .await
+            .map_err(|e| passthrough_api_error(&node, e))?;
+        if timeline_info.ancestor_timeline_id == Some(req.backup_timeline_id)
+            && timeline_info.ancestor_lsn == Some(req.lsn)
+        {
+            // Done already, must not touch the safekeepers again: they have new WAL by now.
+            tracing::info!("Timeline was reset already");
+            return Ok(());
+        }
+
+        let (safekeepers, tip, term) = self
+            .tenant_timeline_fence_safekeepers(tenant_id, timeline_id)
+            .await?;
+        if req.lsn > tip {
+            return Err(ApiError::BadRequest(anyhow::anyhow!(
+                "reset lsn {} is past the end of the timeline at {tip}",
+                req.lsn
+            )));
+        }
+
+        if self
+            .persistence
+            .get_timeline(tenant_id, req.backup_timeline_id)
+            .await?
+            .is_none()
+        {
+            tracing::info!(%tip, "Creating backup timeline {}", req.backup_timeline_id);
+            self.tenant_timeline_create(
+                tenant_id,
+                TimelineCreateRequest {
+                    new_timeline_id: req.backup_timeline_id,
+                    mode: models::TimelineCreateRequestMode::Branch {
+                        ancestor_timeline_id: timeline_id,
+                        ancestor_start_lsn: Some(tip),
+                        pg_version: None,
+                        read_only: false,
+                    },
+                },
+            )
+            .await?;
+        }
+
+        self.tenant_timeline_detach_ancestor(tenant_id, req.backup_timeline_id, None)
+            .await?;
+
+        self.tenant_timeline_reset_safekeepers_wal(
+            &safekeepers,
+            tenant_id,
+            timeline_id,
+            req.lsn,
+            term,
+        )
+        .await?;
+
+        let _tenant_lock = trace_shared_lock(
+            &self.tenant_op_locks,
+            tenant_id,
+            TenantOperations::TimelineReset,
+        )
+        .await;
+
+        self.tenant_remote_mutation(tenant_id, move |targets| async move {
+            if targets.0.is_empty() {
+                return Err(ApiError::NotFound(
+                    anyhow::anyhow!("Tenant not found").into(),
+                ));
+            }
+
+            async fn reset_one(
+                tenant_shard_id: TenantShardId,
+                timeline_id: TimelineId,
+                node: Node,
+                http_client: reqwest::Client,
+                jwt: Option<String>,
+                req: TimelineResetRequest,
+            ) -> Result<(), ApiError> {
+                tracing::info!(
+                    "Resetting timeline on shard {tenant_shard_id}/{timeline_id}, attached to node {node}",
+                );
+
+                let client = PageserverClient::new(node.get_id(), http_client, node.base_url(), jwt.as_deref());
+
+                client
+                    .timeline_reset(tenant_shard_id, timeline_id, &req)
+                    .await
+                    .map_err(|e| passthrough_api_error(&node, e))
+            }
+
+            // no shard needs to go first/last; the operation is idempotent
+            let locations = targets.0.iter().map(|t| (*t.0, t.1.latest.node.clone())).collect();
+            self.tenant_for_shards(locations, |tenant_shard_id, node| {
+                futures::FutureExt::boxed(reset_one(
+                    tenant_shard_id,
+                    timeline_id,
+                    node,
+                    self.http_client.clone(),
+                    self.config.pageserver_jwt_token.clone(),
+                    req.clone(),
+                ))
+            })
+            .await?;
+
+            Ok(())
+        }).await?
+    }
+
     pub(crate) async fn tenant_timeline_block_unblock_gc(
         &self,
         tenant_id: TenantId,
@@ -5360,6 +5762,124 @@ async fn delete_one(
 
         status_code
     }
+
+    /// Restores a timeline that was soft-deleted on the pageservers, see the tenant's
+    /// `timeline_delete_grace_period`. Unlike the pageserver API, this always restores the
+    /// timeline unarchived, so that it can be used right away: if the storage controller manages
+    /// safekeeper timelines, the timeline is created on safekeepers again, starting at the LSN
+    /// that the pageservers persisted at deletion.
+    pub(crate) async fn tenant_timeline_undelete(
+        self: &Arc<Self>,
+        tenant_id: TenantId,
+        timeline_id: TimelineId,
+    ) -> Result<(), ApiError> {
+        tracing::info!("Undeleting timeline {tenant_id}/{timeline_id}");
+
+        let safekeepers = self.config.timelines_onto_safekeepers;
+        if safekeepers {
+            // Pending deletions on safekeepers would race with the recreation below.
+            if let Some(tl) = self
+                .persistence
+                .get_timeline(tenant_id, timeline_id)
+                .await?
+            {
+                if tl.deleted_at.is_some() {
+                    return Err(ApiError::ResourceUnavailable(
+                        "timeline deletion on safekeepers is still in progress".into(),
+                    ));
+                }
+            }
+        }
+
+        let _tenant_lock = trace_shared_lock(
+            &self.tenant_op_locks,
+            tenant_id,
+            TenantOperations::TimelineUndelete,
+        )
+        .await;
+
+        let timeline_info = self.tenant_remote_mutation(tenant_id, move |mut targets| async move {
+            if targets.0.is_empty() {
+                return Err(ApiError::NotFound(
+                    anyhow::anyhow!("Tenant not found").into(),
+                ));
+            }
+
+            async fn undelete_one(
+                tenant_shard_id: TenantShardId,
+                timeline_id: TimelineId,
+                node: Node,
+                http_client: reqwest::Client,
+                jwt: Option<String>,
+            ) -> Result<(), ApiError> {
+                tracing::info!(
+                    "Undeleting timeline on shard {tenant_shard_id}/{timeline_id}, attached to node {node}",
+                );
+
+                let client = PageserverClient::new(node.get_id(), http_client, node.base_url(), jwt.as_deref());
+
+                client
+                    .timeline_undelete(tenant_shard_id, timeline_id)
+                    .await
+                    .map_err(|e| passthrough_api_error(&node, e))?;
+
+                // A no-op unless the timeline was archived before it was deleted.
+                let req = TimelineArchivalConfigRequest {
+                    state: TimelineArchivalState::Unarchived,
+                };
+                client
+                    .timeline_archival_config(tenant_shard_id, timeline_id, &req)
+                    .await
+                    .map_err(|e| match e {
+                        mgmt_api::Error::ApiError(StatusCode::PRECONDITION_FAILED, msg) => {
+                            ApiError::PreconditionFailed(msg.into_boxed_str())
+                        }
+                        _ => passthrough_api_error(&node, e),
+                    })
+            }
+
+            // Both operations are idempotent, so no shard needs to go first.
+            let locations = targets.0.iter().map(|t| (*t.0, t.1.latest.node.clone())).collect();
+            self.tenant_for_shards(locations, |tenant_shard_id, node| {
+                futures::FutureExt::boxed(undelete_one(
+                    tenant_shard_id,
+                    timeline_id,
+                    node,
+                    self.http_client.clone(),
+                    self.config.pageserver_jwt_token.clone(),
+                ))
+            })
+            .await?;
+
+            let (shard_zero_tid, shard_zero_locations) =
+                targets.0.pop_first().expect("Must have at least one shard");
+            assert!(shard_zero_tid.is_shard_zero());
+            let node = shard_zero_locations.latest.node;
+            let client = PageserverClient::new(
+                node.get_id(),
+                self.http_client.clone(),
+                node.base_url(),
+                self.config.pageserver_jwt_token.as_deref(),
+            );
+            client
+                .timeline_detail(shard_zero_tid, timeline_id)
+                .await
+                .map_err(|e| passthrough_api_error(&node, e))
+        }).await??;
+
+        if safekeepers {
+            // A timeline that had been read-only can't be told apart here, so it gets safekeepers
+            // like any other.
+            let read_only = false;
+            self.tenant_timeline_create_safekeepers(tenant_id, &timeline_info, read_only)
+                .instrument(
+                    tracing::info_span!("timeline_create_safekeepers", %tenant_id, %timeline_id),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
     /// When you know the TenantId but not a specific shard, and would like to get the node holding shard 0.
     ///
     /// Returns the node, tenant shard id, and whether it is consistent with the observed state.
@@ -5573,6 +6093,7 @@ fn tenant_describe_impl<'a>(
             stripe_size: shard_zero.shard.stripe_size,
             policy: shard_zero.policy.clone(),
             config: shard_zero.config.clone(),
+            snapshot_policy: None,
         })
     }
 
@@ -9044,6 +9565,7 @@ async fn optimize_all_validate(
                             && progress.bytes_downloaded != progress.bytes_total
                         || progress.bytes_total - progress.bytes_downloaded
                             > DOWNLOAD_FRESHNESS_THRESHOLD
+                        || !self.config.secondary_is_ready(&progress)
                     {
                         tracing::info!(
                             "Skipping migration of {tenant_shard_id} to {node} because secondary isn't ready: {progress:?}"
@@ -9549,11 +10071,11 @@ pub async fn shutdown(&self) {
     /// * Ok(None) if the lag could not be determined from the status,
     /// * Ok(Some(_)) if the lag could be determind
     /// * Err on failures to query the pageserver.
-    async fn secondary_lag(
+    async fn secondary_progress(
         &self,
         secondary: &NodeId,
         tenant_shard_id: TenantShardId,
-    ) -> Result<Option<u64>, mgmt_api::Error> {
+    ) -> Result<Option<SecondaryProgress>, mgmt_api::Error> {
         let nodes = self.inner.read().unwrap().nodes.clone();
         let node = nodes.get(secondary).ok_or(mgmt_api::Error::ApiError(
             StatusCode::NOT_FOUND,
@@ -9573,7 +10095,7 @@ async fn secondary_lag(
             .await
         {
             Some(Ok(status)) => match status.heatmap_mtime {
-                Some(_) => Ok(Some(status.bytes_total - status.bytes_downloaded)),
+                Some(_) => Ok(Some(status)),
                 None => Ok(None),
             },
             Some(Err(e)) => Err(e),
@@ -9658,18 +10180,30 @@ pub(crate) async fn drain_node(
                     }
                 };
 
-                match self.secondary_lag(&dest_node_id, tid).await {
-                    Ok(Some(lag)) if lag <= max_secondary_lag_bytes => {
-                        // The secondary is reasonably up to date.
-                        // Migrate to it
-                    }
-                    Ok(Some(lag)) => {
+                match self.secondary_progress(&dest_node_id, tid).await {
+                    Ok(Some(progress))
+                        if progress.bytes_total - progress.bytes_downloaded
+                            > max_secondary_lag_bytes =>
+                    {
+                        let lag = progress.bytes_total - progress.bytes_downloaded;
                         tracing::info!(
                             tenant_id=%tid.tenant_id, shard_id=%tid.shard_slug(),
                             "Secondary on node {dest_node_id} is lagging by {lag}. Skipping reconcile."
                         );
                         continue;
                     }
+                    Ok(Some(progress)) if !self.config.secondary_is_ready(&progress) => {
+                        tracing::info!(
+                            tenant_id=%tid.tenant_id, shard_id=%tid.shard_slug(),
+                            "Secondary on node {dest_node_id} is not ready (readiness {:.3}). Skipping reconcile.",
+                            progress.readiness()
+                        );
+                        continue;
+                    }
+                    Ok(Some(_)) => {
+                        // The secondary is reasonably up to date.
+                        // Migrate to it
+                    }
                     Ok(None) => {
                         tracing::info!(
                             tenant_id=%tid.tenant_id, shard_id=%tid.shard_slug(),
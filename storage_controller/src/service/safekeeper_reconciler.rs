@@ -143,6 +143,10 @@ pub(crate) async fn load_schedule_requests(
                             // We obviously don't want to pull from ourselves
                             return None;
                         }
+                        if timeline_persist.sk_witness_set.contains(sk_id) {
+                            // Witness has no WAL to pull
+                            return None;
+                        }
                         let Some(sk) = safekeepers.get(&other_node_id) else {
                             tracing::warn!(
                                 "couldn't find safekeeper with pending op id {other_node_id}, not pulling from it"
@@ -370,6 +374,7 @@ async fn reconcile_one(
                     // It could be fixed together with other reconciliation issues:
                     // https://github.com/neondatabase/neon/issues/12189
                     mconf: None,
+                    allow_remote_snapshot: false,
                 };
                 success = self
                     .reconcile_inner(
@@ -378,6 +383,10 @@ async fn reconcile_one(
                         |resp| {
                             if let Some(host) = resp.safekeeper_host {
                                 tracing::info!("pulled timeline from {host} onto {req_host}");
+                            } else if let Some(sk_id) = resp.remote_snapshot {
+                                tracing::info!(
+                                    "bootstrapped timeline from remote snapshot of {sk_id} onto {req_host}"
+                                );
                             } else {
                                 tracing::info!(
                                     "timeline already present on safekeeper on {req_host}"
@@ -25,7 +25,7 @@
 use pageserver_api::models::{SafekeeperInfo, SafekeepersInfo, TimelineInfo};
 use safekeeper_api::PgVersionId;
 use safekeeper_api::Term;
-use safekeeper_api::membership::{self, MemberSet, SafekeeperGeneration};
+use safekeeper_api::membership::{self, MemberRole, MemberSet, SafekeeperGeneration};
 use safekeeper_api::models::{
     PullTimelineRequest, TimelineLocateResponse, TimelineMembershipSwitchRequest,
     TimelineMembershipSwitchResponse,
@@ -40,10 +40,21 @@
 use super::Service;
 
 impl Service {
-    fn make_member_set(safekeepers: &[Safekeeper]) -> Result<MemberSet, anyhow::Error> {
+    /// Make member set of `safekeepers`, those of them which are in
+    /// `witnesses` become witness members.
+    fn make_member_set(
+        safekeepers: &[Safekeeper],
+        witnesses: &[i64],
+    ) -> Result<MemberSet, anyhow::Error> {
         let members = safekeepers
             .iter()
-            .map(|sk| sk.get_safekeeper_id())
+            .map(|sk| {
+                let mut id = sk.get_safekeeper_id();
+                if witnesses.contains(&sk.skp.id) {
+                    id.role = MemberRole::Witness;
+                }
+                id
+            })
             .collect::<Vec<_>>();
 
         MemberSet::new(members)
@@ -81,7 +92,8 @@ pub(super) async fn tenant_timeline_create_safekeepers_quorum(
     ) -> Result<Vec<NodeId>, ApiError> {
         let safekeepers = self.get_safekeepers(&timeline_persistence.sk_set)?;
 
-        let mset = Self::make_member_set(&safekeepers).map_err(ApiError::InternalServerError)?;
+        let mset = Self::make_member_set(&safekeepers, &timeline_persistence.sk_witness_set)
+            .map_err(ApiError::InternalServerError)?;
         let mconf = safekeeper_api::membership::Configuration::new(mset);
 
         let req = safekeeper_api::models::TimelineCreateRequest {
@@ -302,6 +314,13 @@ pub(super) async fn tenant_timeline_create_safekeepers(
             Vec::new()
         };
         let sks_persistence = sks.iter().map(|sk| sk.id.0 as i64).collect::<Vec<_>>();
+        // The last chosen safekeeper is the most loaded one, make it the
+        // witness: it doesn't store WAL.
+        let sk_witness_set = if self.config.timeline_safekeeper_witness {
+            sks_persistence.last().copied().into_iter().collect()
+        } else {
+            Vec::new()
+        };
         // Add timeline to db
         let mut timeline_persist = TimelinePersistence {
             tenant_id: tenant_id.to_string(),
@@ -313,6 +332,7 @@ pub(super) async fn tenant_timeline_create_safekeepers(
             cplane_notified_generation: 0,
             deleted_at: None,
             sk_set_notified_generation: 0,
+            sk_witness_set,
         };
         let inserted = self
             .persistence
@@ -463,6 +483,7 @@ pub(crate) async fn timeline_import(&self, req: TimelineImportRequest) -> Result
             cplane_notified_generation: 1,
             deleted_at: None,
             sk_set_notified_generation: 1,
+            sk_witness_set: Vec::new(),
         };
         let inserted = self
             .persistence
@@ -480,6 +501,7 @@ pub(crate) async fn timeline_import(&self, req: TimelineImportRequest) -> Result
             start_lsn: persistence.start_lsn,
             sk_set: persistence.sk_set,
             new_sk_set: persistence.new_sk_set,
+            sk_witness_set: persistence.sk_witness_set,
         };
         self.persistence.update_timeline_unsafe(update).await?;
         tracing::info!("timeline updated");
@@ -519,6 +541,124 @@ pub(crate) async fn tenant_timeline_locate(
         })
     }
 
+    /// Fence the computes of a timeline by bumping the term on all of its safekeepers, as the
+    /// first step of resetting the timeline to an earlier LSN.
+    ///
+    /// Returns the safekeepers, the highest commit LSN among them and a term which is higher than
+    /// all of their terms, to reset the WAL in.
+    pub(super) async fn tenant_timeline_fence_safekeepers(
+        &self,
+        tenant_id: TenantId,
+        timeline_id: TimelineId,
+    ) -> Result<(Vec<Safekeeper>, Lsn, Term), ApiError> {
+        let Some(timeline) = self
+            .persistence
+            .get_timeline(tenant_id, timeline_id)
+            .await?
+        else {
+            return Err(ApiError::PreconditionFailed(
+                "timeline safekeepers are not managed by the storage controller".into(),
+            ));
+        };
+        if timeline.new_sk_set.is_some() {
+            return Err(ApiError::Conflict(
+                "timeline safekeeper migration is in progress".to_string(),
+            ));
+        }
+        let safekeepers = self.get_safekeepers(&timeline.sk_set)?;
+
+        const SK_FENCE_TIMEOUT: Duration = Duration::from_secs(30);
+
+        let results = self
+            .tenant_timeline_safekeeper_op(
+                &safekeepers,
+                move |client| async move {
+                    let req = safekeeper_api::models::TimelineTermBumpRequest { term: None };
+                    client
+                        .bump_timeline_term(tenant_id, timeline_id, &req)
+                        .await
+                },
+                SK_FENCE_TIMEOUT,
+            )
+            .await?;
+        Self::all_safekeepers_ok(&safekeepers, results, "bump term")?;
+
+        let results = self
+            .tenant_timeline_safekeeper_op(
+                &safekeepers,
+                move |client| async move { client.timeline_status(tenant_id, timeline_id).await },
+                SK_FENCE_TIMEOUT,
+            )
+            .await?;
+        let statuses = Self::all_safekeepers_ok(&safekeepers, results, "get timeline status")?;
+
+        let tip = statuses
+            .iter()
+            .map(|status| status.commit_lsn)
+            .max()
+            .expect("timeline has safekeepers");
+        let term = statuses
+            .iter()
+            .map(|status| status.acceptor_state.term)
+            .max()
+            .expect("timeline has safekeepers")
+            + 1;
+
+        Ok((safekeepers, tip, term))
+    }
+
+    /// Discard the WAL after `lsn` on all given safekeepers of a timeline, and continue it from
+    /// `lsn` in `term`. See [`Self::tenant_timeline_fence_safekeepers`].
+    pub(super) async fn tenant_timeline_reset_safekeepers_wal(
+        &self,
+        safekeepers: &[Safekeeper],
+        tenant_id: TenantId,
+        timeline_id: TimelineId,
+        lsn: Lsn,
+        term: Term,
+    ) -> Result<(), ApiError> {
+        const SK_RESET_WAL_TIMEOUT: Duration = Duration::from_secs(30);
+
+        let req = safekeeper_api::models::TimelineResetWalRequest { lsn, term };
+        let results = self
+            .tenant_timeline_safekeeper_op(
+                safekeepers,
+                move |client| {
+                    let req = req.clone();
+                    async move {
+                        client
+                            .reset_timeline_wal(tenant_id, timeline_id, &req)
+                            .await
+                    }
+                },
+                SK_RESET_WAL_TIMEOUT,
+            )
+            .await?;
+        Self::all_safekeepers_ok(safekeepers, results, "reset WAL")?;
+
+        Ok(())
+    }
+
+    /// Unlike quorum operations, timeline resets need every safekeeper to take part: a safekeeper
+    /// left behind would still have the discarded WAL.
+    fn all_safekeepers_ok<T>(
+        safekeepers: &[Safekeeper],
+        results: Vec<mgmt_api::Result<T>>,
+        op: &str,
+    ) -> Result<Vec<T>, ApiError> {
+        safekeepers
+            .iter()
+            .zip(results)
+            .map(|(sk, res)| {
+                res.map_err(|e| {
+                    ApiError::ResourceUnavailable(
+                        format!("failed to {op} on safekeeper {}: {e}", sk.get_id()).into(),
+                    )
+                })
+            })
+            .collect()
+    }
+
     /// Perform timeline deletion on safekeepers. Will return success: we persist the deletion into the reconciler.
     pub(super) async fn tenant_timeline_delete_safekeepers(
         self: &Arc<Self>,
@@ -983,6 +1123,9 @@ async fn tenant_timeline_set_membership_quorum(
     }
 
     /// Pull timeline to to_safekeepers from from_safekeepers with retries.
+    /// If allow_remote_snapshot is set and none of from_safekeepers is
+    /// reachable, safekeepers bootstrap the timeline from snapshots in remote
+    /// storage.
     ///
     /// Returns Ok(()) only if all the pull_timeline requests were successful.
     async fn tenant_timeline_pull_from_peers(
@@ -992,9 +1135,12 @@ async fn tenant_timeline_pull_from_peers(
         to_safekeepers: &[Safekeeper],
         from_safekeepers: &[Safekeeper],
         mconf: membership::Configuration,
+        allow_remote_snapshot: bool,
     ) -> Result<(), ApiError> {
+        // Witnesses have no WAL to serve.
         let http_hosts = from_safekeepers
             .iter()
+            .filter(|sk| !mconf.is_witness(sk.get_id()))
             .map(|sk| sk.base_url())
             .collect::<Vec<_>>();
 
@@ -1015,6 +1161,7 @@ async fn tenant_timeline_pull_from_peers(
             timeline_id,
             http_hosts,
             mconf: Some(mconf),
+            allow_remote_snapshot,
         };
 
         const SK_PULL_TIMELINE_RECONCILE_TIMEOUT: Duration = Duration::from_secs(30);
@@ -1133,6 +1280,7 @@ pub(crate) async fn tenant_timeline_safekeeper_migrate(
         let all_safekeepers = self.inner.read().unwrap().safekeepers.clone();
 
         let new_sk_set = req.new_sk_set;
+        let allow_remote_snapshot = req.allow_remote_snapshot;
 
         for sk_id in new_sk_set.iter() {
             if !all_safekeepers.contains_key(sk_id) {
@@ -1159,8 +1307,7 @@ pub(crate) async fn tenant_timeline_safekeeper_migrate(
         let new_safekeepers = self.get_safekeepers(&new_sk_set_i64)?;
         // Construct new member set in advance to validate it.
         // E.g. validates that there is no duplicate safekeepers.
-        let new_sk_member_set =
-            Self::make_member_set(&new_safekeepers).map_err(ApiError::BadRequest)?;
+        Self::make_member_set(&new_safekeepers, &[]).map_err(ApiError::BadRequest)?;
 
         // TODO(diko): per-tenant lock is too wide. Consider introducing per-timeline locks.
         let _tenant_lock = trace_shared_lock(
@@ -1192,6 +1339,18 @@ pub(crate) async fn tenant_timeline_safekeeper_migrate(
             .map(|&id| NodeId(id as u64))
             .collect::<Vec<_>>();
 
+        // Witnesses keep their role if they stay in the set. Safekeepers joining
+        // it are always data members: pull_timeline brings WAL to them, and
+        // role of a safekeeper can't change later.
+        let new_sk_witness_set = timeline
+            .sk_witness_set
+            .iter()
+            .copied()
+            .filter(|id| new_sk_set.contains(&NodeId(*id as u64)))
+            .collect::<Vec<_>>();
+        let new_sk_member_set = Self::make_member_set(&new_safekeepers, &new_sk_witness_set)
+            .map_err(ApiError::BadRequest)?;
+
         // Validate that we are not migrating to a decomissioned safekeeper.
         for sk in new_safekeepers.iter() {
             if !cur_sk_set.contains(&sk.get_id())
@@ -1254,6 +1413,7 @@ pub(crate) async fn tenant_timeline_safekeeper_migrate(
                     generation,
                     &cur_sk_set,
                     Some(&new_sk_set),
+                    &timeline.sk_witness_set,
                     &[],
                 )
                 .await?;
@@ -1266,8 +1426,8 @@ pub(crate) async fn tenant_timeline_safekeeper_migrate(
         }
 
         let cur_safekeepers = self.get_safekeepers(&timeline.sk_set)?;
-        let cur_sk_member_set =
-            Self::make_member_set(&cur_safekeepers).map_err(ApiError::InternalServerError)?;
+        let cur_sk_member_set = Self::make_member_set(&cur_safekeepers, &timeline.sk_witness_set)
+            .map_err(ApiError::InternalServerError)?;
 
         let joint_config = membership::Configuration {
             generation,
@@ -1326,6 +1486,7 @@ pub(crate) async fn tenant_timeline_safekeeper_migrate(
             &pull_to_safekeepers,
             &cur_safekeepers,
             joint_config.clone(),
+            allow_remote_snapshot,
         )
         .await?;
 
@@ -1394,6 +1555,7 @@ pub(crate) async fn tenant_timeline_safekeeper_migrate(
                 generation,
                 &new_sk_set,
                 None,
+                &new_sk_witness_set,
                 &exclude_requests,
             )
             .await?;
@@ -1552,8 +1714,8 @@ async fn finish_safekeeper_migration_retry(
         }
 
         let cur_safekeepers = self.get_safekeepers(&timeline.sk_set)?;
-        let cur_sk_member_set =
-            Self::make_member_set(&cur_safekeepers).map_err(ApiError::InternalServerError)?;
+        let cur_sk_member_set = Self::make_member_set(&cur_safekeepers, &timeline.sk_witness_set)
+            .map_err(ApiError::InternalServerError)?;
 
         let mconf = membership::Configuration {
             generation: SafekeeperGeneration::new(timeline.generation as u32),
@@ -1674,8 +1836,8 @@ pub(crate) async fn tenant_timeline_safekeeper_migrate_abort(
         let cur_safekeepers = self.get_safekeepers(&timeline.sk_set)?;
         let new_safekeepers = self.get_safekeepers(new_sk_set)?;
 
-        let cur_sk_member_set =
-            Self::make_member_set(&cur_safekeepers).map_err(ApiError::InternalServerError)?;
+        let cur_sk_member_set = Self::make_member_set(&cur_safekeepers, &timeline.sk_witness_set)
+            .map_err(ApiError::InternalServerError)?;
 
         // Increment current generation and remove new_sk_set from the timeline to abort the migration.
         generation = generation.next();
@@ -1717,6 +1879,7 @@ pub(crate) async fn tenant_timeline_safekeeper_migrate_abort(
                 generation,
                 &cur_sk_set,
                 None,
+                &timeline.sk_witness_set,
                 &exclude_requests,
             )
             .await?;
@@ -0,0 +1,314 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::Utc;
+use http_utils::error::ApiError;
+use pageserver_api::controller_api::{
+    TenantSnapshot, TenantSnapshotPolicy, TenantSnapshotPolicyDescribe,
+};
+use pageserver_api::models::{
+    TimelineArchivalConfigRequest, TimelineArchivalState, TimelineCreateRequest,
+    TimelineCreateRequestMode,
+};
+use pageserver_api::shard::TenantShardId;
+use tracing::instrument;
+use utils::id::{TenantId, TimelineId};
+
+use super::{Service, passthrough_api_error};
+use crate::pageserver_client::PageserverClient;
+use crate::snapshot_policy::SnapshotPolicy;
+
+/// How often the background loop checks whether any snapshot policy is due. This bounds how late
+/// a snapshot may be taken relative to its schedule.
+const SNAPSHOT_POLICY_PERIOD: Duration = Duration::from_secs(60);
+
+impl Service {
+    pub(crate) async fn tenant_snapshot_policy_set(
+        &self,
+        tenant_id: TenantId,
+        policy: TenantSnapshotPolicy,
+    ) -> Result<TenantSnapshotPolicyDescribe, ApiError> {
+        SnapshotPolicy::validate(&policy).map_err(ApiError::BadRequest)?;
+
+        {
+            let locked = self.inner.read().unwrap();
+            if locked
+                .tenants
+                .range(TenantShardId::tenant_range(tenant_id))
+                .next()
+                .is_none()
+            {
+                return Err(ApiError::NotFound(
+                    anyhow::anyhow!("Tenant {tenant_id} not found").into(),
+                ));
+            }
+        }
+
+        tracing::info!(
+            "Setting snapshot policy of tenant {tenant_id} to {:?}",
+            policy
+        );
+
+        self.persistence
+            .upsert_snapshot_policy(&SnapshotPolicy::new(tenant_id, policy, Utc::now()))
+            .await?;
+
+        // Read back: if a policy already existed, its state was retained.
+        self.tenant_snapshot_policy_get(tenant_id)
+            .await?
+            .ok_or_else(|| {
+                ApiError::NotFound(anyhow::anyhow!("Tenant {tenant_id} not found").into())
+            })
+    }
+
+    pub(crate) async fn tenant_snapshot_policy_get(
+        &self,
+        tenant_id: TenantId,
+    ) -> Result<Option<TenantSnapshotPolicyDescribe>, ApiError> {
+        Ok(self
+            .persistence
+            .get_snapshot_policy(tenant_id)
+            .await?
+            .map(|p| p.describe()))
+    }
+
+    /// Removes the policy. Snapshots taken so far are left in place as regular timelines.
+    pub(crate) async fn tenant_snapshot_policy_delete(
+        &self,
+        tenant_id: TenantId,
+    ) -> Result<(), ApiError> {
+        if self.persistence.delete_snapshot_policy(tenant_id).await? {
+            tracing::info!("Removed snapshot policy of tenant {tenant_id}");
+            Ok(())
+        } else {
+            Err(ApiError::NotFound(
+                anyhow::anyhow!("Tenant {tenant_id} has no snapshot policy").into(),
+            ))
+        }
+    }
+
+    /// Periodically take snapshots for all due policies and apply their retention.
+    #[instrument(skip_all)]
+    pub(super) async fn snapshot_policies_background(self: &Arc<Self>) {
+        let mut interval = tokio::time::interval(SNAPSHOT_POLICY_PERIOD);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {},
+                _ = self.cancel.cancelled() => return,
+            }
+
+            let policies = match self.persistence.list_snapshot_policies().await {
+                Ok(policies) => policies,
+                Err(e) => {
+                    tracing::warn!("Failed to load snapshot policies: {e}");
+                    continue;
+                }
+            };
+
+            for policy in policies {
+                if self.cancel.is_cancelled() {
+                    return;
+                }
+                self.run_snapshot_policy(policy).await;
+            }
+        }
+    }
+
+    #[instrument(skip_all, fields(tenant_id=%policy.tenant_id, timeline_id=%policy.policy.timeline_id))]
+    async fn run_snapshot_policy(self: &Arc<Self>, mut policy: SnapshotPolicy) {
+        let tenant_id = policy.tenant_id;
+        let now = Utc::now();
+        let due = policy.is_due(now);
+        if !due && policy.retention_plan() == Default::default() {
+            return;
+        }
+
+        let mut result = Ok(());
+        if due || policy.state.pending.is_some() {
+            match self.take_snapshot(&mut policy).await {
+                Ok(snapshot) => {
+                    tracing::info!(
+                        "Took snapshot {} at LSN {}",
+                        snapshot.timeline_id,
+                        snapshot.lsn
+                    );
+                    policy.record_snapshot(snapshot);
+                }
+                Err(e) => {
+                    // Retry on the next iteration rather than waiting for the next scheduled time.
+                    result = Err(e);
+                }
+            }
+        }
+
+        if result.is_ok() {
+            result = self.apply_snapshot_retention(&mut policy).await;
+        }
+
+        policy.state.last_error = match result {
+            Ok(()) => None,
+            Err(e) => {
+                tracing::warn!("Snapshot policy failed: {e}");
+                Some(e.to_string())
+            }
+        };
+        match self.persistence.update_snapshot_policy_state(&policy).await {
+            Ok(true) => {}
+            Ok(false) => {
+                tracing::info!("Snapshot policy of tenant {tenant_id} was removed while running");
+            }
+            Err(e) => tracing::warn!("Failed to persist snapshot policy state: {e}"),
+        }
+    }
+
+    /// Creates the branch of a snapshot, resuming the pending one if there is one. See
+    /// [`crate::snapshot_policy`] for how this avoids leaking branches.
+    async fn take_snapshot(
+        self: &Arc<Self>,
+        policy: &mut SnapshotPolicy,
+    ) -> Result<TenantSnapshot, ApiError> {
+        let tenant_id = policy.tenant_id;
+        let ancestor_timeline_id = policy.policy.timeline_id;
+
+        let (snapshot, resumed) = match policy.state.pending.clone() {
+            Some(pending) => {
+                tracing::info!(
+                    "Resuming snapshot {} at LSN {}",
+                    pending.timeline_id,
+                    pending.lsn
+                );
+                (pending, true)
+            }
+            None => {
+                let (node, shard_zero) = self.tenant_shard0_node(tenant_id).await?;
+                let client = PageserverClient::new(
+                    node.get_id(),
+                    self.http_client.clone(),
+                    node.base_url(),
+                    self.config.pageserver_jwt_token.as_deref(),
+                );
+                let ancestor = client
+                    .timeline_detail(shard_zero, ancestor_timeline_id)
+                    .await
+                    .map_err(|e| passthrough_api_error(&node, e))?;
+
+                let pending = TenantSnapshot {
+                    timeline_id: TimelineId::generate(),
+                    lsn: ancestor.last_record_lsn,
+                    created_at: Utc::now(),
+                    archived: false,
+                };
+                policy.begin_snapshot(pending.clone());
+                if !self
+                    .persistence
+                    .update_snapshot_policy_state(policy)
+                    .await?
+                {
+                    return Err(ApiError::NotFound(
+                        anyhow::anyhow!("Snapshot policy of tenant {tenant_id} was removed").into(),
+                    ));
+                }
+                (pending, false)
+            }
+        };
+
+        // Keep GC from moving past the snapshot LSN until the branch point retains it.
+        let leased = match self
+            .tenant_timeline_lsn_lease(tenant_id, ancestor_timeline_id, snapshot.lsn)
+            .await
+        {
+            Ok(_) => true,
+            // A resumed intent may be too old to lease, but its branch may exist already.
+            Err(e) if resumed => {
+                tracing::warn!(
+                    "Failed to lease LSN {} of resumed snapshot: {e}",
+                    snapshot.lsn
+                );
+                false
+            }
+            Err(e) => {
+                // Nothing was created yet: drop the intent, the next attempt picks a fresh LSN.
+                policy.state.pending = None;
+                return Err(e);
+            }
+        };
+
+        let create_req = TimelineCreateRequest {
+            new_timeline_id: snapshot.timeline_id,
+            mode: TimelineCreateRequestMode::Branch {
+                ancestor_timeline_id,
+                ancestor_start_lsn: Some(snapshot.lsn),
+                pg_version: None,
+                read_only: true,
+            },
+        };
+        match self.tenant_timeline_create(tenant_id, create_req).await {
+            Ok(_) => Ok(snapshot),
+            Err(e) => {
+                if !leased {
+                    // Creation is idempotent, so the branch doesn't exist, and without a lease
+                    // its LSN may be gone: give up on this intent.
+                    tracing::warn!("Abandoning snapshot {}", snapshot.timeline_id);
+                    policy.state.pending = None;
+                }
+                Err(e)
+            }
+        }
+    }
+
+    /// Archives and deletes snapshots as required by the policy's retention. Snapshots that were
+    /// already deleted by someone else are forgotten.
+    async fn apply_snapshot_retention(
+        self: &Arc<Self>,
+        policy: &mut SnapshotPolicy,
+    ) -> Result<(), ApiError> {
+        let tenant_id = policy.tenant_id;
+        let plan = policy.retention_plan();
+
+        for timeline_id in plan.delete {
+            match self.tenant_timeline_delete(tenant_id, timeline_id).await {
+                Ok(_) | Err(ApiError::NotFound(_)) => {
+                    tracing::info!("Deleted expired snapshot {timeline_id}");
+                }
+                Err(e) => return Err(e),
+            }
+            policy
+                .state
+                .snapshots
+                .retain(|s| s.timeline_id != timeline_id);
+        }
+
+        for timeline_id in plan.archive {
+            let req = TimelineArchivalConfigRequest {
+                state: TimelineArchivalState::Archived,
+            };
+            match self
+                .tenant_timeline_archival_config(tenant_id, timeline_id, req)
+                .await
+            {
+                Ok(()) => {
+                    tracing::info!("Archived snapshot {timeline_id}");
+                    if let Some(snapshot) = policy
+                        .state
+                        .snapshots
+                        .iter_mut()
+                        .find(|s| s.timeline_id == timeline_id)
+                    {
+                        snapshot.archived = true;
+                    }
+                }
+                Err(ApiError::NotFound(_)) => {
+                    tracing::info!("Snapshot {timeline_id} no longer exists");
+                    policy
+                        .state
+                        .snapshots
+                        .retain(|s| s.timeline_id != timeline_id);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok(())
+    }
+}
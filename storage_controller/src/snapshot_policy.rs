@@ -0,0 +1,258 @@
+//! Scheduled snapshots of a tenant's timeline.
+//!
+//! A snapshot is a read-only branch of the policy's timeline, created by the storage controller
+//! on a cron schedule. Branch points are retained by the pageserver's GC for as long as the child
+//! timeline exists, including while it is archived or offloaded. Until the branch exists, the
+//! snapshot LSN is held back from GC by an LSN lease.
+//!
+//! Taking a snapshot is a three step operation, so that a branch can't be leaked: the intent
+//! (timeline ID and LSN) is persisted first, then the branch is created, then the intent is
+//! committed into the list of snapshots. An intent left behind by a failure is resumed by the
+//! next run of the policy, and branch creation is idempotent.
+//!
+//! This module only holds the policy model and its retention logic; the storage controller
+//! service drives it from a background loop.
+
+use std::str::FromStr;
+
+use chrono::{DateTime, Utc};
+use pageserver_api::controller_api::{
+    TenantSnapshot, TenantSnapshotPolicy, TenantSnapshotPolicyDescribe,
+};
+use serde::{Deserialize, Serialize};
+use utils::id::{TenantId, TimelineId};
+
+use crate::persistence::TenantSnapshotPolicyPersistence;
+
+/// Mutable state of a snapshot policy, persisted next to the policy itself.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub(crate) struct SnapshotPolicyState {
+    /// When the policy was set. The first snapshot is due at the first scheduled time after this.
+    pub(crate) created_at: DateTime<Utc>,
+    pub(crate) last_snapshot_at: Option<DateTime<Utc>>,
+    /// Live snapshots, oldest first.
+    pub(crate) snapshots: Vec<TenantSnapshot>,
+    pub(crate) last_error: Option<String>,
+    /// A snapshot whose branch may or may not exist yet, see the module docs.
+    #[serde(default)]
+    pub(crate) pending: Option<TenantSnapshot>,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct SnapshotPolicy {
+    pub(crate) tenant_id: TenantId,
+    pub(crate) policy: TenantSnapshotPolicy,
+    pub(crate) state: SnapshotPolicyState,
+}
+
+/// What needs to happen to existing snapshots for the policy to be satisfied.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub(crate) struct RetentionPlan {
+    pub(crate) archive: Vec<TimelineId>,
+    pub(crate) delete: Vec<TimelineId>,
+}
+
+impl SnapshotPolicy {
+    pub(crate) fn new(
+        tenant_id: TenantId,
+        policy: TenantSnapshotPolicy,
+        now: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            tenant_id,
+            policy,
+            state: SnapshotPolicyState {
+                created_at: now,
+                last_snapshot_at: None,
+                snapshots: Vec::new(),
+                last_error: None,
+                pending: None,
+            },
+        }
+    }
+
+    pub(crate) fn validate(policy: &TenantSnapshotPolicy) -> anyhow::Result<()> {
+        cron::Schedule::from_str(&policy.schedule)
+            .map_err(|e| anyhow::anyhow!("invalid schedule '{}': {e}", policy.schedule))?;
+        if policy.retention_count == 0 {
+            anyhow::bail!("retention_count must be at least 1");
+        }
+        if policy.unarchived_count > policy.retention_count {
+            anyhow::bail!(
+                "unarchived_count ({}) must not exceed retention_count ({})",
+                policy.unarchived_count,
+                policy.retention_count
+            );
+        }
+        Ok(())
+    }
+
+    /// The time at which the next snapshot is due, or None if the schedule has no future events.
+    pub(crate) fn next_snapshot_at(&self) -> Option<DateTime<Utc>> {
+        // The schedule was validated when the policy was set.
+        let schedule = cron::Schedule::from_str(&self.policy.schedule).ok()?;
+        let after = self.state.last_snapshot_at.unwrap_or(self.state.created_at);
+        schedule.after(&after).next()
+    }
+
+    pub(crate) fn is_due(&self, now: DateTime<Utc>) -> bool {
+        self.next_snapshot_at().is_some_and(|next| next <= now)
+    }
+
+    /// Records the intent to create `snapshot`. Must be persisted before the branch is created.
+    pub(crate) fn begin_snapshot(&mut self, snapshot: TenantSnapshot) {
+        self.state.pending = Some(snapshot);
+    }
+
+    /// Commits a snapshot whose branch was created.
+    pub(crate) fn record_snapshot(&mut self, snapshot: TenantSnapshot) {
+        if self
+            .state
+            .pending
+            .as_ref()
+            .is_some_and(|p| p.timeline_id == snapshot.timeline_id)
+        {
+            self.state.pending = None;
+        }
+        self.state.last_snapshot_at = Some(snapshot.created_at);
+        self.state.snapshots.push(snapshot);
+    }
+
+    /// Snapshots beyond `retention_count` are deleted, oldest first. Of the retained ones, all but
+    /// the newest `unarchived_count` are archived.
+    pub(crate) fn retention_plan(&self) -> RetentionPlan {
+        let snapshots = &self.state.snapshots;
+        let delete_count = snapshots.len().saturating_sub(self.policy.retention_count);
+        let (expired, retained) = snapshots.split_at(delete_count);
+        let archive_count = retained.len().saturating_sub(self.policy.unarchived_count);
+
+        RetentionPlan {
+            archive: retained[..archive_count]
+                .iter()
+                .filter(|s| !s.archived)
+                .map(|s| s.timeline_id)
+                .collect(),
+            delete: expired.iter().map(|s| s.timeline_id).collect(),
+        }
+    }
+
+    pub(crate) fn describe(&self) -> TenantSnapshotPolicyDescribe {
+        TenantSnapshotPolicyDescribe {
+            policy: self.policy.clone(),
+            snapshots: self.state.snapshots.clone(),
+            next_snapshot_at: self.next_snapshot_at(),
+            last_error: self.state.last_error.clone(),
+        }
+    }
+
+    pub(crate) fn from_persistent(
+        persistent: TenantSnapshotPolicyPersistence,
+    ) -> anyhow::Result<Self> {
+        Ok(Self {
+            tenant_id: TenantId::from_str(persistent.tenant_id.as_str())?,
+            policy: serde_json::from_value(persistent.policy)?,
+            state: serde_json::from_value(persistent.state)?,
+        })
+    }
+
+    pub(crate) fn to_persistent(&self) -> TenantSnapshotPolicyPersistence {
+        TenantSnapshotPolicyPersistence {
+            tenant_id: self.tenant_id.to_string(),
+            policy: serde_json::to_value(&self.policy).unwrap(),
+            state: serde_json::to_value(&self.state).unwrap(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use utils::lsn::Lsn;
+
+    use super::*;
+
+    fn policy(schedule: &str, retention_count: usize, unarchived_count: usize) -> SnapshotPolicy {
+        SnapshotPolicy::new(
+            TenantId::generate(),
+            TenantSnapshotPolicy {
+                timeline_id: TimelineId::generate(),
+                schedule: schedule.to_string(),
+                retention_count,
+                unarchived_count,
+            },
+            Utc.with_ymd_and_hms(2025, 1, 1, 12, 30, 0).unwrap(),
+        )
+    }
+
+    fn snapshot(archived: bool) -> TenantSnapshot {
+        TenantSnapshot {
+            timeline_id: TimelineId::generate(),
+            lsn: Lsn(0x1000),
+            created_at: Utc::now(),
+            archived,
+        }
+    }
+
+    #[test]
+    fn validate() {
+        assert!(SnapshotPolicy::validate(&policy("0 0 3 * * *", 3, 1).policy).is_ok());
+        assert!(SnapshotPolicy::validate(&policy("not a schedule", 3, 1).policy).is_err());
+        assert!(SnapshotPolicy::validate(&policy("0 0 3 * * *", 0, 0).policy).is_err());
+        assert!(SnapshotPolicy::validate(&policy("0 0 3 * * *", 1, 2).policy).is_err());
+    }
+
+    #[test]
+    fn schedule() {
+        let mut p = policy("0 0 3 * * *", 3, 1);
+        let first = Utc.with_ymd_and_hms(2025, 1, 2, 3, 0, 0).unwrap();
+        assert_eq!(p.next_snapshot_at(), Some(first));
+        assert!(!p.is_due(first - chrono::Duration::seconds(1)));
+        assert!(p.is_due(first));
+
+        p.record_snapshot(TenantSnapshot {
+            created_at: first,
+            ..snapshot(false)
+        });
+        assert_eq!(
+            p.next_snapshot_at(),
+            Some(Utc.with_ymd_and_hms(2025, 1, 3, 3, 0, 0).unwrap())
+        );
+        assert!(!p.is_due(first));
+    }
+
+    #[test]
+    fn pending_snapshot() {
+        let mut p = policy("0 0 3 * * *", 3, 1);
+        let s = snapshot(false);
+        p.begin_snapshot(s.clone());
+
+        // The intent survives a roundtrip through the database.
+        let p2 = SnapshotPolicy::from_persistent(p.to_persistent()).unwrap();
+        assert_eq!(
+            p2.state.pending.as_ref().map(|p| p.timeline_id),
+            Some(s.timeline_id)
+        );
+
+        p.record_snapshot(s.clone());
+        assert!(p.state.pending.is_none());
+        assert_eq!(p.state.snapshots.len(), 1);
+        assert_eq!(p.state.last_snapshot_at, Some(s.created_at));
+    }
+
+    #[test]
+    fn retention() {
+        let mut p = policy("0 0 3 * * *", 3, 1);
+        assert_eq!(p.retention_plan(), RetentionPlan::default());
+
+        let snapshots: Vec<_> = (0..5).map(|i| snapshot(i == 2)).collect();
+        p.state.snapshots = snapshots.clone();
+        assert_eq!(
+            p.retention_plan(),
+            RetentionPlan {
+                // snapshots[2] is already archived
+                archive: vec![snapshots[3].timeline_id],
+                delete: vec![snapshots[0].timeline_id, snapshots[1].timeline_id],
+            }
+        );
+    }
+}
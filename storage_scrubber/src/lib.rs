@@ -9,6 +9,7 @@
 pub mod scan_pageserver_metadata;
 pub mod scan_safekeeper_metadata;
 pub mod tenant_snapshot;
+pub mod verify_safekeeper_wal;
 
 use std::env;
 use std::fmt::Display;
@@ -10,11 +10,13 @@
 use storage_scrubber::scan_pageserver_metadata::scan_pageserver_metadata;
 use storage_scrubber::scan_safekeeper_metadata::{DatabaseOrList, scan_safekeeper_metadata};
 use storage_scrubber::tenant_snapshot::SnapshotDownloader;
+use storage_scrubber::verify_safekeeper_wal::verify_safekeeper_wal;
 use storage_scrubber::{
     BucketConfig, ConsoleConfig, ControllerClientConfig, NodeKind, TraversingDepth,
     find_large_objects, init_logging,
 };
-use utils::id::TenantId;
+use utils::id::{TenantId, TimelineId};
+use utils::lsn::Lsn;
 use utils::{project_build_tag, project_git_version};
 
 project_git_version!(GIT_VERSION);
@@ -113,6 +115,29 @@ enum Command {
         #[arg(long = "concurrency", short = 'j', default_value_t = 64)]
         concurrency: usize,
     },
+    /// Compare WAL of a timeline on its safekeepers and in remote storage, see
+    /// the `wal_verify` safekeeper endpoint. Fails if divergence is found.
+    VerifySafekeeperWal {
+        #[arg(long = "tenant-id")]
+        tenant_id: TenantId,
+        #[arg(long = "timeline-id")]
+        timeline_id: TimelineId,
+        /// Http address of the safekeeper running the verification, followed
+        /// by any safekeepers to compare with besides members of the configuration.
+        #[arg(long = "safekeeper", num_args = 1.., required = true)]
+        safekeepers: Vec<String>,
+        /// JWT token for authenticating with safekeepers.
+        #[arg(long)]
+        safekeeper_jwt: Option<String>,
+        /// Oldest WAL on the safekeeper by default.
+        #[arg(long)]
+        from_lsn: Option<Lsn>,
+        /// commit_lsn of the safekeeper by default.
+        #[arg(long)]
+        until_lsn: Option<Lsn>,
+        #[arg(long)]
+        chunk_size: Option<u64>,
+    },
     CronJob {
         // PageserverPhysicalGc
         #[arg(long = "min-age")]
@@ -138,6 +163,7 @@ async fn main() -> anyhow::Result<()> {
         Command::TenantSnapshot { .. } => "tenant-snapshot",
         Command::PageserverPhysicalGc { .. } => "pageserver-physical-gc",
         Command::FindLargeObjects { .. } => "find-large-objects",
+        Command::VerifySafekeeperWal { .. } => "verify-safekeeper-wal",
         Command::CronJob { .. } => "cron-job",
     };
     let _guard = init_logging(&format!(
@@ -171,7 +197,7 @@ async fn main() -> anyhow::Result<()> {
             // Default to no key: this is a convenience when working in a development environment
             controller_jwt: cli.controller_jwt.unwrap_or("".to_owned()),
         }
-        .build_client(http_client)
+        .build_client(http_client.clone())
     });
 
     match cli.command {
@@ -295,6 +321,37 @@ async fn main() -> anyhow::Result<()> {
             println!("{}", serde_json::to_string(&summary).unwrap());
             Ok(())
         }
+        Command::VerifySafekeeperWal {
+            tenant_id,
+            timeline_id,
+            safekeepers,
+            safekeeper_jwt,
+            from_lsn,
+            until_lsn,
+            chunk_size,
+        } => {
+            let report = verify_safekeeper_wal(
+                http_client,
+                safekeepers,
+                safekeeper_jwt,
+                tenant_id,
+                timeline_id,
+                from_lsn,
+                until_lsn,
+                chunk_size,
+            )
+            .await?;
+            println!("{}", serde_json::to_string(&report).unwrap());
+            if !report.divergences.is_empty() {
+                bail!(
+                    "WAL diverges in {} range(s) between {} and {}",
+                    report.divergences.len(),
+                    report.from_lsn,
+                    report.until_lsn
+                );
+            }
+            Ok(())
+        }
         Command::CronJob {
             gc_min_age,
             gc_mode,
@@ -187,7 +187,8 @@ async fn check_timeline(
             .as_str()
             .strip_prefix(prefix_str)
             .expect("failed to extract segment name");
-        expected_segfiles.remove(seg_name);
+        // Safekeepers may upload segments compressed.
+        expected_segfiles.remove(seg_name.strip_suffix(".zst").unwrap_or(seg_name));
     }
     if !expected_segfiles.is_empty() {
         // Before complaining check cplane, probably timeline is already deleted.
@@ -0,0 +1,60 @@
+//! Compare WAL of a timeline across its safekeepers and remote storage.
+//!
+//! The comparison itself is done by the safekeeper's `wal_verify` endpoint,
+//! which gathers CRCs of the WAL on its local disk, in remote storage and on
+//! the other members of the configuration. This subcommand picks the range to
+//! verify, triggers it and reports divergences.
+
+use anyhow::Context;
+use safekeeper_api::models::{TimelineStatus, WalVerifyRequest, WalVerifyResponse};
+use safekeeper_client::mgmt_api;
+use utils::id::{TenantId, TimelineId};
+use utils::logging::SecretString;
+use utils::lsn::Lsn;
+
+/// Verify WAL of the timeline via the first of `safekeepers`, other ones are
+/// compared in addition to the members of the configuration.
+///
+/// By default the range is from the oldest WAL the safekeeper has locally to
+/// its commit_lsn.
+#[allow(clippy::too_many_arguments)]
+pub async fn verify_safekeeper_wal(
+    http_client: reqwest::Client,
+    safekeepers: Vec<String>,
+    jwt: Option<String>,
+    tenant_id: TenantId,
+    timeline_id: TimelineId,
+    from_lsn: Option<Lsn>,
+    until_lsn: Option<Lsn>,
+    chunk_size: Option<u64>,
+) -> anyhow::Result<WalVerifyResponse> {
+    let (endpoint, others) = safekeepers
+        .split_first()
+        .context("at least one safekeeper is required")?;
+    let client = mgmt_api::Client::new(http_client, endpoint.clone(), jwt.map(SecretString::from));
+
+    let (from_lsn, until_lsn) = match (from_lsn, until_lsn) {
+        (Some(from_lsn), Some(until_lsn)) => (from_lsn, until_lsn),
+        (from_lsn, until_lsn) => {
+            let status: TimelineStatus = client
+                .timeline_status(tenant_id, timeline_id)
+                .await?
+                .json()
+                .await
+                .context("parsing timeline status")?;
+            (
+                from_lsn.unwrap_or(status.local_start_lsn),
+                until_lsn.unwrap_or(status.commit_lsn),
+            )
+        }
+    };
+
+    tracing::info!(%tenant_id, %timeline_id, %from_lsn, %until_lsn, "verifying WAL via {endpoint}");
+    let request = WalVerifyRequest {
+        from_lsn,
+        until_lsn,
+        chunk_size,
+        http_hosts: others.to_vec(),
+    };
+    Ok(client.wal_verify(tenant_id, timeline_id, &request).await?)
+}
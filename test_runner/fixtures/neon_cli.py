@@ -495,6 +495,45 @@ class NeonLocalCli(AbstractNeonCli):
         cmd = ["storage_broker", "stop"]
         return self.raw_cli(cmd)
 
+    def stock_standby_create(
+        self,
+        name: str,
+        tenant_id: TenantId,
+        branch_name: str,
+        pg_port: int,
+        pg_version: PgVersion,
+        safekeeper_id: int | None = None,
+    ) -> subprocess.CompletedProcess[str]:
+        args = [
+            "stock-standby",
+            "create",
+            name,
+            "--tenant-id",
+            str(tenant_id),
+            "--branch-name",
+            branch_name,
+            "--pg-port",
+            str(pg_port),
+            "--pg-version",
+            pg_version,
+        ]
+        if safekeeper_id is not None:
+            args.extend(["--safekeeper-id", str(safekeeper_id)])
+        return self.raw_cli(args)
+
+    def stock_standby_start(
+        self, name: str, pg_version: PgVersion
+    ) -> subprocess.CompletedProcess[str]:
+        return self.raw_cli(["stock-standby", "start", name, "--pg-version", pg_version])
+
+    def stock_standby_stop(
+        self, name: str, pg_version: PgVersion, immediate=False
+    ) -> subprocess.CompletedProcess[str]:
+        args = ["stock-standby", "stop", name, "--pg-version", pg_version]
+        if immediate:
+            args.extend(["-m", "immediate"])
+        return self.raw_cli(args)
+
     def endpoint_create(
         self,
         branch_name: str,
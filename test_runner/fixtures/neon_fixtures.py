@@ -2167,6 +2167,24 @@ class NeonStorageController(MetricsGetter, LogUtils):
             headers=self.headers(TokenScope.INFRA),
         )
 
+    def layer_rewrite(self, max_bytes_per_second: int | None = None) -> dict[str, Any]:
+        log.info(f"layer_rewrite({max_bytes_per_second=})")
+        response = self.request(
+            "PUT",
+            f"{self.api}/control/v1/layer_rewrite",
+            json={"max_bytes_per_second": max_bytes_per_second},
+            headers=self.headers(TokenScope.ADMIN),
+        )
+        return response.json()
+
+    def layer_rewrite_status(self) -> dict[str, Any]:
+        response = self.request(
+            "GET",
+            f"{self.api}/control/v1/layer_rewrite",
+            headers=self.headers(TokenScope.ADMIN),
+        )
+        return response.json()
+
     def cancel_node_drain(self, node_id):
         log.info(f"cancel_node_drain({node_id})")
         self.request(
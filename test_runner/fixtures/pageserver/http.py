@@ -129,6 +129,8 @@ class HistoricLayerInfo:
     # None for image layers, true if pageserver thinks this is an L0 delta layer
     l0: bool | None
     visible: bool
+    # estimated accesses per hour
+    access_rate: float = 0.0
 
     @classmethod
     def from_json(cls, d: dict[str, Any]) -> HistoricLayerInfo:
@@ -148,6 +150,7 @@ class HistoricLayerInfo:
             remote=d["remote"],
             l0=l0_ness,
             visible=d["access_stats"]["visible"],
+            access_rate=d["access_stats"].get("access_rate", 0.0),
         )
 
 
@@ -697,6 +700,15 @@ class PageserverHttpClient(requests.Session, MetricsGetter):
 
         return res.status_code
 
+    def timeline_undelete(self, tenant_id: TenantId | TenantShardId, timeline_id: TimelineId):
+        """
+        Restores a timeline that was soft-deleted within the tenant's `timeline_delete_grace_period`.
+        """
+        res = self.put(
+            f"http://localhost:{self.port}/v1/tenant/{tenant_id}/timeline/{timeline_id}/undelete",
+        )
+        self.verbose_error(res)
+
     def timeline_gc(
         self,
         tenant_id: TenantId | TenantShardId,
@@ -765,6 +777,17 @@ class PageserverHttpClient(requests.Session, MetricsGetter):
         res_json = res.json()
         return res_json
 
+    def timeline_gc_dry_run(
+        self, tenant_id: TenantId | TenantShardId, timeline_id: TimelineId
+    ) -> dict[str, Any]:
+        res = self.get(
+            f"http://localhost:{self.port}/v1/tenant/{tenant_id}/timeline/{timeline_id}/gc_dry_run"
+        )
+        self.verbose_error(res)
+        res_json = res.json()
+        assert isinstance(res_json, dict)
+        return res_json
+
     def timeline_compact(
         self,
         tenant_id: TenantId | TenantShardId,
@@ -1069,6 +1092,33 @@ class PageserverHttpClient(requests.Session, MetricsGetter):
         self.verbose_error(res)
         return LayerMapInfo.from_json(res.json())
 
+    def timeline_hottest_layers(
+        self,
+        tenant_id: TenantId | TenantShardId,
+        timeline_id: TimelineId,
+        limit: int | None = None,
+    ) -> list[HistoricLayerInfo]:
+        params = {"limit": limit} if limit is not None else {}
+        res = self.get(
+            f"http://localhost:{self.port}/v1/tenant/{tenant_id}/timeline/{timeline_id}/hottest_layers",
+            params=params,
+        )
+        self.verbose_error(res)
+        return [HistoricLayerInfo.from_json(layer) for layer in res.json()]
+
+    def timeline_read_amp(
+        self,
+        tenant_id: TenantId | TenantShardId,
+        timeline_id: TimelineId,
+    ) -> list[dict[str, Any]]:
+        res = self.get(
+            f"http://localhost:{self.port}/v1/tenant/{tenant_id}/timeline/{timeline_id}/read_amp",
+        )
+        self.verbose_error(res)
+        res_json = res.json()
+        assert isinstance(res_json, list)
+        return res_json
+
     def timeline_layer_scan_disposable_keys(
         self, tenant_id: TenantId | TenantShardId, timeline_id: TimelineId, layer_name: str
     ) -> ScanDisposableKeysResponse:
@@ -1118,6 +1168,21 @@ class PageserverHttpClient(requests.Session, MetricsGetter):
         json = res.json()
         return set(map(TimelineId, json["reparented_timelines"]))
 
+    def timeline_reset(
+        self,
+        tenant_id: TenantId | TenantShardId,
+        timeline_id: TimelineId,
+        lsn: Lsn,
+        backup_timeline_id: TimelineId,
+        **kwargs,
+    ):
+        res = self.put(
+            f"http://localhost:{self.port}/v1/tenant/{tenant_id}/timeline/{timeline_id}/reset",
+            json={"lsn": str(lsn), "backup_timeline_id": str(backup_timeline_id)},
+            **kwargs,
+        )
+        self.verbose_error(res)
+
     def evict_layer(
         self, tenant_id: TenantId | TenantShardId, timeline_id: TimelineId, layer_name: str
     ):
@@ -1141,6 +1206,39 @@ class PageserverHttpClient(requests.Session, MetricsGetter):
         self.verbose_error(res)
         return res.json()
 
+    def compaction_queue(self) -> dict[str, Any]:
+        res = self.get(f"http://localhost:{self.port}/v1/compaction_queue")
+        self.verbose_error(res)
+        res_json = res.json()
+        assert isinstance(res_json, dict)
+        return res_json
+
+    def timeline_layer_rewrite(
+        self,
+        tenant_id: TenantId | TenantShardId,
+        timeline_id: TimelineId,
+        max_bytes_per_second: int | None = None,
+    ) -> dict[str, Any]:
+        res = self.put(
+            f"http://localhost:{self.port}/v1/tenant/{tenant_id}/timeline/{timeline_id}/layer_rewrite",
+            json={"max_bytes_per_second": max_bytes_per_second},
+        )
+        self.verbose_error(res)
+        res_json = res.json()
+        assert isinstance(res_json, dict)
+        return res_json
+
+    def timeline_layer_rewrite_status(
+        self, tenant_id: TenantId | TenantShardId, timeline_id: TimelineId
+    ) -> dict[str, Any]:
+        res = self.get(
+            f"http://localhost:{self.port}/v1/tenant/{tenant_id}/timeline/{timeline_id}/layer_rewrite"
+        )
+        self.verbose_error(res)
+        res_json = res.json()
+        assert isinstance(res_json, dict)
+        return res_json
+
     def tenant_break(self, tenant_id: TenantId | TenantShardId):
         res = self.put(f"http://localhost:{self.port}/v1/tenant/{tenant_id}/break")
         self.verbose_error(res)
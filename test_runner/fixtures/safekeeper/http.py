@@ -151,6 +151,13 @@ class SafekeeperHttpClient(requests.Session, MetricsGetter):
         res = self.get_metrics_str()
         return SafekeeperMetrics(parse_metrics(res))
 
+    def filesystem_usage(self) -> dict[str, Any]:
+        res = self.get(f"http://localhost:{self.port}/v1/debug/filesystem_usage")
+        res.raise_for_status()
+        res_json = res.json()
+        assert isinstance(res_json, dict)
+        return res_json
+
     def is_testing_enabled_or_skip(self):
         if not self.is_testing_enabled:
             pytest.skip("safekeeper was built without 'testing' feature")
@@ -275,6 +282,24 @@ class SafekeeperHttpClient(requests.Session, MetricsGetter):
         dump = self.debug_dump_timeline(timeline_id, {"dump_control_file": "true"})
         return dump["control_file"]["eviction_state"]
 
+    def get_wal_ingest_throttle(self, timeline_id: TimelineId) -> Any:
+        dump = self.debug_dump_timeline(timeline_id, {"dump_memory": "true"})
+        return dump["memory"]["wal_ingest_throttle"]
+
+    def wal_ingest_limits(self) -> dict[str, Any]:
+        res = self.get(f"http://localhost:{self.port}/v1/wal_ingest_limits")
+        res.raise_for_status()
+        res_json = res.json()
+        assert isinstance(res_json, dict)
+        return res_json
+
+    def set_wal_ingest_limits(self, body: dict[str, Any]) -> dict[str, Any]:
+        res = self.put(f"http://localhost:{self.port}/v1/wal_ingest_limits", json=body)
+        res.raise_for_status()
+        res_json = res.json()
+        assert isinstance(res_json, dict)
+        return res_json
+
     def pull_timeline(self, body: dict[str, Any]) -> dict[str, Any]:
         res = self.post(f"http://localhost:{self.port}/v1/pull_timeline", json=body)
         res.raise_for_status()
@@ -351,6 +376,27 @@ class SafekeeperHttpClient(requests.Session, MetricsGetter):
         assert isinstance(res_json, dict)
         return res_json
 
+    def wal_verify(
+        self,
+        tenant_id: TenantId,
+        timeline_id: TimelineId,
+        from_lsn: Lsn,
+        until_lsn: Lsn,
+        http_hosts: list[str] | None = None,
+    ) -> dict[str, Any]:
+        res = self.post(
+            f"http://localhost:{self.port}/v1/tenant/{tenant_id}/timeline/{timeline_id}/wal_verify",
+            json={
+                "from_lsn": str(from_lsn),
+                "until_lsn": str(until_lsn),
+                "http_hosts": http_hosts or [],
+            },
+        )
+        res.raise_for_status()
+        res_json = res.json()
+        assert isinstance(res_json, dict)
+        return res_json
+
     def backup_partial_reset(self, tenant_id: TenantId, timeline_id: TimelineId):
         res = self.post(
             f"http://localhost:{self.port}/v1/tenant/{tenant_id}/timeline/{timeline_id}/backup_partial_reset",
@@ -165,6 +165,11 @@ def test_fully_custom_config(positive_env: NeonEnv):
         "gc_horizon": 23 * (1024 * 1024),
         "gc_period": "2h 13m",
         "image_creation_threshold": 7,
+        "image_creation_policy": {
+            "kind": "ReadAmplification",
+            "layers_per_read_threshold": 4,
+            "min_sampled_reads": 10,
+        },
         "image_layer_force_creation_period": "1m",
         "pitr_interval": "1m",
         "lagging_wal_timeout": "23m",
@@ -194,6 +199,12 @@ def test_fully_custom_config(positive_env: NeonEnv):
             "numerator": 0,
             "denominator": 10,
         },
+        "timeline_logical_size_limit": 10 * 1024 * 1024 * 1024,
+        "timeline_physical_size_limit": 20 * 1024 * 1024 * 1024,
+        "tenant_logical_size_limit": 30 * 1024 * 1024 * 1024,
+        "tenant_physical_size_limit": 40 * 1024 * 1024 * 1024,
+        "timeline_delete_grace_period": "1day",
+        "eviction_weight_pct": 200,
     }
 
     vps_http = env.storage_controller.pageserver_api()
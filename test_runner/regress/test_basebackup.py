@@ -97,3 +97,37 @@ def test_basebackup_cache(neon_env_builder: NeonEnvBuilder, grpc: bool):
         assert len(bb_files) == 1
 
     wait_until(check_bb_dir_empty)
+
+
+@pytest.mark.parametrize("grpc", [True, False])
+@pytest.mark.parametrize("reject_zstd", [False, True])
+def test_basebackup_zstd(neon_env_builder: NeonEnvBuilder, grpc: bool, reject_zstd: bool):
+    """
+    Start a compute from a zstd-compressed basebackup. With `reject_zstd`, the pageserver rejects
+    zstd like one that predates it does, and the compute falls back to gzip.
+    """
+    env = neon_env_builder.init_start()
+    env.pageserver.allowed_errors.extend(
+        [
+            ".*invalid parameter for basebackup command: --zstd.*",
+            ".*field 'compression' has invalid value.*",
+        ]
+    )
+
+    ep = env.endpoints.create("main", grpc=grpc)
+    ep.respec(features=["basebackup_zstd"])
+    ep.start()
+    ep.safe_psql("CREATE TABLE t AS SELECT g FROM generate_series(1, 10000) g")
+    ep.stop()
+
+    if reject_zstd:
+        env.pageserver.http_client().configure_failpoints(("basebackup-reject-zstd", "return"))
+
+    ep.start()
+    assert ep.safe_psql("SELECT count(*) FROM t")[0][0] == 10000
+    if reject_zstd:
+        assert ep.log_contains("falling back to gzip")
+        assert ep.log_contains("fetched Gzip basebackup")
+    else:
+        assert ep.log_contains("fetched Zstd basebackup")
+        assert not ep.log_contains("fetched Gzip basebackup")
@@ -5,6 +5,7 @@ import math
 import random
 import time
 from enum import StrEnum
+from typing import Any
 
 import pytest
 from fixtures.common_types import TenantShardId
@@ -1030,6 +1031,70 @@ def test_image_layer_compression(neon_env_builder: NeonEnvBuilder, enabled: bool
             assert res[0][0] == 1
 
 
+def test_image_layer_rewrite(neon_env_builder: NeonEnvBuilder):
+    """
+    Image layers written without compression are rewritten with compression by a layer rewrite
+    started after enabling it, and a rewrite in a later run does not pick them up again.
+    """
+    tenant_conf = {
+        "checkpoint_distance": f"{128 * 1024}",
+        "compaction_threshold": "1",
+        "compaction_target_size": f"{128 * 1024}",
+        "pitr_interval": "0s",
+        "gc_period": "0s",
+        "compaction_period": "0s",
+        "image_creation_threshold": "1",
+        "image_layer_creation_check_threshold": "0",
+    }
+    neon_env_builder.pageserver_config_override = "image_compression='disabled'"
+    env = neon_env_builder.init_start(initial_tenant_conf=tenant_conf)
+
+    tenant_id = env.initial_tenant
+    timeline_id = env.initial_timeline
+    pageserver = env.pageserver
+    ps_http = pageserver.http_client()
+
+    with env.endpoints.create_start("main", tenant_id=tenant_id) as endpoint:
+        endpoint.safe_psql("CREATE TABLE foo (id INTEGER PRIMARY KEY, val text)")
+        for v in range(100):
+            endpoint.safe_psql(
+                f"INSERT INTO foo (id, val) VALUES ({v}, repeat('abcde{v:0>3}', 500))"
+            )
+    ps_http.timeline_checkpoint(tenant_id, timeline_id, wait_until_uploaded=True)
+
+    # Restarting puts the tenant into a new generation, which makes the existing layers eligible.
+    pageserver.stop()
+    pageserver.patch_config_toml_nonrecursive({"image_compression": "zstd"})
+    pageserver.start()
+
+    def rewrite_done() -> dict[str, Any]:
+        status = ps_http.timeline_layer_rewrite_status(tenant_id, timeline_id)
+        assert status["state"] == "done", status
+        return status
+
+    response = env.storage_controller.layer_rewrite()
+    assert [n["error"] for n in response["nodes"]] == [None]
+    status = wait_until(rewrite_done)
+    log.info(f"first rewrite: {status}")
+    assert status["layers_rewritten"] > 0
+    assert status["bytes_after"] < status["bytes_before"]
+
+    nodes = env.storage_controller.layer_rewrite_status()["nodes"]
+    assert [t["status"] for t in nodes[0]["timelines"]] == [status]
+
+    # The rewritten layers belong to the current generation, so they are not rewritten again.
+    ps_http.timeline_layer_rewrite(tenant_id, timeline_id)
+    status = wait_until(rewrite_done)
+    assert status["layers_rewritten"] == 0
+
+    with env.endpoints.create_start("main", tenant_id=tenant_id) as endpoint:
+        for v in range(100):
+            res = endpoint.safe_psql(
+                f"SELECT count(*) FROM foo WHERE id={v} and val=repeat('abcde{v:0>3}', 500)"
+            )
+            assert res[0][0] == 1
+
+
 # BEGIN_HADRON
 def get_layer_map(env, tenant_shard_id, timeline_id, ps_id):
     client = env.pageservers[ps_id].http_client()
@@ -1044,6 +1109,109 @@ def get_layer_map(env, tenant_shard_id, timeline_id, ps_id):
     return image_layer_count, delta_layer_count
 
 
+def test_image_creation_read_amp_policy(neon_env_builder: NeonEnvBuilder):
+    """
+    With the read amplification image creation policy, image layers are created for the
+    partitions that GetPage requests read through many layers, even though the delta count
+    is far below `image_creation_threshold`.
+    """
+    conf = AGGRESSIVE_COMPACTION_TENANT_CONF.copy()
+    conf.update(
+        {
+            # The delta count alone would never trigger image creation.
+            "image_creation_threshold": 10000,
+            "image_layer_creation_check_threshold": 0,
+            "image_creation_policy": {
+                "kind": "ReadAmplification",
+                "layers_per_read_threshold": 2,
+                "min_sampled_reads": 1,
+            },
+        }
+    )
+    env = neon_env_builder.init_start(initial_tenant_conf=conf)
+    tenant_id = env.initial_tenant
+    timeline_id = env.initial_timeline
+    ps_http = env.pageserver.http_client()
+
+    workload = Workload(env, tenant_id, timeline_id)
+    workload.init(env.pageserver.id)
+    workload.write_rows(1000, env.pageserver.id)
+    for _ in range(5):
+        workload.churn_rows(1000, env.pageserver.id)
+        ps_http.timeline_compact(tenant_id, timeline_id, force_l0_compaction=True)
+
+    (images_before, deltas) = get_layer_map(env, tenant_id, timeline_id, 0)
+    log.info(f"before reads: images={images_before}, deltas={deltas}")
+
+    # Only a fraction of the reads is sampled: keep reading until the table's partition shows up.
+    def read_amp_sampled():
+        workload.validate(env.pageserver.id)
+        read_amp = ps_http.timeline_read_amp(tenant_id, timeline_id)
+        log.info(f"read amplification: {read_amp}")
+        assert any(r["layers_per_read"] >= 2 for r in read_amp)
+
+    wait_until(read_amp_sampled)
+
+    ps_http.timeline_compact(tenant_id, timeline_id)
+    (images_after, deltas) = get_layer_map(env, tenant_id, timeline_id, 0)
+    log.info(f"after reads: images={images_after}, deltas={deltas}")
+    assert images_after > images_before
+
+    # The reads of the new image layer's range start from scratch.
+    workload.validate(env.pageserver.id)
+
+
+def test_compaction_scheduler_queue(neon_env_builder: NeonEnvBuilder):
+    """
+    The per-node compaction scheduler compacts tenants that accumulate L0 layers without waiting
+    for their compaction period, and shows its queue over HTTP.
+    """
+    env = neon_env_builder.init_start(
+        initial_tenant_conf={
+            # Only L0 triggers and urgency make compaction due.
+            "compaction_period": "1h",
+            "compaction_threshold": 3,
+            "checkpoint_distance": 1024**2,
+            "gc_period": "0s",
+        }
+    )
+    tenant_id = env.initial_tenant
+    timeline_id = env.initial_timeline
+    disabled_tenant_id, _ = env.create_tenant(conf={"compaction_period": "0s"})
+    ps_http = env.pageserver.http_client()
+
+    def queue_entry(tenant_id) -> dict[str, Any]:
+        queue = ps_http.compaction_queue()
+        log.info(f"compaction queue: {queue}")
+        assert queue["max_concurrent_compactions"] >= 1
+        [entry] = [e for e in queue["entries"] if e["tenant_shard_id"] == str(tenant_id)]
+        return entry
+
+    def disabled():
+        entry = queue_entry(disabled_tenant_id)
+        assert entry["state"] == "disabled"
+        assert entry["reason"] == "compaction_period is 0"
+
+    wait_until(disabled)
+
+    workload = Workload(env, tenant_id, timeline_id)
+    workload.init(env.pageserver.id)
+    workload.write_rows(1000, env.pageserver.id)
+    for _ in range(5):
+        workload.churn_rows(1000, env.pageserver.id, upload=False)
+        ps_http.timeline_checkpoint(tenant_id, timeline_id, compact=False)
+
+    # The layer flushes trigger L0 compaction, which gets the L0 count back under the threshold.
+    def compacted():
+        entry = queue_entry(tenant_id)
+        assert entry["timeline_id"] == str(timeline_id)
+        assert entry["state"] == "waiting"
+        assert entry["l0_count"] < 3
+
+    wait_until(compacted)
+    workload.validate(env.pageserver.id)
+
+
 def test_image_layer_creation_time_threshold(neon_env_builder: NeonEnvBuilder):
     """
     Tests that image layers can be created when the time threshold is reached on non-0 shards.
@@ -94,6 +94,7 @@ def test_min_resident_size_override_handling(
 class EvictionOrder(StrEnum):
     RELATIVE_ORDER_EQUAL = "relative_equal"
     RELATIVE_ORDER_SPARE = "relative_spare"
+    COST_AWARE = "cost_aware"
 
     def config(self) -> dict[str, Any]:
         if self == EvictionOrder.RELATIVE_ORDER_EQUAL:
@@ -106,6 +107,11 @@ class EvictionOrder(StrEnum):
                 "type": "RelativeAccessed",
                 "args": {"highest_layer_count_loses_first": True},
             }
+        elif self == EvictionOrder.COST_AWARE:
+            return {
+                "type": "CostAware",
+                "args": {"redownload_overhead_bytes": 8 * 1024**2},
+            }
         else:
             raise RuntimeError(f"not implemented: {self}")
 
@@ -408,7 +414,7 @@ def test_broken_tenants_are_skipped(eviction_env: EvictionEnv):
 
 @pytest.mark.parametrize(
     "order",
-    [EvictionOrder.RELATIVE_ORDER_EQUAL],
+    [EvictionOrder.RELATIVE_ORDER_EQUAL, EvictionOrder.COST_AWARE],
 )
 def test_pageserver_evicts_until_pressure_is_relieved(
     eviction_env: EvictionEnv, order: EvictionOrder
@@ -442,7 +448,7 @@ def test_pageserver_evicts_until_pressure_is_relieved(
 
 @pytest.mark.parametrize(
     "order",
-    [EvictionOrder.RELATIVE_ORDER_EQUAL],
+    [EvictionOrder.RELATIVE_ORDER_EQUAL, EvictionOrder.COST_AWARE],
 )
 def test_pageserver_respects_overridden_resident_size(
     eviction_env: EvictionEnv, order: EvictionOrder
@@ -519,7 +525,7 @@ def test_pageserver_respects_overridden_resident_size(
 
 @pytest.mark.parametrize(
     "order",
-    [EvictionOrder.RELATIVE_ORDER_EQUAL],
+    [EvictionOrder.RELATIVE_ORDER_EQUAL, EvictionOrder.COST_AWARE],
 )
 def test_pageserver_falls_back_to_global_lru(eviction_env: EvictionEnv, order: EvictionOrder):
     """
@@ -693,6 +699,40 @@ def test_fast_growing_tenant(neon_env_builder: NeonEnvBuilder, pg_bin: PgBin, or
         raise RuntimeError(f"unimplemented {order}")
 
 
+def test_cost_aware_respects_tenant_weight(eviction_env: EvictionEnv):
+    """
+    With the cost-aware order, layers of a tenant with a high `eviction_weight_pct` are only
+    evicted after those of regular tenants, regardless of which tenant was used last.
+    """
+    env = eviction_env
+    ps_http = env.pageserver_http
+
+    du_by_timeline = env.du_by_timeline(env.pageserver)
+    assert len(du_by_timeline) == 2, "this test assumes two tenants"
+    [premium, regular] = list(du_by_timeline.keys())
+
+    env.neon_env.storage_controller.pageserver_api().update_tenant_config(
+        premium[0], {"eviction_weight_pct": 100_000}
+    )
+
+    # Make the regular tenant more recently used: a plain LRU order would now evict from the
+    # premium tenant first.
+    time.sleep(ATIME_RESOLUTION)
+    env.warm_up_tenant(regular[0])
+
+    target = du_by_timeline[regular] // 2
+    response = ps_http.disk_usage_eviction_run(
+        {"evict_bytes": target, "eviction_order": EvictionOrder.COST_AWARE.config()}
+    )
+    log.info(f"{response}")
+
+    later_du_by_timeline = env.du_by_timeline(env.pageserver)
+    assert later_du_by_timeline[premium] == du_by_timeline[premium], (
+        "premium tenant sees no haircut"
+    )
+    assert du_by_timeline[regular] - later_du_by_timeline[regular] >= target
+
+
 def poor_mans_du(
     env: NeonEnv,
     timelines: Iterable[tuple[TenantId, TimelineId]],
@@ -849,6 +889,103 @@ def test_statvfs_pressure_min_avail_bytes(eviction_env: EvictionEnv):
     wait_until(more_than_min_avail_bytes_freed, timeout=5)
 
 
+def test_statvfs_pressure_demotes_to_slow_tier(eviction_env: EvictionEnv):
+    """
+    With a slow tier that has room, relieving pressure on the fast tier demotes layers to the slow
+    tier instead of evicting them, and the demoted layers stay readable.
+    """
+    env = eviction_env
+    pageserver = env.neon_env.pageserver
+
+    pageserver.stop()
+
+    total_size, _, _ = env.timelines_du(pageserver)
+    blocksize = 512
+    total_blocks = (total_size + (blocksize - 1)) // blocksize
+    layers_before = env.count_layers_per_tenant(pageserver)
+
+    slow_tier_dir = env.neon_env.repo_dir / "slow_tier"
+    slow_tier_dir.mkdir()
+    pageserver.patch_config_toml_nonrecursive(
+        {
+            "slow_layer_tier": {
+                "path": str(slow_tier_dir),
+                # the mocked statvfs below is shared, make the slow tier take everything
+                "max_usage_pct": 100,
+                "min_avail_bytes": 0,
+            }
+        }
+    )
+
+    env.pageserver_start_with_disk_usage_eviction(
+        pageserver,
+        period="1s",
+        max_usage_pct=33,
+        min_avail_bytes=0,
+        mock_behavior={
+            "type": "Success",
+            "blocksize": blocksize,
+            "total_blocks": total_blocks,
+            # Only count layer files towards used bytes in the mock_statvfs.
+            # This avoids accounting for metadata files & tenant conf in the tests.
+            "name_filter": ".*__.*",
+        },
+        eviction_order=EvictionOrder.RELATIVE_ORDER_SPARE,
+    )
+
+    wait_until(lambda: pageserver.assert_log_contains(".*disk usage pressure relieved"))
+
+    def fast_tier_size() -> int:
+        size = 0
+        for tenant_id, timeline_id in env.timelines:
+            for file in pageserver.timeline_dir(tenant_id, timeline_id).iterdir():
+                if "__" in file.name:
+                    size += file.stat().st_size
+        return size
+
+    def count_layers_on_both_tiers() -> dict[TenantId, int]:
+        ret: dict[TenantId, int] = {}
+        for tenant_id, timeline_id in env.timelines:
+            timeline_dirs = [
+                pageserver.timeline_dir(tenant_id, timeline_id),
+                slow_tier_dir / "tenants" / str(tenant_id) / "timelines" / str(timeline_id),
+            ]
+            names = {
+                file.name
+                for timeline_dir in timeline_dirs
+                if timeline_dir.exists()
+                for file in timeline_dir.iterdir()
+                if "__" in file.name
+            }
+            ret[tenant_id] = ret.get(tenant_id, 0) + len(names)
+        return ret
+
+    def less_than_max_usage_pct_on_fast_tier():
+        assert fast_tier_size() < 0.33 * total_size, "we requested max 33% usage"
+
+    wait_until(less_than_max_usage_pct_on_fast_tier, timeout=5)
+
+    # nothing was evicted: the demoted layers are still resident on the slow tier
+    assert count_layers_on_both_tiers() == layers_before
+    assert any(slow_tier_dir.rglob("*__*"))
+    for tenant_id, timeline_id in env.timelines:
+        layers = env.pageserver_http.layer_map_info(tenant_id, timeline_id)
+        assert all(not layer.remote for layer in layers.historic_layers)
+
+    # reads find the demoted layers on the slow tier
+    for tenant_id, _ in env.timelines:
+        env.warm_up_tenant(tenant_id)
+
+    # restarting keeps the demoted layers resident
+    pageserver.restart()
+    for tenant_id, timeline_id in env.timelines:
+        pageserver.http_client().timeline_wait_logical_size(tenant_id, timeline_id)
+        layers = pageserver.http_client().layer_map_info(tenant_id, timeline_id)
+        assert all(not layer.remote for layer in layers.historic_layers)
+
+    pageserver.allowed_errors.append(".*" + GLOBAL_LRU_LOG_LINE)
+
+
 def test_secondary_mode_eviction(eviction_env_ha: EvictionEnv):
     env = eviction_env_ha
 
@@ -308,3 +308,36 @@ def test_gc_of_remote_layers(neon_env_builder: NeonEnvBuilder):
 
     log.info("after running GC, ensure that resident size is still zero")
     ensure_resident_and_remote_size_metrics()
+
+
+def test_hottest_layers(neon_env_builder: NeonEnvBuilder):
+    """
+    Layers that are read repeatedly accumulate an access rate, which is reported by the
+    hottest layers endpoint, hottest first.
+    """
+    env = neon_env_builder.init_start(
+        initial_tenant_conf={
+            "gc_period": "0s",
+            "compaction_period": "0s",
+        }
+    )
+    client = env.pageserver.http_client()
+    tenant_id = env.initial_tenant
+    timeline_id = env.initial_timeline
+
+    with env.endpoints.create_start("main", tenant_id=tenant_id) as endpoint:
+        endpoint.safe_psql("CREATE TABLE foo AS SELECT generate_series(1, 10000) AS i")
+        flush_ep_to_pageserver(env, endpoint, tenant_id, timeline_id)
+        client.timeline_checkpoint(tenant_id, timeline_id)
+
+    # Read through a fresh endpoint, so that the pages have to come from the pageserver.
+    for _ in range(5):
+        with env.endpoints.create_start("main", tenant_id=tenant_id) as endpoint:
+            endpoint.safe_psql("SELECT count(*) FROM foo")
+
+    hottest = client.timeline_hottest_layers(tenant_id, timeline_id, limit=3)
+    log.info(f"hottest layers: {hottest}")
+    assert 0 < len(hottest) <= 3
+    assert hottest[0].access_rate > 0
+    rates = [layer.access_rate for layer in hottest]
+    assert rates == sorted(rates, reverse=True)
@@ -5,8 +5,10 @@ from typing import TYPE_CHECKING
 
 import pytest
 import requests
+from fixtures.common_types import Lsn
 from fixtures.log_helper import log
 from fixtures.neon_fixtures import StorageControllerApiException
+from fixtures.utils import wait_until
 
 if TYPE_CHECKING:
     from fixtures.neon_fixtures import NeonEnvBuilder
@@ -548,3 +550,85 @@ def test_abort_safekeeper_migration(neon_env_builder: NeonEnvBuilder):
     ep.safe_psql(f"INSERT INTO t VALUES ({cur_gen})")
     ep.clear_buffers()
     assert ep.safe_psql("SELECT * FROM t") == [(i + 1,) for i in range(cur_gen) if i % 2 == 0]
+
+
+def test_safekeeper_witness(neon_env_builder: NeonEnvBuilder):
+    """
+    Test timeline with 2 data safekeepers and 1 witness.
+    1. Check that the witness votes and acknowledges WAL, but doesn't store it.
+    2. Check that writes succeed with any single member down.
+    3. Migrate a data member away and check that the witness keeps its role.
+    """
+    neon_env_builder.num_safekeepers = 4
+    neon_env_builder.storage_controller_config = {
+        "timelines_onto_safekeepers": True,
+        "timeline_safekeeper_count": 3,
+        "timeline_safekeeper_witness": True,
+    }
+    env = neon_env_builder.init_start()
+    env.pageserver.allowed_errors.extend(PAGESERVER_ALLOWED_ERRORS)
+
+    tenant_id = env.initial_tenant
+    timeline_id = env.initial_timeline
+
+    def witnesses(sk_id: int) -> list[int]:
+        status = env.safekeepers[sk_id - 1].http_client().timeline_status(tenant_id, timeline_id)
+        assert status.mconf is not None
+        return [m["id"] for m in status.mconf.members if m.get("role") == "witness"]
+
+    mconf = env.storage_controller.timeline_locate(tenant_id, timeline_id)
+    assert len(mconf["sk_set"]) == 3
+    witness = witnesses(mconf["sk_set"][0])
+    assert len(witness) == 1
+    witness_sk = env.safekeepers[witness[0] - 1]
+    data_sks = [env.safekeepers[sk_id - 1] for sk_id in mconf["sk_set"] if sk_id != witness[0]]
+    log.info(f"witness is sk {witness_sk.id}, data members are {[sk.id for sk in data_sks]}")
+
+    # Protocol version 4 carries member roles, so walproposer doesn't take
+    # witness as a donor; safekeepers refuse older ones on such timelines.
+    ep = env.endpoints.create(
+        "main", tenant_id=tenant_id, config_lines=["neon.safekeeper_proto_version=4"]
+    )
+    ep.start(safekeeper_generation=1, safekeepers=mconf["sk_set"])
+    ep.safe_psql("CREATE TABLE t(key int, value text)")
+    ep.safe_psql("INSERT INTO t SELECT generate_series(1, 10000), 'payload'")
+
+    # Witness follows WAL positions, but has no segments on disk.
+    flush_lsn = data_sks[0].get_flush_lsn(tenant_id, timeline_id)
+
+    def witness_caught_up():
+        assert witness_sk.get_flush_lsn(tenant_id, timeline_id) >= flush_lsn
+
+    wait_until(witness_caught_up)
+    assert witness_sk.list_segments(tenant_id, timeline_id) == ["witness_lsn"]
+    assert len(data_sks[0].list_segments(tenant_id, timeline_id)) > 0
+
+    # Quorum of one data member and the witness is enough to commit.
+    for sk in data_sks + [witness_sk]:
+        sk.stop()
+        ep.safe_psql(f"INSERT INTO t SELECT generate_series(1, 1000), 'down {sk.id}'")
+        sk.start()
+        # Let it catch up before stopping the next one: if the only data member
+        # with the latest WAL goes down, witness can't give it to anyone.
+        flush_lsn = Lsn(ep.safe_psql("SELECT pg_current_wal_flush_lsn()")[0][0])
+
+        def caught_up(sk=sk, flush_lsn=flush_lsn):
+            assert sk.get_flush_lsn(tenant_id, timeline_id) >= flush_lsn
+
+        wait_until(caught_up)
+    ep.clear_buffers()
+    assert ep.safe_psql("SELECT count(*) FROM t") == [(13000,)]
+
+    # Replace one data member. The new one is a data member, witness stays.
+    added_sk = [sk.id for sk in env.safekeepers if sk.id not in mconf["sk_set"]][0]
+    new_sk_set = [sk_id for sk_id in mconf["sk_set"] if sk_id != data_sks[0].id] + [added_sk]
+    env.storage_controller.migrate_safekeepers(tenant_id, timeline_id, new_sk_set)
+
+    mconf = env.storage_controller.timeline_locate(tenant_id, timeline_id)
+    assert mconf["sk_set"] == new_sk_set
+    assert witnesses(added_sk) == [witness_sk.id]
+    assert len(env.safekeepers[added_sk - 1].list_segments(tenant_id, timeline_id)) > 0
+
+    ep.safe_psql("INSERT INTO t SELECT generate_series(1, 1000), 'migrated'")
+    ep.clear_buffers()
+    assert ep.safe_psql("SELECT count(*) FROM t") == [(14000,)]
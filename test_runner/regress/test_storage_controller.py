@@ -2527,6 +2527,79 @@ def test_skip_drain_on_secondary_lag(neon_env_builder: NeonEnvBuilder, pg_bin: P
     assert locations[0]["node_id"] == secondary
 
 
+def test_skip_drain_on_secondary_readiness(neon_env_builder: NeonEnvBuilder, pg_bin: PgBin):
+    """
+    Like test_skip_drain_on_secondary_lag, but with a secondary location whose lag is acceptable
+    while its access-weighted readiness is below the configured minimum.
+    """
+    neon_env_builder.num_pageservers = 2
+    neon_env_builder.storage_controller_config = {
+        "min_secondary_readiness_pct": 100,
+    }
+
+    env = neon_env_builder.init_configs()
+    env.start()
+
+    tid, timeline_id = env.create_tenant(placement_policy='{"Attached":1}')
+    env.storage_controller.reconcile_until_idle(timeout_secs=30)
+
+    locations = env.storage_controller.locate(tid)
+    assert len(locations) == 1
+    primary: int = locations[0]["node_id"]
+    secondary = [ps.id for ps in env.pageservers if ps.id != primary][0]
+    secondary_http = env.get_pageserver(secondary).http_client()
+
+    secondary_http.configure_failpoints(("secondary-layer-download-pausable", "pause"))
+
+    with env.endpoints.create_start("main", tenant_id=tid) as endpoint:
+        run_pg_bench_small(pg_bin, endpoint.connstr())
+        last_flush_lsn_upload(env, endpoint, tid, timeline_id)
+
+    env.get_pageserver(primary).http_client().tenant_heatmap_upload(tid)
+    secondary_http.tenant_secondary_download(tid, wait_ms=100)
+
+    def secondary_is_incomplete():
+        resp = secondary_http.tenant_secondary_status(tid)
+        assert resp["layers_downloaded"] < resp["layers_total"]
+
+    wait_until(secondary_is_incomplete)
+
+    env.storage_controller.retryable_node_operation(
+        lambda ps_id: env.storage_controller.node_drain(ps_id), primary, max_attempts=3, backoff=2
+    )
+    env.storage_controller.poll_node_status(
+        primary,
+        PageserverAvailability.ACTIVE,
+        PageserverSchedulingPolicy.PAUSE_FOR_RESTART,
+        max_attempts=6,
+        backoff=5,
+    )
+    assert env.storage_controller.locate(tid)[0]["node_id"] == primary
+
+    secondary_http.configure_failpoints(("secondary-layer-download-pausable", "off"))
+    secondary_http.tenant_secondary_download(tid)
+
+    resp = secondary_http.tenant_secondary_status(tid)
+    log.info(f"Secondary status after download: {resp}")
+    assert resp["layers_downloaded"] == resp["layers_total"]
+    assert resp["access_rate_downloaded"] == resp["access_rate_total"]
+    assert resp["layer_download_ms"] is not None
+    assert resp["est_read_latency_impact_ms"] == 0
+
+    env.storage_controller.node_configure(primary, {"scheduling": "Active"})
+    env.storage_controller.retryable_node_operation(
+        lambda ps_id: env.storage_controller.node_drain(ps_id), primary, max_attempts=3, backoff=2
+    )
+    env.storage_controller.poll_node_status(
+        primary,
+        PageserverAvailability.ACTIVE,
+        PageserverSchedulingPolicy.PAUSE_FOR_RESTART,
+        max_attempts=6,
+        backoff=5,
+    )
+    assert env.storage_controller.locate(tid)[0]["node_id"] == secondary
+
+
 def test_background_operation_cancellation(neon_env_builder: NeonEnvBuilder):
     neon_env_builder.num_pageservers = 2
     env = neon_env_builder.init_configs()
@@ -864,3 +864,48 @@ def test_timeline_delete_resumed_on_attach(
             )
         ),
     )
+
+
+def test_timeline_soft_delete_and_undelete(neon_env_builder: NeonEnvBuilder):
+    """
+    With a `timeline_delete_grace_period`, deleting a timeline only hides it: its data stays in
+    remote storage and it can be restored with undelete until the grace period expires.
+    """
+    neon_env_builder.enable_pageserver_remote_storage(RemoteStorageKind.LOCAL_FS)
+    env = neon_env_builder.init_start(
+        initial_tenant_conf={"timeline_delete_grace_period": "1h"}
+    )
+    ps_http = env.pageserver.http_client()
+    tenant_id = env.initial_tenant
+
+    timeline_id = env.create_branch("soft_deleted")
+    with env.endpoints.create_start("soft_deleted", tenant_id=tenant_id) as endpoint:
+        endpoint.safe_psql("CREATE TABLE t AS SELECT generate_series(1, 1000) AS i")
+        last_flush_lsn_upload(env, endpoint, tenant_id, timeline_id)
+
+    timeline_delete_wait_completed(ps_http, tenant_id, timeline_id)
+    assert timeline_id not in {
+        TimelineId(t["timeline_id"]) for t in ps_http.timeline_list(tenant_id)
+    }
+
+    timeline_prefix = "/".join(("tenants", str(tenant_id), "timelines", str(timeline_id)))
+    assert_prefix_not_empty(neon_env_builder.pageserver_remote_storage, prefix=timeline_prefix)
+
+    # A soft-deleted timeline survives a restart and can still be restored afterwards.
+    env.pageserver.restart()
+    wait_until_tenant_active(ps_http, tenant_id)
+    with pytest.raises(PageserverApiException) as exc:
+        ps_http.timeline_detail(tenant_id, timeline_id)
+    assert exc.value.status_code == 404
+
+    ps_http.timeline_undelete(tenant_id, timeline_id)
+    detail = ps_http.timeline_detail(tenant_id, timeline_id)
+    assert detail["is_archived"] is False
+
+    with env.endpoints.create_start("soft_deleted", tenant_id=tenant_id) as endpoint:
+        assert endpoint.safe_psql("SELECT count(*) FROM t")[0][0] == 1000
+
+    # Undeleting a timeline that is not soft-deleted is an error.
+    with pytest.raises(PageserverApiException) as exc:
+        ps_http.timeline_undelete(tenant_id, TimelineId.generate())
+    assert exc.value.status_code == 404
@@ -6,7 +6,9 @@ from dataclasses import dataclass
 from typing import TYPE_CHECKING
 
 import pytest
+from fixtures.common_types import Lsn
 from fixtures.log_helper import log
+from fixtures.neon_fixtures import wait_for_last_flush_lsn
 from fixtures.pageserver.utils import wait_timeline_detail_404
 
 if TYPE_CHECKING:
@@ -131,3 +133,56 @@ class ManyPageservers:
         with ThreadPoolExecutor(max_workers=len(self.many)) as rt:
             rt.map(do_quiesce, self.many)
             rt.shutdown(wait=True)
+
+
+def test_gc_dry_run(neon_env_builder: NeonEnvBuilder):
+    """
+    The GC dry run attributes every layer it keeps to a constraint, and reports a blocked gc.
+    """
+    env = neon_env_builder.init_start(
+        initial_tenant_conf={
+            "gc_period": "0s",
+            "pitr_interval": "0s",
+            "gc_horizon": f"{1024**2}",
+            "checkpoint_distance": f"{1024**2}",
+        }
+    )
+    http = env.pageserver.http_client()
+    tenant_id = env.initial_tenant
+    timeline_id = env.initial_timeline
+
+    with env.endpoints.create_start("main", tenant_id=tenant_id) as ep:
+        ep.safe_psql("CREATE TABLE t AS SELECT g, repeat('x', 100) FROM generate_series(1, 50000) g")
+        branch_lsn = Lsn(ep.safe_psql("SELECT pg_current_wal_insert_lsn()")[0][0])
+        env.create_branch(
+            "child", ancestor_branch_name="main", ancestor_start_lsn=branch_lsn, tenant_id=tenant_id
+        )
+        ep.safe_psql("INSERT INTO t SELECT g, repeat('y', 100) FROM generate_series(1, 50000) g")
+        wait_for_last_flush_lsn(env, ep, tenant_id, timeline_id)
+    http.timeline_checkpoint(tenant_id, timeline_id)
+
+    # Before GC computed the PITR cutoff, GC would skip the timeline and so does the dry run.
+    dry_run = http.timeline_gc_dry_run(tenant_id, timeline_id)
+    if dry_run["time_cutoff"] is None:
+        assert dry_run["layers_removable"] == 0
+        assert all(summary["reason"] != "pitr" for summary in dry_run["retained_by"])
+
+    # Computes the cutoffs the dry run reports against.
+    http.timeline_gc(tenant_id, timeline_id, None)
+
+    dry_run = http.timeline_gc_dry_run(tenant_id, timeline_id)
+    log.info(f"dry run: {dry_run}")
+    assert dry_run["blocked_by"] is None
+    assert branch_lsn in map(Lsn, dry_run["retain_lsns"])
+    retained = dry_run["retained_layers"]
+    assert dry_run["layers_total"] == dry_run["layers_removable"] + len(retained)
+    for summary in dry_run["retained_by"]:
+        of_reason = [layer for layer in retained if layer["reason"] == summary["reason"]]
+        assert summary["layers"] == len(of_reason)
+        assert summary["bytes"] == sum(layer["layer_file_size"] for layer in of_reason)
+    assert any(layer["reason"] == "branch" for layer in retained)
+
+    http.timeline_block_gc(tenant_id, timeline_id)
+    dry_run = http.timeline_gc_dry_run(tenant_id, timeline_id)
+    assert dry_run["blocked_by"] is not None
+    assert dry_run["layers_removable"] == 0
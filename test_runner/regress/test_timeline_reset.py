@@ -0,0 +1,71 @@
+from __future__ import annotations
+
+from typing import TYPE_CHECKING
+
+from fixtures.common_types import Lsn, TimelineId
+from fixtures.log_helper import log
+from fixtures.neon_fixtures import wait_for_last_flush_lsn
+
+if TYPE_CHECKING:
+    from fixtures.neon_fixtures import Endpoint, NeonEnv, NeonEnvBuilder
+
+PAGESERVER_ALLOWED_ERRORS = [
+    # The tenant is reloaded after the reset, and its walreceiver cancelled before that.
+    ".*Timeline .* was cancelled and cannot be used anymore.*",
+    ".*wal receiver task finished with an error.*",
+    ".*upload queue is shutting down.*",
+]
+
+
+def start_endpoint(env: NeonEnv, branch_name: str, timeline_id: TimelineId) -> Endpoint:
+    mconf = env.storage_controller.timeline_locate(env.initial_tenant, timeline_id)
+    ep = env.endpoints.create(branch_name, tenant_id=env.initial_tenant)
+    ep.start(safekeeper_generation=mconf["generation"], safekeepers=mconf["sk_set"])
+    return ep
+
+
+def test_timeline_reset(neon_env_builder: NeonEnvBuilder):
+    """
+    Reset a timeline to an earlier LSN through the storage controller, and check that the
+    timeline continues from there while the backup branch keeps the later history.
+    """
+    neon_env_builder.num_safekeepers = 3
+    neon_env_builder.storage_controller_config = {
+        "timelines_onto_safekeepers": True,
+    }
+    env = neon_env_builder.init_start()
+    env.pageserver.allowed_errors.extend(PAGESERVER_ALLOWED_ERRORS)
+
+    tenant_id = env.initial_tenant
+    timeline_id = env.initial_timeline
+
+    ep = start_endpoint(env, "main", timeline_id)
+    ep.safe_psql("CREATE TABLE t(a int)")
+    ep.safe_psql("INSERT INTO t SELECT generate_series(1, 100)")
+    reset_lsn = Lsn(ep.safe_psql("SELECT pg_current_wal_insert_lsn()")[0][0])
+    ep.safe_psql("INSERT INTO t SELECT generate_series(101, 200)")
+    wait_for_last_flush_lsn(env, ep, tenant_id, timeline_id)
+    ep.stop()
+
+    backup_timeline_id = TimelineId.generate()
+    log.info(f"resetting {timeline_id} to {reset_lsn} onto {backup_timeline_id}")
+    client = env.storage_controller.pageserver_api()
+    client.timeline_reset(tenant_id, timeline_id, reset_lsn, backup_timeline_id)
+    # Retrying a completed reset is a no-op.
+    client.timeline_reset(tenant_id, timeline_id, reset_lsn, backup_timeline_id)
+
+    detail = env.pageserver.http_client().timeline_detail(tenant_id, timeline_id)
+    assert detail["ancestor_timeline_id"] == str(backup_timeline_id)
+    assert Lsn(detail["ancestor_lsn"]) == reset_lsn
+
+    mconf = env.storage_controller.timeline_locate(tenant_id, timeline_id)
+    ep.start(safekeeper_generation=mconf["generation"], safekeepers=mconf["sk_set"])
+    assert ep.safe_psql("SELECT count(*) FROM t") == [(100,)]
+    # The timeline accepts writes after the reset.
+    ep.safe_psql("INSERT INTO t SELECT generate_series(1001, 1010)")
+    assert ep.safe_psql("SELECT count(*) FROM t") == [(110,)]
+    ep.stop()
+
+    env.neon_cli.mappings_map_branch("backup", tenant_id, backup_timeline_id)
+    ep_backup = start_endpoint(env, "backup", backup_timeline_id)
+    assert ep_backup.safe_psql("SELECT count(*) FROM t") == [(200,)]
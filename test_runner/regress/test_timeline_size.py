@@ -319,6 +319,71 @@ def test_timeline_size_quota(neon_env_builder: NeonEnvBuilder):
     )
 
 
+def test_timeline_size_limit_from_pageserver(neon_env_builder: NeonEnvBuilder):
+    """
+    The pageserver enforces `timeline_logical_size_limit` by asking the compute to enter read-only
+    mode through the pageserver feedback, without the compute having any limit configured.
+    """
+    env = neon_env_builder.init_start(
+        initial_tenant_conf={
+            # Tenant housekeeping, which runs at the compaction period, picks up limit changes and
+            # refreshes the sizes that the walreceiver enforces the limits against
+            "compaction_period": "1s",
+        }
+    )
+    client = env.pageserver.http_client()
+    api = env.storage_controller.pageserver_api()
+    tenant_id = env.initial_tenant
+    timeline_id = env.initial_timeline
+
+    client.timeline_wait_logical_size(tenant_id, timeline_id)
+
+    endpoint = env.endpoints.create_start("main")
+    with closing(endpoint.connect()) as conn:
+        with conn.cursor() as cur:
+            cur.execute("CREATE TABLE foo (t text)")
+            wait_for_pageserver_catchup(endpoint)
+
+            size = client.timeline_detail(tenant_id, timeline_id)["current_logical_size"]
+            api.patch_tenant_config(tenant_id, {"timeline_logical_size_limit": size + 1024})
+
+            def limit_exceeded():
+                detail = client.timeline_detail(tenant_id, timeline_id)
+                assert detail.get("size_limit_exceeded") == "timeline_logical"
+
+            with pytest.raises(psycopg2.errors.DiskFull):
+                for _ in range(100):
+                    cur.execute(
+                        """
+                        INSERT INTO foo
+                            SELECT 'long string to consume some space' || g
+                            FROM generate_series(1, 10000) g
+                        """
+                    )
+                    wait_for_pageserver_catchup(endpoint)
+                    time.sleep(1)
+
+            wait_until(limit_exceeded)
+            assert (
+                client.get_metric_value(
+                    "pageserver_size_limit_exceeded",
+                    {"tenant_id": str(tenant_id), "timeline_id": str(timeline_id)},
+                )
+                == 1
+            )
+
+            # Lifting the limit takes the compute out of read-only mode
+            api.patch_tenant_config(tenant_id, {"timeline_logical_size_limit": None})
+
+            def limit_lifted():
+                detail = client.timeline_detail(tenant_id, timeline_id)
+                assert detail.get("size_limit_exceeded") is None
+
+            wait_until(limit_lifted)
+            wait_for_pageserver_catchup(endpoint)
+            cur.execute("INSERT INTO foo SELECT 'after limit lifted' FROM generate_series(1, 1000)")
+
+
 @pytest.mark.parametrize("deletion_method", ["tenant_detach", "timeline_delete"])
 def test_timeline_initial_logical_size_calculation_cancellation(
     neon_env_builder: NeonEnvBuilder, deletion_method: str
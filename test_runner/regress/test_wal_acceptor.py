@@ -8,6 +8,7 @@ import shutil
 import signal
 import subprocess
 import sys
+import tarfile
 import threading
 import time
 from contextlib import closing
@@ -21,25 +22,30 @@ import psycopg2.errors
 import psycopg2.extras
 import pytest
 import requests
+import zstandard
 from fixtures.common_types import Lsn, TenantId, TimelineId
 from fixtures.log_helper import log
 from fixtures.metrics import parse_metrics
 from fixtures.neon_fixtures import (
     Endpoint,
+    NeonEnv,
     NeonEnvBuilder,
     PgBin,
     PgProtocol,
     Safekeeper,
     SafekeeperPort,
+    VanillaPostgres,
     last_flush_lsn_upload,
 )
 from fixtures.pageserver.utils import (
     assert_prefix_empty,
     assert_prefix_not_empty,
+    list_prefix,
     timeline_delete_wait_completed,
 )
 from fixtures.pg_version import PgVersion
 from fixtures.remote_storage import (
+    LocalFsStorage,
     RemoteStorageKind,
     default_remote_storage,
     s3_storage,
@@ -926,6 +932,164 @@ def test_sk_auth(neon_env_builder: NeonEnvBuilder):
     connector.safe_psql("IDENTIFY_SYSTEM", port=sk.port.pg_tenant_only, password=tenant_token)
 
 
+def extract_initdb(env: NeonEnv, data_dir: Path):
+    """
+    Extract the initdb archive of the initial timeline, which shares system id
+    and the start of WAL with it, as data directory of a standby.
+    """
+    assert isinstance(env.pageserver_remote_storage, LocalFsStorage)
+    initdb_zst_path = (
+        env.pageserver_remote_storage.timeline_path(env.initial_tenant, env.initial_timeline)
+        / "initdb.tar.zst"
+    )
+    with open(initdb_zst_path, "rb") as f:
+        with zstandard.ZstdDecompressor().stream_reader(f) as reader:
+            with tarfile.open(fileobj=reader, mode="r|") as tfile:
+                tfile.extractall(path=data_dir)
+
+
+# Attach an unmodified postgres physical standby directly to a safekeeper. The
+# standby is bootstrapped from the initdb archive of the timeline, which
+# shares system id and the start of WAL with it, and streams the rest of WAL
+# from the tenant only port using a read-only tenant token.
+def test_vanilla_standby(
+    neon_env_builder: NeonEnvBuilder,
+    pg_bin: PgBin,
+    test_output_dir: Path,
+    port_distributor: PortDistributor,
+):
+    neon_env_builder.auth_enabled = True
+    env = neon_env_builder.init_start()
+    tenant_id = env.initial_tenant
+    timeline_id = env.initial_timeline
+    endpoint = env.endpoints.create_start("main")
+    endpoint.safe_psql("create table t(key int, value text)")
+    endpoint.safe_psql("insert into t select generate_series(1, 1000), 'payload'")
+
+    sk = env.safekeepers[0]
+    read_only_token = env.auth_keys.generate_tenant_read_only_token(tenant_id)
+
+    # Read-only token allows the replication commands, but not pushing WAL.
+    connector = PgProtocol(
+        host="127.0.0.1", options=f"-c timeline_id={timeline_id} tenant_id={tenant_id}"
+    )
+    port = sk.port.pg_tenant_only
+    connector.safe_psql("IDENTIFY_SYSTEM", port=port, password=read_only_token)
+    # The only timeline has no parents, so its history file has no entries.
+    assert connector.safe_psql("TIMELINE_HISTORY 1", port=port, password=read_only_token) == [
+        ("00000001.history", "")
+    ]
+    with pytest.raises(psycopg2.Error, match="not in this server's history"):
+        connector.safe_psql("TIMELINE_HISTORY 2", port=port, password=read_only_token)
+    with pytest.raises(psycopg2.Error, match="ineligible"):
+        connector.safe_psql("START_WAL_PUSH", port=port, password=read_only_token)
+    other_tenant_token = env.auth_keys.generate_tenant_read_only_token(TenantId.generate())
+    with pytest.raises(psycopg2.Error, match="Tenant id mismatch"):
+        connector.safe_psql("IDENTIFY_SYSTEM", port=port, password=other_tenant_token)
+
+    data_dir = test_output_dir / "standby"
+    extract_initdb(env, data_dir)
+
+    with VanillaPostgres(data_dir, pg_bin, port_distributor.get_port(), init=False) as standby:
+        standby.configure(
+            [
+                # WAL of the primary contains neon specific records, see
+                # test_vanilla_standby_requires_neon_rmgr.
+                "shared_preload_libraries='neon_rmgr'",
+                "hot_standby = on",
+                f"primary_conninfo = 'host=127.0.0.1 port={port} password={read_only_token} "
+                f"options=''-c tenant_id={tenant_id} -c timeline_id={timeline_id}'''",
+            ]
+        )
+        (data_dir / "standby.signal").touch()
+        standby.start()
+
+        def caught_up():
+            lsn = Lsn(endpoint.safe_psql("select pg_current_wal_flush_lsn()")[0][0])
+            replay_lsn = Lsn(
+                standby.safe_psql("select pg_last_wal_replay_lsn()", user="cloud_admin")[0][0]
+            )
+            assert replay_lsn >= lsn
+
+        wait_until(caught_up)
+        assert standby.safe_psql("select count(*) from t", user="cloud_admin") == [(1000,)]
+
+        # And it keeps streaming.
+        endpoint.safe_psql("insert into t select generate_series(1001, 2000), 'payload'")
+        wait_until(caught_up)
+        assert standby.safe_psql("select count(*) from t", user="cloud_admin") == [(2000,)]
+
+
+# Same as above, but the stock standby is managed by neon_local.
+def test_vanilla_standby_neon_local(
+    neon_env_builder: NeonEnvBuilder, port_distributor: PortDistributor
+):
+    neon_env_builder.auth_enabled = True
+    env = neon_env_builder.init_start()
+    endpoint = env.endpoints.create_start("main")
+    endpoint.safe_psql("create table t(key int, value text)")
+    endpoint.safe_psql("insert into t select generate_series(1, 1000), 'payload'")
+
+    port = port_distributor.get_port()
+    env.neon_cli.stock_standby_create("standby", env.initial_tenant, "main", port, env.pg_version)
+    env.neon_cli.stock_standby_start("standby", env.pg_version)
+    standby = PgProtocol(host="127.0.0.1", port=port, user="cloud_admin", dbname="postgres")
+
+    def caught_up():
+        lsn = Lsn(endpoint.safe_psql("select pg_current_wal_flush_lsn()")[0][0])
+        replay_lsn = Lsn(standby.safe_psql("select pg_last_wal_replay_lsn()")[0][0])
+        assert replay_lsn >= lsn
+
+    wait_until(caught_up)
+    assert standby.safe_psql("select count(*) from t") == [(1000,)]
+    env.neon_cli.stock_standby_stop("standby", env.pg_version)
+
+
+# Computes of Postgres 16 and later log heap changes with the custom neon
+# resource manager, so a stock standby can only replay their WAL with the
+# neon_rmgr library loaded.
+def test_vanilla_standby_requires_neon_rmgr(
+    neon_env_builder: NeonEnvBuilder,
+    pg_bin: PgBin,
+    test_output_dir: Path,
+    port_distributor: PortDistributor,
+):
+    env = neon_env_builder.init_start()
+    if env.pg_version < PgVersion.V16:
+        pytest.skip("neon resource manager is only used since Postgres 16")
+    tenant_id = env.initial_tenant
+    timeline_id = env.initial_timeline
+    endpoint = env.endpoints.create_start("main")
+    endpoint.safe_psql("create table t(key int, value text)")
+    endpoint.safe_psql("insert into t values (1, 'payload')")
+
+    data_dir = test_output_dir / "standby"
+    extract_initdb(env, data_dir)
+    standby = VanillaPostgres(data_dir, pg_bin, port_distributor.get_port(), init=False)
+    port = env.safekeepers[0].port.pg
+    standby.configure(
+        [
+            "hot_standby = on",
+            f"primary_conninfo = 'host=127.0.0.1 port={port} "
+            f"options=''-c tenant_id={tenant_id} -c timeline_id={timeline_id}'''",
+        ]
+    )
+    (data_dir / "standby.signal").touch()
+    log_path = test_output_dir / "standby.log"
+    # Replay may fail before pg_ctl sees the standby accepting connections.
+    try:
+        standby.start(log_path=str(log_path))
+    except subprocess.CalledProcessError:
+        pass
+
+    def replay_failed():
+        assert "not registered" in log_path.read_text()
+
+    wait_until(replay_failed)
+    # The startup process failure shuts down the standby.
+    standby.running = False
+
+
 # Try restarting endpoint with enabled auth.
 def test_restart_endpoint(neon_env_builder: NeonEnvBuilder):
     neon_env_builder.auth_enabled = True
@@ -1847,6 +2011,80 @@ def test_pull_timeline_term_change(neon_env_builder: NeonEnvBuilder):
         pt_handle.join()
 
 
+# Test bootstrapping a new member from a snapshot in remote storage when none
+# of the peers is reachable: pull_timeline with allow_remote_snapshot falls
+# back to the latest snapshot uploaded with --remote-snapshot-interval, and WAL
+# preceding it is fetched from WAL backup.
+def test_pull_timeline_from_remote_snapshot(neon_env_builder: NeonEnvBuilder):
+    neon_env_builder.num_safekeepers = 4
+    neon_env_builder.enable_safekeeper_remote_storage(RemoteStorageKind.MOCK_S3)
+    neon_env_builder.safekeeper_extra_opts = ["--remote-snapshot-interval", "1s"]
+    env = neon_env_builder.init_start()
+    tenant_id = env.initial_tenant
+    timeline_id = env.initial_timeline
+
+    (src_sks, dst_sk) = (env.safekeepers[:3], env.safekeepers[3])
+    dst_sk.stop()
+
+    endpoint = env.endpoints.create("main")
+    endpoint.active_safekeepers = [1, 2, 3]
+    endpoint.start()
+    endpoint.safe_psql("create table t(key int, value text)")
+    endpoint.safe_psql("insert into t select generate_series(1, 100000), 'payload'")
+    endpoint.stop()
+
+    flush_lsn = src_sks[0].get_flush_lsn(tenant_id, timeline_id)
+    log.info(f"flush_lsn on src: {flush_lsn}")
+
+    prefix = f"{tenant_id}/{timeline_id}/snapshots/"
+
+    def snapshot_uploaded():
+        objects = list_prefix(neon_env_builder.safekeepers_remote_storage, prefix).get(
+            "Contents", []
+        )
+        names = [o["Key"].rsplit("/", 1)[-1] for o in objects]
+        log.info(f"remote snapshots: {names}")
+        assert any(Lsn(int(name.split("_")[2], 16)) == flush_lsn for name in names)
+
+    wait_until(snapshot_uploaded)
+
+    body = {
+        "tenant_id": str(tenant_id),
+        "timeline_id": str(timeline_id),
+        "http_hosts": [f"http://localhost:{sk.port.http}" for sk in src_sks],
+        "allow_remote_snapshot": True,
+    }
+
+    # The snapshot is not used while any of the peers is reachable, as it
+    # may be behind them.
+    for sk in src_sks[:2]:
+        sk.stop()
+    dst_sk.start()
+    with pytest.raises(requests.exceptions.HTTPError):
+        dst_sk.http_client().pull_timeline(body)
+
+    src_sks[2].stop()
+    # Without the flag pull_timeline fails as no peer responds.
+    with pytest.raises(requests.exceptions.HTTPError):
+        dst_sk.pull_timeline(src_sks, tenant_id, timeline_id)
+
+    res = dst_sk.http_client().pull_timeline(body)
+    log.info(f"pull_timeline response: {res}")
+    assert res["safekeeper_host"] is None
+    assert res["remote_snapshot"] in [sk.id for sk in src_sks]
+    assert dst_sk.get_flush_lsn(tenant_id, timeline_id) == flush_lsn
+
+    # The new member serves the timeline together with the rest of the peers.
+    for sk in src_sks[1:]:
+        sk.start()
+    endpoint = env.endpoints.create("main")
+    endpoint.active_safekeepers = [2, 3, 4]
+    endpoint.start()
+    endpoint.safe_psql("insert into t select generate_series(1, 1000), 'payload'")
+    assert endpoint.safe_psql("select count(*) from t")[0][0] == 101000
+    endpoint.stop()
+
+
 def test_pull_timeline_while_evicted(neon_env_builder: NeonEnvBuilder):
     """
     Verify that when pull_timeline is used on an evicted timeline, it does not result in
@@ -2330,6 +2568,102 @@ def test_term_bump(neon_env_builder: NeonEnvBuilder):
     endpoint.safe_psql("insert into t values (1, 'payload')")
 
 
+# Test that WAL ingest limits can be changed at runtime and that the applied
+# backpressure is reported in debug_dump and metrics.
+def test_wal_ingest_limits(neon_env_builder: NeonEnvBuilder):
+    neon_env_builder.num_safekeepers = 1
+    env = neon_env_builder.init_start()
+
+    tenant_id = env.initial_tenant
+    timeline_id = env.initial_timeline
+
+    endpoint = env.endpoints.create_start("main")
+    endpoint.safe_psql("create table t(key int, value text)")
+
+    http_cli = env.safekeepers[0].http_client()
+    assert http_cli.wal_ingest_limits()["tenant_bytes_per_second"] is None
+
+    limits = {"tenant_overrides": {str(tenant_id): 1024 * 1024}}
+    http_cli.set_wal_ingest_limits(limits)
+    assert http_cli.wal_ingest_limits()["tenant_overrides"] == limits["tenant_overrides"]
+
+    # ~10MB of WAL takes several seconds at 1MB/s.
+    endpoint.safe_psql(
+        "insert into t select g, repeat('x', 1000) from generate_series(1, 10000) g"
+    )
+
+    throttle = http_cli.get_wal_ingest_throttle(timeline_id)
+    log.info(f"wal ingest throttle: {throttle}")
+    assert throttle["throttled_count"] > 0
+    assert throttle["throttled_seconds"] > 0
+
+    metrics = http_cli.get_metrics()
+    assert (
+        metrics.query_one("safekeeper_wal_ingest_throttled_total", {"scope": "tenant"}).value > 0
+    )
+
+    # Lifting the limits stops throttling.
+    http_cli.set_wal_ingest_limits({})
+    endpoint.safe_psql(
+        "insert into t select g, repeat('x', 1000) from generate_series(1, 10000) g"
+    )
+    throttled_count = http_cli.get_wal_ingest_throttle(timeline_id)["throttled_count"]
+    assert throttled_count == throttle["throttled_count"]
+
+
+# Test that wal_verify finds no divergence between safekeepers and remote
+# storage, and reports a corrupted segment on one of safekeepers.
+def test_wal_verify(neon_env_builder: NeonEnvBuilder):
+    neon_env_builder.num_safekeepers = 3
+    neon_env_builder.enable_safekeeper_remote_storage(RemoteStorageKind.LOCAL_FS)
+    env = neon_env_builder.init_start()
+
+    tenant_id = env.initial_tenant
+    timeline_id = env.initial_timeline
+
+    endpoint = env.endpoints.create_start("main")
+    endpoint.safe_psql("create table t(key int, value text)")
+    # fills more than one segment
+    endpoint.safe_psql("insert into t select generate_series(1,500000), 'payload'")
+    endpoint.stop()
+
+    sk_http_clis = [sk.http_client() for sk in env.safekeepers]
+    wait(
+        partial(is_flush_lsn_aligned, sk_http_clis, tenant_id, timeline_id),
+        "flush_lsn to get aligned",
+    )
+    sk1, _, sk3 = env.safekeepers
+    from_lsn = sk1.get_timeline_start_lsn(tenant_id, timeline_id)
+    until_lsn = sk1.get_flush_lsn(tenant_id, timeline_id)
+    http_hosts = [f"http://localhost:{sk.port.http}" for sk in env.safekeepers[1:]]
+
+    report = sk_http_clis[0].wal_verify(tenant_id, timeline_id, from_lsn, until_lsn, http_hosts)
+    log.info(f"wal_verify report: {report}")
+    assert report["divergences"] == []
+    sources = {s["source"]: s for s in report["sources"]}
+    for sk in env.safekeepers:
+        source = sources[f"sk-{sk.id}/local"]
+        assert source["error"] is None and source["decode_error"] is None
+        assert source["ranges"] == [[str(from_lsn), str(until_lsn)]]
+
+    # Flip a byte in the first completed segment of sk3.
+    sk3.stop()
+    segment = next(s for s in sk3.list_segments(tenant_id, timeline_id) if "." not in s)
+    with open(sk3.timeline_dir(tenant_id, timeline_id) / segment, "r+b") as f:
+        f.seek(8 * 1024 * 1024)
+        byte = f.read(1)
+        f.seek(8 * 1024 * 1024)
+        f.write(bytes([byte[0] ^ 0xFF]))
+    sk3.start()
+
+    report = sk_http_clis[0].wal_verify(tenant_id, timeline_id, from_lsn, until_lsn, http_hosts)
+    log.info(f"wal_verify report after corruption: {report}")
+    assert len(report["divergences"]) == 1
+    divergence = report["divergences"][0]
+    assert Lsn(divergence["start_lsn"]) < Lsn(divergence["end_lsn"])
+    assert [f"sk-{sk3.id}/local"] in [g["sources"] for g in divergence["groups"]]
+
+
 # Test disables periodic pushes from safekeeper to the broker and checks that
 # pageserver can still discover safekeepers with discovery requests.
 def test_broker_discovery(neon_env_builder: NeonEnvBuilder):
@@ -2938,3 +3272,66 @@ def test_global_disk_usage_limit(neon_env_builder: NeonEnvBuilder):
         with conn.cursor() as cur:
             cur.execute("select count(*) from t2")
             assert cur.fetchone() == (3000,)
+
+
+def test_disk_pressure_eviction(neon_env_builder: NeonEnvBuilder):
+    """
+    Test that inactive timelines are evicted right away once disk usage exceeds
+    --disk-pressure-eviction-ratio, even though --eviction-min-resident would
+    keep them resident, and that the reclaimed space is reported.
+    """
+    neon_env_builder.num_safekeepers = 1
+    neon_env_builder.enable_safekeeper_remote_storage(RemoteStorageKind.LOCAL_FS)
+    neon_env_builder.safekeeper_extra_opts = [
+        "--enable-offload",
+        "--partial-backup-timeout",
+        "50ms",
+        "--control-file-save-interval",
+        "1s",
+        # Regular eviction shouldn't kick in during the test.
+        "--eviction-min-resident=1h",
+        "--global-disk-check-interval=1s",
+        "--disk-pressure-eviction-ratio=0.9",
+    ]
+    initial_tenant_conf = {"lagging_wal_timeout": "1s", "checkpoint_timeout": "100ms"}
+    env = neon_env_builder.init_start(initial_tenant_conf=initial_tenant_conf)
+    sk = env.safekeepers[0]
+    sk_http = sk.http_client()
+    ps_client = env.pageservers[0].http_client()
+
+    n_timelines = 3
+    branch_names = [f"branch{i}" for i in range(n_timelines)]
+    for branch_name in branch_names:
+        timeline_id = env.create_branch(branch_name)
+        endpoint = env.endpoints.create_start(branch_name)
+        endpoint.safe_psql("CREATE TABLE t(i int)")
+        endpoint.safe_psql("INSERT INTO t VALUES (0)")
+        endpoint.stop()
+        # update remote_consistent_lsn on pageserver to make the timeline inactive
+        ps_client.timeline_checkpoint(env.initial_tenant, timeline_id, wait_until_uploaded=True)
+
+    usage = sk_http.filesystem_usage()
+    assert usage["disk_pressure_eviction"]["evicted_timelines_total"] == 0
+    assert sk_http.get_metrics().query_one("safekeeper_evicted_timelines").value == 0
+
+    # Mock disk usage above the threshold.
+    sk_http.configure_failpoints([("sk-global-disk-usage", "return(18446744073709551615)")])
+
+    def all_evicted():
+        usage = sk_http.filesystem_usage()
+        log.info(f"filesystem usage: {usage}")
+        assert usage["disk_pressure_eviction"]["evicted_timelines_total"] >= n_timelines
+        assert usage["disk_pressure_eviction"]["reclaimed_bytes_total"] > 0
+        assert usage["usage_bytes"] > usage["disk_pressure_eviction_threshold_bytes"]
+
+    wait_until(all_evicted, timeout=60)
+    assert sk_http.get_metrics().query_one("safekeeper_evicted_timelines").value >= n_timelines
+
+    sk_http.configure_failpoints([("sk-global-disk-usage", "return(0)")])
+
+    # Evicted timelines are restored on demand.
+    for branch_name in branch_names:
+        endpoint = env.endpoints.create_start(branch_name)
+        res = endpoint.safe_psql("UPDATE t SET i = i + 1 RETURNING i")
+        assert res[0][0] == 1
+        endpoint.stop()